@@ -0,0 +1,26 @@
+USE telemedicina;
+
+/*==============================================================*/
+/* Archivos subidos por pacientes y profesionales               */
+/* El contenido está en el almacén (directorio local o bucket   */
+/* S3) bajo su SHA-256, compartido entre filas con el mismo     */
+/* contenido. id_prof nulo: lo subió el paciente. Fechas en UTC */
+/*==============================================================*/
+CREATE TABLE adjuntos (
+    id          INT AUTO_INCREMENT PRIMARY KEY,
+    id_paciente INT NOT NULL,
+    id_atencion INT,
+    id_prof     INT,
+    nombre      VARCHAR(200) NOT NULL,
+    tipo_mime   VARCHAR(50) NOT NULL,
+    tamano      INT UNSIGNED NOT NULL,
+    sha256      CHAR(64) NOT NULL,
+    subido_en   DATETIME NOT NULL,
+
+    INDEX (id_paciente, subido_en),
+    INDEX (id_atencion),
+    INDEX (sha256),
+    FOREIGN KEY (id_paciente) REFERENCES pacientes(id),
+    FOREIGN KEY (id_atencion) REFERENCES atenciones(id),
+    FOREIGN KEY (id_prof) REFERENCES paso_profesionales(id_prof)
+);
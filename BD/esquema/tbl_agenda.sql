@@ -0,0 +1,51 @@
+USE telemedicina;
+
+/*==============================================================*/
+/* Bloques semanales de disponibilidad por profesional          */
+/* dia_semana: 1 = lunes ... 7 = domingo                        */
+/* Las horas son locales a zona_horaria                         */
+/*==============================================================*/
+CREATE TABLE agenda_bloques (
+    id             INT AUTO_INCREMENT PRIMARY KEY,
+    id_prof        INT NOT NULL,
+    dia_semana     TINYINT NOT NULL,
+    hora_inicio    TIME NOT NULL,
+    hora_fin       TIME NOT NULL,
+    duracion_min   SMALLINT NOT NULL,
+    modalidad      VARCHAR(20) NOT NULL,
+    cod_zona       CHAR(6),
+    zona_horaria   VARCHAR(40) NOT NULL DEFAULT 'America/Santiago',
+    sobrecupos     TINYINT NOT NULL DEFAULT 0,
+    vigencia_desde DATE NOT NULL,
+    vigencia_hasta DATE,
+
+    INDEX (id_prof, dia_semana),
+    FOREIGN KEY (id_prof) REFERENCES paso_profesionales(id_prof),
+    FOREIGN KEY (cod_zona) REFERENCES zonas_acceso(cod_zona)
+);
+
+/*==============================================================*/
+/* Citas agendadas                                              */
+/*==============================================================*/
+CREATE TABLE agenda_citas (
+    id                 INT AUTO_INCREMENT PRIMARY KEY,
+    id_prof            INT NOT NULL,
+    id_paciente        INT NOT NULL,
+    id_bloque          INT NOT NULL, -- sin FK: el bloque puede eliminarse y la cita queda como historial
+    inicio             DATETIME NOT NULL,  -- UTC
+    fin                DATETIME NOT NULL,  -- UTC
+    modalidad          VARCHAR(20) NOT NULL,
+    cod_zona           CHAR(6),
+    estado             VARCHAR(20) NOT NULL,
+    sobrecupo          TINYINT(1) NOT NULL DEFAULT 0,
+    motivo_cancelacion VARCHAR(255),
+    id_cita_original   INT,
+    fecha_creacion     TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+    fecha_actualizacion TIMESTAMP DEFAULT CURRENT_TIMESTAMP ON UPDATE CURRENT_TIMESTAMP,
+
+    INDEX (id_prof, inicio),
+    INDEX (id_paciente, inicio),
+    FOREIGN KEY (id_prof) REFERENCES paso_profesionales(id_prof),
+    FOREIGN KEY (id_paciente) REFERENCES pacientes(id),
+    FOREIGN KEY (id_cita_original) REFERENCES agenda_citas(id)
+);
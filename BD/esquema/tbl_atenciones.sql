@@ -0,0 +1,92 @@
+USE telemedicina;
+
+/*==============================================================*/
+/* Atenciones clínicas                                          */
+/* estado: BORRADOR (editable) o FIRMADA (inmutable)            */
+/* Fechas en UTC                                                */
+/*==============================================================*/
+CREATE TABLE atenciones (
+    id            INT AUTO_INCREMENT PRIMARY KEY,
+    id_paciente   INT NOT NULL,
+    id_prof       INT NOT NULL,
+    id_cita       INT,
+    motivo        VARCHAR(500) NOT NULL,
+    anamnesis     TEXT,
+    examen_fisico TEXT,
+    indicaciones  TEXT,
+    estado        VARCHAR(20) NOT NULL DEFAULT 'BORRADOR',
+    creada_en     DATETIME NOT NULL,
+    firmada_en    DATETIME,
+    fecha_actualizacion TIMESTAMP DEFAULT CURRENT_TIMESTAMP ON UPDATE CURRENT_TIMESTAMP,
+
+    INDEX (id_paciente, creada_en),
+    INDEX (fecha_actualizacion),
+    INDEX (id_prof, creada_en),
+    UNIQUE (id_cita),
+    FOREIGN KEY (id_paciente) REFERENCES pacientes(id),
+    FOREIGN KEY (id_prof) REFERENCES paso_profesionales(id_prof),
+    FOREIGN KEY (id_cita) REFERENCES agenda_citas(id)
+);
+
+CREATE TABLE atencion_diagnosticos (
+    id          INT AUTO_INCREMENT PRIMARY KEY,
+    id_atencion INT NOT NULL,
+    codigo      VARCHAR(10) NOT NULL,  -- CIE-10
+    descripcion VARCHAR(255) NOT NULL,
+    principal   TINYINT(1) NOT NULL DEFAULT 0,
+
+    FOREIGN KEY (id_atencion) REFERENCES atenciones(id)
+);
+
+CREATE TABLE atencion_adjuntos (
+    id          INT AUTO_INCREMENT PRIMARY KEY,
+    id_atencion INT NOT NULL,
+    nombre      VARCHAR(255) NOT NULL,
+    referencia  VARCHAR(500) NOT NULL,
+
+    FOREIGN KEY (id_atencion) REFERENCES atenciones(id)
+);
+
+/*==============================================================*/
+/* Adendas: notas posteriores a la firma, tampoco se modifican  */
+/*==============================================================*/
+CREATE TABLE atencion_adendas (
+    id          INT AUTO_INCREMENT PRIMARY KEY,
+    id_atencion INT NOT NULL,
+    id_prof     INT NOT NULL,
+    texto       TEXT NOT NULL,
+    creada_en   DATETIME NOT NULL,
+
+    INDEX (id_atencion),
+    FOREIGN KEY (id_atencion) REFERENCES atenciones(id),
+    FOREIGN KEY (id_prof) REFERENCES paso_profesionales(id_prof)
+);
+
+/*==============================================================*/
+/* Una atención firmada no se modifica ni se elimina            */
+/*==============================================================*/
+DELIMITER //
+
+CREATE TRIGGER atenciones_firmadas_bu BEFORE UPDATE ON atenciones
+FOR EACH ROW
+BEGIN
+    IF OLD.estado = 'FIRMADA' THEN
+        SIGNAL SQLSTATE '45000' SET MESSAGE_TEXT = 'Atención firmada: use una adenda';
+    END IF;
+END//
+
+CREATE TRIGGER atenciones_firmadas_bd BEFORE DELETE ON atenciones
+FOR EACH ROW
+BEGIN
+    IF OLD.estado = 'FIRMADA' THEN
+        SIGNAL SQLSTATE '45000' SET MESSAGE_TEXT = 'Atención firmada: no se puede eliminar';
+    END IF;
+END//
+
+CREATE TRIGGER atencion_adendas_bu BEFORE UPDATE ON atencion_adendas
+FOR EACH ROW
+BEGIN
+    SIGNAL SQLSTATE '45000' SET MESSAGE_TEXT = 'Las adendas no se modifican';
+END//
+
+DELIMITER ;
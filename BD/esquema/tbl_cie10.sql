@@ -0,0 +1,52 @@
+USE telemedicina;
+
+/*==============================================================*/
+/* Catálogo CIE-10 (versión en español)                         */
+/* Cada versión rige desde vigente_desde; el catálogo completo  */
+/* se carga con POST /api/cie10/importar?version=&vigente_desde=*/
+/* enviando un archivo codigo;descripcion                       */
+/*==============================================================*/
+CREATE TABLE cie10_versiones (
+    version       VARCHAR(20) NOT NULL,
+    vigente_desde DATE NOT NULL,
+    PRIMARY KEY (version)
+);
+
+CREATE TABLE cie10 (
+    version     VARCHAR(20) NOT NULL,
+    codigo      VARCHAR(10) NOT NULL,
+    descripcion VARCHAR(255) NOT NULL,
+    PRIMARY KEY (version, codigo),
+    FOREIGN KEY (version) REFERENCES cie10_versiones(version)
+);
+
+/* Códigos frecuentes en atención primaria, para partir sin importar */
+insert into cie10_versiones(version, vigente_desde) values ('2019', '2019-01-01');
+
+insert into cie10(version, codigo, descripcion) values
+    ('2019', 'A09', 'Diarrea y gastroenteritis de presunto origen infeccioso'),
+    ('2019', 'B34.9', 'Infección viral, no especificada'),
+    ('2019', 'E11', 'Diabetes mellitus no insulinodependiente'),
+    ('2019', 'E11.9', 'Diabetes mellitus no insulinodependiente, sin mención de complicación'),
+    ('2019', 'E78.5', 'Hiperlipidemia, no especificada'),
+    ('2019', 'F32.9', 'Episodio depresivo, no especificado'),
+    ('2019', 'F41.1', 'Trastorno de ansiedad generalizada'),
+    ('2019', 'I10', 'Hipertensión esencial (primaria)'),
+    ('2019', 'I50.9', 'Insuficiencia cardíaca, no especificada'),
+    ('2019', 'J00', 'Rinofaringitis aguda (resfriado común)'),
+    ('2019', 'J02.9', 'Faringitis aguda, no especificada'),
+    ('2019', 'J06.9', 'Infección aguda de las vías respiratorias superiores, no especificada'),
+    ('2019', 'J18.0', 'Bronconeumonía, no especificada'),
+    ('2019', 'J18.9', 'Neumonía, no especificada'),
+    ('2019', 'J20.9', 'Bronquitis aguda, no especificada'),
+    ('2019', 'J44.9', 'Enfermedad pulmonar obstructiva crónica, no especificada'),
+    ('2019', 'J45.9', 'Asma, no especificada'),
+    ('2019', 'K29.7', 'Gastritis, no especificada'),
+    ('2019', 'L89.9', 'Úlcera de decúbito y área de presión, no especificada'),
+    ('2019', 'M54.5', 'Lumbago no especificado'),
+    ('2019', 'N39.0', 'Infección de vías urinarias, sitio no especificado'),
+    ('2019', 'R05', 'Tos'),
+    ('2019', 'R50.9', 'Fiebre, no especificada'),
+    ('2019', 'R51', 'Cefalea'),
+    ('2019', 'U07.1', 'COVID-19, virus identificado'),
+    ('2019', 'Z00.0', 'Examen médico general');
@@ -0,0 +1,42 @@
+USE telemedicina;
+
+/*==============================================================*/
+/* Textos de consentimiento informado por propósito             */
+/* (TELECONSULTA, GRABACION, COMPARTIR_DATOS). Cada cambio es   */
+/* una versión nueva; con requiere_renovacion las aceptaciones  */
+/* de versiones anteriores dejan de valer. Fechas en UTC        */
+/*==============================================================*/
+CREATE TABLE consentimiento_plantillas (
+    id                  INT AUTO_INCREMENT PRIMARY KEY,
+    proposito           VARCHAR(30) NOT NULL,
+    version             SMALLINT UNSIGNED NOT NULL,
+    titulo              VARCHAR(200) NOT NULL,
+    texto               TEXT NOT NULL,
+    requiere_renovacion TINYINT(1) NOT NULL DEFAULT 0,
+    creada_en           DATETIME NOT NULL,
+
+    UNIQUE (proposito, version)
+);
+
+/*==============================================================*/
+/* Aceptaciones (OTORGA) y revocaciones (REVOCA). No se         */
+/* modifican: el estado de un propósito es el de su último      */
+/* evento. otorgante es el RUT del representante si no firma el */
+/* paciente; id_usuario el funcionario que lo registró          */
+/*==============================================================*/
+CREATE TABLE consentimiento_eventos (
+    id          INT AUTO_INCREMENT PRIMARY KEY,
+    id_paciente INT NOT NULL,
+    proposito   VARCHAR(30) NOT NULL,
+    version     SMALLINT UNSIGNED NOT NULL,
+    accion      VARCHAR(10) NOT NULL,
+    canal       VARCHAR(20) NOT NULL,
+    otorgante   VARCHAR(12),
+    id_usuario  INT,
+    ip          VARCHAR(45),
+    ocurrido_en DATETIME NOT NULL,
+
+    INDEX (id_paciente, proposito, id),
+    FOREIGN KEY (id_paciente) REFERENCES pacientes(id),
+    FOREIGN KEY (proposito, version) REFERENCES consentimiento_plantillas(proposito, version)
+);
@@ -0,0 +1,33 @@
+USE telemedicina;
+
+/*==============================================================*/
+/* Períodos de contrato de los profesionales; una persona puede */
+/* tener varios (reingresos) sin traslape. Sin fecha_egreso     */
+/* sigue abierto. El estado y las fechas de paso_profesionales  */
+/* se derivan de estos períodos                                 */
+/*==============================================================*/
+CREATE TABLE contratos_profesionales (
+    id            INT AUTO_INCREMENT PRIMARY KEY,
+    id_prof       INT NOT NULL,
+    fecha_ingreso DATE NOT NULL,
+    fecha_egreso  DATE,
+    motivo_egreso VARCHAR(255),
+    observacion   VARCHAR(255),
+    creado_en     DATETIME NOT NULL,
+
+    INDEX (id_prof, fecha_ingreso),
+    FOREIGN KEY (id_prof) REFERENCES paso_profesionales(id_prof),
+    CHECK (fecha_egreso IS NULL OR fecha_egreso >= fecha_ingreso)
+);
+
+/* Un período por cada profesional cargado con fecha de ingreso. */
+/* Un egreso anterior al ingreso corresponde a un período previo */
+/* que no se cargó, así que el período queda abierto             */
+INSERT INTO contratos_profesionales (id_prof, fecha_ingreso, fecha_egreso, observacion, creado_en)
+SELECT id_prof,
+       fecha_ingreso,
+       IF(fecha_egreso >= fecha_ingreso, fecha_egreso, NULL),
+       'Migrado desde paso_profesionales',
+       UTC_TIMESTAMP()
+FROM paso_profesionales
+WHERE fecha_ingreso IS NOT NULL;
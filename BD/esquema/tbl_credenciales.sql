@@ -0,0 +1,41 @@
+USE telemedicina;
+
+/*==============================================================*/
+/* Credenciales de los profesionales: título, inscripción RNPI, */
+/* vacunación, seguro de responsabilidad civil u otras. El      */
+/* documento está en el almacén de adjuntos bajo su SHA-256.    */
+/* Una renovación es una fila nueva del mismo tipo; vence_en    */
+/* nulo: no vence. Fechas de registro en UTC                    */
+/*==============================================================*/
+CREATE TABLE credenciales_profesionales (
+    id            INT AUTO_INCREMENT PRIMARY KEY,
+    id_prof       INT NOT NULL,
+    tipo          VARCHAR(12) NOT NULL,
+    descripcion   VARCHAR(200),
+    numero        VARCHAR(200),
+    emitida_en    DATE,
+    vence_en      DATE,
+    nombre        VARCHAR(200) NOT NULL,
+    tipo_mime     VARCHAR(50) NOT NULL,
+    tamano        INT UNSIGNED NOT NULL,
+    sha256        CHAR(64) NOT NULL,
+    registrada_en DATETIME NOT NULL,
+
+    INDEX (id_prof, tipo),
+    INDEX (vence_en),
+    FOREIGN KEY (id_prof) REFERENCES paso_profesionales(id_prof),
+    CHECK (vence_en IS NULL OR emitida_en IS NULL OR vence_en >= emitida_en)
+);
+
+/*==============================================================*/
+/* Avisos de vencimiento enviados: dias es 30 o 7. La clave     */
+/* impide enviar dos veces el mismo aviso. Fechas en UTC        */
+/*==============================================================*/
+CREATE TABLE credenciales_alertas (
+    id_credencial INT NOT NULL,
+    dias          SMALLINT UNSIGNED NOT NULL,
+    enviada_en    DATETIME NOT NULL,
+
+    PRIMARY KEY (id_credencial, dias),
+    FOREIGN KEY (id_credencial) REFERENCES credenciales_profesionales(id)
+);
@@ -0,0 +1,35 @@
+USE telemedicina;
+
+/*==============================================================*/
+/* Derivaciones recibidas de establecimientos externos como     */
+/* Bundle FHIR (ServiceRequest). referencia_externa es el       */
+/* identificador de la solicitud en origen (sistema|valor) y    */
+/* evita registrarla dos veces. Fechas en UTC                   */
+/*==============================================================*/
+CREATE TABLE derivaciones (
+    id                 INT AUTO_INCREMENT PRIMARY KEY,
+    id_paciente        INT NOT NULL,
+    origen             VARCHAR(200),
+    referencia_externa VARCHAR(255),
+    especialidad       VARCHAR(200) NOT NULL,
+    prioridad          VARCHAR(20) NOT NULL DEFAULT 'RUTINA',
+    nota               TEXT,
+    estado             VARCHAR(20) NOT NULL DEFAULT 'PENDIENTE',
+    recibida_en        DATETIME NOT NULL,
+
+    INDEX (id_paciente, recibida_en),
+    INDEX (estado, recibida_en),
+    UNIQUE (referencia_externa),
+    FOREIGN KEY (id_paciente) REFERENCES pacientes(id)
+);
+
+/* Diagnósticos (Condition) que motivan la derivación, en CIE-10 */
+CREATE TABLE derivacion_diagnosticos (
+    id            INT AUTO_INCREMENT PRIMARY KEY,
+    id_derivacion INT NOT NULL,
+    codigo        VARCHAR(10) NOT NULL,
+    descripcion   VARCHAR(255) NOT NULL,
+    principal     TINYINT(1) NOT NULL DEFAULT 0,
+
+    FOREIGN KEY (id_derivacion) REFERENCES derivaciones(id)
+);
@@ -0,0 +1,126 @@
+USE telemedicina;
+
+/*==============================================================*/
+/* Plantillas de documentos clínicos                            */
+/* tipo: CERTIFICADO, INFORME, INTERCONSULTA                    */
+/* La vigente es la de mayor versión; {{clave}} marca los datos */
+/* y '# ' / '## ' al inicio de línea los títulos                */
+/*==============================================================*/
+CREATE TABLE documento_plantillas (
+    id        INT AUTO_INCREMENT PRIMARY KEY,
+    tipo      VARCHAR(20) NOT NULL,
+    version   SMALLINT NOT NULL,
+    titulo    VARCHAR(150) NOT NULL,
+    cuerpo    TEXT NOT NULL,
+    creada_en DATETIME NOT NULL,
+
+    UNIQUE (tipo, version)
+);
+
+/*==============================================================*/
+/* Último folio emitido por cliente                             */
+/*==============================================================*/
+CREATE TABLE documento_folios (
+    cod_cliente INT PRIMARY KEY,
+    ultimo      INT NOT NULL DEFAULT 0
+);
+
+/*==============================================================*/
+/* Documentos emitidos; fechas en UTC                           */
+/*==============================================================*/
+CREATE TABLE documentos (
+    id          INT AUTO_INCREMENT PRIMARY KEY,
+    cod_cliente INT NOT NULL,
+    folio       INT NOT NULL,
+    tipo        VARCHAR(20) NOT NULL,
+    id_paciente INT NOT NULL,
+    id_prof     INT NOT NULL,
+    id_atencion INT,
+    creado_en   DATETIME NOT NULL,
+
+    UNIQUE (cod_cliente, folio),
+    INDEX (id_paciente, creado_en),
+    INDEX (id_prof, creado_en),
+    FOREIGN KEY (id_paciente) REFERENCES pacientes(id),
+    FOREIGN KEY (id_prof) REFERENCES paso_profesionales(id_prof),
+    FOREIGN KEY (id_atencion) REFERENCES atenciones(id)
+);
+
+/*==============================================================*/
+/* Versiones: texto ya fusionado, no se modifican               */
+/* huella: SHA-256 hexadecimal del contenido                    */
+/*==============================================================*/
+CREATE TABLE documento_versiones (
+    id           INT AUTO_INCREMENT PRIMARY KEY,
+    id_documento INT NOT NULL,
+    version      SMALLINT NOT NULL,
+    id_plantilla INT NOT NULL,
+    id_prof      INT NOT NULL,
+    titulo       VARCHAR(150) NOT NULL,
+    contenido    TEXT NOT NULL,
+    huella       CHAR(64) NOT NULL,
+    creada_en    DATETIME NOT NULL,
+
+    UNIQUE (id_documento, version),
+    FOREIGN KEY (id_documento) REFERENCES documentos(id),
+    FOREIGN KEY (id_plantilla) REFERENCES documento_plantillas(id),
+    FOREIGN KEY (id_prof) REFERENCES paso_profesionales(id_prof)
+);
+
+DELIMITER //
+
+CREATE TRIGGER documento_versiones_bu BEFORE UPDATE ON documento_versiones
+FOR EACH ROW
+BEGIN
+    SIGNAL SQLSTATE '45000' SET MESSAGE_TEXT = 'Las versiones de un documento no se modifican';
+END//
+
+DELIMITER ;
+
+INSERT INTO documento_plantillas (tipo, version, titulo, cuerpo, creada_en) VALUES
+('CERTIFICADO', 1, 'Certificado de atención',
+'# CERTIFICADO DE ATENCIÓN
+
+{{profesional.nombre}}, {{profesional.especialidad}}, RUT {{profesional.rut}}, registro RNPI {{profesional.rnpi}}, certifica que {{paciente.nombre}}, RUT {{paciente.rut}}, fue atendido(a) el {{atencion.fecha}} por {{atencion.motivo}}.
+
+## Diagnóstico
+{{atencion.diagnosticos}}
+
+## Indicaciones
+{{atencion.indicaciones}}
+
+Se extiende el presente certificado a petición del interesado para los fines que estime conveniente.
+
+{{documento.fecha}}', UTC_TIMESTAMP()),
+('INFORME', 1, 'Informe médico',
+'# INFORME MÉDICO
+
+## Paciente
+{{paciente.nombre}}, RUT {{paciente.rut}}, {{paciente.edad}}
+
+## Antecedentes de la atención
+Atendido(a) el {{atencion.fecha}} por {{atencion.motivo}}.
+
+## Diagnóstico
+{{atencion.diagnosticos}}
+
+## Informe
+{{campos.contenido}}
+
+{{documento.fecha}}', UTC_TIMESTAMP()),
+('INTERCONSULTA', 1, 'Interconsulta',
+'# INTERCONSULTA
+
+## Paciente
+{{paciente.nombre}}, RUT {{paciente.rut}}, {{paciente.edad}}
+
+## Especialidad de destino
+{{campos.especialidad_destino}}
+
+## Motivo de la derivación
+{{campos.motivo}}
+
+## Diagnóstico presuntivo
+{{atencion.diagnosticos}}
+
+Derivado por {{profesional.nombre}}, {{profesional.especialidad}}, el {{documento.fecha}}.', UTC_TIMESTAMP());
@@ -0,0 +1,72 @@
+USE telemedicina;
+
+/*==============================================================*/
+/* Tarifas por código de prestación para una previsión (tramo   */
+/* FONASA o ISAPRE) o un cliente. cobertura es el % que paga la */
+/* previsión; el resto es copago. Sin modalidad aplica a        */
+/* cualquiera. Montos en pesos                                  */
+/*==============================================================*/
+CREATE TABLE tarifas (
+    id                INT AUTO_INCREMENT PRIMARY KEY,
+    codigo_prestacion VARCHAR(20) NOT NULL,
+    descripcion       VARCHAR(200) NOT NULL,
+    modalidad         VARCHAR(20),
+    cod_prevision     INT,
+    cod_cliente       INT,
+    valor             INT UNSIGNED NOT NULL,
+    cobertura         TINYINT UNSIGNED NOT NULL DEFAULT 100,
+    vigencia_desde    DATE NOT NULL,
+    vigencia_hasta    DATE,
+
+    INDEX (cod_prevision, vigencia_desde),
+    INDEX (cod_cliente, vigencia_desde),
+    FOREIGN KEY (cod_prevision) REFERENCES previsiones(cod_prevision)
+);
+
+/*==============================================================*/
+/* Lotes de cobro de un período (AAAA-MM) a un pagador          */
+/*==============================================================*/
+CREATE TABLE lotes_facturacion (
+    id            INT AUTO_INCREMENT PRIMARY KEY,
+    periodo       CHAR(7) NOT NULL,
+    pagador       VARCHAR(20) NOT NULL,
+    cod_prevision INT,
+    cod_cliente   INT,
+    cantidad      INT UNSIGNED NOT NULL,
+    total_valor   BIGINT UNSIGNED NOT NULL,
+    total_pagador BIGINT UNSIGNED NOT NULL,
+    total_copago  BIGINT UNSIGNED NOT NULL,
+    creado_en     DATETIME NOT NULL,
+
+    INDEX (periodo, pagador)
+);
+
+/*==============================================================*/
+/* Cargo de cada atención firmada. OBSERVADO si no se pudo      */
+/* valorizar (observacion dice por qué); FACTURADO al entrar a  */
+/* un lote. fecha es el día local de la atención                */
+/*==============================================================*/
+CREATE TABLE cargos (
+    id                INT AUTO_INCREMENT PRIMARY KEY,
+    id_atencion       INT NOT NULL,
+    id_paciente       INT NOT NULL,
+    fecha             DATE NOT NULL,
+    codigo_prestacion VARCHAR(20),
+    pagador           VARCHAR(20),
+    cod_prevision     INT,
+    cod_cliente       INT,
+    valor             INT UNSIGNED NOT NULL DEFAULT 0,
+    monto_pagador     INT UNSIGNED NOT NULL DEFAULT 0,
+    copago            INT UNSIGNED NOT NULL DEFAULT 0,
+    estado            VARCHAR(20) NOT NULL DEFAULT 'PENDIENTE',
+    observacion       VARCHAR(255),
+    id_lote           INT,
+    generado_en       DATETIME NOT NULL,
+
+    UNIQUE (id_atencion),
+    INDEX (estado, pagador, fecha),
+    INDEX (id_paciente, fecha),
+    FOREIGN KEY (id_atencion) REFERENCES atenciones(id),
+    FOREIGN KEY (id_paciente) REFERENCES pacientes(id),
+    FOREIGN KEY (id_lote) REFERENCES lotes_facturacion(id)
+);
@@ -0,0 +1,20 @@
+USE telemedicina;
+
+/*==============================================================*/
+/* Feriados administrados (elecciones, feriados locales nuevos) */
+/* Los feriados legales se calculan en el backend               */
+/* cod_zona NULL = feriado nacional                             */
+/*==============================================================*/
+CREATE TABLE feriados (
+    id       INT AUTO_INCREMENT PRIMARY KEY,
+    fecha    DATE NOT NULL,
+    nombre   VARCHAR(100) NOT NULL,
+    cod_zona CHAR(6),
+
+    INDEX (fecha),
+    FOREIGN KEY (cod_zona) REFERENCES zonas_acceso(cod_zona)
+);
+
+insert into feriados(fecha, nombre) values
+    ('2025-11-16', 'Elecciones Presidenciales y Parlamentarias'),
+    ('2025-12-14', 'Elecciones Presidenciales (segunda vuelta)');
@@ -0,0 +1,69 @@
+USE telemedicina;
+
+/*==============================================================*/
+/* Claves de firma electrónica de los profesionales (Ed25519)   */
+/* clave_publica: 32 bytes en hexadecimal                       */
+/* clave_privada: cifrada con AES-256-GCM usando la clave       */
+/*   maestra FIRMA_CLAVE_MAESTRA del servidor; nonce en hex     */
+/* Una sola clave vigente (revocada_en NULL) por profesional    */
+/*==============================================================*/
+CREATE TABLE firma_claves (
+    id            INT AUTO_INCREMENT PRIMARY KEY,
+    id_prof       INT NOT NULL,
+    clave_publica CHAR(64) NOT NULL,
+    clave_privada VARCHAR(128) NOT NULL,
+    nonce         CHAR(24) NOT NULL,
+    creada_en     DATETIME NOT NULL,
+    revocada_en   DATETIME,
+
+    INDEX (id_prof),
+    FOREIGN KEY (id_prof) REFERENCES paso_profesionales(id_prof)
+);
+
+/*==============================================================*/
+/* Firmas separadas de recetas y versiones de documentos        */
+/* tipo: RECETA o DOCUMENTO; version 0 en las recetas           */
+/* huella: SHA-256 del contenido canónico al firmar             */
+/* firma: Ed25519 (hex) de la huella junto con tipo, documento, */
+/*   versión, firmante, RUT, RNPI y firmada_en (UTC)            */
+/*==============================================================*/
+CREATE TABLE firmas (
+    id            INT AUTO_INCREMENT PRIMARY KEY,
+    tipo          VARCHAR(20) NOT NULL,
+    id_documento  INT NOT NULL,
+    version       SMALLINT UNSIGNED NOT NULL DEFAULT 0,
+    id_clave      INT NOT NULL,
+    id_prof       INT NOT NULL,
+    rut_firmante  VARCHAR(20) NOT NULL,
+    rnpi_firmante VARCHAR(20) NOT NULL,
+    firmada_en    DATETIME NOT NULL,
+    huella        CHAR(64) NOT NULL,
+    firma         CHAR(128) NOT NULL,
+
+    UNIQUE (tipo, id_documento, version),
+    INDEX (id_prof, firmada_en),
+    FOREIGN KEY (id_clave) REFERENCES firma_claves(id),
+    FOREIGN KEY (id_prof) REFERENCES paso_profesionales(id_prof)
+);
+
+/*==============================================================*/
+/* Una firma no se modifica; una clave sólo puede revocarse     */
+/*==============================================================*/
+DELIMITER //
+
+CREATE TRIGGER firmas_bu BEFORE UPDATE ON firmas
+FOR EACH ROW
+BEGIN
+    SIGNAL SQLSTATE '45000' SET MESSAGE_TEXT = 'Las firmas no se modifican';
+END//
+
+CREATE TRIGGER firma_claves_bu BEFORE UPDATE ON firma_claves
+FOR EACH ROW
+BEGIN
+    IF OLD.revocada_en IS NOT NULL OR NEW.revocada_en IS NULL
+       OR NEW.clave_publica <> OLD.clave_publica OR NEW.clave_privada <> OLD.clave_privada THEN
+        SIGNAL SQLSTATE '45000' SET MESSAGE_TEXT = 'Una clave de firma sólo puede revocarse';
+    END IF;
+END//
+
+DELIMITER ;
@@ -0,0 +1,28 @@
+USE telemedicina;
+
+/*==============================================================*/
+/* Monto que se paga a los profesionales a honorarios por cada  */
+/* atención firmada o visita realizada. Sin especialidad o sin  */
+/* zona aplica a todas; la más específica prevalece. Pesos      */
+/*==============================================================*/
+CREATE TABLE tarifas_honorarios (
+    id             INT AUTO_INCREMENT PRIMARY KEY,
+    tipo           VARCHAR(20) NOT NULL,
+    especialidad   VARCHAR(100),
+    cod_zona       CHAR(6),
+    monto          INT UNSIGNED NOT NULL,
+    vigencia_desde DATE NOT NULL,
+    vigencia_hasta DATE,
+
+    INDEX (tipo, vigencia_desde),
+    FOREIGN KEY (cod_zona) REFERENCES zonas_acceso(cod_zona)
+);
+
+/*==============================================================*/
+/* Tasa de retención de las boletas de honorarios desde cada    */
+/* fecha, en centésimas de punto (1450 = 14,5 %)                */
+/*==============================================================*/
+CREATE TABLE retenciones_honorarios (
+    vigencia_desde DATE PRIMARY KEY,
+    tasa           SMALLINT UNSIGNED NOT NULL
+);
@@ -0,0 +1,56 @@
+USE telemedicina;
+
+/*==============================================================*/
+/* Mensajería segura paciente-profesional                       */
+/* Cliente y zona se copian del paciente al crear la            */
+/* conversación. Los mensajes con más de retencion_dias se      */
+/* eliminan. Fechas en UTC                                      */
+/*==============================================================*/
+CREATE TABLE conversaciones (
+    id                INT AUTO_INCREMENT PRIMARY KEY,
+    id_paciente       INT NOT NULL,
+    asunto            VARCHAR(200) NOT NULL,
+    cod_cliente       INT,
+    cod_zona          CHAR(6),
+    retencion_dias    SMALLINT UNSIGNED NOT NULL DEFAULT 1825,
+    creada_en         DATETIME NOT NULL,
+    ultimo_mensaje_en DATETIME,
+
+    INDEX (id_paciente),
+    INDEX (cod_cliente, cod_zona),
+    FOREIGN KEY (id_paciente) REFERENCES pacientes(id),
+    FOREIGN KEY (cod_zona) REFERENCES zonas_acceso(cod_zona)
+);
+
+/*==============================================================*/
+/* contenido: texto y adjuntos en JSON, cifrados con AES-256-GCM */
+/* (clave maestra FIRMA_CLAVE_MAESTRA), en hexadecimal          */
+/* autor_tipo: PACIENTE o PROFESIONAL                           */
+/*==============================================================*/
+CREATE TABLE mensajes (
+    id              INT AUTO_INCREMENT PRIMARY KEY,
+    id_conversacion INT NOT NULL,
+    autor_tipo      VARCHAR(20) NOT NULL,
+    autor_id        INT NOT NULL,
+    nonce           CHAR(24) NOT NULL,
+    contenido       MEDIUMTEXT NOT NULL,
+    enviado_en      DATETIME NOT NULL,
+
+    INDEX (id_conversacion, id),
+    INDEX (enviado_en),
+    FOREIGN KEY (id_conversacion) REFERENCES conversaciones(id)
+);
+
+/*==============================================================*/
+/* Hasta qué mensaje leyó cada participante                     */
+/*==============================================================*/
+CREATE TABLE conversacion_lecturas (
+    id_conversacion INT NOT NULL,
+    lector_tipo     VARCHAR(20) NOT NULL,
+    lector_id       INT NOT NULL,
+    hasta           INT NOT NULL,
+    leido_en        DATETIME NOT NULL,
+
+    PRIMARY KEY (id_conversacion, lector_tipo, lector_id),
+    FOREIGN KEY (id_conversacion) REFERENCES conversaciones(id)
+);
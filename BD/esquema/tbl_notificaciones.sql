@@ -0,0 +1,44 @@
+USE telemedicina;
+
+/*==============================================================*/
+/* Canales aceptados por cada destinatario                      */
+/* destinatario_tipo: USUARIO, PACIENTE o PROFESIONAL           */
+/* Sin fila se usan todos los canales con dirección             */
+/*==============================================================*/
+CREATE TABLE notificacion_preferencias (
+    destinatario_tipo VARCHAR(20) NOT NULL,
+    destinatario_id   INT NOT NULL,
+    email             BOOLEAN NOT NULL DEFAULT TRUE,
+    sms               BOOLEAN NOT NULL DEFAULT TRUE,
+    app               BOOLEAN NOT NULL DEFAULT TRUE,
+
+    PRIMARY KEY (destinatario_tipo, destinatario_id)
+);
+
+/*==============================================================*/
+/* Outbox de notificaciones                                     */
+/* canal: EMAIL, SMS o APP                                      */
+/* estado: PENDIENTE, ENVIADA, FALLIDA o LEIDA (sólo APP)       */
+/* Cada fallo reprograma proximo_intento_en con espera          */
+/* exponencial; tras 6 intentos queda FALLIDA. Fechas en UTC    */
+/*==============================================================*/
+CREATE TABLE notificaciones (
+    id                 INT AUTO_INCREMENT PRIMARY KEY,
+    plantilla          VARCHAR(40) NOT NULL,
+    destinatario_tipo  VARCHAR(20) NOT NULL,
+    destinatario_id    INT NOT NULL,
+    canal              VARCHAR(10) NOT NULL,
+    direccion          VARCHAR(200),
+    asunto             VARCHAR(200) NOT NULL,
+    cuerpo             TEXT NOT NULL,
+    estado             VARCHAR(20) NOT NULL DEFAULT 'PENDIENTE',
+    intentos           TINYINT UNSIGNED NOT NULL DEFAULT 0,
+    proximo_intento_en DATETIME NOT NULL,
+    ultimo_error       VARCHAR(500),
+    creada_en          DATETIME NOT NULL,
+    enviada_en         DATETIME,
+    leida_en           DATETIME,
+
+    INDEX (estado, proximo_intento_en),
+    INDEX (destinatario_tipo, destinatario_id, canal)
+);
@@ -0,0 +1,29 @@
+USE telemedicina;
+
+CREATE TABLE pacientes (
+    id INT AUTO_INCREMENT PRIMARY KEY,
+    rut VARCHAR(12) NOT NULL,
+    nombres VARCHAR(100) NOT NULL,
+    ap_paterno VARCHAR(100) NOT NULL,
+    ap_materno VARCHAR(100),
+    fecha_nacimiento DATE,
+    sexo CHAR(1),
+    cod_prevision INT,
+    direccion VARCHAR(150),
+    comuna VARCHAR(100),
+    ciudad VARCHAR(100),
+    cod_zona CHAR(6),
+    latitud DOUBLE,
+    longitud DOUBLE,
+    email VARCHAR(100),
+    telefonos VARCHAR(100),
+    cod_cliente INT,
+    estatus INT,
+    fecha_creacion TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+    fecha_actualizacion TIMESTAMP DEFAULT CURRENT_TIMESTAMP ON UPDATE CURRENT_TIMESTAMP,
+
+    UNIQUE KEY (rut),
+    INDEX (ap_paterno, ap_materno, nombres),
+    FOREIGN KEY (cod_zona) REFERENCES zonas_acceso(cod_zona),
+    FOREIGN KEY (cod_prevision) REFERENCES previsiones(cod_prevision)
+);
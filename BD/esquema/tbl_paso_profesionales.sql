@@ -17,7 +17,8 @@ CREATE TABLE paso_profesionales (
     fecha_egreso DATE,
     zona VARCHAR(50),
     estado VARCHAR(50),
-    conara VARCHAR(100)
+    conara VARCHAR(100),
+    fecha_actualizacion TIMESTAMP DEFAULT CURRENT_TIMESTAMP ON UPDATE CURRENT_TIMESTAMP
 );
 
 INSERT INTO paso_profesionales (rut,nombres,ap_paterno,ap_materno,direccion,comuna,ciudad,email,telefonos,especialidad,registro_rnpi,fecha_ingreso,fecha_egreso,zona,estado,conara) VALUES 
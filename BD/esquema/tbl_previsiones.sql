@@ -0,0 +1,28 @@
+USE telemedicina;
+
+CREATE TABLE previsiones (
+    cod_prevision  INT NOT NULL,
+    tipo           VARCHAR(10) NOT NULL,
+    nombre         VARCHAR(100) NOT NULL,
+    tramo          CHAR(1),
+    vigencia_desde DATE NOT NULL,
+    vigencia_hasta DATE,
+    PRIMARY KEY (cod_prevision),
+    INDEX (tipo)
+);
+
+INSERT INTO previsiones(cod_prevision, tipo, nombre, tramo, vigencia_desde, vigencia_hasta)
+VALUES (1,   'FONASA', 'FONASA TRAMO A',          'A',  '2000-01-01', NULL),
+       (2,   'FONASA', 'FONASA TRAMO B',          'B',  '2000-01-01', NULL),
+       (3,   'FONASA', 'FONASA TRAMO C',          'C',  '2000-01-01', NULL),
+       (4,   'FONASA', 'FONASA TRAMO D',          'D',  '2000-01-01', NULL),
+       (101, 'ISAPRE', 'BANMEDICA',               NULL, '2000-01-01', NULL),
+       (102, 'ISAPRE', 'COLMENA GOLDEN CROSS',    NULL, '2000-01-01', NULL),
+       (103, 'ISAPRE', 'CONSALUD',                NULL, '2000-01-01', NULL),
+       (104, 'ISAPRE', 'CRUZBLANCA',              NULL, '2000-01-01', NULL),
+       (105, 'ISAPRE', 'NUEVA MASVIDA',           NULL, '2000-01-01', NULL),
+       (106, 'ISAPRE', 'VIDA TRES',               NULL, '2000-01-01', NULL),
+       (107, 'ISAPRE', 'ESENCIAL',                NULL, '2022-01-01', NULL),
+       (108, 'ISAPRE', 'ISALUD',                  NULL, '2000-01-01', NULL),
+       (109, 'ISAPRE', 'FUNDACION BANCO ESTADO',  NULL, '2000-01-01', NULL),
+       (110, 'ISAPRE', 'CRUZ DEL NORTE',          NULL, '2000-01-01', NULL);
@@ -0,0 +1,64 @@
+USE telemedicina;
+
+/*==============================================================*/
+/* Recetas emitidas en una atención                             */
+/* estado: EMITIDA o ANULADA; el contenido no se modifica       */
+/* codigo: verificación pública (XXXX-XXXX-XXXX)                */
+/* huella: SHA-256 hexadecimal del contenido impreso            */
+/* emitida_en en UTC                                            */
+/*==============================================================*/
+CREATE TABLE recetas (
+    id               INT AUTO_INCREMENT PRIMARY KEY,
+    id_atencion      INT NOT NULL,
+    id_paciente      INT NOT NULL,
+    id_prof          INT NOT NULL,
+    registro_rnpi    VARCHAR(20) NOT NULL,
+    codigo           CHAR(14) NOT NULL,
+    huella           CHAR(64) NOT NULL,
+    indicaciones     TEXT,
+    estado           VARCHAR(20) NOT NULL DEFAULT 'EMITIDA',
+    motivo_anulacion VARCHAR(500),
+    emitida_en       DATETIME NOT NULL,
+    valida_hasta     DATE NOT NULL,
+
+    UNIQUE (codigo),
+    INDEX (id_paciente, emitida_en),
+    INDEX (id_prof, emitida_en),
+    INDEX (id_atencion),
+    FOREIGN KEY (id_atencion) REFERENCES atenciones(id),
+    FOREIGN KEY (id_paciente) REFERENCES pacientes(id),
+    FOREIGN KEY (id_prof) REFERENCES paso_profesionales(id_prof)
+);
+
+CREATE TABLE receta_lineas (
+    id          INT AUTO_INCREMENT PRIMARY KEY,
+    id_receta   INT NOT NULL,
+    medicamento VARCHAR(255) NOT NULL,
+    dosis       VARCHAR(100) NOT NULL,
+    frecuencia  VARCHAR(100) NOT NULL,
+    duracion    VARCHAR(100) NOT NULL,
+
+    FOREIGN KEY (id_receta) REFERENCES recetas(id)
+);
+
+/*==============================================================*/
+/* Una receta emitida sólo puede pasar a ANULADA                */
+/*==============================================================*/
+DELIMITER //
+
+CREATE TRIGGER recetas_bu BEFORE UPDATE ON recetas
+FOR EACH ROW
+BEGIN
+    IF OLD.estado <> 'EMITIDA' OR NEW.estado <> 'ANULADA'
+       OR NEW.huella <> OLD.huella OR NEW.codigo <> OLD.codigo THEN
+        SIGNAL SQLSTATE '45000' SET MESSAGE_TEXT = 'Receta emitida: sólo puede anularse';
+    END IF;
+END//
+
+CREATE TRIGGER receta_lineas_bu BEFORE UPDATE ON receta_lineas
+FOR EACH ROW
+BEGIN
+    SIGNAL SQLSTATE '45000' SET MESSAGE_TEXT = 'Las líneas de una receta no se modifican';
+END//
+
+DELIMITER ;
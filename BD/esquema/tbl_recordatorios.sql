@@ -0,0 +1,27 @@
+USE telemedicina;
+
+/*==============================================================*/
+/* Recordatorios enviados por cita                              */
+/* tipo: 24H o 2H. La clave impide enviar dos veces el mismo    */
+/* recordatorio. Fechas en UTC                                  */
+/*==============================================================*/
+CREATE TABLE cita_recordatorios (
+    id_cita    INT NOT NULL,
+    tipo       VARCHAR(5) NOT NULL,
+    enviado_en DATETIME NOT NULL,
+
+    PRIMARY KEY (id_cita, tipo),
+    FOREIGN KEY (id_cita) REFERENCES agenda_citas(id)
+);
+
+/*==============================================================*/
+/* Respuesta del paciente desde el enlace del recordatorio      */
+/* respuesta: CONFIRMADA o CANCELADA                            */
+/*==============================================================*/
+CREATE TABLE cita_respuestas (
+    id_cita       INT NOT NULL PRIMARY KEY,
+    respuesta     VARCHAR(12) NOT NULL,
+    respondida_en DATETIME NOT NULL,
+
+    FOREIGN KEY (id_cita) REFERENCES agenda_citas(id)
+);
@@ -0,0 +1,22 @@
+USE telemedicina;
+
+/*==============================================================*/
+/* Sala de espera virtual de las teleconsultas                  */
+/* estado: ESPERANDO, LLAMADO, ATENDIDO, AUSENTE o RETIRADO     */
+/* Un llamado que no entra a la videollamada en 5 minutos queda */
+/* AUSENTE. Fechas en UTC                                       */
+/*==============================================================*/
+CREATE TABLE sala_espera (
+    id_cita     INT PRIMARY KEY,
+    id_prof     INT NOT NULL,
+    id_paciente INT NOT NULL,
+    estado      VARCHAR(20) NOT NULL DEFAULT 'ESPERANDO',
+    llegada_en  DATETIME NOT NULL,
+    llamada_en  DATETIME,
+    cerrada_en  DATETIME,
+
+    INDEX (id_prof, estado),
+    FOREIGN KEY (id_cita) REFERENCES agenda_citas(id),
+    FOREIGN KEY (id_prof) REFERENCES paso_profesionales(id_prof),
+    FOREIGN KEY (id_paciente) REFERENCES pacientes(id)
+);
@@ -0,0 +1,19 @@
+USE telemedicina;
+
+/*==============================================================*/
+/* Sala de video de una cita en modalidad TELECONSULTA          */
+/* token_*: SHA-256 hexadecimal del token de acceso de cada rol */
+/*   (el token mismo sólo se entrega al crear la sala)          */
+/* iniciada_en: primera vez que ambos estuvieron conectados     */
+/* terminada_en: cuando alguno colgó. Fechas en UTC             */
+/*==============================================================*/
+CREATE TABLE teleconsultas (
+    id_cita           INT PRIMARY KEY,
+    token_paciente    CHAR(64) NOT NULL,
+    token_profesional CHAR(64) NOT NULL,
+    creada_en         DATETIME NOT NULL,
+    iniciada_en       DATETIME,
+    terminada_en      DATETIME,
+
+    FOREIGN KEY (id_cita) REFERENCES agenda_citas(id)
+);
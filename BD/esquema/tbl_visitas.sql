@@ -0,0 +1,66 @@
+USE telemedicina;
+
+/*==============================================================*/
+/* Visitas domiciliarias                                        */
+/* fecha y ventana en hora local de la zona; creada_en en UTC   */
+/* estado: PENDIENTE, EN_RUTA, REALIZADA, FALLIDA               */
+/*==============================================================*/
+CREATE TABLE visitas (
+    id            INT AUTO_INCREMENT PRIMARY KEY,
+    id_paciente   INT NOT NULL,
+    id_prof       INT,
+    cod_zona      CHAR(6) NOT NULL,
+    direccion     VARCHAR(200) NOT NULL,
+    comuna        VARCHAR(100),
+    latitud       DOUBLE,
+    longitud      DOUBLE,
+    fecha         DATE NOT NULL,
+    ventana_desde TIME,
+    ventana_hasta TIME,
+    duracion_min  SMALLINT NOT NULL DEFAULT 30,
+    motivo        VARCHAR(500) NOT NULL,
+    estado        VARCHAR(20) NOT NULL DEFAULT 'PENDIENTE',
+    motivo_estado VARCHAR(500),
+    creada_en     DATETIME NOT NULL,
+
+    INDEX (id_prof, fecha),
+    INDEX (cod_zona, fecha),
+    INDEX (id_paciente),
+    FOREIGN KEY (id_paciente) REFERENCES pacientes(id),
+    FOREIGN KEY (id_prof) REFERENCES paso_profesionales(id_prof),
+    FOREIGN KEY (cod_zona) REFERENCES zonas_acceso(cod_zona)
+);
+
+/*==============================================================*/
+/* Historial de estados de cada visita                          */
+/*==============================================================*/
+CREATE TABLE visita_eventos (
+    id            INT AUTO_INCREMENT PRIMARY KEY,
+    id_visita     INT NOT NULL,
+    estado        VARCHAR(20) NOT NULL,
+    motivo        VARCHAR(500),
+    registrado_en DATETIME NOT NULL,
+
+    INDEX (id_visita),
+    FOREIGN KEY (id_visita) REFERENCES visitas(id)
+);
+
+/*==============================================================*/
+/* Marcas de llegada y salida enviadas por la app móvil         */
+/* tipo: LLEGADA, SALIDA; fechas en UTC                         */
+/* distancia_km: al domicilio de la visita, NULL sin coordenadas*/
+/*==============================================================*/
+CREATE TABLE visita_marcas (
+    id            INT AUTO_INCREMENT PRIMARY KEY,
+    id_visita     INT NOT NULL,
+    tipo          VARCHAR(10) NOT NULL,
+    latitud       DOUBLE NOT NULL,
+    longitud      DOUBLE NOT NULL,
+    precision_m   DOUBLE,
+    distancia_km  DOUBLE,
+    registrada_en DATETIME NOT NULL,
+    recibida_en   DATETIME NOT NULL,
+
+    UNIQUE (id_visita, tipo),
+    FOREIGN KEY (id_visita) REFERENCES visitas(id)
+);
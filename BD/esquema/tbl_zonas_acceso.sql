@@ -4,6 +4,8 @@ CREATE TABLE zonas_acceso (
     cod_zona   CHAR(6) NOT NULL,
     nom_zona   VARCHAR(100) NOT NULL,
     orden_zona INT,
+    zona_horaria VARCHAR(40) NOT NULL DEFAULT 'America/Santiago',
+    fecha_actualizacion TIMESTAMP DEFAULT CURRENT_TIMESTAMP ON UPDATE CURRENT_TIMESTAMP,
     PRIMARY KEY (cod_zona)
 );
 
@@ -91,3 +93,7 @@ union select '034', 'Villarrica', 85
 union select '016', 'Viña del Mar', 86
 union select '036', 'Chimbarongo - San Fernando', 87
 ;
+
+/* Magallanes no sigue el horario de verano del resto del país */
+update zonas_acceso set zona_horaria = 'America/Punta_Arenas' where cod_zona in ('035', '072');
+update zonas_acceso set zona_horaria = 'America/Coyhaique' where cod_zona = '045';
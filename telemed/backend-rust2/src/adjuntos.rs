@@ -0,0 +1,284 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::TcpStream,
+};
+
+use chrono::NaiveDateTime;
+
+use crate::{
+    almacen::{BlobStore, Credenciales, Local, S3},
+    config::Config,
+    enlaces::Enlaces,
+    error::AppError,
+};
+
+/// Tamaño máximo de un archivo subido.
+pub const TAMANO_MAXIMO: usize = 20 * 1024 * 1024;
+pub const LARGO_MAXIMO_NOMBRE: usize = 200;
+
+pub const MIME_PDF: &str = "application/pdf";
+pub const MIME_JPEG: &str = "image/jpeg";
+pub const MIME_PNG: &str = "image/png";
+pub const MIME_DICOM: &str = "application/dicom";
+pub const TIPOS_PERMITIDOS: [&str; 4] = [MIME_PDF, MIME_JPEG, MIME_PNG, MIME_DICOM];
+
+/// Tipo real del contenido según sus primeros bytes, si es uno de los
+/// permitidos. Los DICOM traen un preámbulo de 128 bytes antes de `DICM`.
+pub fn detectar_tipo(datos: &[u8]) -> Option<&'static str> {
+    if datos.starts_with(b"%PDF-") {
+        Some(MIME_PDF)
+    } else if datos.starts_with(&[0xFF, 0xD8, 0xFF]) {
+        Some(MIME_JPEG)
+    } else if datos.starts_with(b"\x89PNG\r\n\x1a\n") {
+        Some(MIME_PNG)
+    } else if datos.get(128..132) == Some(b"DICM") {
+        Some(MIME_DICOM)
+    } else {
+        None
+    }
+}
+
+/// Tipo del archivo. Se decide por el contenido; el declarado por el
+/// navegador sólo se contrasta, salvo el genérico `application/octet-stream`.
+pub fn validar_tipo(declarado: Option<&str>, datos: &[u8]) -> Result<&'static str, AppError> {
+    let tipo = detectar_tipo(datos).ok_or_else(|| {
+        AppError::Validation(format!("Tipo de archivo no permitido; se aceptan {}", TIPOS_PERMITIDOS.join(", ")))
+    })?;
+    if let Some(declarado) = declarado.map(|d| d.split(';').next().unwrap_or_default().trim().to_lowercase())
+        && declarado != "application/octet-stream"
+        && declarado != tipo
+    {
+        return Err(AppError::Validation(format!("El archivo declara {} pero su contenido es {}", declarado, tipo)));
+    }
+    Ok(tipo)
+}
+
+/// Nombre de archivo sin rutas ni caracteres de control.
+pub fn limpiar_nombre(nombre: Option<&str>) -> String {
+    let nombre: String = nombre
+        .unwrap_or_default()
+        .rsplit(['/', '\\'])
+        .next()
+        .unwrap_or_default()
+        .chars()
+        .filter(|c| !c.is_control())
+        .take(LARGO_MAXIMO_NOMBRE)
+        .collect();
+    match nombre.trim() {
+        "" => "archivo".into(),
+        n => n.into(),
+    }
+}
+
+/// Enlace de descarga de un adjunto, para entregarlo sin exponer el
+/// almacenamiento.
+pub fn enlace_descarga(enlaces: &Enlaces, id_adjunto: u32, expira: NaiveDateTime) -> String {
+    enlaces.firmar(&format!("/api/adjuntos/{}/contenido", id_adjunto), &format!("adjunto:{}", id_adjunto), expira)
+}
+
+pub fn verificar_descarga(enlaces: &Enlaces, id_adjunto: u32, expira: i64, firma: &str, ahora: NaiveDateTime) -> Result<(), AppError> {
+    enlaces.comprobar(&format!("adjunto:{}", id_adjunto), expira, firma, ahora)
+}
+
+/// Resultado del análisis antivirus.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Veredicto {
+    Limpio,
+    Infectado(String),
+}
+
+/// Punto de extensión para analizar los archivos antes de guardarlos
+/// (ClamAV, un servicio externo…). Un error rechaza la subida.
+#[async_trait::async_trait]
+pub trait Antivirus: Send + Sync {
+    async fn analizar(&self, datos: &[u8]) -> Result<Veredicto, AppError>;
+}
+
+/// No analiza: acepta todo. Es el que se usa si no se configura otro.
+pub struct SinAnalisis;
+
+#[async_trait::async_trait]
+impl Antivirus for SinAnalisis {
+    async fn analizar(&self, _datos: &[u8]) -> Result<Veredicto, AppError> {
+        Ok(Veredicto::Limpio)
+    }
+}
+
+/// Analiza con `clamd` por TCP usando el comando INSTREAM.
+pub struct Clamd {
+    direccion: String,
+    espera: Duration,
+}
+
+/// Trozos en que se envía el contenido; clamd acepta hasta StreamMaxLength.
+const TROZO_CLAMD: usize = 64 * 1024;
+/// Tope de la conexión, el envío y el análisis de un archivo. Un clamd
+/// colgado no puede retener la subida indefinidamente.
+const ESPERA_CLAMD: Duration = Duration::from_secs(60);
+
+impl Clamd {
+    pub fn new(direccion: &str) -> Self {
+        Self { direccion: direccion.into(), espera: ESPERA_CLAMD }
+    }
+
+    async fn consultar(&self, datos: &[u8]) -> std::io::Result<String> {
+        let mut conexion = TcpStream::connect(&self.direccion).await?;
+        conexion.write_all(b"zINSTREAM\0").await?;
+        for trozo in datos.chunks(TROZO_CLAMD) {
+            conexion.write_all(&(trozo.len() as u32).to_be_bytes()).await?;
+            conexion.write_all(trozo).await?;
+        }
+        conexion.write_all(&0u32.to_be_bytes()).await?;
+        let mut respuesta = Vec::new();
+        conexion.read_to_end(&mut respuesta).await?;
+        Ok(String::from_utf8_lossy(&respuesta).trim_end_matches(['\0', '\n']).to_string())
+    }
+}
+
+#[async_trait::async_trait]
+impl Antivirus for Clamd {
+    async fn analizar(&self, datos: &[u8]) -> Result<Veredicto, AppError> {
+        // Sin respuesta a tiempo el archivo se rechaza, igual que si clamd fallara
+        let respuesta = tokio::time::timeout(self.espera, self.consultar(datos))
+            .await
+            .map_err(|_| AppError::Internal(format!("clamd no respondió en {} s", self.espera.as_secs())))?
+            .map_err(|e| AppError::Internal(format!("clamd: {}", e)))?;
+        // "stream: OK" o "stream: <firma> FOUND"
+        match respuesta.strip_prefix("stream: ") {
+            Some("OK") => Ok(Veredicto::Limpio),
+            Some(r) if r.ends_with(" FOUND") => Ok(Veredicto::Infectado(r.trim_end_matches(" FOUND").into())),
+            _ => Err(AppError::Internal(format!("clamd respondió: {}", respuesta))),
+        }
+    }
+}
+
+/// Dónde se guardan los archivos y con qué se analizan.
+#[derive(Clone)]
+pub struct Adjuntos {
+    pub almacen: Arc<dyn BlobStore>,
+    pub antivirus: Arc<dyn Antivirus>,
+}
+
+impl Default for Adjuntos {
+    fn default() -> Self {
+        Self { almacen: Arc::new(Local::new("adjuntos")), antivirus: Arc::new(SinAnalisis) }
+    }
+}
+
+impl Adjuntos {
+    /// `ADJUNTOS_ALMACEN` es `local` (en `ADJUNTOS_DIR`) o `s3`. Con
+    /// `CLAMD_ADDRESS` los archivos se analizan con ClamAV.
+    pub fn desde_config(config: &Config) -> Result<Self, AppError> {
+        let requerida = |valor: &Option<String>, nombre: &str| {
+            valor.clone().ok_or_else(|| AppError::Config(format!("{} no configurada", nombre)))
+        };
+        let almacen: Arc<dyn BlobStore> = match config.adjuntos_almacen.as_str() {
+            "local" => Arc::new(Local::new(&config.adjuntos_dir)),
+            "s3" => Arc::new(S3::new(
+                &requerida(&config.s3_url, "S3_URL")?,
+                &requerida(&config.s3_bucket, "S3_BUCKET")?,
+                Credenciales {
+                    region: config.s3_region.clone(),
+                    acceso: requerida(&config.s3_acceso, "S3_ACCESS_KEY")?,
+                    secreto: requerida(&config.s3_secreto, "S3_SECRET_KEY")?,
+                },
+            )?),
+            otro => return Err(AppError::Config(format!("ADJUNTOS_ALMACEN desconocido: {}", otro))),
+        };
+        let antivirus: Arc<dyn Antivirus> = match &config.clamd_address {
+            Some(direccion) => Arc::new(Clamd::new(direccion)),
+            None => Arc::new(SinAnalisis),
+        };
+        Ok(Self { almacen, antivirus })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn detecta_por_contenido() {
+        let mut dicom = vec![0u8; 128];
+        dicom.extend_from_slice(b"DICM\x02\x00");
+        assert_eq!(detectar_tipo(b"%PDF-1.7\n"), Some(MIME_PDF));
+        assert_eq!(detectar_tipo(&[0xFF, 0xD8, 0xFF, 0xE0]), Some(MIME_JPEG));
+        assert_eq!(detectar_tipo(b"\x89PNG\r\n\x1a\n...."), Some(MIME_PNG));
+        assert_eq!(detectar_tipo(&dicom), Some(MIME_DICOM));
+        assert_eq!(detectar_tipo(b"MZ\x90\x00"), None);
+
+        assert_eq!(validar_tipo(Some("application/pdf; charset=binary"), b"%PDF-1.4").unwrap(), MIME_PDF);
+        assert_eq!(validar_tipo(Some("application/octet-stream"), b"%PDF-1.4").unwrap(), MIME_PDF);
+        assert!(validar_tipo(Some("image/png"), b"%PDF-1.4").is_err());
+        assert!(validar_tipo(None, b"<html>").is_err());
+    }
+
+    #[test]
+    fn limpia_nombres() {
+        assert_eq!(limpiar_nombre(Some("C:\\Users\\ana\\hemograma.pdf")), "hemograma.pdf");
+        assert_eq!(limpiar_nombre(Some("../../etc/passwd")), "passwd");
+        assert_eq!(limpiar_nombre(Some("rx\ttórax.png")), "rxtórax.png");
+        assert_eq!(limpiar_nombre(Some("  ")), "archivo");
+        assert_eq!(limpiar_nombre(None), "archivo");
+    }
+
+    #[actix_web::test]
+    async fn consulta_clamd() {
+        // Imita clamd: lee el INSTREAM completo y responde según el contenido
+        let servidor = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let direccion = servidor.local_addr().unwrap().to_string();
+        actix_web::rt::spawn(async move {
+            loop {
+                let (mut conexion, _) = servidor.accept().await.unwrap();
+                let mut comando = [0u8; 10];
+                conexion.read_exact(&mut comando).await.unwrap();
+                assert_eq!(&comando, b"zINSTREAM\0");
+                let mut datos = Vec::new();
+                loop {
+                    let largo = conexion.read_u32().await.unwrap() as usize;
+                    if largo == 0 {
+                        break;
+                    }
+                    let mut trozo = vec![0u8; largo];
+                    conexion.read_exact(&mut trozo).await.unwrap();
+                    datos.extend(trozo);
+                }
+                let respuesta: &[u8] = if datos.windows(5).any(|v| v == b"EICAR") {
+                    b"stream: Eicar-Test-Signature FOUND\0"
+                } else {
+                    b"stream: OK\0"
+                };
+                conexion.write_all(respuesta).await.unwrap();
+            }
+        });
+
+        let clamd = Clamd::new(&direccion);
+        let grande = vec![b'x'; TROZO_CLAMD * 2 + 10];
+        assert_eq!(clamd.analizar(&grande).await.unwrap(), Veredicto::Limpio);
+        assert_eq!(
+            clamd.analizar(b"X5O!P%@AP EICAR").await.unwrap(),
+            Veredicto::Infectado("Eicar-Test-Signature".into()),
+        );
+        assert!(Clamd::new("127.0.0.1:1").analizar(b"x").await.is_err());
+    }
+
+    #[actix_web::test]
+    async fn clamd_colgado_rechaza() {
+        // Acepta la conexión y nunca responde
+        let servidor = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let direccion = servidor.local_addr().unwrap().to_string();
+        actix_web::rt::spawn(async move {
+            let (_conexion, _) = servidor.accept().await.unwrap();
+            std::future::pending::<()>().await;
+        });
+
+        let clamd = Clamd { direccion, espera: Duration::from_millis(200) };
+        match clamd.analizar(b"%PDF-1.4").await {
+            Err(AppError::Internal(e)) => assert!(e.contains("no respondió"), "{}", e),
+            otro => panic!("{:?}", otro),
+        }
+    }
+}
@@ -0,0 +1,260 @@
+use chrono::{Datelike, Days, Duration, NaiveDateTime};
+
+use crate::{
+    error::AppError,
+    feriados::Calendario,
+    models::{BloqueDisponibilidad, Cita, Slot},
+    tiempo,
+};
+
+/// Genera los horarios de los bloques que comienzan entre `desde` y `hasta`
+/// (UTC, `hasta` excluido). Cada bloque se expande en su propia zona horaria
+/// y se omiten los feriados que aplican a su zona.
+pub fn generar_slots(
+    bloques: &[BloqueDisponibilidad],
+    calendario: &Calendario,
+    desde: NaiveDateTime,
+    hasta: NaiveDateTime,
+) -> Vec<Slot> {
+    let mut slots = Vec::new();
+
+    for bloque in bloques {
+        let tz = bloque.tz();
+        let duracion = Duration::minutes(i64::from(bloque.duracion_min));
+        let primer_dia = tiempo::a_local(desde, tz).date();
+        let ultimo_dia = tiempo::a_local(hasta, tz).date();
+
+        for fecha in primer_dia.iter_days().take_while(|f| *f <= ultimo_dia) {
+            if fecha.weekday().number_from_monday() as u8 != bloque.dia_semana
+                || !bloque.vigente_en(fecha)
+                || calendario.es_feriado(fecha, bloque.cod_zona.as_deref())
+            {
+                continue;
+            }
+
+            let fin_bloque = fecha.and_time(bloque.hora_fin);
+            let mut local = fecha.and_time(bloque.hora_inicio);
+            while local + duracion <= fin_bloque {
+                if let Some(inicio) = tiempo::a_utc(local, tz)
+                    && inicio >= desde
+                    && inicio < hasta
+                {
+                    slots.push(Slot {
+                        id_prof: bloque.id_prof,
+                        id_bloque: bloque.id,
+                        inicio,
+                        fin: inicio + duracion,
+                        modalidad: bloque.modalidad.clone(),
+                        cod_zona: bloque.cod_zona.clone(),
+                        zona_horaria: bloque.zona_horaria.clone(),
+                    });
+                }
+                local += duracion;
+            }
+        }
+    }
+
+    slots.sort_by_key(|s| (s.inicio, s.id_prof));
+    slots
+}
+
+/// Busca el horario del profesional que comienza exactamente en `inicio` (UTC).
+pub fn buscar_slot<'a>(
+    bloques: &'a [BloqueDisponibilidad],
+    calendario: &Calendario,
+    inicio: NaiveDateTime,
+    modalidad: &str,
+) -> Option<(Slot, &'a BloqueDisponibilidad)> {
+    generar_slots(bloques, calendario, inicio, inicio + Duration::seconds(1))
+        .into_iter()
+        .find(|s| s.inicio == inicio && s.modalidad == modalidad)
+        .and_then(|s| bloques.iter().find(|b| b.id == s.id_bloque).map(|b| (s, b)))
+}
+
+/// Verifica que el horario pueda reservarse. Retorna `true` si la reserva
+/// ocupa un sobrecupo del bloque.
+pub fn validar_reserva(
+    bloque: &BloqueDisponibilidad,
+    slot: &Slot,
+    citas_profesional: &[Cita],
+    citas_paciente: &[Cita],
+    sobrecupo: bool,
+) -> Result<bool, AppError> {
+    if citas_paciente.iter().any(|c| c.activa() && c.se_traslapa(slot.inicio, slot.fin)) {
+        return Err(AppError::Conflict("El paciente ya tiene una cita en ese horario".into()));
+    }
+
+    let ocupados = citas_profesional
+        .iter()
+        .filter(|c| c.activa() && c.se_traslapa(slot.inicio, slot.fin))
+        .count();
+
+    match ocupados {
+        0 => Ok(false),
+        _ if !sobrecupo => Err(AppError::Conflict("El horario ya está ocupado".into())),
+        n if n <= usize::from(bloque.sobrecupos) => Ok(true),
+        _ => Err(AppError::Conflict("No quedan sobrecupos en ese horario".into())),
+    }
+}
+
+/// Si dos bloques semanales ocupan en algún momento el mismo instante. Se
+/// comparan en UTC porque cada uno tiene sus horas en su propia zona
+/// horaria: 09:00 en Punta Arenas es 08:00 en Santiago durante el invierno.
+/// Basta revisar un año del período en que ambos rigen, ya que los cambios
+/// de hora se repiten cada año.
+pub fn bloques_traslapados(a: &BloqueDisponibilidad, b: &BloqueDisponibilidad) -> bool {
+    let desde = a.vigencia_desde.max(b.vigencia_desde);
+    let hasta = [a.vigencia_hasta, b.vigencia_hasta, desde.checked_add_days(Days::new(366))]
+        .into_iter()
+        .flatten()
+        .min()
+        .unwrap_or(desde);
+    if hasta < desde {
+        return false;
+    }
+
+    // Un día más por lado: el desfase entre zonas puede cambiar la fecha
+    let intervalos = |bloque: &BloqueDisponibilidad| -> Vec<(NaiveDateTime, NaiveDateTime)> {
+        let tz = bloque.tz();
+        desde
+            .pred_opt()
+            .unwrap_or(desde)
+            .iter_days()
+            .take_while(|f| *f <= hasta.succ_opt().unwrap_or(hasta))
+            .filter(|f| f.weekday().number_from_monday() as u8 == bloque.dia_semana && bloque.vigente_en(*f))
+            .filter_map(|f| Some((tiempo::a_utc(f.and_time(bloque.hora_inicio), tz)?, tiempo::a_utc(f.and_time(bloque.hora_fin), tz)?)))
+            .collect()
+    };
+    let de_b = intervalos(b);
+    intervalos(a)
+        .iter()
+        .any(|(inicio, fin)| de_b.iter().any(|(otro_inicio, otro_fin)| inicio < otro_fin && otro_inicio < fin))
+}
+
+/// Primeros `limite` horarios libres a partir de `desde`.
+pub fn proximos_libres(slots: Vec<Slot>, citas: &[Cita], desde: NaiveDateTime, limite: usize) -> Vec<Slot> {
+    slots
+        .into_iter()
+        .filter(|s| s.inicio >= desde)
+        .filter(|s| {
+            !citas
+                .iter()
+                .any(|c| c.id_prof == s.id_prof && c.activa() && c.se_traslapa(s.inicio, s.fin))
+        })
+        .take(limite)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{NaiveDate, NaiveTime};
+    use super::*;
+    use crate::models::CITA_AGENDADA;
+
+    fn calendario() -> Calendario {
+        Calendario::new(NaiveDate::from_ymd_opt(2025, 1, 1).unwrap(), NaiveDate::from_ymd_opt(2025, 12, 31).unwrap(), &[])
+    }
+
+    fn utc(fecha: NaiveDate, h: u32, m: u32) -> NaiveDateTime {
+        fecha.and_hms_opt(h, m, 0).unwrap()
+    }
+
+    fn bloque() -> BloqueDisponibilidad {
+        BloqueDisponibilidad {
+            id: 1,
+            id_prof: 7,
+            dia_semana: 1,
+            hora_inicio: NaiveTime::from_hms_opt(9, 0, 0).unwrap(),
+            hora_fin: NaiveTime::from_hms_opt(10, 0, 0).unwrap(),
+            duracion_min: 20,
+            modalidad: "TELECONSULTA".into(),
+            cod_zona: None,
+            zona_horaria: "America/Santiago".into(),
+            sobrecupos: 1,
+            vigencia_desde: NaiveDate::from_ymd_opt(2025, 1, 1).unwrap(),
+            vigencia_hasta: None,
+        }
+    }
+
+    fn cita(id_paciente: u32, slot: &Slot) -> Cita {
+        Cita {
+            id: 0,
+            id_prof: slot.id_prof,
+            id_paciente,
+            id_bloque: slot.id_bloque,
+            inicio: slot.inicio,
+            fin: slot.fin,
+            modalidad: slot.modalidad.clone(),
+            cod_zona: None,
+            estado: CITA_AGENDADA.into(),
+            sobrecupo: false,
+            motivo_cancelacion: None,
+            id_cita_original: None,
+        }
+    }
+
+    #[test]
+    fn genera_slots_solo_el_dia_del_bloque() {
+        // 2025-03-03 es lunes; Santiago está en UTC-3 (horario de verano)
+        let lunes = NaiveDate::from_ymd_opt(2025, 3, 3).unwrap();
+        let slots = generar_slots(&[bloque()], &calendario(), utc(lunes, 0, 0), utc(lunes, 0, 0) + Duration::days(7));
+        assert_eq!(slots.len(), 3);
+        assert_eq!(slots[0].inicio, utc(lunes, 12, 0));
+        assert_eq!(slots[2].fin, utc(lunes, 13, 0));
+    }
+
+    #[test]
+    fn cambio_de_hora_y_feriados() {
+        // 2025-04-07 es lunes y Santiago ya volvió a UTC-4
+        let lunes = NaiveDate::from_ymd_opt(2025, 4, 7).unwrap();
+        let slots = generar_slots(&[bloque()], &calendario(), utc(lunes, 0, 0), utc(lunes, 0, 0) + Duration::days(1));
+        assert_eq!(slots[0].inicio, utc(lunes, 13, 0));
+
+        // 2025-12-08 (lunes) es feriado nacional
+        let feriado = NaiveDate::from_ymd_opt(2025, 12, 8).unwrap();
+        let slots = generar_slots(&[bloque()], &calendario(), utc(feriado, 0, 0), utc(feriado, 0, 0) + Duration::days(1));
+        assert!(slots.is_empty());
+    }
+
+    #[test]
+    fn sobrecupo_respeta_el_maximo_del_bloque() {
+        let b = bloque();
+        let inicio = utc(NaiveDate::from_ymd_opt(2025, 3, 3).unwrap(), 12, 20);
+        let (slot, _) = buscar_slot(std::slice::from_ref(&b), &calendario(), inicio, "TELECONSULTA").unwrap();
+
+        let mut citas = vec![cita(1, &slot)];
+        assert!(validar_reserva(&b, &slot, &citas, &[], false).is_err());
+        assert!(validar_reserva(&b, &slot, &citas, &[], true).unwrap());
+
+        citas.push(cita(2, &slot));
+        assert!(validar_reserva(&b, &slot, &citas, &[], true).is_err());
+    }
+
+    #[test]
+    fn traslape_de_bloques_en_distintas_zonas() {
+        let hora = |h| NaiveTime::from_hms_opt(h, 0, 0).unwrap();
+        let santiago = BloqueDisponibilidad { hora_inicio: hora(8), hora_fin: hora(9), ..bloque() };
+        let punta_arenas = BloqueDisponibilidad { id: 2, zona_horaria: "America/Punta_Arenas".into(), ..bloque() };
+        // En invierno 08:00 de Santiago y 09:00 de Punta Arenas son 12:00 UTC
+        assert!(bloques_traslapados(&santiago, &punta_arenas));
+        // Si sólo rigen en verano, ambas zonas están en UTC-3
+        let verano = |b: &BloqueDisponibilidad| BloqueDisponibilidad { vigencia_hasta: NaiveDate::from_ymd_opt(2025, 3, 31), ..b.clone() };
+        assert!(!bloques_traslapados(&verano(&santiago), &verano(&punta_arenas)));
+
+        // Las 22:00 del domingo en Rapa Nui son la medianoche del lunes en Santiago
+        let domingo = BloqueDisponibilidad { dia_semana: 7, hora_inicio: hora(22), hora_fin: hora(23), ..bloque() };
+        let pascua = BloqueDisponibilidad { zona_horaria: "Pacific/Easter".into(), ..domingo.clone() };
+        let madrugada = BloqueDisponibilidad { dia_semana: 1, hora_inicio: hora(0), hora_fin: hora(1), ..bloque() };
+        assert!(bloques_traslapados(&pascua, &madrugada));
+        assert!(!bloques_traslapados(&domingo, &madrugada));
+    }
+
+    #[test]
+    fn proximos_libres_omite_ocupados() {
+        let lunes = NaiveDate::from_ymd_opt(2025, 3, 3).unwrap();
+        let slots = generar_slots(&[bloque()], &calendario(), utc(lunes, 0, 0), utc(lunes, 0, 0) + Duration::days(1));
+        let citas = vec![cita(1, &slots[0])];
+        let libres = proximos_libres(slots.clone(), &citas, utc(lunes, 0, 0), 10);
+        assert_eq!(libres, slots[1..].to_vec());
+    }
+}
@@ -0,0 +1,337 @@
+use actix_multipart::{Field, Multipart};
+use actix_web::{http::header::ContentDisposition, web, HttpResponse};
+use chrono::{Duration, Timelike};
+use futures_util::TryStreamExt;
+use sha2::{Digest, Sha256};
+use crate::{
+    adjuntos::{self, Adjuntos, Veredicto, TAMANO_MAXIMO},
+    models::{Adjunto, AdjuntoFiltro, DescargaQuery, EnlaceDescarga, EnlaceQuery},
+    tiempo,
+    app_state::AppState,
+    error::AppError
+};
+use super::super::repositories::{AdjuntoRepository, AtencionRepository, PacienteRepository, ProfesionalRepository};
+
+/// Vigencia de los enlaces de descarga, en minutos.
+const VIGENCIA_ENLACE: u32 = 15;
+const MAX_VIGENCIA_ENLACE: u32 = 24 * 60;
+/// Largo máximo de los campos de texto del formulario.
+const LARGO_CAMPO: usize = 32;
+
+/// Archivo recibido en un formulario, ya leído completo.
+pub(super) struct Archivo {
+    pub nombre: Option<String>,
+    pub tipo_declarado: Option<String>,
+    pub datos: Vec<u8>,
+    pub sha256: String,
+}
+
+/// Campos del formulario de subida.
+#[derive(Default)]
+struct Subida {
+    id_paciente: Option<u32>,
+    id_atencion: Option<u32>,
+    id_prof: Option<u32>,
+    archivo: Option<Archivo>,
+}
+
+pub(super) fn formulario(e: impl std::fmt::Display) -> AppError {
+    AppError::Validation(format!("Formulario inválido: {}", e))
+}
+
+/// Texto de un campo, cortando apenas supera `largo` bytes.
+pub(super) async fn leer_campo(campo: &mut Field, nombre: &str, largo: usize) -> Result<String, AppError> {
+    let mut texto = Vec::new();
+    while let Some(trozo) = campo.try_next().await.map_err(formulario)? {
+        texto.extend_from_slice(&trozo);
+        if texto.len() > largo {
+            return Err(AppError::Validation(format!("{} demasiado largo", nombre)));
+        }
+    }
+    Ok(String::from_utf8_lossy(&texto).trim().to_string())
+}
+
+async fn leer_texto(campo: &mut Field, nombre: &str) -> Result<u32, AppError> {
+    leer_campo(campo, nombre, LARGO_CAMPO)
+        .await?
+        .parse()
+        .map_err(|_| AppError::Validation(format!("{} debe ser numérico", nombre)))
+}
+
+/// Lee el archivo a medida que llega, calculando el hash y cortando apenas
+/// supera el tamaño máximo.
+pub(super) async fn leer_archivo(campo: &mut Field) -> Result<Archivo, AppError> {
+    let nombre = campo.content_disposition().and_then(|c| c.get_filename()).map(str::to_string);
+    let tipo_declarado = campo.content_type().map(|m| m.essence_str().to_string());
+    let mut datos = Vec::new();
+    let mut hash = Sha256::new();
+    while let Some(trozo) = campo.try_next().await.map_err(formulario)? {
+        if datos.len() + trozo.len() > TAMANO_MAXIMO {
+            return Err(AppError::Validation(format!("El archivo supera {} MB", TAMANO_MAXIMO / (1024 * 1024))));
+        }
+        hash.update(&trozo);
+        datos.extend_from_slice(&trozo);
+    }
+    Ok(Archivo { nombre, tipo_declarado, datos, sha256: hex::encode(hash.finalize()) })
+}
+
+async fn leer_formulario(mut multipart: Multipart) -> Result<Subida, AppError> {
+    let mut subida = Subida::default();
+    while let Some(mut campo) = multipart.try_next().await.map_err(formulario)? {
+        match campo.name().unwrap_or_default() {
+            "id_paciente" => subida.id_paciente = Some(leer_texto(&mut campo, "id_paciente").await?),
+            "id_atencion" => subida.id_atencion = Some(leer_texto(&mut campo, "id_atencion").await?),
+            "id_prof" => subida.id_prof = Some(leer_texto(&mut campo, "id_prof").await?),
+            "archivo" if subida.archivo.is_none() => subida.archivo = Some(leer_archivo(&mut campo).await?),
+            "archivo" => return Err(AppError::Validation("Sólo se acepta un archivo por subida".into())),
+            _ => while campo.try_next().await.map_err(formulario)?.is_some() {},
+        }
+    }
+    Ok(subida)
+}
+
+/// Analiza el archivo con el antivirus y lo deja en el almacén si su
+/// contenido no estaba.
+pub(super) async fn almacenar(adjuntos: &Adjuntos, archivo: Archivo) -> Result<(), AppError> {
+    if let Veredicto::Infectado(firma) = adjuntos.antivirus.analizar(&archivo.datos).await? {
+        return Err(AppError::Validation(format!("El archivo fue rechazado por el antivirus: {}", firma)));
+    }
+    if !adjuntos.almacen.existe(&archivo.sha256).await? {
+        adjuntos.almacen.guardar(&archivo.sha256, archivo.datos.into()).await?;
+    }
+    Ok(())
+}
+
+/// Sube un archivo (`multipart/form-data` con `archivo`, `id_paciente` y,
+/// para adjuntarlo a una atención, `id_atencion` e `id_prof`). Si el mismo
+/// contenido ya estaba subido para el paciente y la atención se retorna
+/// ese adjunto con 200 en vez de crear otro.
+pub async fn subir<R>(
+    multipart: Multipart,
+    data: web::Data<AppState<R>>,
+) -> Result<HttpResponse, AppError>
+where
+    R: AdjuntoRepository + AtencionRepository + PacienteRepository + ProfesionalRepository + 'static,
+{
+    let subida = leer_formulario(multipart).await?;
+    let Some(archivo) = subida.archivo else {
+        return Err(AppError::Validation("Falta el archivo".into()));
+    };
+    let id_paciente = subida.id_paciente.ok_or_else(|| AppError::Validation("Falta id_paciente".into()))?;
+    if archivo.datos.is_empty() {
+        return Err(AppError::Validation("El archivo está vacío".into()));
+    }
+
+    if PacienteRepository::get_by_id(data.paciente_repo.as_ref(), id_paciente).await?.is_none() {
+        return Err(AppError::Validation(format!("Paciente {} no existe", id_paciente)));
+    }
+    if let Some(id_prof) = subida.id_prof
+        && ProfesionalRepository::get_by_id(data.profesional_repo.as_ref(), id_prof).await?.is_none()
+    {
+        return Err(AppError::Validation(format!("Profesional {} no existe", id_prof)));
+    }
+    if let Some(id_atencion) = subida.id_atencion {
+        let atencion = AtencionRepository::get_by_id(data.atencion_repo.as_ref(), id_atencion).await?
+            .ok_or_else(|| AppError::Validation(format!("Atención {} no existe", id_atencion)))?
+            .atencion;
+        if atencion.id_paciente != id_paciente {
+            return Err(AppError::Validation(format!("La atención {} no es del paciente {}", id_atencion, id_paciente)));
+        }
+        if subida.id_prof != Some(atencion.id_prof) {
+            return Err(AppError::Forbidden("Sólo el profesional de la atención puede adjuntarle archivos".into()));
+        }
+    }
+
+    let tipo = adjuntos::validar_tipo(archivo.tipo_declarado.as_deref(), &archivo.datos)?;
+    if let Some(existente) = data.adjunto_repo.adjunto_por_hash(id_paciente, subida.id_atencion, &archivo.sha256).await? {
+        return Ok(HttpResponse::Ok().json(existente));
+    }
+
+    let nombre = adjuntos::limpiar_nombre(archivo.nombre.as_deref());
+    let (tamano, sha256) = (archivo.datos.len() as u32, archivo.sha256.clone());
+    almacenar(&data.adjuntos, archivo).await?;
+    let mut adjunto = Adjunto {
+        id: 0,
+        id_paciente,
+        id_atencion: subida.id_atencion,
+        id_prof: subida.id_prof,
+        nombre,
+        tipo_mime: tipo.into(),
+        tamano,
+        sha256,
+        subido_en: tiempo::ahora(),
+    };
+    adjunto.id = data.adjunto_repo.create_adjunto(&adjunto).await?;
+    Ok(HttpResponse::Created().json(adjunto))
+}
+
+pub async fn search<R>(
+    filtro: web::Query<AdjuntoFiltro>,
+    data: web::Data<AppState<R>>,
+) -> Result<HttpResponse, AppError>
+where
+    R: AdjuntoRepository + 'static,
+{
+    let adjuntos = data.adjunto_repo.adjuntos(&filtro).await?;
+    Ok(HttpResponse::Ok().json(adjuntos))
+}
+
+pub async fn get_by_id<R>(
+    id: web::Path<u32>,
+    data: web::Data<AppState<R>>,
+) -> Result<HttpResponse, AppError>
+where
+    R: AdjuntoRepository + 'static,
+{
+    let adjunto = data.adjunto_repo.get_adjunto(id.into_inner()).await?;
+    match adjunto {
+        Some(a) => Ok(HttpResponse::Ok().json(a)),
+        None => Err(AppError::NotFound),
+    }
+}
+
+/// Enlace firmado para descargar el adjunto sin sesión, válido por
+/// `minutos` (15 por defecto).
+pub async fn enlace<R>(
+    id: web::Path<u32>,
+    query: web::Query<EnlaceQuery>,
+    data: web::Data<AppState<R>>,
+) -> Result<HttpResponse, AppError>
+where
+    R: AdjuntoRepository + 'static,
+{
+    let minutos = query.minutos.unwrap_or(VIGENCIA_ENLACE);
+    if minutos == 0 || minutos > MAX_VIGENCIA_ENLACE {
+        return Err(AppError::Validation(format!("minutos debe estar entre 1 y {}", MAX_VIGENCIA_ENLACE)));
+    }
+    let adjunto = data.adjunto_repo.get_adjunto(id.into_inner()).await?.ok_or(AppError::NotFound)?;
+    // Sin fracción de segundo: el enlace lleva la expiración en segundos Unix
+    let expira = (tiempo::ahora() + Duration::minutes(i64::from(minutos))).with_nanosecond(0).unwrap_or_default();
+    Ok(HttpResponse::Ok().json(EnlaceDescarga { url: adjuntos::enlace_descarga(&data.enlaces, adjunto.id, expira), expira }))
+}
+
+/// Destino del enlace firmado: entrega el contenido como descarga.
+pub async fn contenido<R>(
+    id: web::Path<u32>,
+    query: web::Query<DescargaQuery>,
+    data: web::Data<AppState<R>>,
+) -> Result<HttpResponse, AppError>
+where
+    R: AdjuntoRepository + 'static,
+{
+    let id = id.into_inner();
+    adjuntos::verificar_descarga(&data.enlaces, id, query.expira, &query.firma, tiempo::ahora())?;
+    let adjunto = data.adjunto_repo.get_adjunto(id).await?.ok_or(AppError::NotFound)?;
+    let datos = data.adjuntos.almacen.leer(&adjunto.sha256).await?
+        .ok_or_else(|| AppError::Internal(format!("Contenido {} del adjunto {} no está en el almacén", adjunto.sha256, id)))?;
+    Ok(HttpResponse::Ok()
+        .content_type(adjunto.tipo_mime.as_str())
+        .insert_header(ContentDisposition::attachment(adjunto.nombre))
+        .insert_header(("X-Content-Type-Options", "nosniff"))
+        .insert_header(("Cache-Control", "private, no-store"))
+        .body(datos))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+    use std::sync::Arc;
+
+    use actix_web::{test, App};
+    use crate::{
+        adjuntos::{Adjuntos, Antivirus},
+        almacen::Local,
+        enlaces::Enlaces,
+        repositories::{fixtures, MockRepository},
+    };
+    use super::*;
+
+    /// Rechaza lo que contenga la firma de prueba EICAR.
+    struct Eicar;
+
+    #[async_trait::async_trait]
+    impl Antivirus for Eicar {
+        async fn analizar(&self, datos: &[u8]) -> Result<Veredicto, AppError> {
+            Ok(if datos.windows(5).any(|v| v == b"EICAR") {
+                Veredicto::Infectado("Eicar-Test-Signature".into())
+            } else {
+                Veredicto::Limpio
+            })
+        }
+    }
+
+    async fn app_state(dir: &PathBuf) -> AppState<MockRepository> {
+        let adjuntos = Adjuntos { almacen: Arc::new(Local::new(dir)), antivirus: Arc::new(Eicar) };
+        fixtures::app_state(Vec::new(), vec![fixtures::paciente("11.111.111-1")])
+            .await
+            .with_adjuntos(adjuntos)
+            .with_enlaces(Enlaces::new(Some("secreto"), "http://portal"))
+    }
+
+    fn formulario(id_paciente: &str, nombre: &str, tipo: &str, datos: &[u8]) -> (String, Vec<u8>) {
+        let limite = "----limite";
+        let mut cuerpo = format!(
+            "--{0}\r\nContent-Disposition: form-data; name=\"id_paciente\"\r\n\r\n{1}\r\n\
+             --{0}\r\nContent-Disposition: form-data; name=\"archivo\"; filename=\"{2}\"\r\nContent-Type: {3}\r\n\r\n",
+            limite, id_paciente, nombre, tipo,
+        ).into_bytes();
+        cuerpo.extend_from_slice(datos);
+        cuerpo.extend_from_slice(format!("\r\n--{}--\r\n", limite).as_bytes());
+        (format!("multipart/form-data; boundary={}", limite), cuerpo)
+    }
+
+    #[actix_web::test]
+    async fn sube_deduplica_y_descarga() {
+        let dir = std::env::temp_dir().join(format!("telemed-adjuntos-{}", rand::random::<u64>()));
+        let data = app_state(&dir).await;
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(data.clone()))
+                .route("/api/adjuntos", web::post().to(subir::<MockRepository>))
+                .route("/api/adjuntos/{id}/enlace", web::get().to(enlace::<MockRepository>))
+                .route("/api/adjuntos/{id}/contenido", web::get().to(contenido::<MockRepository>))
+        ).await;
+        let subir = |id_paciente: &str, tipo: &str, datos: &[u8]| {
+            let (content_type, cuerpo) = formulario(id_paciente, "examenes/hemograma.pdf", tipo, datos);
+            test::TestRequest::post()
+                .uri("/api/adjuntos")
+                .insert_header(("content-type", content_type))
+                .set_payload(cuerpo)
+                .to_request()
+        };
+
+        let pdf = b"%PDF-1.7\nhemograma";
+        let resp = test::call_service(&app, subir("1", "application/pdf", pdf)).await;
+        assert_eq!(resp.status(), 201);
+        let adjunto: Adjunto = test::read_body_json(resp).await;
+        assert_eq!((adjunto.nombre.as_str(), adjunto.tipo_mime.as_str(), adjunto.tamano), ("hemograma.pdf", "application/pdf", 18));
+
+        let resp = test::call_service(&app, subir("1", "application/pdf", pdf)).await;
+        assert_eq!(resp.status(), 200);
+        let repetido: Adjunto = test::read_body_json(resp).await;
+        assert_eq!(repetido.id, adjunto.id);
+
+        for (id_paciente, tipo, datos) in [
+            ("1", "image/png", &pdf[..]),
+            ("1", "application/pdf", b"MZ ejecutable"),
+            ("1", "application/pdf", b"%PDF-1.7 EICAR"),
+            ("9", "application/pdf", &pdf[..]),
+        ] {
+            let resp = test::call_service(&app, subir(id_paciente, tipo, datos)).await;
+            assert_eq!(resp.status(), 400);
+        }
+
+        let req = test::TestRequest::get().uri(&format!("/api/adjuntos/{}/enlace", adjunto.id)).to_request();
+        let enlace: EnlaceDescarga = test::call_and_read_body_json(&app, req).await;
+        let ruta = enlace.url.replace("http://portal", "");
+        let resp = test::call_service(&app, test::TestRequest::get().uri(&ruta).to_request()).await;
+        assert_eq!(resp.status(), 200);
+        assert_eq!(resp.headers().get("content-type").unwrap(), "application/pdf");
+        assert_eq!(test::read_body(resp).await.as_ref(), pdf);
+
+        let adulterada = ruta.replace(&format!("/adjuntos/{}/", adjunto.id), "/adjuntos/2/");
+        let resp = test::call_service(&app, test::TestRequest::get().uri(&adulterada).to_request()).await;
+        assert_eq!(resp.status(), 403);
+        let _ = std::fs::remove_dir_all(dir);
+    }
+}
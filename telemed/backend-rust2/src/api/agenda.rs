@@ -0,0 +1,463 @@
+use actix_web::{web, HttpResponse};
+use chrono::{Duration, NaiveDateTime};
+use serde::Deserialize;
+use crate::{
+    agenda, contratos, credenciales,
+    feriados::Calendario,
+    tiempo,
+    models::{
+        BloqueDisponibilidad, BloqueInput, Cita, CitaInput, CitaFiltro, CancelacionInput,
+        ReprogramacionInput, ContratoFiltro, CredencialFiltro, DisponibilidadFiltro, Slot, CITA_AGENDADA, CITA_CANCELADA,
+    },
+    app_state::AppState,
+    error::AppError
+};
+use super::super::repositories::{
+    AgendaRepository, ContratoRepository, CredencialRepository, FeriadoRepository, PacienteRepository, ProfesionalRepository,
+    ZonaRepository,
+};
+
+const DIAS_BUSQUEDA: u32 = 14;
+const MAX_DIAS_BUSQUEDA: u32 = 60;
+const LIMITE_SLOTS: usize = 10;
+
+#[derive(Debug, Deserialize)]
+pub struct BloquesQuery {
+    pub id_prof: u32,
+}
+
+/// Calendario de feriados legales y administrados que cubre el rango UTC
+/// (con un día de holgura para las zonas horarias).
+async fn calendario<R>(repo: &R, desde: NaiveDateTime, hasta: NaiveDateTime) -> Result<Calendario, AppError>
+where
+    R: FeriadoRepository,
+{
+    let desde = desde.date() - Duration::days(1);
+    let hasta = hasta.date() + Duration::days(1);
+    let extras = repo.search(desde, hasta).await?;
+    Ok(Calendario::new(desde, hasta, &extras))
+}
+
+/// Valida que `inicio` corresponda a un horario del profesional, que éste
+/// tenga contrato vigente y credenciales al día ese día, y que pueda
+/// reservarse. `excluir` permite
+/// ignorar la cita que se está reprogramando. Junto al horario retorna si
+/// es sobrecupo y cuántas citas del profesional pueden traslaparse con la
+/// nueva, para que `reservar_cita` lo vuelva a comprobar al insertar.
+async fn validar_horario<R>(
+    repo: &R,
+    id_prof: u32,
+    id_paciente: u32,
+    inicio: NaiveDateTime,
+    modalidad: &str,
+    sobrecupo: bool,
+    excluir: Option<u32>,
+) -> Result<(Slot, bool, usize), AppError>
+where
+    R: AgendaRepository + FeriadoRepository + ContratoRepository + CredencialRepository,
+{
+    let bloques = repo.bloques_profesional(id_prof).await?;
+    let calendario = calendario(repo, inicio, inicio).await?;
+    let (slot, bloque) = agenda::buscar_slot(&bloques, &calendario, inicio, modalidad)
+        .ok_or_else(|| AppError::Validation("El profesional no atiende en ese horario".into()))?;
+    let fecha = tiempo::a_local(slot.inicio, tiempo::zona_horaria(&slot.zona_horaria)?).date();
+    contratos::exigir(repo, id_prof, fecha).await?;
+    credenciales::exigir(repo, id_prof, fecha).await?;
+
+    let rango = |filtro: CitaFiltro| CitaFiltro {
+        desde: Some(slot.inicio),
+        hasta: Some(slot.fin),
+        ..filtro
+    };
+    let sin_excluida = |citas: Vec<Cita>| -> Vec<Cita> {
+        citas.into_iter().filter(|c| Some(c.id) != excluir).collect()
+    };
+
+    let citas_prof = sin_excluida(repo.search_citas(&rango(CitaFiltro { id_prof: Some(id_prof), ..Default::default() })).await?);
+    let citas_pac = sin_excluida(repo.search_citas(&rango(CitaFiltro { id_paciente: Some(id_paciente), ..Default::default() })).await?);
+
+    let es_sobrecupo = agenda::validar_reserva(bloque, &slot, &citas_prof, &citas_pac, sobrecupo)?;
+    let ocupados = if sobrecupo { usize::from(bloque.sobrecupos) } else { 0 };
+    Ok((slot, es_sobrecupo, ocupados))
+}
+
+pub async fn get_bloques<R>(
+    query: web::Query<BloquesQuery>,
+    data: web::Data<AppState<R>>,
+) -> Result<HttpResponse, AppError>
+where
+    R: AgendaRepository + 'static,
+{
+    let bloques = data.agenda_repo.bloques_profesional(query.id_prof).await?;
+    Ok(HttpResponse::Ok().json(bloques))
+}
+
+pub async fn create_bloque<R>(
+    bloque: web::Json<BloqueInput>,
+    data: web::Data<AppState<R>>,
+) -> Result<HttpResponse, AppError>
+where
+    R: AgendaRepository + ProfesionalRepository + ZonaRepository + ContratoRepository + CredencialRepository + 'static,
+{
+    let input = bloque.into_inner();
+    let hereda_zona_horaria = input.zona_horaria.is_none();
+    let mut bloque: BloqueDisponibilidad = input.try_into()?;
+
+    if ProfesionalRepository::get_by_id(data.profesional_repo.as_ref(), bloque.id_prof).await?.is_none() {
+        return Err(AppError::Validation(format!("Profesional {} no existe", bloque.id_prof)));
+    }
+    if let Some(cod_zona) = &bloque.cod_zona {
+        let zona = ZonaRepository::get_by_id(data.zona_repo.as_ref(), cod_zona).await?
+            .ok_or_else(|| AppError::Validation(format!("Zona {} no existe", cod_zona)))?;
+        if hereda_zona_horaria {
+            bloque.zona_horaria = zona.zona_horaria;
+        }
+    }
+    let hoy = tiempo::a_local(tiempo::ahora(), tiempo::zona_horaria(&bloque.zona_horaria)?).date();
+    let desde = bloque.vigencia_desde.max(hoy);
+    contratos::exigir(data.contrato_repo.as_ref(), bloque.id_prof, desde).await?;
+    credenciales::exigir(data.credencial_repo.as_ref(), bloque.id_prof, desde).await?;
+
+    let existentes = data.agenda_repo.bloques_profesional(bloque.id_prof).await?;
+    if existentes.iter().any(|b| agenda::bloques_traslapados(b, &bloque)) {
+        return Err(AppError::Conflict("El bloque se traslapa con otro del mismo profesional".into()));
+    }
+
+    let id = data.agenda_repo.create_bloque(&bloque).await?;
+    Ok(HttpResponse::Created().json(serde_json::json!({"id": id})))
+}
+
+pub async fn delete_bloque<R>(
+    id: web::Path<u32>,
+    data: web::Data<AppState<R>>,
+) -> Result<HttpResponse, AppError>
+where
+    R: AgendaRepository + 'static,
+{
+    let bloque = data.agenda_repo.get_bloque(id.into_inner()).await?.ok_or(AppError::NotFound)?;
+
+    let futuras = data.agenda_repo.search_citas(&CitaFiltro {
+        id_prof: Some(bloque.id_prof),
+        desde: Some(tiempo::ahora()),
+        ..Default::default()
+    }).await?;
+    if futuras.iter().any(|c| c.id_bloque == bloque.id && c.activa()) {
+        return Err(AppError::Conflict("El bloque tiene citas futuras; cancélelas o reprográmelas primero".into()));
+    }
+
+    data.agenda_repo.delete_bloque(bloque.id).await?;
+    Ok(HttpResponse::NoContent().finish())
+}
+
+pub async fn disponibilidad<R>(
+    filtro: web::Query<DisponibilidadFiltro>,
+    data: web::Data<AppState<R>>,
+) -> Result<HttpResponse, AppError>
+where
+    R: AgendaRepository + FeriadoRepository + ContratoRepository + CredencialRepository + 'static,
+{
+    let desde = filtro.desde.unwrap_or_else(tiempo::ahora).max(tiempo::ahora());
+    let dias = filtro.dias.unwrap_or(DIAS_BUSQUEDA).min(MAX_DIAS_BUSQUEDA);
+    let hasta = desde + Duration::days(i64::from(dias));
+
+    let bloques = data.agenda_repo.bloques_disponibles(&filtro).await?;
+    let calendario = calendario(data.feriado_repo.as_ref(), desde, hasta).await?;
+    let mut slots = agenda::generar_slots(&bloques, &calendario, desde, hasta);
+
+    // Sólo los días en que el profesional tiene contrato y credenciales al día
+    let contratos = data.contrato_repo.contratos(&ContratoFiltro::default()).await?;
+    let todas = data.credencial_repo.credenciales(&CredencialFiltro::default()).await?;
+    let obligatorias = credenciales::obligatorias(&todas);
+    slots.retain(|s| {
+        tiempo::zona_horaria(&s.zona_horaria).is_ok_and(|tz| {
+            let fecha = tiempo::a_local(s.inicio, tz).date();
+            contratos::vigente(&contratos, s.id_prof, fecha).is_some() && credenciales::habilitado(&obligatorias, s.id_prof, fecha)
+        })
+    });
+
+    let citas = data.agenda_repo.search_citas(&CitaFiltro {
+        desde: Some(desde),
+        hasta: Some(hasta),
+        ..Default::default()
+    }).await?;
+
+    let libres = agenda::proximos_libres(slots, &citas, desde, filtro.limite.unwrap_or(LIMITE_SLOTS));
+    Ok(HttpResponse::Ok().json(libres))
+}
+
+pub async fn get_citas<R>(
+    filtro: web::Query<CitaFiltro>,
+    data: web::Data<AppState<R>>,
+) -> Result<HttpResponse, AppError>
+where
+    R: AgendaRepository + 'static,
+{
+    let citas = data.agenda_repo.search_citas(&filtro).await?;
+    Ok(HttpResponse::Ok().json(citas))
+}
+
+pub async fn get_cita<R>(
+    id: web::Path<u32>,
+    data: web::Data<AppState<R>>,
+) -> Result<HttpResponse, AppError>
+where
+    R: AgendaRepository + 'static,
+{
+    let cita = data.agenda_repo.get_cita(id.into_inner()).await?;
+    match cita {
+        Some(c) => Ok(HttpResponse::Ok().json(c)),
+        None => Err(AppError::NotFound),
+    }
+}
+
+pub async fn reservar<R>(
+    cita: web::Json<CitaInput>,
+    data: web::Data<AppState<R>>,
+) -> Result<HttpResponse, AppError>
+where
+    R: AgendaRepository + PacienteRepository + FeriadoRepository + ContratoRepository + CredencialRepository + 'static,
+{
+    let input = cita.into_inner();
+    let modalidad = input.modalidad.trim().to_uppercase();
+
+    if input.inicio < tiempo::ahora() {
+        return Err(AppError::Validation("No se puede agendar en el pasado".into()));
+    }
+    if data.paciente_repo.get_by_id(input.id_paciente).await?.is_none() {
+        return Err(AppError::Validation(format!("Paciente {} no existe", input.id_paciente)));
+    }
+
+    let (slot, sobrecupo, ocupados) = validar_horario(
+        data.agenda_repo.as_ref(),
+        input.id_prof,
+        input.id_paciente,
+        input.inicio,
+        &modalidad,
+        input.sobrecupo,
+        None,
+    ).await?;
+
+    let mut cita = Cita {
+        id: 0,
+        id_prof: slot.id_prof,
+        id_paciente: input.id_paciente,
+        id_bloque: slot.id_bloque,
+        inicio: slot.inicio,
+        fin: slot.fin,
+        modalidad: slot.modalidad,
+        cod_zona: slot.cod_zona,
+        estado: CITA_AGENDADA.into(),
+        sobrecupo,
+        motivo_cancelacion: None,
+        id_cita_original: None,
+    };
+    cita.id = data.agenda_repo.reservar_cita(&cita, ocupados, None).await?;
+    Ok(HttpResponse::Created().json(cita))
+}
+
+pub async fn cancelar<R>(
+    id: web::Path<u32>,
+    cancelacion: web::Json<CancelacionInput>,
+    data: web::Data<AppState<R>>,
+) -> Result<HttpResponse, AppError>
+where
+    R: AgendaRepository + 'static,
+{
+    let mut cita = data.agenda_repo.get_cita(id.into_inner()).await?.ok_or(AppError::NotFound)?;
+    if cita.estado != CITA_AGENDADA {
+        return Err(AppError::Conflict(format!("No se puede cancelar una cita en estado {}", cita.estado)));
+    }
+    if cancelacion.motivo.trim().is_empty() {
+        return Err(AppError::Validation("Debe indicar el motivo de la cancelación".into()));
+    }
+
+    cita.estado = CITA_CANCELADA.into();
+    cita.motivo_cancelacion = Some(cancelacion.into_inner().motivo);
+    data.agenda_repo.update_cita(&cita).await?;
+    Ok(HttpResponse::Ok().json(cita))
+}
+
+pub async fn reprogramar<R>(
+    id: web::Path<u32>,
+    reprogramacion: web::Json<ReprogramacionInput>,
+    data: web::Data<AppState<R>>,
+) -> Result<HttpResponse, AppError>
+where
+    R: AgendaRepository + FeriadoRepository + ContratoRepository + CredencialRepository + 'static,
+{
+    let original = data.agenda_repo.get_cita(id.into_inner()).await?.ok_or(AppError::NotFound)?;
+    if original.estado != CITA_AGENDADA {
+        return Err(AppError::Conflict(format!("No se puede reprogramar una cita en estado {}", original.estado)));
+    }
+    if reprogramacion.inicio < tiempo::ahora() {
+        return Err(AppError::Validation("No se puede agendar en el pasado".into()));
+    }
+
+    let (slot, sobrecupo, ocupados) = validar_horario(
+        data.agenda_repo.as_ref(),
+        original.id_prof,
+        original.id_paciente,
+        reprogramacion.inicio,
+        &original.modalidad,
+        reprogramacion.sobrecupo,
+        Some(original.id),
+    ).await?;
+
+    let mut nueva = Cita {
+        id: 0,
+        id_bloque: slot.id_bloque,
+        inicio: slot.inicio,
+        fin: slot.fin,
+        cod_zona: slot.cod_zona,
+        sobrecupo,
+        id_cita_original: Some(original.id),
+        ..original.clone()
+    };
+    nueva.id = data.agenda_repo.reservar_cita(&nueva, ocupados, Some(original.id)).await?;
+    Ok(HttpResponse::Created().json(nueva))
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_web::{test, web, App};
+    use chrono::{Datelike, NaiveDate, NaiveTime};
+    use crate::{app_state::AppState, models::{Profesional, CITA_REPROGRAMADA, CREDENCIAL_SEGURO}, repositories::{fixtures, MockRepository}};
+    use super::*;
+
+    async fn app_state() -> AppState<MockRepository> {
+        let ingreso = NaiveDate::from_ymd_opt(2023, 5, 19).unwrap();
+        let profesional = Profesional {
+            especialidad: Some("Médico".into()),
+            fecha_ingreso: Some(ingreso),
+            zona: Some("Temuco".into()),
+            ..fixtures::profesional(1)
+        };
+        let repo = fixtures::repositorio(vec![profesional], vec![fixtures::paciente("0010895960-6")]).await;
+        ContratoRepository::create_contrato(&repo, &contratos::contratado(1, ingreso)).await.unwrap();
+        AppState::new(repo)
+    }
+
+    /// Próximo lunes hábil a partir de mañana, para que los horarios queden en el futuro.
+    fn proximo_lunes() -> NaiveDate {
+        let manana = tiempo::ahora().date() + Duration::days(1);
+        let mut lunes = manana + Duration::days(i64::from((7 - manana.weekday().num_days_from_monday()) % 7));
+        let calendario = Calendario::new(lunes, lunes + Duration::days(366), &[]);
+        while calendario.es_feriado(lunes, None) {
+            lunes += Duration::days(7);
+        }
+        lunes
+    }
+
+    #[actix_web::test]
+    async fn reserva_bloquea_el_horario_y_reprograma() {
+        let state = app_state().await;
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(state.clone()))
+                .route("/bloques", web::post().to(create_bloque::<MockRepository>))
+                .route("/disponibilidad", web::get().to(disponibilidad::<MockRepository>))
+                .route("/citas", web::post().to(reservar::<MockRepository>))
+                .route("/citas/{id}/reprogramar", web::post().to(reprogramar::<MockRepository>)),
+        )
+        .await;
+
+        let lunes = proximo_lunes();
+        let req = test::TestRequest::post()
+            .uri("/bloques")
+            .set_json(serde_json::json!({
+                "id_prof": 1,
+                "dia_semana": 1,
+                "hora_inicio": "09:00:00",
+                "hora_fin": "10:00:00",
+                "duracion_min": 30,
+                "modalidad": "teleconsulta",
+                "vigencia_desde": lunes,
+            }))
+            .to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), 201);
+
+        let santiago = tiempo::zona_horaria(tiempo::ZONA_HORARIA_DEFECTO).unwrap();
+        let nueve = tiempo::a_utc(lunes.and_time(NaiveTime::from_hms_opt(9, 0, 0).unwrap()), santiago).unwrap();
+        let nueve_rfc = nueve.and_utc().to_rfc3339();
+        let req = test::TestRequest::post()
+            .uri("/citas")
+            .set_json(serde_json::json!({
+                "id_prof": 1,
+                "id_paciente": 1,
+                "inicio": nueve_rfc,
+                "modalidad": "TELECONSULTA",
+            }))
+            .to_request();
+        let cita: Cita = test::call_and_read_body_json(&app, req).await;
+
+        let req = test::TestRequest::post()
+            .uri("/citas")
+            .set_json(serde_json::json!({
+                "id_prof": 1,
+                "id_paciente": 1,
+                "inicio": nueve_rfc,
+                "modalidad": "TELECONSULTA",
+            }))
+            .to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), 409);
+
+        let uri = format!("/disponibilidad?especialidad=M%C3%A9dico&desde={}T00:00:00Z&dias=1", lunes);
+        let req = test::TestRequest::get().uri(&uri).to_request();
+        let libres: Vec<Slot> = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(libres.len(), 1);
+        assert_eq!(libres[0].inicio, nueve + Duration::minutes(30));
+
+        let req = test::TestRequest::post()
+            .uri(&format!("/citas/{}/reprogramar", cita.id))
+            .set_json(serde_json::json!({ "inicio": libres[0].inicio.and_utc().to_rfc3339() }))
+            .to_request();
+        let nueva: Cita = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(nueva.id_cita_original, Some(cita.id));
+        let original = state.agenda_repo.get_cita(cita.id).await.unwrap().unwrap();
+        assert_eq!(original.estado, CITA_REPROGRAMADA);
+
+        // Una reserva que se validó antes de que otra tomara el horario choca al insertarse
+        let simultanea = Cita { id: 0, id_paciente: 2, ..nueva.clone() };
+        let error = state.agenda_repo.reservar_cita(&simultanea, 0, None).await.unwrap_err();
+        assert!(matches!(error, AppError::Conflict(_)));
+
+        // Con el seguro vencido tampoco, hasta que se registra la renovación
+        let seguro = |vence_en: NaiveDate| credenciales::credencial(1, CREDENCIAL_SEGURO, Some(vence_en));
+        state.credencial_repo.create_credencial(&seguro(lunes - Duration::days(1))).await.unwrap();
+        let req = test::TestRequest::get().uri(&uri).to_request();
+        let libres: Vec<Slot> = test::call_and_read_body_json(&app, req).await;
+        assert!(libres.is_empty());
+        let req = test::TestRequest::post()
+            .uri("/citas")
+            .set_json(serde_json::json!({
+                "id_prof": 1,
+                "id_paciente": 1,
+                "inicio": nueve_rfc,
+                "modalidad": "TELECONSULTA",
+            }))
+            .to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), 400);
+        state.credencial_repo.create_credencial(&seguro(lunes + Duration::days(365))).await.unwrap();
+        let req = test::TestRequest::get().uri(&uri).to_request();
+        let libres: Vec<Slot> = test::call_and_read_body_json(&app, req).await;
+        assert!(!libres.is_empty());
+
+        // Tras el egreso ya no se ofrece ni se reserva su horario
+        let mut contrato = state.contrato_repo.get_contrato(1).await.unwrap().unwrap();
+        contrato.fecha_egreso = Some(lunes - Duration::days(1));
+        state.contrato_repo.update_contrato(&contrato).await.unwrap();
+        let req = test::TestRequest::get().uri(&uri).to_request();
+        let libres: Vec<Slot> = test::call_and_read_body_json(&app, req).await;
+        assert!(libres.is_empty());
+        let req = test::TestRequest::post()
+            .uri("/citas")
+            .set_json(serde_json::json!({
+                "id_prof": 1,
+                "id_paciente": 1,
+                "inicio": nueve_rfc,
+                "modalidad": "TELECONSULTA",
+            }))
+            .to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), 400);
+    }
+}
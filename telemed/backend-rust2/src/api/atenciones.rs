@@ -0,0 +1,294 @@
+use actix_web::{web, HttpResponse};
+use tracing::error;
+use crate::{
+    models::{
+        Adenda, AdendaInput, AtencionDetalle, AtencionFiltro, AtencionInput, FirmaInput,
+        ATENCION_FIRMADA, CITA_AGENDADA, CITA_REALIZADA,
+    },
+    tiempo,
+    app_state::AppState,
+    error::AppError
+};
+use super::super::repositories::{
+    AgendaRepository, AtencionRepository, Cie10Repository, FacturacionRepository, PacienteRepository, ProfesionalRepository,
+    ZonaRepository,
+};
+use super::facturacion;
+
+/// Verifica que paciente, profesional y cita existan y sean coherentes entre sí,
+/// y que los diagnósticos estén en la CIE-10 vigente.
+async fn validar_referencias<R>(data: &AppState<R>, detalle: &mut AtencionDetalle) -> Result<(), AppError>
+where
+    R: AtencionRepository + AgendaRepository + PacienteRepository + ProfesionalRepository + Cie10Repository,
+{
+    data.cie10.obtener(data.cie10_repo.as_ref()).await?.codificar(&mut detalle.diagnosticos)?;
+
+    let atencion = &detalle.atencion;
+    if PacienteRepository::get_by_id(data.paciente_repo.as_ref(), atencion.id_paciente).await?.is_none() {
+        return Err(AppError::Validation(format!("Paciente {} no existe", atencion.id_paciente)));
+    }
+    if ProfesionalRepository::get_by_id(data.profesional_repo.as_ref(), atencion.id_prof).await?.is_none() {
+        return Err(AppError::Validation(format!("Profesional {} no existe", atencion.id_prof)));
+    }
+
+    let Some(id_cita) = atencion.id_cita else {
+        return Ok(());
+    };
+    let cita = data.agenda_repo.get_cita(id_cita).await?
+        .ok_or_else(|| AppError::Validation(format!("Cita {} no existe", id_cita)))?;
+    if cita.id_paciente != atencion.id_paciente || cita.id_prof != atencion.id_prof {
+        return Err(AppError::Validation("La cita corresponde a otro paciente o profesional".into()));
+    }
+    if !cita.activa() {
+        return Err(AppError::Validation(format!("La cita está {}", cita.estado)));
+    }
+
+    let previas = AtencionRepository::search(data.atencion_repo.as_ref(), &AtencionFiltro {
+        id_cita: Some(id_cita),
+        ..Default::default()
+    }).await?;
+    if previas.iter().any(|p| p.atencion.id != atencion.id) {
+        return Err(AppError::Conflict(format!("La cita {} ya tiene una atención registrada", id_cita)));
+    }
+    Ok(())
+}
+
+pub async fn get_by_id<R>(
+    id: web::Path<u32>,
+    data: web::Data<AppState<R>>,
+) -> Result<HttpResponse, AppError>
+where
+    R: AtencionRepository + 'static,
+{
+    let atencion = data.atencion_repo.get_by_id(id.into_inner()).await?;
+    match atencion {
+        Some(a) => Ok(HttpResponse::Ok().json(a)),
+        None => Err(AppError::NotFound),
+    }
+}
+
+pub async fn search<R>(
+    filtro: web::Query<AtencionFiltro>,
+    data: web::Data<AppState<R>>,
+) -> Result<HttpResponse, AppError>
+where
+    R: AtencionRepository + 'static,
+{
+    let atenciones = data.atencion_repo.search(&filtro).await?;
+    Ok(HttpResponse::Ok().json(atenciones))
+}
+
+/// Historia clínica del paciente: atenciones firmadas con sus adendas, de la
+/// más reciente a la más antigua.
+pub async fn timeline<R>(
+    id_paciente: web::Path<u32>,
+    data: web::Data<AppState<R>>,
+) -> Result<HttpResponse, AppError>
+where
+    R: AtencionRepository + PacienteRepository + 'static,
+{
+    let id_paciente = id_paciente.into_inner();
+    if PacienteRepository::get_by_id(data.paciente_repo.as_ref(), id_paciente).await?.is_none() {
+        return Err(AppError::NotFound);
+    }
+
+    let atenciones = AtencionRepository::search(data.atencion_repo.as_ref(), &AtencionFiltro {
+        id_paciente: Some(id_paciente),
+        estado: Some(ATENCION_FIRMADA.into()),
+        ..Default::default()
+    }).await?;
+    Ok(HttpResponse::Ok().json(atenciones))
+}
+
+pub async fn create<R>(
+    atencion: web::Json<AtencionInput>,
+    data: web::Data<AppState<R>>,
+) -> Result<HttpResponse, AppError>
+where
+    R: AtencionRepository + AgendaRepository + PacienteRepository + ProfesionalRepository + Cie10Repository + 'static,
+{
+    let mut detalle: AtencionDetalle = atencion.into_inner().try_into()?;
+    validar_referencias(&data, &mut detalle).await?;
+
+    let id = AtencionRepository::create(data.atencion_repo.as_ref(), &detalle).await?;
+    Ok(HttpResponse::Created().json(serde_json::json!({"id": id})))
+}
+
+/// Reemplaza el contenido de un borrador. Las atenciones firmadas no se editan.
+pub async fn update<R>(
+    id: web::Path<u32>,
+    atencion: web::Json<AtencionInput>,
+    data: web::Data<AppState<R>>,
+) -> Result<HttpResponse, AppError>
+where
+    R: AtencionRepository + AgendaRepository + PacienteRepository + ProfesionalRepository + Cie10Repository + 'static,
+{
+    let actual = AtencionRepository::get_by_id(data.atencion_repo.as_ref(), id.into_inner()).await?
+        .ok_or(AppError::NotFound)?;
+    if actual.atencion.firmada() {
+        return Err(AppError::Conflict("La atención está firmada; agregue una adenda".into()));
+    }
+
+    let mut detalle: AtencionDetalle = atencion.into_inner().try_into()?;
+    if detalle.atencion.id_paciente != actual.atencion.id_paciente || detalle.atencion.id_prof != actual.atencion.id_prof {
+        return Err(AppError::Validation("No se puede cambiar el paciente ni el profesional de la atención".into()));
+    }
+    detalle.atencion.id = actual.atencion.id;
+    detalle.atencion.creada_en = actual.atencion.creada_en;
+    validar_referencias(&data, &mut detalle).await?;
+
+    if !data.atencion_repo.update_borrador(&detalle).await? {
+        return Err(AppError::Conflict("La atención fue firmada mientras se editaba".into()));
+    }
+    Ok(HttpResponse::Ok().finish())
+}
+
+/// Firma la atención: desde ese momento es inmutable. Si proviene de una cita,
+/// la cita queda realizada. También genera su cargo; si eso falla la firma
+/// se mantiene y el cargo se recalcula después.
+pub async fn firmar<R>(
+    id: web::Path<u32>,
+    firma: web::Json<FirmaInput>,
+    data: web::Data<AppState<R>>,
+) -> Result<HttpResponse, AppError>
+where
+    R: AtencionRepository + AgendaRepository + FacturacionRepository + PacienteRepository + ZonaRepository + 'static,
+{
+    let detalle = AtencionRepository::get_by_id(data.atencion_repo.as_ref(), id.into_inner()).await?.ok_or(AppError::NotFound)?;
+    let atencion = &detalle.atencion;
+    if atencion.firmada() {
+        return Err(AppError::Conflict("La atención ya está firmada".into()));
+    }
+    if firma.id_prof != atencion.id_prof {
+        return Err(AppError::Validation("Sólo el profesional tratante puede firmar la atención".into()));
+    }
+    if detalle.diagnosticos.is_empty() {
+        return Err(AppError::Validation("La atención requiere al menos un diagnóstico para firmarse".into()));
+    }
+
+    if !data.atencion_repo.firmar(atencion.id, tiempo::ahora()).await? {
+        return Err(AppError::Conflict("La atención ya está firmada".into()));
+    }
+
+    if let Some(id_cita) = atencion.id_cita
+        && let Some(mut cita) = data.agenda_repo.get_cita(id_cita).await?
+        && cita.estado == CITA_AGENDADA
+    {
+        cita.estado = CITA_REALIZADA.into();
+        data.agenda_repo.update_cita(&cita).await?;
+    }
+
+    let firmada = AtencionRepository::get_by_id(data.atencion_repo.as_ref(), atencion.id).await?.ok_or(AppError::NotFound)?;
+    if let Err(e) = facturacion::generar_cargo(&data, &firmada.atencion).await {
+        error!("Atención {}: no se generó el cargo: {}", atencion.id, e);
+    }
+    Ok(HttpResponse::Ok().json(firmada))
+}
+
+pub async fn create_adenda<R>(
+    id: web::Path<u32>,
+    adenda: web::Json<AdendaInput>,
+    data: web::Data<AppState<R>>,
+) -> Result<HttpResponse, AppError>
+where
+    R: AtencionRepository + ProfesionalRepository + 'static,
+{
+    let detalle = AtencionRepository::get_by_id(data.atencion_repo.as_ref(), id.into_inner()).await?
+        .ok_or(AppError::NotFound)?;
+    if !detalle.atencion.firmada() {
+        return Err(AppError::Conflict("La atención aún es un borrador; edítela directamente".into()));
+    }
+
+    let adenda = adenda.into_inner();
+    let texto = adenda.texto.trim().to_string();
+    if texto.is_empty() {
+        return Err(AppError::Validation("El texto de la adenda es obligatorio".into()));
+    }
+    if ProfesionalRepository::get_by_id(data.profesional_repo.as_ref(), adenda.id_prof).await?.is_none() {
+        return Err(AppError::Validation(format!("Profesional {} no existe", adenda.id_prof)));
+    }
+
+    let id = data.atencion_repo.create_adenda(&Adenda {
+        id: 0,
+        id_atencion: detalle.atencion.id,
+        id_prof: adenda.id_prof,
+        texto,
+        creada_en: tiempo::ahora(),
+    }).await?;
+    Ok(HttpResponse::Created().json(serde_json::json!({"id": id})))
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_web::{test, web, App};
+    use crate::{app_state::AppState, models::{Cie10, Profesional, CARGO_OBSERVADO}, repositories::{fixtures, MockRepository}};
+    use super::*;
+
+    async fn app_state() -> AppState<MockRepository> {
+        let medico = Profesional { especialidad: Some("Médico".into()), ..fixtures::profesional(1) };
+        let repo = fixtures::repositorio(vec![medico], vec![fixtures::paciente("0010895960-6")]).await;
+        Cie10Repository::importar(&repo, "2019", chrono::NaiveDate::MIN, &[
+            Cie10 { codigo: "J20.9".into(), descripcion: "Bronquitis aguda, no especificada".into() },
+        ]).await.unwrap();
+        AppState::new(repo)
+    }
+
+    #[actix_web::test]
+    async fn atencion_firmada_solo_admite_adendas() {
+        let data = web::Data::new(app_state().await);
+        let app = test::init_service(
+            App::new()
+                .app_data(data.clone())
+                .route("/atenciones", web::post().to(create::<MockRepository>))
+                .route("/atenciones/paciente/{id}", web::get().to(timeline::<MockRepository>))
+                .route("/atenciones/{id}", web::put().to(update::<MockRepository>))
+                .route("/atenciones/{id}/firmar", web::post().to(firmar::<MockRepository>))
+                .route("/atenciones/{id}/adendas", web::post().to(create_adenda::<MockRepository>)),
+        )
+        .await;
+
+        let borrador = serde_json::json!({
+            "id_paciente": 1,
+            "id_prof": 1,
+            "motivo": "Tos de dos semanas",
+        });
+        let req = test::TestRequest::post().uri("/atenciones").set_json(&borrador).to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), 201);
+
+        // Sin diagnóstico no se puede firmar
+        let req = test::TestRequest::post().uri("/atenciones/1/firmar").set_json(serde_json::json!({"id_prof": 1})).to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), 400);
+
+        let mut completa = borrador.clone();
+        completa["diagnosticos"] = serde_json::json!([{ "codigo": "J21.9" }]);
+        let req = test::TestRequest::put().uri("/atenciones/1").set_json(&completa).to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), 400);
+
+        completa["diagnosticos"] = serde_json::json!([{ "codigo": "j209" }]);
+        let req = test::TestRequest::put().uri("/atenciones/1").set_json(&completa).to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), 200);
+
+        let req = test::TestRequest::post().uri("/atenciones/1/firmar").set_json(serde_json::json!({"id_prof": 1})).to_request();
+        let firmada: AtencionDetalle = test::call_and_read_body_json(&app, req).await;
+        assert!(firmada.atencion.firmada() && firmada.atencion.firmada_en.is_some());
+        // El paciente no tiene previsión: el cargo se genera observado
+        let cargo = data.facturacion_repo.get_cargo_por_atencion(1).await.unwrap().unwrap();
+        assert_eq!(cargo.estado, CARGO_OBSERVADO);
+
+        let req = test::TestRequest::put().uri("/atenciones/1").set_json(&borrador).to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), 409);
+
+        let req = test::TestRequest::post()
+            .uri("/atenciones/1/adendas")
+            .set_json(serde_json::json!({"id_prof": 1, "texto": "Resultado de radiografía normal"}))
+            .to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), 201);
+
+        let req = test::TestRequest::get().uri("/atenciones/paciente/1").to_request();
+        let historia: Vec<AtencionDetalle> = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(historia.len(), 1);
+        assert_eq!(historia[0].diagnosticos[0].codigo, "J20.9");
+        assert_eq!(historia[0].diagnosticos[0].descripcion, "Bronquitis aguda, no especificada");
+        assert_eq!(historia[0].adendas.len(), 1);
+    }
+}
@@ -0,0 +1,59 @@
+use actix_web::{web, HttpResponse};
+use crate::{
+    cie10,
+    models::{Cie10Filtro, ImportacionCie10},
+    app_state::AppState,
+    error::AppError
+};
+use super::super::repositories::Cie10Repository;
+
+const LIMITE_RESULTADOS: usize = 20;
+const MAX_RESULTADOS: usize = 100;
+
+pub async fn search<R>(
+    filtro: web::Query<Cie10Filtro>,
+    data: web::Data<AppState<R>>,
+) -> Result<HttpResponse, AppError>
+where
+    R: Cie10Repository + 'static,
+{
+    let catalogo = data.cie10.obtener(data.cie10_repo.as_ref()).await?;
+    let limite = filtro.limite.unwrap_or(LIMITE_RESULTADOS).min(MAX_RESULTADOS);
+    Ok(HttpResponse::Ok().json(catalogo.buscar(&filtro.q, limite)))
+}
+
+pub async fn get_by_codigo<R>(
+    codigo: web::Path<String>,
+    data: web::Data<AppState<R>>,
+) -> Result<HttpResponse, AppError>
+where
+    R: Cie10Repository + 'static,
+{
+    let catalogo = data.cie10.obtener(data.cie10_repo.as_ref()).await?;
+    match catalogo.get(&codigo) {
+        Some(c) => Ok(HttpResponse::Ok().json(c)),
+        None => Err(AppError::NotFound),
+    }
+}
+
+/// Carga una versión del catálogo desde un archivo `codigo;descripcion`
+/// (por ejemplo, la planilla CIE-10 publicada por el DEIS).
+pub async fn importar<R>(
+    params: web::Query<ImportacionCie10>,
+    cuerpo: String,
+    data: web::Data<AppState<R>>,
+) -> Result<HttpResponse, AppError>
+where
+    R: Cie10Repository + 'static,
+{
+    let version = params.version.trim();
+    if version.is_empty() {
+        return Err(AppError::Validation("La versión del catálogo es obligatoria".into()));
+    }
+
+    let codigos = cie10::parsear_importacion(&cuerpo)?;
+    let cargados = data.cie10_repo.importar(version, params.vigente_desde, &codigos).await?;
+    data.cie10.invalidar();
+
+    Ok(HttpResponse::Created().json(serde_json::json!({"version": version, "codigos": cargados})))
+}
@@ -0,0 +1,286 @@
+use actix_web::{web, HttpRequest, HttpResponse};
+use crate::{
+    models::{
+        ConsentimientosPaciente, EventoConsentimiento, OtorgamientoInput, PlantillaConsentimiento,
+        PlantillaConsentimientoInput, RevocacionInput, CONSENTIMIENTO_OTORGA, CONSENTIMIENTO_REVOCA,
+        PROPOSITOS_CONSENTIMIENTO,
+    },
+    consentimientos, rut, tiempo,
+    app_state::AppState,
+    error::AppError
+};
+use super::super::repositories::{ConsentimientoRepository, PacienteRepository};
+
+async fn paciente_existe<R>(data: &AppState<R>, id_paciente: u32) -> Result<(), AppError>
+where
+    R: PacienteRepository,
+{
+    PacienteRepository::get_by_id(data.paciente_repo.as_ref(), id_paciente).await?
+        .map(|_| ())
+        .ok_or(AppError::NotFound)
+}
+
+fn otorgante(otorgante: Option<&str>) -> Result<Option<String>, AppError> {
+    otorgante.map(str::trim).filter(|r| !r.is_empty()).map(rut::normalizar).transpose()
+}
+
+/// IP de la conexión que registró el evento. No se leen
+/// `X-Forwarded-For` ni `Forwarded`: cualquier cliente puede escribirlos y
+/// la IP es evidencia del consentimiento.
+fn ip(req: &HttpRequest) -> Option<String> {
+    req.peer_addr().map(|direccion| direccion.ip().to_string())
+}
+
+pub async fn plantillas<R>(
+    data: web::Data<AppState<R>>,
+) -> Result<HttpResponse, AppError>
+where
+    R: ConsentimientoRepository + 'static,
+{
+    Ok(HttpResponse::Ok().json(data.consentimiento_repo.plantillas_consentimiento().await?))
+}
+
+pub async fn versiones<R>(
+    proposito: web::Path<String>,
+    data: web::Data<AppState<R>>,
+) -> Result<HttpResponse, AppError>
+where
+    R: ConsentimientoRepository + 'static,
+{
+    let proposito = consentimientos::validar_proposito(&proposito)?;
+    Ok(HttpResponse::Ok().json(data.consentimiento_repo.versiones_consentimiento(&proposito).await?))
+}
+
+/// Publica una nueva versión del texto. Con `requiere_renovacion` los
+/// pacientes que aceptaron una versión anterior deben volver a aceptar.
+pub async fn crear_plantilla<R>(
+    proposito: web::Path<String>,
+    plantilla: web::Json<PlantillaConsentimientoInput>,
+    data: web::Data<AppState<R>>,
+) -> Result<HttpResponse, AppError>
+where
+    R: ConsentimientoRepository + 'static,
+{
+    let proposito = consentimientos::validar_proposito(&proposito)?;
+    let input = plantilla.into_inner();
+    let titulo = input.titulo.trim().to_string();
+    if titulo.is_empty() || input.texto.trim().is_empty() {
+        return Err(AppError::Validation("El consentimiento requiere título y texto".into()));
+    }
+
+    let version = data.consentimiento_repo.crear_plantilla_consentimiento(&PlantillaConsentimiento {
+        id: 0,
+        proposito,
+        version: 0,
+        titulo,
+        texto: input.texto,
+        requiere_renovacion: input.requiere_renovacion,
+        creada_en: tiempo::ahora(),
+    }).await?;
+    Ok(HttpResponse::Created().json(serde_json::json!({"version": version})))
+}
+
+pub async fn get_paciente<R>(
+    id_paciente: web::Path<u32>,
+    data: web::Data<AppState<R>>,
+) -> Result<HttpResponse, AppError>
+where
+    R: ConsentimientoRepository + PacienteRepository + 'static,
+{
+    let id_paciente = id_paciente.into_inner();
+    paciente_existe(&data, id_paciente).await?;
+
+    let mut versiones = Vec::new();
+    for proposito in PROPOSITOS_CONSENTIMIENTO {
+        versiones.extend(data.consentimiento_repo.versiones_consentimiento(proposito).await?);
+    }
+    let eventos = data.consentimiento_repo.eventos_consentimiento(id_paciente).await?;
+    Ok(HttpResponse::Ok().json(ConsentimientosPaciente {
+        estados: consentimientos::estados(&eventos, &versiones),
+        eventos,
+    }))
+}
+
+/// Registra la aceptación de la versión vigente del texto: si se publicó
+/// otra mientras el paciente lo leía, debe aceptar la nueva.
+pub async fn otorgar<R>(
+    req: HttpRequest,
+    id_paciente: web::Path<u32>,
+    otorgamiento: web::Json<OtorgamientoInput>,
+    data: web::Data<AppState<R>>,
+) -> Result<HttpResponse, AppError>
+where
+    R: ConsentimientoRepository + PacienteRepository + 'static,
+{
+    let id_paciente = id_paciente.into_inner();
+    let input = otorgamiento.into_inner();
+    let proposito = consentimientos::validar_proposito(&input.proposito)?;
+    let canal = consentimientos::validar_canal(&input.canal)?;
+    paciente_existe(&data, id_paciente).await?;
+
+    let vigente = data.consentimiento_repo.versiones_consentimiento(&proposito).await?
+        .into_iter()
+        .map(|p| p.version)
+        .max()
+        .ok_or_else(|| AppError::Validation(format!("No hay texto publicado para {}", proposito)))?;
+    if input.version != vigente {
+        return Err(AppError::Conflict(format!("La versión vigente de {} es la {}", proposito, vigente)));
+    }
+
+    let mut evento = EventoConsentimiento {
+        id: 0,
+        id_paciente,
+        proposito,
+        version: vigente,
+        accion: CONSENTIMIENTO_OTORGA.into(),
+        canal,
+        otorgante: otorgante(input.otorgante.as_deref())?,
+        id_usuario: input.id_usuario,
+        ip: ip(&req),
+        ocurrido_en: tiempo::ahora(),
+    };
+    evento.id = data.consentimiento_repo.registrar_consentimiento(&evento).await?;
+    Ok(HttpResponse::Created().json(evento))
+}
+
+/// La revocación rige desde ya; no afecta lo realizado antes.
+pub async fn revocar<R>(
+    req: HttpRequest,
+    path: web::Path<(u32, String)>,
+    revocacion: web::Json<RevocacionInput>,
+    data: web::Data<AppState<R>>,
+) -> Result<HttpResponse, AppError>
+where
+    R: ConsentimientoRepository + PacienteRepository + 'static,
+{
+    let (id_paciente, proposito) = path.into_inner();
+    let proposito = consentimientos::validar_proposito(&proposito)?;
+    let input = revocacion.into_inner();
+    let canal = consentimientos::validar_canal(&input.canal)?;
+    paciente_existe(&data, id_paciente).await?;
+
+    let otorgado = data.consentimiento_repo.consentimiento_vigente(id_paciente, &proposito).await?
+        .ok_or_else(|| AppError::Conflict(format!("El paciente no tiene vigente el consentimiento {}", proposito)))?;
+
+    let mut evento = EventoConsentimiento {
+        id: 0,
+        id_paciente,
+        proposito,
+        version: otorgado.version,
+        accion: CONSENTIMIENTO_REVOCA.into(),
+        canal,
+        otorgante: otorgante(input.otorgante.as_deref())?,
+        id_usuario: input.id_usuario,
+        ip: ip(&req),
+        ocurrido_en: tiempo::ahora(),
+    };
+    evento.id = data.consentimiento_repo.registrar_consentimiento(&evento).await?;
+    Ok(HttpResponse::Created().json(evento))
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_web::{test, App};
+    use crate::{
+        models::{Paciente, CONSENTIMIENTO_TELECONSULTA},
+        repositories::{fixtures, MockRepository},
+    };
+    use super::*;
+
+    async fn app_state() -> AppState<MockRepository> {
+        fixtures::app_state(Vec::new(), vec![Paciente { cod_cliente: Some(2), ..fixtures::paciente("0010895960-6") }]).await
+    }
+
+    #[actix_web::test]
+    async fn otorga_revoca_y_exige_renovar() {
+        let data = web::Data::new(app_state().await);
+        let app = test::init_service(
+            App::new()
+                .app_data(data.clone())
+                .route("/consentimientos/plantillas/{proposito}", web::put().to(crear_plantilla::<MockRepository>))
+                .route("/pacientes/{id}/consentimientos", web::get().to(get_paciente::<MockRepository>))
+                .route("/pacientes/{id}/consentimientos", web::post().to(otorgar::<MockRepository>))
+                .route("/pacientes/{id}/consentimientos/{proposito}/revocacion", web::post().to(revocar::<MockRepository>)),
+        )
+        .await;
+        let exigir = async || consentimientos::exigir(data.consentimiento_repo.as_ref(), 1, CONSENTIMIENTO_TELECONSULTA).await;
+        let publicar = async |requiere_renovacion: bool| {
+            let req = test::TestRequest::put()
+                .uri("/consentimientos/plantillas/teleconsulta")
+                .set_json(serde_json::json!({
+                    "titulo": "Teleconsulta",
+                    "texto": "Acepto ser atendido por videollamada.",
+                    "requiere_renovacion": requiere_renovacion,
+                }))
+                .to_request();
+            let resp: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+            resp["version"].as_u64().unwrap()
+        };
+        let otorgar = async |version: u64| {
+            let req = test::TestRequest::post()
+                .uri("/pacientes/1/consentimientos")
+                .peer_addr("190.5.6.7:51000".parse().unwrap())
+                .insert_header(("X-Forwarded-For", "200.1.2.3"))
+                .set_json(serde_json::json!({
+                    "proposito": "TELECONSULTA",
+                    "version": version,
+                    "canal": "portal",
+                    "otorgante": "16.354.813-5",
+                }))
+                .to_request();
+            test::call_service(&app, req).await
+        };
+
+        let req = test::TestRequest::post()
+            .uri("/pacientes/1/consentimientos")
+            .set_json(serde_json::json!({"proposito": "TELECONSULTA", "version": 1, "canal": "PORTAL"}))
+            .to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), 400);
+        assert!(matches!(exigir().await, Err(AppError::Forbidden(_))));
+
+        assert_eq!(publicar(false).await, 1);
+        let resp = otorgar(1).await;
+        assert_eq!(resp.status(), 201);
+        let evento: EventoConsentimiento = test::read_body_json(resp).await;
+        // La cabecera la escribe el cliente; vale la IP de la conexión
+        assert_eq!(evento.ip.as_deref(), Some("190.5.6.7"));
+        assert_eq!(evento.otorgante.as_deref(), Some("0016354813-5"));
+        assert_eq!(evento.canal, "PORTAL");
+        assert_eq!(exigir().await.unwrap().version, 1);
+
+        // Una versión sin renovación obligatoria no invalida la anterior,
+        // pero sólo se acepta la vigente
+        assert_eq!(publicar(false).await, 2);
+        assert!(exigir().await.is_ok());
+        assert_eq!(otorgar(1).await.status(), 409);
+
+        assert_eq!(publicar(true).await, 3);
+        assert!(exigir().await.is_err());
+        assert_eq!(otorgar(3).await.status(), 201);
+        assert_eq!(exigir().await.unwrap().version, 3);
+
+        let revocacion = serde_json::json!({"canal": "TELEFONICO", "id_usuario": 7});
+        let req = test::TestRequest::post()
+            .uri("/pacientes/1/consentimientos/teleconsulta/revocacion")
+            .set_json(&revocacion)
+            .to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), 201);
+        assert!(exigir().await.is_err());
+        let req = test::TestRequest::post()
+            .uri("/pacientes/1/consentimientos/teleconsulta/revocacion")
+            .set_json(&revocacion)
+            .to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), 409);
+
+        let req = test::TestRequest::get().uri("/pacientes/1/consentimientos").to_request();
+        let consentimientos: ConsentimientosPaciente = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(consentimientos.eventos.len(), 3);
+        let teleconsulta = consentimientos.estados.iter().find(|e| e.proposito == CONSENTIMIENTO_TELECONSULTA).unwrap();
+        assert!(!teleconsulta.vigente);
+        assert_eq!(teleconsulta.version_actual, Some(3));
+        assert_eq!(teleconsulta.ultimo_evento.as_ref().map(|e| e.accion.as_str()), Some(CONSENTIMIENTO_REVOCA));
+
+        let req = test::TestRequest::get().uri("/pacientes/9/consentimientos").to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), 404);
+    }
+}
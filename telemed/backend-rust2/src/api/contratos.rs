@@ -0,0 +1,263 @@
+use std::time::Duration as Intervalo;
+
+use actix_web::{rt, web, HttpResponse};
+use chrono::{Days, NaiveDate, NaiveTime};
+use tracing::{error, info};
+use crate::{
+    contratos,
+    models::{
+        CitaFiltro, Contrato, ContratoFiltro, ContratoInput, EgresoInput, Profesional, ProfesionalFiltro, VisitaFiltro,
+        CITA_AGENDADA, VISITA_EN_RUTA, VISITA_PENDIENTE,
+    },
+    tiempo,
+    app_state::AppState,
+    error::AppError
+};
+use super::super::repositories::{AgendaRepository, ContratoRepository, ProfesionalRepository, VisitaRepository};
+
+const REVISION: Intervalo = Intervalo::from_secs(60 * 60);
+
+/// Los contratos se rigen por el calendario de Chile continental.
+pub(super) fn hoy() -> Result<NaiveDate, AppError> {
+    Ok(tiempo::a_local(tiempo::ahora(), tiempo::zona_horaria(tiempo::ZONA_HORARIA_DEFECTO)?).date())
+}
+
+async fn profesional<R>(data: &AppState<R>, id_prof: u32) -> Result<Profesional, AppError>
+where
+    R: ProfesionalRepository,
+{
+    ProfesionalRepository::get_by_id(data.profesional_repo.as_ref(), id_prof).await?.ok_or(AppError::NotFound)
+}
+
+/// Lleva a `paso_profesionales` la situación que derivan los contratos.
+async fn refrescar<R>(data: &AppState<R>, profesional: &Profesional, contratos: &[Contrato], hoy: NaiveDate) -> Result<bool, AppError>
+where
+    R: ContratoRepository,
+{
+    let Some(situacion) = contratos::cambio(profesional, contratos, hoy) else {
+        return Ok(false);
+    };
+    info!(id_prof = profesional.id_prof, estado = %situacion.estado, "situación contractual actualizada");
+    data.contrato_repo.actualizar_situacion(profesional.id_prof, &situacion).await?;
+    Ok(true)
+}
+
+/// Actualiza el estado de todos los profesionales; retorna cuántos cambiaron.
+pub async fn sincronizar_estados<R>(data: &AppState<R>) -> Result<usize, AppError>
+where
+    R: ContratoRepository + ProfesionalRepository,
+{
+    let hoy = hoy()?;
+    let contratos = data.contrato_repo.contratos(&ContratoFiltro::default()).await?;
+    let mut cambiados = 0;
+    for profesional in ProfesionalRepository::search(data.profesional_repo.as_ref(), &ProfesionalFiltro::default()).await? {
+        if refrescar(data, &profesional, &contratos, hoy).await? {
+            cambiados += 1;
+        }
+    }
+    Ok(cambiados)
+}
+
+/// Tarea de fondo: cada hora aplica los ingresos y egresos que llegaron a
+/// su fecha.
+pub async fn sincronizar<R>(data: AppState<R>)
+where
+    R: ContratoRepository + ProfesionalRepository,
+{
+    let mut intervalo = rt::time::interval(REVISION);
+    loop {
+        intervalo.tick().await;
+        if let Err(e) = sincronizar_estados(&data).await {
+            error!("Contratos: {}", e);
+        }
+    }
+}
+
+pub async fn get_contratos<R>(
+    id_prof: web::Path<u32>,
+    data: web::Data<AppState<R>>,
+) -> Result<HttpResponse, AppError>
+where
+    R: ContratoRepository + ProfesionalRepository + 'static,
+{
+    let profesional = profesional(&data, id_prof.into_inner()).await?;
+    let filtro = ContratoFiltro { id_prof: Some(profesional.id_prof), ..Default::default() };
+    Ok(HttpResponse::Ok().json(data.contrato_repo.contratos(&filtro).await?))
+}
+
+/// Registra un período de contrato (un ingreso o reingreso).
+pub async fn create<R>(
+    id_prof: web::Path<u32>,
+    contrato: web::Json<ContratoInput>,
+    data: web::Data<AppState<R>>,
+) -> Result<HttpResponse, AppError>
+where
+    R: ContratoRepository + ProfesionalRepository + 'static,
+{
+    let profesional = profesional(&data, id_prof.into_inner()).await?;
+    let mut contrato: Contrato = contrato.into_inner().try_into()?;
+    contrato.id_prof = profesional.id_prof;
+
+    let mut contratos = data.contrato_repo.contratos(&ContratoFiltro { id_prof: Some(contrato.id_prof), ..Default::default() }).await?;
+    contratos::validar(&contrato, &contratos)?;
+    contrato.id = data.contrato_repo.create_contrato(&contrato).await?;
+
+    contratos.push(contrato.clone());
+    refrescar(&data, &profesional, &contratos, hoy()?).await?;
+    Ok(HttpResponse::Created().json(contrato))
+}
+
+/// Cierra un contrato o corrige su fecha de egreso. No procede si el
+/// profesional tiene citas o visitas comprometidas después de esa fecha.
+pub async fn egreso<R>(
+    path: web::Path<(u32, u32)>,
+    egreso: web::Json<EgresoInput>,
+    data: web::Data<AppState<R>>,
+) -> Result<HttpResponse, AppError>
+where
+    R: ContratoRepository + ProfesionalRepository + AgendaRepository + VisitaRepository + 'static,
+{
+    let (id_prof, id) = path.into_inner();
+    let profesional = profesional(&data, id_prof).await?;
+    let mut contrato = data.contrato_repo.get_contrato(id).await?
+        .filter(|c| c.id_prof == id_prof)
+        .ok_or(AppError::NotFound)?;
+    let EgresoInput { fecha_egreso, motivo } = egreso.into_inner();
+    contrato.fecha_egreso = Some(fecha_egreso);
+    contrato.motivo_egreso = motivo.map(|m| m.trim().to_string()).filter(|m| !m.is_empty());
+
+    let mut contratos = data.contrato_repo.contratos(&ContratoFiltro { id_prof: Some(id_prof), ..Default::default() }).await?;
+    contratos::validar(&contrato, &contratos)?;
+
+    // Un día de margen para las citas de la noche del egreso, que en UTC ya son del día siguiente
+    let santiago = tiempo::zona_horaria(tiempo::ZONA_HORARIA_DEFECTO)?;
+    let citas = data.agenda_repo.search_citas(&CitaFiltro {
+        id_prof: Some(id_prof),
+        desde: Some(fecha_egreso.and_time(NaiveTime::MIN)),
+        ..Default::default()
+    }).await?;
+    let citas = citas
+        .iter()
+        .filter(|c| c.estado == CITA_AGENDADA && tiempo::a_local(c.inicio, santiago).date() > fecha_egreso)
+        .count();
+    let visitas = VisitaRepository::search(data.visita_repo.as_ref(), &VisitaFiltro {
+        id_prof: Some(id_prof),
+        desde: Some(fecha_egreso + Days::new(1)),
+        ..Default::default()
+    }).await?;
+    let visitas = visitas.iter().filter(|v| v.estado == VISITA_PENDIENTE || v.estado == VISITA_EN_RUTA).count();
+    if citas + visitas > 0 {
+        return Err(AppError::Conflict(format!(
+            "El profesional tiene {} citas y {} visitas después del {}; reasígnelas o cancélelas primero",
+            citas, visitas, fecha_egreso
+        )));
+    }
+
+    data.contrato_repo.update_contrato(&contrato).await?;
+    if let Some(c) = contratos.iter_mut().find(|c| c.id == contrato.id) {
+        *c = contrato.clone();
+    }
+    refrescar(&data, &profesional, &contratos, hoy()?).await?;
+    Ok(HttpResponse::Ok().json(contrato))
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_web::{test, App};
+    use crate::{
+        models::{Visita, PROFESIONAL_NO_VIGENTE, PROFESIONAL_VIGENTE},
+        repositories::{fixtures, MockRepository},
+    };
+    use super::*;
+
+    fn fecha(anio: i32, mes: u32, dia: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(anio, mes, dia).unwrap()
+    }
+
+    /// Como en la carga original: vigente aunque su egreso ya pasó.
+    fn profesional() -> Profesional {
+        Profesional {
+            fecha_ingreso: Some(fecha(2019, 1, 9)),
+            fecha_egreso: Some(fecha(2022, 2, 15)),
+            ..fixtures::profesional(1)
+        }
+    }
+
+    #[actix_web::test]
+    async fn registra_periodos_y_deriva_el_estado() {
+        let state = AppState::new(MockRepository::default().with_profesionales(vec![profesional()]));
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(state.clone()))
+                .route("/profesionales/{id_prof}/contratos", web::get().to(get_contratos::<MockRepository>))
+                .route("/profesionales/{id_prof}/contratos", web::post().to(create::<MockRepository>))
+                .route("/profesionales/{id_prof}/contratos/{id}/egreso", web::put().to(egreso::<MockRepository>)),
+        )
+        .await;
+        let situacion = async || {
+            let p = ProfesionalRepository::get_by_id(state.profesional_repo.as_ref(), 1).await.unwrap().unwrap();
+            (p.estado.unwrap(), p.fecha_ingreso, p.fecha_egreso)
+        };
+        let contrato = async |cuerpo: serde_json::Value| {
+            let req = test::TestRequest::post().uri("/profesionales/1/contratos").set_json(cuerpo).to_request();
+            test::call_service(&app, req).await
+        };
+        let egreso = async |id: u32, fecha_egreso: NaiveDate| {
+            let req = test::TestRequest::put()
+                .uri(&format!("/profesionales/1/contratos/{}/egreso", id))
+                .set_json(serde_json::json!({"fecha_egreso": fecha_egreso, "motivo": "Término de convenio"}))
+                .to_request();
+            test::call_service(&app, req).await.status()
+        };
+        let hoy = hoy().unwrap();
+
+        // Sin contratos deja de estar vigente, pero conserva las fechas cargadas
+        assert_eq!(sincronizar_estados(&state).await.unwrap(), 1);
+        assert_eq!(situacion().await, (PROFESIONAL_NO_VIGENTE.into(), Some(fecha(2019, 1, 9)), Some(fecha(2022, 2, 15))));
+        assert_eq!(sincronizar_estados(&state).await.unwrap(), 0);
+
+        let ingreso = hoy - Days::new(30);
+        assert_eq!(contrato(serde_json::json!({"fecha_ingreso": ingreso, "fecha_egreso": ingreso - Days::new(1)})).await.status(), 400);
+        assert_eq!(contrato(serde_json::json!({"fecha_ingreso": fecha(2019, 1, 9), "fecha_egreso": fecha(2022, 2, 15)})).await.status(), 201);
+        let resp = contrato(serde_json::json!({"fecha_ingreso": ingreso, "observacion": "Reingreso"})).await;
+        assert_eq!(resp.status(), 201);
+        let actual: Contrato = test::read_body_json(resp).await;
+        assert_eq!(situacion().await, (PROFESIONAL_VIGENTE.into(), Some(ingreso), None));
+        assert_eq!(contrato(serde_json::json!({"fecha_ingreso": fecha(2021, 1, 1), "fecha_egreso": fecha(2021, 12, 31)})).await.status(), 409);
+
+        let req = test::TestRequest::get().uri("/profesionales/1/contratos").to_request();
+        let contratos: Vec<Contrato> = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(contratos.len(), 2);
+        let req = test::TestRequest::get().uri("/profesionales/2/contratos").to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), 404);
+
+        // El egreso no puede quedar antes del ingreso, y al pasar deja de estar vigente
+        assert_eq!(egreso(actual.id, ingreso - Days::new(1)).await, 400);
+        assert_eq!(egreso(actual.id, hoy - Days::new(1)).await, 200);
+        assert_eq!(situacion().await, (PROFESIONAL_NO_VIGENTE.into(), Some(ingreso), Some(hoy - Days::new(1))));
+        assert_eq!(egreso(actual.id, hoy + Days::new(10)).await, 200);
+        assert_eq!(situacion().await.0, PROFESIONAL_VIGENTE);
+
+        // Con una visita comprometida no puede egresar antes de ella
+        VisitaRepository::create(state.visita_repo.as_ref(), &Visita {
+            id: 0,
+            id_paciente: 1,
+            id_prof: Some(1),
+            cod_zona: "049".into(),
+            direccion: "Granaderos 2360".into(),
+            comuna: None,
+            latitud: None,
+            longitud: None,
+            fecha: hoy + Days::new(5),
+            ventana_desde: None,
+            ventana_hasta: None,
+            duracion_min: 45,
+            motivo: "Curación".into(),
+            estado: VISITA_PENDIENTE.into(),
+            motivo_estado: None,
+            creada_en: tiempo::ahora(),
+        }).await.unwrap();
+        assert_eq!(egreso(actual.id, hoy + Days::new(4)).await, 409);
+        assert_eq!(egreso(actual.id, hoy + Days::new(5)).await, 200);
+    }
+}
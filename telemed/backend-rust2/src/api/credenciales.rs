@@ -0,0 +1,366 @@
+use std::collections::HashMap;
+use std::time::Duration as Intervalo;
+
+use actix_multipart::{Field, Multipart};
+use actix_web::{http::header::ContentDisposition, rt, web, HttpResponse};
+use chrono::NaiveDate;
+use futures_util::TryStreamExt;
+use tracing::{error, warn};
+use crate::{
+    adjuntos, contratos, credenciales,
+    models::{
+        nombre_credencial, AlertaCredencial, ContratoFiltro, Credencial, CredencialFiltro, CredencialInput,
+        CredencialPorVencer, PorVencerFiltro, Profesional, ProfesionalFiltro, DESTINATARIO_PROFESIONAL,
+    },
+    tiempo,
+    app_state::AppState,
+    error::AppError
+};
+use super::adjuntos::{almacenar, formulario, leer_archivo, leer_campo, Archivo};
+use super::contratos::hoy;
+use super::notificaciones;
+use super::super::repositories::{
+    ContratoRepository, CredencialRepository, NotificacionRepository, PacienteRepository, ProfesionalRepository,
+    UsuarioRepository,
+};
+
+/// Los vencimientos se revisan una vez al día.
+const REVISION: Intervalo = Intervalo::from_secs(24 * 60 * 60);
+const DIAS_POR_VENCER: u32 = 30;
+const MAX_DIAS_POR_VENCER: u32 = 366;
+/// Largo máximo de los campos de texto del formulario.
+const LARGO_CAMPO: usize = 200;
+
+async fn profesional<R>(data: &AppState<R>, id_prof: u32) -> Result<Profesional, AppError>
+where
+    R: ProfesionalRepository,
+{
+    ProfesionalRepository::get_by_id(data.profesional_repo.as_ref(), id_prof).await?.ok_or(AppError::NotFound)
+}
+
+async fn leer_fecha(campo: &mut Field, nombre: &str) -> Result<Option<NaiveDate>, AppError> {
+    match leer_campo(campo, nombre, LARGO_CAMPO).await?.as_str() {
+        "" => Ok(None),
+        texto => NaiveDate::parse_from_str(texto, "%Y-%m-%d")
+            .map(Some)
+            .map_err(|_| AppError::Validation(format!("{} debe ser una fecha AAAA-MM-DD", nombre))),
+    }
+}
+
+async fn leer_formulario(mut multipart: Multipart) -> Result<(CredencialInput, Option<Archivo>), AppError> {
+    let mut input = CredencialInput::default();
+    let mut archivo = None;
+    while let Some(mut campo) = multipart.try_next().await.map_err(formulario)? {
+        match campo.name().unwrap_or_default() {
+            "tipo" => input.tipo = leer_campo(&mut campo, "tipo", LARGO_CAMPO).await?,
+            "descripcion" => input.descripcion = Some(leer_campo(&mut campo, "descripcion", LARGO_CAMPO).await?),
+            "numero" => input.numero = Some(leer_campo(&mut campo, "numero", LARGO_CAMPO).await?),
+            "emitida_en" => input.emitida_en = leer_fecha(&mut campo, "emitida_en").await?,
+            "vence_en" => input.vence_en = leer_fecha(&mut campo, "vence_en").await?,
+            "archivo" if archivo.is_none() => archivo = Some(leer_archivo(&mut campo).await?),
+            "archivo" => return Err(AppError::Validation("Sólo se acepta un archivo por credencial".into())),
+            _ => while campo.try_next().await.map_err(formulario)?.is_some() {},
+        }
+    }
+    Ok((input, archivo))
+}
+
+/// Encola los avisos de 30 y 7 días de las credenciales que rigen para
+/// profesionales con contrato vigente. Cada aviso se registra después de
+/// encolarlo, así un fallo al encolar se reintenta en la próxima revisión;
+/// a cambio, si el registro falla tras encolar, el aviso se repite al día
+/// siguiente. Retorna cuántos se encolaron.
+pub async fn alertar_vencimientos<R>(data: &AppState<R>) -> Result<usize, AppError>
+where
+    R: CredencialRepository + ContratoRepository + NotificacionRepository
+        + UsuarioRepository + PacienteRepository + ProfesionalRepository,
+{
+    let hoy = hoy()?;
+    let todas = data.credencial_repo.credenciales(&CredencialFiltro::default()).await?;
+    let contratos = data.contrato_repo.contratos(&ContratoFiltro { vigente_en: Some(hoy), ..Default::default() }).await?;
+    let mut encolados = 0;
+    for credencial in credenciales::actuales(&todas) {
+        let Some(vence_en) = credencial.vence_en else { continue };
+        let Some(dias) = credenciales::aviso_debido(vence_en, hoy) else { continue };
+        if contratos::vigente(&contratos, credencial.id_prof, hoy).is_none() {
+            continue;
+        }
+        if data.credencial_repo.alerta_enviada(credencial.id, dias as u16).await? {
+            continue;
+        }
+
+        let Some(profesional) = ProfesionalRepository::get_by_id(data.profesional_repo.as_ref(), credencial.id_prof).await? else {
+            warn!("Credencial {}: el profesional {} no existe, sin aviso", credencial.id, credencial.id_prof);
+            continue;
+        };
+        let variables: HashMap<String, String> = [
+            ("nombre", profesional.nombre_completo()),
+            ("credencial", nombre_credencial(&credencial.tipo).to_string()),
+            ("fecha", vence_en.format("%d/%m/%Y").to_string()),
+            ("dias", (vence_en - hoy).num_days().to_string()),
+        ]
+        .into_iter()
+        .map(|(k, v)| (k.to_string(), v))
+        .collect();
+
+        // El aviso se registra una vez encolado: si falla, se reintenta en la próxima revisión
+        if let Err(e) = notificaciones::encolar(data, "CREDENCIAL_POR_VENCER", DESTINATARIO_PROFESIONAL, credencial.id_prof, &variables).await {
+            warn!("Credencial {}: no se pudo encolar el aviso de {} días: {}", credencial.id, dias, e);
+            continue;
+        }
+        let alerta = AlertaCredencial { id_credencial: credencial.id, dias: dias as u16, enviada_en: tiempo::ahora() };
+        match data.credencial_repo.registrar_alerta(&alerta).await {
+            Ok(true) => encolados += 1,
+            Ok(false) => {}
+            Err(e) => {
+                warn!("Credencial {}: aviso de {} días encolado sin registrar, se repetirá: {}", credencial.id, dias, e);
+                encolados += 1;
+            }
+        }
+    }
+    Ok(encolados)
+}
+
+/// Tarea de fondo: revisa los vencimientos una vez al día.
+pub async fn alertar<R>(data: AppState<R>)
+where
+    R: CredencialRepository + ContratoRepository + NotificacionRepository
+        + UsuarioRepository + PacienteRepository + ProfesionalRepository,
+{
+    let mut intervalo = rt::time::interval(REVISION);
+    loop {
+        intervalo.tick().await;
+        if let Err(e) = alertar_vencimientos(&data).await {
+            error!("Credenciales: {}", e);
+        }
+    }
+}
+
+/// Todas las credenciales del profesional, incluidas las reemplazadas.
+pub async fn get_credenciales<R>(
+    id_prof: web::Path<u32>,
+    data: web::Data<AppState<R>>,
+) -> Result<HttpResponse, AppError>
+where
+    R: CredencialRepository + ProfesionalRepository + 'static,
+{
+    let profesional = profesional(&data, id_prof.into_inner()).await?;
+    let filtro = CredencialFiltro { id_prof: Some(profesional.id_prof), ..Default::default() };
+    Ok(HttpResponse::Ok().json(data.credencial_repo.credenciales(&filtro).await?))
+}
+
+/// Registra una credencial (`multipart/form-data` con `tipo`, `archivo` y
+/// opcionalmente `descripcion`, `numero`, `emitida_en` y `vence_en`).
+pub async fn subir<R>(
+    id_prof: web::Path<u32>,
+    multipart: Multipart,
+    data: web::Data<AppState<R>>,
+) -> Result<HttpResponse, AppError>
+where
+    R: CredencialRepository + ProfesionalRepository + 'static,
+{
+    let profesional = profesional(&data, id_prof.into_inner()).await?;
+    let (input, archivo) = leer_formulario(multipart).await?;
+    let Some(archivo) = archivo else {
+        return Err(AppError::Validation("Falta el archivo".into()));
+    };
+    if archivo.datos.is_empty() {
+        return Err(AppError::Validation("El archivo está vacío".into()));
+    }
+    let mut credencial: Credencial = input.try_into()?;
+    let tipo = adjuntos::validar_tipo(archivo.tipo_declarado.as_deref(), &archivo.datos)?;
+
+    credencial.id_prof = profesional.id_prof;
+    credencial.nombre = adjuntos::limpiar_nombre(archivo.nombre.as_deref());
+    credencial.tipo_mime = tipo.into();
+    credencial.tamano = archivo.datos.len() as u32;
+    credencial.sha256 = archivo.sha256.clone();
+    almacenar(&data.adjuntos, archivo).await?;
+    credencial.id = data.credencial_repo.create_credencial(&credencial).await?;
+    Ok(HttpResponse::Created().json(credencial))
+}
+
+/// Entrega el documento de la credencial como descarga.
+pub async fn documento<R>(
+    path: web::Path<(u32, u32)>,
+    data: web::Data<AppState<R>>,
+) -> Result<HttpResponse, AppError>
+where
+    R: CredencialRepository + 'static,
+{
+    let (id_prof, id) = path.into_inner();
+    let credencial = data.credencial_repo.get_credencial(id).await?
+        .filter(|c| c.id_prof == id_prof)
+        .ok_or(AppError::NotFound)?;
+    let datos = data.adjuntos.almacen.leer(&credencial.sha256).await?
+        .ok_or_else(|| AppError::Internal(format!("Contenido {} de la credencial {} no está en el almacén", credencial.sha256, id)))?;
+    Ok(HttpResponse::Ok()
+        .content_type(credencial.tipo_mime.as_str())
+        .insert_header(ContentDisposition::attachment(credencial.nombre))
+        .insert_header(("X-Content-Type-Options", "nosniff"))
+        .insert_header(("Cache-Control", "private, no-store"))
+        .body(datos))
+}
+
+/// Credenciales que rigen y vencen dentro de `dias`, o ya vencieron, las
+/// más urgentes primero.
+pub async fn por_vencer<R>(
+    filtro: web::Query<PorVencerFiltro>,
+    data: web::Data<AppState<R>>,
+) -> Result<HttpResponse, AppError>
+where
+    R: CredencialRepository + ProfesionalRepository + 'static,
+{
+    let dias = filtro.dias.unwrap_or(DIAS_POR_VENCER);
+    if dias > MAX_DIAS_POR_VENCER {
+        return Err(AppError::Validation(format!("dias no puede superar {}", MAX_DIAS_POR_VENCER)));
+    }
+    let hoy = hoy()?;
+    let todas = data.credencial_repo.credenciales(&CredencialFiltro { id_prof: filtro.id_prof, ..Default::default() }).await?;
+    let nombres: HashMap<u32, String> = ProfesionalRepository::search(data.profesional_repo.as_ref(), &ProfesionalFiltro::default())
+        .await?
+        .into_iter()
+        .map(|p| (p.id_prof, p.nombre_completo()))
+        .collect();
+    let mut resultado = Vec::new();
+    for credencial in credenciales::actuales(&todas) {
+        let Some(vence_en) = credencial.vence_en else { continue };
+        let restantes = (vence_en - hoy).num_days();
+        if restantes > i64::from(dias) {
+            continue;
+        }
+        resultado.push(CredencialPorVencer {
+            credencial: credencial.clone(),
+            profesional: nombres.get(&credencial.id_prof).cloned().unwrap_or_default(),
+            dias: restantes,
+        });
+    }
+    resultado.sort_by_key(|c| (c.dias, c.credencial.id_prof));
+    Ok(HttpResponse::Ok().json(resultado))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+    use std::sync::Arc;
+
+    use actix_web::{test, App};
+    use chrono::Days;
+    use crate::{
+        adjuntos::Adjuntos,
+        almacen::Local,
+        models::{NotificacionFiltro, CANAL_APP, CREDENCIAL_SEGURO, CREDENCIAL_VACUNACION},
+        repositories::{fixtures, MockRepository},
+    };
+    use super::*;
+
+    fn profesional() -> Profesional {
+        Profesional { ap_materno: Some("ZUNINO".into()), ..fixtures::profesional(1) }
+    }
+
+    async fn app_state(dir: &PathBuf) -> AppState<MockRepository> {
+        let repo = MockRepository::default().with_profesionales(vec![profesional()]);
+        let ingreso = NaiveDate::from_ymd_opt(2023, 5, 19).unwrap();
+        ContratoRepository::create_contrato(&repo, &contratos::contratado(1, ingreso)).await.unwrap();
+        AppState::new(repo).with_adjuntos(Adjuntos { almacen: Arc::new(Local::new(dir)), ..Default::default() })
+    }
+
+    fn formulario(campos: &[(&str, &str)], datos: &[u8]) -> (String, Vec<u8>) {
+        let limite = "----limite";
+        let mut cuerpo = String::new();
+        for (nombre, valor) in campos {
+            cuerpo.push_str(&format!("--{}\r\nContent-Disposition: form-data; name=\"{}\"\r\n\r\n{}\r\n", limite, nombre, valor));
+        }
+        cuerpo.push_str(&format!(
+            "--{}\r\nContent-Disposition: form-data; name=\"archivo\"; filename=\"seguro.pdf\"\r\nContent-Type: application/pdf\r\n\r\n",
+            limite,
+        ));
+        let mut cuerpo = cuerpo.into_bytes();
+        cuerpo.extend_from_slice(datos);
+        cuerpo.extend_from_slice(format!("\r\n--{}--\r\n", limite).as_bytes());
+        (format!("multipart/form-data; boundary={}", limite), cuerpo)
+    }
+
+    #[actix_web::test]
+    async fn registra_credenciales_y_avisa_vencimientos() {
+        let dir = std::env::temp_dir().join(format!("telemed-credenciales-{}", rand::random::<u64>()));
+        let state = app_state(&dir).await;
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(state.clone()))
+                .route("/profesionales/{id_prof}/credenciales", web::get().to(get_credenciales::<MockRepository>))
+                .route("/profesionales/{id_prof}/credenciales", web::post().to(subir::<MockRepository>))
+                .route("/profesionales/{id_prof}/credenciales/{id}/documento", web::get().to(documento::<MockRepository>))
+                .route("/credenciales/por-vencer", web::get().to(por_vencer::<MockRepository>)),
+        )
+        .await;
+        let subir = async |id_prof: u32, campos: &[(&str, &str)], datos: &[u8]| {
+            let (content_type, cuerpo) = formulario(campos, datos);
+            let req = test::TestRequest::post()
+                .uri(&format!("/profesionales/{}/credenciales", id_prof))
+                .insert_header(("content-type", content_type))
+                .set_payload(cuerpo)
+                .to_request();
+            test::call_service(&app, req).await
+        };
+
+        let hoy = hoy().unwrap();
+        let en = |dias: u64| (hoy + Days::new(dias)).to_string();
+        let pdf = b"%PDF-1.7\npoliza";
+        let resp = subir(1, &[("tipo", "seguro"), ("numero", " 4471-2 "), ("vence_en", &en(20))], pdf).await;
+        assert_eq!(resp.status(), 201);
+        let seguro: Credencial = test::read_body_json(resp).await;
+        assert_eq!((seguro.tipo.as_str(), seguro.numero.as_deref(), seguro.nombre.as_str()), (CREDENCIAL_SEGURO, Some("4471-2"), "seguro.pdf"));
+        let resp = subir(1, &[("tipo", "vacunacion"), ("descripcion", "Hepatitis B"), ("vence_en", &en(5))], pdf).await;
+        assert_eq!(resp.status(), 201);
+
+        for (id_prof, campos, datos) in [
+            (1, vec![("tipo", "licencia")], &pdf[..]),
+            (1, vec![("tipo", "rnpi"), ("vence_en", "31/12/2030")], &pdf[..]),
+            (1, vec![("tipo", "rnpi"), ("emitida_en", &en(10)), ("vence_en", &en(5))], &pdf[..]),
+            (1, vec![("tipo", "rnpi")], b"MZ ejecutable"),
+        ] {
+            assert_eq!(subir(id_prof, &campos, datos).await.status(), 400);
+        }
+        assert_eq!(subir(9, &[("tipo", "rnpi")], pdf).await.status(), 404);
+
+        let req = test::TestRequest::get().uri(&format!("/profesionales/1/credenciales/{}/documento", seguro.id)).to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.headers().get("content-type").unwrap(), "application/pdf");
+        assert_eq!(test::read_body(resp).await.as_ref(), pdf);
+        let req = test::TestRequest::get().uri(&format!("/profesionales/2/credenciales/{}/documento", seguro.id)).to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), 404);
+
+        let req = test::TestRequest::get().uri("/credenciales/por-vencer?dias=10").to_request();
+        let por_vencer: Vec<CredencialPorVencer> = test::call_and_read_body_json(&app, req).await;
+        let resumen: Vec<(&str, i64)> = por_vencer.iter().map(|c| (c.credencial.tipo.as_str(), c.dias)).collect();
+        assert_eq!(resumen, vec![(CREDENCIAL_VACUNACION, 5)]);
+        assert_eq!(por_vencer[0].profesional, "NICOLE ELENA ZAMORA ZUNINO");
+
+        // Un aviso por credencial y umbral, aunque la revisión se repita
+        assert_eq!(alertar_vencimientos(&state).await.unwrap(), 2);
+        assert_eq!(alertar_vencimientos(&state).await.unwrap(), 0);
+        let filtro = NotificacionFiltro { plantilla: Some("CREDENCIAL_POR_VENCER".into()), ..Default::default() };
+        let avisos = state.notificacion_repo.notificaciones(&filtro).await.unwrap();
+        assert!(avisos.iter().all(|n| n.canal == CANAL_APP && n.destinatario_tipo == DESTINATARIO_PROFESIONAL));
+        let mut asuntos: Vec<&str> = avisos.iter().map(|n| n.asunto.as_str()).collect();
+        asuntos.sort();
+        let fecha = |dias: u64| (hoy + Days::new(dias)).format("%d/%m/%Y").to_string();
+        assert_eq!(asuntos, vec![
+            format!("Su certificado de vacunación vence el {}", fecha(5)),
+            format!("Su seguro de responsabilidad civil vence el {}", fecha(20)),
+        ]);
+
+        // La renovación reemplaza a la que vence y no se avisa hasta su turno
+        assert_eq!(subir(1, &[("tipo", "vacunacion"), ("vence_en", &en(400))], pdf).await.status(), 201);
+        let req = test::TestRequest::get().uri("/credenciales/por-vencer?dias=10").to_request();
+        let por_vencer: Vec<CredencialPorVencer> = test::call_and_read_body_json(&app, req).await;
+        assert!(por_vencer.is_empty());
+        assert_eq!(alertar_vencimientos(&state).await.unwrap(), 0);
+
+        let req = test::TestRequest::get().uri("/profesionales/1/credenciales").to_request();
+        let todas: Vec<Credencial> = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(todas.len(), 3);
+        let _ = std::fs::remove_dir_all(dir);
+    }
+}
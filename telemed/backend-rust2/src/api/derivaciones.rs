@@ -0,0 +1,32 @@
+use actix_web::{web, HttpResponse};
+use crate::{
+    models::DerivacionFiltro,
+    app_state::AppState,
+    error::AppError
+};
+use super::super::repositories::DerivacionRepository;
+
+pub async fn search<R>(
+    filtro: web::Query<DerivacionFiltro>,
+    data: web::Data<AppState<R>>,
+) -> Result<HttpResponse, AppError>
+where
+    R: DerivacionRepository + 'static,
+{
+    let derivaciones = data.derivacion_repo.derivaciones(&filtro).await?;
+    Ok(HttpResponse::Ok().json(derivaciones))
+}
+
+pub async fn get_by_id<R>(
+    id: web::Path<u32>,
+    data: web::Data<AppState<R>>,
+) -> Result<HttpResponse, AppError>
+where
+    R: DerivacionRepository + 'static,
+{
+    let derivacion = data.derivacion_repo.get_derivacion(id.into_inner()).await?;
+    match derivacion {
+        Some(d) => Ok(HttpResponse::Ok().json(d)),
+        None => Err(AppError::NotFound),
+    }
+}
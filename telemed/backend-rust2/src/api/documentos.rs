@@ -0,0 +1,308 @@
+use std::collections::BTreeMap;
+
+use actix_web::{web, HttpResponse};
+use crate::{
+    models::{
+        tipo_documento, DocumentoClinico, DocumentoFiltro, DocumentoInput, Plantilla, PlantillaInput,
+        VersionDocumento, VersionFiltro, VersionInput, FIRMA_DOCUMENTO,
+    },
+    documentos, tiempo,
+    app_state::AppState,
+    error::AppError
+};
+use super::super::repositories::{
+    AtencionRepository, DocumentoRepository, FirmaRepository, PacienteRepository, ProfesionalRepository, ZonaRepository,
+};
+use super::{firmas, zonas};
+
+/// Fusiona la plantilla vigente del tipo con los datos del paciente, del
+/// profesional y, si se indica, de la atención (que debe estar firmada).
+async fn generar<R>(
+    data: &AppState<R>,
+    tipo: &str,
+    id_paciente: u32,
+    id_prof: u32,
+    id_atencion: Option<u32>,
+    campos: &BTreeMap<String, String>,
+) -> Result<VersionDocumento, AppError>
+where
+    R: DocumentoRepository + AtencionRepository + PacienteRepository + ProfesionalRepository + ZonaRepository,
+{
+    let plantilla = data.documento_repo.plantilla_vigente(tipo).await?
+        .ok_or_else(|| AppError::Validation(format!("No hay plantilla para {}", tipo)))?;
+    let paciente = PacienteRepository::get_by_id(data.paciente_repo.as_ref(), id_paciente).await?
+        .ok_or_else(|| AppError::Validation(format!("Paciente {} no existe", id_paciente)))?;
+    let profesional = ProfesionalRepository::get_by_id(data.profesional_repo.as_ref(), id_prof).await?
+        .ok_or_else(|| AppError::Validation(format!("Profesional {} no existe", id_prof)))?;
+
+    let atencion = match id_atencion {
+        Some(id) => {
+            let atencion = AtencionRepository::get_by_id(data.atencion_repo.as_ref(), id).await?
+                .ok_or_else(|| AppError::Validation(format!("Atención {} no existe", id)))?;
+            if atencion.atencion.id_paciente != id_paciente {
+                return Err(AppError::Validation("La atención corresponde a otro paciente".into()));
+            }
+            if !atencion.atencion.firmada() {
+                return Err(AppError::Validation("Sólo se emiten documentos sobre atenciones firmadas".into()));
+            }
+            Some(atencion)
+        }
+        None => None,
+    };
+
+    let tz = zonas::zona_horaria_de(data.zona_repo.as_ref(), paciente.cod_zona.as_deref()).await?;
+    let datos = documentos::datos(tipo, &paciente, &profesional, atencion.as_ref(), campos, tz)?;
+    let contenido = documentos::fusionar(&plantilla.cuerpo, &datos)?;
+
+    Ok(VersionDocumento {
+        id_documento: 0,
+        version: 0,
+        id_plantilla: plantilla.id,
+        id_prof,
+        titulo: plantilla.titulo,
+        huella: documentos::huella(&contenido),
+        contenido,
+        creada_en: tiempo::ahora(),
+    })
+}
+
+pub async fn plantillas<R>(
+    data: web::Data<AppState<R>>,
+) -> Result<HttpResponse, AppError>
+where
+    R: DocumentoRepository + 'static,
+{
+    Ok(HttpResponse::Ok().json(data.documento_repo.plantillas().await?))
+}
+
+/// Publica una nueva versión de la plantilla del tipo. Los documentos ya
+/// emitidos conservan el texto con que se generaron.
+pub async fn crear_plantilla<R>(
+    tipo: web::Path<String>,
+    plantilla: web::Json<PlantillaInput>,
+    data: web::Data<AppState<R>>,
+) -> Result<HttpResponse, AppError>
+where
+    R: DocumentoRepository + 'static,
+{
+    let tipo = tipo_documento(&tipo)?;
+    let input = plantilla.into_inner();
+    let titulo = input.titulo.trim().to_string();
+    if titulo.is_empty() || input.cuerpo.trim().is_empty() {
+        return Err(AppError::Validation("La plantilla requiere título y cuerpo".into()));
+    }
+    documentos::validar_plantilla(&input.cuerpo)?;
+
+    let version = data.documento_repo.crear_plantilla(&Plantilla {
+        id: 0,
+        tipo,
+        version: 0,
+        titulo,
+        cuerpo: input.cuerpo,
+        creada_en: tiempo::ahora(),
+    }).await?;
+    Ok(HttpResponse::Created().json(serde_json::json!({"version": version})))
+}
+
+pub async fn get_by_id<R>(
+    id: web::Path<u32>,
+    data: web::Data<AppState<R>>,
+) -> Result<HttpResponse, AppError>
+where
+    R: DocumentoRepository + 'static,
+{
+    let documento = data.documento_repo.get_by_id(id.into_inner()).await?;
+    match documento {
+        Some(d) => Ok(HttpResponse::Ok().json(d)),
+        None => Err(AppError::NotFound),
+    }
+}
+
+pub async fn search<R>(
+    filtro: web::Query<DocumentoFiltro>,
+    data: web::Data<AppState<R>>,
+) -> Result<HttpResponse, AppError>
+where
+    R: DocumentoRepository + 'static,
+{
+    let documentos = data.documento_repo.search(&filtro).await?;
+    Ok(HttpResponse::Ok().json(documentos))
+}
+
+/// Emite el documento con el siguiente folio del cliente del paciente.
+pub async fn create<R>(
+    documento: web::Json<DocumentoInput>,
+    data: web::Data<AppState<R>>,
+) -> Result<HttpResponse, AppError>
+where
+    R: DocumentoRepository + AtencionRepository + PacienteRepository + ProfesionalRepository + ZonaRepository + 'static,
+{
+    let input = documento.into_inner();
+    let tipo = tipo_documento(&input.tipo)?;
+    let cod_cliente = PacienteRepository::get_by_id(data.paciente_repo.as_ref(), input.id_paciente).await?
+        .ok_or_else(|| AppError::Validation(format!("Paciente {} no existe", input.id_paciente)))?
+        .cod_cliente
+        .ok_or_else(|| AppError::Validation("El paciente no tiene cliente asignado para numerar el folio".into()))?;
+
+    let version = generar(&data, &tipo, input.id_paciente, input.id_prof, input.id_atencion, &input.campos).await?;
+    let documento = DocumentoClinico {
+        id: 0,
+        cod_cliente,
+        folio: 0,
+        tipo,
+        id_paciente: input.id_paciente,
+        id_prof: input.id_prof,
+        id_atencion: input.id_atencion,
+        creado_en: version.creada_en,
+    };
+
+    let (id, folio) = DocumentoRepository::create(data.documento_repo.as_ref(), &documento, &version).await?;
+    Ok(HttpResponse::Created().json(serde_json::json!({"id": id, "folio": folio})))
+}
+
+/// Regenera el documento con la plantilla vigente y datos actualizados,
+/// conservando folio y versiones anteriores.
+pub async fn crear_version<R>(
+    id: web::Path<u32>,
+    version: web::Json<VersionInput>,
+    data: web::Data<AppState<R>>,
+) -> Result<HttpResponse, AppError>
+where
+    R: DocumentoRepository + AtencionRepository + PacienteRepository + ProfesionalRepository + ZonaRepository + 'static,
+{
+    let detalle = DocumentoRepository::get_by_id(data.documento_repo.as_ref(), id.into_inner()).await?
+        .ok_or(AppError::NotFound)?;
+    let d = &detalle.documento;
+    if d.id_prof != version.id_prof {
+        return Err(AppError::Validation("Sólo quien emitió el documento puede generar nuevas versiones".into()));
+    }
+
+    let mut nueva = generar(&data, &d.tipo, d.id_paciente, d.id_prof, d.id_atencion, &version.campos).await?;
+    nueva.id_documento = d.id;
+    let numero = data.documento_repo.agregar_version(&nueva).await?;
+    Ok(HttpResponse::Created().json(serde_json::json!({"version": numero})))
+}
+
+/// PDF de la versión indicada, o de la última.
+pub async fn pdf<R>(
+    id: web::Path<u32>,
+    filtro: web::Query<VersionFiltro>,
+    data: web::Data<AppState<R>>,
+) -> Result<HttpResponse, AppError>
+where
+    R: DocumentoRepository + PacienteRepository + ProfesionalRepository + ZonaRepository + FirmaRepository + 'static,
+{
+    let detalle = DocumentoRepository::get_by_id(data.documento_repo.as_ref(), id.into_inner()).await?
+        .ok_or(AppError::NotFound)?;
+    let version = match filtro.version {
+        Some(n) => detalle.versiones.iter().find(|v| v.version == n),
+        None => detalle.versiones.iter().max_by_key(|v| v.version),
+    }.ok_or(AppError::NotFound)?;
+
+    let d = &detalle.documento;
+    let profesional = ProfesionalRepository::get_by_id(data.profesional_repo.as_ref(), version.id_prof).await?
+        .ok_or_else(|| AppError::Internal(format!("Documento {} sin profesional", d.id)))?;
+    let paciente = PacienteRepository::get_by_id(data.paciente_repo.as_ref(), d.id_paciente).await?
+        .ok_or_else(|| AppError::Internal(format!("Documento {} sin paciente", d.id)))?;
+    let tz = zonas::zona_horaria_de(data.zona_repo.as_ref(), paciente.cod_zona.as_deref()).await?;
+
+    let leyenda = firmas::leyenda(data.firma_repo.as_ref(), FIRMA_DOCUMENTO, d.id, version.version).await?;
+    let bytes = documentos::documento(d, version, &profesional, tz, leyenda.as_deref()).renderizar()?;
+    Ok(HttpResponse::Ok()
+        .content_type("application/pdf")
+        .insert_header((
+            "Content-Disposition",
+            format!("inline; filename=\"{}-{:02}-{:06}-v{}.pdf\"", d.tipo.to_lowercase(), d.cod_cliente, d.folio, version.version),
+        ))
+        .body(bytes))
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_web::{test, App};
+    use crate::{
+        models::{DocumentoDetalle, Paciente, Profesional, ATENCION_FIRMADA},
+        repositories::{fixtures, MockRepository},
+    };
+    use super::*;
+
+    const INTERCONSULTA: &str = "# INTERCONSULTA\n\nPaciente {{paciente.nombre}}, RUT {{paciente.rut}}.\n\
+        Se deriva a {{campos.especialidad_destino}} por {{atencion.motivo}}.";
+
+    async fn app_state() -> AppState<MockRepository> {
+        let medico = Profesional { especialidad: Some("Médico".into()), ..fixtures::profesional(1) };
+        let pacientes = ["0010895960-6", "0012245585-8"]
+            .map(|rut| Paciente { cod_cliente: Some(2), ..fixtures::paciente(rut) })
+            .to_vec();
+        let repo = fixtures::repositorio(vec![medico], pacientes).await;
+        let mut atencion = fixtures::atencion(1, 1, ATENCION_FIRMADA, tiempo::ahora());
+        atencion.atencion.motivo = "Dolor lumbar".into();
+        AtencionRepository::create(&repo, &atencion).await.unwrap();
+        AppState::new(repo)
+    }
+
+    #[actix_web::test]
+    async fn emite_con_folio_por_cliente_y_versiona() {
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(app_state().await))
+                .route("/documentos", web::post().to(create::<MockRepository>))
+                .route("/documentos/plantillas/{tipo}", web::put().to(crear_plantilla::<MockRepository>))
+                .route("/documentos/{id}", web::get().to(get_by_id::<MockRepository>))
+                .route("/documentos/{id}/versiones", web::post().to(crear_version::<MockRepository>))
+                .route("/documentos/{id}/pdf", web::get().to(pdf::<MockRepository>)),
+        )
+        .await;
+
+        let req = test::TestRequest::put()
+            .uri("/documentos/plantillas/interconsulta")
+            .set_json(serde_json::json!({"titulo": "Interconsulta", "cuerpo": "{{paciente.prevision}}"}))
+            .to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), 400);
+        let req = test::TestRequest::put()
+            .uri("/documentos/plantillas/interconsulta")
+            .set_json(serde_json::json!({"titulo": "Interconsulta", "cuerpo": INTERCONSULTA}))
+            .to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), 201);
+
+        let mut documento = serde_json::json!({"tipo": "INTERCONSULTA", "id_paciente": 1, "id_prof": 1, "id_atencion": 1});
+        let req = test::TestRequest::post().uri("/documentos").set_json(&documento).to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), 400);
+        let error: serde_json::Value = test::read_body_json(resp).await;
+        assert!(error["error"].as_str().unwrap().contains("campos.especialidad_destino"));
+
+        documento["campos"] = serde_json::json!({"especialidad_destino": "Traumatología"});
+        let req = test::TestRequest::post().uri("/documentos").set_json(&documento).to_request();
+        let creado: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(creado["folio"], 1);
+
+        let req = test::TestRequest::post().uri("/documentos").set_json(&documento).to_request();
+        let creado: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(creado["folio"], 2);
+
+        // La atención 1 es de otro paciente
+        documento["id_paciente"] = 2.into();
+        let req = test::TestRequest::post().uri("/documentos").set_json(&documento).to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), 400);
+
+        let req = test::TestRequest::post()
+            .uri("/documentos/1/versiones")
+            .set_json(serde_json::json!({"id_prof": 1, "campos": {"especialidad_destino": "Fisiatría"}}))
+            .to_request();
+        let version: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(version["version"], 2);
+
+        let req = test::TestRequest::get().uri("/documentos/1").to_request();
+        let detalle: DocumentoDetalle = test::call_and_read_body_json(&app, req).await;
+        assert!(detalle.versiones[0].contenido.contains("Se deriva a Traumatología por Dolor lumbar."));
+        assert!(detalle.versiones[1].contenido.contains("Fisiatría"));
+
+        let req = test::TestRequest::get().uri("/documentos/1/pdf?version=1").to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), 200);
+        assert!(test::read_body(resp).await.starts_with(b"%PDF"));
+        let req = test::TestRequest::get().uri("/documentos/1/pdf?version=3").to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), 404);
+    }
+}
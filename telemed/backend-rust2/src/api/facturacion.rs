@@ -0,0 +1,284 @@
+use std::collections::HashMap;
+
+use actix_web::{http::header::ContentDisposition, web, HttpResponse};
+use crate::{
+    models::{
+        Atencion, Cargo, CargoFiltro, LoteDetalle, LoteFiltro, LoteInput, Tarifa, TarifaFiltro, TarifaInput,
+        CARGO_FACTURADO, CARGO_PENDIENTE,
+    },
+    facturacion, tiempo,
+    app_state::AppState,
+    error::AppError
+};
+use super::super::repositories::{
+    AgendaRepository, AtencionRepository, FacturacionRepository, PacienteRepository, ZonaRepository,
+};
+use super::zonas;
+
+/// Valoriza la atención firmada y guarda su cargo, reemplazando el anterior
+/// si aún no se factura. La fecha del cargo es el día de la atención en la
+/// zona del paciente.
+pub async fn generar_cargo<R>(data: &AppState<R>, atencion: &Atencion) -> Result<Cargo, AppError>
+where
+    R: FacturacionRepository + PacienteRepository + AgendaRepository + ZonaRepository,
+{
+    let existente = data.facturacion_repo.get_cargo_por_atencion(atencion.id).await?;
+    if existente.as_ref().is_some_and(|c| c.estado == CARGO_FACTURADO) {
+        return Err(AppError::Conflict("El cargo de la atención ya fue facturado".into()));
+    }
+    let paciente = PacienteRepository::get_by_id(data.paciente_repo.as_ref(), atencion.id_paciente).await?
+        .ok_or_else(|| AppError::Validation(format!("Paciente {} no existe", atencion.id_paciente)))?;
+    let modalidad = match atencion.id_cita {
+        Some(id) => data.agenda_repo.get_cita(id).await?.map(|c| c.modalidad),
+        None => None,
+    };
+
+    let tz = zonas::zona_horaria_de(data.zona_repo.as_ref(), paciente.cod_zona.as_deref()).await?;
+    let fecha = tiempo::a_local(atencion.creada_en, tz).date();
+    let tarifas = data.facturacion_repo.tarifas(&TarifaFiltro { vigente_en: Some(fecha), ..Default::default() }).await?;
+
+    let mut cargo = facturacion::valorizar(atencion, &paciente, modalidad.as_deref(), &tarifas, fecha, tiempo::ahora());
+    cargo.id = existente.map_or(0, |c| c.id);
+    cargo.id = data.facturacion_repo.guardar_cargo(&cargo).await?;
+    Ok(cargo)
+}
+
+pub async fn tarifas<R>(
+    filtro: web::Query<TarifaFiltro>,
+    data: web::Data<AppState<R>>,
+) -> Result<HttpResponse, AppError>
+where
+    R: FacturacionRepository + 'static,
+{
+    Ok(HttpResponse::Ok().json(data.facturacion_repo.tarifas(&filtro).await?))
+}
+
+/// Las tarifas no se editan: un cambio de valor es una tarifa nueva desde
+/// la fecha en que rige, y la anterior se cierra con `vigencia_hasta`.
+pub async fn create_tarifa<R>(
+    tarifa: web::Json<TarifaInput>,
+    data: web::Data<AppState<R>>,
+) -> Result<HttpResponse, AppError>
+where
+    R: FacturacionRepository + 'static,
+{
+    let mut tarifa: Tarifa = tarifa.into_inner().try_into()?;
+    tarifa.id = data.facturacion_repo.create_tarifa(&tarifa).await?;
+    Ok(HttpResponse::Created().json(tarifa))
+}
+
+pub async fn cargos<R>(
+    filtro: web::Query<CargoFiltro>,
+    data: web::Data<AppState<R>>,
+) -> Result<HttpResponse, AppError>
+where
+    R: FacturacionRepository + 'static,
+{
+    Ok(HttpResponse::Ok().json(data.facturacion_repo.cargos(&filtro).await?))
+}
+
+/// Vuelve a valorizar una atención, por ejemplo tras cargar la tarifa o la
+/// previsión que faltaba.
+pub async fn recalcular<R>(
+    id_atencion: web::Path<u32>,
+    data: web::Data<AppState<R>>,
+) -> Result<HttpResponse, AppError>
+where
+    R: FacturacionRepository + AtencionRepository + PacienteRepository + AgendaRepository + ZonaRepository + 'static,
+{
+    let detalle = AtencionRepository::get_by_id(data.atencion_repo.as_ref(), id_atencion.into_inner()).await?
+        .ok_or(AppError::NotFound)?;
+    if !detalle.atencion.firmada() {
+        return Err(AppError::Validation("Sólo se cobran atenciones firmadas".into()));
+    }
+    Ok(HttpResponse::Ok().json(generar_cargo(&data, &detalle.atencion).await?))
+}
+
+pub async fn lotes<R>(
+    filtro: web::Query<LoteFiltro>,
+    data: web::Data<AppState<R>>,
+) -> Result<HttpResponse, AppError>
+where
+    R: FacturacionRepository + 'static,
+{
+    Ok(HttpResponse::Ok().json(data.facturacion_repo.lotes(&filtro).await?))
+}
+
+/// Agrupa los cargos pendientes del período para el pagador. Los cargos
+/// observados quedan fuera hasta que se corrijan y entran en un lote
+/// posterior del mismo período.
+pub async fn crear_lote<R>(
+    lote: web::Json<LoteInput>,
+    data: web::Data<AppState<R>>,
+) -> Result<HttpResponse, AppError>
+where
+    R: FacturacionRepository + 'static,
+{
+    let mut input = lote.into_inner();
+    let (desde, hasta) = facturacion::validar_lote(&mut input)?;
+    let filtro = CargoFiltro {
+        estado: Some(CARGO_PENDIENTE.into()),
+        pagador: Some(input.pagador.clone()),
+        cod_prevision: input.cod_prevision,
+        cod_cliente: input.cod_cliente,
+        desde: Some(desde),
+        hasta: Some(hasta),
+        ..Default::default()
+    };
+    let cargos = data.facturacion_repo.cargos(&filtro).await?;
+    if cargos.is_empty() {
+        return Err(AppError::Validation(format!("No hay cargos pendientes de {} en {}", input.pagador, input.periodo)));
+    }
+
+    let mut lote = facturacion::lote(&input, &cargos, tiempo::ahora());
+    let ids: Vec<u32> = cargos.iter().map(|c| c.id).collect();
+    lote.id = data.facturacion_repo.crear_lote(&lote, &ids).await?;
+    let cargos = cargos
+        .into_iter()
+        .map(|c| Cargo { estado: CARGO_FACTURADO.into(), id_lote: Some(lote.id), ..c })
+        .collect();
+    Ok(HttpResponse::Created().json(LoteDetalle { lote, cargos }))
+}
+
+async fn lote_detalle<R>(data: &AppState<R>, id: u32) -> Result<LoteDetalle, AppError>
+where
+    R: FacturacionRepository,
+{
+    let lote = data.facturacion_repo.get_lote(id).await?.ok_or(AppError::NotFound)?;
+    let cargos = data.facturacion_repo.cargos(&CargoFiltro { id_lote: Some(id), ..Default::default() }).await?;
+    Ok(LoteDetalle { lote, cargos })
+}
+
+pub async fn get_lote<R>(
+    id: web::Path<u32>,
+    data: web::Data<AppState<R>>,
+) -> Result<HttpResponse, AppError>
+where
+    R: FacturacionRepository + 'static,
+{
+    Ok(HttpResponse::Ok().json(lote_detalle(&data, id.into_inner()).await?))
+}
+
+/// Archivo CSV del lote para conciliar con lo que liquide el pagador.
+pub async fn exportar<R>(
+    id: web::Path<u32>,
+    data: web::Data<AppState<R>>,
+) -> Result<HttpResponse, AppError>
+where
+    R: FacturacionRepository + PacienteRepository + 'static,
+{
+    let LoteDetalle { lote, cargos } = lote_detalle(&data, id.into_inner()).await?;
+    let mut ruts = HashMap::new();
+    for cargo in &cargos {
+        if !ruts.contains_key(&cargo.id_paciente)
+            && let Some(paciente) = PacienteRepository::get_by_id(data.paciente_repo.as_ref(), cargo.id_paciente).await?
+        {
+            ruts.insert(cargo.id_paciente, paciente.rut);
+        }
+    }
+
+    Ok(HttpResponse::Ok()
+        .content_type("text/csv; charset=utf-8")
+        .insert_header(ContentDisposition::attachment(format!("lote-{}-{}.csv", lote.id, lote.periodo)))
+        .body(facturacion::exportar(&lote, &cargos, &ruts)))
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_web::{test, App};
+    use chrono::{Duration, NaiveDate};
+    use crate::{
+        models::{Paciente, ATENCION_BORRADOR, ATENCION_FIRMADA, CARGO_OBSERVADO},
+        repositories::{fixtures, MockRepository},
+    };
+    use super::*;
+
+    async fn app_state() -> AppState<MockRepository> {
+        let pacientes = [("0010895960-6", Some(10), None), ("0012602780-K", Some(10), Some(2)), ("0007654321-6", None, None)]
+            .map(|(rut, cod_prevision, cod_cliente)| Paciente { cod_prevision, cod_cliente, ..fixtures::paciente(rut) })
+            .to_vec();
+        let repo = fixtures::repositorio(Vec::new(), pacientes).await;
+        // Las atenciones son de hace una hora, para que caigan en el mes en curso
+        let creada_en = tiempo::ahora() - Duration::hours(1);
+        for (id_paciente, estado) in [(1, ATENCION_FIRMADA), (2, ATENCION_FIRMADA), (3, ATENCION_FIRMADA), (1, ATENCION_BORRADOR)] {
+            AtencionRepository::create(&repo, &fixtures::atencion(id_paciente, 1, estado, creada_en)).await.unwrap();
+        }
+        AppState::new(repo)
+    }
+
+    #[actix_web::test]
+    async fn cobra_atenciones_y_factura_por_lote() {
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(app_state().await))
+                .route("/facturacion/tarifas", web::post().to(create_tarifa::<MockRepository>))
+                .route("/facturacion/cargos", web::get().to(cargos::<MockRepository>))
+                .route("/facturacion/lotes", web::post().to(crear_lote::<MockRepository>))
+                .route("/facturacion/lotes/{id}", web::get().to(get_lote::<MockRepository>))
+                .route("/facturacion/lotes/{id}/exportacion", web::get().to(exportar::<MockRepository>))
+                .route("/atenciones/{id}/cargo", web::post().to(recalcular::<MockRepository>)),
+        )
+        .await;
+        let cargo = async |id_atencion: u32| {
+            let req = test::TestRequest::post().uri(&format!("/atenciones/{}/cargo", id_atencion)).to_request();
+            test::call_service(&app, req).await
+        };
+
+        // Sin tarifas las atenciones quedan observadas
+        let resp = cargo(1).await;
+        assert_eq!(resp.status(), 200);
+        let observado: Cargo = test::read_body_json(resp).await;
+        assert_eq!(observado.estado, CARGO_OBSERVADO);
+        assert_eq!(cargo(4).await.status(), 400);
+
+        let desde = NaiveDate::from_ymd_opt(2020, 1, 1).unwrap();
+        let req = test::TestRequest::post()
+            .uri("/facturacion/tarifas")
+            .set_json(serde_json::json!({
+                "codigo_prestacion": "0101001", "descripcion": "Consulta médica", "valor": 20000,
+                "cod_prevision": 10, "cod_cliente": 2, "vigencia_desde": desde,
+            }))
+            .to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), 400);
+        for tarifa in [
+            serde_json::json!({"codigo_prestacion": "0101001", "descripcion": "Consulta médica", "valor": 20000, "cobertura": 60, "cod_prevision": 10, "vigencia_desde": desde}),
+            serde_json::json!({"codigo_prestacion": "PROG-01", "descripcion": "Control programa", "valor": 15000, "cod_cliente": 2, "vigencia_desde": desde}),
+        ] {
+            let req = test::TestRequest::post().uri("/facturacion/tarifas").set_json(tarifa).to_request();
+            assert_eq!(test::call_service(&app, req).await.status(), 201);
+        }
+
+        // Recalcular reemplaza el cargo observado
+        let pendiente: Cargo = test::read_body_json(cargo(1).await).await;
+        assert_eq!((pendiente.id, pendiente.estado.as_str()), (observado.id, CARGO_PENDIENTE));
+        assert_eq!((pendiente.monto_pagador, pendiente.copago), (12_000, 8_000));
+        let cliente: Cargo = test::read_body_json(cargo(2).await).await;
+        assert_eq!((cliente.codigo_prestacion.as_deref(), cliente.copago), (Some("PROG-01"), 0));
+        let sin_prevision: Cargo = test::read_body_json(cargo(3).await).await;
+        assert_eq!(sin_prevision.estado, CARGO_OBSERVADO);
+
+        let periodo = pendiente.fecha.format("%Y-%m").to_string();
+        let lote = serde_json::json!({"periodo": periodo, "pagador": "prevision", "cod_prevision": 10});
+        let req = test::TestRequest::post().uri("/facturacion/lotes").set_json(&lote).to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), 201);
+        let detalle: LoteDetalle = test::read_body_json(resp).await;
+        assert_eq!((detalle.lote.cantidad, detalle.lote.total_valor, detalle.lote.total_copago), (1, 20_000, 8_000));
+        assert_eq!(detalle.cargos[0].id_lote, Some(detalle.lote.id));
+
+        // Ya no quedan pendientes de esa previsión y el cargo facturado no cambia
+        let req = test::TestRequest::post().uri("/facturacion/lotes").set_json(&lote).to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), 400);
+        assert_eq!(cargo(1).await.status(), 409);
+
+        let req = test::TestRequest::get().uri("/facturacion/cargos?estado=pendiente").to_request();
+        let pendientes: Vec<Cargo> = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(pendientes.iter().map(|c| c.id_atencion).collect::<Vec<_>>(), vec![2]);
+
+        let req = test::TestRequest::get().uri(&format!("/facturacion/lotes/{}/exportacion", detalle.lote.id)).to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.headers().get("content-type").unwrap(), "text/csv; charset=utf-8");
+        let csv = String::from_utf8(test::read_body(resp).await.to_vec()).unwrap();
+        assert!(csv.lines().nth(1).unwrap().contains(";0010895960-6;0101001;20000;12000;8000"));
+    }
+}
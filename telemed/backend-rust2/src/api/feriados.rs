@@ -0,0 +1,102 @@
+use actix_web::{web, HttpResponse};
+use chrono::NaiveDate;
+use crate::{
+    feriados,
+    models::{Feriado, FeriadoInput, FeriadoFiltro},
+    app_state::AppState,
+    error::AppError
+};
+use super::super::repositories::{FeriadoRepository, ZonaRepository};
+
+/// Feriados legales y administrados del año, para la zona indicada.
+pub async fn search<R>(
+    filtro: web::Query<FeriadoFiltro>,
+    data: web::Data<AppState<R>>,
+) -> Result<HttpResponse, AppError>
+where
+    R: FeriadoRepository + 'static,
+{
+    let desde = NaiveDate::from_ymd_opt(filtro.anio, 1, 1)
+        .ok_or_else(|| AppError::Validation(format!("Año inválido: {}", filtro.anio)))?;
+    let hasta = NaiveDate::from_ymd_opt(filtro.anio, 12, 31)
+        .ok_or_else(|| AppError::Validation(format!("Año inválido: {}", filtro.anio)))?;
+
+    let extras = data.feriado_repo.search(desde, hasta).await?;
+    Ok(HttpResponse::Ok().json(feriados::del_anio(filtro.anio, filtro.cod_zona.as_deref(), &extras)))
+}
+
+pub async fn create<R>(
+    feriado: web::Json<FeriadoInput>,
+    data: web::Data<AppState<R>>,
+) -> Result<HttpResponse, AppError>
+where
+    R: FeriadoRepository + ZonaRepository + 'static,
+{
+    let feriado: Feriado = feriado.into_inner().try_into()?;
+    if let Some(cod_zona) = &feriado.cod_zona
+        && data.zona_repo.get_by_id(cod_zona).await?.is_none()
+    {
+        return Err(AppError::Validation(format!("Zona {} no existe", cod_zona)));
+    }
+    let id = data.feriado_repo.create(&feriado).await?;
+    Ok(HttpResponse::Created().json(serde_json::json!({"id": id})))
+}
+
+pub async fn delete<R>(
+    id: web::Path<u32>,
+    data: web::Data<AppState<R>>,
+) -> Result<HttpResponse, AppError>
+where
+    R: FeriadoRepository + 'static,
+{
+    let deleted = data.feriado_repo.delete(id.into_inner()).await?;
+    if deleted {
+        Ok(HttpResponse::NoContent().finish())
+    } else {
+        Err(AppError::NotFound)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_web::{test, web, App};
+    use crate::{app_state::AppState, models::Zona, repositories::MockRepository};
+    use super::*;
+
+    #[actix_web::test]
+    async fn feriado_administrado_se_suma_a_los_legales_de_su_zona() {
+        let repo = MockRepository::default().with_zonas(vec![Zona {
+            cod_zona: "079".into(),
+            nom_zona: "SANTIAGO".into(),
+            orden_zona: None,
+            zona_horaria: "America/Santiago".into(),
+        }]);
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(AppState::new(repo)))
+                .route("/feriados", web::get().to(search::<MockRepository>))
+                .route("/feriados", web::post().to(create::<MockRepository>)),
+        )
+        .await;
+
+        let req = test::TestRequest::post()
+            .uri("/feriados")
+            .set_json(serde_json::json!({ "fecha": "2025-11-16", "nombre": "Elecciones", "cod_zona": "001" }))
+            .to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), 400);
+
+        let req = test::TestRequest::post()
+            .uri("/feriados")
+            .set_json(serde_json::json!({ "fecha": "2025-11-16", "nombre": "Elecciones", "cod_zona": "079" }))
+            .to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), 201);
+
+        let req = test::TestRequest::get().uri("/feriados?anio=2025&cod_zona=079").to_request();
+        let en_zona: Vec<Feriado> = test::call_and_read_body_json(&app, req).await;
+        let req = test::TestRequest::get().uri("/feriados?anio=2025").to_request();
+        let nacionales: Vec<Feriado> = test::call_and_read_body_json(&app, req).await;
+
+        assert_eq!(en_zona.len(), nacionales.len() + 1);
+        assert!(en_zona.iter().any(|f| f.nombre == "Elecciones" && f.id > 0));
+    }
+}
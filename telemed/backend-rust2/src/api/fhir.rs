@@ -0,0 +1,377 @@
+use actix_web::{web, HttpRequest, HttpResponse};
+use serde_json::Value;
+use crate::{
+    consentimientos, derivaciones,
+    fhir::{self, Recurso},
+    models::{AtencionDetalle, ConsultaFhir, PaginaFhir, CONSENTIMIENTO_COMPARTIR},
+    tiempo,
+    app_state::AppState,
+    error::AppError
+};
+use super::super::repositories::{
+    AgendaRepository, AtencionRepository, Cie10Repository, ConsentimientoRepository, DerivacionRepository, FhirRepository,
+    PacienteRepository,
+};
+
+/// URL de `/fhir` tal como la ve el cliente, para los `fullUrl` y enlaces.
+fn base(req: &HttpRequest) -> String {
+    let conexion = req.connection_info();
+    format!("{}://{}/fhir", conexion.scheme(), conexion.host())
+}
+
+/// Parámetros en el orden recibido; FHIR repite nombres (`_lastUpdated`).
+fn parametros(req: &HttpRequest) -> Result<Vec<(String, String)>, AppError> {
+    web::Query::<Vec<(String, String)>>::from_query(req.query_string())
+        .map(|q| q.into_inner())
+        .map_err(|e| AppError::Validation(format!("Parámetros inválidos: {}", e)))
+}
+
+fn responder(recurso: Value) -> HttpResponse {
+    HttpResponse::Ok().content_type(fhir::CONTENT_TYPE).json(recurso)
+}
+
+/// Consulta de lectura por id lógico.
+fn por_id(id: String) -> ConsultaFhir {
+    ConsultaFhir { ids: Some(vec![id]), cantidad: 1, ..Default::default() }
+}
+
+/// Las búsquedas sólo entregan datos de pacientes que consintieron
+/// compartirlos con el cliente.
+fn compartidos(consulta: &ConsultaFhir) -> ConsultaFhir {
+    ConsultaFhir { consentimiento: Some(CONSENTIMIENTO_COMPARTIR.into()), ..consulta.clone() }
+}
+
+fn primero(recursos: Vec<Value>) -> Result<HttpResponse, AppError> {
+    recursos.into_iter().next().map(responder).ok_or(AppError::NotFound)
+}
+
+async fn buscar(
+    req: &HttpRequest,
+    recurso: Recurso,
+    consultar: impl AsyncFnOnce(&ConsultaFhir) -> Result<(u32, Vec<Value>), AppError>,
+) -> Result<HttpResponse, AppError> {
+    let params = parametros(req)?;
+    let consulta = fhir::interpretar(recurso, &params)?;
+    let (total, recursos) = consultar(&consulta).await?;
+    Ok(responder(fhir::bundle(&base(req), recurso, &params, &consulta, total, recursos)?))
+}
+
+async fn pacientes_fhir<R: FhirRepository>(repo: &R, consulta: &ConsultaFhir) -> Result<(u32, Vec<Value>), AppError> {
+    let pagina = repo.fhir_pacientes(consulta).await?;
+    Ok((pagina.total, pagina.registros.iter().map(|r| fhir::paciente(&r.registro, r.actualizado_en)).collect()))
+}
+
+async fn profesionales_fhir<R: FhirRepository>(repo: &R, consulta: &ConsultaFhir) -> Result<(u32, Vec<Value>), AppError> {
+    let pagina = repo.fhir_profesionales(consulta).await?;
+    Ok((pagina.total, pagina.registros.iter().map(|r| fhir::profesional(&r.registro, r.actualizado_en)).collect()))
+}
+
+async fn organizaciones_fhir<R: FhirRepository>(repo: &R, consulta: &ConsultaFhir) -> Result<(u32, Vec<Value>), AppError> {
+    let pagina = repo.fhir_zonas(consulta).await?;
+    Ok((pagina.total, pagina.registros.iter().map(|r| fhir::organizacion(&r.registro, r.actualizado_en)).collect()))
+}
+
+/// Los encuentros toman la modalidad y la zona de la cita de origen.
+async fn encuentros_fhir<R>(data: &AppState<R>, consulta: &ConsultaFhir) -> Result<(u32, Vec<Value>), AppError>
+where
+    R: FhirRepository + AgendaRepository,
+{
+    let pagina: PaginaFhir<AtencionDetalle> = data.fhir_repo.fhir_atenciones(consulta).await?;
+    let mut recursos = Vec::with_capacity(pagina.registros.len());
+    for r in &pagina.registros {
+        let cita = match r.registro.atencion.id_cita {
+            Some(id) => data.agenda_repo.get_cita(id).await?,
+            None => None,
+        };
+        recursos.push(fhir::encuentro(&r.registro, cita.as_ref(), r.actualizado_en));
+    }
+    Ok((pagina.total, recursos))
+}
+
+pub async fn search_pacientes<R>(req: HttpRequest, data: web::Data<AppState<R>>) -> Result<HttpResponse, AppError>
+where
+    R: FhirRepository + 'static,
+{
+    buscar(&req, Recurso::Paciente, async |c: &ConsultaFhir| pacientes_fhir(data.fhir_repo.as_ref(), &compartidos(c)).await).await
+}
+
+/// A diferencia de la búsqueda, leer un paciente sin consentimiento
+/// responde 403.
+pub async fn get_paciente<R>(id: web::Path<String>, data: web::Data<AppState<R>>) -> Result<HttpResponse, AppError>
+where
+    R: FhirRepository + PacienteRepository + ConsentimientoRepository + 'static,
+{
+    let id = id.into_inner();
+    let id_paciente: u32 = id.parse().map_err(|_| AppError::NotFound)?;
+    PacienteRepository::get_by_id(data.paciente_repo.as_ref(), id_paciente).await?.ok_or(AppError::NotFound)?;
+    consentimientos::exigir(data.consentimiento_repo.as_ref(), id_paciente, CONSENTIMIENTO_COMPARTIR).await?;
+    primero(pacientes_fhir(data.fhir_repo.as_ref(), &por_id(id)).await?.1)
+}
+
+pub async fn search_profesionales<R>(req: HttpRequest, data: web::Data<AppState<R>>) -> Result<HttpResponse, AppError>
+where
+    R: FhirRepository + 'static,
+{
+    buscar(&req, Recurso::Profesional, async |c: &ConsultaFhir| profesionales_fhir(data.fhir_repo.as_ref(), c).await).await
+}
+
+pub async fn get_profesional<R>(id: web::Path<String>, data: web::Data<AppState<R>>) -> Result<HttpResponse, AppError>
+where
+    R: FhirRepository + 'static,
+{
+    primero(profesionales_fhir(data.fhir_repo.as_ref(), &por_id(id.into_inner())).await?.1)
+}
+
+pub async fn search_encuentros<R>(req: HttpRequest, data: web::Data<AppState<R>>) -> Result<HttpResponse, AppError>
+where
+    R: FhirRepository + AgendaRepository + 'static,
+{
+    buscar(&req, Recurso::Encuentro, async |c: &ConsultaFhir| encuentros_fhir(&data, &compartidos(c)).await).await
+}
+
+pub async fn get_encuentro<R>(id: web::Path<String>, data: web::Data<AppState<R>>) -> Result<HttpResponse, AppError>
+where
+    R: FhirRepository + AgendaRepository + AtencionRepository + ConsentimientoRepository + 'static,
+{
+    let id = id.into_inner();
+    let id_atencion: u32 = id.parse().map_err(|_| AppError::NotFound)?;
+    let atencion = AtencionRepository::get_by_id(data.atencion_repo.as_ref(), id_atencion).await?.ok_or(AppError::NotFound)?;
+    consentimientos::exigir(data.consentimiento_repo.as_ref(), atencion.atencion.id_paciente, CONSENTIMIENTO_COMPARTIR).await?;
+    primero(encuentros_fhir(&data, &por_id(id)).await?.1)
+}
+
+pub async fn search_organizaciones<R>(req: HttpRequest, data: web::Data<AppState<R>>) -> Result<HttpResponse, AppError>
+where
+    R: FhirRepository + 'static,
+{
+    buscar(&req, Recurso::Organizacion, async |c: &ConsultaFhir| organizaciones_fhir(data.fhir_repo.as_ref(), c).await).await
+}
+
+pub async fn get_organizacion<R>(id: web::Path<String>, data: web::Data<AppState<R>>) -> Result<HttpResponse, AppError>
+where
+    R: FhirRepository + 'static,
+{
+    primero(organizaciones_fhir(data.fhir_repo.as_ref(), &por_id(id.into_inner())).await?.1)
+}
+
+/// Recibe pacientes derivados como Bundle `transaction` con Patient,
+/// Condition y ServiceRequest. Es todo o nada: si alguna entrada es inválida
+/// responde 400 con un OperationOutcome que señala cada problema, y 409 si
+/// una ServiceRequest ya se recibió para otro paciente.
+pub async fn transaccion<R>(
+    bundle: web::Json<Value>,
+    data: web::Data<AppState<R>>,
+) -> Result<HttpResponse, AppError>
+where
+    R: DerivacionRepository + PacienteRepository + Cie10Repository + 'static,
+{
+    let catalogo = data.cie10.obtener(data.cie10_repo.as_ref()).await?;
+    let lote = match derivaciones::interpretar(&bundle, &catalogo, tiempo::ahora()) {
+        Ok(lote) => lote,
+        Err(problemas) => {
+            return Ok(HttpResponse::BadRequest()
+                .content_type(fhir::CONTENT_TYPE)
+                .json(derivaciones::resultado_operacion(&problemas)));
+        }
+    };
+
+    let mut importacion = lote.importacion;
+    for paciente in importacion.pacientes.iter_mut() {
+        if let Some(existente) = PacienteRepository::get_by_rut(data.paciente_repo.as_ref(), &paciente.rut).await? {
+            *paciente = derivaciones::combinar(paciente.clone(), &existente);
+        }
+    }
+    let resultado = data.derivacion_repo.importar_derivaciones(&importacion).await?;
+    if !resultado.rechazadas.is_empty() {
+        return Ok(HttpResponse::Conflict()
+            .content_type(fhir::CONTENT_TYPE)
+            .json(derivaciones::resultado_operacion(&derivaciones::rechazos(&lote.destinos, &resultado.rechazadas))));
+    }
+    Ok(responder(derivaciones::respuesta(&lote.destinos, &resultado)))
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_web::{test, App};
+    use serde_json::json;
+    use crate::{
+        models::{Cie10, Paciente},
+        repositories::{fixtures, MockRepository},
+    };
+    use super::*;
+
+    fn paciente(rut: &str, nombres: &str) -> Paciente {
+        Paciente {
+            nombres: nombres.into(),
+            ap_paterno: "PÉREZ".into(),
+            ap_materno: Some("SOTO".into()),
+            sexo: Some("F".into()),
+            cod_zona: Some("01".into()),
+            telefonos: Some("912345678 / 221234567".into()),
+            ..fixtures::paciente(rut)
+        }
+    }
+
+    #[actix_web::test]
+    async fn busca_pagina_y_lee() {
+        let repo = MockRepository::default().with_profesionales(vec![fixtures::profesional(1)]);
+        for (rut, nombres) in [("0011111111-1", "ANA"), ("0010895960-6", "BEATRIZ"), ("0012602780-K", "CAROLA"), ("0016354813-5", "DANIELA")] {
+            let id = PacienteRepository::create(&repo, &paciente(rut, nombres)).await.unwrap();
+            if nombres != "DANIELA" {
+                ConsentimientoRepository::registrar_consentimiento(&repo, &consentimientos::otorgado(id, CONSENTIMIENTO_COMPARTIR)).await.unwrap();
+            }
+        }
+        let data = AppState::new(repo);
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(data.clone()))
+                .route("/fhir/Patient", web::get().to(search_pacientes::<MockRepository>))
+                .route("/fhir/Patient/{id}", web::get().to(get_paciente::<MockRepository>))
+                .route("/fhir/Practitioner", web::get().to(search_profesionales::<MockRepository>))
+        ).await;
+        let get = |uri: &str| test::TestRequest::get().uri(uri).to_request();
+
+        let resp = test::call_service(&app, get("/fhir/Patient?_count=2")).await;
+        assert_eq!(resp.status(), 200);
+        assert_eq!(resp.headers().get("content-type").unwrap(), fhir::CONTENT_TYPE);
+        let bundle: Value = test::read_body_json(resp).await;
+        assert_eq!((bundle["total"].as_u64(), bundle["entry"].as_array().unwrap().len()), (Some(3), 2));
+        assert_eq!(bundle["link"][1]["relation"], "next");
+        assert!(bundle["link"][1]["url"].as_str().unwrap().ends_with("/fhir/Patient?_count=2&_offset=2"));
+
+        let resp = test::call_service(&app, get("/fhir/Patient?identifier=http://regcivil.cl/Validacion/RUN|10.895.960-6")).await;
+        let bundle: Value = test::read_body_json(resp).await;
+        let encontrado = &bundle["entry"][0]["resource"];
+        assert_eq!(bundle["total"], 1);
+        assert_eq!(encontrado["identifier"][0]["value"], "10895960-6");
+        assert_eq!(encontrado["name"][0]["family"], "PÉREZ SOTO");
+        assert_eq!(encontrado["gender"], "female");
+        assert_eq!(encontrado["telecom"].as_array().unwrap().len(), 2);
+        assert_eq!(encontrado["managingOrganization"]["reference"], "Organization/01");
+
+        let resp = test::call_service(&app, get("/fhir/Patient?_lastUpdated=lt2000-01-01")).await;
+        let bundle: Value = test::read_body_json(resp).await;
+        assert_eq!(bundle["total"], 0);
+
+        let resp = test::call_service(&app, get("/fhir/Patient/3")).await;
+        let leido: Value = test::read_body_json(resp).await;
+        assert_eq!(leido["name"][0]["given"][0], "CAROLA");
+        assert_eq!(test::call_service(&app, get("/fhir/Patient/9")).await.status(), 404);
+        // DANIELA no consintió compartir sus datos: no aparece y no se puede leer
+        assert_eq!(test::call_service(&app, get("/fhir/Patient/4")).await.status(), 403);
+
+        let resp = test::call_service(&app, get("/fhir/Practitioner?identifier=https://rnpi.superdesalud.gob.cl|212402")).await;
+        let bundle: Value = test::read_body_json(resp).await;
+        let identificadores = &bundle["entry"][0]["resource"]["identifier"];
+        assert_eq!((identificadores[0]["value"].as_str(), identificadores[1]["value"].as_str()), (Some("16354813-5"), Some("212402")));
+    }
+
+    fn derivacion(rut: &str, referencia: &str) -> Value {
+        json!({
+            "resourceType": "Bundle",
+            "type": "transaction",
+            "entry": [
+                {
+                    "fullUrl": "urn:uuid:p",
+                    "resource": {
+                        "resourceType": "Patient",
+                        "identifier": [{ "system": fhir::SISTEMA_RUN, "value": rut }],
+                        "name": [{ "family": "Pérez Soto", "given": ["Ana"] }],
+                    },
+                    "request": { "method": "PUT", "url": format!("Patient?identifier={}|{}", fhir::SISTEMA_RUN, rut) },
+                },
+                {
+                    "fullUrl": "urn:uuid:c",
+                    "resource": {
+                        "resourceType": "Condition",
+                        "subject": { "reference": "urn:uuid:p" },
+                        "code": { "coding": [{ "system": fhir::SISTEMA_CIE10, "code": "I10" }] },
+                    },
+                    "request": { "method": "POST", "url": "Condition" },
+                },
+                {
+                    "fullUrl": "urn:uuid:s",
+                    "resource": {
+                        "resourceType": "ServiceRequest",
+                        "status": "active",
+                        "identifier": [{ "system": "urn:hospital", "value": referencia }],
+                        "code": { "text": "Cardiología" },
+                        "subject": { "reference": "urn:uuid:p" },
+                    },
+                    "request": { "method": "POST", "url": "ServiceRequest" },
+                },
+            ],
+        })
+    }
+
+    #[actix_web::test]
+    async fn importa_derivaciones() {
+        let repo = MockRepository::default();
+        PacienteRepository::create(&repo, &paciente("0011111111-1", "ANA")).await.unwrap();
+        Cie10Repository::importar(&repo, "2019", chrono::NaiveDate::MIN, &[
+            Cie10 { codigo: "I10".into(), descripcion: "Hipertensión esencial (primaria)".into() },
+        ]).await.unwrap();
+        let data = AppState::new(repo);
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(data.clone()))
+                .route("/fhir", web::post().to(transaccion::<MockRepository>))
+        ).await;
+        let post = |bundle: Value| {
+            test::TestRequest::post()
+                .uri("/fhir")
+                .insert_header(("content-type", fhir::CONTENT_TYPE))
+                .set_payload(bundle.to_string())
+                .to_request()
+        };
+        let estados = |respuesta: &Value| -> Vec<(String, String)> {
+            respuesta["entry"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .map(|e| (e["response"]["status"].as_str().unwrap().into(), e["response"]["location"].as_str().unwrap_or_default().into()))
+                .collect()
+        };
+
+        // Paciente existente: se actualiza conservando su zona
+        let resp = test::call_service(&app, post(derivacion("11.111.111-1", "D-1"))).await;
+        assert_eq!(resp.status(), 200);
+        let respuesta: Value = test::read_body_json(resp).await;
+        assert_eq!(respuesta["type"], "transaction-response");
+        assert_eq!(estados(&respuesta), vec![
+            ("200 OK".into(), "Patient/1".into()),
+            ("201 Created".into(), String::new()),
+            ("201 Created".into(), "ServiceRequest/1".into()),
+        ]);
+        let ana = PacienteRepository::get_by_id(data.paciente_repo.as_ref(), 1).await.unwrap().unwrap();
+        assert_eq!((ana.ap_materno.as_deref(), ana.cod_zona.as_deref()), (Some("SOTO"), Some("01")));
+        let d = data.derivacion_repo.get_derivacion(1).await.unwrap().unwrap();
+        assert_eq!((d.derivacion.id_paciente, d.diagnosticos[0].descripcion.as_str()), (1, "Hipertensión esencial (primaria)"));
+
+        // Reenvío del mismo Bundle y paciente nuevo
+        let resp = test::call_service(&app, post(derivacion("11.111.111-1", "D-1"))).await;
+        let respuesta: Value = test::read_body_json(resp).await;
+        assert_eq!(estados(&respuesta)[2], ("200 OK".into(), "ServiceRequest/1".into()));
+        let resp = test::call_service(&app, post(derivacion("10.895.960-6", "D-2"))).await;
+        let respuesta: Value = test::read_body_json(resp).await;
+        assert_eq!(estados(&respuesta)[0], ("201 Created".into(), "Patient/2".into()));
+
+        // La misma referencia para otro paciente: se rechaza y no se guarda nada
+        let resp = test::call_service(&app, post(derivacion("10.895.960-6", "D-1"))).await;
+        assert_eq!(resp.status(), 409);
+        let resultado: Value = test::read_body_json(resp).await;
+        assert_eq!(resultado["issue"][0]["expression"][0], "Bundle.entry[2]");
+        assert_eq!(data.derivacion_repo.get_derivacion(1).await.unwrap().unwrap().derivacion.id_paciente, 1);
+        assert!(data.derivacion_repo.get_derivacion(3).await.unwrap().is_none());
+
+        // Inválido: nada se guarda y se indica la entrada
+        let mut invalido = derivacion("22.222.222-2", "D-3");
+        invalido["entry"][1]["resource"]["code"]["coding"][0]["code"] = json!("J20.9");
+        let resp = test::call_service(&app, post(invalido)).await;
+        assert_eq!(resp.status(), 400);
+        let resultado: Value = test::read_body_json(resp).await;
+        assert_eq!(resultado["resourceType"], "OperationOutcome");
+        assert_eq!(resultado["issue"][0]["expression"][0], "Bundle.entry[1]");
+        assert!(PacienteRepository::get_by_rut(data.paciente_repo.as_ref(), "0022222222-2").await.unwrap().is_none());
+    }
+}
@@ -0,0 +1,286 @@
+use actix_web::{web, HttpResponse};
+use crate::{
+    models::{
+        tipo_firma, Firma, FirmaFiltro, SolicitudFirma, VerificacionFirma, FIRMA_DOCUMENTO, FIRMA_RECETA, RECETA_ANULADA,
+    },
+    documentos, firma, recetas, tiempo,
+    app_state::AppState,
+    error::AppError
+};
+use super::super::repositories::{DocumentoRepository, FirmaRepository, ProfesionalRepository, RecetaRepository};
+
+/// Contenido canónico actual de lo firmable, con la versión y su autor.
+/// `None` si el documento (o esa versión) no existe.
+async fn contenido<R>(
+    data: &AppState<R>,
+    tipo: &str,
+    id_documento: u32,
+    version: Option<u16>,
+) -> Result<Option<(String, u16, u32)>, AppError>
+where
+    R: RecetaRepository + DocumentoRepository,
+{
+    if tipo == FIRMA_RECETA {
+        let detalle = RecetaRepository::get_by_id(data.receta_repo.as_ref(), id_documento).await?;
+        return Ok(detalle.map(|d| (recetas::contenido(&d), 0, d.receta.id_prof)));
+    }
+
+    let Some(detalle) = DocumentoRepository::get_by_id(data.documento_repo.as_ref(), id_documento).await? else {
+        return Ok(None);
+    };
+    let version = match version {
+        Some(n) => detalle.versiones.iter().find(|v| v.version == n),
+        None => detalle.versiones.iter().max_by_key(|v| v.version),
+    };
+    Ok(version.map(|v| (documentos::contenido_canonico(&detalle.documento, v), v.version, v.id_prof)))
+}
+
+/// Leyenda de firma para el pie del PDF, si esa versión está firmada.
+pub async fn leyenda<R>(repo: &R, tipo: &str, id_documento: u32, version: u16) -> Result<Option<String>, AppError>
+where
+    R: FirmaRepository,
+{
+    let filtro = FirmaFiltro { tipo: Some(tipo.into()), id_documento: Some(id_documento), id_prof: None };
+    Ok(repo.search(&filtro).await?
+        .into_iter()
+        .find(|f| f.version == version)
+        .map(|f| format!(
+            "Firma electrónica N° {} (RUT {}, RNPI {}) · verifíquela en /api/firmas/{}/verificar",
+            f.id, f.rut_firmante.trim_start_matches('0'), f.rnpi_firmante, f.id,
+        )))
+}
+
+pub async fn get_by_id<R>(
+    id: web::Path<u32>,
+    data: web::Data<AppState<R>>,
+) -> Result<HttpResponse, AppError>
+where
+    R: FirmaRepository + 'static,
+{
+    let firma = data.firma_repo.get_by_id(id.into_inner()).await?;
+    match firma {
+        Some(f) => Ok(HttpResponse::Ok().json(f)),
+        None => Err(AppError::NotFound),
+    }
+}
+
+pub async fn search<R>(
+    filtro: web::Query<FirmaFiltro>,
+    data: web::Data<AppState<R>>,
+) -> Result<HttpResponse, AppError>
+where
+    R: FirmaRepository + 'static,
+{
+    let firmas = FirmaRepository::search(data.firma_repo.as_ref(), &filtro).await?;
+    Ok(HttpResponse::Ok().json(firmas))
+}
+
+/// Claves públicas del profesional, incluidas las revocadas, para verificar
+/// firmas fuera del sistema.
+pub async fn claves<R>(
+    id_prof: web::Path<u32>,
+    data: web::Data<AppState<R>>,
+) -> Result<HttpResponse, AppError>
+where
+    R: FirmaRepository + 'static,
+{
+    let claves = data.firma_repo.claves(id_prof.into_inner()).await?;
+    Ok(HttpResponse::Ok().json(claves))
+}
+
+/// Genera un par de claves nuevo y revoca el vigente (ej. si se sospecha
+/// que fue comprometido). Las firmas ya hechas siguen siendo verificables.
+pub async fn generar_clave<R>(
+    id_prof: web::Path<u32>,
+    data: web::Data<AppState<R>>,
+) -> Result<HttpResponse, AppError>
+where
+    R: FirmaRepository + ProfesionalRepository + 'static,
+{
+    let id_prof = id_prof.into_inner();
+    ProfesionalRepository::get_by_id(data.profesional_repo.as_ref(), id_prof).await?
+        .ok_or(AppError::NotFound)?;
+
+    let clave = data.custodia.generar(id_prof)?;
+    let id = data.firma_repo.crear_clave(&clave).await?;
+    Ok(HttpResponse::Created().json(serde_json::json!({"id": id, "clave_publica": clave.clave_publica})))
+}
+
+/// Firma una receta o una versión de documento. Sólo puede firmar su autor,
+/// con RNPI; si aún no tiene clave se le genera una.
+pub async fn create<R>(
+    input: web::Json<SolicitudFirma>,
+    data: web::Data<AppState<R>>,
+) -> Result<HttpResponse, AppError>
+where
+    R: FirmaRepository + ProfesionalRepository + RecetaRepository + DocumentoRepository + 'static,
+{
+    let tipo = tipo_firma(&input.tipo)?;
+    if tipo == FIRMA_RECETA && input.version.is_some_and(|v| v != 0) {
+        return Err(AppError::Validation("Las recetas no tienen versiones".into()));
+    }
+    let (contenido, version, autor) = contenido(&data, &tipo, input.id_documento, input.version).await?
+        .ok_or_else(|| AppError::Validation(format!("{} {} no existe", tipo, input.id_documento)))?;
+    if autor != input.id_prof {
+        return Err(AppError::Validation("Sólo el autor puede firmar el documento".into()));
+    }
+    if tipo == FIRMA_RECETA
+        && let Some(r) = RecetaRepository::get_by_id(data.receta_repo.as_ref(), input.id_documento).await?
+        && r.receta.estado == RECETA_ANULADA
+    {
+        return Err(AppError::Conflict("La receta está anulada".into()));
+    }
+
+    let profesional = ProfesionalRepository::get_by_id(data.profesional_repo.as_ref(), input.id_prof).await?
+        .ok_or_else(|| AppError::Validation(format!("Profesional {} no existe", input.id_prof)))?;
+    let rnpi = profesional.registro_rnpi.as_deref().map(str::trim).unwrap_or_default();
+    if rnpi.is_empty() {
+        return Err(AppError::Validation("El profesional no tiene registro RNPI para firmar".into()));
+    }
+
+    let clave = match data.firma_repo.clave_vigente(input.id_prof).await? {
+        Some(c) => c,
+        None => {
+            let mut clave = data.custodia.generar(input.id_prof)?;
+            clave.id = data.firma_repo.crear_clave(&clave).await?;
+            clave
+        }
+    };
+
+    let mut nueva = Firma {
+        id: 0,
+        tipo,
+        id_documento: input.id_documento,
+        version,
+        id_clave: clave.id,
+        id_prof: input.id_prof,
+        rut_firmante: profesional.rut.trim().to_string(),
+        rnpi_firmante: rnpi.to_string(),
+        // DATETIME redondea fracciones: se firma lo que queda guardado
+        firmada_en: tiempo::al_segundo(tiempo::ahora()),
+        huella: firma::huella(&contenido),
+        firma: String::new(),
+    };
+    nueva.firma = data.custodia.firmar(&clave, &firma::mensaje(&nueva))?;
+
+    let id = FirmaRepository::create(data.firma_repo.as_ref(), &nueva).await?
+        .ok_or_else(|| AppError::Conflict("El documento ya está firmado".into()))?;
+    Ok(HttpResponse::Created().json(serde_json::json!({"id": id, "huella": nueva.huella})))
+}
+
+/// Verificación pública: comprueba la firma con la clave pública registrada
+/// y que el contenido actual siga siendo el firmado.
+pub async fn verificar<R>(
+    id: web::Path<u32>,
+    data: web::Data<AppState<R>>,
+) -> Result<HttpResponse, AppError>
+where
+    R: FirmaRepository + ProfesionalRepository + RecetaRepository + DocumentoRepository + 'static,
+{
+    let f = FirmaRepository::get_by_id(data.firma_repo.as_ref(), id.into_inner()).await?
+        .ok_or(AppError::NotFound)?;
+    let clave = data.firma_repo.get_clave(f.id_clave).await?
+        .ok_or_else(|| AppError::Internal(format!("Firma {} sin clave", f.id)))?;
+    let version = (f.tipo == FIRMA_DOCUMENTO).then_some(f.version);
+    let huella_actual = contenido(&data, &f.tipo, f.id_documento, version).await?
+        .map(|(c, _, _)| firma::huella(&c));
+    let firmante = ProfesionalRepository::get_by_id(data.profesional_repo.as_ref(), f.id_prof).await?
+        .map(|p| p.nombre_completo())
+        .unwrap_or_default();
+
+    let firma_valida = clave.id_prof == f.id_prof && firma::verificar(&clave.clave_publica, &firma::mensaje(&f), &f.firma);
+    let contenido_integro = huella_actual.as_deref() == Some(f.huella.as_str());
+    Ok(HttpResponse::Ok().json(VerificacionFirma {
+        valida: firma_valida && contenido_integro,
+        firma_valida,
+        contenido_integro,
+        id_firma: f.id,
+        tipo: f.tipo,
+        id_documento: f.id_documento,
+        version: f.version,
+        firmante,
+        rut_firmante: f.rut_firmante,
+        rnpi_firmante: f.rnpi_firmante,
+        firmada_en: f.firmada_en,
+        huella_firmada: f.huella,
+        huella_actual,
+        clave_publica: clave.clave_publica,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_web::{test, App};
+    use crate::{
+        firma::Custodia,
+        models::{Profesional, ATENCION_BORRADOR},
+        repositories::{fixtures, AtencionRepository, MockRepository},
+    };
+    use super::*;
+    use super::super::recetas as api_recetas;
+
+    async fn app_state() -> AppState<MockRepository> {
+        let medico = Profesional { especialidad: Some("Médico".into()), ..fixtures::profesional(1) };
+        let repo = fixtures::repositorio(vec![medico], Vec::new()).await;
+        AtencionRepository::create(&repo, &fixtures::atencion(1, 1, ATENCION_BORRADOR, tiempo::ahora())).await.unwrap();
+        AppState::new(repo).with_custodia(Custodia::new(Some(&"a5".repeat(32))).unwrap())
+    }
+
+    #[actix_web::test]
+    async fn firma_receta_y_detecta_cambios() {
+        let state = app_state().await;
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(state.clone()))
+                .route("/recetas", web::post().to(api_recetas::create::<MockRepository>))
+                .route("/firmas", web::post().to(create::<MockRepository>))
+                .route("/firmas/{id}/verificar", web::get().to(verificar::<MockRepository>))
+                .route("/profesionales/{id_prof}/claves-firma", web::post().to(generar_clave::<MockRepository>)),
+        )
+        .await;
+
+        let receta = serde_json::json!({
+            "id_atencion": 1,
+            "id_prof": 1,
+            "lineas": [{"medicamento": "Paracetamol", "dosis": "500 mg", "frecuencia": "cada 8 horas", "duracion": "3 días"}],
+        });
+        for _ in 0..2 {
+            let req = test::TestRequest::post().uri("/recetas").set_json(&receta).to_request();
+            assert_eq!(test::call_service(&app, req).await.status(), 201);
+        }
+
+        let solicitud = serde_json::json!({"tipo": "receta", "id_documento": 1, "id_prof": 1});
+        let req = test::TestRequest::post().uri("/firmas").set_json(&solicitud).to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), 201);
+        let req = test::TestRequest::post().uri("/firmas").set_json(&solicitud).to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), 409);
+        let ajena = serde_json::json!({"tipo": "RECETA", "id_documento": 2, "id_prof": 2});
+        let req = test::TestRequest::post().uri("/firmas").set_json(&ajena).to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), 400);
+
+        let req = test::TestRequest::get().uri("/firmas/1/verificar").to_request();
+        let verificacion: VerificacionFirma = test::call_and_read_body_json(&app, req).await;
+        assert!(verificacion.valida);
+        assert_eq!(verificacion.rnpi_firmante, "212402");
+        assert_eq!(verificacion.firmante, "NICOLE ELENA ZAMORA");
+
+        // Firma auténtica sobre un contenido distinto del que hoy tiene la receta 2
+        let clave = state.firma_repo.clave_vigente(1).await.unwrap().unwrap();
+        let mut alterada = FirmaRepository::get_by_id(state.firma_repo.as_ref(), 1).await.unwrap().unwrap();
+        alterada.id_documento = 2;
+        alterada.firma = state.custodia.firmar(&clave, &firma::mensaje(&alterada)).unwrap();
+        FirmaRepository::create(state.firma_repo.as_ref(), &alterada).await.unwrap();
+        let req = test::TestRequest::get().uri("/firmas/2/verificar").to_request();
+        let verificacion: VerificacionFirma = test::call_and_read_body_json(&app, req).await;
+        assert!(verificacion.firma_valida);
+        assert!(!verificacion.contenido_integro);
+        assert!(!verificacion.valida);
+
+        // Una clave nueva no invalida lo ya firmado
+        let req = test::TestRequest::post().uri("/profesionales/1/claves-firma").to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), 201);
+        let req = test::TestRequest::get().uri("/firmas/1/verificar").to_request();
+        let verificacion: VerificacionFirma = test::call_and_read_body_json(&app, req).await;
+        assert!(verificacion.valida);
+    }
+}
@@ -0,0 +1,321 @@
+use std::collections::{BTreeMap, HashMap};
+
+use actix_web::{http::header::ContentDisposition, web, HttpResponse};
+use chrono::{Days, NaiveTime};
+use serde::Deserialize;
+use crate::{
+    models::{
+        AtencionFiltro, Liquidacion, LiquidacionFiltro, PrestacionHonorario, RetencionHonorarios, TarifaHonorario,
+        TarifaHonorarioFiltro, TarifaHonorarioInput, VisitaFiltro, ATENCION_FIRMADA, HONORARIO_ATENCION,
+        HONORARIO_VISITA, VISITA_REALIZADA,
+    },
+    facturacion, honorarios, texto::plegar, tiempo,
+    app_state::AppState,
+    error::AppError
+};
+use super::super::repositories::{
+    AgendaRepository, AtencionRepository, HonorarioRepository, ProfesionalRepository, VisitaRepository, ZonaRepository,
+};
+
+#[derive(Debug, Deserialize)]
+pub struct PeriodoQuery {
+    pub periodo: String,
+}
+
+/// Liquida el período a todos los profesionales con atenciones firmadas o
+/// visitas realizadas, o sólo a `id_prof`. La atención se paga en la zona
+/// de su cita (o la del profesional si no tuvo cita) y cuenta en el mes de
+/// su fecha local en esa zona. La retención es la vigente al cierre del mes.
+async fn liquidar<R>(data: &AppState<R>, periodo: &str, id_prof: Option<u32>) -> Result<Vec<Liquidacion>, AppError>
+where
+    R: HonorarioRepository + AtencionRepository + VisitaRepository + ProfesionalRepository + AgendaRepository + ZonaRepository,
+{
+    let periodo = periodo.trim();
+    let (desde, hasta) = facturacion::periodo(periodo)?;
+    let retenciones = data.honorario_repo.retenciones().await?;
+    let tasa = honorarios::tasa_vigente(&retenciones, hasta)
+        .ok_or_else(|| AppError::Validation(format!("No hay tasa de retención vigente al {}", hasta)))?;
+    let tarifas = data.honorario_repo.tarifas_honorario(&TarifaHonorarioFiltro::default()).await?;
+
+    // Un día de margen a cada lado cubre cualquier huso; la fecha local decide
+    let filtro = AtencionFiltro {
+        id_prof,
+        estado: Some(ATENCION_FIRMADA.into()),
+        desde: Some((desde - Days::new(1)).and_time(NaiveTime::MIN)),
+        hasta: Some((hasta + Days::new(2)).and_time(NaiveTime::MIN)),
+        ..Default::default()
+    };
+    let atenciones = AtencionRepository::search(data.atencion_repo.as_ref(), &filtro).await?;
+    let filtro = VisitaFiltro {
+        id_prof,
+        desde: Some(desde),
+        hasta: Some(hasta),
+        estado: Some(VISITA_REALIZADA.into()),
+        ..Default::default()
+    };
+    let visitas = VisitaRepository::search(data.visita_repo.as_ref(), &filtro).await?;
+
+    let mut profesionales = HashMap::new();
+    for id in atenciones.iter().map(|d| d.atencion.id_prof).chain(visitas.iter().filter_map(|v| v.id_prof)) {
+        if !profesionales.contains_key(&id)
+            && let Some(profesional) = ProfesionalRepository::get_by_id(data.profesional_repo.as_ref(), id).await?
+        {
+            profesionales.insert(id, profesional);
+        }
+    }
+
+    // La zona de `paso_profesionales` es el nombre de la zona de acceso
+    let zonas = ZonaRepository::get_all(data.zona_repo.as_ref()).await?;
+    let mut prestaciones: BTreeMap<u32, Vec<PrestacionHonorario>> = BTreeMap::new();
+    for detalle in &atenciones {
+        let atencion = &detalle.atencion;
+        let Some(profesional) = profesionales.get(&atencion.id_prof) else {
+            tracing::warn!(id_prof = atencion.id_prof, id_atencion = atencion.id, "profesional inexistente; atención sin liquidar");
+            continue;
+        };
+        let cita = match atencion.id_cita {
+            Some(id) => data.agenda_repo.get_cita(id).await?,
+            None => None,
+        };
+        let zona = match cita.and_then(|c| c.cod_zona) {
+            Some(cod_zona) => zonas.iter().find(|z| z.cod_zona == cod_zona),
+            None => profesional.zona.as_deref().and_then(|nombre| {
+                zonas.iter().find(|z| plegar(z.nom_zona.trim()) == plegar(nombre.trim()))
+            }),
+        };
+        let cod_zona = zona.map(|z| z.cod_zona.clone());
+        let tz = tiempo::zona_horaria(zona.map_or(tiempo::ZONA_HORARIA_DEFECTO, |z| z.zona_horaria.as_str()))?;
+        let fecha = tiempo::a_local(atencion.creada_en, tz).date();
+        if fecha < desde || fecha > hasta {
+            continue;
+        }
+        prestaciones.entry(atencion.id_prof).or_default().push(honorarios::valorizar(
+            &tarifas,
+            HONORARIO_ATENCION,
+            atencion.id,
+            fecha,
+            profesional.especialidad.as_deref(),
+            cod_zona,
+        ));
+    }
+    for visita in visitas {
+        let Some(profesional) = visita.id_prof.and_then(|id| profesionales.get(&id)) else {
+            tracing::warn!(id_visita = visita.id, "visita realizada sin profesional existente; sin liquidar");
+            continue;
+        };
+        prestaciones.entry(profesional.id_prof).or_default().push(honorarios::valorizar(
+            &tarifas,
+            HONORARIO_VISITA,
+            visita.id,
+            visita.fecha,
+            profesional.especialidad.as_deref(),
+            Some(visita.cod_zona),
+        ));
+    }
+
+    Ok(prestaciones
+        .into_iter()
+        .map(|(id, prestaciones)| honorarios::liquidar(periodo, &profesionales[&id], prestaciones, tasa))
+        .collect())
+}
+
+pub async fn tarifas<R>(
+    filtro: web::Query<TarifaHonorarioFiltro>,
+    data: web::Data<AppState<R>>,
+) -> Result<HttpResponse, AppError>
+where
+    R: HonorarioRepository + 'static,
+{
+    Ok(HttpResponse::Ok().json(data.honorario_repo.tarifas_honorario(&filtro).await?))
+}
+
+/// Como las tarifas de facturación, no se editan: un monto nuevo es una
+/// tarifa nueva desde la fecha en que rige.
+pub async fn create_tarifa<R>(
+    tarifa: web::Json<TarifaHonorarioInput>,
+    data: web::Data<AppState<R>>,
+) -> Result<HttpResponse, AppError>
+where
+    R: HonorarioRepository + 'static,
+{
+    let mut tarifa: TarifaHonorario = tarifa.into_inner().try_into()?;
+    tarifa.id = data.honorario_repo.create_tarifa_honorario(&tarifa).await?;
+    Ok(HttpResponse::Created().json(tarifa))
+}
+
+pub async fn retenciones<R>(
+    data: web::Data<AppState<R>>,
+) -> Result<HttpResponse, AppError>
+where
+    R: HonorarioRepository + 'static,
+{
+    Ok(HttpResponse::Ok().json(data.honorario_repo.retenciones().await?))
+}
+
+/// Fija la tasa de retención desde una fecha (la del año siguiente, por
+/// ejemplo); si ya había una desde esa fecha la reemplaza.
+pub async fn guardar_retencion<R>(
+    retencion: web::Json<RetencionHonorarios>,
+    data: web::Data<AppState<R>>,
+) -> Result<HttpResponse, AppError>
+where
+    R: HonorarioRepository + 'static,
+{
+    if retencion.tasa > 10_000 {
+        return Err(AppError::Validation("La tasa se expresa en centésimas de punto, entre 0 y 10000".into()));
+    }
+    data.honorario_repo.guardar_retencion(&retencion).await?;
+    Ok(HttpResponse::Ok().json(retencion.into_inner()))
+}
+
+pub async fn liquidaciones<R>(
+    filtro: web::Query<LiquidacionFiltro>,
+    data: web::Data<AppState<R>>,
+) -> Result<HttpResponse, AppError>
+where
+    R: HonorarioRepository + AtencionRepository + VisitaRepository + ProfesionalRepository + AgendaRepository + ZonaRepository + 'static,
+{
+    Ok(HttpResponse::Ok().json(liquidar(&data, &filtro.periodo, filtro.id_prof).await?))
+}
+
+/// Liquidación de un profesional; 404 si no tiene prestaciones en el período.
+pub async fn get_liquidacion<R>(
+    id_prof: web::Path<u32>,
+    query: web::Query<PeriodoQuery>,
+    data: web::Data<AppState<R>>,
+) -> Result<HttpResponse, AppError>
+where
+    R: HonorarioRepository + AtencionRepository + VisitaRepository + ProfesionalRepository + AgendaRepository + ZonaRepository + 'static,
+{
+    let liquidacion = liquidar(&data, &query.periodo, Some(id_prof.into_inner())).await?
+        .pop()
+        .ok_or(AppError::NotFound)?;
+    Ok(HttpResponse::Ok().json(liquidacion))
+}
+
+/// Planilla CSV del período para el pago de honorarios.
+pub async fn planilla<R>(
+    query: web::Query<PeriodoQuery>,
+    data: web::Data<AppState<R>>,
+) -> Result<HttpResponse, AppError>
+where
+    R: HonorarioRepository + AtencionRepository + VisitaRepository + ProfesionalRepository + AgendaRepository + ZonaRepository + 'static,
+{
+    let periodo = query.periodo.trim();
+    let liquidaciones = liquidar(&data, periodo, None).await?;
+    Ok(HttpResponse::Ok()
+        .content_type("text/csv; charset=utf-8")
+        .insert_header(ContentDisposition::attachment(format!("honorarios-{}.csv", periodo)))
+        .body(honorarios::planilla(periodo, &liquidaciones)))
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_web::{test, App};
+    use chrono::{Duration, NaiveDate};
+    use crate::{
+        models::{Profesional, Visita},
+        repositories::{fixtures, MockRepository},
+    };
+    use super::*;
+
+    fn profesional(id_prof: u32, especialidad: &str) -> Profesional {
+        Profesional { especialidad: Some(especialidad.into()), zona: Some("SUR".into()), ..fixtures::profesional(id_prof) }
+    }
+
+    /// Una atención firmada de hace una hora y una visita realizada el mismo
+    /// día, ambas de la profesional 1. Retorna el estado y el día local.
+    async fn app_state() -> (AppState<MockRepository>, NaiveDate) {
+        let repo = MockRepository::default()
+            .with_profesionales(vec![profesional(1, "Kinesiología"), profesional(2, "Enfermería")]);
+        let creada_en = tiempo::ahora() - Duration::hours(1);
+        let santiago = tiempo::zona_horaria(tiempo::ZONA_HORARIA_DEFECTO).unwrap();
+        let fecha = tiempo::a_local(creada_en, santiago).date();
+        AtencionRepository::create(&repo, &fixtures::atencion(1, 1, ATENCION_FIRMADA, creada_en)).await.unwrap();
+        VisitaRepository::create(&repo, &Visita {
+            id: 0,
+            id_paciente: 1,
+            id_prof: Some(1),
+            cod_zona: "SUR".into(),
+            direccion: "Los Aromos 123".into(),
+            comuna: None,
+            latitud: None,
+            longitud: None,
+            fecha,
+            ventana_desde: None,
+            ventana_hasta: None,
+            duracion_min: 45,
+            motivo: "Rehabilitación".into(),
+            estado: VISITA_REALIZADA.into(),
+            motivo_estado: None,
+            creada_en,
+        }).await.unwrap();
+        (AppState::new(repo), fecha)
+    }
+
+    #[actix_web::test]
+    async fn liquida_honorarios_del_mes() {
+        let (state, fecha) = app_state().await;
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(state))
+                .route("/honorarios/tarifas", web::post().to(create_tarifa::<MockRepository>))
+                .route("/honorarios/retenciones", web::put().to(guardar_retencion::<MockRepository>))
+                .route("/honorarios/liquidaciones", web::get().to(liquidaciones::<MockRepository>))
+                .route("/honorarios/liquidaciones/{id_prof}", web::get().to(get_liquidacion::<MockRepository>))
+                .route("/honorarios/planilla", web::get().to(planilla::<MockRepository>)),
+        )
+        .await;
+        let periodo = fecha.format("%Y-%m").to_string();
+        let uri = format!("/honorarios/liquidaciones?periodo={}", periodo);
+
+        // Sin tasa de retención no se liquida
+        let req = test::TestRequest::get().uri(&uri).to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), 400);
+
+        let desde = NaiveDate::from_ymd_opt(2020, 1, 1).unwrap();
+        for (tasa, status) in [(10_001, 400), (1450, 200)] {
+            let req = test::TestRequest::put()
+                .uri("/honorarios/retenciones")
+                .set_json(serde_json::json!({"vigencia_desde": desde, "tasa": tasa}))
+                .to_request();
+            assert_eq!(test::call_service(&app, req).await.status(), status);
+        }
+        let req = test::TestRequest::post()
+            .uri("/honorarios/tarifas")
+            .set_json(serde_json::json!({"tipo": "traslado", "monto": 1000, "vigencia_desde": desde}))
+            .to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), 400);
+        let req = test::TestRequest::post()
+            .uri("/honorarios/tarifas")
+            .set_json(serde_json::json!({"tipo": "atencion", "especialidad": "kinesiologia", "monto": 12000, "vigencia_desde": desde}))
+            .to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), 201);
+
+        // La visita aún no tiene tarifa: va en cero y se informa
+        let req = test::TestRequest::get().uri(&uri).to_request();
+        let liquidaciones: Vec<Liquidacion> = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(liquidaciones.len(), 1);
+        assert_eq!((liquidaciones[0].atenciones, liquidaciones[0].visitas, liquidaciones[0].sin_tarifa), (1, 1, 1));
+        assert_eq!(liquidaciones[0].bruto, 12_000);
+
+        let req = test::TestRequest::post()
+            .uri("/honorarios/tarifas")
+            .set_json(serde_json::json!({"tipo": "VISITA", "cod_zona": "SUR", "monto": 20000, "vigencia_desde": desde}))
+            .to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), 201);
+
+        let req = test::TestRequest::get().uri(&format!("/honorarios/liquidaciones/1?periodo={}", periodo)).to_request();
+        let liquidacion: Liquidacion = test::call_and_read_body_json(&app, req).await;
+        assert_eq!((liquidacion.bruto, liquidacion.retencion, liquidacion.liquido), (32_000, 4_640, 27_360));
+        assert_eq!(liquidacion.sin_tarifa, 0);
+        let req = test::TestRequest::get().uri(&format!("/honorarios/liquidaciones/2?periodo={}", periodo)).to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), 404);
+
+        let req = test::TestRequest::get().uri(&format!("/honorarios/planilla?periodo={}", periodo)).to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.headers().get("content-type").unwrap(), "text/csv; charset=utf-8");
+        let csv = String::from_utf8(test::read_body(resp).await.to_vec()).unwrap();
+        assert!(csv.lines().nth(1).unwrap().ends_with(";1;1;0;32000;14,50;4640;27360"));
+    }
+}
@@ -0,0 +1,452 @@
+use std::time::{Duration as Intervalo, Instant};
+
+use actix_web::{rt, web, HttpRequest, HttpResponse};
+use actix_ws::{Message, MessageStream, Session};
+use tokio::sync::broadcast::{self, error::RecvError};
+use tracing::{error, info, warn};
+use crate::{
+    mensajeria::{self, Entrante, Evento},
+    models::{
+        BandejaFiltro, ContenidoMensaje, Conversacion, ConversacionFiltro, ConversacionInput, ConversacionResumen,
+        HistorialFiltro, HistorialMensajes, Lectura, LecturaInput, Lector, Mensaje, MensajeInput, Participante,
+        PARTICIPANTE_PACIENTE,
+    },
+    tiempo,
+    app_state::AppState,
+    error::AppError
+};
+use super::super::repositories::{MensajeRepository, PacienteRepository, ProfesionalRepository, ZonaRepository};
+
+/// Cada cuánto se eliminan los mensajes que superaron su retención.
+const DEPURACION: Intervalo = Intervalo::from_secs(3600);
+const LATIDO: Intervalo = Intervalo::from_secs(15);
+const SIN_RESPUESTA: Intervalo = Intervalo::from_secs(45);
+
+/// El paciente sólo accede a su conversación; un profesional, a las de su
+/// zona (todas si no tiene zona asignada).
+async fn autorizar<R>(data: &AppState<R>, id_conversacion: u32, lector: &Participante) -> Result<Conversacion, AppError>
+where
+    R: MensajeRepository + ProfesionalRepository + ZonaRepository,
+{
+    let conversacion = data.mensaje_repo.get_conversacion(id_conversacion).await?.ok_or(AppError::NotFound)?;
+    let permitido = if lector.tipo == PARTICIPANTE_PACIENTE {
+        conversacion.id_paciente == lector.id
+    } else {
+        zonas_profesional(data, lector.id).await?.is_some_and(|zonas| mensajeria::en_zona(&conversacion, zonas.as_deref()))
+    };
+    if !permitido {
+        return Err(AppError::Forbidden("Sin acceso a la conversación".into()));
+    }
+    Ok(conversacion)
+}
+
+/// Códigos de zona del profesional. `None` si no existe; `Some(None)` si
+/// no tiene zona.
+async fn zonas_profesional<R>(data: &AppState<R>, id_prof: u32) -> Result<Option<Option<Vec<String>>>, AppError>
+where
+    R: ProfesionalRepository + ZonaRepository,
+{
+    let Some(profesional) = ProfesionalRepository::get_by_id(data.profesional_repo.as_ref(), id_prof).await? else {
+        return Ok(None);
+    };
+    let Some(zona) = profesional.zona.filter(|z| !z.trim().is_empty()) else {
+        return Ok(Some(None));
+    };
+    let zonas = ZonaRepository::get_all(data.zona_repo.as_ref()).await?;
+    Ok(Some(Some(mensajeria::codigos_de_zona(&zona, &zonas))))
+}
+
+/// Guarda el mensaje cifrado, lo da por leído para su autor y lo difunde a
+/// los conectados.
+async fn enviar<R>(
+    data: &AppState<R>,
+    conversacion: &Conversacion,
+    autor: &Participante,
+    contenido: ContenidoMensaje,
+) -> Result<Mensaje, AppError>
+where
+    R: MensajeRepository,
+{
+    let contenido = mensajeria::validar(contenido)?;
+    let ahora = tiempo::ahora();
+    let mut cifrado = mensajeria::cifrar(&data.custodia, conversacion.id, autor, &contenido, ahora)?;
+    cifrado.id = data.mensaje_repo.create_mensaje(&cifrado).await?;
+    let mensaje = mensajeria::descifrar(&data.custodia, cifrado)?;
+
+    leer(data, conversacion, autor, mensaje.id).await?;
+    data.buzones.publicar(conversacion.id, Evento::Mensaje(mensaje.clone()))?;
+    Ok(mensaje)
+}
+
+/// Avanza la lectura del participante y avisa a los conectados. `None` si
+/// ya había leído hasta ahí. `hasta` se ajusta al último mensaje existente
+/// de la conversación que no lo supere: un id inventado no deja leídos los
+/// mensajes que aún no llegan.
+async fn leer<R>(
+    data: &AppState<R>,
+    conversacion: &Conversacion,
+    lector: &Participante,
+    hasta: u32,
+) -> Result<Option<Lectura>, AppError>
+where
+    R: MensajeRepository,
+{
+    let Some(ultimo) = data.mensaje_repo.mensajes(conversacion.id, hasta.checked_add(1), 1).await?.pop() else {
+        return Ok(None);
+    };
+    let lectura = Lectura {
+        id_conversacion: conversacion.id,
+        lector_tipo: lector.tipo.clone(),
+        lector_id: lector.id,
+        hasta: ultimo.id,
+        leido_en: tiempo::ahora(),
+    };
+    if !data.mensaje_repo.marcar_leido(&lectura).await? {
+        return Ok(None);
+    }
+    data.buzones.publicar(conversacion.id, Evento::Lectura(lectura.clone()))?;
+    Ok(Some(lectura))
+}
+
+/// Elimina periódicamente los mensajes que superaron la retención.
+pub async fn depurar<R>(data: AppState<R>)
+where
+    R: MensajeRepository,
+{
+    let mut intervalo = rt::time::interval(DEPURACION);
+    loop {
+        intervalo.tick().await;
+        match data.mensaje_repo.depurar(tiempo::ahora()).await {
+            Ok(0) => {}
+            Ok(n) => info!("Mensajería: {} mensajes eliminados por retención", n),
+            Err(e) => error!("Mensajería: {}", e),
+        }
+    }
+}
+
+/// Abre una conversación del paciente. Cliente y zona se toman de su ficha.
+pub async fn create_conversacion<R>(
+    input: web::Json<ConversacionInput>,
+    data: web::Data<AppState<R>>,
+) -> Result<HttpResponse, AppError>
+where
+    R: MensajeRepository + PacienteRepository + 'static,
+{
+    let input = input.into_inner();
+    let asunto = input.asunto.trim().to_string();
+    if asunto.is_empty() {
+        return Err(AppError::Validation("El asunto es obligatorio".into()));
+    }
+    let retencion_dias = input.retencion_dias.unwrap_or(mensajeria::RETENCION_DIAS);
+    if retencion_dias < mensajeria::RETENCION_MINIMA_DIAS {
+        return Err(AppError::Validation(format!(
+            "La retención mínima es de {} días", mensajeria::RETENCION_MINIMA_DIAS,
+        )));
+    }
+    let paciente = PacienteRepository::get_by_id(data.paciente_repo.as_ref(), input.id_paciente).await?
+        .ok_or_else(|| AppError::Validation(format!("Paciente {} no existe", input.id_paciente)))?;
+
+    let mut conversacion = Conversacion {
+        id: 0,
+        id_paciente: paciente.id,
+        asunto,
+        cod_cliente: paciente.cod_cliente,
+        cod_zona: paciente.cod_zona,
+        retencion_dias,
+        creada_en: tiempo::ahora(),
+        ultimo_mensaje_en: None,
+    };
+    conversacion.id = data.mensaje_repo.create_conversacion(&conversacion).await?;
+    Ok(HttpResponse::Created().json(conversacion))
+}
+
+/// Conversaciones del participante con sus no leídos.
+pub async fn get_conversaciones<R>(
+    filtro: web::Query<BandejaFiltro>,
+    data: web::Data<AppState<R>>,
+) -> Result<HttpResponse, AppError>
+where
+    R: MensajeRepository + ProfesionalRepository + ZonaRepository + 'static,
+{
+    let lector = filtro.lector().participante()?;
+    let mut criterio = ConversacionFiltro { cod_cliente: filtro.cod_cliente, ..Default::default() };
+    if lector.tipo == PARTICIPANTE_PACIENTE {
+        criterio.id_paciente = Some(lector.id);
+    } else {
+        criterio.zonas = zonas_profesional(&data, lector.id).await?
+            .ok_or_else(|| AppError::Forbidden(format!("Profesional {} no existe", lector.id)))?;
+    }
+
+    let conversaciones = data.mensaje_repo.conversaciones(&criterio).await?;
+    let mut bandeja = Vec::with_capacity(conversaciones.len());
+    for conversacion in conversaciones {
+        let no_leidos = data.mensaje_repo.no_leidos(conversacion.id, &lector).await?;
+        bandeja.push(ConversacionResumen { conversacion, no_leidos });
+    }
+    Ok(HttpResponse::Ok().json(bandeja))
+}
+
+/// Historial paginado hacia atrás; alternativa al WebSocket para cargar
+/// mensajes anteriores o cuando no hay conexión en vivo.
+pub async fn get_mensajes<R>(
+    id: web::Path<u32>,
+    filtro: web::Query<HistorialFiltro>,
+    data: web::Data<AppState<R>>,
+) -> Result<HttpResponse, AppError>
+where
+    R: MensajeRepository + ProfesionalRepository + ZonaRepository + 'static,
+{
+    let lector = filtro.lector().participante()?;
+    let conversacion = autorizar(&data, id.into_inner(), &lector).await?;
+    let limite = filtro.limite.unwrap_or(mensajeria::PAGINA).clamp(1, mensajeria::PAGINA_MAXIMA);
+
+    let cifrados = data.mensaje_repo.mensajes(conversacion.id, filtro.antes_de, limite).await?;
+    let siguiente = (cifrados.len() == limite as usize).then(|| cifrados.last().map(|m| m.id)).flatten();
+    let mut mensajes = cifrados
+        .into_iter()
+        .map(|m| mensajeria::descifrar(&data.custodia, m))
+        .collect::<Result<Vec<_>, _>>()?;
+    mensajes.reverse();
+
+    Ok(HttpResponse::Ok().json(HistorialMensajes {
+        mensajes,
+        lecturas: data.mensaje_repo.lecturas(conversacion.id).await?,
+        siguiente,
+    }))
+}
+
+pub async fn create_mensaje<R>(
+    id: web::Path<u32>,
+    input: web::Json<MensajeInput>,
+    data: web::Data<AppState<R>>,
+) -> Result<HttpResponse, AppError>
+where
+    R: MensajeRepository + ProfesionalRepository + ZonaRepository + 'static,
+{
+    let input = input.into_inner();
+    let autor = input.autor.participante()?;
+    let conversacion = autorizar(&data, id.into_inner(), &autor).await?;
+    let mensaje = enviar(&data, &conversacion, &autor, input.contenido).await?;
+    Ok(HttpResponse::Created().json(mensaje))
+}
+
+/// Confirma la lectura hasta un mensaje. Sólo avanza: confirmar uno
+/// anterior no cambia nada.
+pub async fn marcar_leido<R>(
+    id: web::Path<u32>,
+    input: web::Json<LecturaInput>,
+    data: web::Data<AppState<R>>,
+) -> Result<HttpResponse, AppError>
+where
+    R: MensajeRepository + ProfesionalRepository + ZonaRepository + 'static,
+{
+    let lector = input.lector.participante()?;
+    let conversacion = autorizar(&data, id.into_inner(), &lector).await?;
+    leer(&data, &conversacion, &lector, input.hasta).await?;
+    Ok(HttpResponse::Ok().json(serde_json::json!({
+        "no_leidos": data.mensaje_repo.no_leidos(conversacion.id, &lector).await?,
+    })))
+}
+
+/// WebSocket de la conversación: entrega mensajes y lecturas en vivo y
+/// acepta `enviar` y `leer`.
+pub async fn conectar<R>(
+    req: HttpRequest,
+    body: web::Payload,
+    id: web::Path<u32>,
+    lector: web::Query<Lector>,
+    data: web::Data<AppState<R>>,
+) -> Result<HttpResponse, AppError>
+where
+    R: MensajeRepository + ProfesionalRepository + ZonaRepository + 'static,
+{
+    let participante = lector.participante()?;
+    let conversacion = autorizar(&data, id.into_inner(), &participante).await?;
+    let (respuesta, session, mensajes) = actix_ws::handle(&req, body)
+        .map_err(|e| AppError::Validation(e.to_string()))?;
+    let eventos = data.buzones.suscribir(conversacion.id)?;
+
+    let data = data.into_inner();
+    rt::spawn(async move {
+        if let Err(e) = sesion(&data, &conversacion, &participante, session, mensajes, eventos).await {
+            error!("Conversación {}: {}", conversacion.id, e);
+        }
+    });
+    Ok(respuesta)
+}
+
+async fn sesion<R>(
+    data: &AppState<R>,
+    conversacion: &Conversacion,
+    participante: &Participante,
+    mut session: Session,
+    mut mensajes: MessageStream,
+    mut eventos: broadcast::Receiver<std::sync::Arc<Evento>>,
+) -> Result<(), AppError>
+where
+    R: MensajeRepository,
+{
+    let mut latido = rt::time::interval(LATIDO);
+    let mut ultima_actividad = Instant::now();
+
+    loop {
+        tokio::select! {
+            mensaje = mensajes.recv() => {
+                let Some(Ok(mensaje)) = mensaje else { break };
+                ultima_actividad = Instant::now();
+                match mensaje {
+                    Message::Text(texto) => {
+                        let resultado = match serde_json::from_str::<Entrante>(&texto) {
+                            Ok(Entrante::Enviar(contenido)) => enviar(data, conversacion, participante, contenido).await.map(|_| ()),
+                            Ok(Entrante::Leer { hasta }) => leer(data, conversacion, participante, hasta).await.map(|_| ()),
+                            Err(e) => Err(AppError::Validation(format!("Mensaje inválido: {}", e))),
+                        };
+                        if let Err(e) = resultado {
+                            warn!("Conversación {}: {}", conversacion.id, e);
+                            if !notificar(&mut session, &Evento::Error { mensaje: e.to_string() }).await {
+                                break;
+                            }
+                        }
+                    }
+                    Message::Ping(bytes) if session.pong(&bytes).await.is_err() => break,
+                    Message::Close(_) => break,
+                    _ => {}
+                }
+            }
+            evento = eventos.recv() => {
+                match evento {
+                    Ok(evento) => {
+                        if !notificar(&mut session, &evento).await {
+                            break;
+                        }
+                    }
+                    // Quien se atrasó recupera lo perdido con el historial
+                    Err(RecvError::Lagged(_)) => continue,
+                    Err(RecvError::Closed) => break,
+                }
+            }
+            _ = latido.tick() => {
+                if ultima_actividad.elapsed() > SIN_RESPUESTA || session.ping(b"").await.is_err() {
+                    break;
+                }
+            }
+        }
+    }
+
+    let _ = session.close(None).await;
+    Ok(())
+}
+
+async fn notificar(session: &mut Session, evento: &Evento) -> bool {
+    match serde_json::to_string(evento) {
+        Ok(texto) => session.text(texto).await.is_ok(),
+        Err(_) => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_web::{test, App};
+    use crate::{
+        firma::Custodia,
+        models::{Paciente, Profesional, Zona},
+        repositories::{fixtures, MockRepository},
+    };
+    use super::*;
+
+    fn profesional(id_prof: u32, zona: &str) -> Profesional {
+        Profesional { zona: Some(zona.into()), ..fixtures::profesional(id_prof) }
+    }
+
+    fn zona(cod_zona: &str, nom_zona: &str) -> Zona {
+        Zona { cod_zona: cod_zona.into(), nom_zona: nom_zona.into(), orden_zona: None, zona_horaria: "America/Santiago".into() }
+    }
+
+    async fn app_state() -> AppState<MockRepository> {
+        let paciente = Paciente { cod_zona: Some("079".into()), cod_cliente: Some(1), ..fixtures::paciente("0010895960-6") };
+        let repo = fixtures::repositorio(vec![profesional(1, "Metropolitana"), profesional(2, "Ovalle")], vec![paciente])
+            .await
+            .with_zonas(vec![zona("079", "Metropolitana"), zona("047", "Metropolitana Norte"), zona("027", "Ovalle")]);
+        AppState::new(repo).with_custodia(Custodia::new(Some(&"0a".repeat(32))).unwrap())
+    }
+
+    #[actix_web::test]
+    async fn conversacion_con_lecturas_y_zona() {
+        let state = app_state().await;
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(state.clone()))
+                .route("/conversaciones", web::post().to(create_conversacion::<MockRepository>))
+                .route("/conversaciones", web::get().to(get_conversaciones::<MockRepository>))
+                .route("/conversaciones/{id}/mensajes", web::get().to(get_mensajes::<MockRepository>))
+                .route("/conversaciones/{id}/mensajes", web::post().to(create_mensaje::<MockRepository>))
+                .route("/conversaciones/{id}/lecturas", web::post().to(marcar_leido::<MockRepository>)),
+        )
+        .await;
+
+        let req = test::TestRequest::post().uri("/conversaciones")
+            .set_json(serde_json::json!({"id_paciente": 1, "asunto": "Control de presión"}))
+            .to_request();
+        let conversacion: Conversacion = test::call_and_read_body_json(&app, req).await;
+        assert_eq!((conversacion.cod_zona.as_deref(), conversacion.cod_cliente), (Some("079"), Some(1)));
+
+        for (autor, texto) in [("id_paciente", "Hola doctora"), ("id_prof", "Hola Ana"), ("id_paciente", "Adjunto examen")] {
+            let req = test::TestRequest::post().uri("/conversaciones/1/mensajes")
+                .set_json(serde_json::json!({autor: 1, "texto": texto}))
+                .to_request();
+            assert_eq!(test::call_service(&app, req).await.status(), 201);
+        }
+        let guardado = state.mensaje_repo.mensajes(1, None, 1).await.unwrap();
+        assert!(!guardado[0].contenido.contains("examen"));
+
+        // Profesional de otra zona
+        let req = test::TestRequest::post().uri("/conversaciones/1/mensajes")
+            .set_json(serde_json::json!({"id_prof": 2, "texto": "Hola"}))
+            .to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), 403);
+        let req = test::TestRequest::get().uri("/conversaciones?id_prof=2").to_request();
+        let bandeja: Vec<ConversacionResumen> = test::call_and_read_body_json(&app, req).await;
+        assert!(bandeja.is_empty());
+
+        let req = test::TestRequest::get().uri("/conversaciones?id_prof=1").to_request();
+        let bandeja: Vec<ConversacionResumen> = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(bandeja[0].no_leidos, 1);
+
+        // Historial en páginas de dos hacia atrás
+        let req = test::TestRequest::get().uri("/conversaciones/1/mensajes?id_prof=1&limite=2").to_request();
+        let pagina: HistorialMensajes = test::call_and_read_body_json(&app, req).await;
+        let textos: Vec<&str> = pagina.mensajes.iter().map(|m| m.contenido.texto.as_str()).collect();
+        assert_eq!(textos, vec!["Hola Ana", "Adjunto examen"]);
+        assert_eq!(pagina.siguiente, Some(2));
+        let req = test::TestRequest::get().uri("/conversaciones/1/mensajes?id_prof=1&limite=2&antes_de=2").to_request();
+        let pagina: HistorialMensajes = test::call_and_read_body_json(&app, req).await;
+        assert_eq!((pagina.mensajes.len(), pagina.siguiente), (1, None));
+
+        let req = test::TestRequest::post().uri("/conversaciones/1/lecturas")
+            .set_json(serde_json::json!({"id_prof": 1, "hasta": 3}))
+            .to_request();
+        let leido: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(leido["no_leidos"], 0);
+        let req = test::TestRequest::get().uri("/conversaciones?id_paciente=1").to_request();
+        let bandeja: Vec<ConversacionResumen> = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(bandeja[0].no_leidos, 0);
+
+        // Un `hasta` más allá del último mensaje no marca los que vengan después
+        let req = test::TestRequest::post().uri("/conversaciones/1/lecturas")
+            .set_json(serde_json::json!({"id_paciente": 1, "hasta": u32::MAX}))
+            .to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), 200);
+        let lecturas = state.mensaje_repo.lecturas(1).await.unwrap();
+        assert!(lecturas.iter().all(|l| l.hasta == 3));
+        let req = test::TestRequest::post().uri("/conversaciones/1/mensajes")
+            .set_json(serde_json::json!({"id_prof": 1, "texto": "¿Cómo sigue?"}))
+            .to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), 201);
+        let req = test::TestRequest::get().uri("/conversaciones?id_paciente=1").to_request();
+        let bandeja: Vec<ConversacionResumen> = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(bandeja[0].no_leidos, 1);
+
+        let req = test::TestRequest::get().uri("/conversaciones/1/mensajes?id_paciente=2").to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), 403);
+    }
+}
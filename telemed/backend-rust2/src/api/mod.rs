@@ -2,6 +2,41 @@ use actix_web::web;
 use crate::repositories::MysqlRepository;
 
 mod usuarios;
+mod pacientes;
+mod previsiones;
+mod profesionales;
+mod agenda;
+mod zonas;
+mod feriados;
+mod atenciones;
+mod cie10;
+mod visitas;
+mod recetas;
+mod documentos;
+mod firmas;
+mod teleconsultas;
+mod sala_espera;
+mod mensajes;
+mod notificaciones;
+mod recordatorios;
+mod adjuntos;
+mod fhir;
+mod derivaciones;
+mod consentimientos;
+mod facturacion;
+mod honorarios;
+mod contratos;
+mod credenciales;
+
+pub use sala_espera::vigilar;
+pub use mensajes::depurar;
+pub use notificaciones::despachar;
+pub use recordatorios::recordar;
+pub use contratos::sincronizar;
+pub use credenciales::alertar;
+
+/// La CIE-10 completa pesa alrededor de 1 MB.
+const TAMANO_MAXIMO_CIE10: usize = 8 * 1024 * 1024;
 
 pub fn configure(cfg: &mut web::ServiceConfig) {
     cfg.service(
@@ -17,5 +52,454 @@ pub fn configure(cfg: &mut web::ServiceConfig) {
                     .route(web::put().to(usuarios::update::<MysqlRepository>))
                     .route(web::delete().to(usuarios::delete::<MysqlRepository>))
             )
+            .service(
+                web::resource("/pacientes")
+                    .route(web::get().to(pacientes::search::<MysqlRepository>))
+                    .route(web::post().to(pacientes::create::<MysqlRepository>))
+            )
+            .service(
+                web::resource("/pacientes/rut/{rut}")
+                    .route(web::get().to(pacientes::get_by_rut::<MysqlRepository>))
+            )
+            .service(
+                web::resource("/pacientes/{id}/consentimientos")
+                    .route(web::get().to(consentimientos::get_paciente::<MysqlRepository>))
+                    .route(web::post().to(consentimientos::otorgar::<MysqlRepository>))
+            )
+            .service(
+                web::resource("/pacientes/{id}/consentimientos/{proposito}/revocacion")
+                    .route(web::post().to(consentimientos::revocar::<MysqlRepository>))
+            )
+            .service(
+                web::resource("/pacientes/{id}")
+                    .route(web::get().to(pacientes::get_by_id::<MysqlRepository>))
+                    .route(web::put().to(pacientes::update::<MysqlRepository>))
+                    .route(web::delete().to(pacientes::delete::<MysqlRepository>))
+            )
+            .service(
+                web::resource("/previsiones")
+                    .route(web::get().to(previsiones::search::<MysqlRepository>))
+                    .route(web::post().to(previsiones::create::<MysqlRepository>))
+            )
+            .service(
+                web::resource("/previsiones/{cod_prevision}")
+                    .route(web::get().to(previsiones::get_by_id::<MysqlRepository>))
+                    .route(web::put().to(previsiones::update::<MysqlRepository>))
+                    .route(web::delete().to(previsiones::delete::<MysqlRepository>))
+            )
+            .service(
+                web::resource("/profesionales")
+                    .route(web::get().to(profesionales::search::<MysqlRepository>))
+            )
+            .service(
+                web::resource("/profesionales/{id_prof}")
+                    .route(web::get().to(profesionales::get_by_id::<MysqlRepository>))
+            )
+            .service(
+                web::resource("/profesionales/{id_prof}/contratos")
+                    .route(web::get().to(contratos::get_contratos::<MysqlRepository>))
+                    .route(web::post().to(contratos::create::<MysqlRepository>))
+            )
+            .service(
+                web::resource("/profesionales/{id_prof}/contratos/{id}/egreso")
+                    .route(web::put().to(contratos::egreso::<MysqlRepository>))
+            )
+            .service(
+                web::resource("/profesionales/{id_prof}/credenciales")
+                    .route(web::get().to(credenciales::get_credenciales::<MysqlRepository>))
+                    .route(web::post().to(credenciales::subir::<MysqlRepository>))
+            )
+            .service(
+                web::resource("/profesionales/{id_prof}/credenciales/{id}/documento")
+                    .route(web::get().to(credenciales::documento::<MysqlRepository>))
+            )
+            .service(
+                web::resource("/credenciales/por-vencer")
+                    .route(web::get().to(credenciales::por_vencer::<MysqlRepository>))
+            )
+            .service(
+                web::resource("/agenda/bloques")
+                    .route(web::get().to(agenda::get_bloques::<MysqlRepository>))
+                    .route(web::post().to(agenda::create_bloque::<MysqlRepository>))
+            )
+            .service(
+                web::resource("/agenda/bloques/{id}")
+                    .route(web::delete().to(agenda::delete_bloque::<MysqlRepository>))
+            )
+            .service(
+                web::resource("/agenda/disponibilidad")
+                    .route(web::get().to(agenda::disponibilidad::<MysqlRepository>))
+            )
+            .service(
+                web::resource("/agenda/citas")
+                    .route(web::get().to(agenda::get_citas::<MysqlRepository>))
+                    .route(web::post().to(agenda::reservar::<MysqlRepository>))
+            )
+            .service(
+                web::resource("/agenda/citas/{id}")
+                    .route(web::get().to(agenda::get_cita::<MysqlRepository>))
+            )
+            .service(
+                web::resource("/agenda/citas/{id}/cancelar")
+                    .route(web::post().to(agenda::cancelar::<MysqlRepository>))
+            )
+            .service(
+                web::resource("/agenda/citas/{id}/reprogramar")
+                    .route(web::post().to(agenda::reprogramar::<MysqlRepository>))
+            )
+            .service(
+                web::resource("/zonas")
+                    .route(web::get().to(zonas::get_all::<MysqlRepository>))
+            )
+            .service(
+                web::resource("/zonas/{cod_zona}")
+                    .route(web::get().to(zonas::get_by_id::<MysqlRepository>))
+                    .route(web::put().to(zonas::update::<MysqlRepository>))
+            )
+            .service(
+                web::resource("/feriados")
+                    .route(web::get().to(feriados::search::<MysqlRepository>))
+                    .route(web::post().to(feriados::create::<MysqlRepository>))
+            )
+            .service(
+                web::resource("/feriados/{id}")
+                    .route(web::delete().to(feriados::delete::<MysqlRepository>))
+            )
+            .service(
+                web::resource("/atenciones")
+                    .route(web::get().to(atenciones::search::<MysqlRepository>))
+                    .route(web::post().to(atenciones::create::<MysqlRepository>))
+            )
+            .service(
+                web::resource("/atenciones/paciente/{id_paciente}")
+                    .route(web::get().to(atenciones::timeline::<MysqlRepository>))
+            )
+            .service(
+                web::resource("/atenciones/{id}")
+                    .route(web::get().to(atenciones::get_by_id::<MysqlRepository>))
+                    .route(web::put().to(atenciones::update::<MysqlRepository>))
+            )
+            .service(
+                web::resource("/atenciones/{id}/firmar")
+                    .route(web::post().to(atenciones::firmar::<MysqlRepository>))
+            )
+            .service(
+                web::resource("/atenciones/{id}/cargo")
+                    .route(web::post().to(facturacion::recalcular::<MysqlRepository>))
+            )
+            .service(
+                web::resource("/atenciones/{id}/adendas")
+                    .route(web::post().to(atenciones::create_adenda::<MysqlRepository>))
+            )
+            .service(
+                web::resource("/cie10")
+                    .route(web::get().to(cie10::search::<MysqlRepository>))
+            )
+            .service(
+                web::resource("/cie10/importar")
+                    .app_data(web::PayloadConfig::new(TAMANO_MAXIMO_CIE10))
+                    .route(web::post().to(cie10::importar::<MysqlRepository>))
+            )
+            .service(
+                web::resource("/cie10/{codigo}")
+                    .route(web::get().to(cie10::get_by_codigo::<MysqlRepository>))
+            )
+            .service(
+                web::resource("/visitas")
+                    .route(web::get().to(visitas::search::<MysqlRepository>))
+                    .route(web::post().to(visitas::create::<MysqlRepository>))
+            )
+            .service(
+                web::resource("/visitas/ruta")
+                    .route(web::get().to(visitas::ruta::<MysqlRepository>))
+            )
+            .service(
+                web::resource("/visitas/verificacion")
+                    .route(web::get().to(visitas::verificacion::<MysqlRepository>))
+            )
+            .service(
+                web::resource("/visitas/{id}")
+                    .route(web::get().to(visitas::get_by_id::<MysqlRepository>))
+            )
+            .service(
+                web::resource("/visitas/{id}/candidatos")
+                    .route(web::get().to(visitas::candidatos::<MysqlRepository>))
+            )
+            .service(
+                web::resource("/visitas/{id}/asignar")
+                    .route(web::post().to(visitas::asignar::<MysqlRepository>))
+            )
+            .service(
+                web::resource("/visitas/{id}/estado")
+                    .route(web::post().to(visitas::cambiar_estado::<MysqlRepository>))
+            )
+            .service(
+                web::resource("/visitas/{id}/historial")
+                    .route(web::get().to(visitas::historial::<MysqlRepository>))
+            )
+            .service(
+                web::resource("/visitas/{id}/marcas")
+                    .route(web::post().to(visitas::marcar::<MysqlRepository>))
+            )
+            .service(
+                web::resource("/recetas")
+                    .route(web::get().to(recetas::search::<MysqlRepository>))
+                    .route(web::post().to(recetas::create::<MysqlRepository>))
+            )
+            .service(
+                web::resource("/recetas/verificar/{codigo}")
+                    .route(web::get().to(recetas::verificar::<MysqlRepository>))
+            )
+            .service(
+                web::resource("/recetas/{id}")
+                    .route(web::get().to(recetas::get_by_id::<MysqlRepository>))
+            )
+            .service(
+                web::resource("/recetas/{id}/pdf")
+                    .route(web::get().to(recetas::pdf::<MysqlRepository>))
+            )
+            .service(
+                web::resource("/recetas/{id}/anular")
+                    .route(web::post().to(recetas::anular::<MysqlRepository>))
+            )
+            .service(
+                web::resource("/documentos")
+                    .route(web::get().to(documentos::search::<MysqlRepository>))
+                    .route(web::post().to(documentos::create::<MysqlRepository>))
+            )
+            .service(
+                web::resource("/documentos/plantillas")
+                    .route(web::get().to(documentos::plantillas::<MysqlRepository>))
+            )
+            .service(
+                web::resource("/documentos/plantillas/{tipo}")
+                    .route(web::put().to(documentos::crear_plantilla::<MysqlRepository>))
+            )
+            .service(
+                web::resource("/documentos/{id}")
+                    .route(web::get().to(documentos::get_by_id::<MysqlRepository>))
+            )
+            .service(
+                web::resource("/documentos/{id}/versiones")
+                    .route(web::post().to(documentos::crear_version::<MysqlRepository>))
+            )
+            .service(
+                web::resource("/documentos/{id}/pdf")
+                    .route(web::get().to(documentos::pdf::<MysqlRepository>))
+            )
+            .service(
+                web::resource("/profesionales/{id_prof}/claves-firma")
+                    .route(web::get().to(firmas::claves::<MysqlRepository>))
+                    .route(web::post().to(firmas::generar_clave::<MysqlRepository>))
+            )
+            .service(
+                web::resource("/firmas")
+                    .route(web::get().to(firmas::search::<MysqlRepository>))
+                    .route(web::post().to(firmas::create::<MysqlRepository>))
+            )
+            .service(
+                web::resource("/firmas/{id}")
+                    .route(web::get().to(firmas::get_by_id::<MysqlRepository>))
+            )
+            .service(
+                web::resource("/firmas/{id}/verificar")
+                    .route(web::get().to(firmas::verificar::<MysqlRepository>))
+            )
+            .service(
+                web::resource("/agenda/citas/{id}/teleconsulta")
+                    .route(web::get().to(teleconsultas::estado::<MysqlRepository>))
+                    .route(web::post().to(teleconsultas::crear::<MysqlRepository>))
+            )
+            .service(
+                web::resource("/teleconsultas/{id}/ws")
+                    .route(web::get().to(teleconsultas::conectar::<MysqlRepository>))
+            )
+            .service(
+                web::resource("/teleconsultas/{id}/espera")
+                    .route(web::post().to(sala_espera::ingresar::<MysqlRepository>))
+                    .route(web::delete().to(sala_espera::retirarse::<MysqlRepository>))
+            )
+            .service(
+                web::resource("/teleconsultas/{id}/espera/eventos")
+                    .route(web::get().to(sala_espera::eventos_paciente::<MysqlRepository>))
+            )
+            .service(
+                web::resource("/sala-espera/{id_prof}")
+                    .route(web::get().to(sala_espera::get_cola::<MysqlRepository>))
+            )
+            .service(
+                web::resource("/sala-espera/{id_prof}/eventos")
+                    .route(web::get().to(sala_espera::eventos_profesional::<MysqlRepository>))
+            )
+            .service(
+                web::resource("/sala-espera/{id_prof}/llamar")
+                    .route(web::post().to(sala_espera::llamar::<MysqlRepository>))
+            )
+            .service(
+                web::resource("/conversaciones")
+                    .route(web::get().to(mensajes::get_conversaciones::<MysqlRepository>))
+                    .route(web::post().to(mensajes::create_conversacion::<MysqlRepository>))
+            )
+            .service(
+                web::resource("/conversaciones/{id}/mensajes")
+                    .route(web::get().to(mensajes::get_mensajes::<MysqlRepository>))
+                    .route(web::post().to(mensajes::create_mensaje::<MysqlRepository>))
+            )
+            .service(
+                web::resource("/conversaciones/{id}/lecturas")
+                    .route(web::post().to(mensajes::marcar_leido::<MysqlRepository>))
+            )
+            .service(
+                web::resource("/conversaciones/{id}/ws")
+                    .route(web::get().to(mensajes::conectar::<MysqlRepository>))
+            )
+            .service(
+                web::resource("/notificaciones")
+                    .route(web::get().to(notificaciones::search::<MysqlRepository>))
+                    .route(web::post().to(notificaciones::create::<MysqlRepository>))
+            )
+            .service(
+                web::resource("/notificaciones/preferencias/{tipo}/{id}")
+                    .route(web::get().to(notificaciones::get_preferencias::<MysqlRepository>))
+                    .route(web::put().to(notificaciones::update_preferencias::<MysqlRepository>))
+            )
+            .service(
+                web::resource("/notificaciones/{id}")
+                    .route(web::get().to(notificaciones::get_by_id::<MysqlRepository>))
+            )
+            .service(
+                web::resource("/notificaciones/{id}/leida")
+                    .route(web::post().to(notificaciones::leer::<MysqlRepository>))
+            )
+            .service(
+                web::resource("/agenda/citas/{id}/respuesta")
+                    .route(web::get().to(recordatorios::preguntar::<MysqlRepository>))
+                    .route(web::post().to(recordatorios::responder::<MysqlRepository>))
+            )
+            .service(
+                web::resource("/agenda/sin-confirmar")
+                    .route(web::get().to(recordatorios::sin_confirmar::<MysqlRepository>))
+            )
+            .service(
+                web::resource("/agenda/confirmaciones")
+                    .route(web::get().to(recordatorios::resumen::<MysqlRepository>))
+            )
+            .service(
+                web::resource("/adjuntos")
+                    .route(web::get().to(adjuntos::search::<MysqlRepository>))
+                    .route(web::post().to(adjuntos::subir::<MysqlRepository>))
+            )
+            .service(
+                web::resource("/adjuntos/{id}")
+                    .route(web::get().to(adjuntos::get_by_id::<MysqlRepository>))
+            )
+            .service(
+                web::resource("/adjuntos/{id}/enlace")
+                    .route(web::get().to(adjuntos::enlace::<MysqlRepository>))
+            )
+            .service(
+                web::resource("/adjuntos/{id}/contenido")
+                    .route(web::get().to(adjuntos::contenido::<MysqlRepository>))
+            )
+            .service(
+                web::resource("/derivaciones")
+                    .route(web::get().to(derivaciones::search::<MysqlRepository>))
+            )
+            .service(
+                web::resource("/derivaciones/{id}")
+                    .route(web::get().to(derivaciones::get_by_id::<MysqlRepository>))
+            )
+            .service(
+                web::resource("/consentimientos/plantillas")
+                    .route(web::get().to(consentimientos::plantillas::<MysqlRepository>))
+            )
+            .service(
+                web::resource("/consentimientos/plantillas/{proposito}")
+                    .route(web::get().to(consentimientos::versiones::<MysqlRepository>))
+                    .route(web::put().to(consentimientos::crear_plantilla::<MysqlRepository>))
+            )
+            .service(
+                web::resource("/facturacion/tarifas")
+                    .route(web::get().to(facturacion::tarifas::<MysqlRepository>))
+                    .route(web::post().to(facturacion::create_tarifa::<MysqlRepository>))
+            )
+            .service(
+                web::resource("/facturacion/cargos")
+                    .route(web::get().to(facturacion::cargos::<MysqlRepository>))
+            )
+            .service(
+                web::resource("/facturacion/lotes")
+                    .route(web::get().to(facturacion::lotes::<MysqlRepository>))
+                    .route(web::post().to(facturacion::crear_lote::<MysqlRepository>))
+            )
+            .service(
+                web::resource("/facturacion/lotes/{id}")
+                    .route(web::get().to(facturacion::get_lote::<MysqlRepository>))
+            )
+            .service(
+                web::resource("/facturacion/lotes/{id}/exportacion")
+                    .route(web::get().to(facturacion::exportar::<MysqlRepository>))
+            )
+            .service(
+                web::resource("/honorarios/tarifas")
+                    .route(web::get().to(honorarios::tarifas::<MysqlRepository>))
+                    .route(web::post().to(honorarios::create_tarifa::<MysqlRepository>))
+            )
+            .service(
+                web::resource("/honorarios/retenciones")
+                    .route(web::get().to(honorarios::retenciones::<MysqlRepository>))
+                    .route(web::put().to(honorarios::guardar_retencion::<MysqlRepository>))
+            )
+            .service(
+                web::resource("/honorarios/liquidaciones")
+                    .route(web::get().to(honorarios::liquidaciones::<MysqlRepository>))
+            )
+            .service(
+                web::resource("/honorarios/liquidaciones/{id_prof}")
+                    .route(web::get().to(honorarios::get_liquidacion::<MysqlRepository>))
+            )
+            .service(
+                web::resource("/honorarios/planilla")
+                    .route(web::get().to(honorarios::planilla::<MysqlRepository>))
+            )
+    );
+    cfg.service(
+        web::scope("/fhir")
+            .service(
+                web::resource("")
+                    .route(web::post().to(fhir::transaccion::<MysqlRepository>))
+            )
+            .service(
+                web::resource("/Patient")
+                    .route(web::get().to(fhir::search_pacientes::<MysqlRepository>))
+            )
+            .service(
+                web::resource("/Patient/{id}")
+                    .route(web::get().to(fhir::get_paciente::<MysqlRepository>))
+            )
+            .service(
+                web::resource("/Practitioner")
+                    .route(web::get().to(fhir::search_profesionales::<MysqlRepository>))
+            )
+            .service(
+                web::resource("/Practitioner/{id}")
+                    .route(web::get().to(fhir::get_profesional::<MysqlRepository>))
+            )
+            .service(
+                web::resource("/Encounter")
+                    .route(web::get().to(fhir::search_encuentros::<MysqlRepository>))
+            )
+            .service(
+                web::resource("/Encounter/{id}")
+                    .route(web::get().to(fhir::get_encuentro::<MysqlRepository>))
+            )
+            .service(
+                web::resource("/Organization")
+                    .route(web::get().to(fhir::search_organizaciones::<MysqlRepository>))
+            )
+            .service(
+                web::resource("/Organization/{id}")
+                    .route(web::get().to(fhir::get_organizacion::<MysqlRepository>))
+            )
     );
-}
\ No newline at end of file
+}
@@ -0,0 +1,380 @@
+use std::collections::HashMap;
+use std::time::Duration as Intervalo;
+
+use actix_web::{rt, web, HttpResponse};
+use chrono::Duration;
+use tracing::{error, warn};
+use crate::{
+    models::{
+        tipo_destinatario, Notificacion, NotificacionFiltro, NotificacionInput, PreferenciaInput,
+        PreferenciaNotificacion, CANALES, CANAL_APP, DESTINATARIO_PACIENTE, DESTINATARIO_PROFESIONAL,
+        NOTIFICACION_ENVIADA, NOTIFICACION_FALLIDA, NOTIFICACION_PENDIENTE,
+    },
+    notificaciones,
+    transportes::Envio,
+    tiempo,
+    app_state::AppState,
+    error::AppError
+};
+use super::super::repositories::{NotificacionRepository, PacienteRepository, ProfesionalRepository, UsuarioRepository};
+
+/// Cada cuánto se revisa el outbox y cuántas notificaciones se envían por vuelta.
+const DESPACHO: Intervalo = Intervalo::from_secs(15);
+const LOTE: u32 = 20;
+/// Tope de cada envío, sea cual sea el transporte. Un lote completo debe
+/// terminar dentro de la reserva; si no, otra vuelta reclamaría las
+/// notificaciones que aún no se envían y saldrían dos veces.
+const ESPERA_ENVIO: Intervalo = Intervalo::from_secs(15);
+const _: () = assert!(LOTE as u64 * ESPERA_ENVIO.as_secs() * 2 <= notificaciones::RESERVA_MIN as u64 * 60);
+
+/// Correo y teléfonos del destinatario; `None` si no existe.
+async fn contacto<R>(data: &AppState<R>, tipo: &str, id: u32) -> Result<Option<(Option<String>, Option<String>)>, AppError>
+where
+    R: UsuarioRepository + PacienteRepository + ProfesionalRepository,
+{
+    Ok(match tipo {
+        DESTINATARIO_PACIENTE => PacienteRepository::get_by_id(data.paciente_repo.as_ref(), id).await?
+            .map(|p| (p.email, p.telefonos)),
+        DESTINATARIO_PROFESIONAL => ProfesionalRepository::get_by_id(data.profesional_repo.as_ref(), id).await?
+            .map(|p| (p.email, p.telefonos)),
+        _ => UsuarioRepository::get_by_id(data.usuario_repo.as_ref(), id).await?
+            .map(|u| (Some(u.email), Some(u.telefonos))),
+    })
+}
+
+/// Deja en el outbox una notificación por cada canal que el destinatario
+/// acepta y para el que tiene dirección. Retorna las encoladas, que
+/// pueden ser ninguna.
+pub async fn encolar<R>(
+    data: &AppState<R>,
+    plantilla: &str,
+    destinatario_tipo: &str,
+    destinatario_id: u32,
+    variables: &HashMap<String, String>,
+) -> Result<Vec<Notificacion>, AppError>
+where
+    R: NotificacionRepository + UsuarioRepository + PacienteRepository + ProfesionalRepository,
+{
+    let plantilla = notificaciones::plantilla(plantilla)?;
+    let tipo = tipo_destinatario(destinatario_tipo)?;
+    let (email, telefonos) = contacto(data, &tipo, destinatario_id).await?
+        .ok_or_else(|| AppError::Validation(format!("Destinatario {} {} no existe", tipo, destinatario_id)))?;
+    let preferencias = data.notificacion_repo.preferencias(&tipo, destinatario_id).await?
+        .unwrap_or_else(|| PreferenciaNotificacion::por_defecto(&tipo, destinatario_id));
+
+    let ahora = tiempo::ahora();
+    let mut encoladas = Vec::new();
+    for canal in CANALES.into_iter().filter(|c| preferencias.acepta(c)) {
+        let direccion = notificaciones::direccion(canal, email.as_deref(), telefonos.as_deref());
+        if canal != CANAL_APP && direccion.is_none() {
+            continue;
+        }
+        let (asunto, cuerpo) = notificaciones::componer(plantilla, canal, variables)?;
+        // Las de la aplicación no se despachan: quedan disponibles de inmediato
+        let en_app = canal == CANAL_APP;
+        let mut notificacion = Notificacion {
+            id: 0,
+            plantilla: plantilla.codigo.into(),
+            destinatario_tipo: tipo.clone(),
+            destinatario_id,
+            canal: canal.into(),
+            direccion,
+            asunto,
+            cuerpo,
+            estado: if en_app { NOTIFICACION_ENVIADA } else { NOTIFICACION_PENDIENTE }.into(),
+            intentos: 0,
+            proximo_intento_en: ahora,
+            ultimo_error: None,
+            creada_en: ahora,
+            enviada_en: en_app.then_some(ahora),
+            leida_en: None,
+        };
+        notificacion.id = data.notificacion_repo.encolar(&notificacion).await?;
+        encoladas.push(notificacion);
+    }
+    Ok(encoladas)
+}
+
+/// Intenta enviar las notificaciones pendientes cuyo turno llegó. Un
+/// fallo reprograma con espera exponencial; al agotar los intentos, o si
+/// el error es de validación (una dirección que el transporte rechaza no
+/// mejora reintentando), la notificación queda FALLIDA. Retorna cuántas
+/// se enviaron.
+pub async fn despachar_pendientes<R>(data: &AppState<R>) -> Result<usize, AppError>
+where
+    R: NotificacionRepository,
+{
+    let mut enviadas = 0;
+    let ahora = tiempo::ahora();
+    let reservar_hasta = ahora + Duration::minutes(notificaciones::RESERVA_MIN);
+    for mut notificacion in data.notificacion_repo.reclamar(ahora, reservar_hasta, LOTE).await? {
+        let resultado = match (data.transportes.para(&notificacion.canal), notificacion.direccion.as_deref()) {
+            (Some(transporte), Some(direccion)) => {
+                let envio = Envio { direccion, asunto: &notificacion.asunto, cuerpo: &notificacion.cuerpo };
+                rt::time::timeout(ESPERA_ENVIO, transporte.enviar(&envio))
+                    .await
+                    .unwrap_or_else(|_| Err(AppError::Internal(format!("{} no respondió a tiempo", notificacion.canal))))
+            }
+            _ => Err(AppError::Internal(format!("Sin transporte o dirección para {}", notificacion.canal))),
+        };
+
+        let ahora = tiempo::ahora();
+        notificacion.intentos = notificacion.intentos.saturating_add(1);
+        match resultado {
+            Ok(()) => {
+                notificacion.estado = NOTIFICACION_ENVIADA.into();
+                notificacion.enviada_en = Some(ahora);
+                notificacion.ultimo_error = None;
+                enviadas += 1;
+            }
+            Err(e) => {
+                warn!("Notificación {} (intento {}): {}", notificacion.id, notificacion.intentos, e);
+                if notificacion.intentos >= notificaciones::MAXIMO_INTENTOS || matches!(e, AppError::Validation(_)) {
+                    notificacion.estado = NOTIFICACION_FALLIDA.into();
+                }
+                notificacion.proximo_intento_en = notificaciones::proximo_intento(notificacion.intentos, ahora);
+                notificacion.ultimo_error = Some(e.to_string());
+            }
+        }
+        data.notificacion_repo.registrar_intento(&notificacion).await?;
+    }
+    Ok(enviadas)
+}
+
+/// Despacha el outbox periódicamente.
+pub async fn despachar<R>(data: AppState<R>)
+where
+    R: NotificacionRepository,
+{
+    let mut intervalo = rt::time::interval(DESPACHO);
+    loop {
+        intervalo.tick().await;
+        if let Err(e) = despachar_pendientes(&data).await {
+            error!("Notificaciones: {}", e);
+        }
+    }
+}
+
+/// Encola una notificación, por ejemplo una alerta manual.
+pub async fn create<R>(
+    input: web::Json<NotificacionInput>,
+    data: web::Data<AppState<R>>,
+) -> Result<HttpResponse, AppError>
+where
+    R: NotificacionRepository + UsuarioRepository + PacienteRepository + ProfesionalRepository + 'static,
+{
+    let encoladas = encolar(&data, &input.plantilla, &input.destinatario_tipo, input.destinatario_id, &input.variables).await?;
+    if encoladas.is_empty() {
+        return Err(AppError::Validation("El destinatario no tiene canales habilitados con dirección".into()));
+    }
+    Ok(HttpResponse::Created().json(encoladas))
+}
+
+/// Seguimiento del outbox y bandeja de la aplicación
+/// (`canal=APP&destinatario_tipo=...&destinatario_id=...`).
+pub async fn search<R>(
+    filtro: web::Query<NotificacionFiltro>,
+    data: web::Data<AppState<R>>,
+) -> Result<HttpResponse, AppError>
+where
+    R: NotificacionRepository + 'static,
+{
+    Ok(HttpResponse::Ok().json(data.notificacion_repo.notificaciones(&filtro).await?))
+}
+
+pub async fn get_by_id<R>(
+    id: web::Path<u32>,
+    data: web::Data<AppState<R>>,
+) -> Result<HttpResponse, AppError>
+where
+    R: NotificacionRepository + 'static,
+{
+    let notificacion = data.notificacion_repo.get_notificacion(id.into_inner()).await?.ok_or(AppError::NotFound)?;
+    Ok(HttpResponse::Ok().json(notificacion))
+}
+
+/// Marca como leída una notificación de la aplicación.
+pub async fn leer<R>(
+    id: web::Path<u32>,
+    data: web::Data<AppState<R>>,
+) -> Result<HttpResponse, AppError>
+where
+    R: NotificacionRepository + 'static,
+{
+    let id = id.into_inner();
+    let notificacion = data.notificacion_repo.get_notificacion(id).await?.ok_or(AppError::NotFound)?;
+    if notificacion.canal != CANAL_APP {
+        return Err(AppError::Validation("Sólo las notificaciones de la aplicación se marcan como leídas".into()));
+    }
+    data.notificacion_repo.marcar_leida(id, tiempo::ahora()).await?;
+    Ok(HttpResponse::NoContent().finish())
+}
+
+pub async fn get_preferencias<R>(
+    path: web::Path<(String, u32)>,
+    data: web::Data<AppState<R>>,
+) -> Result<HttpResponse, AppError>
+where
+    R: NotificacionRepository + 'static,
+{
+    let (tipo, id) = path.into_inner();
+    let tipo = tipo_destinatario(&tipo)?;
+    let preferencias = data.notificacion_repo.preferencias(&tipo, id).await?
+        .unwrap_or_else(|| PreferenciaNotificacion::por_defecto(&tipo, id));
+    Ok(HttpResponse::Ok().json(preferencias))
+}
+
+pub async fn update_preferencias<R>(
+    path: web::Path<(String, u32)>,
+    input: web::Json<PreferenciaInput>,
+    data: web::Data<AppState<R>>,
+) -> Result<HttpResponse, AppError>
+where
+    R: NotificacionRepository + UsuarioRepository + PacienteRepository + ProfesionalRepository + 'static,
+{
+    let (tipo, id) = path.into_inner();
+    let tipo = tipo_destinatario(&tipo)?;
+    if contacto(&data, &tipo, id).await?.is_none() {
+        return Err(AppError::NotFound);
+    }
+    let preferencias = PreferenciaNotificacion {
+        destinatario_tipo: tipo,
+        destinatario_id: id,
+        email: input.email,
+        sms: input.sms,
+        app: input.app,
+    };
+    data.notificacion_repo.guardar_preferencias(&preferencias).await?;
+    Ok(HttpResponse::Ok().json(preferencias))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use actix_web::{test, App};
+    use crate::{
+        models::{Paciente, CANAL_EMAIL, CANAL_SMS},
+        repositories::{fixtures, MockRepository},
+        transportes::{Transporte, Transportes},
+    };
+    use super::*;
+
+    /// Guarda las direcciones a las que envía; falla si `falla` está activo
+    /// y rechaza toda dirección si `rechaza` lo está.
+    #[derive(Default)]
+    struct Prueba {
+        enviados: Mutex<Vec<String>>,
+        falla: bool,
+        rechaza: bool,
+    }
+
+    #[async_trait::async_trait]
+    impl Transporte for Prueba {
+        async fn enviar(&self, envio: &Envio<'_>) -> Result<(), AppError> {
+            if self.falla {
+                return Err(AppError::Internal("proveedor caído".into()));
+            }
+            if self.rechaza {
+                return Err(AppError::Validation(format!("Dirección {} inválida", envio.direccion)));
+            }
+            self.enviados.lock().unwrap().push(envio.direccion.into());
+            Ok(())
+        }
+    }
+
+    async fn app_state(email: Arc<Prueba>, sms: Arc<Prueba>) -> AppState<MockRepository> {
+        let paciente = Paciente {
+            email: Some("ana@correo.cl".into()),
+            telefonos: Some("987654321".into()),
+            cod_cliente: Some(1),
+            ..fixtures::paciente("11.111.111-1")
+        };
+        fixtures::app_state(Vec::new(), vec![paciente]).await.with_transportes(Transportes { email, sms })
+    }
+
+    #[actix_web::test]
+    async fn encola_por_canal_y_reintenta() {
+        let email = Arc::new(Prueba::default());
+        let sms = Arc::new(Prueba { falla: true, ..Default::default() });
+        let state = app_state(email.clone(), sms.clone()).await;
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(state.clone()))
+                .route("/notificaciones", web::post().to(create::<MockRepository>))
+                .route("/notificaciones", web::get().to(search::<MockRepository>))
+                .route("/notificaciones/{id}/leida", web::post().to(leer::<MockRepository>))
+                .route("/notificaciones/preferencias/{tipo}/{id}", web::put().to(update_preferencias::<MockRepository>)),
+        )
+        .await;
+
+        let alerta = serde_json::json!({
+            "plantilla": "ALERTA",
+            "destinatario_tipo": "paciente",
+            "destinatario_id": 1,
+            "variables": {"titulo": "Resultados disponibles", "detalle": "Ya puede revisar sus exámenes."},
+        });
+        let req = test::TestRequest::post().uri("/notificaciones").set_json(&alerta).to_request();
+        let encoladas: Vec<Notificacion> = test::call_and_read_body_json(&app, req).await;
+        let canales: Vec<&str> = encoladas.iter().map(|n| n.canal.as_str()).collect();
+        assert_eq!(canales, vec![CANAL_EMAIL, CANAL_SMS, CANAL_APP]);
+        assert_eq!(encoladas[1].direccion.as_deref(), Some("+56987654321"));
+
+        assert_eq!(despachar_pendientes(&state).await.unwrap(), 1);
+        assert_eq!(*email.enviados.lock().unwrap(), vec!["ana@correo.cl"]);
+        let fallida = state.notificacion_repo.get_notificacion(2).await.unwrap().unwrap();
+        assert_eq!((fallida.estado.as_str(), fallida.intentos), (NOTIFICACION_PENDIENTE, 1));
+        assert!(fallida.proximo_intento_en > tiempo::ahora());
+        assert_eq!(fallida.ultimo_error.as_deref(), Some("Internal error: proveedor caído"));
+        // Sin que llegue su turno no se reintenta
+        assert_eq!(despachar_pendientes(&state).await.unwrap(), 0);
+        assert_eq!(state.notificacion_repo.get_notificacion(2).await.unwrap().unwrap().intentos, 1);
+
+        let req = test::TestRequest::post().uri("/notificaciones/3/leida").to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), 204);
+        let req = test::TestRequest::get().uri("/notificaciones?canal=app&destinatario_tipo=PACIENTE&destinatario_id=1").to_request();
+        let bandeja: Vec<Notificacion> = test::call_and_read_body_json(&app, req).await;
+        assert!(bandeja[0].leida_en.is_some());
+
+        // Sólo correo
+        let req = test::TestRequest::put().uri("/notificaciones/preferencias/PACIENTE/1")
+            .set_json(serde_json::json!({"email": true, "sms": false, "app": false}))
+            .to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), 200);
+        let req = test::TestRequest::post().uri("/notificaciones").set_json(&alerta).to_request();
+        let encoladas: Vec<Notificacion> = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(encoladas.len(), 1);
+
+        let mut sin_variable = alerta.clone();
+        sin_variable["variables"] = serde_json::json!({"titulo": "x"});
+        let req = test::TestRequest::post().uri("/notificaciones").set_json(&sin_variable).to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), 400);
+    }
+
+    #[actix_web::test]
+    async fn reclama_y_no_reintenta_rechazos() {
+        let email = Arc::new(Prueba { rechaza: true, ..Default::default() });
+        let sms = Arc::new(Prueba { falla: true, ..Default::default() });
+        let state = app_state(email, sms).await;
+        let variables = [("titulo", "Aviso"), ("detalle", "Texto")].into_iter().map(|(k, v)| (k.to_string(), v.to_string())).collect();
+        encolar(&state, "ALERTA", DESTINATARIO_PACIENTE, 1, &variables).await.unwrap();
+
+        // Reclamadas, otro despachador no las ve hasta que venza la reserva
+        let ahora = tiempo::ahora();
+        let reclamadas = state.notificacion_repo.reclamar(ahora, ahora + Duration::minutes(10), 10).await.unwrap();
+        assert_eq!(reclamadas.len(), 2);
+        assert!(state.notificacion_repo.reclamar(ahora, ahora, 10).await.unwrap().is_empty());
+        let despues = ahora + Duration::minutes(10);
+        assert_eq!(state.notificacion_repo.reclamar(despues, despues, 10).await.unwrap().len(), 2);
+
+        for mut n in state.notificacion_repo.notificaciones(&Default::default()).await.unwrap() {
+            n.proximo_intento_en = ahora;
+            state.notificacion_repo.registrar_intento(&n).await.unwrap();
+        }
+        assert_eq!(despachar_pendientes(&state).await.unwrap(), 0);
+        let correo = state.notificacion_repo.get_notificacion(1).await.unwrap().unwrap();
+        assert_eq!((correo.estado.as_str(), correo.intentos), (NOTIFICACION_FALLIDA, 1));
+        let sms = state.notificacion_repo.get_notificacion(2).await.unwrap().unwrap();
+        assert_eq!(sms.estado, NOTIFICACION_PENDIENTE);
+    }
+}
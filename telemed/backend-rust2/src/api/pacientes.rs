@@ -0,0 +1,209 @@
+use actix_web::{web, HttpResponse};
+use chrono::Local;
+use crate::{
+    models::{Paciente, PacienteInput, PacienteFiltro},
+    app_state::AppState,
+    error::AppError,
+    rut,
+};
+use super::super::repositories::{PacienteRepository, PrevisionRepository};
+use super::previsiones;
+
+pub async fn get_by_id<R>(
+    id: web::Path<u32>,
+    data: web::Data<AppState<R>>,
+) -> Result<HttpResponse, AppError>
+where
+    R: PacienteRepository + 'static,
+{
+    let paciente = data.paciente_repo.get_by_id(id.into_inner()).await?;
+    match paciente {
+        Some(p) => Ok(HttpResponse::Ok().json(p)),
+        None => Err(AppError::NotFound),
+    }
+}
+
+pub async fn get_by_rut<R>(
+    rut: web::Path<String>,
+    data: web::Data<AppState<R>>,
+) -> Result<HttpResponse, AppError>
+where
+    R: PacienteRepository + 'static,
+{
+    let rut = rut::normalizar(&rut.into_inner())?;
+    let paciente = data.paciente_repo.get_by_rut(&rut).await?;
+    match paciente {
+        Some(p) => Ok(HttpResponse::Ok().json(p)),
+        None => Err(AppError::NotFound),
+    }
+}
+
+pub async fn search<R>(
+    filtro: web::Query<PacienteFiltro>,
+    data: web::Data<AppState<R>>,
+) -> Result<HttpResponse, AppError>
+where
+    R: PacienteRepository + 'static,
+{
+    let mut filtro = filtro.into_inner();
+    filtro.rut = filtro.rut.as_deref().map(rut::normalizar).transpose()?;
+    let pacientes = data.paciente_repo.search(&filtro).await?;
+    Ok(HttpResponse::Ok().json(pacientes))
+}
+
+pub async fn create<R>(
+    paciente: web::Json<PacienteInput>,
+    data: web::Data<AppState<R>>,
+) -> Result<HttpResponse, AppError>
+where
+    R: PacienteRepository + PrevisionRepository + 'static,
+{
+    let paciente: Paciente = paciente.into_inner().try_into()?;
+    if let Some(cod) = paciente.cod_prevision {
+        previsiones::validar_vigente(data.prevision_repo.as_ref(), cod, Local::now().date_naive()).await?;
+    }
+    if PacienteRepository::get_by_rut(data.paciente_repo.as_ref(), &paciente.rut).await?.is_some() {
+        return Err(AppError::Conflict(format!("Ya existe un paciente con RUT {}", paciente.rut)));
+    }
+    let id = PacienteRepository::create(data.paciente_repo.as_ref(), &paciente).await?;
+    Ok(HttpResponse::Created().json(serde_json::json!({"id": id})))
+}
+
+pub async fn update<R>(
+    id: web::Path<u32>,
+    paciente: web::Json<PacienteInput>,
+    data: web::Data<AppState<R>>,
+) -> Result<HttpResponse, AppError>
+where
+    R: PacienteRepository + PrevisionRepository + 'static,
+{
+    let mut paciente: Paciente = paciente.into_inner().try_into()?;
+    paciente.id = id.into_inner();
+    if let Some(cod) = paciente.cod_prevision {
+        previsiones::validar_vigente(data.prevision_repo.as_ref(), cod, Local::now().date_naive()).await?;
+    }
+    if PacienteRepository::get_by_id(data.paciente_repo.as_ref(), paciente.id).await?.is_none() {
+        return Err(AppError::NotFound);
+    }
+    if let Some(otro) = PacienteRepository::get_by_rut(data.paciente_repo.as_ref(), &paciente.rut).await?
+        && otro.id != paciente.id
+    {
+        return Err(AppError::Conflict(format!("Ya existe un paciente con RUT {}", paciente.rut)));
+    }
+    PacienteRepository::update(data.paciente_repo.as_ref(), &paciente).await?;
+    Ok(HttpResponse::Ok().finish())
+}
+
+pub async fn delete<R>(
+    id: web::Path<u32>,
+    data: web::Data<AppState<R>>,
+) -> Result<HttpResponse, AppError>
+where
+    R: PacienteRepository + 'static,
+{
+    let deleted = data.paciente_repo.delete(id.into_inner()).await?;
+    if deleted {
+        Ok(HttpResponse::NoContent().finish())
+    } else {
+        Err(AppError::NotFound)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_web::{test, web, App};
+    use chrono::NaiveDate;
+    use crate::{app_state::AppState, models::Prevision, repositories::{fixtures, MockRepository}};
+    use super::*;
+
+    async fn app_state() -> AppState<MockRepository> {
+        let repo = MockRepository::default();
+        PrevisionRepository::create(&repo, &Prevision {
+            cod_prevision: 1,
+            tipo: "FONASA".into(),
+            nombre: "FONASA TRAMO A".into(),
+            tramo: Some("A".into()),
+            vigencia_desde: NaiveDate::from_ymd_opt(2000, 1, 1).unwrap(),
+            vigencia_hasta: None,
+        }).await.unwrap();
+        AppState::new(repo)
+    }
+
+    #[actix_web::test]
+    async fn crea_y_busca_por_rut_y_nombre() {
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(app_state().await))
+                .route("/pacientes", web::get().to(search::<MockRepository>))
+                .route("/pacientes", web::post().to(create::<MockRepository>)),
+        )
+        .await;
+
+        let req = test::TestRequest::post()
+            .uri("/pacientes")
+            .set_json(serde_json::json!({
+                "rut": "10.895.960-6",
+                "nombres": "Claudio",
+                "ap_paterno": "Sáez",
+                "ap_materno": "Catalán",
+                "cod_prevision": 1,
+                "cod_cliente": 1,
+                "estatus": 1
+            }))
+            .to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), 201);
+
+        let req = test::TestRequest::get().uri("/pacientes?rut=10895960-6").to_request();
+        let encontrados: Vec<Paciente> = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(encontrados.len(), 1);
+        assert_eq!(encontrados[0].cod_prevision, Some(1));
+
+        let req = test::TestRequest::get().uri("/pacientes?nombre=claudio").to_request();
+        let encontrados: Vec<Paciente> = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(encontrados.len(), 1);
+    }
+
+    #[actix_web::test]
+    async fn rechaza_rut_duplicado() {
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(app_state().await))
+                .route("/pacientes", web::post().to(create::<MockRepository>))
+                .route("/pacientes/{id}", web::put().to(update::<MockRepository>)),
+        )
+        .await;
+
+        let body = serde_json::json!({
+            "rut": "12.602.780-K",
+            "nombres": "Cynthia",
+            "ap_paterno": "Bravo",
+            "estatus": 1
+        });
+        let req = test::TestRequest::post().uri("/pacientes").set_json(&body).to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), 201);
+
+        let req = test::TestRequest::post().uri("/pacientes").set_json(&body).to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), 409);
+
+        // Tampoco al cambiarle el RUT a otro paciente, pero sí al conservar el propio
+        let otro = serde_json::json!({"rut": "10.895.960-6", "nombres": "Claudio", "ap_paterno": "Sáez", "estatus": 1});
+        let req = test::TestRequest::post().uri("/pacientes").set_json(&otro).to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), 201);
+        let req = test::TestRequest::put().uri("/pacientes/2").set_json(&body).to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), 409);
+        let req = test::TestRequest::put().uri("/pacientes/2").set_json(&otro).to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), 200);
+    }
+
+    #[actix_web::test]
+    async fn conflicto_si_el_rut_se_registra_tras_la_verificacion() {
+        // Otra solicitud inserta el RUT entre la consulta previa y el INSERT
+        let state = app_state().await;
+        let paciente = fixtures::paciente("12602780-K");
+        PacienteRepository::create(state.paciente_repo.as_ref(), &paciente).await.unwrap();
+        match PacienteRepository::create(state.paciente_repo.as_ref(), &paciente).await {
+            Err(AppError::Conflict(e)) => assert!(e.contains("12602780-K"), "{}", e),
+            otro => panic!("{:?}", otro),
+        }
+    }
+}
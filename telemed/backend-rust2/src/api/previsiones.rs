@@ -0,0 +1,92 @@
+use actix_web::{web, HttpResponse};
+use chrono::NaiveDate;
+use crate::{
+    models::{Prevision, PrevisionInput, PrevisionFiltro},
+    app_state::AppState,
+    error::AppError
+};
+use super::super::repositories::PrevisionRepository;
+
+/// Verifica que la previsión exista en el catálogo y esté vigente en `fecha`.
+pub async fn validar_vigente<R>(repo: &R, cod_prevision: u32, fecha: NaiveDate) -> Result<Prevision, AppError>
+where
+    R: PrevisionRepository,
+{
+    match repo.get_by_id(cod_prevision).await? {
+        Some(p) if p.vigente_en(fecha) => Ok(p),
+        Some(p) => Err(AppError::Validation(format!("La previsión {} no está vigente al {}", p.nombre, fecha))),
+        None => Err(AppError::Validation(format!("Previsión {} no existe", cod_prevision))),
+    }
+}
+
+pub async fn get_by_id<R>(
+    cod_prevision: web::Path<u32>,
+    data: web::Data<AppState<R>>,
+) -> Result<HttpResponse, AppError>
+where
+    R: PrevisionRepository + 'static,
+{
+    let prevision = data.prevision_repo.get_by_id(cod_prevision.into_inner()).await?;
+    match prevision {
+        Some(p) => Ok(HttpResponse::Ok().json(p)),
+        None => Err(AppError::NotFound),
+    }
+}
+
+pub async fn search<R>(
+    filtro: web::Query<PrevisionFiltro>,
+    data: web::Data<AppState<R>>,
+) -> Result<HttpResponse, AppError>
+where
+    R: PrevisionRepository + 'static,
+{
+    let previsiones = data.prevision_repo.search(&filtro).await?;
+    Ok(HttpResponse::Ok().json(previsiones))
+}
+
+pub async fn create<R>(
+    prevision: web::Json<PrevisionInput>,
+    data: web::Data<AppState<R>>,
+) -> Result<HttpResponse, AppError>
+where
+    R: PrevisionRepository + 'static,
+{
+    let prevision: Prevision = prevision.into_inner().try_into()?;
+    if data.prevision_repo.get_by_id(prevision.cod_prevision).await?.is_some() {
+        return Err(AppError::Validation(format!("Ya existe la previsión {}", prevision.cod_prevision)));
+    }
+    data.prevision_repo.create(&prevision).await?;
+    Ok(HttpResponse::Created().finish())
+}
+
+pub async fn update<R>(
+    cod_prevision: web::Path<u32>,
+    prevision: web::Json<PrevisionInput>,
+    data: web::Data<AppState<R>>,
+) -> Result<HttpResponse, AppError>
+where
+    R: PrevisionRepository + 'static,
+{
+    let mut prevision: Prevision = prevision.into_inner().try_into()?;
+    prevision.cod_prevision = cod_prevision.into_inner();
+    if data.prevision_repo.get_by_id(prevision.cod_prevision).await?.is_none() {
+        return Err(AppError::NotFound);
+    }
+    data.prevision_repo.update(&prevision).await?;
+    Ok(HttpResponse::Ok().finish())
+}
+
+pub async fn delete<R>(
+    cod_prevision: web::Path<u32>,
+    data: web::Data<AppState<R>>,
+) -> Result<HttpResponse, AppError>
+where
+    R: PrevisionRepository + 'static,
+{
+    let deleted = data.prevision_repo.delete(cod_prevision.into_inner()).await?;
+    if deleted {
+        Ok(HttpResponse::NoContent().finish())
+    } else {
+        Err(AppError::NotFound)
+    }
+}
@@ -0,0 +1,32 @@
+use actix_web::{web, HttpResponse};
+use crate::{
+    models::ProfesionalFiltro,
+    app_state::AppState,
+    error::AppError
+};
+use super::super::repositories::ProfesionalRepository;
+
+pub async fn get_by_id<R>(
+    id_prof: web::Path<u32>,
+    data: web::Data<AppState<R>>,
+) -> Result<HttpResponse, AppError>
+where
+    R: ProfesionalRepository + 'static,
+{
+    let profesional = data.profesional_repo.get_by_id(id_prof.into_inner()).await?;
+    match profesional {
+        Some(p) => Ok(HttpResponse::Ok().json(p)),
+        None => Err(AppError::NotFound),
+    }
+}
+
+pub async fn search<R>(
+    filtro: web::Query<ProfesionalFiltro>,
+    data: web::Data<AppState<R>>,
+) -> Result<HttpResponse, AppError>
+where
+    R: ProfesionalRepository + 'static,
+{
+    let profesionales = data.profesional_repo.search(&filtro).await?;
+    Ok(HttpResponse::Ok().json(profesionales))
+}
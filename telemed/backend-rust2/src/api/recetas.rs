@@ -0,0 +1,213 @@
+use actix_web::{web, HttpResponse};
+use crate::{
+    models::{AnulacionInput, RecetaDetalle, RecetaFiltro, RecetaInput, VerificacionReceta, FIRMA_RECETA, RECETA_EMITIDA},
+    recetas, tiempo,
+    app_state::AppState,
+    error::AppError
+};
+use super::super::repositories::{
+    AtencionRepository, FirmaRepository, PacienteRepository, ProfesionalRepository, RecetaRepository, ZonaRepository,
+};
+use super::{firmas, zonas};
+
+pub async fn get_by_id<R>(
+    id: web::Path<u32>,
+    data: web::Data<AppState<R>>,
+) -> Result<HttpResponse, AppError>
+where
+    R: RecetaRepository + 'static,
+{
+    let receta = data.receta_repo.get_by_id(id.into_inner()).await?;
+    match receta {
+        Some(r) => Ok(HttpResponse::Ok().json(r)),
+        None => Err(AppError::NotFound),
+    }
+}
+
+pub async fn search<R>(
+    filtro: web::Query<RecetaFiltro>,
+    data: web::Data<AppState<R>>,
+) -> Result<HttpResponse, AppError>
+where
+    R: RecetaRepository + 'static,
+{
+    let recetas = data.receta_repo.search(&filtro).await?;
+    Ok(HttpResponse::Ok().json(recetas))
+}
+
+/// Emite una receta en una atención del mismo profesional, que debe tener RNPI.
+pub async fn create<R>(
+    receta: web::Json<RecetaInput>,
+    data: web::Data<AppState<R>>,
+) -> Result<HttpResponse, AppError>
+where
+    R: RecetaRepository + AtencionRepository + ProfesionalRepository + 'static,
+{
+    let mut detalle: RecetaDetalle = receta.into_inner().try_into()?;
+    let id_atencion = detalle.receta.id_atencion;
+    let atencion = AtencionRepository::get_by_id(data.atencion_repo.as_ref(), id_atencion).await?
+        .ok_or_else(|| AppError::Validation(format!("Atención {} no existe", id_atencion)))?;
+    if atencion.atencion.id_prof != detalle.receta.id_prof {
+        return Err(AppError::Validation("Sólo el profesional de la atención puede emitir recetas en ella".into()));
+    }
+    let profesional = ProfesionalRepository::get_by_id(data.profesional_repo.as_ref(), detalle.receta.id_prof).await?
+        .ok_or_else(|| AppError::Validation(format!("Profesional {} no existe", detalle.receta.id_prof)))?;
+    let registro_rnpi = profesional.registro_rnpi.as_deref().map(str::trim).unwrap_or_default();
+    if registro_rnpi.is_empty() {
+        return Err(AppError::Validation("El profesional no tiene registro RNPI para emitir recetas".into()));
+    }
+
+    detalle.receta.id_paciente = atencion.atencion.id_paciente;
+    detalle.receta.registro_rnpi = registro_rnpi.to_string();
+    detalle.receta.codigo = recetas::generar_codigo();
+    detalle.receta.huella = recetas::huella(&detalle);
+
+    let id = RecetaRepository::create(data.receta_repo.as_ref(), &detalle).await?;
+    Ok(HttpResponse::Created().json(serde_json::json!({"id": id, "codigo": detalle.receta.codigo})))
+}
+
+pub async fn pdf<R>(
+    id: web::Path<u32>,
+    data: web::Data<AppState<R>>,
+) -> Result<HttpResponse, AppError>
+where
+    R: RecetaRepository + PacienteRepository + ProfesionalRepository + ZonaRepository + FirmaRepository + 'static,
+{
+    let detalle = RecetaRepository::get_by_id(data.receta_repo.as_ref(), id.into_inner()).await?
+        .ok_or(AppError::NotFound)?;
+    let receta = &detalle.receta;
+    let profesional = ProfesionalRepository::get_by_id(data.profesional_repo.as_ref(), receta.id_prof).await?
+        .ok_or_else(|| AppError::Internal(format!("Receta {} sin profesional", receta.id)))?;
+    let paciente = PacienteRepository::get_by_id(data.paciente_repo.as_ref(), receta.id_paciente).await?
+        .ok_or_else(|| AppError::Internal(format!("Receta {} sin paciente", receta.id)))?;
+    let tz = zonas::zona_horaria_de(data.zona_repo.as_ref(), paciente.cod_zona.as_deref()).await?;
+
+    let leyenda = firmas::leyenda(data.firma_repo.as_ref(), FIRMA_RECETA, receta.id, 0).await?;
+    let bytes = recetas::documento(&detalle, &profesional, &paciente, tz, leyenda.as_deref()).renderizar()?;
+    Ok(HttpResponse::Ok()
+        .content_type("application/pdf")
+        .insert_header(("Content-Disposition", format!("inline; filename=\"receta-{}.pdf\"", receta.codigo)))
+        .body(bytes))
+}
+
+pub async fn anular<R>(
+    id: web::Path<u32>,
+    anulacion: web::Json<AnulacionInput>,
+    data: web::Data<AppState<R>>,
+) -> Result<HttpResponse, AppError>
+where
+    R: RecetaRepository + 'static,
+{
+    let detalle = data.receta_repo.get_by_id(id.into_inner()).await?.ok_or(AppError::NotFound)?;
+    if detalle.receta.id_prof != anulacion.id_prof {
+        return Err(AppError::Validation("Sólo quien emitió la receta puede anularla".into()));
+    }
+    let motivo = anulacion.motivo.trim();
+    if motivo.is_empty() {
+        return Err(AppError::Validation("La anulación requiere un motivo".into()));
+    }
+
+    if !data.receta_repo.anular(detalle.receta.id, motivo).await? {
+        return Err(AppError::Conflict("La receta ya está anulada".into()));
+    }
+    Ok(HttpResponse::Ok().finish())
+}
+
+/// Verificación pública para farmacias. Un código mal formado responde
+/// igual que uno inexistente.
+pub async fn verificar<R>(
+    codigo: web::Path<String>,
+    data: web::Data<AppState<R>>,
+) -> Result<HttpResponse, AppError>
+where
+    R: RecetaRepository + PacienteRepository + ProfesionalRepository + ZonaRepository + 'static,
+{
+    let codigo = recetas::normalizar_codigo(&codigo).ok_or(AppError::NotFound)?;
+    let detalle = data.receta_repo.get_by_codigo(&codigo).await?.ok_or(AppError::NotFound)?;
+    let receta = detalle.receta;
+    let profesional = ProfesionalRepository::get_by_id(data.profesional_repo.as_ref(), receta.id_prof).await?
+        .ok_or_else(|| AppError::Internal(format!("Receta {} sin profesional", receta.id)))?;
+    let paciente = PacienteRepository::get_by_id(data.paciente_repo.as_ref(), receta.id_paciente).await?
+        .ok_or_else(|| AppError::Internal(format!("Receta {} sin paciente", receta.id)))?;
+    let tz = zonas::zona_horaria_de(data.zona_repo.as_ref(), paciente.cod_zona.as_deref()).await?;
+    let hoy = tiempo::a_local(tiempo::ahora(), tz).date();
+
+    Ok(HttpResponse::Ok().json(VerificacionReceta {
+        vigente: receta.estado == RECETA_EMITIDA && hoy <= receta.valida_hasta,
+        codigo: receta.codigo,
+        estado: receta.estado,
+        emitida_en: receta.emitida_en,
+        valida_hasta: receta.valida_hasta,
+        profesional: profesional.nombre_completo(),
+        registro_rnpi: receta.registro_rnpi,
+        paciente: recetas::abreviar_nombre(&paciente),
+        rut_paciente: recetas::enmascarar_rut(&paciente.rut),
+        lineas: detalle.lineas,
+        huella: receta.huella,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_web::{test, App};
+    use crate::{
+        models::{Paciente, Profesional, ATENCION_BORRADOR},
+        repositories::{fixtures, MockRepository},
+    };
+    use super::*;
+
+    async fn app_state() -> AppState<MockRepository> {
+        let medico = Profesional { especialidad: Some("Médico".into()), ..fixtures::profesional(1) };
+        let paciente = Paciente { ap_materno: Some("SOTO".into()), ..fixtures::paciente("0010895960-6") };
+        let repo = fixtures::repositorio(vec![medico], vec![paciente]).await;
+        AtencionRepository::create(&repo, &fixtures::atencion(1, 1, ATENCION_BORRADOR, tiempo::ahora())).await.unwrap();
+        AppState::new(repo)
+    }
+
+    #[actix_web::test]
+    async fn emite_imprime_y_verifica_receta() {
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(app_state().await))
+                .route("/recetas", web::post().to(create::<MockRepository>))
+                .route("/recetas/verificar/{codigo}", web::get().to(verificar::<MockRepository>))
+                .route("/recetas/{id}/pdf", web::get().to(pdf::<MockRepository>))
+                .route("/recetas/{id}/anular", web::post().to(anular::<MockRepository>)),
+        )
+        .await;
+
+        let receta = serde_json::json!({
+            "id_atencion": 1,
+            "id_prof": 1,
+            "lineas": [{"medicamento": "Paracetamol", "dosis": "500 mg", "frecuencia": "cada 8 horas", "duracion": "3 días"}],
+        });
+        let req = test::TestRequest::post().uri("/recetas").set_json(&receta).to_request();
+        let creada: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+        let codigo = creada["codigo"].as_str().unwrap().to_string();
+
+        let req = test::TestRequest::get().uri("/recetas/1/pdf").to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.headers().get("content-type").unwrap(), "application/pdf");
+        assert!(test::read_body(resp).await.starts_with(b"%PDF"));
+
+        let uri = format!("/recetas/verificar/{}", codigo.to_lowercase());
+        let req = test::TestRequest::get().uri(&uri).to_request();
+        let verificacion: VerificacionReceta = test::call_and_read_body_json(&app, req).await;
+        assert!(verificacion.vigente);
+        assert_eq!(verificacion.paciente, "CLAUDIO S. S.");
+        assert_eq!(verificacion.rut_paciente, "***960-6");
+        assert_eq!(verificacion.registro_rnpi, "212402");
+
+        let req = test::TestRequest::post()
+            .uri("/recetas/1/anular")
+            .set_json(serde_json::json!({"id_prof": 1, "motivo": "Dosis errónea"}))
+            .to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), 200);
+        let req = test::TestRequest::get().uri(&format!("/recetas/verificar/{}", codigo)).to_request();
+        let verificacion: VerificacionReceta = test::call_and_read_body_json(&app, req).await;
+        assert!(!verificacion.vigente);
+
+        let req = test::TestRequest::get().uri("/recetas/verificar/AAAA-BBBB-CCCC").to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), 404);
+    }
+}
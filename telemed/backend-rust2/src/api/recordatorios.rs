@@ -0,0 +1,340 @@
+use std::collections::HashMap;
+use std::time::Duration as Intervalo;
+
+use actix_web::{rt, web, HttpResponse};
+use chrono::Duration;
+use tracing::{error, warn};
+use crate::{
+    models::{
+        CitaFiltro, CitaSinConfirmar, RecordatorioCita, RespuestaCita, RespuestaQuery, ResumenFiltro,
+        SinConfirmarFiltro, ACCION_CANCELAR, ACCION_CONFIRMAR, CITA_AGENDADA, CITA_CANCELADA, DESTINATARIO_PACIENTE,
+        RESPUESTA_CANCELADA, RESPUESTA_CONFIRMADA,
+    },
+    recordatorios,
+    tiempo,
+    app_state::AppState,
+    error::AppError
+};
+use super::{notificaciones, zonas};
+use super::super::repositories::{
+    AgendaRepository, NotificacionRepository, PacienteRepository, ProfesionalRepository, RecordatorioRepository,
+    UsuarioRepository, ZonaRepository,
+};
+
+/// Cada cuánto se buscan citas por recordar.
+const REVISION: Intervalo = Intervalo::from_secs(5 * 60);
+const HORAS_SIN_CONFIRMAR: u32 = 24;
+const MAX_HORAS_SIN_CONFIRMAR: u32 = 7 * 24;
+
+const MOTIVO_CANCELACION: &str = "Cancelada por el paciente desde el recordatorio";
+
+/// Encola los recordatorios de 24 y 2 horas que correspondan. Cada uno se
+/// registra antes de encolarlo, así un reinicio no lo duplica. Retorna
+/// cuántos se encolaron.
+pub async fn recordar_pendientes<R>(data: &AppState<R>) -> Result<usize, AppError>
+where
+    R: AgendaRepository + RecordatorioRepository + ZonaRepository + NotificacionRepository
+        + UsuarioRepository + PacienteRepository + ProfesionalRepository,
+{
+    let ahora = tiempo::ahora();
+    let filtro = CitaFiltro { desde: Some(ahora), hasta: Some(ahora + Duration::hours(24)), ..Default::default() };
+    let mut encolados = 0;
+    for cita in data.agenda_repo.search_citas(&filtro).await? {
+        if cita.estado != CITA_AGENDADA {
+            continue;
+        }
+        let Some(tipo) = recordatorios::debido(cita.inicio, ahora) else { continue };
+        let recordatorio = RecordatorioCita { id_cita: cita.id, tipo: tipo.into(), enviado_en: ahora };
+        if !data.recordatorio_repo.registrar_recordatorio(&recordatorio).await? {
+            continue;
+        }
+
+        let paciente = PacienteRepository::get_by_id(data.paciente_repo.as_ref(), cita.id_paciente).await?;
+        let profesional = ProfesionalRepository::get_by_id(data.profesional_repo.as_ref(), cita.id_prof).await?;
+        let (Some(paciente), Some(profesional)) = (paciente, profesional) else {
+            warn!("Cita {}: paciente o profesional no existe, sin recordatorio", cita.id);
+            continue;
+        };
+        let tz = zonas::zona_horaria_de(data.zona_repo.as_ref(), cita.cod_zona.as_deref()).await?;
+        let local = tiempo::a_local(cita.inicio, tz);
+        let variables: HashMap<String, String> = [
+            ("nombre", paciente.nombre_completo()),
+            ("profesional", profesional.nombre_completo()),
+            ("fecha", local.format("%d/%m/%Y").to_string()),
+            ("hora", local.format("%H:%M").to_string()),
+            ("confirmar", recordatorios::enlace(&data.enlaces, cita.id, ACCION_CONFIRMAR, cita.inicio)),
+            ("cancelar", recordatorios::enlace(&data.enlaces, cita.id, ACCION_CANCELAR, cita.inicio)),
+        ]
+        .into_iter()
+        .map(|(k, v)| (k.to_string(), v))
+        .collect();
+
+        match notificaciones::encolar(data, "RECORDATORIO_CITA", DESTINATARIO_PACIENTE, cita.id_paciente, &variables).await {
+            Ok(_) => encolados += 1,
+            Err(e) => warn!("Cita {}: no se pudo encolar el recordatorio {}: {}", cita.id, tipo, e),
+        }
+    }
+    Ok(encolados)
+}
+
+/// Tarea de fondo: revisa cada cinco minutos las citas de las próximas 24 horas.
+pub async fn recordar<R>(data: AppState<R>)
+where
+    R: AgendaRepository + RecordatorioRepository + ZonaRepository + NotificacionRepository
+        + UsuarioRepository + PacienteRepository + ProfesionalRepository,
+{
+    let mut intervalo = rt::time::interval(REVISION);
+    loop {
+        intervalo.tick().await;
+        if let Err(e) = recordar_pendientes(&data).await {
+            error!("Recordatorios: {}", e);
+        }
+    }
+}
+
+fn pagina(titulo: &str, detalle: &str) -> HttpResponse {
+    HttpResponse::Ok().content_type("text/html; charset=utf-8").body(format!(
+        "<!DOCTYPE html><html lang=\"es\"><head><meta charset=\"utf-8\"><title>{0}</title></head>\
+         <body><h1>{0}</h1><p>{1}</p></body></html>",
+        titulo, detalle,
+    ))
+}
+
+/// Página con un botón que envía la respuesta por POST al mismo enlace.
+fn formulario(titulo: &str, detalle: &str, id: u32, query: &RespuestaQuery, boton: &str) -> HttpResponse {
+    HttpResponse::Ok().content_type("text/html; charset=utf-8").body(format!(
+        "<!DOCTYPE html><html lang=\"es\"><head><meta charset=\"utf-8\"><title>{0}</title></head>\
+         <body><h1>{0}</h1><p>{1}</p>\
+         <form method=\"post\" action=\"/api/agenda/citas/{2}/respuesta?accion={3}&amp;expira={4}&amp;firma={5}\">\
+         <button type=\"submit\">{6}</button></form></body></html>",
+        titulo, detalle, id, query.accion, query.expira, query.firma, boton,
+    ))
+}
+
+/// Destino del enlace de un clic del recordatorio. Sólo muestra la
+/// pregunta: los lectores de correo y antivirus abren los enlaces, así que
+/// la cita cambia recién cuando el paciente envía el formulario.
+pub async fn preguntar<R>(
+    id: web::Path<u32>,
+    query: web::Query<RespuestaQuery>,
+    data: web::Data<AppState<R>>,
+) -> Result<HttpResponse, AppError>
+where
+    R: AgendaRepository + 'static,
+{
+    let id = id.into_inner();
+    recordatorios::verificar(&data.enlaces, id, &query.accion, query.expira, &query.firma, tiempo::ahora())?;
+    data.agenda_repo.get_cita(id).await?.ok_or(AppError::NotFound)?;
+
+    Ok(if query.accion == ACCION_CONFIRMAR {
+        formulario("Confirmar cita", "¿Confirma que asistirá a su cita?", id, &query, "Confirmar asistencia")
+    } else {
+        formulario(
+            "Cancelar cita",
+            "¿Desea cancelar su hora? Quedará disponible para otro paciente.",
+            id,
+            &query,
+            "Cancelar hora",
+        )
+    })
+}
+
+/// Respuesta enviada desde la página del enlace. Repetir la misma acción
+/// no hace nada; confirmar una cita ya cancelada es un conflicto.
+pub async fn responder<R>(
+    id: web::Path<u32>,
+    query: web::Query<RespuestaQuery>,
+    data: web::Data<AppState<R>>,
+) -> Result<HttpResponse, AppError>
+where
+    R: AgendaRepository + RecordatorioRepository + 'static,
+{
+    let id = id.into_inner();
+    let ahora = tiempo::ahora();
+    recordatorios::verificar(&data.enlaces, id, &query.accion, query.expira, &query.firma, ahora)?;
+
+    let mut cita = data.agenda_repo.get_cita(id).await?.ok_or(AppError::NotFound)?;
+    let respuesta = if query.accion == ACCION_CONFIRMAR { RESPUESTA_CONFIRMADA } else { RESPUESTA_CANCELADA };
+    let anterior = data.recordatorio_repo.get_respuesta(id).await?;
+
+    if anterior.as_ref().is_none_or(|a| a.respuesta != respuesta) {
+        if cita.estado != CITA_AGENDADA {
+            return Err(AppError::Conflict(format!("La cita está en estado {}", cita.estado)));
+        }
+        if respuesta == RESPUESTA_CANCELADA {
+            cita.estado = CITA_CANCELADA.into();
+            cita.motivo_cancelacion = Some(MOTIVO_CANCELACION.into());
+            data.agenda_repo.update_cita(&cita).await?;
+        }
+        data.recordatorio_repo
+            .guardar_respuesta(&RespuestaCita { id_cita: id, respuesta: respuesta.into(), respondida_en: ahora })
+            .await?;
+    }
+
+    Ok(if respuesta == RESPUESTA_CONFIRMADA {
+        pagina("Cita confirmada", "Gracias por confirmar su asistencia.")
+    } else {
+        pagina("Cita cancelada", "Su hora fue cancelada y quedará disponible para otro paciente.")
+    })
+}
+
+/// Citas agendadas de las próximas horas a las que se envió recordatorio
+/// y el paciente no ha respondido, para que coordinación las contacte.
+pub async fn sin_confirmar<R>(
+    filtro: web::Query<SinConfirmarFiltro>,
+    data: web::Data<AppState<R>>,
+) -> Result<HttpResponse, AppError>
+where
+    R: AgendaRepository + RecordatorioRepository + 'static,
+{
+    let horas = filtro.horas.unwrap_or(HORAS_SIN_CONFIRMAR);
+    if horas == 0 || horas > MAX_HORAS_SIN_CONFIRMAR {
+        return Err(AppError::Validation(format!("horas debe estar entre 1 y {}", MAX_HORAS_SIN_CONFIRMAR)));
+    }
+    let ahora = tiempo::ahora();
+    let citas: Vec<_> = data.agenda_repo
+        .search_citas(&CitaFiltro { desde: Some(ahora), hasta: Some(ahora + Duration::hours(i64::from(horas))), ..Default::default() })
+        .await?
+        .into_iter()
+        .filter(|c| c.estado == CITA_AGENDADA && c.inicio > ahora)
+        .filter(|c| filtro.cod_zona.as_ref().is_none_or(|z| c.cod_zona.as_ref() == Some(z)))
+        .collect();
+
+    let ids: Vec<u32> = citas.iter().map(|c| c.id).collect();
+    let respondidas: Vec<u32> = data.recordatorio_repo.respuestas(&ids).await?.into_iter().map(|r| r.id_cita).collect();
+    let recordatorios = data.recordatorio_repo.recordatorios(&ids).await?;
+
+    let sin_confirmar: Vec<CitaSinConfirmar> = citas
+        .into_iter()
+        .filter(|c| !respondidas.contains(&c.id))
+        .filter_map(|cita| {
+            let enviados: Vec<_> = recordatorios.iter().filter(|r| r.id_cita == cita.id).cloned().collect();
+            (!enviados.is_empty()).then_some(CitaSinConfirmar { cita, recordatorios: enviados })
+        })
+        .collect();
+    Ok(HttpResponse::Ok().json(sin_confirmar))
+}
+
+/// Tasas de confirmación y cancelación por zona de las citas recordadas
+/// que comienzan en el rango.
+pub async fn resumen<R>(
+    filtro: web::Query<ResumenFiltro>,
+    data: web::Data<AppState<R>>,
+) -> Result<HttpResponse, AppError>
+where
+    R: AgendaRepository + RecordatorioRepository + 'static,
+{
+    if filtro.hasta <= filtro.desde {
+        return Err(AppError::Validation("hasta debe ser posterior a desde".into()));
+    }
+    let citas: Vec<_> = data.agenda_repo
+        .search_citas(&CitaFiltro { desde: Some(filtro.desde), hasta: Some(filtro.hasta), ..Default::default() })
+        .await?
+        .into_iter()
+        .filter(|c| c.inicio >= filtro.desde)
+        .filter(|c| filtro.cod_zona.as_ref().is_none_or(|z| c.cod_zona.as_ref() == Some(z)))
+        .collect();
+
+    let ids: Vec<u32> = citas.iter().map(|c| c.id).collect();
+    let recordadas: Vec<u32> = data.recordatorio_repo.recordatorios(&ids).await?.into_iter().map(|r| r.id_cita).collect();
+    let respuestas = data.recordatorio_repo.respuestas(&ids).await?;
+
+    let citas: Vec<_> = citas
+        .into_iter()
+        .filter(|c| recordadas.contains(&c.id))
+        .map(|c| {
+            let respuesta = respuestas.iter().find(|r| r.id_cita == c.id).map(|r| r.respuesta.clone());
+            (c, respuesta)
+        })
+        .collect();
+    Ok(HttpResponse::Ok().json(recordatorios::resumir(&citas)))
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_web::{test, App};
+    use crate::{
+        models::{Cita, Paciente},
+        enlaces::Enlaces,
+        repositories::{fixtures, MockRepository},
+    };
+    use super::*;
+
+    /// Paciente con correo y una cita en `horas` horas.
+    async fn app_state(horas: i64) -> (AppState<MockRepository>, Cita) {
+        let paciente = Paciente { email: Some("ana@correo.cl".into()), ..fixtures::paciente("11.111.111-1") };
+        let repo = fixtures::repositorio(vec![fixtures::profesional(1)], vec![paciente]).await;
+        let id_paciente = 1;
+        let inicio = tiempo::ahora() + Duration::hours(horas);
+        let mut cita = Cita {
+            id: 0,
+            id_prof: 1,
+            id_paciente,
+            id_bloque: 1,
+            inicio,
+            fin: inicio + Duration::minutes(20),
+            modalidad: "TELECONSULTA".into(),
+            cod_zona: Some("RM".into()),
+            estado: CITA_AGENDADA.into(),
+            sobrecupo: false,
+            motivo_cancelacion: None,
+            id_cita_original: None,
+        };
+        cita.id = repo.reservar_cita(&cita, 0, None).await.unwrap();
+        (AppState::new(repo).with_enlaces(Enlaces::new(Some("secreto"), "http://portal")), cita)
+    }
+
+    #[actix_web::test]
+    async fn recuerda_una_vez_por_tipo() {
+        let (data, cita) = app_state(3).await;
+        assert_eq!(recordar_pendientes(&data).await.unwrap(), 1);
+        assert_eq!(recordar_pendientes(&data).await.unwrap(), 0);
+
+        let enviados = data.recordatorio_repo.recordatorios(&[cita.id]).await.unwrap();
+        assert_eq!(enviados.iter().map(|r| r.tipo.as_str()).collect::<Vec<_>>(), vec!["24H"]);
+        let correos = data.notificacion_repo.notificaciones(&Default::default()).await.unwrap();
+        assert!(correos.iter().any(|n| n.cuerpo.contains("NICOLE ELENA ZAMORA") && n.cuerpo.contains("accion=cancelar")));
+    }
+
+    #[actix_web::test]
+    async fn cancela_desde_el_enlace() {
+        let (data, cita) = app_state(3).await;
+        recordar_pendientes(&data).await.unwrap();
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(data.clone()))
+                .route("/api/agenda/citas/{id}/respuesta", web::get().to(preguntar::<MockRepository>))
+                .route("/api/agenda/citas/{id}/respuesta", web::post().to(responder::<MockRepository>))
+                .route("/api/agenda/sin-confirmar", web::get().to(sin_confirmar::<MockRepository>))
+        ).await;
+
+        let req = test::TestRequest::get().uri("/api/agenda/sin-confirmar").to_request();
+        let pendientes: Vec<CitaSinConfirmar> = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(pendientes.len(), 1);
+
+        let ruta = |accion| recordatorios::enlace(&data.enlaces, cita.id, accion, cita.inicio).replace("http://portal", "");
+        let adulterada = ruta(ACCION_CANCELAR).replace("accion=cancelar", "accion=confirmar");
+        let resp = test::call_service(&app, test::TestRequest::post().uri(&adulterada).to_request()).await;
+        assert_eq!(resp.status(), 403);
+
+        // Abrir el enlace sólo muestra el formulario
+        let resp = test::call_service(&app, test::TestRequest::get().uri(&ruta(ACCION_CANCELAR)).to_request()).await;
+        assert_eq!(resp.status(), 200);
+        let pagina = String::from_utf8(test::read_body(resp).await.to_vec()).unwrap();
+        assert!(pagina.contains("method=\"post\""));
+        assert_eq!(data.agenda_repo.get_cita(cita.id).await.unwrap().unwrap().estado, CITA_AGENDADA);
+
+        for _ in 0..2 {
+            let resp = test::call_service(&app, test::TestRequest::post().uri(&ruta(ACCION_CANCELAR)).to_request()).await;
+            assert_eq!(resp.status(), 200);
+        }
+        let cancelada = data.agenda_repo.get_cita(cita.id).await.unwrap().unwrap();
+        assert_eq!(cancelada.estado, CITA_CANCELADA);
+        let resp = test::call_service(&app, test::TestRequest::post().uri(&ruta(ACCION_CONFIRMAR)).to_request()).await;
+        assert_eq!(resp.status(), 409);
+
+        let req = test::TestRequest::get().uri("/api/agenda/sin-confirmar").to_request();
+        let pendientes: Vec<CitaSinConfirmar> = test::call_and_read_body_json(&app, req).await;
+        assert!(pendientes.is_empty());
+    }
+}
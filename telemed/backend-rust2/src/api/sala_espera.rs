@@ -0,0 +1,431 @@
+use std::sync::Arc;
+use std::time::Duration as Intervalo;
+
+use actix_web::{rt, web, HttpRequest, HttpResponse};
+use chrono::Duration;
+use futures_util::{stream, Stream};
+use tokio::sync::broadcast::{self, error::RecvError};
+use tracing::error;
+use crate::{
+    models::{
+        ColaEspera, EntradaEspera, ESPERA_ATENDIDO, ESPERA_AUSENTE,
+        ESPERA_ESPERANDO, ESPERA_LLAMADO, ESPERA_RETIRADO,
+    },
+    sala_espera,
+    senalizacion::Rol,
+    tiempo,
+    app_state::AppState,
+    error::AppError
+};
+use super::super::repositories::{AgendaRepository, SalaEsperaRepository, TeleconsultaRepository};
+use super::teleconsultas;
+
+/// Cada cuánto se revisan las colas para detectar ausentes y refrescar las
+/// esperas estimadas de quienes escuchan.
+const REVISION: Intervalo = Intervalo::from_secs(30);
+
+/// Cabecera con el token de teleconsulta del paciente. No va en la URL
+/// para que no quede en registros de acceso ni en el historial.
+const CABECERA_TOKEN: &str = "X-Teleconsulta-Token";
+
+fn token(req: &HttpRequest) -> Result<&str, AppError> {
+    req.headers()
+        .get(CABECERA_TOKEN)
+        .and_then(|v| v.to_str().ok())
+        .map(str::trim)
+        .filter(|t| !t.is_empty())
+        .ok_or_else(|| AppError::Forbidden("Falta el token de teleconsulta".into()))
+}
+
+/// Cola del profesional tal como está guardada.
+pub async fn cola<R>(data: &AppState<R>, id_prof: u32) -> Result<ColaEspera, AppError>
+where
+    R: SalaEsperaRepository + AgendaRepository,
+{
+    let ahora = tiempo::ahora();
+    let abiertas = data.sala_espera_repo.abiertas(id_prof).await?;
+    let mut entradas = Vec::with_capacity(abiertas.len());
+    for e in abiertas {
+        match data.agenda_repo.get_cita(e.id_cita).await? {
+            Some(cita) => entradas.push((e, cita)),
+            None => error!("Sala de espera: cita {} no existe", e.id_cita),
+        }
+    }
+    Ok(ColaEspera { id_prof, generada_en: ahora, pacientes: sala_espera::ordenar(entradas, ahora) })
+}
+
+/// Arma la cola y la difunde a quienes la escuchan.
+pub async fn publicar<R>(data: &AppState<R>, id_prof: u32) -> Result<ColaEspera, AppError>
+where
+    R: SalaEsperaRepository + AgendaRepository,
+{
+    let cola = cola(data, id_prof).await?;
+    data.avisos.publicar(cola.clone())?;
+    Ok(cola)
+}
+
+/// Saca de la cola al paciente que ya entró a la videollamada.
+pub async fn atendida<R>(data: &AppState<R>, id_cita: u32) -> Result<(), AppError>
+where
+    R: SalaEsperaRepository + AgendaRepository,
+{
+    if let Some(mut entrada) = SalaEsperaRepository::get_by_cita(data.sala_espera_repo.as_ref(), id_cita).await?
+        && entrada.abierta()
+    {
+        entrada.estado = ESPERA_ATENDIDO.into();
+        entrada.cerrada_en = Some(tiempo::ahora());
+        SalaEsperaRepository::guardar(data.sala_espera_repo.as_ref(), &entrada).await?;
+        publicar(data, entrada.id_prof).await?;
+    }
+    Ok(())
+}
+
+/// Marca como ausentes a los llamados que no entraron a tiempo y difunde la
+/// cola con las esperas al día.
+async fn revisar<R>(data: &AppState<R>, id_prof: u32) -> Result<ColaEspera, AppError>
+where
+    R: SalaEsperaRepository + AgendaRepository,
+{
+    let ahora = tiempo::ahora();
+    let abiertas = data.sala_espera_repo.abiertas(id_prof).await?;
+    for id_cita in sala_espera::ausentes(&abiertas, ahora) {
+        if let Some(e) = abiertas.iter().find(|e| e.id_cita == id_cita) {
+            let ausente = EntradaEspera { estado: ESPERA_AUSENTE.into(), cerrada_en: Some(ahora), ..e.clone() };
+            SalaEsperaRepository::guardar(data.sala_espera_repo.as_ref(), &ausente).await?;
+        }
+    }
+    publicar(data, id_prof).await
+}
+
+/// Revisa periódicamente las colas con pacientes. Es lo único que da por
+/// ausentes a los llamados que no entraron: leer la cola no la modifica.
+pub async fn vigilar<R>(data: AppState<R>)
+where
+    R: SalaEsperaRepository + AgendaRepository,
+{
+    let mut intervalo = rt::time::interval(REVISION);
+    loop {
+        intervalo.tick().await;
+        let profesionales = match data.sala_espera_repo.profesionales_en_espera().await {
+            Ok(p) => p,
+            Err(e) => {
+                error!("Sala de espera: {}", e);
+                continue;
+            }
+        };
+        for id_prof in profesionales {
+            if let Err(e) = revisar(&data, id_prof).await {
+                error!("Sala de espera del profesional {}: {}", id_prof, e);
+            }
+        }
+    }
+}
+
+/// Server-sent events: la cola actual y luego una por cada cambio. `vista`
+/// decide qué enviar de cada cola y si el flujo termina.
+fn eventos<F>(
+    inicial: ColaEspera,
+    rx: broadcast::Receiver<Arc<ColaEspera>>,
+    vista: F,
+) -> impl Stream<Item = Result<web::Bytes, actix_web::Error>> + 'static
+where
+    F: Fn(&ColaEspera) -> (String, bool) + Clone + 'static,
+{
+    stream::unfold((Some(Arc::new(inicial)), rx, false), move |(pendiente, mut rx, terminado)| {
+        let vista = vista.clone();
+        async move {
+            if terminado {
+                return None;
+            }
+            let cola = match pendiente {
+                Some(cola) => cola,
+                None => loop {
+                    match rx.recv().await {
+                        Ok(cola) => break cola,
+                        Err(RecvError::Lagged(_)) => continue,
+                        Err(RecvError::Closed) => return None,
+                    }
+                },
+            };
+            let (evento, fin) = vista(&cola);
+            Some((Ok(web::Bytes::from(evento)), (None, rx, fin)))
+        }
+    })
+}
+
+fn evento(nombre: &str, datos: &impl serde::Serialize) -> String {
+    format!("event: {}\ndata: {}\n\n", nombre, serde_json::to_string(datos).unwrap_or_default())
+}
+
+fn flujo(eventos: impl Stream<Item = Result<web::Bytes, actix_web::Error>> + 'static) -> HttpResponse {
+    HttpResponse::Ok()
+        .content_type("text/event-stream")
+        .insert_header(("Cache-Control", "no-cache"))
+        .streaming(eventos)
+}
+
+pub async fn get_cola<R>(
+    id_prof: web::Path<u32>,
+    data: web::Data<AppState<R>>,
+) -> Result<HttpResponse, AppError>
+where
+    R: SalaEsperaRepository + AgendaRepository + 'static,
+{
+    Ok(HttpResponse::Ok().json(cola(&data, id_prof.into_inner()).await?))
+}
+
+/// La cola del profesional en tiempo real.
+pub async fn eventos_profesional<R>(
+    id_prof: web::Path<u32>,
+    data: web::Data<AppState<R>>,
+) -> Result<HttpResponse, AppError>
+where
+    R: SalaEsperaRepository + AgendaRepository + 'static,
+{
+    let id_prof = id_prof.into_inner();
+    let rx = data.avisos.suscribir(id_prof)?;
+    let inicial = cola(&data, id_prof).await?;
+    Ok(flujo(eventos(inicial, rx, |cola| (evento("cola", cola), false))))
+}
+
+/// Llama al siguiente paciente. Un llamado anterior que no entró se da por
+/// ausente, pues el profesional siguió con otro.
+pub async fn llamar<R>(
+    id_prof: web::Path<u32>,
+    data: web::Data<AppState<R>>,
+) -> Result<HttpResponse, AppError>
+where
+    R: SalaEsperaRepository + AgendaRepository + 'static,
+{
+    let id_prof = id_prof.into_inner();
+    let ahora = tiempo::ahora();
+    let actual = cola(&data, id_prof).await?;
+    let siguiente = actual.pacientes
+        .iter()
+        .find(|p| p.estado == ESPERA_ESPERANDO)
+        .ok_or_else(|| AppError::Conflict("No hay pacientes esperando".into()))?;
+
+    for p in actual.pacientes.iter().filter(|p| p.estado == ESPERA_LLAMADO) {
+        if let Some(mut e) = SalaEsperaRepository::get_by_cita(data.sala_espera_repo.as_ref(), p.id_cita).await? {
+            e.estado = ESPERA_AUSENTE.into();
+            e.cerrada_en = Some(ahora);
+            SalaEsperaRepository::guardar(data.sala_espera_repo.as_ref(), &e).await?;
+        }
+    }
+    let mut entrada = SalaEsperaRepository::get_by_cita(data.sala_espera_repo.as_ref(), siguiente.id_cita).await?
+        .ok_or_else(|| AppError::Internal(format!("Sala de espera sin la cita {}", siguiente.id_cita)))?;
+    entrada.estado = ESPERA_LLAMADO.into();
+    entrada.llamada_en = Some(ahora);
+    SalaEsperaRepository::guardar(data.sala_espera_repo.as_ref(), &entrada).await?;
+
+    let cola = publicar(&data, id_prof).await?;
+    let llamado = cola.pacientes.into_iter().find(|p| p.id_cita == entrada.id_cita);
+    Ok(HttpResponse::Ok().json(llamado))
+}
+
+async fn entrada_paciente<R>(data: &AppState<R>, id_cita: u32, token: &str) -> Result<Option<EntradaEspera>, AppError>
+where
+    R: TeleconsultaRepository + SalaEsperaRepository,
+{
+    let (_, rol) = teleconsultas::autorizar(data.teleconsulta_repo.as_ref(), id_cita, token).await?;
+    if rol != Rol::Paciente {
+        return Err(AppError::Forbidden("Sólo el paciente entra a la sala de espera".into()));
+    }
+    SalaEsperaRepository::get_by_cita(data.sala_espera_repo.as_ref(), id_cita).await
+}
+
+/// El paciente se anuncia con su token de teleconsulta, en la cabecera
+/// `X-Teleconsulta-Token` como al retirarse y al escuchar su lugar. Si ya estaba en la
+/// cola conserva su lugar; si había quedado ausente o se retiró, vuelve al
+/// final de los de su hora.
+pub async fn ingresar<R>(
+    req: HttpRequest,
+    id_cita: web::Path<u32>,
+    data: web::Data<AppState<R>>,
+) -> Result<HttpResponse, AppError>
+where
+    R: TeleconsultaRepository + SalaEsperaRepository + AgendaRepository + 'static,
+{
+    let id_cita = id_cita.into_inner();
+    let existente = entrada_paciente(&data, id_cita, token(&req)?).await?;
+    let cita = teleconsultas::cita_teleconsulta(data.agenda_repo.as_ref(), id_cita).await?;
+    let ahora = tiempo::ahora();
+    if ahora < cita.inicio - Duration::minutes(teleconsultas::MINUTOS_ANTES) || ahora > cita.fin {
+        return Err(AppError::Validation("La sala de espera no está disponible en este horario".into()));
+    }
+
+    let entrada = match existente {
+        Some(e) if e.abierta() => e,
+        Some(e) if e.estado == ESPERA_ATENDIDO => return Err(AppError::Conflict("La teleconsulta ya fue atendida".into())),
+        _ => {
+            let entrada = EntradaEspera {
+                id_cita,
+                id_prof: cita.id_prof,
+                id_paciente: cita.id_paciente,
+                estado: ESPERA_ESPERANDO.into(),
+                llegada_en: ahora,
+                llamada_en: None,
+                cerrada_en: None,
+            };
+            SalaEsperaRepository::guardar(data.sala_espera_repo.as_ref(), &entrada).await?;
+            entrada
+        }
+    };
+
+    let cola = publicar(&data, entrada.id_prof).await?;
+    let posicion = cola.pacientes.into_iter().find(|p| p.id_cita == id_cita);
+    Ok(HttpResponse::Ok().json(posicion))
+}
+
+pub async fn retirarse<R>(
+    req: HttpRequest,
+    id_cita: web::Path<u32>,
+    data: web::Data<AppState<R>>,
+) -> Result<HttpResponse, AppError>
+where
+    R: TeleconsultaRepository + SalaEsperaRepository + AgendaRepository + 'static,
+{
+    let mut entrada = entrada_paciente(&data, id_cita.into_inner(), token(&req)?).await?
+        .filter(EntradaEspera::abierta)
+        .ok_or(AppError::NotFound)?;
+    entrada.estado = ESPERA_RETIRADO.into();
+    entrada.cerrada_en = Some(tiempo::ahora());
+    SalaEsperaRepository::guardar(data.sala_espera_repo.as_ref(), &entrada).await?;
+    publicar(&data, entrada.id_prof).await?;
+    Ok(HttpResponse::NoContent().finish())
+}
+
+/// El lugar del paciente en tiempo real. Al salir de la cola (atendido,
+/// ausente o retirado) envía `salida` y termina. El token va en cabecera,
+/// así que el cliente lee el flujo con `fetch` y no con `EventSource`.
+pub async fn eventos_paciente<R>(
+    req: HttpRequest,
+    id_cita: web::Path<u32>,
+    data: web::Data<AppState<R>>,
+) -> Result<HttpResponse, AppError>
+where
+    R: TeleconsultaRepository + SalaEsperaRepository + AgendaRepository + 'static,
+{
+    let id_cita = id_cita.into_inner();
+    let entrada = entrada_paciente(&data, id_cita, token(&req)?).await?
+        .filter(EntradaEspera::abierta)
+        .ok_or(AppError::NotFound)?;
+    let rx = data.avisos.suscribir(entrada.id_prof)?;
+    let inicial = cola(&data, entrada.id_prof).await?;
+
+    Ok(flujo(eventos(inicial, rx, move |cola| {
+        match cola.pacientes.iter().find(|p| p.id_cita == id_cita) {
+            Some(posicion) => (evento("posicion", posicion), false),
+            None => (evento("salida", &serde_json::json!({"id_cita": id_cita})), true),
+        }
+    })))
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_web::{test, App};
+    use crate::{
+        consentimientos,
+        models::{Cita, PosicionEspera, CITA_AGENDADA, CONSENTIMIENTO_TELECONSULTA, MODALIDAD_TELECONSULTA},
+        repositories::{ConsentimientoRepository, MockRepository},
+    };
+    use super::*;
+
+    async fn app_state() -> AppState<MockRepository> {
+        let repo = MockRepository::default();
+        let ahora = tiempo::ahora();
+        for (id_paciente, minutos) in [(1, 10), (2, 30)] {
+            AgendaRepository::reservar_cita(&repo, &Cita {
+                id: 0,
+                id_prof: 1,
+                id_paciente,
+                id_bloque: 1,
+                inicio: ahora + Duration::minutes(minutos),
+                fin: ahora + Duration::minutes(minutos + 20),
+                modalidad: MODALIDAD_TELECONSULTA.into(),
+                cod_zona: None,
+                estado: CITA_AGENDADA.into(),
+                sobrecupo: false,
+                motivo_cancelacion: None,
+                id_cita_original: None,
+            }, 0, None).await.unwrap();
+            ConsentimientoRepository::registrar_consentimiento(
+                &repo,
+                &consentimientos::otorgado(id_paciente, CONSENTIMIENTO_TELECONSULTA),
+            ).await.unwrap();
+        }
+        AppState::new(repo)
+    }
+
+    #[actix_web::test]
+    async fn cola_llamado_y_ausente() {
+        let state = app_state().await;
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(state.clone()))
+                .route("/agenda/citas/{id}/teleconsulta", web::post().to(teleconsultas::crear::<MockRepository>))
+                .route("/teleconsultas/{id}/espera", web::post().to(ingresar::<MockRepository>))
+                .route("/teleconsultas/{id}/espera", web::delete().to(retirarse::<MockRepository>))
+                .route("/sala-espera/{id_prof}", web::get().to(get_cola::<MockRepository>))
+                .route("/sala-espera/{id_prof}/llamar", web::post().to(llamar::<MockRepository>)),
+        )
+        .await;
+
+        let mut tokens = Vec::new();
+        for (id_cita, id_paciente) in [(1, 1), (2, 2)] {
+            let mut sala = serde_json::Map::new();
+            for (rol, quien) in [("paciente", serde_json::json!({"id_paciente": id_paciente})), ("profesional", serde_json::json!({"id_prof": 1}))] {
+                let req = test::TestRequest::post()
+                    .uri(&format!("/agenda/citas/{}/teleconsulta", id_cita))
+                    .set_json(quien)
+                    .to_request();
+                let respuesta: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+                sala.insert(rol.into(), respuesta["token"].clone());
+            }
+            tokens.push(sala);
+        }
+        let token = |i: usize, rol: &str| tokens[i][rol].as_str().unwrap().to_string();
+
+        let espera = |metodo: test::TestRequest, id_cita: u32, token: String| {
+            metodo.uri(&format!("/teleconsultas/{}/espera", id_cita)).insert_header((CABECERA_TOKEN, token)).to_request()
+        };
+        let req = espera(test::TestRequest::post(), 1, token(0, "profesional"));
+        assert_eq!(test::call_service(&app, req).await.status(), 403);
+        let req = test::TestRequest::post().uri(&format!("/teleconsultas/1/espera?token={}", token(0, "paciente"))).to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), 403);
+
+        // El de las 30 llega primero, pero el de las 10 pasa adelante
+        for (id_cita, i) in [(2, 1), (1, 0)] {
+            let req = espera(test::TestRequest::post(), id_cita, token(i, "paciente"));
+            assert_eq!(test::call_service(&app, req).await.status(), 200);
+        }
+        let req = test::TestRequest::get().uri("/sala-espera/1").to_request();
+        let cola: ColaEspera = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(cola.pacientes.iter().map(|p| p.id_cita).collect::<Vec<_>>(), vec![1, 2]);
+        assert_eq!(cola.pacientes[1].espera_estimada_min, 29);
+
+        let req = test::TestRequest::post().uri("/sala-espera/1/llamar").to_request();
+        let llamado: PosicionEspera = test::call_and_read_body_json(&app, req).await;
+        assert_eq!((llamado.id_cita, llamado.estado.as_str()), (1, ESPERA_LLAMADO));
+
+        // Pasado el plazo sin entrar a la videollamada queda ausente en la
+        // siguiente revisión; consultar la cola no lo marca
+        let mut entrada = SalaEsperaRepository::get_by_cita(state.sala_espera_repo.as_ref(), 1).await.unwrap().unwrap();
+        entrada.llamada_en = Some(tiempo::ahora() - Duration::minutes(sala_espera::MINUTOS_PARA_ENTRAR));
+        SalaEsperaRepository::guardar(state.sala_espera_repo.as_ref(), &entrada).await.unwrap();
+        let req = test::TestRequest::get().uri("/sala-espera/1").to_request();
+        let cola: ColaEspera = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(cola.pacientes.len(), 2);
+        revisar(&state, 1).await.unwrap();
+        let req = test::TestRequest::get().uri("/sala-espera/1").to_request();
+        let cola: ColaEspera = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(cola.pacientes.iter().map(|p| p.id_cita).collect::<Vec<_>>(), vec![2]);
+        let ausente = SalaEsperaRepository::get_by_cita(state.sala_espera_repo.as_ref(), 1).await.unwrap().unwrap();
+        assert_eq!(ausente.estado, ESPERA_AUSENTE);
+
+        let req = espera(test::TestRequest::delete(), 2, token(1, "paciente"));
+        assert_eq!(test::call_service(&app, req).await.status(), 204);
+        let req = test::TestRequest::post().uri("/sala-espera/1/llamar").to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), 409);
+    }
+}
@@ -0,0 +1,354 @@
+use std::time::{Duration as Intervalo, Instant};
+
+use actix_web::{http::header, rt, web, HttpRequest, HttpResponse};
+use actix_ws::{Message, MessageStream, Session};
+use chrono::Duration;
+use tokio::sync::mpsc::UnboundedReceiver;
+use tracing::{error, warn};
+use crate::{
+    models::{
+        Cita, EstadoTeleconsulta, Lector, Teleconsulta, CITA_AGENDADA, CONSENTIMIENTO_GRABACION,
+        CONSENTIMIENTO_TELECONSULTA, MODALIDAD_TELECONSULTA, PARTICIPANTE_PACIENTE, PARTICIPANTE_PROFESIONAL,
+    },
+    senalizacion::{self, Entrante, Rol, Saliente},
+    consentimientos, tiempo,
+    app_state::AppState,
+    error::AppError
+};
+use super::super::repositories::{AgendaRepository, ConsentimientoRepository, SalaEsperaRepository, TeleconsultaRepository};
+use super::sala_espera;
+
+/// Se puede entrar a la sala desde un rato antes del inicio de la cita
+/// hasta un rato después de su término.
+pub const MINUTOS_ANTES: i64 = 30;
+const MINUTOS_DESPUES: i64 = 120;
+/// Cada cuánto se envía ping y tras cuánto silencio se da por caída la conexión.
+const LATIDO: Intervalo = Intervalo::from_secs(15);
+const SIN_RESPUESTA: Intervalo = Intervalo::from_secs(45);
+
+pub async fn cita_teleconsulta<R>(repo: &R, id_cita: u32) -> Result<Cita, AppError>
+where
+    R: AgendaRepository,
+{
+    let cita = repo.get_cita(id_cita).await?.ok_or(AppError::NotFound)?;
+    if cita.modalidad != MODALIDAD_TELECONSULTA {
+        return Err(AppError::Validation("La cita no es una teleconsulta".into()));
+    }
+    if cita.estado != CITA_AGENDADA {
+        return Err(AppError::Validation(format!("La cita está {}", cita.estado)));
+    }
+    Ok(cita)
+}
+
+/// Teleconsulta de la cita y el rol que corresponde al token.
+pub async fn autorizar<R>(repo: &R, id_cita: u32, token: &str) -> Result<(Teleconsulta, Rol), AppError>
+where
+    R: TeleconsultaRepository,
+{
+    let teleconsulta = repo.get_by_cita(id_cita).await?.ok_or(AppError::NotFound)?;
+    let huella = senalizacion::huella_token(token);
+    let rol = if huella == teleconsulta.token_paciente {
+        Rol::Paciente
+    } else if huella == teleconsulta.token_profesional {
+        Rol::Profesional
+    } else {
+        return Err(AppError::Forbidden("Token de teleconsulta inválido".into()));
+    };
+    Ok((teleconsulta, rol))
+}
+
+/// Abre la sala de la cita y entrega el token de quien lo pide, que debe
+/// ser el paciente o el profesional de la cita. Volver a llamarlo renueva
+/// sólo ese token (el anterior deja de servir), por ejemplo si se extravió
+/// el enlace. Requiere que el paciente haya consentido la teleconsulta.
+pub async fn crear<R>(
+    id_cita: web::Path<u32>,
+    solicitante: web::Json<Lector>,
+    data: web::Data<AppState<R>>,
+) -> Result<HttpResponse, AppError>
+where
+    R: AgendaRepository + TeleconsultaRepository + ConsentimientoRepository + 'static,
+{
+    let solicitante = solicitante.participante()?;
+    let cita = cita_teleconsulta(data.agenda_repo.as_ref(), id_cita.into_inner()).await?;
+    let rol = match solicitante.tipo.as_str() {
+        PARTICIPANTE_PACIENTE if solicitante.id == cita.id_paciente => Rol::Paciente,
+        PARTICIPANTE_PROFESIONAL if solicitante.id == cita.id_prof => Rol::Profesional,
+        _ => return Err(AppError::Forbidden("No participa en la cita".into())),
+    };
+    consentimientos::exigir(data.consentimiento_repo.as_ref(), cita.id_paciente, CONSENTIMIENTO_TELECONSULTA).await?;
+    let existente = data.teleconsulta_repo.get_by_cita(cita.id).await?;
+    if existente.as_ref().is_some_and(|t| t.terminada_en.is_some()) {
+        return Err(AppError::Conflict("La teleconsulta ya terminó".into()));
+    }
+
+    // El token del otro rol se conserva; si la sala es nueva queda uno
+    // que nadie conoce hasta que lo pida.
+    let token = senalizacion::generar_token();
+    let mut teleconsulta = existente.unwrap_or_else(|| Teleconsulta {
+        id_cita: cita.id,
+        token_paciente: senalizacion::huella_token(&senalizacion::generar_token()),
+        token_profesional: senalizacion::huella_token(&senalizacion::generar_token()),
+        creada_en: tiempo::ahora(),
+        iniciada_en: None,
+        terminada_en: None,
+    });
+    match rol {
+        Rol::Paciente => teleconsulta.token_paciente = senalizacion::huella_token(&token),
+        Rol::Profesional => teleconsulta.token_profesional = senalizacion::huella_token(&token),
+    }
+    data.teleconsulta_repo.guardar(&teleconsulta).await?;
+
+    Ok(HttpResponse::Created().json(serde_json::json!({
+        "id_cita": cita.id,
+        "rol": rol,
+        "token": token,
+        "ws": format!("/api/teleconsultas/{}/ws", cita.id),
+        "protocolo": senalizacion::PROTOCOLO,
+    })))
+}
+
+pub async fn estado<R>(
+    id_cita: web::Path<u32>,
+    data: web::Data<AppState<R>>,
+) -> Result<HttpResponse, AppError>
+where
+    R: AgendaRepository + TeleconsultaRepository + ConsentimientoRepository + 'static,
+{
+    let id_cita = id_cita.into_inner();
+    let teleconsulta = data.teleconsulta_repo.get_by_cita(id_cita).await?.ok_or(AppError::NotFound)?;
+    let cita = data.agenda_repo.get_cita(id_cita).await?.ok_or(AppError::NotFound)?;
+    let grabacion = data.consentimiento_repo.consentimiento_vigente(cita.id_paciente, CONSENTIMIENTO_GRABACION).await?;
+    Ok(HttpResponse::Ok().json(EstadoTeleconsulta {
+        teleconsulta,
+        conectados: data.salas.conectados(id_cita)?,
+        grabacion_consentida: grabacion.is_some(),
+    }))
+}
+
+/// WebSocket de señalización. El token viaja como subprotocolo
+/// (`Sec-WebSocket-Protocol: teleconsulta, <token>`) para que no quede en
+/// la URL ni en los registros, y define el rol. Sólo se acepta dentro del
+/// horario de la cita, mientras la teleconsulta no termine y el paciente no
+/// haya revocado su consentimiento.
+pub async fn conectar<R>(
+    req: HttpRequest,
+    body: web::Payload,
+    id_cita: web::Path<u32>,
+    data: web::Data<AppState<R>>,
+) -> Result<HttpResponse, AppError>
+where
+    R: AgendaRepository + TeleconsultaRepository + SalaEsperaRepository + ConsentimientoRepository + 'static,
+{
+    let id_cita = id_cita.into_inner();
+    let token = req
+        .headers()
+        .get(header::SEC_WEBSOCKET_PROTOCOL)
+        .and_then(|v| v.to_str().ok())
+        .and_then(senalizacion::token_de_protocolos)
+        .ok_or_else(|| AppError::Forbidden("Falta el token de teleconsulta".into()))?;
+    let (teleconsulta, rol) = autorizar(data.teleconsulta_repo.as_ref(), id_cita, &token).await?;
+    if teleconsulta.terminada_en.is_some() {
+        return Err(AppError::Conflict("La teleconsulta ya terminó".into()));
+    }
+    let cita = cita_teleconsulta(data.agenda_repo.as_ref(), id_cita).await?;
+    let ahora = tiempo::ahora();
+    if ahora < cita.inicio - Duration::minutes(MINUTOS_ANTES) || ahora > cita.fin + Duration::minutes(MINUTOS_DESPUES) {
+        return Err(AppError::Validation("La teleconsulta no está disponible en este horario".into()));
+    }
+    consentimientos::exigir(data.consentimiento_repo.as_ref(), cita.id_paciente, CONSENTIMIENTO_TELECONSULTA).await?;
+
+    let (mut respuesta, session, mensajes) = actix_ws::handle(&req, body)
+        .map_err(|e| AppError::Validation(e.to_string()))?;
+    respuesta.headers_mut().insert(
+        header::SEC_WEBSOCKET_PROTOCOL,
+        header::HeaderValue::from_static(senalizacion::PROTOCOLO),
+    );
+    let (conexion, salida, par_presente) = data.salas.unir(id_cita, rol)?;
+    if par_presente {
+        data.teleconsulta_repo.iniciar(id_cita, ahora).await?;
+        sala_espera::atendida(&data, id_cita).await?;
+    }
+
+    let data = data.into_inner();
+    rt::spawn(async move {
+        let resultado = sesion(&data, id_cita, rol, session, mensajes, salida).await;
+        if let Err(e) = resultado {
+            error!("Teleconsulta {}: {}", id_cita, e);
+        }
+        if let Err(e) = data.salas.salir(id_cita, rol, conexion) {
+            error!("Teleconsulta {}: {}", id_cita, e);
+        }
+    });
+    Ok(respuesta)
+}
+
+/// Atiende una conexión hasta que se cierra, se cae, es reemplazada o
+/// alguien cuelga.
+async fn sesion<R>(
+    data: &AppState<R>,
+    id_cita: u32,
+    rol: Rol,
+    mut session: Session,
+    mut mensajes: MessageStream,
+    mut salida: UnboundedReceiver<Saliente>,
+) -> Result<(), AppError>
+where
+    R: TeleconsultaRepository,
+{
+    let mut latido = rt::time::interval(LATIDO);
+    let mut ultima_actividad = Instant::now();
+
+    loop {
+        tokio::select! {
+            mensaje = mensajes.recv() => {
+                let Some(Ok(mensaje)) = mensaje else { break };
+                ultima_actividad = Instant::now();
+                match mensaje {
+                    Message::Text(texto) => match serde_json::from_str::<Entrante>(&texto) {
+                        Ok(Entrante::Colgar) => {
+                            data.teleconsulta_repo.terminar(id_cita, tiempo::ahora()).await?;
+                            data.salas.terminar(id_cita)?;
+                        }
+                        Ok(entrante) => {
+                            if !data.salas.retransmitir(id_cita, rol, entrante)? {
+                                enviar(&mut session, &Saliente::Error { mensaje: "El otro participante no está conectado".into() }).await;
+                            }
+                        }
+                        Err(e) => {
+                            warn!("Teleconsulta {}: mensaje inválido de {:?}: {}", id_cita, rol, e);
+                            enviar(&mut session, &Saliente::Error { mensaje: format!("Mensaje inválido: {}", e) }).await;
+                        }
+                    },
+                    Message::Ping(bytes) if session.pong(&bytes).await.is_err() => break,
+                    Message::Close(_) => break,
+                    _ => {}
+                }
+            }
+            saliente = salida.recv() => {
+                let Some(saliente) = saliente else { break };
+                let fin = matches!(saliente, Saliente::Reemplazada | Saliente::Terminada);
+                if !enviar(&mut session, &saliente).await || fin {
+                    break;
+                }
+            }
+            _ = latido.tick() => {
+                if ultima_actividad.elapsed() > SIN_RESPUESTA || session.ping(b"").await.is_err() {
+                    break;
+                }
+            }
+        }
+    }
+
+    let _ = session.close(None).await;
+    Ok(())
+}
+
+async fn enviar(session: &mut Session, mensaje: &Saliente) -> bool {
+    match serde_json::to_string(mensaje) {
+        Ok(texto) => session.text(texto).await.is_ok(),
+        Err(_) => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_web::{test, App};
+    use crate::{
+        models::{Cita, EventoConsentimiento, CONSENTIMIENTO_REVOCA},
+        repositories::MockRepository,
+    };
+    use super::*;
+
+    async fn app_state() -> AppState<MockRepository> {
+        let repo = MockRepository::default();
+        let ahora = tiempo::ahora();
+        for (minutos, modalidad) in [(10, MODALIDAD_TELECONSULTA), (60, "DOMICILIO")] {
+            AgendaRepository::reservar_cita(&repo, &Cita {
+                id: 0,
+                id_prof: 1,
+                id_paciente: 1,
+                id_bloque: 1,
+                inicio: ahora + Duration::minutes(minutos),
+                fin: ahora + Duration::minutes(minutos + 30),
+                modalidad: modalidad.into(),
+                cod_zona: Some("RM".into()),
+                estado: CITA_AGENDADA.into(),
+                sobrecupo: false,
+                motivo_cancelacion: None,
+                id_cita_original: None,
+            }, 0, None).await.unwrap();
+        }
+        AppState::new(repo)
+    }
+
+    fn ws(token: &str) -> test::TestRequest {
+        let protocolos = format!("{}, {}", senalizacion::PROTOCOLO, token);
+        test::TestRequest::get().uri("/teleconsultas/1/ws").insert_header((header::SEC_WEBSOCKET_PROTOCOL, protocolos))
+    }
+
+    #[actix_web::test]
+    async fn abre_sala_y_valida_tokens() {
+        let data = web::Data::new(app_state().await);
+        let app = test::init_service(
+            App::new()
+                .app_data(data.clone())
+                .route("/agenda/citas/{id}/teleconsulta", web::post().to(crear::<MockRepository>))
+                .route("/agenda/citas/{id}/teleconsulta", web::get().to(estado::<MockRepository>))
+                .route("/teleconsultas/{id}/ws", web::get().to(conectar::<MockRepository>)),
+        )
+        .await;
+        let pedir = |id_cita: u32, quien: serde_json::Value| {
+            test::TestRequest::post().uri(&format!("/agenda/citas/{}/teleconsulta", id_cita)).set_json(quien).to_request()
+        };
+        let paciente = || serde_json::json!({"id_paciente": 1});
+
+        assert_eq!(test::call_service(&app, pedir(2, paciente())).await.status(), 400);
+
+        // Sin consentimiento del paciente no se abre la sala
+        assert_eq!(test::call_service(&app, pedir(1, paciente())).await.status(), 403);
+        let repo = data.consentimiento_repo.as_ref();
+        repo.registrar_consentimiento(&consentimientos::otorgado(1, CONSENTIMIENTO_TELECONSULTA)).await.unwrap();
+
+        // Sólo quienes participan en la cita reciben token, y sólo el suyo
+        for ajeno in [serde_json::json!({"id_paciente": 2}), serde_json::json!({"id_prof": 2}), serde_json::json!({})] {
+            assert!(test::call_service(&app, pedir(1, ajeno)).await.status().is_client_error());
+        }
+        let sala: serde_json::Value = test::call_and_read_body_json(&app, pedir(1, paciente())).await;
+        assert_eq!(sala["rol"], "PACIENTE");
+        assert!(sala.get("token_profesional").is_none());
+        let token = sala["token"].as_str().unwrap().to_string();
+        let sala: serde_json::Value = test::call_and_read_body_json(&app, pedir(1, serde_json::json!({"id_prof": 1}))).await;
+        let token_profesional = sala["token"].as_str().unwrap().to_string();
+        assert_ne!(token, token_profesional);
+
+        assert_eq!(test::call_service(&app, ws("otro").to_request()).await.status(), 403);
+        let req = test::TestRequest::get().uri(&format!("/teleconsultas/1/ws?token={}", token)).to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), 403);
+
+        // Con un token válido se llega al handshake, que esta petición no trae
+        assert_eq!(test::call_service(&app, ws(&token).to_request()).await.status(), 400);
+
+        // Renovar el token del paciente invalida el anterior, no el del profesional
+        assert_eq!(test::call_service(&app, pedir(1, paciente())).await.status(), 201);
+        assert_eq!(test::call_service(&app, ws(&token).to_request()).await.status(), 403);
+        assert_eq!(test::call_service(&app, ws(&token_profesional).to_request()).await.status(), 400);
+
+        let req = test::TestRequest::get().uri("/agenda/citas/1/teleconsulta").to_request();
+        let estado: EstadoTeleconsulta = test::call_and_read_body_json(&app, req).await;
+        assert!(estado.conectados.is_empty());
+        assert!(estado.teleconsulta.iniciada_en.is_none());
+        assert!(!estado.grabacion_consentida);
+
+        // Revocar el consentimiento corta el acceso aun con token válido
+        let sala: serde_json::Value = test::call_and_read_body_json(&app, pedir(1, paciente())).await;
+        let token = sala["token"].as_str().unwrap();
+        assert_eq!(test::call_service(&app, ws(token).to_request()).await.status(), 400);
+        let revocacion = EventoConsentimiento {
+            accion: CONSENTIMIENTO_REVOCA.into(),
+            ..consentimientos::otorgado(1, CONSENTIMIENTO_TELECONSULTA)
+        };
+        repo.registrar_consentimiento(&revocacion).await.unwrap();
+        assert_eq!(test::call_service(&app, ws(token).to_request()).await.status(), 403);
+    }
+}
@@ -0,0 +1,529 @@
+use actix_web::{web, HttpResponse};
+use chrono::{Duration, NaiveDate, NaiveTime, Timelike};
+use crate::{
+    models::{
+        AsignacionInput, CambioEstadoInput, ContratoFiltro, CredencialFiltro, DisponibilidadFiltro, EventoVisita, MarcaInput, MarcaVisita, ParadaRuta,
+        Profesional, ProfesionalFiltro, ReporteVerificacion, Ruta, RutaFiltro, VerificacionFiltro, Visita, VisitaFiltro,
+        VisitaInput, Zona, transiciones_visita, MARCA_LLEGADA, MARCA_SALIDA, VISITA_EN_RUTA, VISITA_FALLIDA,
+        VISITA_PENDIENTE,
+    },
+    contratos, credenciales, geo, rutas, tiempo, visitas,
+    app_state::AppState,
+    error::AppError
+};
+use super::super::repositories::{
+    AgendaRepository, ContratoRepository, CredencialRepository, PacienteRepository, ProfesionalRepository, VisitaRepository,
+    ZonaRepository,
+};
+
+/// Profesionales con contrato vigente y credenciales al día que cubren la
+/// zona en la fecha indicada.
+async fn profesionales_de_zona<R>(data: &AppState<R>, zona: &Zona, fecha: NaiveDate) -> Result<Vec<Profesional>, AppError>
+where
+    R: AgendaRepository + ProfesionalRepository + ContratoRepository + CredencialRepository,
+{
+    let bloques = data.agenda_repo.bloques_disponibles(&DisponibilidadFiltro {
+        cod_zona: Some(zona.cod_zona.clone()),
+        modalidad: Some("DOMICILIO".into()),
+        ..Default::default()
+    }).await?;
+    let profesionales = ProfesionalRepository::search(data.profesional_repo.as_ref(), &ProfesionalFiltro::default()).await?;
+    let contratos = data.contrato_repo.contratos(&ContratoFiltro { vigente_en: Some(fecha), ..Default::default() }).await?;
+    let todas = data.credencial_repo.credenciales(&CredencialFiltro::default()).await?;
+    let obligatorias = credenciales::obligatorias(&todas);
+
+    Ok(profesionales
+        .into_iter()
+        .filter(|p| {
+            contratos::vigente(&contratos, p.id_prof, fecha).is_some()
+                && credenciales::habilitado(&obligatorias, p.id_prof, fecha)
+                && visitas::cubre_zona(p, zona, &bloques, fecha)
+        })
+        .collect())
+}
+
+async fn zona_de<R>(data: &AppState<R>, cod_zona: &str) -> Result<Zona, AppError>
+where
+    R: ZonaRepository,
+{
+    ZonaRepository::get_by_id(data.zona_repo.as_ref(), cod_zona).await?
+        .ok_or_else(|| AppError::Validation(format!("Zona {} no existe", cod_zona)))
+}
+
+const HORA_INICIO_RUTA: u32 = 8;
+/// Adelanto tolerado del reloj del dispositivo respecto del servidor.
+const TOLERANCIA_RELOJ_MIN: i64 = 5;
+const MAX_DIAS_VERIFICACION: i64 = 92;
+const VELOCIDAD_CIUDAD_KMH: f64 = 30.0;
+/// Velocidades aceptadas para simular la ruta; fuera de ellas los minutos
+/// de viaje pierden sentido o desbordan.
+const VELOCIDAD_MINIMA_KMH: f64 = 1.0;
+const VELOCIDAD_MAXIMA_KMH: f64 = 150.0;
+
+fn minutos(hora: NaiveTime) -> i64 {
+    i64::from(hora.num_seconds_from_midnight() / 60)
+}
+
+fn hora(minutos: i64) -> NaiveTime {
+    NaiveTime::MIN.overflowing_add_signed(Duration::minutes(minutos)).0
+}
+
+pub async fn get_by_id<R>(
+    id: web::Path<u32>,
+    data: web::Data<AppState<R>>,
+) -> Result<HttpResponse, AppError>
+where
+    R: VisitaRepository + 'static,
+{
+    let visita = data.visita_repo.get_by_id(id.into_inner()).await?;
+    match visita {
+        Some(v) => Ok(HttpResponse::Ok().json(v)),
+        None => Err(AppError::NotFound),
+    }
+}
+
+pub async fn search<R>(
+    filtro: web::Query<VisitaFiltro>,
+    data: web::Data<AppState<R>>,
+) -> Result<HttpResponse, AppError>
+where
+    R: VisitaRepository + 'static,
+{
+    let visitas = data.visita_repo.search(&filtro).await?;
+    Ok(HttpResponse::Ok().json(visitas))
+}
+
+pub async fn historial<R>(
+    id: web::Path<u32>,
+    data: web::Data<AppState<R>>,
+) -> Result<HttpResponse, AppError>
+where
+    R: VisitaRepository + 'static,
+{
+    let id = id.into_inner();
+    if data.visita_repo.get_by_id(id).await?.is_none() {
+        return Err(AppError::NotFound);
+    }
+    Ok(HttpResponse::Ok().json(data.visita_repo.historial(id).await?))
+}
+
+pub async fn create<R>(
+    visita: web::Json<VisitaInput>,
+    data: web::Data<AppState<R>>,
+) -> Result<HttpResponse, AppError>
+where
+    R: VisitaRepository + PacienteRepository + ZonaRepository + 'static,
+{
+    let mut input = visita.into_inner();
+    let paciente = PacienteRepository::get_by_id(data.paciente_repo.as_ref(), input.id_paciente).await?
+        .ok_or_else(|| AppError::Validation(format!("Paciente {} no existe", input.id_paciente)))?;
+    if input.direccion.is_none() {
+        input.direccion = paciente.direccion;
+        input.comuna = input.comuna.or(paciente.comuna);
+        input.latitud = paciente.latitud;
+        input.longitud = paciente.longitud;
+    }
+    input.cod_zona = input.cod_zona.or(paciente.cod_zona);
+
+    let visita: Visita = input.try_into()?;
+    let zona = zona_de(&data, &visita.cod_zona).await?;
+    let hoy = tiempo::a_local(tiempo::ahora(), tiempo::zona_horaria(&zona.zona_horaria)?).date();
+    if visita.fecha < hoy {
+        return Err(AppError::Validation("No se pueden programar visitas en fechas pasadas".into()));
+    }
+
+    let id = VisitaRepository::create(data.visita_repo.as_ref(), &visita).await?;
+    Ok(HttpResponse::Created().json(serde_json::json!({"id": id})))
+}
+
+/// Profesionales que pueden tomar la visita: vigentes y de la misma zona.
+pub async fn candidatos<R>(
+    id: web::Path<u32>,
+    data: web::Data<AppState<R>>,
+) -> Result<HttpResponse, AppError>
+where
+    R: VisitaRepository + AgendaRepository + ProfesionalRepository + ZonaRepository + ContratoRepository
+        + CredencialRepository + 'static,
+{
+    let visita = VisitaRepository::get_by_id(data.visita_repo.as_ref(), id.into_inner()).await?
+        .ok_or(AppError::NotFound)?;
+    let zona = zona_de(&data, &visita.cod_zona).await?;
+    Ok(HttpResponse::Ok().json(profesionales_de_zona(&data, &zona, visita.fecha).await?))
+}
+
+pub async fn asignar<R>(
+    id: web::Path<u32>,
+    asignacion: web::Json<AsignacionInput>,
+    data: web::Data<AppState<R>>,
+) -> Result<HttpResponse, AppError>
+where
+    R: VisitaRepository + AgendaRepository + ProfesionalRepository + ZonaRepository + ContratoRepository
+        + CredencialRepository + 'static,
+{
+    let visita = VisitaRepository::get_by_id(data.visita_repo.as_ref(), id.into_inner()).await?
+        .ok_or(AppError::NotFound)?;
+    if visita.estado != VISITA_PENDIENTE {
+        return Err(AppError::Conflict(format!("No se puede reasignar una visita {}", visita.estado)));
+    }
+
+    credenciales::exigir(data.credencial_repo.as_ref(), asignacion.id_prof, visita.fecha).await?;
+    let zona = zona_de(&data, &visita.cod_zona).await?;
+    let candidatos = profesionales_de_zona(&data, &zona, visita.fecha).await?;
+    if !candidatos.iter().any(|p| p.id_prof == asignacion.id_prof) {
+        return Err(AppError::Validation(format!(
+            "El profesional {} no atiende la zona {} el {}", asignacion.id_prof, zona.nom_zona.trim(), visita.fecha
+        )));
+    }
+
+    if !data.visita_repo.asignar(visita.id, asignacion.id_prof).await? {
+        return Err(AppError::Conflict("La visita cambió de estado mientras se asignaba".into()));
+    }
+    Ok(HttpResponse::Ok().finish())
+}
+
+pub async fn cambiar_estado<R>(
+    id: web::Path<u32>,
+    cambio: web::Json<CambioEstadoInput>,
+    data: web::Data<AppState<R>>,
+) -> Result<HttpResponse, AppError>
+where
+    R: VisitaRepository + 'static,
+{
+    let visita = data.visita_repo.get_by_id(id.into_inner()).await?.ok_or(AppError::NotFound)?;
+    let cambio = cambio.into_inner();
+    let estado = cambio.estado.trim().to_uppercase();
+    let motivo = cambio.motivo.map(|m| m.trim().to_string()).filter(|m| !m.is_empty());
+
+    if !transiciones_visita(&visita.estado).contains(&estado.as_str()) {
+        return Err(AppError::Conflict(format!("Una visita {} no puede pasar a {}", visita.estado, estado)));
+    }
+    if estado == VISITA_EN_RUTA && visita.id_prof.is_none() {
+        return Err(AppError::Validation("La visita no tiene profesional asignado".into()));
+    }
+    if (estado == VISITA_FALLIDA || estado == VISITA_PENDIENTE) && motivo.is_none() {
+        return Err(AppError::Validation(format!("Pasar a {} requiere un motivo", estado)));
+    }
+
+    let evento = EventoVisita { estado, motivo, registrado_en: tiempo::ahora() };
+    if !data.visita_repo.cambiar_estado(visita.id, &visita.estado, &evento).await? {
+        return Err(AppError::Conflict("La visita cambió de estado; vuelva a consultarla".into()));
+    }
+    Ok(HttpResponse::Ok().finish())
+}
+
+/// Orden sugerido para las visitas pendientes del día de un profesional.
+pub async fn ruta<R>(
+    filtro: web::Query<RutaFiltro>,
+    data: web::Data<AppState<R>>,
+) -> Result<HttpResponse, AppError>
+where
+    R: VisitaRepository + ProfesionalRepository + 'static,
+{
+    if ProfesionalRepository::get_by_id(data.profesional_repo.as_ref(), filtro.profesional).await?.is_none() {
+        return Err(AppError::NotFound);
+    }
+    let origen = geo::validar_coordenadas(filtro.origen_lat, filtro.origen_lon)?;
+    let velocidad_kmh = filtro.velocidad_kmh.unwrap_or(VELOCIDAD_CIUDAD_KMH);
+    if !(VELOCIDAD_MINIMA_KMH..=VELOCIDAD_MAXIMA_KMH).contains(&velocidad_kmh) {
+        return Err(AppError::Validation(format!(
+            "velocidad_kmh debe estar entre {} y {}", VELOCIDAD_MINIMA_KMH, VELOCIDAD_MAXIMA_KMH
+        )));
+    }
+    let inicio = filtro.hora_inicio.unwrap_or_else(|| NaiveTime::from_hms_opt(HORA_INICIO_RUTA, 0, 0).unwrap_or_default());
+
+    let visitas: Vec<Visita> = VisitaRepository::search(data.visita_repo.as_ref(), &VisitaFiltro {
+        id_prof: Some(filtro.profesional),
+        fecha: Some(filtro.fecha),
+        ..Default::default()
+    }).await?
+        .into_iter()
+        .filter(|v| v.estado == VISITA_PENDIENTE || v.estado == VISITA_EN_RUTA)
+        .collect();
+
+    let (ubicadas, sin_ubicacion): (Vec<&Visita>, Vec<&Visita>) =
+        visitas.iter().partition(|v| v.latitud.is_some() && v.longitud.is_some());
+    let paradas: Vec<rutas::Parada> = ubicadas
+        .iter()
+        .map(|v| rutas::Parada {
+            id_visita: v.id,
+            punto: (v.latitud.unwrap_or_default(), v.longitud.unwrap_or_default()),
+            ventana_desde: v.ventana_desde.map(minutos),
+            ventana_hasta: v.ventana_hasta.map(minutos),
+            duracion_min: i64::from(v.duracion_min),
+        })
+        .collect();
+
+    let parametros = rutas::Parametros { origen, inicio_min: minutos(inicio), velocidad_kmh };
+    let orden = rutas::ordenar(&paradas, &parametros);
+    let (tramos, _) = rutas::simular(&paradas, &orden, &parametros);
+
+    let paradas: Vec<ParadaRuta> = orden
+        .iter()
+        .zip(tramos)
+        .enumerate()
+        .map(|(n, (&i, tramo))| ParadaRuta {
+            orden: n + 1,
+            id_visita: tramo.id_visita,
+            id_paciente: ubicadas[i].id_paciente,
+            direccion: ubicadas[i].direccion.clone(),
+            distancia_km: (tramo.distancia_km * 10.0).round() / 10.0,
+            llegada: hora(tramo.llegada_min),
+            inicio: hora(tramo.inicio_min),
+            fin: hora(tramo.fin_min),
+            atraso_min: tramo.atraso_min,
+        })
+        .collect();
+
+    Ok(HttpResponse::Ok().json(Ruta {
+        id_prof: filtro.profesional,
+        fecha: filtro.fecha,
+        distancia_total_km: paradas.iter().map(|p| p.distancia_km).sum(),
+        paradas,
+        sin_ubicacion: sin_ubicacion.iter().map(|v| v.id).collect(),
+    }))
+}
+
+/// Registra la llegada o salida del profesional asignado, con su ubicación.
+pub async fn marcar<R>(
+    id: web::Path<u32>,
+    marca: web::Json<MarcaInput>,
+    data: web::Data<AppState<R>>,
+) -> Result<HttpResponse, AppError>
+where
+    R: VisitaRepository + 'static,
+{
+    let visita = data.visita_repo.get_by_id(id.into_inner()).await?.ok_or(AppError::NotFound)?;
+    let input = marca.into_inner();
+    let tipo = input.tipo.trim().to_uppercase();
+    if tipo != MARCA_LLEGADA && tipo != MARCA_SALIDA {
+        return Err(AppError::Validation(format!("Tipo de marca inválido: {}", input.tipo)));
+    }
+    if visita.id_prof != Some(input.id_prof) {
+        return Err(AppError::Validation("La visita no está asignada a este profesional".into()));
+    }
+    if visita.estado == VISITA_PENDIENTE {
+        return Err(AppError::Conflict("La visita aún no está en ruta".into()));
+    }
+    geo::validar_coordenadas(Some(input.latitud), Some(input.longitud))?;
+    if input.precision_m.is_some_and(|p| !p.is_finite() || p < 0.0) {
+        return Err(AppError::Validation("precision_m debe ser un número no negativo".into()));
+    }
+
+    let recibida_en = tiempo::ahora();
+    let registrada_en = input.registrada_en.unwrap_or(recibida_en);
+    if registrada_en > recibida_en + Duration::minutes(TOLERANCIA_RELOJ_MIN) {
+        return Err(AppError::Validation("La hora de la marca está en el futuro".into()));
+    }
+    if tipo == MARCA_SALIDA {
+        let marcas = data.visita_repo.marcas(&[visita.id]).await?;
+        let llegada = marcas.iter().find(|m| m.tipo == MARCA_LLEGADA)
+            .ok_or_else(|| AppError::Conflict("La salida requiere registrar antes la llegada".into()))?;
+        if registrada_en < llegada.registrada_en {
+            return Err(AppError::Validation("La salida no puede ser anterior a la llegada".into()));
+        }
+    }
+
+    let mut marca = MarcaVisita {
+        id: 0,
+        id_visita: visita.id,
+        distancia_km: visitas::distancia_al_domicilio(&visita, input.latitud, input.longitud),
+        tipo,
+        latitud: input.latitud,
+        longitud: input.longitud,
+        precision_m: input.precision_m,
+        registrada_en,
+        recibida_en,
+    };
+    marca.id = data.visita_repo.registrar_marca(&marca).await?
+        .ok_or_else(|| AppError::Conflict(format!("La visita ya tiene marca de {}", marca.tipo)))?;
+
+    Ok(HttpResponse::Created().json(serde_json::json!({
+        "id": marca.id,
+        "distancia_km": marca.distancia_km,
+        "lejos_del_domicilio": visitas::lejos_del_domicilio(&marca),
+        "precision_baja": visitas::precision_baja(&marca),
+    })))
+}
+
+/// Evidencia de las visitas de un profesional en el período, con anomalías.
+pub async fn verificacion<R>(
+    filtro: web::Query<VerificacionFiltro>,
+    data: web::Data<AppState<R>>,
+) -> Result<HttpResponse, AppError>
+where
+    R: VisitaRepository + ProfesionalRepository + 'static,
+{
+    if filtro.hasta < filtro.desde {
+        return Err(AppError::Validation("hasta debe ser igual o posterior a desde".into()));
+    }
+    if (filtro.hasta - filtro.desde).num_days() > MAX_DIAS_VERIFICACION {
+        return Err(AppError::Validation(format!("El período no puede superar {} días", MAX_DIAS_VERIFICACION)));
+    }
+    if ProfesionalRepository::get_by_id(data.profesional_repo.as_ref(), filtro.id_prof).await?.is_none() {
+        return Err(AppError::NotFound);
+    }
+
+    let del_periodo = VisitaRepository::search(data.visita_repo.as_ref(), &VisitaFiltro {
+        id_prof: Some(filtro.id_prof),
+        desde: Some(filtro.desde),
+        hasta: Some(filtro.hasta),
+        ..Default::default()
+    }).await?;
+    let ids: Vec<u32> = del_periodo.iter().map(|v| v.id).collect();
+    let marcas = data.visita_repo.marcas(&ids).await?;
+    let verificadas = visitas::verificar(&del_periodo, &marcas);
+
+    Ok(HttpResponse::Ok().json(ReporteVerificacion {
+        id_prof: filtro.id_prof,
+        desde: filtro.desde,
+        hasta: filtro.hasta,
+        total: verificadas.len(),
+        con_anomalias: verificadas.iter().filter(|v| !v.anomalias.is_empty()).count(),
+        visitas: verificadas,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_web::{test, web, App};
+    use chrono::Duration;
+    use crate::{app_state::AppState, models::Paciente, repositories::{fixtures, MockRepository}};
+    use super::*;
+
+    fn profesional(id_prof: u32, zona: &str) -> Profesional {
+        Profesional { zona: Some(zona.into()), ..fixtures::profesional(id_prof) }
+    }
+
+    async fn app_state() -> AppState<MockRepository> {
+        let paciente = Paciente {
+            direccion: Some("Granaderos 2360".into()),
+            comuna: Some("CALAMA".into()),
+            cod_zona: Some("049".into()),
+            latitud: Some(-22.4560),
+            longitud: Some(-68.9290),
+            cod_cliente: Some(2),
+            ..fixtures::paciente("0010895960-6")
+        };
+        let repo = fixtures::repositorio(vec![profesional(1, "Calama"), profesional(2, "Temuco")], vec![paciente])
+            .await
+            .with_zonas(vec![Zona {
+                cod_zona: "049".into(),
+                nom_zona: "Calama".into(),
+                orden_zona: None,
+                zona_horaria: "America/Santiago".into(),
+            }]);
+        for id_prof in [1, 2] {
+            let ingreso = NaiveDate::from_ymd_opt(2020, 1, 1).unwrap();
+            ContratoRepository::create_contrato(&repo, &contratos::contratado(id_prof, ingreso)).await.unwrap();
+        }
+        AppState::new(repo)
+    }
+
+    #[actix_web::test]
+    async fn asigna_profesional_de_la_zona_y_registra_estados() {
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(app_state().await))
+                .route("/visitas", web::post().to(create::<MockRepository>))
+                .route("/visitas", web::get().to(search::<MockRepository>))
+                .route("/visitas/ruta", web::get().to(ruta::<MockRepository>))
+                .route("/visitas/{id}/asignar", web::post().to(asignar::<MockRepository>))
+                .route("/visitas/{id}/estado", web::post().to(cambiar_estado::<MockRepository>))
+                .route("/visitas/{id}/historial", web::get().to(historial::<MockRepository>)),
+        )
+        .await;
+
+        let manana = tiempo::ahora().date() + Duration::days(2);
+        let req = test::TestRequest::post()
+            .uri("/visitas")
+            .set_json(serde_json::json!({ "id_paciente": 1, "fecha": manana, "motivo": "Curación" }))
+            .to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), 201);
+
+        let req = test::TestRequest::post().uri("/visitas/1/asignar").set_json(serde_json::json!({"id_prof": 2})).to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), 400);
+        let req = test::TestRequest::post().uri("/visitas/1/asignar").set_json(serde_json::json!({"id_prof": 1})).to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), 200);
+
+        let uri = format!("/visitas?id_prof=1&fecha={}", manana);
+        let req = test::TestRequest::get().uri(&uri).to_request();
+        let del_dia: Vec<Visita> = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(del_dia.len(), 1);
+        assert_eq!(del_dia[0].direccion, "Granaderos 2360");
+
+        let uri = format!("/visitas/ruta?profesional=1&fecha={}&origen_lat=-22.46&origen_lon=-68.93", manana);
+        let req = test::TestRequest::get().uri(&uri).to_request();
+        let ruta: Ruta = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(ruta.paradas.len(), 1);
+        assert_eq!(ruta.paradas[0].id_visita, 1);
+        for velocidad in ["NaN", "inf", "-30", "0.001", "1000"] {
+            let req = test::TestRequest::get().uri(&format!("{}&velocidad_kmh={}", uri, velocidad)).to_request();
+            assert_eq!(test::call_service(&app, req).await.status(), 400, "{}", velocidad);
+        }
+
+        let req = test::TestRequest::post().uri("/visitas/1/estado").set_json(serde_json::json!({"estado": "en_ruta"})).to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), 200);
+        let req = test::TestRequest::post().uri("/visitas/1/estado").set_json(serde_json::json!({"estado": "FALLIDA"})).to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), 400);
+        let req = test::TestRequest::post()
+            .uri("/visitas/1/estado")
+            .set_json(serde_json::json!({"estado": "FALLIDA", "motivo": "Paciente no se encontraba"}))
+            .to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), 200);
+        let req = test::TestRequest::post().uri("/visitas/1/estado").set_json(serde_json::json!({"estado": "REALIZADA"})).to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), 409);
+
+        let req = test::TestRequest::get().uri("/visitas/1/historial").to_request();
+        let historial: Vec<EventoVisita> = test::call_and_read_body_json(&app, req).await;
+        let estados: Vec<&str> = historial.iter().map(|e| e.estado.as_str()).collect();
+        assert_eq!(estados, [VISITA_PENDIENTE, VISITA_EN_RUTA, VISITA_FALLIDA]);
+    }
+
+    #[actix_web::test]
+    async fn registra_marcas_y_reporta_anomalias() {
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(app_state().await))
+                .route("/visitas", web::post().to(create::<MockRepository>))
+                .route("/visitas/verificacion", web::get().to(verificacion::<MockRepository>))
+                .route("/visitas/{id}/asignar", web::post().to(asignar::<MockRepository>))
+                .route("/visitas/{id}/estado", web::post().to(cambiar_estado::<MockRepository>))
+                .route("/visitas/{id}/marcas", web::post().to(marcar::<MockRepository>)),
+        )
+        .await;
+
+        let fecha = tiempo::ahora().date() + Duration::days(1);
+        let req = test::TestRequest::post()
+            .uri("/visitas")
+            .set_json(serde_json::json!({ "id_paciente": 1, "fecha": fecha, "motivo": "Control" }))
+            .to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), 201);
+        let req = test::TestRequest::post().uri("/visitas/1/asignar").set_json(serde_json::json!({"id_prof": 1})).to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), 200);
+
+        let llegada = serde_json::json!({"id_prof": 1, "tipo": "llegada", "latitud": -22.4561, "longitud": -68.9291});
+        let req = test::TestRequest::post().uri("/visitas/1/marcas").set_json(&llegada).to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), 409);
+
+        let req = test::TestRequest::post().uri("/visitas/1/estado").set_json(serde_json::json!({"estado": "EN_RUTA"})).to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), 200);
+        let salida = serde_json::json!({"id_prof": 1, "tipo": "SALIDA", "latitud": -22.4700, "longitud": -68.9290});
+        let req = test::TestRequest::post().uri("/visitas/1/marcas").set_json(&salida).to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), 409);
+
+        let req = test::TestRequest::post().uri("/visitas/1/marcas").set_json(&llegada).to_request();
+        let registrada: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(registrada["lejos_del_domicilio"], false);
+        let req = test::TestRequest::post().uri("/visitas/1/marcas").set_json(&llegada).to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), 409);
+        let req = test::TestRequest::post().uri("/visitas/1/marcas").set_json(&salida).to_request();
+        let registrada: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(registrada["lejos_del_domicilio"], true);
+
+        let uri = format!("/visitas/verificacion?id_prof=1&desde={}&hasta={}", fecha, fecha);
+        let req = test::TestRequest::get().uri(&uri).to_request();
+        let reporte: ReporteVerificacion = test::call_and_read_body_json(&app, req).await;
+        assert_eq!((reporte.total, reporte.con_anomalias), (1, 1));
+        assert_eq!(reporte.visitas[0].anomalias, [visitas::ANOMALIA_LEJOS, visitas::ANOMALIA_BREVE]);
+    }
+}
@@ -0,0 +1,63 @@
+use actix_web::{web, HttpResponse};
+use chrono_tz::Tz;
+use crate::{
+    models::{Zona, ZonaInput},
+    tiempo,
+    app_state::AppState,
+    error::AppError
+};
+use super::super::repositories::ZonaRepository;
+
+/// Zona horaria de la zona de acceso, o la de Chile continental si no hay
+/// zona o ésta no existe.
+pub async fn zona_horaria_de<R>(repo: &R, cod_zona: Option<&str>) -> Result<Tz, AppError>
+where
+    R: ZonaRepository,
+{
+    let zona = match cod_zona {
+        Some(cod_zona) => repo.get_by_id(cod_zona).await?,
+        None => None,
+    };
+    tiempo::zona_horaria(zona.as_ref().map_or(tiempo::ZONA_HORARIA_DEFECTO, |z| z.zona_horaria.as_str()))
+}
+
+pub async fn get_by_id<R>(
+    cod_zona: web::Path<String>,
+    data: web::Data<AppState<R>>,
+) -> Result<HttpResponse, AppError>
+where
+    R: ZonaRepository + 'static,
+{
+    let zona = data.zona_repo.get_by_id(&cod_zona).await?;
+    match zona {
+        Some(z) => Ok(HttpResponse::Ok().json(z)),
+        None => Err(AppError::NotFound),
+    }
+}
+
+pub async fn get_all<R>(
+    data: web::Data<AppState<R>>,
+) -> Result<HttpResponse, AppError>
+where
+    R: ZonaRepository + 'static,
+{
+    let zonas = data.zona_repo.get_all().await?;
+    Ok(HttpResponse::Ok().json(zonas))
+}
+
+pub async fn update<R>(
+    cod_zona: web::Path<String>,
+    zona: web::Json<ZonaInput>,
+    data: web::Data<AppState<R>>,
+) -> Result<HttpResponse, AppError>
+where
+    R: ZonaRepository + 'static,
+{
+    let mut zona: Zona = zona.into_inner().try_into()?;
+    zona.cod_zona = cod_zona.into_inner();
+    if data.zona_repo.get_by_id(&zona.cod_zona).await?.is_none() {
+        return Err(AppError::NotFound);
+    }
+    data.zona_repo.update(&zona).await?;
+    Ok(HttpResponse::Ok().finish())
+}
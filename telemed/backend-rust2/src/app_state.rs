@@ -1,15 +1,112 @@
 use std::sync::Arc;
-use crate::repositories::UsuarioRepository;
+use crate::{
+    adjuntos::Adjuntos, cie10::CacheCie10, enlaces::Enlaces, firma::Custodia, mensajeria::Buzones,
+    sala_espera::Avisos, senalizacion::Salas, transportes::Transportes,
+};
 
+/// Estado compartido por los handlers. Todos los repositorios se construyen a
+/// partir de la misma implementación `R` (MySQL en producción, memoria en tests).
+/// `cie10` cachea el catálogo vigente entre requests, `custodia` guarda la
+/// clave maestra de las claves de firma y los mensajes, `salas` las
+/// teleconsultas en curso, `avisos` difunde las colas de espera, `buzones`
+/// los mensajes a quienes están conectados, `transportes` entrega las
+/// notificaciones por correo y SMS, `enlaces` firma los enlaces de los
+/// recordatorios de citas y de descarga, y `adjuntos` guarda los archivos.
 #[derive(Clone)]
-pub struct AppState<R: UsuarioRepository> {
+pub struct AppState<R> {
     pub usuario_repo: Arc<R>,
+    pub paciente_repo: Arc<R>,
+    pub prevision_repo: Arc<R>,
+    pub profesional_repo: Arc<R>,
+    pub agenda_repo: Arc<R>,
+    pub zona_repo: Arc<R>,
+    pub feriado_repo: Arc<R>,
+    pub atencion_repo: Arc<R>,
+    pub cie10_repo: Arc<R>,
+    pub visita_repo: Arc<R>,
+    pub receta_repo: Arc<R>,
+    pub documento_repo: Arc<R>,
+    pub firma_repo: Arc<R>,
+    pub teleconsulta_repo: Arc<R>,
+    pub sala_espera_repo: Arc<R>,
+    pub mensaje_repo: Arc<R>,
+    pub notificacion_repo: Arc<R>,
+    pub recordatorio_repo: Arc<R>,
+    pub adjunto_repo: Arc<R>,
+    pub fhir_repo: Arc<R>,
+    pub derivacion_repo: Arc<R>,
+    pub consentimiento_repo: Arc<R>,
+    pub facturacion_repo: Arc<R>,
+    pub honorario_repo: Arc<R>,
+    pub contrato_repo: Arc<R>,
+    pub credencial_repo: Arc<R>,
+    pub cie10: CacheCie10,
+    pub custodia: Custodia,
+    pub salas: Salas,
+    pub avisos: Avisos,
+    pub buzones: Buzones,
+    pub transportes: Transportes,
+    pub enlaces: Enlaces,
+    pub adjuntos: Adjuntos,
 }
 
-impl<R: UsuarioRepository> AppState<R> {
+impl<R: Clone> AppState<R> {
     pub fn new(repository: R) -> Self {
         Self {
-            usuario_repo: Arc::new(repository),
+            usuario_repo: Arc::new(repository.clone()),
+            paciente_repo: Arc::new(repository.clone()),
+            prevision_repo: Arc::new(repository.clone()),
+            profesional_repo: Arc::new(repository.clone()),
+            agenda_repo: Arc::new(repository.clone()),
+            zona_repo: Arc::new(repository.clone()),
+            feriado_repo: Arc::new(repository.clone()),
+            atencion_repo: Arc::new(repository.clone()),
+            cie10_repo: Arc::new(repository.clone()),
+            visita_repo: Arc::new(repository.clone()),
+            receta_repo: Arc::new(repository.clone()),
+            documento_repo: Arc::new(repository.clone()),
+            firma_repo: Arc::new(repository.clone()),
+            teleconsulta_repo: Arc::new(repository.clone()),
+            sala_espera_repo: Arc::new(repository.clone()),
+            mensaje_repo: Arc::new(repository.clone()),
+            notificacion_repo: Arc::new(repository.clone()),
+            recordatorio_repo: Arc::new(repository.clone()),
+            adjunto_repo: Arc::new(repository.clone()),
+            fhir_repo: Arc::new(repository.clone()),
+            derivacion_repo: Arc::new(repository.clone()),
+            consentimiento_repo: Arc::new(repository.clone()),
+            facturacion_repo: Arc::new(repository.clone()),
+            honorario_repo: Arc::new(repository.clone()),
+            contrato_repo: Arc::new(repository.clone()),
+            credencial_repo: Arc::new(repository),
+            cie10: CacheCie10::default(),
+            custodia: Custodia::default(),
+            salas: Salas::default(),
+            avisos: Avisos::default(),
+            buzones: Buzones::default(),
+            transportes: Transportes::default(),
+            enlaces: Enlaces::default(),
+            adjuntos: Adjuntos::default(),
         }
     }
-}
\ No newline at end of file
+
+    pub fn with_custodia(mut self, custodia: Custodia) -> Self {
+        self.custodia = custodia;
+        self
+    }
+
+    pub fn with_transportes(mut self, transportes: Transportes) -> Self {
+        self.transportes = transportes;
+        self
+    }
+
+    pub fn with_enlaces(mut self, enlaces: Enlaces) -> Self {
+        self.enlaces = enlaces;
+        self
+    }
+
+    pub fn with_adjuntos(mut self, adjuntos: Adjuntos) -> Self {
+        self.adjuntos = adjuntos;
+        self
+    }
+}
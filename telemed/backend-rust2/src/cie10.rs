@@ -0,0 +1,207 @@
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+use chrono::NaiveDate;
+
+use crate::{error::AppError, models::{Cie10, Diagnostico}, repositories::Cie10Repository, texto::plegar, tiempo};
+
+/// Normaliza un código CIE-10 a la forma `J18.9`. Retorna `None` si no tiene
+/// la forma letra + dos dígitos + subcategoría opcional.
+pub fn normalizar_codigo(codigo: &str) -> Option<String> {
+    let limpio: String = codigo.trim().to_uppercase().chars().filter(|c| *c != '.').collect();
+    let valido = (3..=5).contains(&limpio.len())
+        && limpio.chars().all(|c| c.is_ascii_alphanumeric())
+        && limpio.starts_with(|c: char| c.is_ascii_uppercase())
+        && limpio[1..3].chars().all(|c| c.is_ascii_digit())
+        && limpio[3..].chars().all(|c| c.is_ascii_alphanumeric());
+    if !valido {
+        return None;
+    }
+    Some(if limpio.len() > 3 { format!("{}.{}", &limpio[..3], &limpio[3..]) } else { limpio })
+}
+
+struct Entrada {
+    cie10: Cie10,
+    /// Código sin punto, para búsquedas por prefijo ("J189" o "J18.9").
+    codigo_plano: String,
+    descripcion_plegada: String,
+}
+
+/// Catálogo CIE-10 de una versión, indexado para búsqueda en memoria.
+pub struct Catalogo {
+    pub version: String,
+    entradas: Vec<Entrada>,
+}
+
+impl Catalogo {
+    pub fn new(version: String, mut codigos: Vec<Cie10>) -> Self {
+        codigos.sort_by(|a, b| a.codigo.cmp(&b.codigo));
+        let entradas = codigos
+            .into_iter()
+            .map(|cie10| Entrada {
+                codigo_plano: cie10.codigo.replace('.', ""),
+                descripcion_plegada: plegar(&cie10.descripcion),
+                cie10,
+            })
+            .collect();
+        Self { version, entradas }
+    }
+
+    pub fn get(&self, codigo: &str) -> Option<&Cie10> {
+        let codigo = normalizar_codigo(codigo)?;
+        self.entradas
+            .binary_search_by(|e| e.cie10.codigo.as_str().cmp(&codigo))
+            .ok()
+            .map(|i| &self.entradas[i].cie10)
+    }
+
+    /// Si la consulta parece un código se buscan códigos que comiencen con
+    /// ella; si no, descripciones que contengan todas sus palabras. Las
+    /// descripciones que comienzan con la consulta aparecen primero.
+    pub fn buscar(&self, consulta: &str, limite: usize) -> Vec<Cie10> {
+        let consulta = consulta.trim();
+        if consulta.is_empty() {
+            return Vec::new();
+        }
+
+        let plano: String = consulta.to_uppercase().chars().filter(|c| *c != '.').collect();
+        let es_codigo = plano.len() <= 5
+            && plano.chars().next().is_some_and(|c| c.is_ascii_uppercase())
+            && plano.chars().nth(1).is_none_or(|c| c.is_ascii_digit())
+            && plano.chars().all(|c| c.is_ascii_alphanumeric());
+        if es_codigo {
+            let por_codigo: Vec<Cie10> = self.entradas
+                .iter()
+                .filter(|e| e.codigo_plano.starts_with(&plano))
+                .take(limite)
+                .map(|e| e.cie10.clone())
+                .collect();
+            if !por_codigo.is_empty() {
+                return por_codigo;
+            }
+        }
+
+        let plegada = plegar(consulta);
+        let palabras: Vec<&str> = plegada.split_whitespace().collect();
+        let mut encontradas: Vec<&Entrada> = self.entradas
+            .iter()
+            .filter(|e| palabras.iter().all(|p| e.descripcion_plegada.contains(p)))
+            .collect();
+        encontradas.sort_by_key(|e| !e.descripcion_plegada.starts_with(&plegada));
+        encontradas.into_iter().take(limite).map(|e| e.cie10.clone()).collect()
+    }
+
+    /// Verifica que los códigos existan en esta versión y completa las
+    /// descripciones que vienen vacías.
+    pub fn codificar(&self, diagnosticos: &mut [Diagnostico]) -> Result<(), AppError> {
+        for d in diagnosticos {
+            let cie10 = self.get(&d.codigo).ok_or_else(|| {
+                AppError::Validation(format!("El código {} no existe en la CIE-10 vigente ({})", d.codigo, self.version))
+            })?;
+            if d.descripcion.is_empty() {
+                d.descripcion = cie10.descripcion.clone();
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Catálogo cargado y el día en que se cargó.
+type Cargado = Option<(NaiveDate, Arc<Catalogo>)>;
+
+/// Catálogo vigente cacheado en memoria. Se recarga al cambiar el día (por si
+/// entró en vigencia una nueva versión) o tras una importación.
+#[derive(Clone, Default)]
+pub struct CacheCie10 {
+    cargado: Arc<RwLock<Cargado>>,
+}
+
+impl CacheCie10 {
+    pub async fn obtener<R: Cie10Repository>(&self, repo: &R) -> Result<Arc<Catalogo>, AppError> {
+        let hoy = tiempo::ahora().date();
+        if let Some((fecha, catalogo)) = self.cargado.read().map_err(|e| AppError::Internal(e.to_string()))?.as_ref()
+            && *fecha == hoy
+        {
+            return Ok(catalogo.clone());
+        }
+
+        let version = repo.version_vigente(hoy).await?
+            .ok_or_else(|| AppError::Config("No hay una versión vigente del catálogo CIE-10".into()))?;
+        let catalogo = Arc::new(Catalogo::new(version.clone(), repo.codigos(&version).await?));
+        *self.cargado.write().map_err(|e| AppError::Internal(e.to_string()))? = Some((hoy, catalogo.clone()));
+        Ok(catalogo)
+    }
+
+    pub fn invalidar(&self) {
+        if let Ok(mut cargado) = self.cargado.write() {
+            *cargado = None;
+        }
+    }
+}
+
+/// Lee un archivo `codigo;descripcion` (también acepta tabulador). Ignora la
+/// línea de encabezado y líneas vacías.
+pub fn parsear_importacion(texto: &str) -> Result<Vec<Cie10>, AppError> {
+    let mut codigos = Vec::new();
+    let mut lineas: HashMap<String, usize> = HashMap::new();
+    for (n, linea) in texto.lines().enumerate() {
+        let linea = linea.trim().trim_start_matches('\u{feff}');
+        if linea.is_empty() || (n == 0 && plegar(linea).starts_with("codigo")) {
+            continue;
+        }
+        let (codigo, descripcion) = linea
+            .split_once([';', '\t'])
+            .ok_or_else(|| AppError::Validation(format!("Línea {}: se esperaba codigo;descripcion", n + 1)))?;
+        let codigo = normalizar_codigo(codigo.trim_matches('"'))
+            .ok_or_else(|| AppError::Validation(format!("Línea {}: código CIE-10 inválido: {}", n + 1, codigo)))?;
+        let descripcion = descripcion.trim().trim_matches('"').trim();
+        if descripcion.is_empty() {
+            return Err(AppError::Validation(format!("Línea {}: falta la descripción", n + 1)));
+        }
+        if let Some(anterior) = lineas.insert(codigo.clone(), n + 1) {
+            return Err(AppError::Validation(format!("Línea {}: el código {} ya viene en la línea {}", n + 1, codigo, anterior)));
+        }
+        codigos.push(Cie10 { codigo, descripcion: descripcion.to_string() });
+    }
+    if codigos.is_empty() {
+        return Err(AppError::Validation("El archivo no contiene códigos".into()));
+    }
+    Ok(codigos)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn catalogo() -> Catalogo {
+        let texto = "codigo;descripcion\nJ18.9;Neumonía, no especificada\nNEUMO;código inválido\n";
+        assert!(parsear_importacion(texto).is_err());
+        match parsear_importacion("J18.9;Neumonía\nI10;Hipertensión\nJ189;Neumonía otra vez\n") {
+            Err(AppError::Validation(e)) => assert!(e.contains("J18.9") && e.contains("línea 1"), "{}", e),
+            otro => panic!("{:?}", otro.map(|c| c.len())),
+        }
+
+        let texto = "Código;Descripción\nJ189;Neumonía, no especificada\nJ18.0;Bronconeumonía, no especificada\nJ20.9;Bronquitis aguda, no especificada\nI10;Hipertensión esencial (primaria)\n";
+        Catalogo::new("2019".into(), parsear_importacion(texto).unwrap())
+    }
+
+    #[test]
+    fn normaliza_codigos() {
+        assert_eq!(normalizar_codigo("j189").as_deref(), Some("J18.9"));
+        assert_eq!(normalizar_codigo(" I10 ").as_deref(), Some("I10"));
+        assert_eq!(normalizar_codigo("18.9"), None);
+        assert_eq!(normalizar_codigo("AÑ1"), None);
+    }
+
+    #[test]
+    fn busca_por_prefijo_de_codigo_y_texto_sin_tildes() {
+        let catalogo = catalogo();
+        let codigos = |r: Vec<Cie10>| r.into_iter().map(|c| c.codigo).collect::<Vec<_>>();
+
+        assert_eq!(codigos(catalogo.buscar("j18", 10)), ["J18.0", "J18.9"]);
+        assert_eq!(codigos(catalogo.buscar("J18.9", 10)), ["J18.9"]);
+        assert_eq!(codigos(catalogo.buscar("NEUMONIA", 10)), ["J18.9", "J18.0"]);
+        assert_eq!(codigos(catalogo.buscar("aguda bronquitis", 10)), ["J20.9"]);
+        assert!(catalogo.get("i10").is_some());
+        assert!(catalogo.get("J99").is_none());
+    }
+}
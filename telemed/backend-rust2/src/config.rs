@@ -5,6 +5,25 @@ pub struct Config {
     pub database_url: String,
     pub server_address: String,
     pub log_level: String,
+    pub firma_clave_maestra: Option<String>,
+    pub notificaciones_email: String,
+    pub notificaciones_sms: String,
+    pub notificaciones_dir: String,
+    pub smtp_url: Option<String>,
+    pub smtp_remitente: Option<String>,
+    pub sms_url: Option<String>,
+    pub sms_token: Option<String>,
+    pub sms_remitente: Option<String>,
+    pub enlaces_clave: Option<String>,
+    pub portal_url: String,
+    pub adjuntos_almacen: String,
+    pub adjuntos_dir: String,
+    pub s3_url: Option<String>,
+    pub s3_bucket: Option<String>,
+    pub s3_region: String,
+    pub s3_acceso: Option<String>,
+    pub s3_secreto: Option<String>,
+    pub clamd_address: Option<String>,
 }
 
 impl Config {
@@ -13,6 +32,25 @@ impl Config {
             database_url: env::var("DATABASE_URL")?,
             server_address: env::var("SERVER_ADDRESS").unwrap_or_else(|_| "0.0.0.0:8080".into()),
             log_level: env::var("LOG_LEVEL").unwrap_or_else(|_| "info".into()),
+            firma_clave_maestra: env::var("FIRMA_CLAVE_MAESTRA").ok(),
+            notificaciones_email: env::var("NOTIFICACIONES_EMAIL").unwrap_or_else(|_| "consola".into()),
+            notificaciones_sms: env::var("NOTIFICACIONES_SMS").unwrap_or_else(|_| "consola".into()),
+            notificaciones_dir: env::var("NOTIFICACIONES_DIR").unwrap_or_else(|_| "notificaciones".into()),
+            smtp_url: env::var("SMTP_URL").ok(),
+            smtp_remitente: env::var("SMTP_REMITENTE").ok(),
+            sms_url: env::var("SMS_URL").ok(),
+            sms_token: env::var("SMS_TOKEN").ok(),
+            sms_remitente: env::var("SMS_REMITENTE").ok(),
+            enlaces_clave: env::var("ENLACES_CLAVE").ok(),
+            portal_url: env::var("PORTAL_URL").unwrap_or_else(|_| "http://localhost:8080".into()),
+            adjuntos_almacen: env::var("ADJUNTOS_ALMACEN").unwrap_or_else(|_| "local".into()),
+            adjuntos_dir: env::var("ADJUNTOS_DIR").unwrap_or_else(|_| "adjuntos".into()),
+            s3_url: env::var("S3_URL").ok(),
+            s3_bucket: env::var("S3_BUCKET").ok(),
+            s3_region: env::var("S3_REGION").unwrap_or_else(|_| "us-east-1".into()),
+            s3_acceso: env::var("S3_ACCESS_KEY").ok(),
+            s3_secreto: env::var("S3_SECRET_KEY").ok(),
+            clamd_address: env::var("CLAMD_ADDRESS").ok(),
         })
     }
 }
\ No newline at end of file
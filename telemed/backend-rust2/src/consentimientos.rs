@@ -0,0 +1,133 @@
+use crate::{
+    error::AppError,
+    models::{
+        EstadoConsentimiento, EventoConsentimiento, PlantillaConsentimiento, CANALES_CONSENTIMIENTO,
+        CONSENTIMIENTO_OTORGA, PROPOSITOS_CONSENTIMIENTO,
+    },
+    repositories::ConsentimientoRepository,
+};
+
+pub fn validar_proposito(proposito: &str) -> Result<String, AppError> {
+    let proposito = proposito.trim().to_uppercase();
+    if !PROPOSITOS_CONSENTIMIENTO.contains(&proposito.as_str()) {
+        return Err(AppError::Validation(format!(
+            "Propósito inválido: {}; se aceptan {}",
+            proposito,
+            PROPOSITOS_CONSENTIMIENTO.join(", ")
+        )));
+    }
+    Ok(proposito)
+}
+
+pub fn validar_canal(canal: &str) -> Result<String, AppError> {
+    let canal = canal.trim().to_uppercase();
+    if !CANALES_CONSENTIMIENTO.contains(&canal.as_str()) {
+        return Err(AppError::Validation(format!("Canal inválido: {}", canal)));
+    }
+    Ok(canal)
+}
+
+/// Versión mínima aceptada: la última que exigió renovar, o cualquiera si
+/// ninguna lo hizo.
+pub fn version_minima(versiones: &[PlantillaConsentimiento]) -> u16 {
+    versiones.iter().filter(|p| p.requiere_renovacion).map(|p| p.version).max().unwrap_or(0)
+}
+
+/// Aceptación vigente del propósito según los eventos del paciente.
+pub fn vigente<'a>(eventos: &'a [EventoConsentimiento], proposito: &str, minima: u16) -> Option<&'a EventoConsentimiento> {
+    eventos
+        .iter()
+        .filter(|e| e.proposito == proposito)
+        .max_by_key(|e| e.id)
+        .filter(|e| e.accion == CONSENTIMIENTO_OTORGA && e.version >= minima)
+}
+
+/// Estado de cada propósito a partir de los eventos del paciente y las
+/// versiones publicadas (de todos los propósitos).
+pub fn estados(eventos: &[EventoConsentimiento], versiones: &[PlantillaConsentimiento]) -> Vec<EstadoConsentimiento> {
+    PROPOSITOS_CONSENTIMIENTO
+        .iter()
+        .map(|&proposito| {
+            let del_proposito: Vec<PlantillaConsentimiento> =
+                versiones.iter().filter(|p| p.proposito == proposito).cloned().collect();
+            EstadoConsentimiento {
+                proposito: proposito.into(),
+                vigente: vigente(eventos, proposito, version_minima(&del_proposito)).is_some(),
+                version_actual: del_proposito.iter().map(|p| p.version).max(),
+                ultimo_evento: eventos.iter().filter(|e| e.proposito == proposito).max_by_key(|e| e.id).cloned(),
+            }
+        })
+        .collect()
+}
+
+/// Punto de control para las operaciones que requieren consentimiento:
+/// falla con 403 si el paciente no lo tiene vigente.
+pub async fn exigir<R>(repo: &R, id_paciente: u32, proposito: &str) -> Result<EventoConsentimiento, AppError>
+where
+    R: ConsentimientoRepository,
+{
+    repo.consentimiento_vigente(id_paciente, proposito).await?.ok_or_else(|| {
+        AppError::Forbidden(format!("El paciente {} no ha otorgado el consentimiento {}", id_paciente, proposito))
+    })
+}
+
+#[cfg(test)]
+pub fn otorgado(id_paciente: u32, proposito: &str) -> EventoConsentimiento {
+    EventoConsentimiento {
+        id: 0,
+        id_paciente,
+        proposito: proposito.into(),
+        version: 1,
+        accion: CONSENTIMIENTO_OTORGA.into(),
+        canal: "PORTAL".into(),
+        otorgante: None,
+        id_usuario: None,
+        ip: None,
+        ocurrido_en: crate::tiempo::ahora(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::models::{CONSENTIMIENTO_GRABACION, CONSENTIMIENTO_REVOCA, CONSENTIMIENTO_TELECONSULTA};
+    use super::*;
+
+    fn plantilla(version: u16, requiere_renovacion: bool) -> PlantillaConsentimiento {
+        PlantillaConsentimiento {
+            id: u32::from(version),
+            proposito: CONSENTIMIENTO_TELECONSULTA.into(),
+            version,
+            titulo: "Teleconsulta".into(),
+            texto: "Acepto…".into(),
+            requiere_renovacion,
+            creada_en: chrono::NaiveDateTime::default(),
+        }
+    }
+
+    fn evento(id: u32, version: u16, accion: &str) -> EventoConsentimiento {
+        EventoConsentimiento { id, version, accion: accion.into(), ..otorgado(1, CONSENTIMIENTO_TELECONSULTA) }
+    }
+
+    #[test]
+    fn vigencia_por_ultimo_evento_y_version() {
+        let versiones = vec![plantilla(1, false), plantilla(2, false), plantilla(3, true), plantilla(4, false)];
+        assert_eq!(version_minima(&versiones), 3);
+        assert_eq!(version_minima(&versiones[..2]), 0);
+
+        let eventos = vec![evento(1, 2, CONSENTIMIENTO_OTORGA)];
+        assert!(vigente(&eventos, CONSENTIMIENTO_TELECONSULTA, 0).is_some());
+        // Una versión que exige renovar invalida las aceptaciones anteriores
+        assert!(vigente(&eventos, CONSENTIMIENTO_TELECONSULTA, 3).is_none());
+
+        let eventos = vec![evento(1, 3, CONSENTIMIENTO_OTORGA), evento(2, 3, CONSENTIMIENTO_REVOCA)];
+        assert!(vigente(&eventos, CONSENTIMIENTO_TELECONSULTA, 3).is_none());
+        let eventos = vec![evento(3, 4, CONSENTIMIENTO_OTORGA), evento(2, 3, CONSENTIMIENTO_REVOCA)];
+        assert_eq!(vigente(&eventos, CONSENTIMIENTO_TELECONSULTA, 3).map(|e| e.id), Some(3));
+
+        let estados = estados(&eventos, &versiones);
+        let teleconsulta = estados.iter().find(|e| e.proposito == CONSENTIMIENTO_TELECONSULTA).unwrap();
+        assert_eq!((teleconsulta.vigente, teleconsulta.version_actual), (true, Some(4)));
+        let grabacion = estados.iter().find(|e| e.proposito == CONSENTIMIENTO_GRABACION).unwrap();
+        assert!(!grabacion.vigente && grabacion.ultimo_evento.is_none());
+    }
+}
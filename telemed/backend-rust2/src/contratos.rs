@@ -0,0 +1,127 @@
+use chrono::NaiveDate;
+
+use crate::{
+    error::AppError,
+    models::{Contrato, ContratoFiltro, Profesional, SituacionContractual, PROFESIONAL_NO_VIGENTE, PROFESIONAL_VIGENTE},
+    repositories::ContratoRepository,
+};
+
+/// Contrato del profesional vigente en la fecha, si lo hay.
+pub fn vigente(contratos: &[Contrato], id_prof: u32, fecha: NaiveDate) -> Option<&Contrato> {
+    contratos.iter().find(|c| c.id_prof == id_prof && c.vigente_en(fecha))
+}
+
+/// El contrato no puede traslaparse con otro período del mismo profesional.
+pub fn validar(contrato: &Contrato, existentes: &[Contrato]) -> Result<(), AppError> {
+    if let Some(egreso) = contrato.fecha_egreso && egreso < contrato.fecha_ingreso {
+        return Err(AppError::Validation("La fecha de egreso es anterior a la de ingreso".into()));
+    }
+    match existentes.iter().find(|c| c.id != contrato.id && c.id_prof == contrato.id_prof && c.se_traslapa(contrato)) {
+        Some(otro) => Err(AppError::Conflict(format!(
+            "Se traslapa con el contrato {} ({} a {})",
+            otro.id,
+            otro.fecha_ingreso,
+            otro.fecha_egreso.map_or("sin egreso".into(), |e| e.to_string()),
+        ))),
+        None => Ok(()),
+    }
+}
+
+/// Situación que corresponde al profesional en `hoy`. Las fechas son las
+/// del contrato vigente o, si no hay, las del último iniciado (o del
+/// próximo, si aún no empieza ninguno). Sin contratos conserva las fechas
+/// cargadas y queda no vigente.
+pub fn situacion(profesional: &Profesional, contratos: &[Contrato], hoy: NaiveDate) -> SituacionContractual {
+    let propios = || contratos.iter().filter(|c| c.id_prof == profesional.id_prof);
+    let vigente = vigente(contratos, profesional.id_prof, hoy);
+    let referencia = vigente
+        .or_else(|| propios().filter(|c| c.fecha_ingreso <= hoy).max_by_key(|c| c.fecha_ingreso))
+        .or_else(|| propios().min_by_key(|c| c.fecha_ingreso));
+
+    SituacionContractual {
+        estado: if vigente.is_some() { PROFESIONAL_VIGENTE } else { PROFESIONAL_NO_VIGENTE }.into(),
+        fecha_ingreso: referencia.map_or(profesional.fecha_ingreso, |c| Some(c.fecha_ingreso)),
+        fecha_egreso: referencia.map_or(profesional.fecha_egreso, |c| c.fecha_egreso),
+    }
+}
+
+/// La situación nueva si difiere de la que muestra `paso_profesionales`.
+pub fn cambio(profesional: &Profesional, contratos: &[Contrato], hoy: NaiveDate) -> Option<SituacionContractual> {
+    let nueva = situacion(profesional, contratos, hoy);
+    let actual = SituacionContractual {
+        estado: profesional.estado.clone().unwrap_or_default(),
+        fecha_ingreso: profesional.fecha_ingreso,
+        fecha_egreso: profesional.fecha_egreso,
+    };
+    (nueva != actual).then_some(nueva)
+}
+
+/// Punto de control para asignar trabajo: falla si el profesional no tiene
+/// contrato vigente en la fecha.
+pub async fn exigir<R>(repo: &R, id_prof: u32, fecha: NaiveDate) -> Result<Contrato, AppError>
+where
+    R: ContratoRepository,
+{
+    let filtro = ContratoFiltro { id_prof: Some(id_prof), vigente_en: Some(fecha) };
+    repo.contratos(&filtro).await?.into_iter().next().ok_or_else(|| {
+        AppError::Validation(format!("El profesional {} no tiene contrato vigente al {}", id_prof, fecha))
+    })
+}
+
+#[cfg(test)]
+pub fn contratado(id_prof: u32, fecha_ingreso: NaiveDate) -> Contrato {
+    Contrato {
+        id: 0,
+        id_prof,
+        fecha_ingreso,
+        fecha_egreso: None,
+        motivo_egreso: None,
+        observacion: None,
+        creado_en: crate::tiempo::ahora(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::repositories::fixtures;
+    use super::*;
+
+    fn fecha(m: u32, d: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(2025, m, d).unwrap()
+    }
+
+    fn profesional() -> Profesional {
+        Profesional { id_prof: 3, fecha_ingreso: Some(fecha(1, 2)), ..fixtures::profesional(3) }
+    }
+
+    #[test]
+    fn deriva_estado_de_los_periodos() {
+        let primero = Contrato { id: 1, fecha_egreso: Some(fecha(3, 31)), ..contratado(3, fecha(1, 2)) };
+        let segundo = Contrato { id: 2, ..contratado(3, fecha(6, 1)) };
+        let contratos = vec![primero.clone(), segundo.clone()];
+
+        // El día de egreso aún está vigente
+        assert_eq!(vigente(&contratos, 3, fecha(3, 31)).map(|c| c.id), Some(1));
+        assert!(vigente(&contratos, 3, fecha(4, 1)).is_none());
+        assert!(vigente(&contratos, 4, fecha(2, 1)).is_none());
+
+        assert_eq!(cambio(&profesional(), &contratos, fecha(2, 1)), Some(SituacionContractual {
+            estado: PROFESIONAL_VIGENTE.into(),
+            fecha_ingreso: Some(fecha(1, 2)),
+            fecha_egreso: Some(fecha(3, 31)),
+        }));
+        let entre = situacion(&profesional(), &contratos, fecha(5, 1));
+        assert_eq!((entre.estado.as_str(), entre.fecha_egreso), (PROFESIONAL_NO_VIGENTE, Some(fecha(3, 31))));
+        let reingreso = situacion(&profesional(), &contratos, fecha(6, 1));
+        assert_eq!((reingreso.estado.as_str(), reingreso.fecha_ingreso, reingreso.fecha_egreso), (PROFESIONAL_VIGENTE, Some(fecha(6, 1)), None));
+        assert_eq!(situacion(&profesional(), &[], fecha(6, 1)).fecha_ingreso, Some(fecha(1, 2)));
+
+        let traslapado = Contrato { fecha_egreso: Some(fecha(6, 15)), ..contratado(3, fecha(3, 15)) };
+        assert!(matches!(validar(&traslapado, &contratos), Err(AppError::Conflict(_))));
+        let entre_medio = Contrato { fecha_egreso: Some(fecha(5, 31)), ..contratado(3, fecha(4, 1)) };
+        assert!(validar(&entre_medio, &contratos).is_ok());
+        // Corregir el egreso del propio contrato no choca consigo mismo
+        assert!(validar(&Contrato { fecha_egreso: Some(fecha(4, 30)), ..primero }, &contratos).is_ok());
+        assert!(validar(&Contrato { fecha_egreso: Some(fecha(5, 1)), ..contratado(3, fecha(5, 2)) }, &contratos).is_err());
+    }
+}
@@ -0,0 +1,139 @@
+use std::collections::HashMap;
+
+use chrono::NaiveDate;
+
+use crate::{
+    error::AppError,
+    models::{nombre_credencial, Credencial, CredencialFiltro},
+    repositories::CredencialRepository,
+};
+
+/// Días de anticipación con que se avisa un vencimiento.
+pub const AVISOS: [i64; 2] = [30, 7];
+
+/// La credencial que rige de cada tipo por profesional: la que vence más
+/// tarde (o no vence) y, a igual vencimiento, la última registrada.
+pub fn actuales(credenciales: &[Credencial]) -> Vec<&Credencial> {
+    let mut actuales: HashMap<(u32, &str), &Credencial> = HashMap::new();
+    for credencial in credenciales {
+        let clave = |c: &Credencial| (c.vence_en.unwrap_or(NaiveDate::MAX), c.registrada_en, c.id);
+        actuales
+            .entry((credencial.id_prof, credencial.tipo.as_str()))
+            .and_modify(|actual| if clave(credencial) > clave(actual) { *actual = credencial })
+            .or_insert(credencial);
+    }
+    let mut actuales: Vec<&Credencial> = actuales.into_values().collect();
+    actuales.sort_by_key(|c| (c.id_prof, c.tipo.as_str()));
+    actuales
+}
+
+/// Las obligatorias que rigen, para revisar muchas fechas sin recalcularlas.
+pub fn obligatorias(credenciales: &[Credencial]) -> Vec<&Credencial> {
+    actuales(credenciales).into_iter().filter(|c| c.obligatoria()).collect()
+}
+
+/// Credenciales obligatorias del profesional que en la fecha están
+/// vencidas sin una renovación registrada.
+pub fn vencidas(credenciales: &[Credencial], id_prof: u32, fecha: NaiveDate) -> Vec<&Credencial> {
+    obligatorias(credenciales)
+        .into_iter()
+        .filter(|c| c.id_prof == id_prof && !c.vigente_en(fecha))
+        .collect()
+}
+
+/// Si el profesional puede recibir trabajo en la fecha, según las
+/// `obligatorias` que rigen. Sin credenciales cargadas no se bloquea.
+pub fn habilitado(obligatorias: &[&Credencial], id_prof: u32, fecha: NaiveDate) -> bool {
+    !obligatorias.iter().any(|c| c.id_prof == id_prof && !c.vigente_en(fecha))
+}
+
+/// Umbral de aviso que corresponde a una credencial que vence en
+/// `vence_en`: el menor de `AVISOS` que ya se alcanzó. Una cargada a 5
+/// días de vencer sólo recibe el aviso de 7. Las vencidas no se avisan.
+pub fn aviso_debido(vence_en: NaiveDate, hoy: NaiveDate) -> Option<i64> {
+    let restantes = (vence_en - hoy).num_days();
+    if restantes < 0 {
+        return None;
+    }
+    AVISOS.into_iter().filter(|&a| restantes <= a).min()
+}
+
+/// Punto de control para asignar trabajo: falla si el profesional tiene
+/// alguna credencial obligatoria vencida en la fecha.
+pub async fn exigir<R>(repo: &R, id_prof: u32, fecha: NaiveDate) -> Result<(), AppError>
+where
+    R: CredencialRepository,
+{
+    let credenciales = repo.credenciales(&CredencialFiltro { id_prof: Some(id_prof), ..Default::default() }).await?;
+    let vencidas = vencidas(&credenciales, id_prof, fecha);
+    if vencidas.is_empty() {
+        return Ok(());
+    }
+    let detalle: Vec<String> = vencidas
+        .iter()
+        .filter_map(|c| c.vence_en.map(|v| format!("{} venció el {}", nombre_credencial(&c.tipo), v)))
+        .collect();
+    Err(AppError::Validation(format!(
+        "El profesional {} tiene credenciales vencidas al {}: {}",
+        id_prof,
+        fecha,
+        detalle.join(", "),
+    )))
+}
+
+#[cfg(test)]
+pub fn credencial(id_prof: u32, tipo: &str, vence_en: Option<NaiveDate>) -> Credencial {
+    Credencial {
+        id: 0,
+        id_prof,
+        tipo: tipo.into(),
+        descripcion: None,
+        numero: None,
+        emitida_en: None,
+        vence_en,
+        nombre: "credencial.pdf".into(),
+        tipo_mime: "application/pdf".into(),
+        tamano: 0,
+        sha256: String::new(),
+        registrada_en: crate::tiempo::ahora(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::models::{CREDENCIAL_OTRA, CREDENCIAL_SEGURO, CREDENCIAL_TITULO, CREDENCIAL_VACUNACION};
+    use super::*;
+
+    fn fecha(m: u32, d: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(2025, m, d).unwrap()
+    }
+
+    #[test]
+    fn detecta_vencidas_y_avisos() {
+        let credenciales = vec![
+            Credencial { id: 1, ..credencial(3, CREDENCIAL_TITULO, None) },
+            Credencial { id: 2, ..credencial(3, CREDENCIAL_SEGURO, Some(fecha(3, 31))) },
+            Credencial { id: 3, ..credencial(3, CREDENCIAL_VACUNACION, Some(fecha(3, 31))) },
+            // Renovación del seguro
+            Credencial { id: 4, ..credencial(3, CREDENCIAL_SEGURO, Some(fecha(12, 31))) },
+            Credencial { id: 5, ..credencial(3, CREDENCIAL_OTRA, Some(fecha(1, 31))) },
+            Credencial { id: 6, ..credencial(4, CREDENCIAL_VACUNACION, Some(fecha(6, 30))) },
+        ];
+
+        let ids = |v: Vec<&Credencial>| v.iter().map(|c| c.id).collect::<Vec<_>>();
+        assert_eq!(ids(actuales(&credenciales)), vec![5, 4, 1, 3, 6]);
+        // El día de vencimiento aún vale; la otra no es obligatoria
+        assert!(vencidas(&credenciales, 3, fecha(3, 31)).is_empty());
+        assert_eq!(ids(vencidas(&credenciales, 3, fecha(4, 1))), vec![3]);
+        assert!(vencidas(&credenciales, 4, fecha(4, 1)).is_empty());
+        let obligatorias = obligatorias(&credenciales);
+        assert!(habilitado(&obligatorias, 3, fecha(3, 31)) && !habilitado(&obligatorias, 3, fecha(4, 1)));
+        assert!(habilitado(&obligatorias, 9, fecha(4, 1)));
+
+        assert_eq!(aviso_debido(fecha(3, 31), fecha(2, 28)), None);
+        assert_eq!(aviso_debido(fecha(3, 31), fecha(3, 1)), Some(30));
+        assert_eq!(aviso_debido(fecha(3, 31), fecha(3, 24)), Some(7));
+        assert_eq!(aviso_debido(fecha(3, 31), fecha(3, 31)), Some(7));
+        assert_eq!(aviso_debido(fecha(3, 31), fecha(4, 1)), None);
+    }
+}
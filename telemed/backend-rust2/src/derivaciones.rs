@@ -0,0 +1,502 @@
+use chrono::{NaiveDate, NaiveDateTime};
+use serde_json::{json, Value};
+
+use crate::{
+    cie10::{self, Catalogo},
+    error::AppError,
+    fhir::{SISTEMA_CIE10, SISTEMA_RUN},
+    models::{
+        Derivacion, DerivacionDetalle, Diagnostico, Importacion, Paciente, PacienteInput, ResultadoImportacion,
+        DERIVACION_PENDIENTE, PRIORIDAD_INMEDIATA, PRIORIDAD_PRONTO, PRIORIDAD_RUTINA, PRIORIDAD_URGENTE,
+    },
+};
+
+/// Extensión de HL7 Chile Core con el segundo apellido del nombre.
+const EXT_SEGUNDO_APELLIDO: &str = "https://hl7chile.cl/fhir/ig/clcore/StructureDefinition/SegundoApellido";
+
+/// Error de validación de una entrada del Bundle, o del Bundle completo si
+/// `entrada` es `None`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Problema {
+    pub entrada: Option<usize>,
+    pub mensaje: String,
+}
+
+impl Problema {
+    fn new(entrada: Option<usize>, mensaje: impl Into<String>) -> Self {
+        Self { entrada, mensaje: mensaje.into() }
+    }
+}
+
+/// En qué se convirtió cada entrada: un paciente, una derivación o un
+/// diagnóstico de una derivación (posiciones en la `Importacion`).
+#[derive(Debug, Clone, PartialEq)]
+pub enum Destino {
+    Paciente(usize),
+    Derivacion(usize),
+    Diagnostico(usize),
+}
+
+/// Bundle interpretado: lo que hay que importar y el destino de cada entrada,
+/// en el orden del Bundle.
+#[derive(Debug)]
+pub struct Lote {
+    pub importacion: Importacion,
+    pub destinos: Vec<Destino>,
+}
+
+fn mensaje(e: AppError) -> String {
+    match e {
+        AppError::Validation(m) => m,
+        otro => otro.to_string(),
+    }
+}
+
+fn texto(valor: &Value) -> Option<String> {
+    valor.as_str().map(str::trim).filter(|t| !t.is_empty()).map(str::to_string)
+}
+
+fn lista(valor: &Value) -> impl Iterator<Item = &Value> {
+    valor.as_array().into_iter().flatten()
+}
+
+/// Una referencia apunta a una entrada si coincide con su `fullUrl`
+/// (`urn:uuid:…`) o es la forma relativa (`Patient/123`) de una URL absoluta.
+fn apunta_a(referencia: &str, url: &str) -> bool {
+    referencia == url || url.ends_with(&format!("/{}", referencia))
+}
+
+fn resolver(referencia: &Value, urls: &[(String, usize)]) -> Option<usize> {
+    let referencia = referencia["reference"].as_str()?;
+    urls.iter().find(|(url, _)| apunta_a(referencia, url)).map(|(_, pos)| *pos)
+}
+
+fn paciente(recurso: &Value) -> Result<Paciente, String> {
+    let rut = lista(&recurso["identifier"])
+        .find(|i| i["system"] == SISTEMA_RUN)
+        .and_then(|i| texto(&i["value"]))
+        .ok_or_else(|| format!("Patient sin identifier {}", SISTEMA_RUN))?;
+    let nombres: Vec<&Value> = lista(&recurso["name"]).collect();
+    let nombre = nombres
+        .iter()
+        .find(|n| n["use"] == "official")
+        .or(nombres.first())
+        .ok_or("Patient sin name")?;
+
+    let familia = texto(&nombre["family"]).unwrap_or_default();
+    let segundo = lista(&nombre["_family"]["extension"])
+        .find(|e| e["url"] == EXT_SEGUNDO_APELLIDO)
+        .and_then(|e| texto(&e["valueString"]));
+    // Sin la extensión se asume "PATERNO MATERNO" en family
+    let (ap_paterno, ap_materno) = match segundo {
+        Some(segundo) => (familia, Some(segundo)),
+        None => match familia.split_once(' ') {
+            Some((paterno, materno)) => (paterno.to_string(), Some(materno.trim().to_string())),
+            None => (familia, None),
+        },
+    };
+    let given: Vec<String> = lista(&nombre["given"]).filter_map(texto).collect();
+
+    let sexo = match recurso["gender"].as_str() {
+        Some("female") => Some("F"),
+        Some("male") => Some("M"),
+        Some("other") => Some("O"),
+        _ => None,
+    };
+    let fecha_nacimiento = match recurso["birthDate"].as_str() {
+        Some(f) => Some(NaiveDate::parse_from_str(f, "%Y-%m-%d").map_err(|_| format!("birthDate inválida: {}", f))?),
+        None => None,
+    };
+    let telefonos: Vec<String> = lista(&recurso["telecom"])
+        .filter(|t| t["system"] == "phone" || t["system"] == "sms")
+        .filter_map(|t| texto(&t["value"]))
+        .collect();
+    let email = lista(&recurso["telecom"]).find(|t| t["system"] == "email").and_then(|t| texto(&t["value"]));
+    let direccion = lista(&recurso["address"]).next();
+    let lineas: Vec<String> = direccion.map(|d| lista(&d["line"]).filter_map(texto).collect()).unwrap_or_default();
+
+    Paciente::try_from(PacienteInput {
+        rut,
+        nombres: given.join(" "),
+        ap_paterno,
+        ap_materno,
+        fecha_nacimiento,
+        sexo: sexo.map(str::to_string),
+        cod_prevision: None,
+        direccion: (!lineas.is_empty()).then(|| lineas.join(", ")),
+        comuna: direccion.and_then(|d| texto(&d["district"])),
+        ciudad: direccion.and_then(|d| texto(&d["city"])),
+        cod_zona: None,
+        latitud: None,
+        longitud: None,
+        email,
+        telefonos: (!telefonos.is_empty()).then(|| telefonos.join(" / ")),
+        cod_cliente: None,
+        estatus: if recurso["active"] == false { 0 } else { 1 },
+    })
+    .map_err(mensaje)
+}
+
+fn diagnostico(recurso: &Value, catalogo: &Catalogo) -> Result<Diagnostico, String> {
+    let codificacion = lista(&recurso["code"]["coding"])
+        .find(|c| c["system"] == SISTEMA_CIE10)
+        .ok_or_else(|| format!("Condition sin código {}", SISTEMA_CIE10))?;
+    let codigo = texto(&codificacion["code"]).unwrap_or_default();
+    let mut diagnostico = Diagnostico {
+        codigo: cie10::normalizar_codigo(&codigo).ok_or_else(|| format!("Código CIE-10 inválido: {}", codigo))?,
+        descripcion: texto(&codificacion["display"]).unwrap_or_default(),
+        principal: false,
+    };
+    catalogo.codificar(std::slice::from_mut(&mut diagnostico)).map_err(mensaje)?;
+    Ok(diagnostico)
+}
+
+fn derivacion(recurso: &Value, ahora: NaiveDateTime) -> Result<Derivacion, String> {
+    if let Some(estado) = recurso["status"].as_str()
+        && estado != "active"
+    {
+        return Err(format!("Sólo se aceptan ServiceRequest activas, no {}", estado));
+    }
+    let codigo = &recurso["code"];
+    let especialidad = texto(&codigo["text"])
+        .or_else(|| lista(&codigo["coding"]).find_map(|c| texto(&c["display"]).or_else(|| texto(&c["code"]))))
+        .ok_or("ServiceRequest sin code")?;
+    let prioridad = match recurso["priority"].as_str().unwrap_or("routine") {
+        "routine" => PRIORIDAD_RUTINA,
+        "urgent" => PRIORIDAD_URGENTE,
+        "asap" => PRIORIDAD_PRONTO,
+        "stat" => PRIORIDAD_INMEDIATA,
+        otra => return Err(format!("priority inválida: {}", otra)),
+    };
+    // Sin `system` el valor no identifica a quién deriva: "123" de un
+    // hospital chocaría con el "123" de otro
+    let identificadores: Vec<&Value> = lista(&recurso["identifier"]).filter(|i| texto(&i["value"]).is_some()).collect();
+    let referencia_externa = identificadores
+        .iter()
+        .find_map(|i| Some(format!("{}|{}", texto(&i["system"])?, texto(&i["value"])?)));
+    if referencia_externa.is_none() && !identificadores.is_empty() {
+        return Err("ServiceRequest.identifier sin system".into());
+    }
+    let notas: Vec<String> = lista(&recurso["note"]).filter_map(|n| texto(&n["text"])).collect();
+    Ok(Derivacion {
+        id: 0,
+        id_paciente: 0,
+        origen: texto(&recurso["requester"]["display"]),
+        referencia_externa,
+        especialidad,
+        prioridad: prioridad.into(),
+        nota: (!notas.is_empty()).then(|| notas.join("\n")),
+        estado: DERIVACION_PENDIENTE.into(),
+        recibida_en: ahora,
+    })
+}
+
+/// Interpreta un Bundle `transaction` con Patient, Condition y
+/// ServiceRequest. Cada ServiceRequest es una derivación de su paciente y
+/// lleva como diagnósticos las Condition de `reasonReference`, o si no
+/// indica ninguna, las Condition del mismo paciente que no citó otra
+/// solicitud. Se reportan todos los problemas encontrados, no sólo el primero.
+pub fn interpretar(bundle: &Value, catalogo: &Catalogo, ahora: NaiveDateTime) -> Result<Lote, Vec<Problema>> {
+    if bundle["resourceType"] != "Bundle" || bundle["type"] != "transaction" {
+        return Err(vec![Problema::new(None, "Se espera un Bundle de tipo transaction")]);
+    }
+    let entradas: Vec<&Value> = lista(&bundle["entry"]).collect();
+    if entradas.is_empty() {
+        return Err(vec![Problema::new(None, "El Bundle no tiene entradas")]);
+    }
+
+    let mut problemas = Vec::new();
+    let mut importacion = Importacion::default();
+    let mut destinos = vec![None; entradas.len()];
+    let mut urls_pacientes: Vec<(String, usize)> = Vec::new();
+    let mut condiciones: Vec<(usize, String, usize, Diagnostico)> = Vec::new();
+    let mut solicitudes: Vec<(usize, usize, Derivacion, Vec<String>)> = Vec::new();
+
+    for (i, entrada) in entradas.iter().enumerate() {
+        let recurso = &entrada["resource"];
+        let tipo = recurso["resourceType"].as_str().unwrap_or_default();
+        let url = texto(&entrada["fullUrl"]).unwrap_or_default();
+        let problema = |m: String| Problema::new(Some(i), m);
+        match entrada["request"]["method"].as_str() {
+            Some("POST") => {}
+            Some("PUT") if tipo == "Patient" => {}
+            metodo => {
+                problemas.push(problema(format!("Método no soportado para {}: {}", tipo, metodo.unwrap_or("(ninguno)"))));
+                continue;
+            }
+        }
+        match tipo {
+            "Patient" => match paciente(recurso) {
+                Ok(p) if importacion.pacientes.iter().any(|o| o.rut == p.rut) => {
+                    problemas.push(problema(format!("RUT {} repetido en el Bundle", p.rut)))
+                }
+                Ok(p) => {
+                    destinos[i] = Some(Destino::Paciente(importacion.pacientes.len()));
+                    urls_pacientes.push((url, importacion.pacientes.len()));
+                    importacion.pacientes.push(p);
+                }
+                Err(m) => problemas.push(problema(m)),
+            },
+            "Condition" | "ServiceRequest" => {}
+            otro => problemas.push(problema(format!("Recurso no soportado: {}", otro))),
+        }
+    }
+
+    for (i, entrada) in entradas.iter().enumerate() {
+        let recurso = &entrada["resource"];
+        let tipo = recurso["resourceType"].as_str().unwrap_or_default();
+        if tipo != "Condition" && tipo != "ServiceRequest" {
+            continue;
+        }
+        let Some(paciente) = resolver(&recurso["subject"], &urls_pacientes) else {
+            problemas.push(Problema::new(Some(i), format!("{} sin subject a un Patient del Bundle", tipo)));
+            continue;
+        };
+        let url = texto(&entrada["fullUrl"]).unwrap_or_default();
+        let resultado = if tipo == "Condition" {
+            diagnostico(recurso, catalogo).map(|d| condiciones.push((i, url, paciente, d)))
+        } else {
+            derivacion(recurso, ahora).map(|d| {
+                let motivos = lista(&recurso["reasonReference"]).filter_map(|r| texto(&r["reference"])).collect();
+                solicitudes.push((i, paciente, d, motivos));
+            })
+        };
+        if let Err(m) = resultado {
+            problemas.push(Problema::new(Some(i), m));
+        }
+    }
+
+    let citadas: Vec<bool> = condiciones
+        .iter()
+        .map(|(_, url, _, _)| solicitudes.iter().any(|s| s.3.iter().any(|r| apunta_a(r, url))))
+        .collect();
+    for (k, (i, paciente, derivacion, motivos)) in solicitudes.into_iter().enumerate() {
+        let mut elegidas = Vec::new();
+        for motivo in &motivos {
+            match condiciones.iter().position(|c| apunta_a(motivo, &c.1)) {
+                Some(c) if condiciones[c].2 == paciente => elegidas.push(c),
+                Some(_) => problemas.push(Problema::new(Some(i), format!("{} es de otro paciente", motivo))),
+                None => problemas.push(Problema::new(Some(i), format!("reasonReference {} no es una Condition del Bundle", motivo))),
+            }
+        }
+        if motivos.is_empty() {
+            elegidas = (0..condiciones.len()).filter(|&c| condiciones[c].2 == paciente && !citadas[c]).collect();
+        }
+        let diagnosticos = elegidas
+            .iter()
+            .enumerate()
+            .map(|(orden, &c)| {
+                destinos[condiciones[c].0].get_or_insert(Destino::Diagnostico(k));
+                Diagnostico { principal: orden == 0, ..condiciones[c].3.clone() }
+            })
+            .collect();
+        importacion.derivaciones.push((paciente, DerivacionDetalle { derivacion, diagnosticos }));
+        destinos[i] = Some(Destino::Derivacion(k));
+    }
+    for (i, ..) in &condiciones {
+        if destinos[*i].is_none() {
+            problemas.push(Problema::new(Some(*i), "Condition no asociada a ninguna ServiceRequest"));
+        }
+    }
+
+    if !problemas.is_empty() {
+        problemas.sort_by_key(|p| p.entrada);
+        return Err(problemas);
+    }
+    Ok(Lote { importacion, destinos: destinos.into_iter().flatten().collect() })
+}
+
+/// Datos del paciente ya registrado que el establecimiento no envía: la
+/// previsión, la zona y la geocodificación son nuestras, y los campos que
+/// vienen vacíos no borran los que teníamos.
+pub fn combinar(mut nuevo: Paciente, existente: &Paciente) -> Paciente {
+    nuevo.id = existente.id;
+    nuevo.cod_prevision = existente.cod_prevision;
+    nuevo.cod_zona = existente.cod_zona.clone();
+    nuevo.latitud = existente.latitud;
+    nuevo.longitud = existente.longitud;
+    nuevo.cod_cliente = existente.cod_cliente;
+    nuevo.ap_materno = nuevo.ap_materno.or_else(|| existente.ap_materno.clone());
+    nuevo.fecha_nacimiento = nuevo.fecha_nacimiento.or(existente.fecha_nacimiento);
+    nuevo.sexo = nuevo.sexo.or_else(|| existente.sexo.clone());
+    nuevo.direccion = nuevo.direccion.or_else(|| existente.direccion.clone());
+    nuevo.comuna = nuevo.comuna.or_else(|| existente.comuna.clone());
+    nuevo.ciudad = nuevo.ciudad.or_else(|| existente.ciudad.clone());
+    nuevo.email = nuevo.email.or_else(|| existente.email.clone());
+    nuevo.telefonos = nuevo.telefonos.or_else(|| existente.telefonos.clone());
+    nuevo
+}
+
+/// OperationOutcome con un issue por problema, apuntando a su entrada.
+pub fn resultado_operacion(problemas: &[Problema]) -> Value {
+    let issues: Vec<Value> = problemas
+        .iter()
+        .map(|p| {
+            let mut issue = json!({ "severity": "error", "code": "invalid", "diagnostics": p.mensaje });
+            if let Some(i) = p.entrada {
+                issue["expression"] = json!([format!("Bundle.entry[{}]", i)]);
+            }
+            issue
+        })
+        .collect();
+    json!({ "resourceType": "OperationOutcome", "issue": issues })
+}
+
+fn estado(creado: bool) -> &'static str {
+    if creado { "201 Created" } else { "200 OK" }
+}
+
+/// Problemas de las derivaciones que la importación rechazó porque su
+/// referencia ya está registrada para otro paciente.
+pub fn rechazos(destinos: &[Destino], rechazadas: &[usize]) -> Vec<Problema> {
+    destinos
+        .iter()
+        .enumerate()
+        .filter(|(_, destino)| matches!(destino, Destino::Derivacion(k) if rechazadas.contains(k)))
+        .map(|(i, _)| Problema::new(Some(i), "La ServiceRequest ya está registrada para otro paciente"))
+        .collect()
+}
+
+/// Bundle `transaction-response` con el resultado de cada entrada, en el
+/// mismo orden en que llegaron.
+pub fn respuesta(destinos: &[Destino], resultado: &ResultadoImportacion) -> Value {
+    let entradas: Vec<Value> = destinos
+        .iter()
+        .map(|destino| match *destino {
+            Destino::Paciente(k) => {
+                let (id, creado) = resultado.pacientes[k];
+                json!({ "response": { "status": estado(creado), "location": format!("Patient/{}", id) } })
+            }
+            Destino::Derivacion(k) => {
+                let (id, creada) = resultado.derivaciones[k];
+                json!({ "response": { "status": estado(creada), "location": format!("ServiceRequest/{}", id) } })
+            }
+            Destino::Diagnostico(k) => {
+                let (id, creada) = resultado.derivaciones[k];
+                json!({ "response": {
+                    "status": estado(creada),
+                    "outcome": {
+                        "resourceType": "OperationOutcome",
+                        "issue": [{
+                            "severity": "information",
+                            "code": "informational",
+                            "diagnostics": format!("Registrada como diagnóstico de ServiceRequest/{}", id),
+                        }],
+                    },
+                } })
+            }
+        })
+        .collect();
+    json!({ "resourceType": "Bundle", "type": "transaction-response", "entry": entradas })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::models::Cie10;
+    use super::*;
+
+    fn catalogo() -> Catalogo {
+        Catalogo::new("2019".into(), vec![
+            Cie10 { codigo: "E11.9".into(), descripcion: "Diabetes mellitus tipo 2 sin complicaciones".into() },
+            Cie10 { codigo: "I10".into(), descripcion: "Hipertensión esencial (primaria)".into() },
+        ])
+    }
+
+    fn entrada(url: &str, recurso: Value) -> Value {
+        json!({ "fullUrl": url, "resource": recurso, "request": { "method": "POST", "url": recurso["resourceType"] } })
+    }
+
+    fn bundle() -> Value {
+        json!({
+            "resourceType": "Bundle",
+            "type": "transaction",
+            "entry": [
+                entrada("urn:uuid:p1", json!({
+                    "resourceType": "Patient",
+                    "identifier": [{ "system": SISTEMA_RUN, "value": "10895960-6" }],
+                    "name": [{ "family": "Pérez", "_family": { "extension": [{ "url": EXT_SEGUNDO_APELLIDO, "valueString": "de la Fuente" }] }, "given": ["Ana", "María"] }],
+                    "gender": "female",
+                    "birthDate": "1956-04-02",
+                    "telecom": [{ "system": "phone", "value": "912345678" }],
+                })),
+                entrada("urn:uuid:c1", json!({
+                    "resourceType": "Condition",
+                    "subject": { "reference": "urn:uuid:p1" },
+                    "code": { "coding": [{ "system": SISTEMA_CIE10, "code": "E119" }] },
+                })),
+                entrada("urn:uuid:c2", json!({
+                    "resourceType": "Condition",
+                    "subject": { "reference": "urn:uuid:p1" },
+                    "code": { "coding": [{ "system": SISTEMA_CIE10, "code": "I10", "display": "HTA" }] },
+                })),
+                entrada("urn:uuid:s1", json!({
+                    "resourceType": "ServiceRequest",
+                    "status": "active",
+                    "intent": "order",
+                    "priority": "urgent",
+                    "identifier": [{ "system": "urn:hospital:derivaciones", "value": "D-881" }],
+                    "code": { "text": "Control diabetes" },
+                    "subject": { "reference": "urn:uuid:p1" },
+                    "requester": { "display": "Hospital de Ovalle" },
+                    "reasonReference": [{ "reference": "urn:uuid:c2" }, { "reference": "urn:uuid:c1" }],
+                })),
+            ],
+        })
+    }
+
+    #[test]
+    fn interpreta_bundle() {
+        let ahora = NaiveDate::from_ymd_opt(2025, 3, 3).unwrap().and_hms_opt(12, 0, 0).unwrap();
+        let lote = interpretar(&bundle(), &catalogo(), ahora).unwrap();
+        assert_eq!(lote.destinos, vec![Destino::Paciente(0), Destino::Diagnostico(0), Destino::Diagnostico(0), Destino::Derivacion(0)]);
+
+        let p = &lote.importacion.pacientes[0];
+        assert_eq!((p.rut.as_str(), p.nombres.as_str(), p.ap_paterno.as_str(), p.ap_materno.as_deref()), ("0010895960-6", "ANA MARÍA", "PÉREZ", Some("DE LA FUENTE")));
+        assert_eq!((p.sexo.as_deref(), p.telefonos.as_deref(), p.estatus), (Some("F"), Some("912345678"), 1));
+
+        let (paciente, d) = &lote.importacion.derivaciones[0];
+        assert_eq!(*paciente, 0);
+        assert_eq!((d.derivacion.prioridad.as_str(), d.derivacion.origen.as_deref()), (PRIORIDAD_URGENTE, Some("Hospital de Ovalle")));
+        assert_eq!(d.derivacion.referencia_externa.as_deref(), Some("urn:hospital:derivaciones|D-881"));
+        let codigos: Vec<(&str, &str, bool)> = d.diagnosticos.iter().map(|g| (g.codigo.as_str(), g.descripcion.as_str(), g.principal)).collect();
+        assert_eq!(codigos, vec![("I10", "HTA", true), ("E11.9", "Diabetes mellitus tipo 2 sin complicaciones", false)]);
+    }
+
+    #[test]
+    fn reporta_todos_los_problemas() {
+        let mut b = bundle();
+        b["entry"][0]["resource"]["identifier"][0]["value"] = json!("10895960-5");
+        b["entry"][2]["resource"]["code"]["coding"][0]["code"] = json!("Z99.9");
+        b["entry"][3]["resource"]["priority"] = json!("whenever");
+        b["entry"].as_array_mut().unwrap().push(json!({ "resource": { "resourceType": "Observation" }, "request": { "method": "POST" } }));
+
+        let problemas = interpretar(&b, &catalogo(), chrono::NaiveDateTime::default()).unwrap_err();
+        let entradas: Vec<Option<usize>> = problemas.iter().map(|p| p.entrada).collect();
+        // El paciente inválido deja a las Condition y la ServiceRequest sin subject
+        assert_eq!(entradas, vec![Some(0), Some(1), Some(2), Some(3), Some(4)]);
+        assert!(problemas[0].mensaje.contains("Dígito verificador"));
+        assert_eq!(resultado_operacion(&problemas)["issue"][4]["expression"][0], "Bundle.entry[4]");
+
+        assert!(interpretar(&json!({ "resourceType": "Bundle", "type": "batch" }), &catalogo(), chrono::NaiveDateTime::default()).is_err());
+
+        let mut b = bundle();
+        b["entry"][3]["resource"]["identifier"][0].as_object_mut().unwrap().remove("system");
+        let problemas = interpretar(&b, &catalogo(), chrono::NaiveDateTime::default()).unwrap_err();
+        assert!(problemas.iter().any(|p| p.entrada == Some(3) && p.mensaje.contains("sin system")));
+    }
+
+    #[test]
+    fn combina_con_el_registrado() {
+        let ahora = chrono::NaiveDateTime::default();
+        let mut existente = interpretar(&bundle(), &catalogo(), ahora).unwrap().importacion.pacientes.remove(0);
+        existente.id = 7;
+        existente.cod_prevision = Some(1);
+        existente.email = Some("ana@correo.cl".into());
+        let mut b = bundle();
+        b["entry"][0]["resource"]["telecom"] = json!([]);
+        let nuevo = interpretar(&b, &catalogo(), ahora).unwrap().importacion.pacientes.remove(0);
+        let combinado = combinar(nuevo, &existente);
+        assert_eq!((combinado.id, combinado.cod_prevision), (7, Some(1)));
+        assert_eq!((combinado.email.as_deref(), combinado.telefonos.as_deref()), (Some("ana@correo.cl"), Some("912345678")));
+    }
+}
@@ -0,0 +1,238 @@
+use std::collections::BTreeMap;
+
+use chrono::{Datelike, NaiveDate};
+use chrono_tz::Tz;
+use sha2::{Digest, Sha256};
+
+use crate::{
+    error::AppError,
+    models::{AtencionDetalle, DocumentoClinico, Paciente, Profesional, VersionDocumento},
+    pdf::Documento,
+    tiempo,
+};
+
+const FORMATO_FECHA: &str = "%d-%m-%Y";
+
+/// Datos que las plantillas pueden usar, además de `campos.*`.
+pub const CLAVES: [&str; 15] = [
+    "paciente.nombre",
+    "paciente.rut",
+    "paciente.fecha_nacimiento",
+    "paciente.edad",
+    "paciente.direccion",
+    "profesional.nombre",
+    "profesional.rut",
+    "profesional.especialidad",
+    "profesional.rnpi",
+    "atencion.fecha",
+    "atencion.motivo",
+    "atencion.diagnosticos",
+    "atencion.indicaciones",
+    "documento.fecha",
+    "documento.tipo",
+];
+
+/// Claves `{{...}}` de la plantilla, en orden de aparición.
+fn claves(cuerpo: &str) -> Result<Vec<&str>, AppError> {
+    let mut claves = Vec::new();
+    let mut resto = cuerpo;
+    while let Some(inicio) = resto.find("{{") {
+        let tras = &resto[inicio + 2..];
+        let fin = tras.find("}}")
+            .ok_or_else(|| AppError::Validation("Plantilla con '{{' sin cerrar".into()))?;
+        claves.push(tras[..fin].trim());
+        resto = &tras[fin + 2..];
+    }
+    Ok(claves)
+}
+
+fn clave_de_campo(clave: &str) -> Option<&str> {
+    clave.strip_prefix("campos.")
+        .filter(|c| !c.is_empty() && c.chars().all(|ch| ch.is_ascii_lowercase() || ch.is_ascii_digit() || ch == '_'))
+}
+
+/// Una plantilla sólo puede usar claves conocidas o `campos.<nombre>`.
+pub fn validar_plantilla(cuerpo: &str) -> Result<(), AppError> {
+    let desconocidas: Vec<&str> = claves(cuerpo)?
+        .into_iter()
+        .filter(|c| !CLAVES.contains(c) && clave_de_campo(c).is_none())
+        .collect();
+    if !desconocidas.is_empty() {
+        return Err(AppError::Validation(format!("Claves desconocidas en la plantilla: {}", desconocidas.join(", "))));
+    }
+    Ok(())
+}
+
+/// Reemplaza cada `{{clave}}` por su dato. Informa todas las que falten de
+/// una vez, para que se puedan completar en un solo intento.
+pub fn fusionar(cuerpo: &str, datos: &BTreeMap<String, String>) -> Result<String, AppError> {
+    let faltantes: Vec<&str> = claves(cuerpo)?.into_iter().filter(|c| !datos.contains_key(*c)).collect();
+    if !faltantes.is_empty() {
+        return Err(AppError::Validation(format!("Faltan datos para el documento: {}", faltantes.join(", "))));
+    }
+
+    let mut texto = String::with_capacity(cuerpo.len());
+    let mut resto = cuerpo;
+    while let Some(inicio) = resto.find("{{") {
+        texto.push_str(&resto[..inicio]);
+        let tras = &resto[inicio + 2..];
+        let fin = tras.find("}}").unwrap_or(tras.len());
+        texto.push_str(&datos[tras[..fin].trim()]);
+        resto = tras.get(fin + 2..).unwrap_or_default();
+    }
+    texto.push_str(resto);
+    Ok(texto)
+}
+
+fn edad(nacimiento: NaiveDate, hoy: NaiveDate) -> i32 {
+    let mut anios = hoy.year() - nacimiento.year();
+    if (hoy.month(), hoy.day()) < (nacimiento.month(), nacimiento.day()) {
+        anios -= 1;
+    }
+    anios
+}
+
+/// Datos de la ficha para fusionar. Los de la atención sólo existen si el
+/// documento se asocia a una; los `campos` vacíos se consideran faltantes.
+pub fn datos(
+    tipo: &str,
+    paciente: &Paciente,
+    profesional: &Profesional,
+    atencion: Option<&AtencionDetalle>,
+    campos: &BTreeMap<String, String>,
+    tz: Tz,
+) -> Result<BTreeMap<String, String>, AppError> {
+    let hoy = tiempo::a_local(tiempo::ahora(), tz).date();
+    let mut datos = BTreeMap::new();
+    let mut poner = |clave: &str, valor: String| {
+        datos.insert(clave.to_string(), valor);
+    };
+
+    poner("paciente.nombre", paciente.nombre_completo());
+    poner("paciente.rut", paciente.rut.trim_start_matches('0').to_string());
+    poner("paciente.fecha_nacimiento", paciente.fecha_nacimiento.map(|f| f.format(FORMATO_FECHA).to_string()).unwrap_or_default());
+    poner("paciente.edad", paciente.fecha_nacimiento.map(|f| format!("{} años", edad(f, hoy))).unwrap_or_default());
+    poner("paciente.direccion", [paciente.direccion.as_deref(), paciente.comuna.as_deref()]
+        .into_iter()
+        .flatten()
+        .map(str::trim)
+        .collect::<Vec<_>>()
+        .join(", "));
+    poner("profesional.nombre", profesional.nombre_completo());
+    poner("profesional.rut", profesional.rut.trim_start_matches('0').to_string());
+    poner("profesional.especialidad", profesional.especialidad.clone().unwrap_or_default());
+    poner("profesional.rnpi", profesional.registro_rnpi.clone().unwrap_or_default());
+    poner("documento.fecha", hoy.format(FORMATO_FECHA).to_string());
+    poner("documento.tipo", tipo.to_string());
+
+    if let Some(a) = atencion {
+        poner("atencion.fecha", tiempo::a_local(a.atencion.creada_en, tz).format(FORMATO_FECHA).to_string());
+        poner("atencion.motivo", a.atencion.motivo.clone());
+        poner("atencion.diagnosticos", a.diagnosticos
+            .iter()
+            .map(|d| format!("{} {}", d.codigo, d.descripcion))
+            .collect::<Vec<_>>()
+            .join("\n"));
+        poner("atencion.indicaciones", a.atencion.indicaciones.clone().unwrap_or_default());
+    }
+
+    for (clave, valor) in campos {
+        if clave_de_campo(&format!("campos.{}", clave)).is_none() {
+            return Err(AppError::Validation(format!("Nombre de campo inválido: {}", clave)));
+        }
+        if !valor.trim().is_empty() {
+            poner(&format!("campos.{}", clave), valor.trim().to_string());
+        }
+    }
+    Ok(datos)
+}
+
+/// Contenido canónico de una versión para firmarla: identifica el documento
+/// además del texto, de modo que cambiar el folio o el paciente también
+/// invalida la firma.
+pub fn contenido_canonico(doc: &DocumentoClinico, version: &VersionDocumento) -> String {
+    [
+        format!("{:02}-{:06}", doc.cod_cliente, doc.folio),
+        doc.tipo.clone(),
+        doc.id_paciente.to_string(),
+        version.version.to_string(),
+        version.titulo.clone(),
+        version.contenido.clone(),
+    ]
+    .join("\n")
+}
+
+pub fn huella(contenido: &str) -> String {
+    hex::encode(Sha256::digest(contenido.as_bytes()))
+}
+
+/// PDF de una versión: `# ` y `## ` marcan títulos y una línea vacía separa
+/// párrafos. Al pie van el folio, la versión, la huella y, si la versión
+/// está firmada, la `leyenda` de la firma.
+pub fn documento(
+    doc: &DocumentoClinico,
+    version: &VersionDocumento,
+    profesional: &Profesional,
+    tz: Tz,
+    leyenda: Option<&str>,
+) -> Documento {
+    let mut pdf = Documento::new(&version.titulo);
+    for linea in version.contenido.lines() {
+        let linea = linea.trim_end();
+        if let Some(t) = linea.strip_prefix("## ") {
+            pdf.subtitulo(t);
+        } else if let Some(t) = linea.strip_prefix("# ") {
+            pdf.titulo(t);
+        } else if linea.trim().is_empty() {
+            pdf.espacio();
+        } else {
+            pdf.parrafo(linea);
+        }
+    }
+
+    if leyenda.is_some() {
+        let rnpi = profesional.registro_rnpi.as_deref().map(|r| format!(" (RNPI {})", r)).unwrap_or_default();
+        pdf.espacio()
+            .parrafo(format!("Firmado electrónicamente por {}{}.", profesional.nombre_completo(), rnpi));
+    }
+    pdf.pie(format!(
+        "{} folio N° {:02}-{:06} · versión {} del {}",
+        doc.tipo,
+        doc.cod_cliente,
+        doc.folio,
+        version.version,
+        tiempo::a_local(version.creada_en, tz).format("%d-%m-%Y %H:%M"),
+    ))
+    .pie(format!("Huella SHA-256 {}", version.huella));
+    if let Some(leyenda) = leyenda {
+        pdf.pie(leyenda);
+    }
+    pdf
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fusiona_y_reporta_faltantes() {
+        let datos = BTreeMap::from([
+            ("paciente.nombre".to_string(), "CLAUDIO SAEZ".to_string()),
+            ("campos.reposo".to_string(), "3".to_string()),
+        ]);
+        let texto = fusionar("{{ paciente.nombre }} requiere {{campos.reposo}} días.", &datos).unwrap();
+        assert_eq!(texto, "CLAUDIO SAEZ requiere 3 días.");
+
+        let error = fusionar("{{atencion.motivo}} {{campos.destino}}", &datos).unwrap_err();
+        assert!(error.to_string().contains("atencion.motivo, campos.destino"));
+        assert!(fusionar("{{paciente.nombre", &datos).is_err());
+    }
+
+    #[test]
+    fn valida_claves_de_plantilla() {
+        assert!(validar_plantilla("{{paciente.rut}} {{campos.especialidad_destino}}").is_ok());
+        assert!(validar_plantilla("{{paciente.prevision}}").is_err());
+        assert!(validar_plantilla("{{campos.}}").is_err());
+        assert_eq!(edad(NaiveDate::from_ymd_opt(1980, 6, 15).unwrap(), NaiveDate::from_ymd_opt(2025, 6, 14).unwrap()), 44);
+    }
+}
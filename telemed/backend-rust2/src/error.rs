@@ -11,6 +11,8 @@ pub enum AppError {
     Config(String),
     NotFound,
     Validation(String),
+    Forbidden(String),
+    Conflict(String),
     Internal(String),
 }
 
@@ -21,6 +23,8 @@ impl fmt::Display for AppError {
             Self::Config(e) => write!(f, "Configuration error: {}", e),
             Self::NotFound => write!(f, "Resource not found"),
             Self::Validation(e) => write!(f, "Validation error: {}", e),
+            Self::Forbidden(e) => write!(f, "Forbidden: {}", e),
+            Self::Conflict(e) => write!(f, "Conflict: {}", e),
             Self::Internal(e) => write!(f, "Internal error: {}", e),
         }
     }
@@ -43,6 +47,12 @@ impl ResponseError for AppError {
             Self::Validation(msg) => HttpResponse::BadRequest().json(
                 serde_json::json!({"error": msg})
             ),
+            Self::Forbidden(msg) => HttpResponse::Forbidden().json(
+                serde_json::json!({"error": msg})
+            ),
+            Self::Conflict(msg) => HttpResponse::Conflict().json(
+                serde_json::json!({"error": msg})
+            ),
             Self::Internal(_) => HttpResponse::InternalServerError().json(
                 serde_json::json!({"error": "Internal server error"})
             ),
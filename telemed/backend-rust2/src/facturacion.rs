@@ -0,0 +1,272 @@
+use std::collections::HashMap;
+
+use chrono::{Months, NaiveDate, NaiveDateTime};
+
+use crate::{
+    error::AppError,
+    models::{
+        Atencion, Cargo, LoteFacturacion, LoteInput, Paciente, Tarifa, CARGO_OBSERVADO, CARGO_PENDIENTE,
+        PAGADORES, PAGADOR_CLIENTE, PAGADOR_PREVISION,
+    },
+    texto::campo_csv,
+};
+
+/// Primer y último día de un período `AAAA-MM`.
+pub fn periodo(periodo: &str) -> Result<(NaiveDate, NaiveDate), AppError> {
+    let invalido = || AppError::Validation(format!("Período inválido: {}; se espera AAAA-MM", periodo));
+    let desde = NaiveDate::parse_from_str(&format!("{}-01", periodo.trim()), "%Y-%m-%d").map_err(|_| invalido())?;
+    let hasta = desde.checked_add_months(Months::new(1)).and_then(|d| d.pred_opt()).ok_or_else(invalido)?;
+    Ok((desde, hasta))
+}
+
+/// Normaliza el lote pedido y retorna su rango de fechas. El pagador
+/// `PREVISION` requiere `cod_prevision` y `CLIENTE` requiere `cod_cliente`.
+pub fn validar_lote(input: &mut LoteInput) -> Result<(NaiveDate, NaiveDate), AppError> {
+    input.pagador = input.pagador.trim().to_uppercase();
+    input.periodo = input.periodo.trim().to_string();
+    match (input.pagador.as_str(), input.cod_prevision, input.cod_cliente) {
+        (PAGADOR_PREVISION, Some(_), None) | (PAGADOR_CLIENTE, None, Some(_)) => periodo(&input.periodo),
+        (p, _, _) if !PAGADORES.contains(&p) => Err(AppError::Validation(format!("Pagador inválido: {}", p))),
+        _ => Err(AppError::Validation(format!(
+            "Un lote de {} se emite para {}",
+            input.pagador,
+            if input.pagador == PAGADOR_PREVISION { "una previsión (cod_prevision)" } else { "un cliente (cod_cliente)" }
+        ))),
+    }
+}
+
+/// Tarifa que corresponde a la atención: la del cliente del paciente si la
+/// hay, si no la de su previsión. Entre las vigentes prefiere la de la
+/// modalidad exacta sobre la genérica y, a igualdad, la más reciente.
+pub fn tarifa_aplicable<'a>(
+    tarifas: &'a [Tarifa],
+    paciente: &Paciente,
+    modalidad: Option<&str>,
+    fecha: NaiveDate,
+) -> Option<&'a Tarifa> {
+    let candidatas = |es_del_pagador: &dyn Fn(&Tarifa) -> bool| {
+        tarifas
+            .iter()
+            .filter(|t| t.vigente_en(fecha) && es_del_pagador(t))
+            .filter(|t| t.modalidad.is_none() || t.modalidad.as_deref() == modalidad)
+            .max_by_key(|t| (t.modalidad.is_some(), t.vigencia_desde, t.id))
+    };
+    paciente.cod_cliente
+        .and_then(|c| candidatas(&|t| t.cod_cliente == Some(c)))
+        .or_else(|| paciente.cod_prevision.and_then(|p| candidatas(&|t| t.cod_prevision == Some(p))))
+}
+
+/// Parte del valor que paga la previsión (redondeada al peso) y copago.
+pub fn repartir(valor: u32, cobertura: u8) -> (u32, u32) {
+    let pagador = ((u64::from(valor) * u64::from(cobertura.min(100)) + 50) / 100) as u32;
+    (pagador, valor - pagador)
+}
+
+/// Valoriza una atención firmada. Si no se puede (sin previsión ni
+/// cliente, o sin tarifa vigente) el cargo queda `OBSERVADO` en cero con
+/// el motivo, para corregirlo y recalcular.
+pub fn valorizar(
+    atencion: &Atencion,
+    paciente: &Paciente,
+    modalidad: Option<&str>,
+    tarifas: &[Tarifa],
+    fecha: NaiveDate,
+    ahora: NaiveDateTime,
+) -> Cargo {
+    let mut cargo = Cargo {
+        id: 0,
+        id_atencion: atencion.id,
+        id_paciente: paciente.id,
+        fecha,
+        codigo_prestacion: None,
+        pagador: None,
+        cod_prevision: None,
+        cod_cliente: None,
+        valor: 0,
+        monto_pagador: 0,
+        copago: 0,
+        estado: CARGO_OBSERVADO.into(),
+        observacion: None,
+        id_lote: None,
+        generado_en: ahora,
+    };
+
+    if paciente.cod_cliente.is_none() && paciente.cod_prevision.is_none() {
+        cargo.observacion = Some("El paciente no tiene previsión ni cliente".into());
+        return cargo;
+    }
+    let Some(tarifa) = tarifa_aplicable(tarifas, paciente, modalidad, fecha) else {
+        cargo.observacion = Some(format!(
+            "No hay tarifa vigente al {} para la modalidad {}",
+            fecha,
+            modalidad.unwrap_or("sin cita")
+        ));
+        return cargo;
+    };
+
+    let (monto_pagador, copago) = repartir(tarifa.valor, tarifa.cobertura);
+    Cargo {
+        codigo_prestacion: Some(tarifa.codigo_prestacion.clone()),
+        pagador: Some(if tarifa.cod_cliente.is_some() { PAGADOR_CLIENTE } else { PAGADOR_PREVISION }.into()),
+        cod_prevision: tarifa.cod_prevision,
+        cod_cliente: tarifa.cod_cliente,
+        valor: tarifa.valor,
+        monto_pagador,
+        copago,
+        estado: CARGO_PENDIENTE.into(),
+        ..cargo
+    }
+}
+
+/// Lote con los totales de los cargos que agrupa.
+pub fn lote(input: &LoteInput, cargos: &[Cargo], ahora: NaiveDateTime) -> LoteFacturacion {
+    LoteFacturacion {
+        id: 0,
+        periodo: input.periodo.clone(),
+        pagador: input.pagador.clone(),
+        cod_prevision: input.cod_prevision,
+        cod_cliente: input.cod_cliente,
+        cantidad: cargos.len() as u32,
+        total_valor: cargos.iter().map(|c| u64::from(c.valor)).sum(),
+        total_pagador: cargos.iter().map(|c| u64::from(c.monto_pagador)).sum(),
+        total_copago: cargos.iter().map(|c| u64::from(c.copago)).sum(),
+        creado_en: ahora,
+    }
+}
+
+/// Archivo de conciliación del lote: CSV separado por `;` (como lo abre
+/// Excel en es-CL), una línea por cargo y una final con los totales. Los
+/// textos van entre comillas cuando hace falta.
+pub fn exportar(lote: &LoteFacturacion, cargos: &[Cargo], ruts: &HashMap<u32, String>) -> String {
+    let codigo_pagador = lote.cod_prevision.or(lote.cod_cliente.map(u32::from)).unwrap_or_default();
+    let mut csv = String::from("lote;periodo;pagador;codigo_pagador;cargo;atencion;fecha;rut_paciente;prestacion;valor;monto_pagador;copago\r\n");
+    for c in cargos {
+        csv.push_str(&format!(
+            "{};{};{};{};{};{};{};{};{};{};{};{}\r\n",
+            lote.id,
+            campo_csv(&lote.periodo),
+            campo_csv(&lote.pagador),
+            codigo_pagador,
+            c.id,
+            c.id_atencion,
+            c.fecha,
+            campo_csv(ruts.get(&c.id_paciente).map_or("", String::as_str)),
+            campo_csv(c.codigo_prestacion.as_deref().unwrap_or_default()),
+            c.valor,
+            c.monto_pagador,
+            c.copago,
+        ));
+    }
+    csv.push_str(&format!(
+        "{};{};{};{};TOTAL;{};;;;{};{};{}\r\n",
+        lote.id, campo_csv(&lote.periodo), campo_csv(&lote.pagador), codigo_pagador, lote.cantidad, lote.total_valor, lote.total_pagador, lote.total_copago,
+    ));
+    csv
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{models::{ATENCION_FIRMADA, MODALIDAD_TELECONSULTA}, repositories::fixtures};
+    use super::*;
+
+    fn fecha(d: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(2025, 3, d).unwrap()
+    }
+
+    fn tarifa(id: u32, modalidad: Option<&str>, cod_prevision: Option<u32>, cod_cliente: Option<u8>, valor: u32, cobertura: u8) -> Tarifa {
+        Tarifa {
+            id,
+            codigo_prestacion: format!("P{}", id),
+            descripcion: "Consulta médica".into(),
+            modalidad: modalidad.map(Into::into),
+            cod_prevision,
+            cod_cliente,
+            valor,
+            cobertura,
+            vigencia_desde: fecha(1),
+            vigencia_hasta: None,
+        }
+    }
+
+    fn paciente(cod_prevision: Option<u32>, cod_cliente: Option<u8>) -> Paciente {
+        Paciente { id: 7, cod_prevision, cod_cliente, ..fixtures::paciente("0010895960-6") }
+    }
+
+    fn atencion() -> Atencion {
+        Atencion {
+            id: 3,
+            id_paciente: 7,
+            id_prof: 1,
+            id_cita: None,
+            motivo: "Control".into(),
+            anamnesis: None,
+            examen_fisico: None,
+            indicaciones: None,
+            estado: ATENCION_FIRMADA.into(),
+            creada_en: NaiveDateTime::default(),
+            firmada_en: None,
+        }
+    }
+
+    #[test]
+    fn elige_tarifa_y_calcula_copago() {
+        let tarifas = vec![
+            tarifa(1, None, Some(10), None, 20_000, 70),
+            tarifa(2, Some(MODALIDAD_TELECONSULTA), Some(10), None, 15_001, 50),
+            tarifa(3, Some("DOMICILIO"), Some(10), None, 30_000, 70),
+            tarifa(4, None, None, Some(2), 12_000, 100),
+        ];
+        let ahora = NaiveDateTime::default();
+
+        let cargo = valorizar(&atencion(), &paciente(Some(10), None), Some(MODALIDAD_TELECONSULTA), &tarifas, fecha(5), ahora);
+        assert_eq!(cargo.codigo_prestacion.as_deref(), Some("P2"));
+        assert_eq!((cargo.valor, cargo.monto_pagador, cargo.copago), (15_001, 7_501, 7_500));
+        assert_eq!(cargo.estado, CARGO_PENDIENTE);
+
+        // Sin cita aplica la tarifa genérica
+        let cargo = valorizar(&atencion(), &paciente(Some(10), None), None, &tarifas, fecha(5), ahora);
+        assert_eq!((cargo.codigo_prestacion.as_deref(), cargo.copago), (Some("P1"), 6_000));
+
+        // El programa del cliente tiene prioridad y no genera copago
+        let cargo = valorizar(&atencion(), &paciente(Some(10), Some(2)), None, &tarifas, fecha(5), ahora);
+        assert_eq!((cargo.pagador.as_deref(), cargo.cod_cliente, cargo.copago), (Some(PAGADOR_CLIENTE), Some(2), 0));
+
+        let cargo = valorizar(&atencion(), &paciente(Some(11), None), None, &tarifas, fecha(5), ahora);
+        assert_eq!((cargo.estado.as_str(), cargo.valor), (CARGO_OBSERVADO, 0));
+        let cargo = valorizar(&atencion(), &paciente(Some(10), None), None, &tarifas, NaiveDate::from_ymd_opt(2025, 2, 28).unwrap(), ahora);
+        assert!(cargo.observacion.unwrap().contains("No hay tarifa"));
+        let cargo = valorizar(&atencion(), &paciente(None, None), None, &tarifas, fecha(5), ahora);
+        assert!(cargo.observacion.unwrap().contains("ni cliente"));
+    }
+
+    #[test]
+    fn lote_por_periodo_y_exportacion() {
+        assert_eq!(periodo("2024-02").unwrap(), (NaiveDate::from_ymd_opt(2024, 2, 1).unwrap(), NaiveDate::from_ymd_opt(2024, 2, 29).unwrap()));
+        assert!(periodo("2024-13").is_err());
+
+        let mut input = LoteInput { periodo: "2025-03".into(), pagador: "cliente".into(), cod_prevision: None, cod_cliente: None };
+        assert!(validar_lote(&mut input).is_err());
+        input.cod_cliente = Some(2);
+        assert_eq!(validar_lote(&mut input).unwrap().1, fecha(31));
+
+        let tarifas = vec![tarifa(4, None, None, Some(2), 12_000, 100)];
+        let cargos: Vec<Cargo> = (0..2)
+            .map(|i| Cargo { id: i + 1, ..valorizar(&atencion(), &paciente(None, Some(2)), None, &tarifas, fecha(5), NaiveDateTime::default()) })
+            .collect();
+        let mut lote = lote(&input, &cargos, NaiveDateTime::default());
+        lote.id = 9;
+        assert_eq!((lote.cantidad, lote.total_valor, lote.total_copago), (2, 24_000, 0));
+
+        let ruts = HashMap::from([(7, "0010895960-6".to_string())]);
+        let csv = exportar(&lote, &cargos, &ruts);
+        let lineas: Vec<&str> = csv.lines().collect();
+        assert_eq!(lineas.len(), 4);
+        assert_eq!(lineas[1], "9;2025-03;CLIENTE;2;1;3;2025-03-05;0010895960-6;P4;12000;12000;0");
+        assert_eq!(lineas[3], "9;2025-03;CLIENTE;2;TOTAL;2;;;;24000;24000;0");
+
+        // Un texto con el separador o comillas no corre las columnas
+        let raro = Cargo { codigo_prestacion: Some("P4;\"bis\"".into()), ..cargos[0].clone() };
+        let csv = exportar(&lote, &[raro], &ruts);
+        assert_eq!(csv.lines().nth(1), Some("9;2025-03;CLIENTE;2;1;3;2025-03-05;0010895960-6;\"P4;\"\"bis\"\"\";12000;12000;0"));
+    }
+}
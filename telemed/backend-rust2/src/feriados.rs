@@ -0,0 +1,174 @@
+use std::collections::HashSet;
+use chrono::{Datelike, Duration, NaiveDate, Weekday};
+
+use crate::models::Feriado;
+
+/// Día Nacional de los Pueblos Indígenas: se fija cada año en el solsticio de
+/// invierno según el decreto respectivo (Ley 21.357).
+const SOLSTICIOS: [(i32, u32); 10] = [
+    (2021, 21), (2022, 21), (2023, 21), (2024, 20), (2025, 20),
+    (2026, 21), (2027, 21), (2028, 20), (2029, 20), (2030, 21),
+];
+
+/// Feriados regionales por ley, asociados a las zonas de acceso que cubren.
+const REGIONALES: [(u32, u32, &str, &str); 2] = [
+    (6, 7, "Asalto y Toma del Morro de Arica", "004"),
+    (8, 20, "Nacimiento del Prócer de la Independencia", "005"),
+];
+
+fn fecha(anio: i32, mes: u32, dia: u32) -> NaiveDate {
+    NaiveDate::from_ymd_opt(anio, mes, dia).expect("fecha de feriado válida")
+}
+
+/// Domingo de Pascua (algoritmo anónimo gregoriano).
+fn pascua(anio: i32) -> NaiveDate {
+    let a = anio % 19;
+    let b = anio / 100;
+    let c = anio % 100;
+    let d = b / 4;
+    let e = b % 4;
+    let f = (b + 8) / 25;
+    let g = (b - f + 1) / 3;
+    let h = (19 * a + b - d - g + 15) % 30;
+    let i = c / 4;
+    let k = c % 4;
+    let l = (32 + 2 * e + 2 * i - h - k) % 7;
+    let m = (a + 11 * h + 22 * l) / 451;
+    let mes = (h + l - 7 * m + 114) / 31;
+    let dia = (h + l - 7 * m + 114) % 31 + 1;
+    fecha(anio, mes as u32, dia as u32)
+}
+
+/// Ley 19.668: si cae martes, miércoles o jueves se traslada al lunes
+/// anterior; si cae viernes, al lunes siguiente.
+fn trasladar_a_lunes(dia: NaiveDate) -> NaiveDate {
+    match dia.weekday() {
+        Weekday::Tue | Weekday::Wed | Weekday::Thu => {
+            dia - Duration::days(i64::from(dia.weekday().num_days_from_monday()))
+        }
+        Weekday::Fri => dia + Duration::days(3),
+        _ => dia,
+    }
+}
+
+/// Ley 20.299: si el 31 de octubre cae martes se traslada al viernes anterior;
+/// si cae miércoles, al viernes siguiente.
+fn iglesias_evangelicas(anio: i32) -> NaiveDate {
+    let dia = fecha(anio, 10, 31);
+    match dia.weekday() {
+        Weekday::Tue => dia - Duration::days(4),
+        Weekday::Wed => dia + Duration::days(2),
+        _ => dia,
+    }
+}
+
+/// Feriados nacionales definidos por ley para `anio`.
+pub fn nacionales(anio: i32) -> Vec<Feriado> {
+    let viernes_santo = pascua(anio) - Duration::days(2);
+    let mut dias = vec![
+        (fecha(anio, 1, 1), "Año Nuevo"),
+        (viernes_santo, "Viernes Santo"),
+        (viernes_santo + Duration::days(1), "Sábado Santo"),
+        (fecha(anio, 5, 1), "Día Nacional del Trabajo"),
+        (fecha(anio, 5, 21), "Día de las Glorias Navales"),
+        (trasladar_a_lunes(fecha(anio, 6, 29)), "San Pedro y San Pablo"),
+        (fecha(anio, 7, 16), "Día de la Virgen del Carmen"),
+        (fecha(anio, 8, 15), "Asunción de la Virgen"),
+        (fecha(anio, 9, 18), "Independencia Nacional"),
+        (fecha(anio, 9, 19), "Día de las Glorias del Ejército"),
+        (trasladar_a_lunes(fecha(anio, 10, 12)), "Encuentro de Dos Mundos"),
+        (iglesias_evangelicas(anio), "Día de las Iglesias Evangélicas y Protestantes"),
+        (fecha(anio, 11, 1), "Día de Todos los Santos"),
+        (fecha(anio, 12, 8), "Inmaculada Concepción"),
+        (fecha(anio, 12, 25), "Navidad"),
+    ];
+
+    if let Some((_, dia)) = SOLSTICIOS.iter().find(|(a, _)| *a == anio) {
+        dias.push((fecha(anio, 6, *dia), "Día Nacional de los Pueblos Indígenas"));
+    }
+    if fecha(anio, 9, 17).weekday() == Weekday::Mon {
+        dias.push((fecha(anio, 9, 17), "Fiestas Patrias"));
+    }
+    if fecha(anio, 9, 20).weekday() == Weekday::Fri {
+        dias.push((fecha(anio, 9, 20), "Fiestas Patrias"));
+    }
+
+    dias.sort_by_key(|(f, _)| *f);
+    dias.into_iter()
+        .map(|(fecha, nombre)| Feriado { id: 0, fecha, nombre: nombre.into(), cod_zona: None })
+        .collect()
+}
+
+/// Feriados regionales definidos por ley para `anio`.
+pub fn regionales(anio: i32) -> Vec<Feriado> {
+    REGIONALES
+        .iter()
+        .map(|(mes, dia, nombre, zona)| Feriado {
+            id: 0,
+            fecha: fecha(anio, *mes, *dia),
+            nombre: (*nombre).into(),
+            cod_zona: Some((*zona).into()),
+        })
+        .collect()
+}
+
+/// Feriados legales y administrados que aplican en `cod_zona` (o sólo los
+/// nacionales si no se indica zona), ordenados por fecha.
+pub fn del_anio(anio: i32, cod_zona: Option<&str>, extras: &[Feriado]) -> Vec<Feriado> {
+    let mut feriados: Vec<Feriado> = nacionales(anio)
+        .into_iter()
+        .chain(regionales(anio))
+        .chain(extras.iter().filter(|f| f.fecha.year() == anio).cloned())
+        .filter(|f| f.cod_zona.is_none() || f.cod_zona.as_deref() == cod_zona)
+        .collect();
+    feriados.sort_by_key(|f| f.fecha);
+    feriados
+}
+
+/// Calendario precalculado para consultar rápidamente durante la generación
+/// de horarios.
+pub struct Calendario {
+    dias: HashSet<(NaiveDate, Option<String>)>,
+}
+
+impl Calendario {
+    pub fn new(desde: NaiveDate, hasta: NaiveDate, extras: &[Feriado]) -> Self {
+        let dias = (desde.year()..=hasta.year())
+            .flat_map(|anio| nacionales(anio).into_iter().chain(regionales(anio)))
+            .chain(extras.iter().cloned())
+            .map(|f| (f.fecha, f.cod_zona))
+            .collect();
+        Self { dias }
+    }
+
+    pub fn es_feriado(&self, fecha: NaiveDate, cod_zona: Option<&str>) -> bool {
+        self.dias.contains(&(fecha, None))
+            || cod_zona.is_some_and(|z| self.dias.contains(&(fecha, Some(z.to_string()))))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn calcula_semana_santa_y_traslados() {
+        let f2025: Vec<NaiveDate> = nacionales(2025).into_iter().map(|f| f.fecha).collect();
+        assert!(f2025.contains(&fecha(2025, 4, 18)));
+        assert!(f2025.contains(&fecha(2025, 6, 20)));
+
+        let f2027: Vec<NaiveDate> = nacionales(2027).into_iter().map(|f| f.fecha).collect();
+        assert!(f2027.contains(&fecha(2027, 3, 26)));
+        // 29 de junio de 2027 es martes: se traslada al lunes 28
+        assert!(f2027.contains(&fecha(2027, 6, 28)));
+        assert!(!f2027.contains(&fecha(2027, 6, 29)));
+    }
+
+    #[test]
+    fn feriado_regional_solo_aplica_en_su_zona() {
+        let calendario = Calendario::new(fecha(2025, 1, 1), fecha(2025, 12, 31), &[]);
+        assert!(calendario.es_feriado(fecha(2025, 6, 7), Some("004")));
+        assert!(!calendario.es_feriado(fecha(2025, 6, 7), Some("079")));
+        assert!(calendario.es_feriado(fecha(2025, 9, 18), Some("079")));
+    }
+}
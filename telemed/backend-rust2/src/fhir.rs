@@ -0,0 +1,441 @@
+use chrono::{DateTime, Duration, NaiveDate, NaiveDateTime, Timelike};
+use serde_json::{json, Value};
+
+use crate::{
+    error::AppError,
+    models::{AtencionDetalle, Cita, ConsultaFhir, Paciente, Profesional, Zona, MODALIDAD_TELECONSULTA},
+    rut,
+};
+
+pub const CONTENT_TYPE: &str = "application/fhir+json";
+
+/// Sistemas de los identificadores que publicamos.
+pub const SISTEMA_RUN: &str = "http://regcivil.cl/Validacion/RUN";
+pub const SISTEMA_RNPI: &str = "https://rnpi.superdesalud.gob.cl";
+pub const SISTEMA_ZONA: &str = "urn:telemedicina:zona";
+pub const SISTEMA_ATENCION: &str = "urn:telemedicina:atencion";
+pub const SISTEMA_CIE10: &str = "http://hl7.org/fhir/sid/icd-10";
+const SISTEMA_ACT_CODE: &str = "http://terminology.hl7.org/CodeSystem/v3-ActCode";
+
+/// Tamaño de página por defecto y máximo (`_count`).
+pub const PAGINA: u32 = 20;
+pub const MAX_PAGINA: u32 = 100;
+
+/// Recursos expuestos y la tabla de la que salen.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Recurso {
+    Paciente,
+    Profesional,
+    Encuentro,
+    Organizacion,
+}
+
+impl Recurso {
+    pub fn nombre(self) -> &'static str {
+        match self {
+            Recurso::Paciente => "Patient",
+            Recurso::Profesional => "Practitioner",
+            Recurso::Encuentro => "Encounter",
+            Recurso::Organizacion => "Organization",
+        }
+    }
+}
+
+fn invalido(parametro: &str, valor: &str) -> AppError {
+    AppError::Validation(format!("Valor inválido para {}: {}", parametro, valor))
+}
+
+/// Restringe la consulta a `ids`; varios `_id` se combinan con AND, así que
+/// se intersectan.
+fn restringir_ids(consulta: &mut ConsultaFhir, ids: Vec<String>) {
+    consulta.ids = Some(match consulta.ids.take() {
+        Some(previos) => previos.into_iter().filter(|id| ids.contains(id)).collect(),
+        None => ids,
+    });
+}
+
+/// Fija un criterio de igualdad; si ya tenía otro valor nada coincide.
+fn fijar(campo: &mut Option<String>, valor: String, consulta_vacia: &mut bool) {
+    match campo {
+        Some(previo) if *previo != valor => *consulta_vacia = true,
+        _ => *campo = Some(valor),
+    }
+}
+
+/// Instante y precisión de un `_lastUpdated` (fecha o fecha-hora). Las
+/// fechas se toman en UTC, igual que `fecha_actualizacion`.
+fn instante(valor: &str) -> Option<(NaiveDateTime, Duration)> {
+    if let Ok(fecha) = NaiveDate::parse_from_str(valor, "%Y-%m-%d") {
+        return Some((fecha.and_hms_opt(0, 0, 0)?, Duration::days(1)));
+    }
+    let fecha_hora = DateTime::parse_from_rfc3339(valor)
+        .map(|f| f.naive_utc())
+        .or_else(|_| NaiveDateTime::parse_from_str(valor, "%Y-%m-%dT%H:%M:%S"))
+        .ok()?;
+    Some((fecha_hora.with_nanosecond(0)?, Duration::seconds(1)))
+}
+
+/// Convierte `_lastUpdated=[prefijo]fecha` en el rango `[desde, hasta)` y lo
+/// intersecta con el que ya tenga la consulta.
+fn actualizado(consulta: &mut ConsultaFhir, valor: &str) -> Result<(), AppError> {
+    let (prefijo, resto) = match valor.get(..2) {
+        Some(p) if p.chars().all(|c| c.is_ascii_alphabetic()) => (p, &valor[2..]),
+        _ => ("eq", valor),
+    };
+    let (t, unidad) = instante(resto).ok_or_else(|| invalido("_lastUpdated", valor))?;
+    let (desde, hasta) = match prefijo {
+        "eq" => (Some(t), Some(t + unidad)),
+        "gt" => (Some(t + unidad), None),
+        "ge" => (Some(t), None),
+        "lt" => (None, Some(t)),
+        "le" => (None, Some(t + unidad)),
+        _ => return Err(AppError::Validation(format!("Prefijo no soportado en _lastUpdated: {}", prefijo))),
+    };
+    if let Some(desde) = desde {
+        consulta.actualizado_desde = Some(consulta.actualizado_desde.map_or(desde, |d| d.max(desde)));
+    }
+    if let Some(hasta) = hasta {
+        consulta.actualizado_hasta = Some(consulta.actualizado_hasta.map_or(hasta, |h| h.min(hasta)));
+    }
+    Ok(())
+}
+
+/// Aplica `identifier=[sistema|]valor`. Un RUT inválido o un sistema que el
+/// recurso no usa no da error: simplemente nada coincide.
+fn identificador(recurso: Recurso, consulta: &mut ConsultaFhir, valor: &str, vacia: &mut bool) -> Result<(), AppError> {
+    if valor.contains(',') {
+        return Err(AppError::Validation("identifier admite un solo valor".into()));
+    }
+    let (sistema, valor) = match valor.split_once('|') {
+        Some((s, v)) => (Some(s), v),
+        None => (None, valor),
+    };
+    match (recurso, sistema) {
+        (Recurso::Paciente | Recurso::Profesional, Some(SISTEMA_RUN)) => match rut::normalizar(valor) {
+            Ok(r) => fijar(&mut consulta.rut, r, vacia),
+            Err(_) => *vacia = true,
+        },
+        (Recurso::Paciente, None) => match rut::normalizar(valor) {
+            Ok(r) => fijar(&mut consulta.rut, r, vacia),
+            Err(_) => *vacia = true,
+        },
+        (Recurso::Profesional, Some(SISTEMA_RNPI)) => fijar(&mut consulta.rnpi, valor.to_string(), vacia),
+        // Sin sistema, un valor con guion es RUT y uno sólo numérico es RNPI
+        (Recurso::Profesional, None) => match valor.contains('-').then(|| rut::normalizar(valor)) {
+            Some(Ok(r)) => fijar(&mut consulta.rut, r, vacia),
+            Some(Err(_)) => *vacia = true,
+            None => fijar(&mut consulta.rnpi, valor.to_string(), vacia),
+        },
+        (Recurso::Encuentro, Some(SISTEMA_ATENCION) | None) | (Recurso::Organizacion, Some(SISTEMA_ZONA) | None) => {
+            restringir_ids(consulta, vec![valor.to_string()])
+        }
+        _ => *vacia = true,
+    }
+    Ok(())
+}
+
+/// Interpreta los parámetros de búsqueda. Se soportan `_id` (valores
+/// separados por coma), `identifier`, `_lastUpdated`, `_count` y `_offset`;
+/// los demás se ignoran, como permite FHIR. Si los criterios no pueden
+/// coincidir con nada la consulta queda con `ids` vacío.
+pub fn interpretar(recurso: Recurso, params: &[(String, String)]) -> Result<ConsultaFhir, AppError> {
+    let mut consulta = ConsultaFhir { cantidad: PAGINA, ..Default::default() };
+    let mut vacia = false;
+    for (nombre, valor) in params {
+        match nombre.as_str() {
+            "_id" => restringir_ids(&mut consulta, valor.split(',').map(|v| v.trim().to_string()).collect()),
+            "identifier" => identificador(recurso, &mut consulta, valor, &mut vacia)?,
+            "_lastUpdated" => actualizado(&mut consulta, valor)?,
+            "_count" => {
+                consulta.cantidad = valor.parse::<u32>().map_err(|_| invalido(nombre, valor))?.min(MAX_PAGINA)
+            }
+            "_offset" => consulta.salto = valor.parse().map_err(|_| invalido(nombre, valor))?,
+            _ => {}
+        }
+    }
+    if vacia {
+        consulta.ids = Some(Vec::new());
+    }
+    Ok(consulta)
+}
+
+fn instante_fhir(fecha: NaiveDateTime) -> String {
+    fecha.format("%Y-%m-%dT%H:%M:%SZ").to_string()
+}
+
+fn meta(actualizado: NaiveDateTime) -> Value {
+    json!({ "lastUpdated": instante_fhir(actualizado) })
+}
+
+/// RUN como lo publica el Registro Civil: sin puntos ni ceros a la izquierda.
+fn run(rut: &str) -> String {
+    rut.replace('.', "").trim_start_matches('0').to_string()
+}
+
+fn nombre_humano(nombres: &str, ap_paterno: &str, ap_materno: Option<&str>) -> Value {
+    let familia = match ap_materno.map(str::trim).filter(|m| !m.is_empty()) {
+        Some(materno) => format!("{} {}", ap_paterno, materno),
+        None => ap_paterno.to_string(),
+    };
+    json!([{ "use": "official", "family": familia, "given": nombres.split_whitespace().collect::<Vec<_>>() }])
+}
+
+fn contactos(telefonos: Option<&str>, email: Option<&str>) -> Vec<Value> {
+    let mut contactos: Vec<Value> = telefonos
+        .unwrap_or_default()
+        .split([',', ';', '/'])
+        .map(str::trim)
+        .filter(|t| !t.is_empty())
+        .map(|t| json!({ "system": "phone", "value": t }))
+        .collect();
+    if let Some(email) = email.map(str::trim).filter(|e| !e.is_empty()) {
+        contactos.push(json!({ "system": "email", "value": email }));
+    }
+    contactos
+}
+
+fn direccion(linea: Option<&str>, comuna: Option<&str>, ciudad: Option<&str>) -> Option<Value> {
+    let no_vacio = |v: Option<&str>| v.map(str::trim).filter(|v| !v.is_empty()).map(str::to_string);
+    let (linea, comuna, ciudad) = (no_vacio(linea), no_vacio(comuna), no_vacio(ciudad));
+    if linea.is_none() && comuna.is_none() && ciudad.is_none() {
+        return None;
+    }
+    let mut direccion = json!({ "use": "home", "country": "CL" });
+    if let Some(linea) = linea {
+        direccion["line"] = json!([linea]);
+    }
+    if let Some(comuna) = comuna {
+        direccion["district"] = json!(comuna);
+    }
+    if let Some(ciudad) = ciudad {
+        direccion["city"] = json!(ciudad);
+    }
+    Some(direccion)
+}
+
+/// Asigna `clave` sólo si el valor no está vacío, para no publicar campos nulos.
+fn agregar(recurso: &mut Value, clave: &str, valor: Value) {
+    let vacio = valor.is_null() || valor.as_array().is_some_and(|a| a.is_empty());
+    if !vacio {
+        recurso[clave] = valor;
+    }
+}
+
+pub fn paciente(p: &Paciente, actualizado: NaiveDateTime) -> Value {
+    let genero = match p.sexo.as_deref().map(|s| s.trim().to_uppercase()).as_deref() {
+        Some("F") => "female",
+        Some("M") => "male",
+        Some("O") => "other",
+        _ => "unknown",
+    };
+    let mut recurso = json!({
+        "resourceType": "Patient",
+        "id": p.id.to_string(),
+        "meta": meta(actualizado),
+        "identifier": [{ "use": "official", "system": SISTEMA_RUN, "value": run(&p.rut) }],
+        "active": p.estatus == 1,
+        "name": nombre_humano(&p.nombres, &p.ap_paterno, p.ap_materno.as_deref()),
+        "gender": genero,
+    });
+    agregar(&mut recurso, "telecom", json!(contactos(p.telefonos.as_deref(), p.email.as_deref())));
+    agregar(&mut recurso, "birthDate", json!(p.fecha_nacimiento.map(|f| f.format("%Y-%m-%d").to_string())));
+    agregar(&mut recurso, "address", json!(direccion(p.direccion.as_deref(), p.comuna.as_deref(), p.ciudad.as_deref()).map(|d| vec![d])));
+    agregar(
+        &mut recurso,
+        "managingOrganization",
+        json!(p.cod_zona.as_ref().map(|z| json!({ "reference": format!("Organization/{}", z) }))),
+    );
+    recurso
+}
+
+pub fn profesional(p: &Profesional, actualizado: NaiveDateTime) -> Value {
+    let mut identificadores = vec![json!({ "use": "official", "system": SISTEMA_RUN, "value": run(&p.rut) })];
+    if let Some(rnpi) = p.registro_rnpi.as_deref().map(str::trim).filter(|r| !r.is_empty()) {
+        identificadores.push(json!({ "system": SISTEMA_RNPI, "value": rnpi }));
+    }
+    let mut recurso = json!({
+        "resourceType": "Practitioner",
+        "id": p.id_prof.to_string(),
+        "meta": meta(actualizado),
+        "identifier": identificadores,
+        "active": p.estado.as_deref() == Some("VIGENTE") && p.fecha_egreso.is_none(),
+        "name": nombre_humano(&p.nombres, &p.ap_paterno, p.ap_materno.as_deref()),
+    });
+    agregar(&mut recurso, "telecom", json!(contactos(p.telefonos.as_deref(), p.email.as_deref())));
+    agregar(&mut recurso, "address", json!(direccion(p.direccion.as_deref(), p.comuna.as_deref(), p.ciudad.as_deref()).map(|d| vec![d])));
+    agregar(
+        &mut recurso,
+        "qualification",
+        json!(p.especialidad.as_deref().map(str::trim).filter(|e| !e.is_empty()).map(|e| vec![json!({ "code": { "text": e } })])),
+    );
+    recurso
+}
+
+/// `cita` es la cita de la que salió la atención, si la hubo; da la
+/// modalidad y la zona que presta el servicio.
+pub fn encuentro(d: &AtencionDetalle, cita: Option<&Cita>, actualizado: NaiveDateTime) -> Value {
+    let a = &d.atencion;
+    let (clase, nombre_clase) = match cita.map(|c| c.modalidad.as_str()) {
+        Some(MODALIDAD_TELECONSULTA) => ("VR", "virtual"),
+        Some("DOMICILIO") => ("HH", "home health"),
+        _ => ("AMB", "ambulatory"),
+    };
+    let mut periodo = json!({ "start": instante_fhir(a.creada_en) });
+    if let Some(firmada) = a.firmada_en {
+        periodo["end"] = json!(instante_fhir(firmada));
+    }
+    let mut motivos = vec![json!({ "text": a.motivo })];
+    motivos.extend(d.diagnosticos.iter().map(|diag| {
+        json!({ "coding": [{ "system": SISTEMA_CIE10, "code": diag.codigo, "display": diag.descripcion }] })
+    }));
+    let mut recurso = json!({
+        "resourceType": "Encounter",
+        "id": a.id.to_string(),
+        "meta": meta(actualizado),
+        "status": if a.firmada() { "finished" } else { "in-progress" },
+        "class": { "system": SISTEMA_ACT_CODE, "code": clase, "display": nombre_clase },
+        "subject": { "reference": format!("Patient/{}", a.id_paciente) },
+        "participant": [{ "individual": { "reference": format!("Practitioner/{}", a.id_prof) } }],
+        "period": periodo,
+        "reasonCode": motivos,
+    });
+    agregar(
+        &mut recurso,
+        "serviceProvider",
+        json!(cita.and_then(|c| c.cod_zona.as_ref()).map(|z| json!({ "reference": format!("Organization/{}", z) }))),
+    );
+    recurso
+}
+
+pub fn organizacion(z: &Zona, actualizado: NaiveDateTime) -> Value {
+    json!({
+        "resourceType": "Organization",
+        "id": z.cod_zona,
+        "meta": meta(actualizado),
+        "identifier": [{ "system": SISTEMA_ZONA, "value": z.cod_zona }],
+        "active": true,
+        "name": z.nom_zona,
+    })
+}
+
+fn enlace(base: &str, recurso: Recurso, params: &[(String, String)], salto: u32, cantidad: u32) -> Result<String, AppError> {
+    let mut pares: Vec<(String, String)> = params
+        .iter()
+        .filter(|(n, _)| n != "_count" && n != "_offset")
+        .cloned()
+        .collect();
+    pares.push(("_count".into(), cantidad.to_string()));
+    pares.push(("_offset".into(), salto.to_string()));
+    reqwest::Url::parse_with_params(&format!("{}/{}", base, recurso.nombre()), &pares)
+        .map(String::from)
+        .map_err(|e| AppError::Internal(format!("URL FHIR inválida: {}", e)))
+}
+
+/// Bundle `searchset` con los enlaces de paginación `self`, `next` y
+/// `previous`. `base` es la URL de `/fhir` tal como la ve el cliente.
+pub fn bundle(
+    base: &str,
+    recurso: Recurso,
+    params: &[(String, String)],
+    consulta: &ConsultaFhir,
+    total: u32,
+    recursos: Vec<Value>,
+) -> Result<Value, AppError> {
+    let (salto, cantidad) = (consulta.salto, consulta.cantidad);
+    let mut enlaces = vec![json!({ "relation": "self", "url": enlace(base, recurso, params, salto, cantidad)? })];
+    if cantidad > 0 && salto.saturating_add(cantidad) < total {
+        enlaces.push(json!({ "relation": "next", "url": enlace(base, recurso, params, salto + cantidad, cantidad)? }));
+    }
+    if salto > 0 {
+        enlaces.push(json!({ "relation": "previous", "url": enlace(base, recurso, params, salto.saturating_sub(cantidad), cantidad)? }));
+    }
+    let entradas: Vec<Value> = recursos
+        .into_iter()
+        .map(|r| {
+            let url = format!("{}/{}/{}", base, recurso.nombre(), r["id"].as_str().unwrap_or_default());
+            json!({ "fullUrl": url, "resource": r, "search": { "mode": "match" } })
+        })
+        .collect();
+    Ok(json!({
+        "resourceType": "Bundle",
+        "type": "searchset",
+        "total": total,
+        "link": enlaces,
+        "entry": entradas,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn params(pares: &[(&str, &str)]) -> Vec<(String, String)> {
+        pares.iter().map(|(n, v)| (n.to_string(), v.to_string())).collect()
+    }
+
+    fn fecha(texto: &str) -> NaiveDateTime {
+        NaiveDateTime::parse_from_str(texto, "%Y-%m-%d %H:%M:%S").unwrap()
+    }
+
+    #[test]
+    fn interpreta_last_updated() {
+        let c = interpretar(Recurso::Paciente, &params(&[("_lastUpdated", "2025-03-01")])).unwrap();
+        assert_eq!((c.actualizado_desde, c.actualizado_hasta), (Some(fecha("2025-03-01 00:00:00")), Some(fecha("2025-03-02 00:00:00"))));
+
+        let c = interpretar(Recurso::Paciente, &params(&[
+            ("_lastUpdated", "gt2025-03-01"),
+            ("_lastUpdated", "le2025-03-10T12:00:00-03:00"),
+        ])).unwrap();
+        assert_eq!(c.actualizado_desde, Some(fecha("2025-03-02 00:00:00")));
+        assert_eq!(c.actualizado_hasta, Some(fecha("2025-03-10 15:00:01")));
+
+        assert!(interpretar(Recurso::Paciente, &params(&[("_lastUpdated", "ap2025-03-01")])).is_err());
+        assert!(interpretar(Recurso::Paciente, &params(&[("_lastUpdated", "ayer")])).is_err());
+    }
+
+    #[test]
+    fn interpreta_identificadores() {
+        let c = interpretar(Recurso::Paciente, &params(&[("identifier", &format!("{}|10.895.960-6", SISTEMA_RUN))])).unwrap();
+        assert_eq!((c.rut.as_deref(), c.ids), (Some("0010895960-6"), None));
+
+        let c = interpretar(Recurso::Profesional, &params(&[("identifier", "212402")])).unwrap();
+        assert_eq!(c.rnpi.as_deref(), Some("212402"));
+        let c = interpretar(Recurso::Profesional, &params(&[("identifier", "16354813-5")])).unwrap();
+        assert_eq!(c.rut.as_deref(), Some("0016354813-5"));
+
+        // RUT inválido, sistema ajeno o criterios contradictorios: nada coincide
+        for (recurso, pares) in [
+            (Recurso::Paciente, params(&[("identifier", "10895960-5")])),
+            (Recurso::Paciente, params(&[("identifier", &format!("{}|212402", SISTEMA_RNPI))])),
+            (Recurso::Organizacion, params(&[("_id", "01,02"), ("identifier", "03")])),
+        ] {
+            assert_eq!(interpretar(recurso, &pares).unwrap().ids, Some(Vec::new()));
+        }
+        let c = interpretar(Recurso::Organizacion, &params(&[("_id", "01,02"), ("identifier", "02")])).unwrap();
+        assert_eq!(c.ids, Some(vec!["02".to_string()]));
+
+        let c = interpretar(Recurso::Encuentro, &params(&[("_count", "500"), ("_offset", "40"), ("_format", "json")])).unwrap();
+        assert_eq!((c.cantidad, c.salto), (MAX_PAGINA, 40));
+        assert!(interpretar(Recurso::Encuentro, &params(&[("_count", "-1")])).is_err());
+    }
+
+    #[test]
+    fn enlaces_de_paginacion() {
+        let pares = params(&[("identifier", "1|2"), ("_count", "10"), ("_offset", "10")]);
+        let consulta = interpretar(Recurso::Paciente, &pares).unwrap();
+        let b = bundle("http://h/fhir", Recurso::Paciente, &pares, &consulta, 25, vec![json!({ "id": "7" })]).unwrap();
+        let enlaces: Vec<(&str, &str)> = b["link"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|l| (l["relation"].as_str().unwrap(), l["url"].as_str().unwrap()))
+            .collect();
+        assert_eq!(enlaces, vec![
+            ("self", "http://h/fhir/Patient?identifier=1%7C2&_count=10&_offset=10"),
+            ("next", "http://h/fhir/Patient?identifier=1%7C2&_count=10&_offset=20"),
+            ("previous", "http://h/fhir/Patient?identifier=1%7C2&_count=10&_offset=0"),
+        ]);
+        assert_eq!(b["entry"][0]["fullUrl"], "http://h/fhir/Patient/7");
+        assert_eq!(b["total"], 25);
+    }
+}
@@ -0,0 +1,205 @@
+use aes_gcm::{aead::{Aead, KeyInit, Payload}, Aes256Gcm, Nonce};
+use ed25519_dalek::{Signature, Signer, SigningKey, Verifier, VerifyingKey};
+use rand::{rngs::OsRng, RngCore};
+use sha2::{Digest, Sha256};
+
+use crate::{
+    error::AppError,
+    models::{ClaveFirma, Firma},
+    tiempo,
+};
+
+const LARGO_NONCE: usize = 12;
+const ENCABEZADO: &str = "TELEMED-FIRMA-1";
+
+/// Guarda la clave maestra con que se cifran las claves privadas de firma
+/// y los mensajes. Sin ella se puede verificar, pero no firmar ni cifrar.
+#[derive(Clone, Default)]
+pub struct Custodia {
+    clave_maestra: Option<[u8; 32]>,
+}
+
+impl Custodia {
+    /// `clave_hex` son 32 bytes en hexadecimal (`FIRMA_CLAVE_MAESTRA`).
+    pub fn new(clave_hex: Option<&str>) -> Result<Self, AppError> {
+        let clave_maestra = match clave_hex.map(str::trim).filter(|c| !c.is_empty()) {
+            Some(c) => Some(
+                hex::decode(c)
+                    .ok()
+                    .and_then(|b| <[u8; 32]>::try_from(b).ok())
+                    .ok_or_else(|| AppError::Config("FIRMA_CLAVE_MAESTRA debe tener 64 dígitos hexadecimales".into()))?,
+            ),
+            None => None,
+        };
+        Ok(Self { clave_maestra })
+    }
+
+    fn cifrador(&self) -> Result<Aes256Gcm, AppError> {
+        let clave = self.clave_maestra
+            .ok_or_else(|| AppError::Config("FIRMA_CLAVE_MAESTRA no configurada".into()))?;
+        Ok(Aes256Gcm::new(&clave.into()))
+    }
+
+    /// Cifra `datos` con AES-256-GCM. `contexto` queda autenticado junto
+    /// con ellos: descifrar con otro contexto falla. Retorna nonce y texto
+    /// cifrado en hexadecimal.
+    pub fn cifrar(&self, contexto: &str, datos: &[u8]) -> Result<(String, String), AppError> {
+        let cifrador = self.cifrador()?;
+        let mut nonce = [0u8; LARGO_NONCE];
+        OsRng.fill_bytes(&mut nonce);
+        let cifrado = cifrador
+            .encrypt(Nonce::from_slice(&nonce), Payload { msg: datos, aad: contexto.as_bytes() })
+            .map_err(|_| AppError::Internal("No se pudo cifrar".into()))?;
+        Ok((hex::encode(nonce), hex::encode(cifrado)))
+    }
+
+    /// `None` si el texto fue alterado, el contexto no corresponde o se
+    /// cifró con otra clave maestra.
+    pub fn descifrar(&self, contexto: &str, nonce: &str, cifrado: &str) -> Result<Option<Vec<u8>>, AppError> {
+        let cifrador = self.cifrador()?;
+        let (Ok(nonce), Ok(cifrado)) = (hex::decode(nonce), hex::decode(cifrado)) else {
+            return Ok(None);
+        };
+        if nonce.len() != LARGO_NONCE {
+            return Ok(None);
+        }
+        Ok(cifrador.decrypt(Nonce::from_slice(&nonce), Payload { msg: &cifrado, aad: contexto.as_bytes() }).ok())
+    }
+
+    pub fn generar(&self, id_prof: u32) -> Result<ClaveFirma, AppError> {
+        let privada = SigningKey::generate(&mut OsRng);
+        let (nonce, cifrada) = self.cifrar(&contexto(id_prof), privada.as_bytes())?;
+
+        Ok(ClaveFirma {
+            id: 0,
+            id_prof,
+            clave_publica: hex::encode(privada.verifying_key().as_bytes()),
+            clave_privada: cifrada,
+            nonce,
+            creada_en: tiempo::ahora(),
+            revocada_en: None,
+        })
+    }
+
+    /// Firma separada (hex) de `mensaje` con la clave del profesional.
+    pub fn firmar(&self, clave: &ClaveFirma, mensaje: &[u8]) -> Result<String, AppError> {
+        let privada = self.descifrar(&contexto(clave.id_prof), &clave.nonce, &clave.clave_privada)?
+            .and_then(|b| <[u8; 32]>::try_from(b).ok())
+            .map(|b| SigningKey::from_bytes(&b))
+            .ok_or_else(|| AppError::Internal(format!("Clave de firma {} ilegible", clave.id)))?;
+        Ok(hex::encode(privada.sign(mensaje).to_bytes()))
+    }
+}
+
+/// La clave privada queda ligada al profesional: copiada a otro no sirve.
+fn contexto(id_prof: u32) -> String {
+    format!("{}:{}", ENCABEZADO, id_prof)
+}
+
+pub fn huella(contenido: &str) -> String {
+    hex::encode(Sha256::digest(contenido.as_bytes()))
+}
+
+/// Bytes que se firman: la huella del contenido junto con quién, cuándo y
+/// qué documento. Alterar cualquiera de estos datos invalida la firma.
+pub fn mensaje(firma: &Firma) -> Vec<u8> {
+    [
+        ENCABEZADO.to_string(),
+        firma.tipo.clone(),
+        firma.id_documento.to_string(),
+        firma.version.to_string(),
+        firma.id_prof.to_string(),
+        firma.rut_firmante.clone(),
+        firma.rnpi_firmante.clone(),
+        firma.firmada_en.format("%Y-%m-%dT%H:%M:%SZ").to_string(),
+        firma.huella.clone(),
+    ]
+    .join("\n")
+    .into_bytes()
+}
+
+/// `true` si `firma_hex` es una firma válida de `mensaje` con la clave pública.
+pub fn verificar(clave_publica_hex: &str, mensaje: &[u8], firma_hex: &str) -> bool {
+    let publica = hex::decode(clave_publica_hex)
+        .ok()
+        .and_then(|b| <[u8; 32]>::try_from(b).ok())
+        .and_then(|b| VerifyingKey::from_bytes(&b).ok());
+    let firma = hex::decode(firma_hex)
+        .ok()
+        .and_then(|b| <[u8; 64]>::try_from(b).ok())
+        .map(|b| Signature::from_bytes(&b));
+    match (publica, firma) {
+        (Some(p), Some(f)) => p.verify(mensaje, &f).is_ok(),
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::FIRMA_DOCUMENTO;
+
+    const CLAVE: &str = "000102030405060708090a0b0c0d0e0f101112131415161718191a1b1c1d1e1f";
+
+    fn firma(huella_contenido: &str) -> Firma {
+        Firma {
+            id: 0,
+            tipo: FIRMA_DOCUMENTO.into(),
+            id_documento: 7,
+            version: 2,
+            id_clave: 1,
+            id_prof: 1,
+            rut_firmante: "16354813-5".into(),
+            rnpi_firmante: "212402".into(),
+            firmada_en: tiempo::ahora(),
+            huella: huella(huella_contenido),
+            firma: String::new(),
+        }
+    }
+
+    #[test]
+    fn firma_y_detecta_alteraciones() {
+        let custodia = Custodia::new(Some(CLAVE)).unwrap();
+        let clave = custodia.generar(1).unwrap();
+        let mut f = firma("Reposo por 3 días.");
+        f.firma = custodia.firmar(&clave, &mensaje(&f)).unwrap();
+        assert!(verificar(&clave.clave_publica, &mensaje(&f), &f.firma));
+
+        let mut alterada = f.clone();
+        alterada.huella = huella("Reposo por 30 días.");
+        assert!(!verificar(&clave.clave_publica, &mensaje(&alterada), &f.firma));
+        let mut alterada = f.clone();
+        alterada.rnpi_firmante = "999999".into();
+        assert!(!verificar(&clave.clave_publica, &mensaje(&alterada), &f.firma));
+        assert!(!verificar(&clave.clave_publica, &mensaje(&f), "zz"));
+    }
+
+    #[test]
+    fn sobrevive_el_redondeo_del_datetime() {
+        let custodia = Custodia::new(Some(CLAVE)).unwrap();
+        let clave = custodia.generar(1).unwrap();
+        let mut f = firma("Reposo por 3 días.");
+        let instante = chrono::NaiveDate::from_ymd_opt(2026, 3, 2).unwrap().and_hms_milli_opt(12, 0, 0, 700).unwrap();
+        f.firmada_en = tiempo::al_segundo(instante);
+        f.firma = custodia.firmar(&clave, &mensaje(&f)).unwrap();
+
+        // MySQL redondea la fracción al guardar en DATETIME
+        let mut releida = f.clone();
+        releida.firmada_en = tiempo::al_segundo(f.firmada_en + chrono::Duration::milliseconds(500));
+        assert!(verificar(&clave.clave_publica, &mensaje(&releida), &f.firma));
+    }
+
+    #[test]
+    fn clave_privada_queda_ligada_a_custodia_y_profesional() {
+        let custodia = Custodia::new(Some(CLAVE)).unwrap();
+        let mut clave = custodia.generar(1).unwrap();
+        let otra = Custodia::new(Some(&"ff".repeat(32))).unwrap();
+        assert!(otra.firmar(&clave, b"x").is_err());
+        clave.id_prof = 2;
+        assert!(custodia.firmar(&clave, b"x").is_err());
+
+        assert!(Custodia::default().generar(1).is_err());
+        assert!(Custodia::new(Some("abcd")).is_err());
+        assert!(Custodia::new(None).is_ok());
+    }
+}
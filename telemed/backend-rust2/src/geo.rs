@@ -0,0 +1,34 @@
+use crate::error::AppError;
+
+const RADIO_TIERRA_KM: f64 = 6371.0;
+
+/// Distancia en línea recta (haversine) entre dos puntos `(latitud, longitud)`.
+pub fn distancia_km(a: (f64, f64), b: (f64, f64)) -> f64 {
+    let (lat1, lon1) = (a.0.to_radians(), a.1.to_radians());
+    let (lat2, lon2) = (b.0.to_radians(), b.1.to_radians());
+    let h = ((lat2 - lat1) / 2.0).sin().powi(2) + lat1.cos() * lat2.cos() * ((lon2 - lon1) / 2.0).sin().powi(2);
+    2.0 * RADIO_TIERRA_KM * h.sqrt().asin()
+}
+
+/// Las coordenadas se indican ambas o ninguna, y dentro de rango.
+pub fn validar_coordenadas(latitud: Option<f64>, longitud: Option<f64>) -> Result<Option<(f64, f64)>, AppError> {
+    match (latitud, longitud) {
+        (None, None) => Ok(None),
+        (Some(lat), Some(lon)) if (-90.0..=90.0).contains(&lat) && (-180.0..=180.0).contains(&lon) => Ok(Some((lat, lon))),
+        _ => Err(AppError::Validation("Latitud y longitud deben indicarse juntas y en rango".into())),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn distancia_santiago_valparaiso() {
+        let santiago = (-33.4489, -70.6693);
+        let valparaiso = (-33.0472, -71.6127);
+        let d = distancia_km(santiago, valparaiso);
+        assert!((d - 98.0).abs() < 3.0, "{}", d);
+        assert!(validar_coordenadas(Some(-33.4), None).is_err());
+    }
+}
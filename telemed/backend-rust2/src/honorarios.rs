@@ -0,0 +1,200 @@
+use chrono::NaiveDate;
+
+use crate::{
+    models::{Liquidacion, PrestacionHonorario, Profesional, RetencionHonorarios, TarifaHonorario, HONORARIO_ATENCION},
+    texto::{campo_csv, plegar},
+};
+
+/// Tarifa que se paga por la prestación. Entre las vigentes del tipo
+/// prefiere la de la especialidad del profesional y luego la de la zona;
+/// a igualdad, la más reciente.
+pub fn tarifa_aplicable<'a>(
+    tarifas: &'a [TarifaHonorario],
+    tipo: &str,
+    especialidad: Option<&str>,
+    cod_zona: Option<&str>,
+    fecha: NaiveDate,
+) -> Option<&'a TarifaHonorario> {
+    let especialidad = especialidad.map(|e| plegar(e.trim()));
+    tarifas
+        .iter()
+        .filter(|t| t.tipo == tipo && t.vigente_en(fecha))
+        .filter(|t| t.especialidad.as_deref().is_none_or(|e| especialidad.as_deref() == Some(plegar(e.trim()).as_str())))
+        .filter(|t| t.cod_zona.is_none() || t.cod_zona.as_deref() == cod_zona)
+        .max_by_key(|t| (t.especialidad.is_some(), t.cod_zona.is_some(), t.vigencia_desde, t.id))
+}
+
+/// Tasa de retención vigente a la fecha: la de inicio más reciente.
+pub fn tasa_vigente(retenciones: &[RetencionHonorarios], fecha: NaiveDate) -> Option<u16> {
+    retenciones
+        .iter()
+        .filter(|r| r.vigencia_desde <= fecha)
+        .max_by_key(|r| r.vigencia_desde)
+        .map(|r| r.tasa)
+}
+
+/// Retención sobre el bruto, redondeada al peso.
+pub fn retener(bruto: u64, tasa: u16) -> u64 {
+    (bruto * u64::from(tasa) + 5_000) / 10_000
+}
+
+/// Valoriza una prestación. Sin tarifa queda en cero con el motivo, para
+/// cargarla y volver a liquidar.
+pub fn valorizar(
+    tarifas: &[TarifaHonorario],
+    tipo: &str,
+    id_origen: u32,
+    fecha: NaiveDate,
+    especialidad: Option<&str>,
+    cod_zona: Option<String>,
+) -> PrestacionHonorario {
+    let tarifa = tarifa_aplicable(tarifas, tipo, especialidad, cod_zona.as_deref(), fecha);
+    PrestacionHonorario {
+        tipo: tipo.into(),
+        id_origen,
+        fecha,
+        observacion: tarifa.is_none().then(|| format!(
+            "No hay tarifa vigente al {} para {} en la zona {}",
+            fecha,
+            especialidad.unwrap_or("sin especialidad"),
+            cod_zona.as_deref().unwrap_or("sin zona"),
+        )),
+        cod_zona,
+        monto: tarifa.map_or(0, |t| t.monto),
+    }
+}
+
+/// Liquidación del período con la retención de la boleta de honorarios.
+pub fn liquidar(periodo: &str, profesional: &Profesional, mut prestaciones: Vec<PrestacionHonorario>, tasa: u16) -> Liquidacion {
+    prestaciones.sort_by(|a, b| (a.fecha, &a.tipo, a.id_origen).cmp(&(b.fecha, &b.tipo, b.id_origen)));
+    let bruto: u64 = prestaciones.iter().map(|p| u64::from(p.monto)).sum();
+    let retencion = retener(bruto, tasa);
+    let atenciones = prestaciones.iter().filter(|p| p.tipo == HONORARIO_ATENCION).count() as u32;
+    Liquidacion {
+        periodo: periodo.into(),
+        id_prof: profesional.id_prof,
+        rut: profesional.rut.clone(),
+        nombre: profesional.nombre_completo(),
+        especialidad: profesional.especialidad.clone(),
+        atenciones,
+        visitas: prestaciones.len() as u32 - atenciones,
+        sin_tarifa: prestaciones.iter().filter(|p| p.observacion.is_some()).count() as u32,
+        bruto,
+        tasa_retencion: tasa,
+        retencion,
+        liquido: bruto - retencion,
+        prestaciones,
+    }
+}
+
+/// Planilla de pago del período: CSV separado por `;`, una línea por
+/// profesional y una final con los totales. La tasa va en porcentaje con
+/// coma decimal, como la lee Excel en es-CL; los textos van entre comillas
+/// cuando hace falta.
+pub fn planilla(periodo: &str, liquidaciones: &[Liquidacion]) -> String {
+    let mut csv = String::from("periodo;rut;nombre;especialidad;atenciones;visitas;sin_tarifa;bruto;tasa_retencion;retencion;liquido\r\n");
+    for l in liquidaciones {
+        csv.push_str(&format!(
+            "{};{};{};{};{};{};{};{};{},{:02};{};{}\r\n",
+            campo_csv(&l.periodo),
+            campo_csv(&l.rut),
+            campo_csv(&l.nombre),
+            campo_csv(l.especialidad.as_deref().unwrap_or_default()),
+            l.atenciones,
+            l.visitas,
+            l.sin_tarifa,
+            l.bruto,
+            l.tasa_retencion / 100,
+            l.tasa_retencion % 100,
+            l.retencion,
+            l.liquido,
+        ));
+    }
+    let total = |f: fn(&Liquidacion) -> u64| liquidaciones.iter().map(f).sum::<u64>();
+    csv.push_str(&format!(
+        "{};TOTAL;;;{};{};{};{};;{};{}\r\n",
+        campo_csv(periodo),
+        total(|l| u64::from(l.atenciones)),
+        total(|l| u64::from(l.visitas)),
+        total(|l| u64::from(l.sin_tarifa)),
+        total(|l| l.bruto),
+        total(|l| l.retencion),
+        total(|l| l.liquido),
+    ));
+    csv
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{models::HONORARIO_VISITA, repositories::fixtures};
+    use super::*;
+
+    fn fecha(d: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(2025, 3, d).unwrap()
+    }
+
+    fn tarifa(id: u32, tipo: &str, especialidad: Option<&str>, cod_zona: Option<&str>, monto: u32) -> TarifaHonorario {
+        TarifaHonorario {
+            id,
+            tipo: tipo.into(),
+            especialidad: especialidad.map(Into::into),
+            cod_zona: cod_zona.map(Into::into),
+            monto,
+            vigencia_desde: fecha(1),
+            vigencia_hasta: None,
+        }
+    }
+
+    fn profesional() -> Profesional {
+        Profesional {
+            id_prof: 4,
+            especialidad: Some("Kinesiología".into()),
+            zona: Some("SUR".into()),
+            ..fixtures::profesional(4)
+        }
+    }
+
+    #[test]
+    fn liquida_con_tarifa_mas_especifica_y_retencion() {
+        let tarifas = vec![
+            tarifa(1, HONORARIO_ATENCION, None, None, 10_000),
+            tarifa(2, HONORARIO_ATENCION, Some("KINESIOLOGIA"), None, 12_000),
+            tarifa(3, HONORARIO_VISITA, Some("kinesiología"), Some("SUR"), 25_000),
+            tarifa(4, HONORARIO_VISITA, None, Some("SUR"), 18_000),
+        ];
+        let especialidad = profesional().especialidad;
+        let especialidad = especialidad.as_deref();
+
+        let prestaciones = vec![
+            valorizar(&tarifas, HONORARIO_VISITA, 8, fecha(12), especialidad, Some("SUR".into())),
+            valorizar(&tarifas, HONORARIO_ATENCION, 5, fecha(3), especialidad, None),
+            valorizar(&tarifas, HONORARIO_VISITA, 9, fecha(14), especialidad, Some("NORTE".into())),
+        ];
+        assert_eq!(prestaciones.iter().map(|p| p.monto).collect::<Vec<_>>(), vec![25_000, 12_000, 0]);
+        assert!(prestaciones[2].observacion.as_ref().unwrap().contains("NORTE"));
+        assert_eq!(valorizar(&tarifas, HONORARIO_ATENCION, 6, fecha(3), Some("Medicina"), None).monto, 10_000);
+
+        let retenciones = vec![
+            RetencionHonorarios { vigencia_desde: NaiveDate::from_ymd_opt(2024, 1, 1).unwrap(), tasa: 1375 },
+            RetencionHonorarios { vigencia_desde: NaiveDate::from_ymd_opt(2025, 1, 1).unwrap(), tasa: 1450 },
+        ];
+        assert_eq!(tasa_vigente(&retenciones, fecha(31)), Some(1450));
+        assert_eq!(tasa_vigente(&retenciones, NaiveDate::from_ymd_opt(2023, 12, 31).unwrap()), None);
+
+        let liquidacion = liquidar("2025-03", &profesional(), prestaciones, 1450);
+        assert_eq!(liquidacion.prestaciones[0].id_origen, 5);
+        assert_eq!((liquidacion.atenciones, liquidacion.visitas, liquidacion.sin_tarifa), (1, 2, 1));
+        // 37.000 * 14,5 % = 5.365
+        assert_eq!((liquidacion.bruto, liquidacion.retencion, liquidacion.liquido), (37_000, 5_365, 31_635));
+        assert_eq!(retener(10_001, 1450), 1_450);
+
+        let csv = planilla("2025-03", std::slice::from_ref(&liquidacion));
+        let lineas: Vec<&str> = csv.lines().collect();
+        assert_eq!(lineas[1], "2025-03;16.354.813-5;NICOLE ELENA ZAMORA;Kinesiología;1;2;1;37000;14,50;5365;31635");
+        assert_eq!(lineas[2], "2025-03;TOTAL;;;1;2;1;37000;;5365;31635");
+
+        let raro = Liquidacion { nombre: "ZAMORA; NICOLE \"NICO\"".into(), ..liquidacion };
+        let csv = planilla("2025-03", &[raro]);
+        assert!(csv.lines().nth(1).unwrap().starts_with("2025-03;16.354.813-5;\"ZAMORA; NICOLE \"\"NICO\"\"\";Kinesiología;"));
+    }
+}
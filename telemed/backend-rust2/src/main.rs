@@ -1,7 +1,7 @@
 use actix_web::{web, App, HttpServer};
 use tracing::info;
 use tracing_subscriber::{fmt, EnvFilter};
-use mysql_async::Opts;
+use mysql_async::{Opts, OptsBuilder};
 
 mod config;
 mod models;
@@ -9,11 +9,44 @@ mod error;
 mod repositories;
 mod api;
 mod app_state;
+mod rut;
+mod agenda;
+mod tiempo;
+mod feriados;
+mod cie10;
+mod texto;
+mod visitas;
+mod geo;
+mod rutas;
+mod pdf;
+mod recetas;
+mod documentos;
+mod firma;
+mod senalizacion;
+mod sala_espera;
+mod mensajeria;
+mod notificaciones;
+mod transportes;
+mod recordatorios;
+mod enlaces;
+mod almacen;
+mod adjuntos;
+mod fhir;
+mod derivaciones;
+mod consentimientos;
+mod facturacion;
+mod honorarios;
+mod contratos;
+mod credenciales;
 
 use crate::{
     config::Config,
     repositories::MysqlRepository,
     app_state::AppState,
+    adjuntos::Adjuntos,
+    firma::Custodia,
+    enlaces::Enlaces,
+    transportes::Transportes,
 };
 
 #[actix_web::main]
@@ -26,16 +59,34 @@ async fn main() -> std::io::Result<()> {
     
     // 3. Logging
     fmt()
-        .with_env_filter(EnvFilter::new(config.log_level))
+        .with_env_filter(EnvFilter::new(&config.log_level))
         .init();
     
     // 4. Pool de conexiones MySQL
+    // Las fechas se guardan en UTC; la sesión en UTC hace que los TIMESTAMP
+    // (CURRENT_TIMESTAMP, ON UPDATE) también lo estén. `setup` se repite
+    // cuando el pool reinicia una conexión.
     let opts = Opts::from_url(&config.database_url).expect("Invalid database URL");
+    let opts = OptsBuilder::from_opts(opts).setup(vec!["SET time_zone = '+00:00'"]);
     let pool = mysql_async::Pool::new(opts);
     
     // 5. Repositorio
     let repository = MysqlRepository::new(pool);
-    let app_state = AppState::new(repository);
+    let custodia = Custodia::new(config.firma_clave_maestra.as_deref()).expect("Invalid FIRMA_CLAVE_MAESTRA");
+    let transportes = Transportes::desde_config(&config).expect("Invalid notification transport configuration");
+    let enlaces = Enlaces::new(config.enlaces_clave.as_deref(), &config.portal_url);
+    let adjuntos = Adjuntos::desde_config(&config).expect("Invalid attachment storage configuration");
+    let app_state = AppState::new(repository)
+        .with_custodia(custodia)
+        .with_transportes(transportes)
+        .with_enlaces(enlaces)
+        .with_adjuntos(adjuntos);
+    actix_web::rt::spawn(api::vigilar(app_state.clone()));
+    actix_web::rt::spawn(api::depurar(app_state.clone()));
+    actix_web::rt::spawn(api::despachar(app_state.clone()));
+    actix_web::rt::spawn(api::recordar(app_state.clone()));
+    actix_web::rt::spawn(api::sincronizar(app_state.clone()));
+    actix_web::rt::spawn(api::alertar(app_state.clone()));
     
     info!("Starting server on {}", config.server_address);
     
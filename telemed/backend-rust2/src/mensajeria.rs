@@ -0,0 +1,224 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+use chrono::NaiveDateTime;
+use serde::{Deserialize, Serialize};
+use tokio::sync::broadcast;
+
+use crate::{
+    error::AppError,
+    firma::Custodia,
+    tiempo,
+    models::{ContenidoMensaje, Conversacion, Lectura, Mensaje, MensajeCifrado, Participante, Zona},
+    texto::plegar,
+};
+
+/// Retención por defecto y mínima de los mensajes, en días.
+pub const RETENCION_DIAS: u16 = 1825;
+pub const RETENCION_MINIMA_DIAS: u16 = 30;
+pub const LARGO_MAXIMO: usize = 4000;
+pub const MAXIMO_ADJUNTOS: usize = 10;
+/// Mensajes por página del historial, por defecto y como máximo.
+pub const PAGINA: u16 = 50;
+pub const PAGINA_MAXIMA: u16 = 200;
+const ENCABEZADO: &str = "TELEMED-MENSAJE-1";
+const CAPACIDAD_EVENTOS: usize = 64;
+
+/// Lo que se difunde a los conectados a una conversación.
+#[derive(Debug, Clone, Serialize, PartialEq)]
+#[serde(tag = "tipo", rename_all = "snake_case")]
+pub enum Evento {
+    Mensaje(Mensaje),
+    Lectura(Lectura),
+    Error { mensaje: String },
+}
+
+/// Lo que envía el cliente por el WebSocket.
+#[derive(Debug, Deserialize)]
+#[serde(tag = "tipo", rename_all = "snake_case")]
+pub enum Entrante {
+    Enviar(ContenidoMensaje),
+    Leer { hasta: u32 },
+}
+
+/// Texto sin espacios sobrantes; rechaza mensajes vacíos o excedidos.
+pub fn validar(contenido: ContenidoMensaje) -> Result<ContenidoMensaje, AppError> {
+    let texto = contenido.texto.trim().to_string();
+    if texto.is_empty() && contenido.adjuntos.is_empty() {
+        return Err(AppError::Validation("El mensaje está vacío".into()));
+    }
+    if texto.chars().count() > LARGO_MAXIMO {
+        return Err(AppError::Validation(format!("El mensaje excede {} caracteres", LARGO_MAXIMO)));
+    }
+    if contenido.adjuntos.len() > MAXIMO_ADJUNTOS {
+        return Err(AppError::Validation(format!("Máximo {} adjuntos por mensaje", MAXIMO_ADJUNTOS)));
+    }
+    if contenido.adjuntos.iter().any(|a| a.nombre.trim().is_empty() || a.referencia.trim().is_empty()) {
+        return Err(AppError::Validation("Adjunto sin nombre o referencia".into()));
+    }
+    Ok(ContenidoMensaje { texto, adjuntos: contenido.adjuntos })
+}
+
+/// Códigos de las zonas de acceso cuyo nombre es la zona del profesional,
+/// que en `paso_profesionales` es texto libre ("Ovalle", no "027").
+pub fn codigos_de_zona(zona_profesional: &str, zonas: &[Zona]) -> Vec<String> {
+    let nombre = plegar(zona_profesional.trim());
+    zonas
+        .iter()
+        .filter(|z| plegar(z.nom_zona.trim()) == nombre)
+        .map(|z| z.cod_zona.clone())
+        .collect()
+}
+
+/// Un profesional sin zona asignada (`None`) ve las conversaciones de todas
+/// las zonas; si no, las de los códigos de su zona.
+pub fn en_zona(conversacion: &Conversacion, codigos: Option<&[String]>) -> bool {
+    codigos.is_none_or(|c| conversacion.cod_zona.as_ref().is_some_and(|z| c.contains(z)))
+}
+
+/// El contenido cifrado queda ligado a la conversación, al autor y a la
+/// hora de envío: moverlo a otra fila o cambiar el autor lo hace ilegible.
+/// La hora va al segundo, igual que en `cifrar`.
+fn contexto(id_conversacion: u32, autor: &Participante, enviado_en: NaiveDateTime) -> String {
+    format!(
+        "{}:{}:{}:{}:{}",
+        ENCABEZADO, id_conversacion, autor.tipo, autor.id, enviado_en.format("%Y-%m-%dT%H:%M:%S"),
+    )
+}
+
+pub fn cifrar(
+    custodia: &Custodia,
+    id_conversacion: u32,
+    autor: &Participante,
+    contenido: &ContenidoMensaje,
+    enviado_en: NaiveDateTime,
+) -> Result<MensajeCifrado, AppError> {
+    let json = serde_json::to_vec(contenido).map_err(|e| AppError::Internal(e.to_string()))?;
+    // Se guarda tal como se liga: el DATETIME redondearía la fracción
+    let enviado_en = tiempo::al_segundo(enviado_en);
+    let (nonce, contenido) = custodia.cifrar(&contexto(id_conversacion, autor, enviado_en), &json)?;
+    Ok(MensajeCifrado {
+        id: 0,
+        id_conversacion,
+        autor_tipo: autor.tipo.clone(),
+        autor_id: autor.id,
+        nonce,
+        contenido,
+        enviado_en,
+    })
+}
+
+pub fn descifrar(custodia: &Custodia, mensaje: MensajeCifrado) -> Result<Mensaje, AppError> {
+    let autor = Participante { tipo: mensaje.autor_tipo, id: mensaje.autor_id };
+    let contenido = custodia
+        .descifrar(&contexto(mensaje.id_conversacion, &autor, mensaje.enviado_en), &mensaje.nonce, &mensaje.contenido)?
+        .and_then(|json| serde_json::from_slice(&json).ok())
+        .ok_or_else(|| AppError::Internal(format!("Mensaje {} ilegible", mensaje.id)))?;
+    Ok(Mensaje {
+        id: mensaje.id,
+        id_conversacion: mensaje.id_conversacion,
+        autor,
+        contenido,
+        enviado_en: mensaje.enviado_en,
+    })
+}
+
+/// Canales por conversación hacia los participantes conectados por
+/// WebSocket. Quien no está conectado ve los mensajes en el historial.
+#[derive(Clone, Default)]
+pub struct Buzones {
+    canales: Arc<Mutex<HashMap<u32, broadcast::Sender<Arc<Evento>>>>>,
+}
+
+impl Buzones {
+    pub fn suscribir(&self, id_conversacion: u32) -> Result<broadcast::Receiver<Arc<Evento>>, AppError> {
+        let mut canales = self.canales.lock().map_err(|e| AppError::Internal(e.to_string()))?;
+        Ok(canales
+            .entry(id_conversacion)
+            .or_insert_with(|| broadcast::channel(CAPACIDAD_EVENTOS).0)
+            .subscribe())
+    }
+
+    /// Difunde el evento; el canal se descarta cuando ya nadie escucha.
+    pub fn publicar(&self, id_conversacion: u32, evento: Evento) -> Result<(), AppError> {
+        let mut canales = self.canales.lock().map_err(|e| AppError::Internal(e.to_string()))?;
+        if let Some(canal) = canales.get(&id_conversacion)
+            && canal.send(Arc::new(evento)).is_err()
+        {
+            canales.remove(&id_conversacion);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{Duration, NaiveDate};
+    use crate::models::{AdjuntoMensaje, PARTICIPANTE_PACIENTE, PARTICIPANTE_PROFESIONAL};
+
+    fn contenido(texto: &str) -> ContenidoMensaje {
+        ContenidoMensaje {
+            texto: texto.into(),
+            adjuntos: vec![AdjuntoMensaje { nombre: "examen.pdf".into(), referencia: "adj/91".into() }],
+        }
+    }
+
+    #[test]
+    fn cifra_ligado_a_conversacion_y_autor() {
+        let custodia = Custodia::new(Some(&"0a".repeat(32))).unwrap();
+        let paciente = Participante { tipo: PARTICIPANTE_PACIENTE.into(), id: 4 };
+        let ahora = tiempo::ahora();
+        let cifrado = cifrar(&custodia, 7, &paciente, &contenido("Me subió la fiebre"), ahora).unwrap();
+        assert!(!cifrado.contenido.contains("fiebre"));
+
+        let mensaje = descifrar(&custodia, cifrado.clone()).unwrap();
+        assert_eq!(mensaje.contenido, contenido("Me subió la fiebre"));
+        assert_eq!(mensaje.autor, paciente);
+
+        let mut movido = cifrado.clone();
+        movido.id_conversacion = 8;
+        assert!(descifrar(&custodia, movido).is_err());
+        let mut suplantado = cifrado;
+        suplantado.autor_tipo = PARTICIPANTE_PROFESIONAL.into();
+        assert!(descifrar(&custodia, suplantado).is_err());
+    }
+
+    #[test]
+    fn sobrevive_el_redondeo_del_datetime() {
+        let custodia = Custodia::new(Some(&"0a".repeat(32))).unwrap();
+        let paciente = Participante { tipo: PARTICIPANTE_PACIENTE.into(), id: 4 };
+        let enviado_en = NaiveDate::from_ymd_opt(2025, 3, 10).unwrap().and_hms_milli_opt(12, 0, 0, 700).unwrap();
+        let cifrado = cifrar(&custodia, 7, &paciente, &contenido("Hola"), enviado_en).unwrap();
+        assert_eq!(cifrado.enviado_en, NaiveDate::from_ymd_opt(2025, 3, 10).unwrap().and_hms_opt(12, 0, 0).unwrap());
+
+        // MySQL redondea la fracción al guardar en DATETIME
+        let redondear = |t: NaiveDateTime| tiempo::al_segundo(t + Duration::milliseconds(500));
+        let recargado = MensajeCifrado { enviado_en: redondear(cifrado.enviado_en), ..cifrado };
+        assert_eq!(descifrar(&custodia, recargado).unwrap().contenido, contenido("Hola"));
+    }
+
+    #[test]
+    fn resuelve_la_zona_por_nombre() {
+        let zona = |cod: &str, nombre: &str| Zona {
+            cod_zona: cod.into(),
+            nom_zona: nombre.into(),
+            orden_zona: None,
+            zona_horaria: tiempo::ZONA_HORARIA_DEFECTO.into(),
+        };
+        let zonas = vec![zona("079", "Metropolitana"), zona("047", "Metropolitana Norte"), zona("005", "Chillán")];
+        assert_eq!(codigos_de_zona(" METROPOLITANA ", &zonas), vec!["079"]);
+        assert_eq!(codigos_de_zona("Chillan", &zonas), vec!["005"]);
+        assert!(codigos_de_zona("V Región", &zonas).is_empty());
+    }
+
+    #[test]
+    fn valida_contenido() {
+        assert_eq!(validar(contenido("  Hola ")).unwrap().texto, "Hola");
+        assert!(validar(ContenidoMensaje { texto: " ".into(), adjuntos: vec![] }).is_err());
+        assert!(validar(contenido(&"a".repeat(LARGO_MAXIMO + 1))).is_err());
+        let mut sin_referencia = contenido("x");
+        sin_referencia.adjuntos[0].referencia.clear();
+        assert!(validar(sin_referencia).is_err());
+    }
+}
@@ -0,0 +1,50 @@
+use chrono::NaiveDateTime;
+use serde::{Deserialize, Serialize};
+use mysql_async::prelude::FromRow;
+
+use crate::tiempo;
+
+/// Archivo subido por un paciente (exámenes) o por un profesional para una
+/// atención (imágenes, informes). El contenido está en el almacén bajo su
+/// SHA-256; varias filas pueden compartir el mismo contenido.
+#[derive(Debug, Serialize, Deserialize, FromRow, Clone)]
+pub struct Adjunto {
+    pub id: u32,
+    pub id_paciente: u32,
+    pub id_atencion: Option<u32>,
+    /// Profesional que lo subió; `None` si lo subió el paciente.
+    pub id_prof: Option<u32>,
+    pub nombre: String,
+    pub tipo_mime: String,
+    pub tamano: u32,
+    pub sha256: String,
+    #[serde(with = "tiempo::utc")]
+    pub subido_en: NaiveDateTime,
+}
+
+/// Criterios de búsqueda para `GET /api/adjuntos`.
+#[derive(Debug, Default, Deserialize)]
+pub struct AdjuntoFiltro {
+    pub id_paciente: Option<u32>,
+    pub id_atencion: Option<u32>,
+}
+
+/// Vigencia pedida para `GET /api/adjuntos/{id}/enlace`, en minutos.
+#[derive(Debug, Default, Deserialize)]
+pub struct EnlaceQuery {
+    pub minutos: Option<u32>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct EnlaceDescarga {
+    pub url: String,
+    #[serde(with = "tiempo::utc")]
+    pub expira: NaiveDateTime,
+}
+
+/// Parámetros del enlace de descarga firmado. `expira` en segundos Unix.
+#[derive(Debug, Deserialize)]
+pub struct DescargaQuery {
+    pub expira: i64,
+    pub firma: String,
+}
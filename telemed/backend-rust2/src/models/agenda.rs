@@ -0,0 +1,202 @@
+use chrono::{NaiveDate, NaiveDateTime, NaiveTime};
+use chrono_tz::Tz;
+use serde::{Deserialize, Serialize};
+use mysql_async::prelude::FromRow;
+
+use crate::{error::AppError, tiempo};
+
+pub const MODALIDAD_TELECONSULTA: &str = "TELECONSULTA";
+pub const MODALIDADES: [&str; 2] = [MODALIDAD_TELECONSULTA, "DOMICILIO"];
+
+pub const CITA_AGENDADA: &str = "AGENDADA";
+pub const CITA_CANCELADA: &str = "CANCELADA";
+pub const CITA_REPROGRAMADA: &str = "REPROGRAMADA";
+pub const CITA_REALIZADA: &str = "REALIZADA";
+
+/// Bloque semanal recurrente en que un profesional atiende.
+/// `dia_semana` va de 1 (lunes) a 7 (domingo); las horas son locales a
+/// `zona_horaria`.
+#[derive(Debug, Serialize, Deserialize, FromRow, Clone)]
+pub struct BloqueDisponibilidad {
+    pub id: u32,
+    pub id_prof: u32,
+    pub dia_semana: u8,
+    pub hora_inicio: NaiveTime,
+    pub hora_fin: NaiveTime,
+    pub duracion_min: u16,
+    pub modalidad: String,
+    pub cod_zona: Option<String>,
+    pub zona_horaria: String,
+    pub sobrecupos: u8,
+    pub vigencia_desde: NaiveDate,
+    pub vigencia_hasta: Option<NaiveDate>,
+}
+
+impl BloqueDisponibilidad {
+    pub fn vigente_en(&self, fecha: NaiveDate) -> bool {
+        self.vigencia_desde <= fecha && self.vigencia_hasta.is_none_or(|h| fecha <= h)
+    }
+
+    pub fn tz(&self) -> Tz {
+        self.zona_horaria.parse().unwrap_or(chrono_tz::America::Santiago)
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct BloqueInput {
+    pub id_prof: u32,
+    pub dia_semana: u8,
+    pub hora_inicio: NaiveTime,
+    pub hora_fin: NaiveTime,
+    pub duracion_min: u16,
+    pub modalidad: String,
+    pub cod_zona: Option<String>,
+    /// Si no se indica, se usa la de la zona de acceso o la de Santiago.
+    pub zona_horaria: Option<String>,
+    #[serde(default)]
+    pub sobrecupos: u8,
+    pub vigencia_desde: NaiveDate,
+    pub vigencia_hasta: Option<NaiveDate>,
+}
+
+impl TryFrom<BloqueInput> for BloqueDisponibilidad {
+    type Error = AppError;
+
+    fn try_from(input: BloqueInput) -> Result<Self, Self::Error> {
+        if !(1..=7).contains(&input.dia_semana) {
+            return Err(AppError::Validation("dia_semana debe estar entre 1 (lunes) y 7 (domingo)".into()));
+        }
+        if input.hora_fin <= input.hora_inicio {
+            return Err(AppError::Validation("hora_fin debe ser posterior a hora_inicio".into()));
+        }
+        if input.duracion_min == 0 {
+            return Err(AppError::Validation("duracion_min debe ser mayor que cero".into()));
+        }
+        let bloque_min = (input.hora_fin - input.hora_inicio).num_minutes();
+        if i64::from(input.duracion_min) > bloque_min {
+            return Err(AppError::Validation("duracion_min excede el largo del bloque".into()));
+        }
+
+        let modalidad = input.modalidad.trim().to_uppercase();
+        if !MODALIDADES.contains(&modalidad.as_str()) {
+            return Err(AppError::Validation(format!("Modalidad inválida: {}", modalidad)));
+        }
+        if modalidad == "DOMICILIO" && input.cod_zona.is_none() {
+            return Err(AppError::Validation("Los bloques a domicilio requieren cod_zona".into()));
+        }
+
+        if let Some(hasta) = input.vigencia_hasta && hasta < input.vigencia_desde {
+            return Err(AppError::Validation("vigencia_hasta es anterior a vigencia_desde".into()));
+        }
+
+        let zona_horaria = input.zona_horaria.unwrap_or_else(|| tiempo::ZONA_HORARIA_DEFECTO.into());
+        tiempo::zona_horaria(&zona_horaria)?;
+
+        Ok(Self {
+            id: 0,
+            id_prof: input.id_prof,
+            dia_semana: input.dia_semana,
+            hora_inicio: input.hora_inicio,
+            hora_fin: input.hora_fin,
+            duracion_min: input.duracion_min,
+            modalidad,
+            cod_zona: input.cod_zona,
+            zona_horaria,
+            sobrecupos: input.sobrecupos,
+            vigencia_desde: input.vigencia_desde,
+            vigencia_hasta: input.vigencia_hasta,
+        })
+    }
+}
+
+/// Hora agendada de un paciente con un profesional. `inicio` y `fin` están en UTC.
+#[derive(Debug, Serialize, Deserialize, FromRow, Clone)]
+pub struct Cita {
+    pub id: u32,
+    pub id_prof: u32,
+    pub id_paciente: u32,
+    pub id_bloque: u32,
+    #[serde(with = "tiempo::utc")]
+    pub inicio: NaiveDateTime,
+    #[serde(with = "tiempo::utc")]
+    pub fin: NaiveDateTime,
+    pub modalidad: String,
+    pub cod_zona: Option<String>,
+    pub estado: String,
+    pub sobrecupo: bool,
+    pub motivo_cancelacion: Option<String>,
+    pub id_cita_original: Option<u32>,
+}
+
+impl Cita {
+    /// Una cita ocupa su horario mientras no haya sido cancelada o reprogramada.
+    pub fn activa(&self) -> bool {
+        self.estado == CITA_AGENDADA || self.estado == CITA_REALIZADA
+    }
+
+    pub fn se_traslapa(&self, inicio: NaiveDateTime, fin: NaiveDateTime) -> bool {
+        self.inicio < fin && inicio < self.fin
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CitaInput {
+    pub id_prof: u32,
+    pub id_paciente: u32,
+    #[serde(with = "tiempo::utc")]
+    pub inicio: NaiveDateTime,
+    pub modalidad: String,
+    #[serde(default)]
+    pub sobrecupo: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CancelacionInput {
+    pub motivo: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ReprogramacionInput {
+    #[serde(with = "tiempo::utc")]
+    pub inicio: NaiveDateTime,
+    #[serde(default)]
+    pub sobrecupo: bool,
+}
+
+/// Criterios de búsqueda para `GET /api/agenda/citas`.
+#[derive(Debug, Default, Deserialize)]
+pub struct CitaFiltro {
+    pub id_prof: Option<u32>,
+    pub id_paciente: Option<u32>,
+    #[serde(default, deserialize_with = "tiempo::utc::opcional::deserialize")]
+    pub desde: Option<NaiveDateTime>,
+    #[serde(default, deserialize_with = "tiempo::utc::opcional::deserialize")]
+    pub hasta: Option<NaiveDateTime>,
+}
+
+/// Criterios de búsqueda para `GET /api/agenda/disponibilidad`.
+#[derive(Debug, Default, Deserialize)]
+pub struct DisponibilidadFiltro {
+    pub especialidad: Option<String>,
+    pub cod_zona: Option<String>,
+    pub modalidad: Option<String>,
+    #[serde(default, deserialize_with = "tiempo::utc::opcional::deserialize")]
+    pub desde: Option<NaiveDateTime>,
+    pub dias: Option<u32>,
+    pub limite: Option<usize>,
+}
+
+/// Horario concreto generado a partir de un bloque de disponibilidad.
+/// `inicio` y `fin` en UTC; `zona_horaria` indica cómo mostrarlos.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct Slot {
+    pub id_prof: u32,
+    pub id_bloque: u32,
+    #[serde(with = "tiempo::utc")]
+    pub inicio: NaiveDateTime,
+    #[serde(with = "tiempo::utc")]
+    pub fin: NaiveDateTime,
+    pub modalidad: String,
+    pub cod_zona: Option<String>,
+    pub zona_horaria: String,
+}
@@ -0,0 +1,205 @@
+use chrono::NaiveDateTime;
+use serde::{Deserialize, Serialize};
+use mysql_async::prelude::FromRow;
+
+use crate::{cie10, error::AppError, tiempo};
+
+pub const ATENCION_BORRADOR: &str = "BORRADOR";
+pub const ATENCION_FIRMADA: &str = "FIRMADA";
+
+/// Registro clínico de una atención. Mientras está en `BORRADOR` puede
+/// editarse; una vez `FIRMADA` es inmutable y sólo admite adendas.
+/// Las fechas están en UTC.
+#[derive(Debug, Serialize, Deserialize, FromRow, Clone)]
+pub struct Atencion {
+    pub id: u32,
+    pub id_paciente: u32,
+    pub id_prof: u32,
+    pub id_cita: Option<u32>,
+    pub motivo: String,
+    pub anamnesis: Option<String>,
+    pub examen_fisico: Option<String>,
+    pub indicaciones: Option<String>,
+    pub estado: String,
+    #[serde(with = "tiempo::utc")]
+    pub creada_en: NaiveDateTime,
+    #[serde(default, with = "tiempo::utc::opcional")]
+    pub firmada_en: Option<NaiveDateTime>,
+}
+
+impl Atencion {
+    pub fn firmada(&self) -> bool {
+        self.estado == ATENCION_FIRMADA
+    }
+}
+
+/// Diagnóstico codificado en CIE-10. Si no se indica descripción se usa la
+/// del catálogo.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct Diagnostico {
+    pub codigo: String,
+    #[serde(default)]
+    pub descripcion: String,
+    #[serde(default)]
+    pub principal: bool,
+}
+
+/// Documento externo asociado a la atención (examen, imagen, informe).
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct AdjuntoAtencion {
+    pub nombre: String,
+    pub referencia: String,
+}
+
+/// Nota agregada a una atención firmada. Las adendas tampoco se modifican.
+#[derive(Debug, Serialize, Deserialize, FromRow, Clone)]
+pub struct Adenda {
+    pub id: u32,
+    pub id_atencion: u32,
+    pub id_prof: u32,
+    pub texto: String,
+    #[serde(with = "tiempo::utc")]
+    pub creada_en: NaiveDateTime,
+}
+
+/// Atención con sus diagnósticos, adjuntos y adendas.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct AtencionDetalle {
+    #[serde(flatten)]
+    pub atencion: Atencion,
+    pub diagnosticos: Vec<Diagnostico>,
+    pub adjuntos: Vec<AdjuntoAtencion>,
+    pub adendas: Vec<Adenda>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct AtencionInput {
+    pub id_paciente: u32,
+    pub id_prof: u32,
+    pub id_cita: Option<u32>,
+    pub motivo: String,
+    pub anamnesis: Option<String>,
+    pub examen_fisico: Option<String>,
+    pub indicaciones: Option<String>,
+    #[serde(default)]
+    pub diagnosticos: Vec<Diagnostico>,
+    #[serde(default)]
+    pub adjuntos: Vec<AdjuntoAtencion>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct FirmaInput {
+    pub id_prof: u32,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct AdendaInput {
+    pub id_prof: u32,
+    pub texto: String,
+}
+
+/// Criterios de búsqueda para `GET /api/atenciones`.
+#[derive(Debug, Default, Deserialize)]
+pub struct AtencionFiltro {
+    pub id_paciente: Option<u32>,
+    pub id_prof: Option<u32>,
+    pub id_cita: Option<u32>,
+    pub estado: Option<String>,
+    #[serde(default, deserialize_with = "tiempo::utc::opcional::deserialize")]
+    pub desde: Option<NaiveDateTime>,
+    #[serde(default, deserialize_with = "tiempo::utc::opcional::deserialize")]
+    pub hasta: Option<NaiveDateTime>,
+}
+
+fn texto_opcional(valor: Option<String>) -> Option<String> {
+    valor.map(|v| v.trim().to_string()).filter(|v| !v.is_empty())
+}
+
+impl TryFrom<AtencionInput> for AtencionDetalle {
+    type Error = AppError;
+
+    fn try_from(input: AtencionInput) -> Result<Self, Self::Error> {
+        let motivo = input.motivo.trim().to_string();
+        if motivo.is_empty() {
+            return Err(AppError::Validation("El motivo de consulta es obligatorio".into()));
+        }
+
+        let mut diagnosticos = Vec::with_capacity(input.diagnosticos.len());
+        for d in input.diagnosticos {
+            let codigo = cie10::normalizar_codigo(&d.codigo)
+                .ok_or_else(|| AppError::Validation(format!("Código CIE-10 inválido: {}", d.codigo)))?;
+            diagnosticos.push(Diagnostico {
+                codigo,
+                descripcion: d.descripcion.trim().to_string(),
+                principal: d.principal,
+            });
+        }
+        match diagnosticos.iter().filter(|d| d.principal).count() {
+            0 => if let Some(primero) = diagnosticos.first_mut() {
+                primero.principal = true;
+            },
+            1 => {}
+            _ => return Err(AppError::Validation("Sólo puede haber un diagnóstico principal".into())),
+        }
+
+        for a in &input.adjuntos {
+            if a.nombre.trim().is_empty() || a.referencia.trim().is_empty() {
+                return Err(AppError::Validation("Los adjuntos requieren nombre y referencia".into()));
+            }
+        }
+
+        Ok(Self {
+            atencion: Atencion {
+                id: 0,
+                id_paciente: input.id_paciente,
+                id_prof: input.id_prof,
+                id_cita: input.id_cita,
+                motivo,
+                anamnesis: texto_opcional(input.anamnesis),
+                examen_fisico: texto_opcional(input.examen_fisico),
+                indicaciones: texto_opcional(input.indicaciones),
+                estado: ATENCION_BORRADOR.into(),
+                creada_en: tiempo::ahora(),
+                firmada_en: None,
+            },
+            diagnosticos,
+            adjuntos: input.adjuntos,
+            adendas: Vec::new(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn input(diagnosticos: Vec<Diagnostico>) -> AtencionInput {
+        AtencionInput {
+            id_paciente: 1,
+            id_prof: 1,
+            id_cita: None,
+            motivo: " Tos ".into(),
+            anamnesis: Some("  ".into()),
+            examen_fisico: None,
+            indicaciones: None,
+            diagnosticos,
+            adjuntos: Vec::new(),
+        }
+    }
+
+    fn diagnostico(codigo: &str, principal: bool) -> Diagnostico {
+        Diagnostico { codigo: codigo.into(), descripcion: String::new(), principal }
+    }
+
+    #[test]
+    fn primer_diagnostico_es_principal_por_defecto() {
+        let detalle = AtencionDetalle::try_from(input(vec![diagnostico("j18.9", false), diagnostico("R05", false)])).unwrap();
+        assert_eq!(detalle.atencion.motivo, "Tos");
+        assert_eq!(detalle.atencion.anamnesis, None);
+        assert_eq!(detalle.diagnosticos[0].codigo, "J18.9");
+        assert!(detalle.diagnosticos[0].principal && !detalle.diagnosticos[1].principal);
+
+        assert!(AtencionDetalle::try_from(input(vec![diagnostico("J18.9", true), diagnostico("R05", true)])).is_err());
+        assert!(AtencionDetalle::try_from(input(vec![diagnostico("neumonia", false)])).is_err());
+    }
+}
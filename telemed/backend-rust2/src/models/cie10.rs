@@ -0,0 +1,24 @@
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+use mysql_async::prelude::FromRow;
+
+#[derive(Debug, Serialize, Deserialize, FromRow, Clone, PartialEq)]
+pub struct Cie10 {
+    pub codigo: String,
+    pub descripcion: String,
+}
+
+/// Criterios de búsqueda para `GET /api/cie10`. `q` puede ser un prefijo de
+/// código (`J18`) o palabras de la descripción.
+#[derive(Debug, Deserialize)]
+pub struct Cie10Filtro {
+    pub q: String,
+    pub limite: Option<usize>,
+}
+
+/// Parámetros de `POST /api/cie10/importar`.
+#[derive(Debug, Deserialize)]
+pub struct ImportacionCie10 {
+    pub version: String,
+    pub vigente_desde: NaiveDate,
+}
@@ -0,0 +1,93 @@
+use chrono::NaiveDateTime;
+use serde::{Deserialize, Serialize};
+use mysql_async::prelude::FromRow;
+
+use crate::tiempo;
+
+/// Propósitos que requieren consentimiento informado (Ley 20.584).
+pub const CONSENTIMIENTO_TELECONSULTA: &str = "TELECONSULTA";
+pub const CONSENTIMIENTO_GRABACION: &str = "GRABACION";
+pub const CONSENTIMIENTO_COMPARTIR: &str = "COMPARTIR_DATOS";
+pub const PROPOSITOS_CONSENTIMIENTO: [&str; 3] = [CONSENTIMIENTO_TELECONSULTA, CONSENTIMIENTO_GRABACION, CONSENTIMIENTO_COMPARTIR];
+
+/// Por dónde se registró la aceptación o revocación.
+pub const CANALES_CONSENTIMIENTO: [&str; 3] = ["PORTAL", "PRESENCIAL", "TELEFONICO"];
+
+pub const CONSENTIMIENTO_OTORGA: &str = "OTORGA";
+pub const CONSENTIMIENTO_REVOCA: &str = "REVOCA";
+
+/// Texto de un consentimiento. Cada cambio crea una versión nueva; la
+/// vigente es la de mayor versión. Si `requiere_renovacion`, las
+/// aceptaciones de versiones anteriores dejan de valer.
+#[derive(Debug, Serialize, Deserialize, FromRow, Clone)]
+pub struct PlantillaConsentimiento {
+    pub id: u32,
+    pub proposito: String,
+    pub version: u16,
+    pub titulo: String,
+    pub texto: String,
+    pub requiere_renovacion: bool,
+    #[serde(with = "tiempo::utc")]
+    pub creada_en: NaiveDateTime,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PlantillaConsentimientoInput {
+    pub titulo: String,
+    pub texto: String,
+    #[serde(default)]
+    pub requiere_renovacion: bool,
+}
+
+/// Aceptación o revocación de un consentimiento. Los eventos no se
+/// modifican; el estado es el del último evento del propósito.
+/// `otorgante` es el RUT del representante cuando no lo firma el propio
+/// paciente e `id_usuario` el funcionario que lo registró, si no fue el
+/// paciente desde el portal. Fechas en UTC.
+#[derive(Debug, Serialize, Deserialize, FromRow, Clone)]
+pub struct EventoConsentimiento {
+    pub id: u32,
+    pub id_paciente: u32,
+    pub proposito: String,
+    pub version: u16,
+    pub accion: String,
+    pub canal: String,
+    pub otorgante: Option<String>,
+    pub id_usuario: Option<u32>,
+    pub ip: Option<String>,
+    #[serde(with = "tiempo::utc")]
+    pub ocurrido_en: NaiveDateTime,
+}
+
+/// Aceptación de la versión que se le mostró al paciente.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct OtorgamientoInput {
+    pub proposito: String,
+    pub version: u16,
+    pub canal: String,
+    pub otorgante: Option<String>,
+    pub id_usuario: Option<u32>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RevocacionInput {
+    pub canal: String,
+    pub otorgante: Option<String>,
+    pub id_usuario: Option<u32>,
+}
+
+/// Situación de un propósito para un paciente.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct EstadoConsentimiento {
+    pub proposito: String,
+    pub vigente: bool,
+    /// Versión vigente del texto, si hay alguna publicada.
+    pub version_actual: Option<u16>,
+    pub ultimo_evento: Option<EventoConsentimiento>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ConsentimientosPaciente {
+    pub estados: Vec<EstadoConsentimiento>,
+    pub eventos: Vec<EventoConsentimiento>,
+}
@@ -0,0 +1,83 @@
+use chrono::{NaiveDate, NaiveDateTime};
+use serde::{Deserialize, Serialize};
+use mysql_async::prelude::FromRow;
+
+use crate::{error::AppError, tiempo};
+
+/// Valores de `paso_profesionales.estado`, que se derivan de los contratos.
+pub const PROFESIONAL_VIGENTE: &str = "VIGENTE";
+pub const PROFESIONAL_NO_VIGENTE: &str = "NO VIGENTE";
+
+/// Un período de contrato de un profesional. Una persona puede tener varios
+/// (reingresos) pero no traslapados; sin `fecha_egreso` sigue abierto.
+#[derive(Debug, Serialize, Deserialize, FromRow, Clone, PartialEq)]
+pub struct Contrato {
+    pub id: u32,
+    pub id_prof: u32,
+    pub fecha_ingreso: NaiveDate,
+    pub fecha_egreso: Option<NaiveDate>,
+    pub motivo_egreso: Option<String>,
+    pub observacion: Option<String>,
+    #[serde(with = "tiempo::utc")]
+    pub creado_en: NaiveDateTime,
+}
+
+impl Contrato {
+    /// Ambas fechas son inclusive: el día de egreso aún se trabaja.
+    pub fn vigente_en(&self, fecha: NaiveDate) -> bool {
+        self.fecha_ingreso <= fecha && self.fecha_egreso.is_none_or(|e| fecha <= e)
+    }
+
+    pub fn se_traslapa(&self, otro: &Contrato) -> bool {
+        self.fecha_egreso.is_none_or(|e| otro.fecha_ingreso <= e)
+            && otro.fecha_egreso.is_none_or(|e| self.fecha_ingreso <= e)
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ContratoInput {
+    pub fecha_ingreso: NaiveDate,
+    pub fecha_egreso: Option<NaiveDate>,
+    pub observacion: Option<String>,
+}
+
+impl TryFrom<ContratoInput> for Contrato {
+    type Error = AppError;
+
+    fn try_from(input: ContratoInput) -> Result<Self, Self::Error> {
+        if let Some(egreso) = input.fecha_egreso && egreso < input.fecha_ingreso {
+            return Err(AppError::Validation("La fecha de egreso es anterior a la de ingreso".into()));
+        }
+        Ok(Self {
+            id: 0,
+            id_prof: 0,
+            fecha_ingreso: input.fecha_ingreso,
+            fecha_egreso: input.fecha_egreso,
+            motivo_egreso: None,
+            observacion: input.observacion.map(|o| o.trim().to_string()).filter(|o| !o.is_empty()),
+            creado_en: tiempo::ahora(),
+        })
+    }
+}
+
+/// Cierre (o corrección del cierre) de un contrato.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct EgresoInput {
+    pub fecha_egreso: NaiveDate,
+    pub motivo: Option<String>,
+}
+
+/// Criterios de búsqueda de contratos.
+#[derive(Debug, Default, Deserialize)]
+pub struct ContratoFiltro {
+    pub id_prof: Option<u32>,
+    pub vigente_en: Option<NaiveDate>,
+}
+
+/// Estado y fechas que `paso_profesionales` muestra según los contratos.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct SituacionContractual {
+    pub estado: String,
+    pub fecha_ingreso: Option<NaiveDate>,
+    pub fecha_egreso: Option<NaiveDate>,
+}
@@ -0,0 +1,133 @@
+use chrono::{NaiveDate, NaiveDateTime};
+use serde::{Deserialize, Serialize};
+use mysql_async::prelude::FromRow;
+
+use crate::{error::AppError, tiempo};
+
+/// Documentos que acreditan al profesional. Sin los obligatorios al día no
+/// se le asigna trabajo; `OTRA` sólo se archiva.
+pub const CREDENCIAL_TITULO: &str = "TITULO";
+pub const CREDENCIAL_RNPI: &str = "RNPI";
+pub const CREDENCIAL_VACUNACION: &str = "VACUNACION";
+pub const CREDENCIAL_SEGURO: &str = "SEGURO";
+pub const CREDENCIAL_OTRA: &str = "OTRA";
+pub const TIPOS_CREDENCIAL: [&str; 5] =
+    [CREDENCIAL_TITULO, CREDENCIAL_RNPI, CREDENCIAL_VACUNACION, CREDENCIAL_SEGURO, CREDENCIAL_OTRA];
+pub const CREDENCIALES_OBLIGATORIAS: [&str; 4] =
+    [CREDENCIAL_TITULO, CREDENCIAL_RNPI, CREDENCIAL_VACUNACION, CREDENCIAL_SEGURO];
+
+/// Cómo se nombra el tipo en los avisos al profesional.
+pub fn nombre_credencial(tipo: &str) -> &'static str {
+    match tipo {
+        CREDENCIAL_TITULO => "título profesional",
+        CREDENCIAL_RNPI => "inscripción en el RNPI",
+        CREDENCIAL_VACUNACION => "certificado de vacunación",
+        CREDENCIAL_SEGURO => "seguro de responsabilidad civil",
+        _ => "credencial",
+    }
+}
+
+/// Un documento del profesional. El archivo está en el almacén de
+/// adjuntos bajo su SHA-256. Una renovación se registra como una
+/// credencial nueva del mismo tipo; sin `vence_en` no vence.
+#[derive(Debug, Serialize, Deserialize, FromRow, Clone, PartialEq)]
+pub struct Credencial {
+    pub id: u32,
+    pub id_prof: u32,
+    pub tipo: String,
+    pub descripcion: Option<String>,
+    pub numero: Option<String>,
+    pub emitida_en: Option<NaiveDate>,
+    pub vence_en: Option<NaiveDate>,
+    pub nombre: String,
+    pub tipo_mime: String,
+    pub tamano: u32,
+    pub sha256: String,
+    #[serde(with = "tiempo::utc")]
+    pub registrada_en: NaiveDateTime,
+}
+
+impl Credencial {
+    /// El día de vencimiento aún es válida.
+    pub fn vigente_en(&self, fecha: NaiveDate) -> bool {
+        self.vence_en.is_none_or(|v| fecha <= v)
+    }
+
+    pub fn obligatoria(&self) -> bool {
+        CREDENCIALES_OBLIGATORIAS.contains(&self.tipo.as_str())
+    }
+}
+
+/// Campos del formulario de subida, además del `archivo`.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct CredencialInput {
+    pub tipo: String,
+    pub descripcion: Option<String>,
+    pub numero: Option<String>,
+    pub emitida_en: Option<NaiveDate>,
+    pub vence_en: Option<NaiveDate>,
+}
+
+impl TryFrom<CredencialInput> for Credencial {
+    type Error = AppError;
+
+    fn try_from(input: CredencialInput) -> Result<Self, Self::Error> {
+        let tipo = input.tipo.trim().to_uppercase();
+        if !TIPOS_CREDENCIAL.contains(&tipo.as_str()) {
+            return Err(AppError::Validation(format!("tipo debe ser uno de {}", TIPOS_CREDENCIAL.join(", "))));
+        }
+        if let (Some(emitida), Some(vence)) = (input.emitida_en, input.vence_en) && vence < emitida {
+            return Err(AppError::Validation("La fecha de vencimiento es anterior a la de emisión".into()));
+        }
+        let limpiar = |texto: Option<String>| texto.map(|t| t.trim().to_string()).filter(|t| !t.is_empty());
+        Ok(Self {
+            id: 0,
+            id_prof: 0,
+            tipo,
+            descripcion: limpiar(input.descripcion),
+            numero: limpiar(input.numero),
+            emitida_en: input.emitida_en,
+            vence_en: input.vence_en,
+            nombre: String::new(),
+            tipo_mime: String::new(),
+            tamano: 0,
+            sha256: String::new(),
+            registrada_en: tiempo::ahora(),
+        })
+    }
+}
+
+/// Criterios de búsqueda de credenciales.
+#[derive(Debug, Default, Deserialize)]
+pub struct CredencialFiltro {
+    pub id_prof: Option<u32>,
+    pub tipo: Option<String>,
+}
+
+/// Aviso de vencimiento ya enviado; cada umbral se avisa una sola vez por
+/// credencial.
+#[derive(Debug, Serialize, Deserialize, FromRow, Clone, PartialEq)]
+pub struct AlertaCredencial {
+    pub id_credencial: u32,
+    pub dias: u16,
+    #[serde(with = "tiempo::utc")]
+    pub enviada_en: NaiveDateTime,
+}
+
+/// Credencial vigente (la última de su tipo) que vence dentro del plazo
+/// consultado o ya venció; `dias` es negativo si está vencida.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct CredencialPorVencer {
+    #[serde(flatten)]
+    pub credencial: Credencial,
+    pub profesional: String,
+    pub dias: i64,
+}
+
+/// Criterios de `GET /api/credenciales/por-vencer`: las que vencen en los
+/// próximos `dias` (30 por defecto).
+#[derive(Debug, Default, Deserialize)]
+pub struct PorVencerFiltro {
+    pub dias: Option<u32>,
+    pub id_prof: Option<u32>,
+}
@@ -0,0 +1,65 @@
+use chrono::NaiveDateTime;
+use serde::{Deserialize, Serialize};
+use mysql_async::prelude::FromRow;
+
+use crate::tiempo;
+use super::{Diagnostico, Paciente};
+
+pub const DERIVACION_PENDIENTE: &str = "PENDIENTE";
+
+/// Prioridades de FHIR (`routine`, `urgent`, `asap`, `stat`) en castellano.
+pub const PRIORIDAD_RUTINA: &str = "RUTINA";
+pub const PRIORIDAD_URGENTE: &str = "URGENTE";
+pub const PRIORIDAD_PRONTO: &str = "PRONTO";
+pub const PRIORIDAD_INMEDIATA: &str = "INMEDIATA";
+
+/// Paciente derivado por un establecimiento externo, recibido como
+/// `ServiceRequest` FHIR. `referencia_externa` es el identificador de la
+/// solicitud en el establecimiento (`sistema|valor`) y evita registrarla dos
+/// veces si reenvían el mismo Bundle. Fechas en UTC.
+#[derive(Debug, Serialize, Deserialize, FromRow, Clone)]
+pub struct Derivacion {
+    pub id: u32,
+    pub id_paciente: u32,
+    pub origen: Option<String>,
+    pub referencia_externa: Option<String>,
+    pub especialidad: String,
+    pub prioridad: String,
+    pub nota: Option<String>,
+    pub estado: String,
+    #[serde(with = "tiempo::utc")]
+    pub recibida_en: NaiveDateTime,
+}
+
+/// Derivación con los diagnósticos (`Condition`) que la motivan.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct DerivacionDetalle {
+    #[serde(flatten)]
+    pub derivacion: Derivacion,
+    pub diagnosticos: Vec<Diagnostico>,
+}
+
+/// Criterios de búsqueda para `GET /api/derivaciones`.
+#[derive(Debug, Default, Deserialize)]
+pub struct DerivacionFiltro {
+    pub id_paciente: Option<u32>,
+    pub estado: Option<String>,
+}
+
+/// Lo que trae un Bundle de derivación, ya validado. `derivaciones` indica
+/// la posición de su paciente en `pacientes`, cuyo id aún no se conoce.
+#[derive(Debug, Default, Clone)]
+pub struct Importacion {
+    pub pacientes: Vec<Paciente>,
+    pub derivaciones: Vec<(usize, DerivacionDetalle)>,
+}
+
+/// Ids asignados al importar, en el mismo orden, y si se crearon (`true`) o
+/// ya existían. `rechazadas` son las posiciones de las derivaciones cuya
+/// referencia ya es de otro paciente; si hay alguna no se importa nada.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct ResultadoImportacion {
+    pub pacientes: Vec<(u32, bool)>,
+    pub derivaciones: Vec<(u32, bool)>,
+    pub rechazadas: Vec<usize>,
+}
@@ -0,0 +1,116 @@
+use std::collections::BTreeMap;
+
+use chrono::NaiveDateTime;
+use serde::{Deserialize, Serialize};
+use mysql_async::prelude::FromRow;
+
+use crate::{error::AppError, tiempo};
+
+pub const DOCUMENTO_CERTIFICADO: &str = "CERTIFICADO";
+pub const DOCUMENTO_INFORME: &str = "INFORME";
+pub const DOCUMENTO_INTERCONSULTA: &str = "INTERCONSULTA";
+pub const TIPOS_DOCUMENTO: [&str; 3] = [DOCUMENTO_CERTIFICADO, DOCUMENTO_INFORME, DOCUMENTO_INTERCONSULTA];
+
+/// Plantilla de un tipo de documento. Cada cambio crea una versión nueva;
+/// la vigente es la de mayor versión. El cuerpo usa `{{clave}}` para los
+/// datos y `#`/`##` al inicio de línea para títulos.
+#[derive(Debug, Serialize, Deserialize, FromRow, Clone)]
+pub struct Plantilla {
+    pub id: u32,
+    pub tipo: String,
+    pub version: u16,
+    pub titulo: String,
+    pub cuerpo: String,
+    #[serde(with = "tiempo::utc")]
+    pub creada_en: NaiveDateTime,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PlantillaInput {
+    pub titulo: String,
+    pub cuerpo: String,
+}
+
+/// Certificado, informe o interconsulta emitido a un paciente. El folio es
+/// correlativo por cliente (`cod_cliente` del paciente).
+#[derive(Debug, Serialize, Deserialize, FromRow, Clone)]
+pub struct DocumentoClinico {
+    pub id: u32,
+    pub cod_cliente: u8,
+    pub folio: u32,
+    pub tipo: String,
+    pub id_paciente: u32,
+    pub id_prof: u32,
+    pub id_atencion: Option<u32>,
+    #[serde(with = "tiempo::utc")]
+    pub creado_en: NaiveDateTime,
+}
+
+/// Contenido de una versión ya fusionado con los datos; el PDF se genera
+/// desde aquí, por lo que una versión antigua se reimprime tal cual.
+#[derive(Debug, Serialize, Deserialize, FromRow, Clone)]
+pub struct VersionDocumento {
+    pub id_documento: u32,
+    pub version: u16,
+    pub id_plantilla: u32,
+    pub id_prof: u32,
+    pub titulo: String,
+    pub contenido: String,
+    pub huella: String,
+    #[serde(with = "tiempo::utc")]
+    pub creada_en: NaiveDateTime,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct DocumentoDetalle {
+    #[serde(flatten)]
+    pub documento: DocumentoClinico,
+    pub versiones: Vec<VersionDocumento>,
+}
+
+/// `campos` completa lo que no sale de la ficha (ej. `especialidad_destino`
+/// en una interconsulta) y se usa en la plantilla como `{{campos.clave}}`.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct DocumentoInput {
+    pub tipo: String,
+    pub id_paciente: u32,
+    pub id_prof: u32,
+    pub id_atencion: Option<u32>,
+    #[serde(default)]
+    pub campos: BTreeMap<String, String>,
+}
+
+/// Nueva versión de un documento: se vuelve a fusionar con la plantilla
+/// vigente y los datos actuales.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct VersionInput {
+    pub id_prof: u32,
+    #[serde(default)]
+    pub campos: BTreeMap<String, String>,
+}
+
+/// Criterios de búsqueda para `GET /api/documentos`.
+#[derive(Debug, Default, Deserialize)]
+pub struct DocumentoFiltro {
+    pub id_paciente: Option<u32>,
+    pub id_prof: Option<u32>,
+    pub tipo: Option<String>,
+    pub cod_cliente: Option<u8>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub struct VersionFiltro {
+    pub version: Option<u16>,
+}
+
+/// Tipo de documento en mayúsculas, si es uno de los conocidos.
+pub fn tipo_documento(tipo: &str) -> Result<String, AppError> {
+    let tipo = tipo.trim().to_uppercase();
+    if TIPOS_DOCUMENTO.contains(&tipo.as_str()) {
+        Ok(tipo)
+    } else {
+        Err(AppError::Validation(format!(
+            "Tipo de documento inválido: {} (use {})", tipo, TIPOS_DOCUMENTO.join(", ")
+        )))
+    }
+}
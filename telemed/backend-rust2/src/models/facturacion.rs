@@ -0,0 +1,182 @@
+use chrono::{NaiveDate, NaiveDateTime};
+use serde::{Deserialize, Serialize};
+use mysql_async::prelude::FromRow;
+
+use crate::{error::AppError, tiempo};
+
+/// A quién se cobra una atención: a la previsión del paciente (con copago)
+/// o al programa del cliente que lo atiende.
+pub const PAGADOR_PREVISION: &str = "PREVISION";
+pub const PAGADOR_CLIENTE: &str = "CLIENTE";
+pub const PAGADORES: [&str; 2] = [PAGADOR_PREVISION, PAGADOR_CLIENTE];
+
+/// `PENDIENTE` espera lote; `OBSERVADO` no se pudo valorizar (falta
+/// previsión o tarifa) y se recalcula cuando se corrija; `FACTURADO` ya
+/// está en un lote y no cambia.
+pub const CARGO_PENDIENTE: &str = "PENDIENTE";
+pub const CARGO_OBSERVADO: &str = "OBSERVADO";
+pub const CARGO_FACTURADO: &str = "FACTURADO";
+
+/// Valor de una prestación para una previsión (un tramo FONASA o una
+/// ISAPRE) o para un cliente. `cobertura` es el porcentaje que paga la
+/// previsión; el resto es copago. Sin `modalidad` aplica a cualquiera.
+/// Montos en pesos.
+#[derive(Debug, Serialize, Deserialize, FromRow, Clone)]
+pub struct Tarifa {
+    pub id: u32,
+    pub codigo_prestacion: String,
+    pub descripcion: String,
+    pub modalidad: Option<String>,
+    pub cod_prevision: Option<u32>,
+    pub cod_cliente: Option<u8>,
+    pub valor: u32,
+    pub cobertura: u8,
+    pub vigencia_desde: NaiveDate,
+    pub vigencia_hasta: Option<NaiveDate>,
+}
+
+impl Tarifa {
+    pub fn vigente_en(&self, fecha: NaiveDate) -> bool {
+        self.vigencia_desde <= fecha && self.vigencia_hasta.is_none_or(|h| fecha <= h)
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TarifaInput {
+    pub codigo_prestacion: String,
+    pub descripcion: String,
+    pub modalidad: Option<String>,
+    pub cod_prevision: Option<u32>,
+    pub cod_cliente: Option<u8>,
+    pub valor: u32,
+    /// Por omisión la previsión paga todo; a un cliente siempre se le cobra todo.
+    #[serde(default = "cobertura_total")]
+    pub cobertura: u8,
+    pub vigencia_desde: NaiveDate,
+    pub vigencia_hasta: Option<NaiveDate>,
+}
+
+fn cobertura_total() -> u8 {
+    100
+}
+
+/// Criterios de búsqueda para `GET /api/facturacion/tarifas`.
+#[derive(Debug, Default, Deserialize)]
+pub struct TarifaFiltro {
+    pub codigo_prestacion: Option<String>,
+    pub cod_prevision: Option<u32>,
+    pub cod_cliente: Option<u8>,
+    pub vigente_en: Option<NaiveDate>,
+}
+
+impl TryFrom<TarifaInput> for Tarifa {
+    type Error = AppError;
+
+    fn try_from(input: TarifaInput) -> Result<Self, Self::Error> {
+        let codigo_prestacion = input.codigo_prestacion.trim().to_uppercase();
+        if codigo_prestacion.is_empty() || input.descripcion.trim().is_empty() {
+            return Err(AppError::Validation("La tarifa requiere código de prestación y descripción".into()));
+        }
+
+        let modalidad = input.modalidad.map(|m| m.trim().to_uppercase());
+        if let Some(m) = &modalidad && !super::MODALIDADES.contains(&m.as_str()) {
+            return Err(AppError::Validation(format!("Modalidad inválida: {}", m)));
+        }
+
+        let cobertura = match (input.cod_prevision, input.cod_cliente) {
+            (Some(_), None) if input.cobertura <= 100 => input.cobertura,
+            (Some(_), None) => return Err(AppError::Validation("La cobertura es un porcentaje entre 0 y 100".into())),
+            (None, Some(_)) => 100,
+            _ => return Err(AppError::Validation("La tarifa es de una previsión o de un cliente".into())),
+        };
+
+        if let Some(hasta) = input.vigencia_hasta && hasta < input.vigencia_desde {
+            return Err(AppError::Validation("vigencia_hasta es anterior a vigencia_desde".into()));
+        }
+
+        Ok(Self {
+            id: 0,
+            codigo_prestacion,
+            descripcion: input.descripcion.trim().to_string(),
+            modalidad,
+            cod_prevision: input.cod_prevision,
+            cod_cliente: input.cod_cliente,
+            valor: input.valor,
+            cobertura,
+            vigencia_desde: input.vigencia_desde,
+            vigencia_hasta: input.vigencia_hasta,
+        })
+    }
+}
+
+/// Cobro de una atención firmada, uno por atención. `fecha` es el día local
+/// de la atención y define el período en que se factura.
+#[derive(Debug, Serialize, Deserialize, FromRow, Clone, PartialEq)]
+pub struct Cargo {
+    pub id: u32,
+    pub id_atencion: u32,
+    pub id_paciente: u32,
+    pub fecha: NaiveDate,
+    pub codigo_prestacion: Option<String>,
+    pub pagador: Option<String>,
+    pub cod_prevision: Option<u32>,
+    pub cod_cliente: Option<u8>,
+    pub valor: u32,
+    pub monto_pagador: u32,
+    pub copago: u32,
+    pub estado: String,
+    pub observacion: Option<String>,
+    pub id_lote: Option<u32>,
+    #[serde(with = "tiempo::utc")]
+    pub generado_en: NaiveDateTime,
+}
+
+/// Criterios de búsqueda para `GET /api/facturacion/cargos`.
+#[derive(Debug, Default, Deserialize)]
+pub struct CargoFiltro {
+    pub id_paciente: Option<u32>,
+    pub estado: Option<String>,
+    pub pagador: Option<String>,
+    pub cod_prevision: Option<u32>,
+    pub cod_cliente: Option<u8>,
+    pub desde: Option<NaiveDate>,
+    pub hasta: Option<NaiveDate>,
+    pub id_lote: Option<u32>,
+}
+
+/// Cargos de un período (`AAAA-MM`) agrupados para cobrarlos a un pagador.
+#[derive(Debug, Serialize, Deserialize, FromRow, Clone)]
+pub struct LoteFacturacion {
+    pub id: u32,
+    pub periodo: String,
+    pub pagador: String,
+    pub cod_prevision: Option<u32>,
+    pub cod_cliente: Option<u8>,
+    pub cantidad: u32,
+    pub total_valor: u64,
+    pub total_pagador: u64,
+    pub total_copago: u64,
+    #[serde(with = "tiempo::utc")]
+    pub creado_en: NaiveDateTime,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct LoteInput {
+    pub periodo: String,
+    pub pagador: String,
+    pub cod_prevision: Option<u32>,
+    pub cod_cliente: Option<u8>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub struct LoteFiltro {
+    pub periodo: Option<String>,
+    pub pagador: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct LoteDetalle {
+    #[serde(flatten)]
+    pub lote: LoteFacturacion,
+    pub cargos: Vec<Cargo>,
+}
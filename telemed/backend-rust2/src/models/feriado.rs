@@ -0,0 +1,47 @@
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+use mysql_async::prelude::FromRow;
+
+use crate::error::AppError;
+
+/// Día feriado. Los definidos por ley se calculan en `feriados` y se
+/// informan con `id = 0`; los agregados por un administrador (elecciones,
+/// feriados locales nuevos) viven en la tabla `feriados`.
+/// Sin `cod_zona` el feriado es nacional.
+#[derive(Debug, Serialize, Deserialize, FromRow, Clone)]
+pub struct Feriado {
+    pub id: u32,
+    pub fecha: NaiveDate,
+    pub nombre: String,
+    pub cod_zona: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct FeriadoInput {
+    pub fecha: NaiveDate,
+    pub nombre: String,
+    pub cod_zona: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct FeriadoFiltro {
+    pub anio: i32,
+    pub cod_zona: Option<String>,
+}
+
+impl TryFrom<FeriadoInput> for Feriado {
+    type Error = AppError;
+
+    fn try_from(input: FeriadoInput) -> Result<Self, Self::Error> {
+        if input.nombre.trim().is_empty() {
+            return Err(AppError::Validation("El nombre del feriado es obligatorio".into()));
+        }
+
+        Ok(Self {
+            id: 0,
+            fecha: input.fecha,
+            nombre: input.nombre.trim().to_string(),
+            cod_zona: input.cod_zona,
+        })
+    }
+}
@@ -0,0 +1,38 @@
+use chrono::NaiveDateTime;
+
+/// Registro con la fecha de su última modificación, que FHIR publica como
+/// `meta.lastUpdated`.
+#[derive(Debug, Clone)]
+pub struct Actualizado<T> {
+    pub registro: T,
+    pub actualizado_en: NaiveDateTime,
+}
+
+/// Una página de resultados y el total de coincidencias.
+#[derive(Debug, Clone)]
+pub struct PaginaFhir<T> {
+    pub total: u32,
+    pub registros: Vec<Actualizado<T>>,
+}
+
+impl<T> Default for PaginaFhir<T> {
+    fn default() -> Self {
+        Self { total: 0, registros: Vec::new() }
+    }
+}
+
+/// Búsqueda FHIR ya interpretada; los criterios se combinan con AND.
+/// `rut` viene normalizado y el rango de actualización es
+/// `[actualizado_desde, actualizado_hasta)`.
+#[derive(Debug, Default, Clone)]
+pub struct ConsultaFhir {
+    pub ids: Option<Vec<String>>,
+    pub rut: Option<String>,
+    pub rnpi: Option<String>,
+    pub actualizado_desde: Option<NaiveDateTime>,
+    pub actualizado_hasta: Option<NaiveDateTime>,
+    /// Sólo pacientes (o atenciones de pacientes) con este consentimiento vigente.
+    pub consentimiento: Option<String>,
+    pub cantidad: u32,
+    pub salto: u32,
+}
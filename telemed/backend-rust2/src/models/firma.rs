@@ -0,0 +1,92 @@
+use chrono::NaiveDateTime;
+use serde::{Deserialize, Serialize};
+use mysql_async::prelude::FromRow;
+
+use crate::{error::AppError, tiempo};
+
+pub const FIRMA_RECETA: &str = "RECETA";
+pub const FIRMA_DOCUMENTO: &str = "DOCUMENTO";
+pub const TIPOS_FIRMA: [&str; 2] = [FIRMA_RECETA, FIRMA_DOCUMENTO];
+
+/// Par de claves Ed25519 de un profesional. La privada se guarda cifrada
+/// con la clave maestra del servidor y nunca sale de él. Generar una clave
+/// nueva revoca la anterior; sus firmas siguen verificándose.
+#[derive(Debug, Serialize, Deserialize, FromRow, Clone)]
+pub struct ClaveFirma {
+    pub id: u32,
+    pub id_prof: u32,
+    pub clave_publica: String,
+    #[serde(skip)]
+    pub clave_privada: String,
+    #[serde(skip)]
+    pub nonce: String,
+    #[serde(with = "tiempo::utc")]
+    pub creada_en: NaiveDateTime,
+    #[serde(with = "tiempo::utc::opcional")]
+    pub revocada_en: Option<NaiveDateTime>,
+}
+
+/// Firma separada de una receta o de una versión de documento (`version` es
+/// 0 en las recetas). `huella` es el SHA-256 del contenido canónico y
+/// `firma` la firma Ed25519, en hexadecimal, de `firma::mensaje`.
+#[derive(Debug, Serialize, Deserialize, FromRow, Clone)]
+pub struct Firma {
+    pub id: u32,
+    pub tipo: String,
+    pub id_documento: u32,
+    pub version: u16,
+    pub id_clave: u32,
+    pub id_prof: u32,
+    pub rut_firmante: String,
+    pub rnpi_firmante: String,
+    #[serde(with = "tiempo::utc")]
+    pub firmada_en: NaiveDateTime,
+    pub huella: String,
+    pub firma: String,
+}
+
+/// Sin `version` se firma la última versión del documento.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SolicitudFirma {
+    pub tipo: String,
+    pub id_documento: u32,
+    pub version: Option<u16>,
+    pub id_prof: u32,
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub struct FirmaFiltro {
+    pub tipo: Option<String>,
+    pub id_documento: Option<u32>,
+    pub id_prof: Option<u32>,
+}
+
+/// Resultado de verificar una firma contra el contenido actual.
+/// `contenido_integro` es falso si el documento cambió después de firmado.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct VerificacionFirma {
+    pub valida: bool,
+    pub firma_valida: bool,
+    pub contenido_integro: bool,
+    pub id_firma: u32,
+    pub tipo: String,
+    pub id_documento: u32,
+    pub version: u16,
+    pub firmante: String,
+    pub rut_firmante: String,
+    pub rnpi_firmante: String,
+    #[serde(with = "tiempo::utc")]
+    pub firmada_en: NaiveDateTime,
+    pub huella_firmada: String,
+    pub huella_actual: Option<String>,
+    pub clave_publica: String,
+}
+
+pub fn tipo_firma(tipo: &str) -> Result<String, AppError> {
+    let tipo = tipo.trim().to_uppercase();
+    if TIPOS_FIRMA.contains(&tipo.as_str()) {
+        Ok(tipo)
+    } else {
+        Err(AppError::Validation(format!("Tipo de firma inválido: {} (use {})", tipo, TIPOS_FIRMA.join(", "))))
+    }
+}
@@ -0,0 +1,117 @@
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+use mysql_async::prelude::FromRow;
+
+use crate::error::AppError;
+
+/// Trabajo que se paga a honorarios: una atención firmada o una visita
+/// domiciliaria realizada.
+pub const HONORARIO_ATENCION: &str = "ATENCION";
+pub const HONORARIO_VISITA: &str = "VISITA";
+pub const TIPOS_HONORARIO: [&str; 2] = [HONORARIO_ATENCION, HONORARIO_VISITA];
+
+/// Monto que se paga por cada atención o visita. Sin `especialidad` o sin
+/// `cod_zona` aplica a todas; la más específica prevalece. Pesos brutos.
+#[derive(Debug, Serialize, Deserialize, FromRow, Clone)]
+pub struct TarifaHonorario {
+    pub id: u32,
+    pub tipo: String,
+    pub especialidad: Option<String>,
+    pub cod_zona: Option<String>,
+    pub monto: u32,
+    pub vigencia_desde: NaiveDate,
+    pub vigencia_hasta: Option<NaiveDate>,
+}
+
+impl TarifaHonorario {
+    pub fn vigente_en(&self, fecha: NaiveDate) -> bool {
+        self.vigencia_desde <= fecha && self.vigencia_hasta.is_none_or(|h| fecha <= h)
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TarifaHonorarioInput {
+    pub tipo: String,
+    pub especialidad: Option<String>,
+    pub cod_zona: Option<String>,
+    pub monto: u32,
+    pub vigencia_desde: NaiveDate,
+    pub vigencia_hasta: Option<NaiveDate>,
+}
+
+impl TryFrom<TarifaHonorarioInput> for TarifaHonorario {
+    type Error = AppError;
+
+    fn try_from(input: TarifaHonorarioInput) -> Result<Self, Self::Error> {
+        let tipo = input.tipo.trim().to_uppercase();
+        if !TIPOS_HONORARIO.contains(&tipo.as_str()) {
+            return Err(AppError::Validation(format!("Tipo de honorario inválido: {}", tipo)));
+        }
+        if let Some(hasta) = input.vigencia_hasta && hasta < input.vigencia_desde {
+            return Err(AppError::Validation("vigencia_hasta es anterior a vigencia_desde".into()));
+        }
+        let opcional = |s: Option<String>| s.map(|s| s.trim().to_string()).filter(|s| !s.is_empty());
+
+        Ok(Self {
+            id: 0,
+            tipo,
+            especialidad: opcional(input.especialidad),
+            cod_zona: opcional(input.cod_zona),
+            monto: input.monto,
+            vigencia_desde: input.vigencia_desde,
+            vigencia_hasta: input.vigencia_hasta,
+        })
+    }
+}
+
+/// Criterios de búsqueda para `GET /api/honorarios/tarifas`.
+#[derive(Debug, Default, Deserialize)]
+pub struct TarifaHonorarioFiltro {
+    pub tipo: Option<String>,
+    pub vigente_en: Option<NaiveDate>,
+}
+
+/// Tasa de retención de las boletas de honorarios desde una fecha, en
+/// centésimas de punto (1450 = 14,5 %). La ley la sube cada año.
+#[derive(Debug, Serialize, Deserialize, FromRow, Clone)]
+pub struct RetencionHonorarios {
+    pub vigencia_desde: NaiveDate,
+    pub tasa: u16,
+}
+
+/// Una atención o visita de la liquidación. Sin tarifa aplicable va en cero
+/// con `observacion`.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct PrestacionHonorario {
+    pub tipo: String,
+    pub id_origen: u32,
+    pub fecha: NaiveDate,
+    pub cod_zona: Option<String>,
+    pub monto: u32,
+    pub observacion: Option<String>,
+}
+
+/// Liquidación mensual de un profesional.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Liquidacion {
+    pub periodo: String,
+    pub id_prof: u32,
+    pub rut: String,
+    pub nombre: String,
+    pub especialidad: Option<String>,
+    pub atenciones: u32,
+    pub visitas: u32,
+    pub sin_tarifa: u32,
+    pub bruto: u64,
+    pub tasa_retencion: u16,
+    pub retencion: u64,
+    pub liquido: u64,
+    pub prestaciones: Vec<PrestacionHonorario>,
+}
+
+/// Criterios para `GET /api/honorarios/liquidaciones`; el período es `AAAA-MM`.
+#[derive(Debug, Deserialize)]
+pub struct LiquidacionFiltro {
+    pub periodo: String,
+    pub id_prof: Option<u32>,
+}
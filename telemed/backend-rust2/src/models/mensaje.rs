@@ -0,0 +1,176 @@
+use chrono::NaiveDateTime;
+use serde::{Deserialize, Serialize};
+use mysql_async::prelude::FromRow;
+
+use crate::{error::AppError, tiempo};
+
+pub const PARTICIPANTE_PACIENTE: &str = "PACIENTE";
+pub const PARTICIPANTE_PROFESIONAL: &str = "PROFESIONAL";
+
+/// Conversación entre un paciente y los profesionales que lo atienden.
+/// Hereda cliente y zona del paciente al crearse; sólo la ven los
+/// profesionales de esa zona (o sin zona asignada). Los mensajes más
+/// antiguos que `retencion_dias` se eliminan.
+#[derive(Debug, Serialize, Deserialize, FromRow, Clone)]
+pub struct Conversacion {
+    pub id: u32,
+    pub id_paciente: u32,
+    pub asunto: String,
+    pub cod_cliente: Option<u8>,
+    pub cod_zona: Option<String>,
+    pub retencion_dias: u16,
+    #[serde(with = "tiempo::utc")]
+    pub creada_en: NaiveDateTime,
+    #[serde(with = "tiempo::utc::opcional")]
+    pub ultimo_mensaje_en: Option<NaiveDateTime>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ConversacionInput {
+    pub id_paciente: u32,
+    pub asunto: String,
+    pub retencion_dias: Option<u16>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ConversacionResumen {
+    #[serde(flatten)]
+    pub conversacion: Conversacion,
+    pub no_leidos: u32,
+}
+
+/// Quién escribe o lee: el paciente o un profesional. En consultas y
+/// cuerpos se indica con `id_paciente` o `id_prof`, uno de los dos.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct Participante {
+    pub tipo: String,
+    pub id: u32,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct Lector {
+    pub id_paciente: Option<u32>,
+    pub id_prof: Option<u32>,
+}
+
+impl Lector {
+    pub fn participante(&self) -> Result<Participante, AppError> {
+        match (self.id_paciente, self.id_prof) {
+            (Some(id), None) => Ok(Participante { tipo: PARTICIPANTE_PACIENTE.into(), id }),
+            (None, Some(id)) => Ok(Participante { tipo: PARTICIPANTE_PROFESIONAL.into(), id }),
+            _ => Err(AppError::Validation("Indique id_paciente o id_prof, uno de los dos".into())),
+        }
+    }
+}
+
+/// Archivo enviado en un mensaje. `referencia` apunta al archivo ya subido.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct AdjuntoMensaje {
+    pub nombre: String,
+    pub referencia: String,
+}
+
+/// Texto y adjuntos de un mensaje; es lo que se guarda cifrado.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct ContenidoMensaje {
+    pub texto: String,
+    #[serde(default)]
+    pub adjuntos: Vec<AdjuntoMensaje>,
+}
+
+/// Mensaje tal como se guarda: `contenido` es un `ContenidoMensaje` en
+/// JSON cifrado con la clave maestra.
+#[derive(Debug, Serialize, Deserialize, FromRow, Clone)]
+pub struct MensajeCifrado {
+    pub id: u32,
+    pub id_conversacion: u32,
+    pub autor_tipo: String,
+    pub autor_id: u32,
+    pub nonce: String,
+    pub contenido: String,
+    pub enviado_en: NaiveDateTime,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct Mensaje {
+    pub id: u32,
+    pub id_conversacion: u32,
+    pub autor: Participante,
+    #[serde(flatten)]
+    pub contenido: ContenidoMensaje,
+    #[serde(with = "tiempo::utc")]
+    pub enviado_en: NaiveDateTime,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct MensajeInput {
+    #[serde(flatten)]
+    pub autor: Lector,
+    #[serde(flatten)]
+    pub contenido: ContenidoMensaje,
+}
+
+/// Hasta qué mensaje leyó cada participante. Los no leídos son los de
+/// otros autores posteriores a `hasta`.
+#[derive(Debug, Serialize, Deserialize, FromRow, Clone, PartialEq)]
+pub struct Lectura {
+    pub id_conversacion: u32,
+    pub lector_tipo: String,
+    pub lector_id: u32,
+    pub hasta: u32,
+    #[serde(with = "tiempo::utc")]
+    pub leido_en: NaiveDateTime,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct LecturaInput {
+    #[serde(flatten)]
+    pub lector: Lector,
+    pub hasta: u32,
+}
+
+/// Página del historial, del más antiguo al más reciente. Para la página
+/// anterior se pide `antes_de` con el valor de `siguiente`.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct HistorialMensajes {
+    pub mensajes: Vec<Mensaje>,
+    pub lecturas: Vec<Lectura>,
+    pub siguiente: Option<u32>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub struct HistorialFiltro {
+    pub id_paciente: Option<u32>,
+    pub id_prof: Option<u32>,
+    pub antes_de: Option<u32>,
+    pub limite: Option<u16>,
+}
+
+impl HistorialFiltro {
+    pub fn lector(&self) -> Lector {
+        Lector { id_paciente: self.id_paciente, id_prof: self.id_prof }
+    }
+}
+
+/// Consulta de `GET /api/conversaciones`.
+#[derive(Debug, Default, Deserialize)]
+pub struct BandejaFiltro {
+    pub id_paciente: Option<u32>,
+    pub id_prof: Option<u32>,
+    pub cod_cliente: Option<u8>,
+}
+
+impl BandejaFiltro {
+    pub fn lector(&self) -> Lector {
+        Lector { id_paciente: self.id_paciente, id_prof: self.id_prof }
+    }
+}
+
+/// Conversaciones visibles para el participante. Para un profesional con
+/// zona, sólo las de los códigos de `zonas_acceso` que llevan ese nombre.
+#[derive(Debug, Default, Deserialize)]
+pub struct ConversacionFiltro {
+    pub id_paciente: Option<u32>,
+    pub zonas: Option<Vec<String>>,
+    pub cod_cliente: Option<u8>,
+}
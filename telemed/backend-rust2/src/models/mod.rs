@@ -0,0 +1,53 @@
+mod usuario;
+mod paciente;
+mod prevision;
+mod profesional;
+mod agenda;
+mod zona;
+mod feriado;
+mod atencion;
+mod cie10;
+mod visita;
+mod receta;
+mod documento;
+mod firma;
+mod teleconsulta;
+mod sala_espera;
+mod mensaje;
+mod notificacion;
+mod recordatorio;
+mod adjunto;
+mod fhir;
+mod derivacion;
+mod consentimiento;
+mod facturacion;
+mod honorario;
+mod contrato;
+mod credencial;
+
+pub use usuario::*;
+pub use paciente::*;
+pub use prevision::*;
+pub use profesional::*;
+pub use agenda::*;
+pub use zona::*;
+pub use feriado::*;
+pub use atencion::*;
+pub use cie10::*;
+pub use visita::*;
+pub use receta::*;
+pub use documento::*;
+pub use firma::*;
+pub use teleconsulta::*;
+pub use sala_espera::*;
+pub use mensaje::*;
+pub use notificacion::*;
+pub use recordatorio::*;
+pub use adjunto::*;
+pub use fhir::*;
+pub use derivacion::*;
+pub use consentimiento::*;
+pub use facturacion::*;
+pub use honorario::*;
+pub use contrato::*;
+pub use credencial::*;
@@ -0,0 +1,110 @@
+use std::collections::HashMap;
+
+use chrono::NaiveDateTime;
+use serde::{Deserialize, Serialize};
+use mysql_async::prelude::FromRow;
+
+use crate::{error::AppError, tiempo};
+
+pub const CANAL_EMAIL: &str = "EMAIL";
+pub const CANAL_SMS: &str = "SMS";
+pub const CANAL_APP: &str = "APP";
+pub const CANALES: [&str; 3] = [CANAL_EMAIL, CANAL_SMS, CANAL_APP];
+
+pub const DESTINATARIO_USUARIO: &str = "USUARIO";
+pub const DESTINATARIO_PACIENTE: &str = "PACIENTE";
+pub const DESTINATARIO_PROFESIONAL: &str = "PROFESIONAL";
+pub const DESTINATARIOS: [&str; 3] = [DESTINATARIO_USUARIO, DESTINATARIO_PACIENTE, DESTINATARIO_PROFESIONAL];
+
+pub const NOTIFICACION_PENDIENTE: &str = "PENDIENTE";
+pub const NOTIFICACION_ENVIADA: &str = "ENVIADA";
+pub const NOTIFICACION_FALLIDA: &str = "FALLIDA";
+pub const NOTIFICACION_LEIDA: &str = "LEIDA";
+
+/// Valida el tipo de destinatario y lo normaliza a mayúsculas.
+pub fn tipo_destinatario(tipo: &str) -> Result<String, AppError> {
+    let tipo = tipo.trim().to_uppercase();
+    if !DESTINATARIOS.contains(&tipo.as_str()) {
+        return Err(AppError::Validation(format!("Destinatario debe ser uno de {:?}", DESTINATARIOS)));
+    }
+    Ok(tipo)
+}
+
+/// Canales por los que acepta avisos un usuario, paciente o profesional.
+/// Sin preferencias guardadas se usan todos los canales con dirección.
+#[derive(Debug, Serialize, Deserialize, FromRow, Clone, PartialEq)]
+pub struct PreferenciaNotificacion {
+    pub destinatario_tipo: String,
+    pub destinatario_id: u32,
+    pub email: bool,
+    pub sms: bool,
+    pub app: bool,
+}
+
+impl PreferenciaNotificacion {
+    pub fn por_defecto(destinatario_tipo: &str, destinatario_id: u32) -> Self {
+        Self { destinatario_tipo: destinatario_tipo.into(), destinatario_id, email: true, sms: true, app: true }
+    }
+
+    pub fn acepta(&self, canal: &str) -> bool {
+        match canal {
+            CANAL_EMAIL => self.email,
+            CANAL_SMS => self.sms,
+            CANAL_APP => self.app,
+            _ => false,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PreferenciaInput {
+    pub email: bool,
+    pub sms: bool,
+    pub app: bool,
+}
+
+/// Fila del outbox. Las de EMAIL y SMS quedan PENDIENTE hasta que el
+/// despachador las entrega (ENVIADA) o agota los reintentos (FALLIDA);
+/// las de APP quedan ENVIADA al crearse y pasan a LEIDA.
+#[derive(Debug, Serialize, Deserialize, FromRow, Clone)]
+pub struct Notificacion {
+    pub id: u32,
+    pub plantilla: String,
+    pub destinatario_tipo: String,
+    pub destinatario_id: u32,
+    pub canal: String,
+    /// Correo o teléfono; `None` en las de APP.
+    pub direccion: Option<String>,
+    pub asunto: String,
+    pub cuerpo: String,
+    pub estado: String,
+    pub intentos: u8,
+    #[serde(with = "tiempo::utc")]
+    pub proximo_intento_en: NaiveDateTime,
+    pub ultimo_error: Option<String>,
+    #[serde(with = "tiempo::utc")]
+    pub creada_en: NaiveDateTime,
+    #[serde(with = "tiempo::utc::opcional")]
+    pub enviada_en: Option<NaiveDateTime>,
+    #[serde(with = "tiempo::utc::opcional")]
+    pub leida_en: Option<NaiveDateTime>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct NotificacionInput {
+    pub plantilla: String,
+    pub destinatario_tipo: String,
+    pub destinatario_id: u32,
+    #[serde(default)]
+    pub variables: HashMap<String, String>,
+}
+
+/// Criterios de búsqueda para `GET /api/notificaciones`.
+#[derive(Debug, Default, Deserialize)]
+pub struct NotificacionFiltro {
+    pub destinatario_tipo: Option<String>,
+    pub destinatario_id: Option<u32>,
+    pub canal: Option<String>,
+    pub estado: Option<String>,
+    pub plantilla: Option<String>,
+}
@@ -0,0 +1,102 @@
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+use mysql_async::prelude::FromRow;
+
+use crate::{error::AppError, geo, rut};
+
+pub const SEXOS: [&str; 3] = ["F", "M", "O"];
+
+#[derive(Debug, Serialize, Deserialize, FromRow, Clone)]
+pub struct Paciente {
+    pub id: u32,
+    pub rut: String,
+    pub nombres: String,
+    pub ap_paterno: String,
+    pub ap_materno: Option<String>,
+    pub fecha_nacimiento: Option<NaiveDate>,
+    pub sexo: Option<String>,
+    pub cod_prevision: Option<u32>,
+    pub direccion: Option<String>,
+    pub comuna: Option<String>,
+    pub ciudad: Option<String>,
+    pub cod_zona: Option<String>,
+    /// Coordenadas geocodificadas del domicilio, para las rutas de visitas.
+    pub latitud: Option<f64>,
+    pub longitud: Option<f64>,
+    pub email: Option<String>,
+    pub telefonos: Option<String>,
+    pub cod_cliente: Option<u8>,
+    pub estatus: u8,
+}
+
+impl Paciente {
+    pub fn nombre_completo(&self) -> String {
+        super::nombre_completo(&self.nombres, &self.ap_paterno, self.ap_materno.as_deref())
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PacienteInput {
+    pub rut: String,
+    pub nombres: String,
+    pub ap_paterno: String,
+    pub ap_materno: Option<String>,
+    pub fecha_nacimiento: Option<NaiveDate>,
+    pub sexo: Option<String>,
+    pub cod_prevision: Option<u32>,
+    pub direccion: Option<String>,
+    pub comuna: Option<String>,
+    pub ciudad: Option<String>,
+    pub cod_zona: Option<String>,
+    pub latitud: Option<f64>,
+    pub longitud: Option<f64>,
+    pub email: Option<String>,
+    pub telefonos: Option<String>,
+    pub cod_cliente: Option<u8>,
+    pub estatus: u8,
+}
+
+/// Criterios de búsqueda para `GET /api/pacientes`.
+#[derive(Debug, Default, Deserialize)]
+pub struct PacienteFiltro {
+    pub rut: Option<String>,
+    pub nombre: Option<String>,
+}
+
+impl TryFrom<PacienteInput> for Paciente {
+    type Error = AppError;
+
+    fn try_from(input: PacienteInput) -> Result<Self, Self::Error> {
+        if input.nombres.trim().is_empty() || input.ap_paterno.trim().is_empty() {
+            return Err(AppError::Validation("Nombres y apellido paterno son obligatorios".into()));
+        }
+
+        let sexo = input.sexo.map(|s| s.trim().to_uppercase());
+        if let Some(s) = &sexo && !SEXOS.contains(&s.as_str()) {
+            return Err(AppError::Validation(format!("Sexo inválido: {}", s)));
+        }
+
+        geo::validar_coordenadas(input.latitud, input.longitud)?;
+
+        Ok(Self {
+            id: 0,
+            rut: rut::normalizar(&input.rut)?,
+            nombres: input.nombres.trim().to_uppercase(),
+            ap_paterno: input.ap_paterno.trim().to_uppercase(),
+            ap_materno: input.ap_materno.map(|a| a.trim().to_uppercase()),
+            fecha_nacimiento: input.fecha_nacimiento,
+            sexo,
+            cod_prevision: input.cod_prevision,
+            direccion: input.direccion,
+            comuna: input.comuna.map(|c| c.trim().to_uppercase()),
+            ciudad: input.ciudad.map(|c| c.trim().to_uppercase()),
+            cod_zona: input.cod_zona,
+            latitud: input.latitud,
+            longitud: input.longitud,
+            email: input.email.map(|e| e.trim().to_lowercase()),
+            telefonos: input.telefonos,
+            cod_cliente: input.cod_cliente,
+            estatus: input.estatus,
+        })
+    }
+}
@@ -0,0 +1,113 @@
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+use mysql_async::prelude::FromRow;
+
+use crate::error::AppError;
+
+pub const TIPOS_PREVISION: [&str; 2] = ["FONASA", "ISAPRE"];
+pub const TRAMOS_FONASA: [&str; 4] = ["A", "B", "C", "D"];
+
+/// Entrada del catálogo de previsiones: un tramo FONASA o una ISAPRE.
+#[derive(Debug, Serialize, Deserialize, FromRow, Clone)]
+pub struct Prevision {
+    pub cod_prevision: u32,
+    pub tipo: String,
+    pub nombre: String,
+    pub tramo: Option<String>,
+    pub vigencia_desde: NaiveDate,
+    pub vigencia_hasta: Option<NaiveDate>,
+}
+
+impl Prevision {
+    pub fn vigente_en(&self, fecha: NaiveDate) -> bool {
+        self.vigencia_desde <= fecha && self.vigencia_hasta.is_none_or(|h| fecha <= h)
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PrevisionInput {
+    pub cod_prevision: u32,
+    pub tipo: String,
+    pub nombre: String,
+    pub tramo: Option<String>,
+    pub vigencia_desde: NaiveDate,
+    pub vigencia_hasta: Option<NaiveDate>,
+}
+
+/// Criterios de búsqueda para `GET /api/previsiones`.
+#[derive(Debug, Default, Deserialize)]
+pub struct PrevisionFiltro {
+    pub tipo: Option<String>,
+    pub vigente_en: Option<NaiveDate>,
+}
+
+impl TryFrom<PrevisionInput> for Prevision {
+    type Error = AppError;
+
+    fn try_from(input: PrevisionInput) -> Result<Self, Self::Error> {
+        let tipo = input.tipo.trim().to_uppercase();
+        if !TIPOS_PREVISION.contains(&tipo.as_str()) {
+            return Err(AppError::Validation(format!("Tipo de previsión inválido: {}", tipo)));
+        }
+
+        let tramo = input.tramo.map(|t| t.trim().to_uppercase());
+        match (tipo.as_str(), &tramo) {
+            ("FONASA", Some(t)) if TRAMOS_FONASA.contains(&t.as_str()) => {}
+            ("FONASA", _) => {
+                return Err(AppError::Validation("FONASA requiere tramo A, B, C o D".into()));
+            }
+            (_, Some(_)) => {
+                return Err(AppError::Validation("Sólo FONASA admite tramo".into()));
+            }
+            _ => {}
+        }
+
+        if input.nombre.trim().is_empty() {
+            return Err(AppError::Validation("El nombre de la previsión es obligatorio".into()));
+        }
+
+        if let Some(hasta) = input.vigencia_hasta && hasta < input.vigencia_desde {
+            return Err(AppError::Validation("vigencia_hasta es anterior a vigencia_desde".into()));
+        }
+
+        Ok(Self {
+            cod_prevision: input.cod_prevision,
+            tipo,
+            nombre: input.nombre.trim().to_uppercase(),
+            tramo,
+            vigencia_desde: input.vigencia_desde,
+            vigencia_hasta: input.vigencia_hasta,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn input(tipo: &str, tramo: Option<&str>) -> PrevisionInput {
+        PrevisionInput {
+            cod_prevision: 1,
+            tipo: tipo.into(),
+            nombre: "Prueba".into(),
+            tramo: tramo.map(Into::into),
+            vigencia_desde: NaiveDate::from_ymd_opt(2024, 1, 1).unwrap(),
+            vigencia_hasta: NaiveDate::from_ymd_opt(2024, 12, 31),
+        }
+    }
+
+    #[test]
+    fn fonasa_exige_tramo_e_isapre_no_lo_admite() {
+        assert!(Prevision::try_from(input("fonasa", Some("b"))).is_ok());
+        assert!(Prevision::try_from(input("FONASA", None)).is_err());
+        assert!(Prevision::try_from(input("FONASA", Some("E"))).is_err());
+        assert!(Prevision::try_from(input("ISAPRE", Some("A"))).is_err());
+    }
+
+    #[test]
+    fn vigencia_incluye_ambos_extremos() {
+        let p = Prevision::try_from(input("ISAPRE", None)).unwrap();
+        assert!(p.vigente_en(NaiveDate::from_ymd_opt(2024, 12, 31).unwrap()));
+        assert!(!p.vigente_en(NaiveDate::from_ymd_opt(2025, 1, 1).unwrap()));
+    }
+}
@@ -0,0 +1,49 @@
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+use mysql_async::prelude::FromRow;
+
+/// Profesional de salud tal como viene en `paso_profesionales`.
+#[derive(Debug, Serialize, Deserialize, FromRow, Clone)]
+pub struct Profesional {
+    pub id_prof: u32,
+    pub rut: String,
+    pub nombres: String,
+    pub ap_paterno: String,
+    pub ap_materno: Option<String>,
+    pub direccion: Option<String>,
+    pub comuna: Option<String>,
+    pub ciudad: Option<String>,
+    pub email: Option<String>,
+    pub telefonos: Option<String>,
+    pub especialidad: Option<String>,
+    pub registro_rnpi: Option<String>,
+    pub fecha_ingreso: Option<NaiveDate>,
+    pub fecha_egreso: Option<NaiveDate>,
+    pub zona: Option<String>,
+    pub estado: Option<String>,
+    pub conara: Option<String>,
+}
+
+impl Profesional {
+    pub fn nombre_completo(&self) -> String {
+        nombre_completo(&self.nombres, &self.ap_paterno, self.ap_materno.as_deref())
+    }
+}
+
+/// Nombres y apellidos separados por un espacio, sin el materno si falta.
+pub fn nombre_completo(nombres: &str, ap_paterno: &str, ap_materno: Option<&str>) -> String {
+    [Some(nombres), Some(ap_paterno), ap_materno]
+        .into_iter()
+        .flatten()
+        .map(str::trim)
+        .filter(|p| !p.is_empty())
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Criterios de búsqueda para `GET /api/profesionales`.
+#[derive(Debug, Default, Deserialize)]
+pub struct ProfesionalFiltro {
+    pub especialidad: Option<String>,
+    pub zona: Option<String>,
+}
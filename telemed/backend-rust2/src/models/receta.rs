@@ -0,0 +1,145 @@
+use chrono::{NaiveDate, NaiveDateTime};
+use serde::{Deserialize, Serialize};
+use mysql_async::prelude::FromRow;
+
+use crate::{error::AppError, tiempo};
+
+pub const RECETA_EMITIDA: &str = "EMITIDA";
+pub const RECETA_ANULADA: &str = "ANULADA";
+
+const VIGENCIA_DEFECTO_DIAS: u16 = 30;
+const VIGENCIA_MAXIMA_DIAS: u16 = 180;
+
+/// Receta emitida en una atención. El RNPI del profesional se copia al
+/// emitirla; `codigo` es el que la farmacia ingresa para verificarla y
+/// `huella` el SHA-256 del contenido impreso. Una receta no se edita: sólo
+/// puede anularse.
+#[derive(Debug, Serialize, Deserialize, FromRow, Clone)]
+pub struct Receta {
+    pub id: u32,
+    pub id_atencion: u32,
+    pub id_paciente: u32,
+    pub id_prof: u32,
+    pub registro_rnpi: String,
+    pub codigo: String,
+    pub huella: String,
+    pub indicaciones: Option<String>,
+    pub estado: String,
+    pub motivo_anulacion: Option<String>,
+    #[serde(with = "tiempo::utc")]
+    pub emitida_en: NaiveDateTime,
+    pub valida_hasta: NaiveDate,
+}
+
+/// Medicamento recetado. Los campos son texto libre tal como los escribe el
+/// médico (ej. "500 mg", "cada 8 horas", "5 días").
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct LineaReceta {
+    pub medicamento: String,
+    pub dosis: String,
+    pub frecuencia: String,
+    pub duracion: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct RecetaDetalle {
+    #[serde(flatten)]
+    pub receta: Receta,
+    pub lineas: Vec<LineaReceta>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RecetaInput {
+    pub id_atencion: u32,
+    pub id_prof: u32,
+    pub indicaciones: Option<String>,
+    pub dias_vigencia: Option<u16>,
+    pub lineas: Vec<LineaReceta>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct AnulacionInput {
+    pub id_prof: u32,
+    pub motivo: String,
+}
+
+/// Criterios de búsqueda para `GET /api/recetas`.
+#[derive(Debug, Default, Deserialize)]
+pub struct RecetaFiltro {
+    pub id_paciente: Option<u32>,
+    pub id_prof: Option<u32>,
+    pub id_atencion: Option<u32>,
+    pub estado: Option<String>,
+}
+
+/// Lo que ve la farmacia al verificar un código: sin datos clínicos ni el
+/// RUT completo del paciente.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct VerificacionReceta {
+    pub codigo: String,
+    pub estado: String,
+    pub vigente: bool,
+    #[serde(with = "tiempo::utc")]
+    pub emitida_en: NaiveDateTime,
+    pub valida_hasta: NaiveDate,
+    pub profesional: String,
+    pub registro_rnpi: String,
+    pub paciente: String,
+    pub rut_paciente: String,
+    pub lineas: Vec<LineaReceta>,
+    pub huella: String,
+}
+
+impl TryFrom<RecetaInput> for RecetaDetalle {
+    type Error = AppError;
+
+    /// Valida las líneas y la vigencia. El paciente, el RNPI, el código y la
+    /// huella se completan al emitirla.
+    fn try_from(input: RecetaInput) -> Result<Self, Self::Error> {
+        if input.lineas.is_empty() {
+            return Err(AppError::Validation("La receta debe tener al menos un medicamento".into()));
+        }
+        let mut lineas = Vec::with_capacity(input.lineas.len());
+        for l in input.lineas {
+            let linea = LineaReceta {
+                medicamento: l.medicamento.trim().to_string(),
+                dosis: l.dosis.trim().to_string(),
+                frecuencia: l.frecuencia.trim().to_string(),
+                duracion: l.duracion.trim().to_string(),
+            };
+            if [&linea.medicamento, &linea.dosis, &linea.frecuencia, &linea.duracion].iter().any(|c| c.is_empty()) {
+                return Err(AppError::Validation(
+                    "Cada medicamento requiere nombre, dosis, frecuencia y duración".into(),
+                ));
+            }
+            lineas.push(linea);
+        }
+
+        let dias = input.dias_vigencia.unwrap_or(VIGENCIA_DEFECTO_DIAS);
+        if dias == 0 || dias > VIGENCIA_MAXIMA_DIAS {
+            return Err(AppError::Validation(format!(
+                "dias_vigencia debe estar entre 1 y {}", VIGENCIA_MAXIMA_DIAS
+            )));
+        }
+        // A segundos, como queda en DATETIME, para que la huella se recalcule igual
+        let emitida_en = tiempo::al_segundo(tiempo::ahora());
+
+        Ok(Self {
+            receta: Receta {
+                id: 0,
+                id_atencion: input.id_atencion,
+                id_paciente: 0,
+                id_prof: input.id_prof,
+                registro_rnpi: String::new(),
+                codigo: String::new(),
+                huella: String::new(),
+                indicaciones: input.indicaciones.map(|i| i.trim().to_string()).filter(|i| !i.is_empty()),
+                estado: RECETA_EMITIDA.into(),
+                motivo_anulacion: None,
+                emitida_en,
+                valida_hasta: emitida_en.date() + chrono::Duration::days(i64::from(dias)),
+            },
+            lineas,
+        })
+    }
+}
@@ -0,0 +1,81 @@
+use chrono::NaiveDateTime;
+use serde::{Deserialize, Serialize};
+use mysql_async::prelude::FromRow;
+
+use crate::tiempo;
+use super::Cita;
+
+pub const RECORDATORIO_24H: &str = "24H";
+pub const RECORDATORIO_2H: &str = "2H";
+
+pub const RESPUESTA_CONFIRMADA: &str = "CONFIRMADA";
+pub const RESPUESTA_CANCELADA: &str = "CANCELADA";
+
+pub const ACCION_CONFIRMAR: &str = "confirmar";
+pub const ACCION_CANCELAR: &str = "cancelar";
+
+/// Recordatorio ya enviado; cada tipo se envía una sola vez por cita.
+#[derive(Debug, Serialize, Deserialize, FromRow, Clone, PartialEq)]
+pub struct RecordatorioCita {
+    pub id_cita: u32,
+    pub tipo: String,
+    #[serde(with = "tiempo::utc")]
+    pub enviado_en: NaiveDateTime,
+}
+
+/// Lo que respondió el paciente desde el enlace del recordatorio.
+#[derive(Debug, Serialize, Deserialize, FromRow, Clone, PartialEq)]
+pub struct RespuestaCita {
+    pub id_cita: u32,
+    pub respuesta: String,
+    #[serde(with = "tiempo::utc")]
+    pub respondida_en: NaiveDateTime,
+}
+
+/// Parámetros del enlace firmado. `expira` en segundos Unix.
+#[derive(Debug, Deserialize)]
+pub struct RespuestaQuery {
+    pub accion: String,
+    pub expira: i64,
+    pub firma: String,
+}
+
+/// Cita próxima a la que se envió recordatorio y el paciente no respondió.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct CitaSinConfirmar {
+    #[serde(flatten)]
+    pub cita: Cita,
+    pub recordatorios: Vec<RecordatorioCita>,
+}
+
+/// Criterios de `GET /api/agenda/sin-confirmar`: citas de las próximas
+/// `horas` (24 por defecto).
+#[derive(Debug, Default, Deserialize)]
+pub struct SinConfirmarFiltro {
+    pub cod_zona: Option<String>,
+    pub horas: Option<u32>,
+}
+
+/// Respuestas a los recordatorios de una zona. Las tasas son porcentajes
+/// sobre las citas recordadas.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct ResumenConfirmaciones {
+    pub cod_zona: Option<String>,
+    pub recordadas: u32,
+    pub confirmadas: u32,
+    pub canceladas: u32,
+    pub sin_respuesta: u32,
+    pub tasa_confirmacion: f64,
+    pub tasa_cancelacion: f64,
+}
+
+/// Criterios de `GET /api/agenda/confirmaciones`: citas que comienzan
+/// entre `desde` y `hasta`.
+#[derive(Debug, Deserialize)]
+pub struct ResumenFiltro {
+    #[serde(with = "tiempo::utc")]
+    pub desde: NaiveDateTime,
+    #[serde(with = "tiempo::utc")]
+    pub hasta: NaiveDateTime,
+    pub cod_zona: Option<String>,
+}
@@ -0,0 +1,59 @@
+use chrono::NaiveDateTime;
+use serde::{Deserialize, Serialize};
+use mysql_async::prelude::FromRow;
+
+use crate::tiempo;
+
+pub const ESPERA_ESPERANDO: &str = "ESPERANDO";
+pub const ESPERA_LLAMADO: &str = "LLAMADO";
+pub const ESPERA_ATENDIDO: &str = "ATENDIDO";
+pub const ESPERA_AUSENTE: &str = "AUSENTE";
+pub const ESPERA_RETIRADO: &str = "RETIRADO";
+
+/// Paciente que se conectó antes de su teleconsulta. Pasa de ESPERANDO a
+/// LLAMADO cuando el profesional lo llama y a ATENDIDO al entrar a la
+/// videollamada; AUSENTE si no entra a tiempo y RETIRADO si se va.
+#[derive(Debug, Serialize, Deserialize, FromRow, Clone)]
+pub struct EntradaEspera {
+    pub id_cita: u32,
+    pub id_prof: u32,
+    pub id_paciente: u32,
+    pub estado: String,
+    #[serde(with = "tiempo::utc")]
+    pub llegada_en: NaiveDateTime,
+    #[serde(with = "tiempo::utc::opcional")]
+    pub llamada_en: Option<NaiveDateTime>,
+    #[serde(with = "tiempo::utc::opcional")]
+    pub cerrada_en: Option<NaiveDateTime>,
+}
+
+impl EntradaEspera {
+    pub fn abierta(&self) -> bool {
+        self.estado == ESPERA_ESPERANDO || self.estado == ESPERA_LLAMADO
+    }
+}
+
+/// Lugar de un paciente en la cola. `posicion` es 0 para el llamado y
+/// desde 1 para los que esperan.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct PosicionEspera {
+    pub id_cita: u32,
+    pub id_paciente: u32,
+    pub estado: String,
+    pub posicion: u32,
+    #[serde(with = "tiempo::utc")]
+    pub inicio_cita: NaiveDateTime,
+    #[serde(with = "tiempo::utc")]
+    pub llegada_en: NaiveDateTime,
+    #[serde(with = "tiempo::utc::opcional")]
+    pub llamada_en: Option<NaiveDateTime>,
+    pub espera_estimada_min: i64,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ColaEspera {
+    pub id_prof: u32,
+    #[serde(with = "tiempo::utc")]
+    pub generada_en: NaiveDateTime,
+    pub pacientes: Vec<PosicionEspera>,
+}
@@ -0,0 +1,33 @@
+use chrono::NaiveDateTime;
+use serde::{Deserialize, Serialize};
+use mysql_async::prelude::FromRow;
+
+use crate::{senalizacion::Rol, tiempo};
+
+/// Sala de video de una cita en modalidad teleconsulta. Paciente y
+/// profesional reciben cada uno un token para entrar; aquí sólo se guarda
+/// su SHA-256. `iniciada_en` es cuando ambos estuvieron conectados por
+/// primera vez y `terminada_en` cuando alguno colgó.
+#[derive(Debug, Serialize, Deserialize, FromRow, Clone)]
+pub struct Teleconsulta {
+    pub id_cita: u32,
+    #[serde(skip)]
+    pub token_paciente: String,
+    #[serde(skip)]
+    pub token_profesional: String,
+    #[serde(with = "tiempo::utc")]
+    pub creada_en: NaiveDateTime,
+    #[serde(with = "tiempo::utc::opcional")]
+    pub iniciada_en: Option<NaiveDateTime>,
+    #[serde(with = "tiempo::utc::opcional")]
+    pub terminada_en: Option<NaiveDateTime>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct EstadoTeleconsulta {
+    #[serde(flatten)]
+    pub teleconsulta: Teleconsulta,
+    pub conectados: Vec<Rol>,
+    /// Si el paciente autorizó grabar la sesión.
+    pub grabacion_consentida: bool,
+}
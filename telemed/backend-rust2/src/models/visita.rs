@@ -0,0 +1,244 @@
+use chrono::{NaiveDate, NaiveDateTime, NaiveTime};
+use serde::{Deserialize, Serialize};
+use mysql_async::prelude::FromRow;
+
+use crate::{error::AppError, geo, tiempo};
+
+pub const VISITA_PENDIENTE: &str = "PENDIENTE";
+pub const VISITA_EN_RUTA: &str = "EN_RUTA";
+pub const VISITA_REALIZADA: &str = "REALIZADA";
+pub const VISITA_FALLIDA: &str = "FALLIDA";
+
+pub const MARCA_LLEGADA: &str = "LLEGADA";
+pub const MARCA_SALIDA: &str = "SALIDA";
+
+const DURACION_DEFECTO_MIN: u16 = 30;
+
+/// Visita domiciliaria. `fecha` y la ventana horaria son locales a la zona
+/// de la visita; la dirección y sus coordenadas se copian del paciente al
+/// crearla para que la visita conserve a dónde se fue aunque el paciente se mude.
+#[derive(Debug, Serialize, Deserialize, FromRow, Clone)]
+pub struct Visita {
+    pub id: u32,
+    pub id_paciente: u32,
+    pub id_prof: Option<u32>,
+    pub cod_zona: String,
+    pub direccion: String,
+    pub comuna: Option<String>,
+    pub latitud: Option<f64>,
+    pub longitud: Option<f64>,
+    pub fecha: NaiveDate,
+    pub ventana_desde: Option<NaiveTime>,
+    pub ventana_hasta: Option<NaiveTime>,
+    pub duracion_min: u16,
+    pub motivo: String,
+    pub estado: String,
+    pub motivo_estado: Option<String>,
+    #[serde(with = "tiempo::utc")]
+    pub creada_en: NaiveDateTime,
+}
+
+/// Estados a los que puede pasar una visita desde `estado`.
+pub fn transiciones_visita(estado: &str) -> &'static [&'static str] {
+    match estado {
+        VISITA_PENDIENTE => &[VISITA_EN_RUTA, VISITA_FALLIDA],
+        VISITA_EN_RUTA => &[VISITA_REALIZADA, VISITA_FALLIDA, VISITA_PENDIENTE],
+        _ => &[],
+    }
+}
+
+/// Cambio de estado registrado en el historial de la visita.
+#[derive(Debug, Serialize, Deserialize, FromRow, Clone)]
+pub struct EventoVisita {
+    pub estado: String,
+    pub motivo: Option<String>,
+    #[serde(with = "tiempo::utc")]
+    pub registrado_en: NaiveDateTime,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct VisitaInput {
+    pub id_paciente: u32,
+    /// Por defecto, los del paciente.
+    pub cod_zona: Option<String>,
+    pub direccion: Option<String>,
+    pub comuna: Option<String>,
+    pub latitud: Option<f64>,
+    pub longitud: Option<f64>,
+    pub fecha: NaiveDate,
+    pub ventana_desde: Option<NaiveTime>,
+    pub ventana_hasta: Option<NaiveTime>,
+    pub duracion_min: Option<u16>,
+    pub motivo: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct AsignacionInput {
+    pub id_prof: u32,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CambioEstadoInput {
+    pub estado: String,
+    pub motivo: Option<String>,
+}
+
+/// Criterios de búsqueda para `GET /api/visitas`. Con `id_prof` y `fecha`
+/// se obtiene la lista del día de un profesional; `desde` y `hasta` acotan
+/// un período (ambos inclusive).
+#[derive(Debug, Default, Deserialize)]
+pub struct VisitaFiltro {
+    pub id_prof: Option<u32>,
+    pub id_paciente: Option<u32>,
+    pub cod_zona: Option<String>,
+    pub fecha: Option<NaiveDate>,
+    pub desde: Option<NaiveDate>,
+    pub hasta: Option<NaiveDate>,
+    pub estado: Option<String>,
+}
+
+impl TryFrom<VisitaInput> for Visita {
+    type Error = AppError;
+
+    /// Valida la solicitud; la dirección y la zona deben completarse con las
+    /// del paciente antes de convertir si no vienen en la entrada.
+    fn try_from(input: VisitaInput) -> Result<Self, Self::Error> {
+        let motivo = input.motivo.trim().to_string();
+        if motivo.is_empty() {
+            return Err(AppError::Validation("El motivo de la visita es obligatorio".into()));
+        }
+        let direccion = input.direccion.map(|d| d.trim().to_string()).unwrap_or_default();
+        if direccion.is_empty() {
+            return Err(AppError::Validation("La visita requiere una dirección".into()));
+        }
+        let cod_zona = input.cod_zona
+            .ok_or_else(|| AppError::Validation("La visita requiere cod_zona".into()))?;
+        if let (Some(desde), Some(hasta)) = (input.ventana_desde, input.ventana_hasta)
+            && hasta <= desde
+        {
+            return Err(AppError::Validation("ventana_hasta debe ser posterior a ventana_desde".into()));
+        }
+        geo::validar_coordenadas(input.latitud, input.longitud)?;
+        let duracion_min = input.duracion_min.unwrap_or(DURACION_DEFECTO_MIN);
+        if duracion_min == 0 {
+            return Err(AppError::Validation("duracion_min debe ser mayor que cero".into()));
+        }
+
+        Ok(Self {
+            id: 0,
+            id_paciente: input.id_paciente,
+            id_prof: None,
+            cod_zona,
+            direccion,
+            comuna: input.comuna,
+            latitud: input.latitud,
+            longitud: input.longitud,
+            fecha: input.fecha,
+            ventana_desde: input.ventana_desde,
+            ventana_hasta: input.ventana_hasta,
+            duracion_min,
+            motivo,
+            estado: VISITA_PENDIENTE.into(),
+            motivo_estado: None,
+            creada_en: tiempo::ahora(),
+        })
+    }
+}
+
+/// Parámetros de `GET /api/visitas/ruta`. El origen y la hora de inicio son
+/// los del profesional; la velocidad es la media esperada en ciudad.
+#[derive(Debug, Deserialize)]
+pub struct RutaFiltro {
+    pub profesional: u32,
+    pub fecha: NaiveDate,
+    pub origen_lat: Option<f64>,
+    pub origen_lon: Option<f64>,
+    pub hora_inicio: Option<NaiveTime>,
+    pub velocidad_kmh: Option<f64>,
+}
+
+/// Parada de la ruta con horarios estimados (hora local de la zona).
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ParadaRuta {
+    pub orden: usize,
+    pub id_visita: u32,
+    pub id_paciente: u32,
+    pub direccion: String,
+    pub distancia_km: f64,
+    pub llegada: NaiveTime,
+    pub inicio: NaiveTime,
+    pub fin: NaiveTime,
+    pub atraso_min: i64,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Ruta {
+    pub id_prof: u32,
+    pub fecha: NaiveDate,
+    pub paradas: Vec<ParadaRuta>,
+    pub distancia_total_km: f64,
+    /// Visitas sin coordenadas: quedan fuera del orden y deben ubicarse a mano.
+    pub sin_ubicacion: Vec<u32>,
+}
+
+/// Llegada o salida del domicilio informada por la aplicación móvil.
+/// `registrada_en` es la hora del dispositivo (puede enviarse tarde si no
+/// había señal) y `recibida_en` la del servidor, ambas en UTC. La distancia
+/// al domicilio se calcula al recibir la marca.
+#[derive(Debug, Serialize, Deserialize, FromRow, Clone)]
+pub struct MarcaVisita {
+    pub id: u32,
+    pub id_visita: u32,
+    pub tipo: String,
+    pub latitud: f64,
+    pub longitud: f64,
+    pub precision_m: Option<f64>,
+    pub distancia_km: Option<f64>,
+    #[serde(with = "tiempo::utc")]
+    pub registrada_en: NaiveDateTime,
+    #[serde(with = "tiempo::utc")]
+    pub recibida_en: NaiveDateTime,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct MarcaInput {
+    pub id_prof: u32,
+    pub tipo: String,
+    pub latitud: f64,
+    pub longitud: f64,
+    pub precision_m: Option<f64>,
+    /// Por defecto, la hora de recepción.
+    #[serde(default, with = "tiempo::utc::opcional")]
+    pub registrada_en: Option<NaiveDateTime>,
+}
+
+/// Parámetros de `GET /api/visitas/verificacion`; fechas locales de las visitas.
+#[derive(Debug, Deserialize)]
+pub struct VerificacionFiltro {
+    pub id_prof: u32,
+    pub desde: NaiveDate,
+    pub hasta: NaiveDate,
+}
+
+/// Evidencia de una visita y las anomalías detectadas en ella.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct VerificacionVisita {
+    pub id_visita: u32,
+    pub id_paciente: u32,
+    pub fecha: NaiveDate,
+    pub estado: String,
+    pub llegada: Option<MarcaVisita>,
+    pub salida: Option<MarcaVisita>,
+    pub minutos_en_domicilio: Option<i64>,
+    pub anomalias: Vec<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ReporteVerificacion {
+    pub id_prof: u32,
+    pub desde: NaiveDate,
+    pub hasta: NaiveDate,
+    pub total: usize,
+    pub con_anomalias: usize,
+    pub visitas: Vec<VerificacionVisita>,
+}
@@ -0,0 +1,37 @@
+use serde::{Deserialize, Serialize};
+use mysql_async::prelude::FromRow;
+
+use crate::{error::AppError, tiempo};
+
+#[derive(Debug, Serialize, Deserialize, FromRow, Clone)]
+pub struct Zona {
+    pub cod_zona: String,
+    pub nom_zona: String,
+    pub orden_zona: Option<i32>,
+    pub zona_horaria: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ZonaInput {
+    pub nom_zona: String,
+    pub orden_zona: Option<i32>,
+    pub zona_horaria: String,
+}
+
+impl TryFrom<ZonaInput> for Zona {
+    type Error = AppError;
+
+    fn try_from(input: ZonaInput) -> Result<Self, Self::Error> {
+        tiempo::zona_horaria(&input.zona_horaria)?;
+        if input.nom_zona.trim().is_empty() {
+            return Err(AppError::Validation("El nombre de la zona es obligatorio".into()));
+        }
+
+        Ok(Self {
+            cod_zona: String::new(),
+            nom_zona: input.nom_zona.trim().to_string(),
+            orden_zona: input.orden_zona,
+            zona_horaria: input.zona_horaria,
+        })
+    }
+}
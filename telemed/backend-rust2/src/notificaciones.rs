@@ -0,0 +1,170 @@
+use std::collections::HashMap;
+
+use chrono::{Duration, NaiveDateTime};
+
+use crate::{
+    error::AppError,
+    models::{CANAL_EMAIL, CANAL_SMS},
+};
+
+/// Intentos de envío antes de dar una notificación por fallida.
+pub const MAXIMO_INTENTOS: u8 = 6;
+const ESPERA_INICIAL_MIN: i64 = 1;
+const ESPERA_MAXIMA_MIN: i64 = 6 * 60;
+/// Minutos que una notificación reclamada queda reservada para quien la
+/// envía. Si el proceso cae a mitad de envío, se reintenta al vencer.
+pub const RESERVA_MIN: i64 = 10;
+
+/// Textos de una notificación. `{variable}` se reemplaza al encolar; el
+/// SMS es una versión corta del cuerpo. No se trunca para no cortar
+/// enlaces: si excede 160 caracteres el proveedor lo envía en partes.
+pub struct Plantilla {
+    pub codigo: &'static str,
+    pub asunto: &'static str,
+    pub cuerpo: &'static str,
+    pub sms: &'static str,
+}
+
+pub const PLANTILLAS: &[Plantilla] = &[
+    Plantilla {
+        codigo: "RECORDATORIO_CITA",
+        asunto: "Recordatorio de su cita del {fecha}",
+        cuerpo: "Estimado(a) {nombre}:\n\nLe recordamos su atención con {profesional} el {fecha} a las {hora}.\n\nPara confirmar su asistencia ingrese a:\n{confirmar}\n\nSi no puede asistir, cancele la hora en el siguiente enlace para ofrecerla a otro paciente:\n{cancelar}",
+        sms: "Cita con {profesional} el {fecha} a las {hora}. Confirme: {confirmar} Cancele: {cancelar}",
+    },
+    Plantilla {
+        codigo: "RESTABLECER_CLAVE",
+        asunto: "Restablecer su contraseña",
+        cuerpo: "Estimado(a) {nombre}:\n\nRecibimos una solicitud para restablecer su contraseña. Ingrese al siguiente enlace dentro de los próximos {minutos} minutos:\n\n{enlace}\n\nSi usted no lo solicitó, ignore este mensaje.",
+        sms: "Su enlace para restablecer la contraseña: {enlace} (válido por {minutos} min).",
+    },
+    Plantilla {
+        codigo: "MENSAJE_NUEVO",
+        asunto: "Tiene un mensaje nuevo",
+        cuerpo: "Estimado(a) {nombre}:\n\nTiene un mensaje nuevo en la conversación \"{asunto}\". Ingrese al portal para leerlo.",
+        sms: "Tiene un mensaje nuevo en el portal de telemedicina.",
+    },
+    Plantilla {
+        codigo: "CREDENCIAL_POR_VENCER",
+        asunto: "Su {credencial} vence el {fecha}",
+        cuerpo: "Estimado(a) {nombre}:\n\nSu {credencial} vence el {fecha}, en {dias} días. Cargue el documento renovado en el portal antes de esa fecha; con credenciales obligatorias vencidas no se le podrán asignar citas ni visitas.",
+        sms: "Su {credencial} vence el {fecha}. Cargue el documento renovado en el portal para seguir recibiendo citas y visitas.",
+    },
+    Plantilla {
+        codigo: "ALERTA",
+        asunto: "{titulo}",
+        cuerpo: "{detalle}",
+        sms: "{titulo}: {detalle}",
+    },
+];
+
+pub fn plantilla(codigo: &str) -> Result<&'static Plantilla, AppError> {
+    let codigo = codigo.trim().to_uppercase();
+    PLANTILLAS
+        .iter()
+        .find(|p| p.codigo == codigo)
+        .ok_or_else(|| AppError::Validation(format!("Plantilla de notificación {} no existe", codigo)))
+}
+
+/// Asunto y cuerpo de la plantilla para el canal. Falla si falta alguna
+/// variable, para no enviar textos con `{llaves}` sin reemplazar.
+pub fn componer(plantilla: &Plantilla, canal: &str, variables: &HashMap<String, String>) -> Result<(String, String), AppError> {
+    let cuerpo = if canal == CANAL_SMS { plantilla.sms } else { plantilla.cuerpo };
+    let asunto = reemplazar(plantilla.asunto, variables)?;
+    let cuerpo = reemplazar(cuerpo, variables)?;
+    Ok((asunto, cuerpo))
+}
+
+fn reemplazar(texto: &str, variables: &HashMap<String, String>) -> Result<String, AppError> {
+    let mut resultado = String::with_capacity(texto.len());
+    let mut resto = texto;
+    while let Some(inicio) = resto.find('{') {
+        let Some(fin) = resto[inicio..].find('}') else { break };
+        let nombre = &resto[inicio + 1..inicio + fin];
+        let valor = variables
+            .get(nombre)
+            .ok_or_else(|| AppError::Validation(format!("Falta la variable {} de la plantilla", nombre)))?;
+        resultado.push_str(&resto[..inicio]);
+        resultado.push_str(valor);
+        resto = &resto[inicio + fin + 1..];
+    }
+    resultado.push_str(resto);
+    Ok(resultado)
+}
+
+/// Próximo intento tras `intentos` fallidos: 1, 2, 4, 8… minutos, hasta
+/// un máximo de seis horas.
+pub fn proximo_intento(intentos: u8, ahora: NaiveDateTime) -> NaiveDateTime {
+    let exponente = u32::from(intentos.saturating_sub(1)).min(16);
+    ahora + Duration::minutes((ESPERA_INICIAL_MIN << exponente).min(ESPERA_MAXIMA_MIN))
+}
+
+/// Dirección del destinatario en el canal, o `None` si no tiene una
+/// válida. De `telefonos` se usa el primero; los celulares chilenos de
+/// 9 dígitos se completan con +56.
+pub fn direccion(canal: &str, email: Option<&str>, telefonos: Option<&str>) -> Option<String> {
+    match canal {
+        CANAL_EMAIL => email
+            .map(str::trim)
+            .filter(|e| e.split_once('@').is_some_and(|(u, d)| !u.is_empty() && d.contains('.')))
+            .map(str::to_lowercase),
+        CANAL_SMS => {
+            let primero = telefonos?.split([',', ';', '/']).next()?;
+            let digitos: String = primero.chars().filter(char::is_ascii_digit).collect();
+            match digitos.len() {
+                9 if digitos.starts_with('9') => Some(format!("+56{}", digitos)),
+                11 if digitos.starts_with("569") => Some(format!("+{}", digitos)),
+                _ => None,
+            }
+        }
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{models::CANAL_APP, tiempo};
+
+    fn variables(pares: &[(&str, &str)]) -> HashMap<String, String> {
+        pares.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect()
+    }
+
+    #[test]
+    fn compone_plantillas() {
+        let recordatorio = plantilla("recordatorio_cita").unwrap();
+        let vars = variables(&[
+            ("nombre", "Ana"),
+            ("profesional", "Nicole Zamora"),
+            ("fecha", "21/10/2026"),
+            ("hora", "09:30"),
+            ("confirmar", "https://portal/c"),
+            ("cancelar", "https://portal/x"),
+        ]);
+        let (asunto, cuerpo) = componer(recordatorio, CANAL_EMAIL, &vars).unwrap();
+        assert_eq!(asunto, "Recordatorio de su cita del 21/10/2026");
+        assert!(cuerpo.starts_with("Estimado(a) Ana:"));
+        let (_, sms) = componer(recordatorio, CANAL_SMS, &vars).unwrap();
+        assert_eq!(sms, "Cita con Nicole Zamora el 21/10/2026 a las 09:30. Confirme: https://portal/c Cancele: https://portal/x");
+
+        assert!(componer(recordatorio, CANAL_APP, &variables(&[("nombre", "Ana")])).is_err());
+        assert!(plantilla("NO_EXISTE").is_err());
+    }
+
+    #[test]
+    fn espera_exponencial_con_tope() {
+        let ahora = tiempo::ahora();
+        let esperas: Vec<i64> = [1, 2, 3, 4, 10, 255].iter().map(|&i| (proximo_intento(i, ahora) - ahora).num_minutes()).collect();
+        assert_eq!(esperas, vec![1, 2, 4, 8, 360, 360]);
+    }
+
+    #[test]
+    fn normaliza_direcciones() {
+        assert_eq!(direccion(CANAL_EMAIL, Some(" Ana@Correo.cl "), None).as_deref(), Some("ana@correo.cl"));
+        assert_eq!(direccion(CANAL_EMAIL, Some("sin-arroba"), None), None);
+        assert_eq!(direccion(CANAL_SMS, None, Some("9 8765 4321, 22 123 4567")).as_deref(), Some("+56987654321"));
+        assert_eq!(direccion(CANAL_SMS, None, Some("+56 9 8765-4321")).as_deref(), Some("+56987654321"));
+        assert_eq!(direccion(CANAL_SMS, None, Some("22 123 4567")), None);
+        assert_eq!(direccion(CANAL_APP, Some("ana@correo.cl"), None), None);
+    }
+}
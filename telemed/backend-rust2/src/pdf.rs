@@ -0,0 +1,150 @@
+use printpdf::{BuiltinFont, IndirectFontRef, Mm, PdfDocument, PdfLayerReference};
+
+use crate::error::AppError;
+
+const ANCHO_MM: f32 = 210.0;
+const ALTO_MM: f32 = 297.0;
+const MARGEN_MM: f32 = 20.0;
+/// Bajo esta altura se pasa a la página siguiente para no pisar el pie.
+const LIMITE_INFERIOR_MM: f32 = 30.0;
+/// Helvetica de 10 pt promedia unos 1,8 mm por carácter: 170 mm útiles.
+const CARACTERES_POR_LINEA: usize = 92;
+
+/// Contenido de un documento imprimible (A4, Helvetica). Las fuentes base
+/// usan WinAnsi, que cubre tildes y eñes.
+#[derive(Debug, Clone)]
+pub enum Bloque {
+    Titulo(String),
+    Subtitulo(String),
+    Parrafo(String),
+    Espacio,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Documento {
+    pub titulo: String,
+    pub bloques: Vec<Bloque>,
+    /// Se repite al pie de cada página (código de verificación, huella).
+    pub pie: Vec<String>,
+}
+
+impl Documento {
+    pub fn new(titulo: &str) -> Self {
+        Self { titulo: titulo.into(), ..Default::default() }
+    }
+
+    pub fn titulo(&mut self, texto: impl Into<String>) -> &mut Self {
+        self.bloques.push(Bloque::Titulo(texto.into()));
+        self
+    }
+
+    pub fn subtitulo(&mut self, texto: impl Into<String>) -> &mut Self {
+        self.bloques.push(Bloque::Subtitulo(texto.into()));
+        self
+    }
+
+    pub fn parrafo(&mut self, texto: impl Into<String>) -> &mut Self {
+        self.bloques.push(Bloque::Parrafo(texto.into()));
+        self
+    }
+
+    pub fn espacio(&mut self) -> &mut Self {
+        self.bloques.push(Bloque::Espacio);
+        self
+    }
+
+    pub fn pie(&mut self, texto: impl Into<String>) -> &mut Self {
+        self.pie.push(texto.into());
+        self
+    }
+
+    pub fn renderizar(&self) -> Result<Vec<u8>, AppError> {
+        let (doc, pagina, capa) = PdfDocument::new(&self.titulo, Mm(ANCHO_MM), Mm(ALTO_MM), "contenido");
+        let normal = doc.add_builtin_font(BuiltinFont::Helvetica).map_err(error_pdf)?;
+        let negrita = doc.add_builtin_font(BuiltinFont::HelveticaBold).map_err(error_pdf)?;
+
+        let mut capa = doc.get_page(pagina).get_layer(capa);
+        self.escribir_pie(&capa, &normal);
+        let mut y = ALTO_MM - MARGEN_MM;
+
+        for bloque in &self.bloques {
+            let (lineas, tamano, alto, fuente) = match bloque {
+                Bloque::Titulo(t) => (vec![t.clone()], 15.0, 9.0, &negrita),
+                Bloque::Subtitulo(t) => (vec![t.clone()], 11.0, 6.5, &negrita),
+                Bloque::Parrafo(t) => (envolver(t, CARACTERES_POR_LINEA), 10.0, 5.0, &normal),
+                Bloque::Espacio => (Vec::new(), 0.0, 4.0, &normal),
+            };
+            if lineas.is_empty() {
+                y -= alto;
+                continue;
+            }
+            for linea in lineas {
+                if y < LIMITE_INFERIOR_MM {
+                    let (pagina, nueva) = doc.add_page(Mm(ANCHO_MM), Mm(ALTO_MM), "contenido");
+                    capa = doc.get_page(pagina).get_layer(nueva);
+                    self.escribir_pie(&capa, &normal);
+                    y = ALTO_MM - MARGEN_MM;
+                }
+                capa.use_text(linea, tamano, Mm(MARGEN_MM), Mm(y), fuente);
+                y -= alto;
+            }
+        }
+
+        doc.save_to_bytes().map_err(error_pdf)
+    }
+
+    fn escribir_pie(&self, capa: &PdfLayerReference, fuente: &IndirectFontRef) {
+        let mut y = 10.0 + 3.5 * self.pie.len() as f32;
+        for linea in &self.pie {
+            capa.use_text(linea.as_str(), 7.5, Mm(MARGEN_MM), Mm(y), fuente);
+            y -= 3.5;
+        }
+    }
+}
+
+fn error_pdf(e: printpdf::Error) -> AppError {
+    AppError::Internal(format!("No se pudo generar el PDF: {}", e))
+}
+
+/// Corta el texto en líneas de hasta `ancho` caracteres sin partir palabras,
+/// respetando los saltos de línea del original.
+pub fn envolver(texto: &str, ancho: usize) -> Vec<String> {
+    let mut lineas = Vec::new();
+    for parrafo in texto.lines() {
+        let mut actual = String::new();
+        for palabra in parrafo.split_whitespace() {
+            let largo = actual.chars().count();
+            if largo > 0 && largo + 1 + palabra.chars().count() > ancho {
+                lineas.push(std::mem::take(&mut actual));
+            }
+            if !actual.is_empty() {
+                actual.push(' ');
+            }
+            actual.push_str(palabra);
+        }
+        lineas.push(actual);
+    }
+    lineas
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn envuelve_sin_partir_palabras() {
+        assert_eq!(envolver("uno dos tres cuatro", 8), ["uno dos", "tres", "cuatro"]);
+        assert_eq!(envolver("línea\n\notra", 20), ["línea", "", "otra"]);
+    }
+
+    #[test]
+    fn genera_pdf_de_varias_paginas() {
+        let mut doc = Documento::new("Prueba");
+        doc.titulo("Título con tildes: ñandú").pie("Pie de página");
+        for i in 0..80 {
+            doc.parrafo(format!("Párrafo {}", i));
+        }
+        let bytes = doc.renderizar().unwrap();
+        assert!(bytes.starts_with(b"%PDF"));
+    }
+}
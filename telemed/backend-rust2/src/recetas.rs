@@ -0,0 +1,177 @@
+use chrono_tz::Tz;
+use rand::Rng;
+use sha2::{Digest, Sha256};
+
+use crate::{
+    models::{Paciente, Profesional, RecetaDetalle, RECETA_ANULADA},
+    pdf::Documento,
+    tiempo,
+};
+
+/// Sin 0/O ni 1/I para que el código se pueda dictar y transcribir.
+const ALFABETO: &[u8] = b"23456789ABCDEFGHJKLMNPQRSTUVWXYZ";
+const LARGO_CODIGO: usize = 12;
+const FORMATO_FECHA: &str = "%d-%m-%Y";
+
+/// Código de verificación aleatorio con formato `XXXX-XXXX-XXXX`.
+pub fn generar_codigo() -> String {
+    let mut rng = rand::thread_rng();
+    let crudo: String = (0..LARGO_CODIGO)
+        .map(|_| ALFABETO[rng.gen_range(0..ALFABETO.len())] as char)
+        .collect();
+    formatear_codigo(&crudo)
+}
+
+fn formatear_codigo(crudo: &str) -> String {
+    crudo.as_bytes()
+        .chunks(4)
+        .map(|g| String::from_utf8_lossy(g).into_owned())
+        .collect::<Vec<_>>()
+        .join("-")
+}
+
+/// Acepta el código en minúsculas, sin guiones o con espacios.
+pub fn normalizar_codigo(codigo: &str) -> Option<String> {
+    let crudo: String = codigo
+        .chars()
+        .filter(|c| !matches!(c, '-' | ' '))
+        .map(|c| c.to_ascii_uppercase())
+        .collect();
+    (crudo.len() == LARGO_CODIGO && crudo.bytes().all(|b| ALFABETO.contains(&b))).then(|| formatear_codigo(&crudo))
+}
+
+/// Contenido canónico de la receta: lo que se imprime y lo que se firma.
+pub fn contenido(detalle: &RecetaDetalle) -> String {
+    let r = &detalle.receta;
+    let mut contenido = vec![
+        r.codigo.clone(),
+        r.id_paciente.to_string(),
+        r.id_prof.to_string(),
+        r.registro_rnpi.clone(),
+        r.emitida_en.format("%Y-%m-%dT%H:%M:%SZ").to_string(),
+        r.valida_hasta.to_string(),
+        r.indicaciones.clone().unwrap_or_default(),
+    ];
+    contenido.extend(
+        detalle.lineas.iter().map(|l| format!("{}|{}|{}|{}", l.medicamento, l.dosis, l.frecuencia, l.duracion)),
+    );
+    contenido.join("\n")
+}
+
+/// SHA-256 del contenido que se imprime, en hexadecimal. Permite a quien
+/// tiene el papel comprobar que coincide con lo registrado.
+pub fn huella(detalle: &RecetaDetalle) -> String {
+    hex::encode(Sha256::digest(contenido(detalle).as_bytes()))
+}
+
+/// RUT con sólo los últimos dígitos visibles: `0010895960-6` → `***960-6`.
+pub fn enmascarar_rut(rut: &str) -> String {
+    match rut.split_once('-') {
+        Some((cuerpo, dv)) if cuerpo.len() > 3 => format!("***{}-{}", &cuerpo[cuerpo.len() - 3..], dv),
+        _ => "***".into(),
+    }
+}
+
+/// Nombre con los apellidos abreviados: `CLAUDIO SAEZ SOTO` → `CLAUDIO S. S.`.
+pub fn abreviar_nombre(paciente: &Paciente) -> String {
+    let mut partes = vec![paciente.nombres.trim().to_string()];
+    partes.extend(
+        [Some(paciente.ap_paterno.as_str()), paciente.ap_materno.as_deref()]
+            .into_iter()
+            .flatten()
+            .filter_map(|a| a.trim().chars().next())
+            .map(|inicial| format!("{}.", inicial)),
+    );
+    partes.join(" ")
+}
+
+/// Receta lista para imprimir, con el código y la huella al pie. La hora de
+/// emisión se muestra en la zona horaria `tz` del paciente. Sólo con la
+/// `leyenda` de una firma registrada se declara firmada electrónicamente.
+pub fn documento(
+    detalle: &RecetaDetalle,
+    profesional: &Profesional,
+    paciente: &Paciente,
+    tz: Tz,
+    leyenda: Option<&str>,
+) -> Documento {
+    let r = &detalle.receta;
+    let emitida = tiempo::a_local(r.emitida_en, tz);
+    let mut doc = Documento::new(&format!("Receta {}", r.codigo));
+
+    doc.titulo("RECETA MÉDICA");
+    if r.estado == RECETA_ANULADA {
+        doc.subtitulo(format!("ANULADA: {}", r.motivo_anulacion.as_deref().unwrap_or_default()));
+    }
+    doc.parrafo(format!("Emitida el {} hrs.", emitida.format("%d-%m-%Y %H:%M")))
+        .espacio()
+        .subtitulo("Profesional")
+        .parrafo(profesional.nombre_completo());
+    if let Some(especialidad) = &profesional.especialidad {
+        doc.parrafo(especialidad.trim());
+    }
+    doc.parrafo(format!("RUT {} · Registro RNPI {}", profesional.rut.trim_start_matches('0'), r.registro_rnpi))
+        .espacio()
+        .subtitulo("Paciente")
+        .parrafo(paciente.nombre_completo())
+        .parrafo(format!("RUT {}", paciente.rut.trim_start_matches('0')));
+    if let Some(nacimiento) = paciente.fecha_nacimiento {
+        doc.parrafo(format!("Fecha de nacimiento {}", nacimiento.format(FORMATO_FECHA)));
+    }
+
+    doc.espacio().subtitulo("Rp.");
+    for (i, l) in detalle.lineas.iter().enumerate() {
+        doc.parrafo(format!("{}. {} {}, {}, durante {}", i + 1, l.medicamento, l.dosis, l.frecuencia, l.duracion));
+    }
+    if let Some(indicaciones) = &r.indicaciones {
+        doc.espacio().subtitulo("Indicaciones").parrafo(indicaciones.as_str());
+    }
+
+    doc.espacio()
+        .parrafo(format!("Válida hasta el {}.", r.valida_hasta.format(FORMATO_FECHA)));
+    if leyenda.is_some() {
+        doc.espacio().parrafo(format!(
+            "Firmada electrónicamente por {} (RNPI {}).", profesional.nombre_completo(), r.registro_rnpi
+        ));
+    }
+    doc.pie(format!("Código de verificación {} · verifíquelo en /api/recetas/verificar/{}", r.codigo, r.codigo))
+        .pie(format!("Huella SHA-256 {}", r.huella));
+    if let Some(leyenda) = leyenda {
+        doc.pie(leyenda);
+    }
+    doc
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{LineaReceta, RecetaInput};
+
+    #[test]
+    fn codigo_se_normaliza_y_verifica() {
+        let codigo = generar_codigo();
+        assert_eq!(codigo.len(), 14);
+        assert_eq!(normalizar_codigo(&codigo.to_lowercase().replace('-', "")), Some(codigo));
+        assert_eq!(normalizar_codigo("abcd-efgh-jkl0"), None);
+        assert_eq!(normalizar_codigo("abcd"), None);
+        assert_eq!(enmascarar_rut("0010895960-6"), "***960-6");
+    }
+
+    #[test]
+    fn huella_sobrevive_el_redondeo_del_datetime() {
+        let linea = LineaReceta {
+            medicamento: "Paracetamol".into(),
+            dosis: "500 mg".into(),
+            frecuencia: "cada 8 horas".into(),
+            duracion: "3 días".into(),
+        };
+        let input = RecetaInput { id_atencion: 1, id_prof: 1, indicaciones: None, dias_vigencia: None, lineas: vec![linea] };
+        let detalle = RecetaDetalle::try_from(input).unwrap();
+        assert_eq!(detalle.receta.emitida_en, tiempo::al_segundo(detalle.receta.emitida_en));
+
+        // MySQL redondea la fracción al guardar en DATETIME
+        let mut releida = detalle.clone();
+        releida.receta.emitida_en = tiempo::al_segundo(detalle.receta.emitida_en + chrono::Duration::milliseconds(500));
+        assert_eq!(huella(&releida), huella(&detalle));
+    }
+}
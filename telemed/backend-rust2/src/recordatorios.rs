@@ -0,0 +1,134 @@
+use std::collections::BTreeMap;
+
+use chrono::{Duration, NaiveDateTime};
+
+use crate::{
+    enlaces::Enlaces,
+    error::AppError,
+    models::{Cita, ResumenConfirmaciones, ACCION_CANCELAR, ACCION_CONFIRMAR, RECORDATORIO_24H, RECORDATORIO_2H, RESPUESTA_CANCELADA, RESPUESTA_CONFIRMADA},
+};
+
+/// Recordatorio que corresponde enviar ahora. Dentro de las dos horas
+/// previas toca el de 2 horas, aunque el de 24 no se haya enviado.
+pub fn debido(inicio: NaiveDateTime, ahora: NaiveDateTime) -> Option<&'static str> {
+    let faltan = inicio - ahora;
+    if faltan <= Duration::zero() {
+        None
+    } else if faltan <= Duration::hours(2) {
+        Some(RECORDATORIO_2H)
+    } else if faltan <= Duration::hours(24) {
+        Some(RECORDATORIO_24H)
+    } else {
+        None
+    }
+}
+
+/// Enlace de un clic con que el paciente confirma o cancela la cita.
+pub fn enlace(enlaces: &Enlaces, id_cita: u32, accion: &str, expira: NaiveDateTime) -> String {
+    enlaces.firmar(
+        &format!("/api/agenda/citas/{}/respuesta?accion={}", id_cita, accion),
+        &format!("{}:{}", id_cita, accion),
+        expira,
+    )
+}
+
+/// Valida acción, firma y vigencia del enlace.
+pub fn verificar(enlaces: &Enlaces, id_cita: u32, accion: &str, expira: i64, firma: &str, ahora: NaiveDateTime) -> Result<(), AppError> {
+    if accion != ACCION_CONFIRMAR && accion != ACCION_CANCELAR {
+        return Err(AppError::Validation(format!("Acción {} desconocida", accion)));
+    }
+    enlaces.comprobar(&format!("{}:{}", id_cita, accion), expira, firma, ahora)
+}
+
+/// Agrupa por zona las citas recordadas con la respuesta de cada una.
+pub fn resumir(citas: &[(Cita, Option<String>)]) -> Vec<ResumenConfirmaciones> {
+    let mut zonas: BTreeMap<Option<String>, (u32, u32, u32)> = BTreeMap::new();
+    for (cita, respuesta) in citas {
+        let (recordadas, confirmadas, canceladas) = zonas.entry(cita.cod_zona.clone()).or_default();
+        *recordadas += 1;
+        match respuesta.as_deref() {
+            Some(RESPUESTA_CONFIRMADA) => *confirmadas += 1,
+            Some(RESPUESTA_CANCELADA) => *canceladas += 1,
+            _ => {}
+        }
+    }
+    let tasa = |parte: u32, total: u32| (f64::from(parte) * 1000.0 / f64::from(total)).round() / 10.0;
+    zonas
+        .into_iter()
+        .map(|(cod_zona, (recordadas, confirmadas, canceladas))| ResumenConfirmaciones {
+            cod_zona,
+            recordadas,
+            confirmadas,
+            canceladas,
+            sin_respuesta: recordadas - confirmadas - canceladas,
+            tasa_confirmacion: tasa(confirmadas, recordadas),
+            tasa_cancelacion: tasa(canceladas, recordadas),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{models::CITA_AGENDADA, tiempo};
+
+    #[test]
+    fn elige_recordatorio() {
+        let ahora = tiempo::ahora();
+        let h = Duration::hours;
+        assert_eq!(debido(ahora + h(30), ahora), None);
+        assert_eq!(debido(ahora + h(24), ahora), Some(RECORDATORIO_24H));
+        assert_eq!(debido(ahora + h(3), ahora), Some(RECORDATORIO_24H));
+        assert_eq!(debido(ahora + h(2), ahora), Some(RECORDATORIO_2H));
+        assert_eq!(debido(ahora - h(1), ahora), None);
+    }
+
+    #[test]
+    fn enlaces_firmados_por_cita_y_accion() {
+        let enlaces = Enlaces::new(Some("secreto"), "https://portal.cl/");
+        let ahora = tiempo::ahora();
+        let expira = ahora + Duration::hours(3);
+        let enlace = enlace(&enlaces, 7, ACCION_CONFIRMAR, expira);
+        assert!(enlace.starts_with("https://portal.cl/api/agenda/citas/7/respuesta?accion=confirmar&"));
+        let firma = enlace.rsplit("firma=").next().unwrap();
+        let t = expira.and_utc().timestamp();
+
+        assert!(verificar(&enlaces, 7, ACCION_CONFIRMAR, t, firma, ahora).is_ok());
+        assert!(matches!(verificar(&enlaces, 7, ACCION_CANCELAR, t, firma, ahora), Err(AppError::Forbidden(_))));
+        assert!(matches!(verificar(&enlaces, 8, ACCION_CONFIRMAR, t, firma, ahora), Err(AppError::Forbidden(_))));
+        assert!(matches!(verificar(&enlaces, 7, ACCION_CONFIRMAR, t + 3600, firma, ahora), Err(AppError::Forbidden(_))));
+        assert!(matches!(verificar(&enlaces, 7, ACCION_CONFIRMAR, t, firma, expira), Err(AppError::Validation(_))));
+        assert!(verificar(&Enlaces::new(Some("otro"), "https://portal.cl"), 7, ACCION_CONFIRMAR, t, firma, ahora).is_err());
+    }
+
+    #[test]
+    fn resume_por_zona() {
+        let ahora = tiempo::ahora();
+        let cita = |id: u32, zona: &str| Cita {
+            id,
+            id_prof: 1,
+            id_paciente: id,
+            id_bloque: 1,
+            inicio: ahora,
+            fin: ahora + Duration::minutes(20),
+            modalidad: "DOMICILIO".into(),
+            cod_zona: Some(zona.into()),
+            estado: CITA_AGENDADA.into(),
+            sobrecupo: false,
+            motivo_cancelacion: None,
+            id_cita_original: None,
+        };
+        let resumen = resumir(&[
+            (cita(1, "RM"), Some(RESPUESTA_CONFIRMADA.into())),
+            (cita(2, "RM"), Some(RESPUESTA_CANCELADA.into())),
+            (cita(3, "RM"), None),
+            (cita(4, "BIOBIO"), Some(RESPUESTA_CONFIRMADA.into())),
+        ]);
+        assert_eq!(resumen.len(), 2);
+        assert_eq!(resumen[0].cod_zona.as_deref(), Some("BIOBIO"));
+        assert_eq!(resumen[0].tasa_confirmacion, 100.0);
+        let rm = &resumen[1];
+        assert_eq!((rm.recordadas, rm.confirmadas, rm.canceladas, rm.sin_respuesta), (3, 1, 1, 1));
+        assert_eq!((rm.tasa_confirmacion, rm.tasa_cancelacion), (33.3, 33.3));
+    }
+}
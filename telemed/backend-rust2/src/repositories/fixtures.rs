@@ -0,0 +1,90 @@
+use chrono::NaiveDateTime;
+use crate::{
+    app_state::AppState,
+    models::{Atencion, AtencionDetalle, Paciente, Profesional, ATENCION_BORRADOR, PROFESIONAL_VIGENTE},
+};
+use super::{MockRepository, PacienteRepository};
+
+/// Profesional vigente con RNPI y sin datos de contacto. Cada test ajusta
+/// lo que le importa con `Profesional { zona: …, ..profesional(1) }`.
+pub fn profesional(id_prof: u32) -> Profesional {
+    Profesional {
+        id_prof,
+        rut: "16.354.813-5".into(),
+        nombres: "NICOLE ELENA".into(),
+        ap_paterno: "ZAMORA".into(),
+        ap_materno: None,
+        direccion: None,
+        comuna: None,
+        ciudad: None,
+        email: None,
+        telefonos: None,
+        especialidad: Some("Enfermera".into()),
+        registro_rnpi: Some("212402".into()),
+        fecha_ingreso: None,
+        fecha_egreso: None,
+        zona: None,
+        estado: Some(PROFESIONAL_VIGENTE.into()),
+        conara: None,
+    }
+}
+
+/// Paciente activo sin previsión, zona ni datos de contacto.
+pub fn paciente(rut: &str) -> Paciente {
+    Paciente {
+        id: 0,
+        rut: rut.into(),
+        nombres: "CLAUDIO".into(),
+        ap_paterno: "SAEZ".into(),
+        ap_materno: None,
+        fecha_nacimiento: None,
+        sexo: None,
+        cod_prevision: None,
+        direccion: None,
+        comuna: None,
+        ciudad: None,
+        cod_zona: None,
+        latitud: None,
+        longitud: None,
+        email: None,
+        telefonos: None,
+        cod_cliente: None,
+        estatus: 1,
+    }
+}
+
+/// Atención de control sin diagnósticos; salvo en borrador, firmada al crearla.
+pub fn atencion(id_paciente: u32, id_prof: u32, estado: &str, creada_en: NaiveDateTime) -> AtencionDetalle {
+    AtencionDetalle {
+        atencion: Atencion {
+            id: 0,
+            id_paciente,
+            id_prof,
+            id_cita: None,
+            motivo: "Control".into(),
+            anamnesis: None,
+            examen_fisico: None,
+            indicaciones: None,
+            estado: estado.into(),
+            creada_en,
+            firmada_en: (estado != ATENCION_BORRADOR).then_some(creada_en),
+        },
+        diagnosticos: Vec::new(),
+        adjuntos: Vec::new(),
+        adendas: Vec::new(),
+    }
+}
+
+/// Repositorio en memoria con los profesionales y los pacientes, que
+/// reciben ids desde 1 en el orden dado.
+pub async fn repositorio(profesionales: Vec<Profesional>, pacientes: Vec<Paciente>) -> MockRepository {
+    let repo = MockRepository::default().with_profesionales(profesionales);
+    for paciente in &pacientes {
+        PacienteRepository::create(&repo, paciente).await.unwrap();
+    }
+    repo
+}
+
+pub async fn app_state(profesionales: Vec<Profesional>, pacientes: Vec<Paciente>) -> AppState<MockRepository> {
+    AppState::new(repositorio(profesionales, pacientes).await)
+}
@@ -0,0 +1,1480 @@
+use std::sync::{Arc, Mutex, MutexGuard};
+use chrono::{NaiveDate, NaiveDateTime};
+use crate::{
+    consentimientos, tiempo,
+    models::{
+        Usuario, Paciente, PacienteFiltro, Prevision, PrevisionFiltro, Profesional, ProfesionalFiltro,
+        BloqueDisponibilidad, Cita, CitaFiltro, DisponibilidadFiltro, Feriado, Zona, CITA_REPROGRAMADA,
+        Adenda, AtencionDetalle, AtencionFiltro, ATENCION_BORRADOR, ATENCION_FIRMADA, Cie10,
+        EventoVisita, MarcaVisita, Visita, VisitaFiltro, VISITA_PENDIENTE,
+        RecetaDetalle, RecetaFiltro, RECETA_ANULADA, RECETA_EMITIDA,
+        DocumentoClinico, DocumentoDetalle, DocumentoFiltro, Plantilla, VersionDocumento,
+        ClaveFirma, Firma, FirmaFiltro, Teleconsulta, EntradaEspera,
+        Conversacion, ConversacionFiltro, Lectura, MensajeCifrado, Participante,
+        Notificacion, NotificacionFiltro, PreferenciaNotificacion, CANAL_APP, NOTIFICACION_ENVIADA,
+        NOTIFICACION_LEIDA, NOTIFICACION_PENDIENTE, RecordatorioCita, RespuestaCita,
+        Adjunto, AdjuntoFiltro, Actualizado, ConsultaFhir, PaginaFhir,
+        DerivacionDetalle, DerivacionFiltro, Importacion, ResultadoImportacion,
+        EventoConsentimiento, PlantillaConsentimiento,
+        Cargo, CargoFiltro, LoteFacturacion, LoteFiltro, Tarifa, TarifaFiltro, CARGO_FACTURADO, CARGO_PENDIENTE,
+        RetencionHonorarios, TarifaHonorario, TarifaHonorarioFiltro, Contrato, ContratoFiltro, SituacionContractual,
+        AlertaCredencial, Credencial, CredencialFiltro,
+    },
+    error::AppError,
+};
+use super::{
+    UsuarioRepository, PacienteRepository, PrevisionRepository, ProfesionalRepository, AgendaRepository,
+    ZonaRepository, FeriadoRepository, AtencionRepository, Cie10Repository, VisitaRepository,
+    RecetaRepository, DocumentoRepository, FirmaRepository, TeleconsultaRepository,
+    SalaEsperaRepository, MensajeRepository, NotificacionRepository, RecordatorioRepository,
+    AdjuntoRepository, FhirRepository, DerivacionRepository, ConsentimientoRepository,
+    FacturacionRepository, HonorarioRepository, ContratoRepository, CredencialRepository,
+};
+
+/// Repositorio en memoria para pruebas de handlers sin base de datos.
+#[derive(Clone, Default)]
+pub struct MockRepository {
+    usuarios: Arc<Mutex<Vec<Usuario>>>,
+    pacientes: Arc<Mutex<Vec<Paciente>>>,
+    /// `fecha_actualizacion` de cada paciente, que en MySQL mantiene la tabla.
+    pacientes_actualizados: Arc<Mutex<Vec<(u32, NaiveDateTime)>>>,
+    previsiones: Arc<Mutex<Vec<Prevision>>>,
+    profesionales: Arc<Mutex<Vec<Profesional>>>,
+    bloques: Arc<Mutex<Vec<BloqueDisponibilidad>>>,
+    citas: Arc<Mutex<Vec<Cita>>>,
+    zonas: Arc<Mutex<Vec<Zona>>>,
+    feriados: Arc<Mutex<Vec<Feriado>>>,
+    atenciones: Arc<Mutex<Vec<AtencionDetalle>>>,
+    cie10_versiones: Arc<Mutex<Vec<(String, NaiveDate)>>>,
+    cie10: Arc<Mutex<Vec<(String, Cie10)>>>,
+    visitas: Arc<Mutex<Vec<Visita>>>,
+    visita_eventos: Arc<Mutex<Vec<(u32, EventoVisita)>>>,
+    visita_marcas: Arc<Mutex<Vec<MarcaVisita>>>,
+    recetas: Arc<Mutex<Vec<RecetaDetalle>>>,
+    plantillas: Arc<Mutex<Vec<Plantilla>>>,
+    documentos: Arc<Mutex<Vec<DocumentoDetalle>>>,
+    firma_claves: Arc<Mutex<Vec<ClaveFirma>>>,
+    firmas: Arc<Mutex<Vec<Firma>>>,
+    teleconsultas: Arc<Mutex<Vec<Teleconsulta>>>,
+    sala_espera: Arc<Mutex<Vec<EntradaEspera>>>,
+    conversaciones: Arc<Mutex<Vec<Conversacion>>>,
+    mensajes: Arc<Mutex<Vec<MensajeCifrado>>>,
+    lecturas: Arc<Mutex<Vec<Lectura>>>,
+    notificacion_preferencias: Arc<Mutex<Vec<PreferenciaNotificacion>>>,
+    notificaciones: Arc<Mutex<Vec<Notificacion>>>,
+    recordatorios: Arc<Mutex<Vec<RecordatorioCita>>>,
+    respuestas: Arc<Mutex<Vec<RespuestaCita>>>,
+    adjuntos: Arc<Mutex<Vec<Adjunto>>>,
+    derivaciones: Arc<Mutex<Vec<DerivacionDetalle>>>,
+    consentimiento_plantillas: Arc<Mutex<Vec<PlantillaConsentimiento>>>,
+    consentimiento_eventos: Arc<Mutex<Vec<EventoConsentimiento>>>,
+    tarifas: Arc<Mutex<Vec<Tarifa>>>,
+    cargos: Arc<Mutex<Vec<Cargo>>>,
+    lotes: Arc<Mutex<Vec<LoteFacturacion>>>,
+    tarifas_honorario: Arc<Mutex<Vec<TarifaHonorario>>>,
+    retenciones: Arc<Mutex<Vec<RetencionHonorarios>>>,
+    contratos: Arc<Mutex<Vec<Contrato>>>,
+    credenciales: Arc<Mutex<Vec<Credencial>>>,
+    credencial_alertas: Arc<Mutex<Vec<AlertaCredencial>>>,
+}
+
+impl MockRepository {
+    /// `paso_profesionales` es una tabla de carga; en memoria se puebla directamente.
+    pub fn with_profesionales(self, profesionales: Vec<Profesional>) -> Self {
+        *self.profesionales.lock().unwrap() = profesionales;
+        self
+    }
+
+    /// `zonas_acceso` se carga por script; en memoria se puebla directamente.
+    pub fn with_zonas(self, zonas: Vec<Zona>) -> Self {
+        *self.zonas.lock().unwrap() = zonas;
+        self
+    }
+
+    fn marcar_paciente(&self, id: u32) -> Result<(), AppError> {
+        let mut marcas = lock(&self.pacientes_actualizados)?;
+        marcas.retain(|(p, _)| *p != id);
+        marcas.push((id, tiempo::ahora()));
+        Ok(())
+    }
+
+    fn consentimiento_vigente_sync(&self, id_paciente: u32, proposito: &str) -> Result<Option<EventoConsentimiento>, AppError> {
+        let versiones: Vec<PlantillaConsentimiento> = lock(&self.consentimiento_plantillas)?
+            .iter()
+            .filter(|p| p.proposito == proposito)
+            .cloned()
+            .collect();
+        let eventos: Vec<EventoConsentimiento> = lock(&self.consentimiento_eventos)?
+            .iter()
+            .filter(|e| e.id_paciente == id_paciente)
+            .cloned()
+            .collect();
+        Ok(consentimientos::vigente(&eventos, proposito, consentimientos::version_minima(&versiones)).cloned())
+    }
+
+    /// Filtro de `ConsultaFhir::consentimiento` sobre el paciente.
+    fn comparte(&self, consulta: &ConsultaFhir, id_paciente: u32) -> Result<bool, AppError> {
+        match &consulta.consentimiento {
+            Some(proposito) => Ok(self.consentimiento_vigente_sync(id_paciente, proposito)?.is_some()),
+            None => Ok(true),
+        }
+    }
+}
+
+fn lock<T>(m: &Mutex<T>) -> Result<MutexGuard<'_, T>, AppError> {
+    m.lock().map_err(|e| AppError::Internal(e.to_string()))
+}
+
+/// Siguiente id autoincremental para una colección en memoria.
+fn next_id<T>(items: &[T], id: impl Fn(&T) -> u32) -> u32 {
+    items.iter().map(id).max().unwrap_or(0) + 1
+}
+
+#[async_trait::async_trait]
+impl UsuarioRepository for MockRepository {
+    async fn get_by_id(&self, id: u32) -> Result<Option<Usuario>, AppError> {
+        Ok(lock(&self.usuarios)?.iter().find(|u| u.id == id).cloned())
+    }
+
+    async fn get_all(&self) -> Result<Vec<Usuario>, AppError> {
+        Ok(lock(&self.usuarios)?.clone())
+    }
+
+    async fn create(&self, usuario: &Usuario) -> Result<(), AppError> {
+        let mut usuarios = lock(&self.usuarios)?;
+        let mut usuario = usuario.clone();
+        usuario.id = next_id(&usuarios, |u| u.id);
+        usuarios.push(usuario);
+        Ok(())
+    }
+
+    async fn update(&self, usuario: &Usuario) -> Result<(), AppError> {
+        let mut usuarios = lock(&self.usuarios)?;
+        if let Some(u) = usuarios.iter_mut().find(|u| u.id == usuario.id) {
+            *u = usuario.clone();
+        }
+        Ok(())
+    }
+
+    async fn delete(&self, id: u32) -> Result<bool, AppError> {
+        let mut usuarios = lock(&self.usuarios)?;
+        let antes = usuarios.len();
+        usuarios.retain(|u| u.id != id);
+        Ok(usuarios.len() < antes)
+    }
+}
+
+#[async_trait::async_trait]
+impl PacienteRepository for MockRepository {
+    async fn get_by_id(&self, id: u32) -> Result<Option<Paciente>, AppError> {
+        Ok(lock(&self.pacientes)?.iter().find(|p| p.id == id).cloned())
+    }
+
+    async fn get_by_rut(&self, rut: &str) -> Result<Option<Paciente>, AppError> {
+        Ok(lock(&self.pacientes)?.iter().find(|p| p.rut == rut).cloned())
+    }
+
+    async fn search(&self, filtro: &PacienteFiltro) -> Result<Vec<Paciente>, AppError> {
+        let nombre = filtro.nombre.as_ref().map(|n| n.trim().to_uppercase());
+        Ok(lock(&self.pacientes)?
+            .iter()
+            .filter(|p| filtro.rut.as_ref().is_none_or(|r| &p.rut == r))
+            .filter(|p| {
+                nombre.as_ref().is_none_or(|n| {
+                    let completo = format!(
+                        "{} {} {}",
+                        p.nombres,
+                        p.ap_paterno,
+                        p.ap_materno.as_deref().unwrap_or_default()
+                    );
+                    completo.contains(n.as_str())
+                })
+            })
+            .cloned()
+            .collect())
+    }
+
+    async fn create(&self, paciente: &Paciente) -> Result<u32, AppError> {
+        let mut pacientes = lock(&self.pacientes)?;
+        if pacientes.iter().any(|p| p.rut == paciente.rut) {
+            return Err(AppError::Conflict(format!("Ya existe un paciente con RUT {}", paciente.rut)));
+        }
+        let mut paciente = paciente.clone();
+        paciente.id = next_id(&pacientes, |p| p.id);
+        pacientes.push(paciente.clone());
+        self.marcar_paciente(paciente.id)?;
+        Ok(paciente.id)
+    }
+
+    async fn update(&self, paciente: &Paciente) -> Result<(), AppError> {
+        let mut pacientes = lock(&self.pacientes)?;
+        if pacientes.iter().any(|p| p.rut == paciente.rut && p.id != paciente.id) {
+            return Err(AppError::Conflict(format!("Ya existe un paciente con RUT {}", paciente.rut)));
+        }
+        if let Some(p) = pacientes.iter_mut().find(|p| p.id == paciente.id) {
+            *p = paciente.clone();
+            self.marcar_paciente(paciente.id)?;
+        }
+        Ok(())
+    }
+
+    async fn delete(&self, id: u32) -> Result<bool, AppError> {
+        let mut pacientes = lock(&self.pacientes)?;
+        let antes = pacientes.len();
+        pacientes.retain(|p| p.id != id);
+        Ok(pacientes.len() < antes)
+    }
+}
+
+#[async_trait::async_trait]
+impl PrevisionRepository for MockRepository {
+    async fn get_by_id(&self, cod_prevision: u32) -> Result<Option<Prevision>, AppError> {
+        Ok(lock(&self.previsiones)?.iter().find(|p| p.cod_prevision == cod_prevision).cloned())
+    }
+
+    async fn search(&self, filtro: &PrevisionFiltro) -> Result<Vec<Prevision>, AppError> {
+        Ok(lock(&self.previsiones)?
+            .iter()
+            .filter(|p| filtro.tipo.as_ref().is_none_or(|t| p.tipo.eq_ignore_ascii_case(t)))
+            .filter(|p| filtro.vigente_en.is_none_or(|f| p.vigente_en(f)))
+            .cloned()
+            .collect())
+    }
+
+    async fn create(&self, prevision: &Prevision) -> Result<(), AppError> {
+        lock(&self.previsiones)?.push(prevision.clone());
+        Ok(())
+    }
+
+    async fn update(&self, prevision: &Prevision) -> Result<(), AppError> {
+        let mut previsiones = lock(&self.previsiones)?;
+        if let Some(p) = previsiones.iter_mut().find(|p| p.cod_prevision == prevision.cod_prevision) {
+            *p = prevision.clone();
+        }
+        Ok(())
+    }
+
+    async fn delete(&self, cod_prevision: u32) -> Result<bool, AppError> {
+        let mut previsiones = lock(&self.previsiones)?;
+        let antes = previsiones.len();
+        previsiones.retain(|p| p.cod_prevision != cod_prevision);
+        Ok(previsiones.len() < antes)
+    }
+}
+
+#[async_trait::async_trait]
+impl ProfesionalRepository for MockRepository {
+    async fn get_by_id(&self, id_prof: u32) -> Result<Option<Profesional>, AppError> {
+        Ok(lock(&self.profesionales)?.iter().find(|p| p.id_prof == id_prof).cloned())
+    }
+
+    async fn search(&self, filtro: &ProfesionalFiltro) -> Result<Vec<Profesional>, AppError> {
+        Ok(lock(&self.profesionales)?
+            .iter()
+            .filter(|p| filtro.especialidad.as_ref().is_none_or(|e| p.especialidad.as_ref() == Some(e)))
+            .filter(|p| filtro.zona.as_ref().is_none_or(|z| p.zona.as_ref() == Some(z)))
+            .cloned()
+            .collect())
+    }
+}
+
+#[async_trait::async_trait]
+impl AgendaRepository for MockRepository {
+    async fn get_bloque(&self, id: u32) -> Result<Option<BloqueDisponibilidad>, AppError> {
+        Ok(lock(&self.bloques)?.iter().find(|b| b.id == id).cloned())
+    }
+
+    async fn bloques_profesional(&self, id_prof: u32) -> Result<Vec<BloqueDisponibilidad>, AppError> {
+        Ok(lock(&self.bloques)?.iter().filter(|b| b.id_prof == id_prof).cloned().collect())
+    }
+
+    async fn bloques_disponibles(&self, filtro: &DisponibilidadFiltro) -> Result<Vec<BloqueDisponibilidad>, AppError> {
+        let profesionales = lock(&self.profesionales)?;
+        Ok(lock(&self.bloques)?
+            .iter()
+            .filter(|b| {
+                filtro.especialidad.as_ref().is_none_or(|e| {
+                    profesionales
+                        .iter()
+                        .any(|p| p.id_prof == b.id_prof && p.especialidad.as_ref() == Some(e))
+                })
+            })
+            .filter(|b| filtro.cod_zona.as_ref().is_none_or(|z| b.cod_zona.as_ref() == Some(z)))
+            .filter(|b| filtro.modalidad.as_ref().is_none_or(|m| b.modalidad.eq_ignore_ascii_case(m)))
+            .cloned()
+            .collect())
+    }
+
+    async fn create_bloque(&self, bloque: &BloqueDisponibilidad) -> Result<u32, AppError> {
+        let mut bloques = lock(&self.bloques)?;
+        let mut bloque = bloque.clone();
+        bloque.id = next_id(&bloques, |b| b.id);
+        bloques.push(bloque.clone());
+        Ok(bloque.id)
+    }
+
+    async fn delete_bloque(&self, id: u32) -> Result<bool, AppError> {
+        let mut bloques = lock(&self.bloques)?;
+        let antes = bloques.len();
+        bloques.retain(|b| b.id != id);
+        Ok(bloques.len() < antes)
+    }
+
+    async fn get_cita(&self, id: u32) -> Result<Option<Cita>, AppError> {
+        Ok(lock(&self.citas)?.iter().find(|c| c.id == id).cloned())
+    }
+
+    async fn search_citas(&self, filtro: &CitaFiltro) -> Result<Vec<Cita>, AppError> {
+        Ok(lock(&self.citas)?
+            .iter()
+            .filter(|c| filtro.id_prof.is_none_or(|id| c.id_prof == id))
+            .filter(|c| filtro.id_paciente.is_none_or(|id| c.id_paciente == id))
+            .filter(|c| filtro.desde.is_none_or(|d| c.fin > d))
+            .filter(|c| filtro.hasta.is_none_or(|h| c.inicio < h))
+            .cloned()
+            .collect())
+    }
+
+    async fn reservar_cita(&self, cita: &Cita, ocupados: usize, reprogramada: Option<u32>) -> Result<u32, AppError> {
+        let mut citas = lock(&self.citas)?;
+        let traslapadas = |c: &&Cita| Some(c.id) != reprogramada && c.activa() && c.se_traslapa(cita.inicio, cita.fin);
+        if citas.iter().filter(traslapadas).any(|c| c.id_paciente == cita.id_paciente) {
+            return Err(AppError::Conflict("El paciente ya tiene una cita en ese horario".into()));
+        }
+        if citas.iter().filter(traslapadas).filter(|c| c.id_prof == cita.id_prof).count() > ocupados {
+            return Err(AppError::Conflict("El horario ya está ocupado".into()));
+        }
+        if let Some(c) = citas.iter_mut().find(|c| Some(c.id) == reprogramada) {
+            c.estado = CITA_REPROGRAMADA.into();
+        }
+        let mut cita = cita.clone();
+        cita.id = next_id(&citas, |c| c.id);
+        citas.push(cita.clone());
+        Ok(cita.id)
+    }
+
+    async fn update_cita(&self, cita: &Cita) -> Result<(), AppError> {
+        let mut citas = lock(&self.citas)?;
+        if let Some(c) = citas.iter_mut().find(|c| c.id == cita.id) {
+            *c = cita.clone();
+        }
+        Ok(())
+    }
+}
+
+#[async_trait::async_trait]
+impl ZonaRepository for MockRepository {
+    async fn get_by_id(&self, cod_zona: &str) -> Result<Option<Zona>, AppError> {
+        Ok(lock(&self.zonas)?.iter().find(|z| z.cod_zona == cod_zona).cloned())
+    }
+
+    async fn get_all(&self) -> Result<Vec<Zona>, AppError> {
+        Ok(lock(&self.zonas)?.clone())
+    }
+
+    async fn update(&self, zona: &Zona) -> Result<(), AppError> {
+        let mut zonas = lock(&self.zonas)?;
+        if let Some(z) = zonas.iter_mut().find(|z| z.cod_zona == zona.cod_zona) {
+            *z = zona.clone();
+        }
+        Ok(())
+    }
+}
+
+#[async_trait::async_trait]
+impl FeriadoRepository for MockRepository {
+    async fn search(&self, desde: NaiveDate, hasta: NaiveDate) -> Result<Vec<Feriado>, AppError> {
+        Ok(lock(&self.feriados)?
+            .iter()
+            .filter(|f| desde <= f.fecha && f.fecha <= hasta)
+            .cloned()
+            .collect())
+    }
+
+    async fn create(&self, feriado: &Feriado) -> Result<u32, AppError> {
+        let mut feriados = lock(&self.feriados)?;
+        let mut feriado = feriado.clone();
+        feriado.id = next_id(&feriados, |f| f.id);
+        feriados.push(feriado.clone());
+        Ok(feriado.id)
+    }
+
+    async fn delete(&self, id: u32) -> Result<bool, AppError> {
+        let mut feriados = lock(&self.feriados)?;
+        let antes = feriados.len();
+        feriados.retain(|f| f.id != id);
+        Ok(feriados.len() < antes)
+    }
+}
+
+#[async_trait::async_trait]
+impl AtencionRepository for MockRepository {
+    async fn get_by_id(&self, id: u32) -> Result<Option<AtencionDetalle>, AppError> {
+        Ok(lock(&self.atenciones)?.iter().find(|a| a.atencion.id == id).cloned())
+    }
+
+    async fn search(&self, filtro: &AtencionFiltro) -> Result<Vec<AtencionDetalle>, AppError> {
+        let mut atenciones: Vec<AtencionDetalle> = lock(&self.atenciones)?
+            .iter()
+            .filter(|d| filtro.id_paciente.is_none_or(|id| d.atencion.id_paciente == id))
+            .filter(|d| filtro.id_prof.is_none_or(|id| d.atencion.id_prof == id))
+            .filter(|d| filtro.id_cita.is_none_or(|id| d.atencion.id_cita == Some(id)))
+            .filter(|d| filtro.estado.as_ref().is_none_or(|e| d.atencion.estado.eq_ignore_ascii_case(e)))
+            .filter(|d| filtro.desde.is_none_or(|f| d.atencion.creada_en >= f))
+            .filter(|d| filtro.hasta.is_none_or(|f| d.atencion.creada_en < f))
+            .cloned()
+            .collect();
+        atenciones.sort_by_key(|a| std::cmp::Reverse(a.atencion.creada_en));
+        Ok(atenciones)
+    }
+
+    async fn create(&self, atencion: &AtencionDetalle) -> Result<u32, AppError> {
+        let mut atenciones = lock(&self.atenciones)?;
+        let mut atencion = atencion.clone();
+        atencion.atencion.id = next_id(&atenciones, |a| a.atencion.id);
+        atenciones.push(atencion.clone());
+        Ok(atencion.atencion.id)
+    }
+
+    async fn update_borrador(&self, atencion: &AtencionDetalle) -> Result<bool, AppError> {
+        let mut atenciones = lock(&self.atenciones)?;
+        match atenciones.iter_mut().find(|a| a.atencion.id == atencion.atencion.id) {
+            Some(a) if a.atencion.estado == ATENCION_BORRADOR => {
+                *a = atencion.clone();
+                Ok(true)
+            }
+            _ => Ok(false),
+        }
+    }
+
+    async fn firmar(&self, id: u32, firmada_en: NaiveDateTime) -> Result<bool, AppError> {
+        let mut atenciones = lock(&self.atenciones)?;
+        match atenciones.iter_mut().find(|a| a.atencion.id == id) {
+            Some(a) if a.atencion.estado == ATENCION_BORRADOR => {
+                a.atencion.estado = ATENCION_FIRMADA.into();
+                a.atencion.firmada_en = Some(firmada_en);
+                Ok(true)
+            }
+            _ => Ok(false),
+        }
+    }
+
+    async fn create_adenda(&self, adenda: &Adenda) -> Result<u32, AppError> {
+        let mut atenciones = lock(&self.atenciones)?;
+        let id = atenciones.iter().flat_map(|a| &a.adendas).map(|a| a.id).max().unwrap_or(0) + 1;
+        let atencion = atenciones
+            .iter_mut()
+            .find(|a| a.atencion.id == adenda.id_atencion)
+            .ok_or_else(|| AppError::Internal(format!("Atención {} no existe", adenda.id_atencion)))?;
+        atencion.adendas.push(Adenda { id, ..adenda.clone() });
+        Ok(id)
+    }
+}
+
+#[async_trait::async_trait]
+impl Cie10Repository for MockRepository {
+    async fn version_vigente(&self, fecha: NaiveDate) -> Result<Option<String>, AppError> {
+        Ok(lock(&self.cie10_versiones)?
+            .iter()
+            .filter(|(_, desde)| *desde <= fecha)
+            .max_by_key(|(_, desde)| *desde)
+            .map(|(version, _)| version.clone()))
+    }
+
+    async fn codigos(&self, version: &str) -> Result<Vec<Cie10>, AppError> {
+        Ok(lock(&self.cie10)?
+            .iter()
+            .filter(|(v, _)| v == version)
+            .map(|(_, c)| c.clone())
+            .collect())
+    }
+
+    async fn importar(&self, version: &str, vigente_desde: NaiveDate, codigos: &[Cie10]) -> Result<usize, AppError> {
+        let mut versiones = lock(&self.cie10_versiones)?;
+        versiones.retain(|(v, _)| v != version);
+        versiones.push((version.to_string(), vigente_desde));
+
+        let mut cie10 = lock(&self.cie10)?;
+        cie10.retain(|(v, _)| v != version);
+        cie10.extend(codigos.iter().map(|c| (version.to_string(), c.clone())));
+        Ok(codigos.len())
+    }
+}
+
+#[async_trait::async_trait]
+impl VisitaRepository for MockRepository {
+    async fn get_by_id(&self, id: u32) -> Result<Option<Visita>, AppError> {
+        Ok(lock(&self.visitas)?.iter().find(|v| v.id == id).cloned())
+    }
+
+    async fn search(&self, filtro: &VisitaFiltro) -> Result<Vec<Visita>, AppError> {
+        let mut visitas: Vec<Visita> = lock(&self.visitas)?
+            .iter()
+            .filter(|v| filtro.id_prof.is_none_or(|id| v.id_prof == Some(id)))
+            .filter(|v| filtro.id_paciente.is_none_or(|id| v.id_paciente == id))
+            .filter(|v| filtro.cod_zona.as_ref().is_none_or(|z| &v.cod_zona == z))
+            .filter(|v| filtro.fecha.is_none_or(|f| v.fecha == f))
+            .filter(|v| filtro.desde.is_none_or(|d| v.fecha >= d))
+            .filter(|v| filtro.hasta.is_none_or(|h| v.fecha <= h))
+            .filter(|v| filtro.estado.as_ref().is_none_or(|e| v.estado.eq_ignore_ascii_case(e)))
+            .cloned()
+            .collect();
+        visitas.sort_by_key(|v| (v.fecha, v.ventana_desde.is_none(), v.ventana_desde, v.id));
+        Ok(visitas)
+    }
+
+    async fn create(&self, visita: &Visita) -> Result<u32, AppError> {
+        let mut visitas = lock(&self.visitas)?;
+        let mut visita = visita.clone();
+        visita.id = next_id(&visitas, |v| v.id);
+        lock(&self.visita_eventos)?.push((visita.id, EventoVisita {
+            estado: visita.estado.clone(),
+            motivo: None,
+            registrado_en: visita.creada_en,
+        }));
+        visitas.push(visita.clone());
+        Ok(visita.id)
+    }
+
+    async fn asignar(&self, id: u32, id_prof: u32) -> Result<bool, AppError> {
+        let mut visitas = lock(&self.visitas)?;
+        match visitas.iter_mut().find(|v| v.id == id) {
+            Some(v) if v.estado == VISITA_PENDIENTE => {
+                v.id_prof = Some(id_prof);
+                Ok(true)
+            }
+            _ => Ok(false),
+        }
+    }
+
+    async fn cambiar_estado(&self, id: u32, desde: &str, evento: &EventoVisita) -> Result<bool, AppError> {
+        let mut visitas = lock(&self.visitas)?;
+        match visitas.iter_mut().find(|v| v.id == id) {
+            Some(v) if v.estado == desde => {
+                v.estado = evento.estado.clone();
+                v.motivo_estado = evento.motivo.clone();
+                lock(&self.visita_eventos)?.push((id, evento.clone()));
+                Ok(true)
+            }
+            _ => Ok(false),
+        }
+    }
+
+    async fn historial(&self, id: u32) -> Result<Vec<EventoVisita>, AppError> {
+        Ok(lock(&self.visita_eventos)?
+            .iter()
+            .filter(|(id_visita, _)| *id_visita == id)
+            .map(|(_, e)| e.clone())
+            .collect())
+    }
+
+    async fn registrar_marca(&self, marca: &MarcaVisita) -> Result<Option<u32>, AppError> {
+        let mut marcas = lock(&self.visita_marcas)?;
+        if marcas.iter().any(|m| m.id_visita == marca.id_visita && m.tipo == marca.tipo) {
+            return Ok(None);
+        }
+        let mut marca = marca.clone();
+        marca.id = next_id(&marcas, |m| m.id);
+        marcas.push(marca.clone());
+        Ok(Some(marca.id))
+    }
+
+    async fn marcas(&self, ids_visita: &[u32]) -> Result<Vec<MarcaVisita>, AppError> {
+        let mut marcas: Vec<MarcaVisita> = lock(&self.visita_marcas)?
+            .iter()
+            .filter(|m| ids_visita.contains(&m.id_visita))
+            .cloned()
+            .collect();
+        marcas.sort_by_key(|m| m.registrada_en);
+        Ok(marcas)
+    }
+}
+
+#[async_trait::async_trait]
+impl RecetaRepository for MockRepository {
+    async fn get_by_id(&self, id: u32) -> Result<Option<RecetaDetalle>, AppError> {
+        Ok(lock(&self.recetas)?.iter().find(|r| r.receta.id == id).cloned())
+    }
+
+    async fn get_by_codigo(&self, codigo: &str) -> Result<Option<RecetaDetalle>, AppError> {
+        Ok(lock(&self.recetas)?.iter().find(|r| r.receta.codigo == codigo).cloned())
+    }
+
+    async fn search(&self, filtro: &RecetaFiltro) -> Result<Vec<RecetaDetalle>, AppError> {
+        let mut recetas: Vec<RecetaDetalle> = lock(&self.recetas)?
+            .iter()
+            .filter(|r| filtro.id_paciente.is_none_or(|id| r.receta.id_paciente == id))
+            .filter(|r| filtro.id_prof.is_none_or(|id| r.receta.id_prof == id))
+            .filter(|r| filtro.id_atencion.is_none_or(|id| r.receta.id_atencion == id))
+            .filter(|r| filtro.estado.as_ref().is_none_or(|e| r.receta.estado.eq_ignore_ascii_case(e)))
+            .cloned()
+            .collect();
+        recetas.sort_by_key(|r| std::cmp::Reverse(r.receta.emitida_en));
+        Ok(recetas)
+    }
+
+    async fn create(&self, detalle: &RecetaDetalle) -> Result<u32, AppError> {
+        let mut recetas = lock(&self.recetas)?;
+        let mut detalle = detalle.clone();
+        detalle.receta.id = next_id(&recetas, |r| r.receta.id);
+        recetas.push(detalle.clone());
+        Ok(detalle.receta.id)
+    }
+
+    async fn anular(&self, id: u32, motivo: &str) -> Result<bool, AppError> {
+        let mut recetas = lock(&self.recetas)?;
+        match recetas.iter_mut().find(|r| r.receta.id == id) {
+            Some(r) if r.receta.estado == RECETA_EMITIDA => {
+                r.receta.estado = RECETA_ANULADA.into();
+                r.receta.motivo_anulacion = Some(motivo.into());
+                Ok(true)
+            }
+            _ => Ok(false),
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl DocumentoRepository for MockRepository {
+    async fn plantillas(&self) -> Result<Vec<Plantilla>, AppError> {
+        let plantillas = lock(&self.plantillas)?;
+        let mut vigentes: Vec<Plantilla> = plantillas
+            .iter()
+            .filter(|p| !plantillas.iter().any(|o| o.tipo == p.tipo && o.version > p.version))
+            .cloned()
+            .collect();
+        vigentes.sort_by(|a, b| a.tipo.cmp(&b.tipo));
+        Ok(vigentes)
+    }
+
+    async fn plantilla_vigente(&self, tipo: &str) -> Result<Option<Plantilla>, AppError> {
+        Ok(lock(&self.plantillas)?.iter().filter(|p| p.tipo == tipo).max_by_key(|p| p.version).cloned())
+    }
+
+    async fn crear_plantilla(&self, plantilla: &Plantilla) -> Result<u16, AppError> {
+        let mut plantillas = lock(&self.plantillas)?;
+        let mut plantilla = plantilla.clone();
+        plantilla.id = next_id(&plantillas, |p| p.id);
+        plantilla.version = plantillas.iter().filter(|p| p.tipo == plantilla.tipo).map(|p| p.version).max().unwrap_or(0) + 1;
+        plantillas.push(plantilla.clone());
+        Ok(plantilla.version)
+    }
+
+    async fn get_by_id(&self, id: u32) -> Result<Option<DocumentoDetalle>, AppError> {
+        Ok(lock(&self.documentos)?.iter().find(|d| d.documento.id == id).cloned())
+    }
+
+    async fn search(&self, filtro: &DocumentoFiltro) -> Result<Vec<DocumentoClinico>, AppError> {
+        let mut documentos: Vec<DocumentoClinico> = lock(&self.documentos)?
+            .iter()
+            .map(|d| &d.documento)
+            .filter(|d| filtro.id_paciente.is_none_or(|id| d.id_paciente == id))
+            .filter(|d| filtro.id_prof.is_none_or(|id| d.id_prof == id))
+            .filter(|d| filtro.tipo.as_ref().is_none_or(|t| d.tipo.eq_ignore_ascii_case(t)))
+            .filter(|d| filtro.cod_cliente.is_none_or(|c| d.cod_cliente == c))
+            .cloned()
+            .collect();
+        documentos.sort_by_key(|d| std::cmp::Reverse(d.creado_en));
+        Ok(documentos)
+    }
+
+    async fn create(&self, documento: &DocumentoClinico, version: &VersionDocumento) -> Result<(u32, u32), AppError> {
+        let mut documentos = lock(&self.documentos)?;
+        let mut documento = documento.clone();
+        documento.id = next_id(&documentos, |d| d.documento.id);
+        documento.folio = documentos
+            .iter()
+            .filter(|d| d.documento.cod_cliente == documento.cod_cliente)
+            .map(|d| d.documento.folio)
+            .max()
+            .unwrap_or(0) + 1;
+        let version = VersionDocumento { id_documento: documento.id, version: 1, ..version.clone() };
+        documentos.push(DocumentoDetalle { documento: documento.clone(), versiones: vec![version] });
+        Ok((documento.id, documento.folio))
+    }
+
+    async fn agregar_version(&self, version: &VersionDocumento) -> Result<u16, AppError> {
+        let mut documentos = lock(&self.documentos)?;
+        let detalle = documentos
+            .iter_mut()
+            .find(|d| d.documento.id == version.id_documento)
+            .ok_or(AppError::NotFound)?;
+        let numero = detalle.versiones.iter().map(|v| v.version).max().unwrap_or(0) + 1;
+        detalle.versiones.push(VersionDocumento { version: numero, ..version.clone() });
+        Ok(numero)
+    }
+}
+
+#[async_trait::async_trait]
+impl FirmaRepository for MockRepository {
+    async fn clave_vigente(&self, id_prof: u32) -> Result<Option<ClaveFirma>, AppError> {
+        Ok(lock(&self.firma_claves)?.iter().find(|c| c.id_prof == id_prof && c.revocada_en.is_none()).cloned())
+    }
+
+    async fn get_clave(&self, id: u32) -> Result<Option<ClaveFirma>, AppError> {
+        Ok(lock(&self.firma_claves)?.iter().find(|c| c.id == id).cloned())
+    }
+
+    async fn claves(&self, id_prof: u32) -> Result<Vec<ClaveFirma>, AppError> {
+        Ok(lock(&self.firma_claves)?.iter().filter(|c| c.id_prof == id_prof).cloned().collect())
+    }
+
+    async fn crear_clave(&self, clave: &ClaveFirma) -> Result<u32, AppError> {
+        let mut claves = lock(&self.firma_claves)?;
+        for c in claves.iter_mut().filter(|c| c.id_prof == clave.id_prof && c.revocada_en.is_none()) {
+            c.revocada_en = Some(clave.creada_en);
+        }
+        let mut clave = clave.clone();
+        clave.id = next_id(&claves, |c| c.id);
+        clave.revocada_en = None;
+        claves.push(clave.clone());
+        Ok(clave.id)
+    }
+
+    async fn get_by_id(&self, id: u32) -> Result<Option<Firma>, AppError> {
+        Ok(lock(&self.firmas)?.iter().find(|f| f.id == id).cloned())
+    }
+
+    async fn search(&self, filtro: &FirmaFiltro) -> Result<Vec<Firma>, AppError> {
+        let mut firmas: Vec<Firma> = lock(&self.firmas)?
+            .iter()
+            .filter(|f| filtro.tipo.as_ref().is_none_or(|t| f.tipo.eq_ignore_ascii_case(t)))
+            .filter(|f| filtro.id_documento.is_none_or(|id| f.id_documento == id))
+            .filter(|f| filtro.id_prof.is_none_or(|id| f.id_prof == id))
+            .cloned()
+            .collect();
+        firmas.sort_by_key(|f| std::cmp::Reverse(f.firmada_en));
+        Ok(firmas)
+    }
+
+    async fn create(&self, firma: &Firma) -> Result<Option<u32>, AppError> {
+        let mut firmas = lock(&self.firmas)?;
+        if firmas.iter().any(|f| f.tipo == firma.tipo && f.id_documento == firma.id_documento && f.version == firma.version) {
+            return Ok(None);
+        }
+        let mut firma = firma.clone();
+        firma.id = next_id(&firmas, |f| f.id);
+        firmas.push(firma.clone());
+        Ok(Some(firma.id))
+    }
+}
+
+#[async_trait::async_trait]
+impl TeleconsultaRepository for MockRepository {
+    async fn get_by_cita(&self, id_cita: u32) -> Result<Option<Teleconsulta>, AppError> {
+        Ok(lock(&self.teleconsultas)?.iter().find(|t| t.id_cita == id_cita).cloned())
+    }
+
+    async fn guardar(&self, teleconsulta: &Teleconsulta) -> Result<(), AppError> {
+        let mut teleconsultas = lock(&self.teleconsultas)?;
+        match teleconsultas.iter_mut().find(|t| t.id_cita == teleconsulta.id_cita) {
+            Some(t) => {
+                t.token_paciente = teleconsulta.token_paciente.clone();
+                t.token_profesional = teleconsulta.token_profesional.clone();
+            }
+            None => teleconsultas.push(teleconsulta.clone()),
+        }
+        Ok(())
+    }
+
+    async fn iniciar(&self, id_cita: u32, en: NaiveDateTime) -> Result<bool, AppError> {
+        let mut teleconsultas = lock(&self.teleconsultas)?;
+        match teleconsultas.iter_mut().find(|t| t.id_cita == id_cita && t.iniciada_en.is_none()) {
+            Some(t) => {
+                t.iniciada_en = Some(en);
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
+
+    async fn terminar(&self, id_cita: u32, en: NaiveDateTime) -> Result<bool, AppError> {
+        let mut teleconsultas = lock(&self.teleconsultas)?;
+        match teleconsultas.iter_mut().find(|t| t.id_cita == id_cita && t.terminada_en.is_none()) {
+            Some(t) => {
+                t.terminada_en = Some(en);
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl SalaEsperaRepository for MockRepository {
+    async fn get_by_cita(&self, id_cita: u32) -> Result<Option<EntradaEspera>, AppError> {
+        Ok(lock(&self.sala_espera)?.iter().find(|e| e.id_cita == id_cita).cloned())
+    }
+
+    async fn guardar(&self, entrada: &EntradaEspera) -> Result<(), AppError> {
+        let mut entradas = lock(&self.sala_espera)?;
+        entradas.retain(|e| e.id_cita != entrada.id_cita);
+        entradas.push(entrada.clone());
+        Ok(())
+    }
+
+    async fn abiertas(&self, id_prof: u32) -> Result<Vec<EntradaEspera>, AppError> {
+        Ok(lock(&self.sala_espera)?.iter().filter(|e| e.id_prof == id_prof && e.abierta()).cloned().collect())
+    }
+
+    async fn profesionales_en_espera(&self) -> Result<Vec<u32>, AppError> {
+        let mut profesionales: Vec<u32> = lock(&self.sala_espera)?.iter().filter(|e| e.abierta()).map(|e| e.id_prof).collect();
+        profesionales.sort();
+        profesionales.dedup();
+        Ok(profesionales)
+    }
+}
+
+#[async_trait::async_trait]
+impl MensajeRepository for MockRepository {
+    async fn get_conversacion(&self, id: u32) -> Result<Option<Conversacion>, AppError> {
+        Ok(lock(&self.conversaciones)?.iter().find(|c| c.id == id).cloned())
+    }
+
+    async fn conversaciones(&self, filtro: &ConversacionFiltro) -> Result<Vec<Conversacion>, AppError> {
+        let mut conversaciones: Vec<Conversacion> = lock(&self.conversaciones)?
+            .iter()
+            .filter(|c| filtro.id_paciente.is_none_or(|id| c.id_paciente == id))
+            .filter(|c| filtro.zonas.as_ref().is_none_or(|zonas| c.cod_zona.as_ref().is_some_and(|z| zonas.contains(z))))
+            .filter(|c| filtro.cod_cliente.is_none() || c.cod_cliente == filtro.cod_cliente)
+            .cloned()
+            .collect();
+        conversaciones.sort_by_key(|c| std::cmp::Reverse(c.ultimo_mensaje_en.unwrap_or(c.creada_en)));
+        Ok(conversaciones)
+    }
+
+    async fn create_conversacion(&self, conversacion: &Conversacion) -> Result<u32, AppError> {
+        let mut conversaciones = lock(&self.conversaciones)?;
+        let id = next_id(&conversaciones, |c| c.id);
+        conversaciones.push(Conversacion { id, ..conversacion.clone() });
+        Ok(id)
+    }
+
+    async fn create_mensaje(&self, mensaje: &MensajeCifrado) -> Result<u32, AppError> {
+        let mut mensajes = lock(&self.mensajes)?;
+        let id = next_id(&mensajes, |m| m.id);
+        mensajes.push(MensajeCifrado { id, ..mensaje.clone() });
+        if let Some(c) = lock(&self.conversaciones)?.iter_mut().find(|c| c.id == mensaje.id_conversacion) {
+            c.ultimo_mensaje_en = Some(mensaje.enviado_en);
+        }
+        Ok(id)
+    }
+
+    async fn mensajes(&self, id_conversacion: u32, antes_de: Option<u32>, limite: u16) -> Result<Vec<MensajeCifrado>, AppError> {
+        let mut mensajes: Vec<MensajeCifrado> = lock(&self.mensajes)?
+            .iter()
+            .filter(|m| m.id_conversacion == id_conversacion && antes_de.is_none_or(|a| m.id < a))
+            .cloned()
+            .collect();
+        mensajes.sort_by_key(|m| std::cmp::Reverse(m.id));
+        mensajes.truncate(limite as usize);
+        Ok(mensajes)
+    }
+
+    async fn lecturas(&self, id_conversacion: u32) -> Result<Vec<Lectura>, AppError> {
+        Ok(lock(&self.lecturas)?.iter().filter(|l| l.id_conversacion == id_conversacion).cloned().collect())
+    }
+
+    async fn marcar_leido(&self, lectura: &Lectura) -> Result<bool, AppError> {
+        let mut lecturas = lock(&self.lecturas)?;
+        let existente = lecturas.iter_mut().find(|l| {
+            l.id_conversacion == lectura.id_conversacion && l.lector_tipo == lectura.lector_tipo && l.lector_id == lectura.lector_id
+        });
+        match existente {
+            Some(l) if l.hasta >= lectura.hasta => Ok(false),
+            Some(l) => {
+                *l = lectura.clone();
+                Ok(true)
+            }
+            None => {
+                lecturas.push(lectura.clone());
+                Ok(true)
+            }
+        }
+    }
+
+    async fn no_leidos(&self, id_conversacion: u32, lector: &Participante) -> Result<u32, AppError> {
+        let hasta = lock(&self.lecturas)?
+            .iter()
+            .find(|l| l.id_conversacion == id_conversacion && l.lector_tipo == lector.tipo && l.lector_id == lector.id)
+            .map_or(0, |l| l.hasta);
+        Ok(lock(&self.mensajes)?
+            .iter()
+            .filter(|m| m.id_conversacion == id_conversacion && m.id > hasta)
+            .filter(|m| !(m.autor_tipo == lector.tipo && m.autor_id == lector.id))
+            .count() as u32)
+    }
+
+    async fn depurar(&self, ahora: NaiveDateTime) -> Result<u64, AppError> {
+        let conversaciones = lock(&self.conversaciones)?;
+        let mut mensajes = lock(&self.mensajes)?;
+        let antes = mensajes.len();
+        mensajes.retain(|m| {
+            conversaciones
+                .iter()
+                .find(|c| c.id == m.id_conversacion)
+                .is_none_or(|c| m.enviado_en >= ahora - chrono::Duration::days(c.retencion_dias.into()))
+        });
+        Ok((antes - mensajes.len()) as u64)
+    }
+}
+
+#[async_trait::async_trait]
+impl NotificacionRepository for MockRepository {
+    async fn preferencias(&self, destinatario_tipo: &str, destinatario_id: u32) -> Result<Option<PreferenciaNotificacion>, AppError> {
+        Ok(lock(&self.notificacion_preferencias)?
+            .iter()
+            .find(|p| p.destinatario_tipo == destinatario_tipo && p.destinatario_id == destinatario_id)
+            .cloned())
+    }
+
+    async fn guardar_preferencias(&self, preferencia: &PreferenciaNotificacion) -> Result<(), AppError> {
+        let mut preferencias = lock(&self.notificacion_preferencias)?;
+        preferencias.retain(|p| !(p.destinatario_tipo == preferencia.destinatario_tipo && p.destinatario_id == preferencia.destinatario_id));
+        preferencias.push(preferencia.clone());
+        Ok(())
+    }
+
+    async fn encolar(&self, notificacion: &Notificacion) -> Result<u32, AppError> {
+        let mut notificaciones = lock(&self.notificaciones)?;
+        let id = next_id(&notificaciones, |n| n.id);
+        notificaciones.push(Notificacion { id, ..notificacion.clone() });
+        Ok(id)
+    }
+
+    async fn get_notificacion(&self, id: u32) -> Result<Option<Notificacion>, AppError> {
+        Ok(lock(&self.notificaciones)?.iter().find(|n| n.id == id).cloned())
+    }
+
+    async fn notificaciones(&self, filtro: &NotificacionFiltro) -> Result<Vec<Notificacion>, AppError> {
+        let igual = |valor: &str, filtro: &Option<String>| filtro.as_ref().is_none_or(|f| f.eq_ignore_ascii_case(valor));
+        let mut notificaciones: Vec<Notificacion> = lock(&self.notificaciones)?
+            .iter()
+            .filter(|n| igual(&n.destinatario_tipo, &filtro.destinatario_tipo))
+            .filter(|n| filtro.destinatario_id.is_none_or(|id| n.destinatario_id == id))
+            .filter(|n| igual(&n.canal, &filtro.canal) && igual(&n.estado, &filtro.estado) && igual(&n.plantilla, &filtro.plantilla))
+            .cloned()
+            .collect();
+        notificaciones.sort_by_key(|n| std::cmp::Reverse((n.creada_en, n.id)));
+        Ok(notificaciones)
+    }
+
+    async fn reclamar(&self, ahora: NaiveDateTime, reservar_hasta: NaiveDateTime, limite: u32) -> Result<Vec<Notificacion>, AppError> {
+        let mut notificaciones = lock(&self.notificaciones)?;
+        let mut pendientes: Vec<&mut Notificacion> = notificaciones
+            .iter_mut()
+            .filter(|n| n.estado == NOTIFICACION_PENDIENTE && n.proximo_intento_en <= ahora)
+            .collect();
+        pendientes.sort_by_key(|n| n.proximo_intento_en);
+        pendientes.truncate(limite as usize);
+        Ok(pendientes
+            .into_iter()
+            .map(|n| {
+                n.proximo_intento_en = reservar_hasta;
+                n.clone()
+            })
+            .collect())
+    }
+
+    async fn registrar_intento(&self, notificacion: &Notificacion) -> Result<(), AppError> {
+        if let Some(n) = lock(&self.notificaciones)?.iter_mut().find(|n| n.id == notificacion.id) {
+            n.estado = notificacion.estado.clone();
+            n.intentos = notificacion.intentos;
+            n.proximo_intento_en = notificacion.proximo_intento_en;
+            n.ultimo_error = notificacion.ultimo_error.clone();
+            n.enviada_en = notificacion.enviada_en;
+        }
+        Ok(())
+    }
+
+    async fn marcar_leida(&self, id: u32, ahora: NaiveDateTime) -> Result<bool, AppError> {
+        let mut notificaciones = lock(&self.notificaciones)?;
+        match notificaciones.iter_mut().find(|n| n.id == id && n.canal == CANAL_APP && n.estado == NOTIFICACION_ENVIADA) {
+            Some(n) => {
+                n.estado = NOTIFICACION_LEIDA.into();
+                n.leida_en = Some(ahora);
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl RecordatorioRepository for MockRepository {
+    async fn registrar_recordatorio(&self, recordatorio: &RecordatorioCita) -> Result<bool, AppError> {
+        let mut recordatorios = lock(&self.recordatorios)?;
+        if recordatorios.iter().any(|r| r.id_cita == recordatorio.id_cita && r.tipo == recordatorio.tipo) {
+            return Ok(false);
+        }
+        recordatorios.push(recordatorio.clone());
+        Ok(true)
+    }
+
+    async fn recordatorios(&self, ids_cita: &[u32]) -> Result<Vec<RecordatorioCita>, AppError> {
+        Ok(lock(&self.recordatorios)?.iter().filter(|r| ids_cita.contains(&r.id_cita)).cloned().collect())
+    }
+
+    async fn get_respuesta(&self, id_cita: u32) -> Result<Option<RespuestaCita>, AppError> {
+        Ok(lock(&self.respuestas)?.iter().find(|r| r.id_cita == id_cita).cloned())
+    }
+
+    async fn guardar_respuesta(&self, respuesta: &RespuestaCita) -> Result<(), AppError> {
+        let mut respuestas = lock(&self.respuestas)?;
+        respuestas.retain(|r| r.id_cita != respuesta.id_cita);
+        respuestas.push(respuesta.clone());
+        Ok(())
+    }
+
+    async fn respuestas(&self, ids_cita: &[u32]) -> Result<Vec<RespuestaCita>, AppError> {
+        Ok(lock(&self.respuestas)?.iter().filter(|r| ids_cita.contains(&r.id_cita)).cloned().collect())
+    }
+}
+
+#[async_trait::async_trait]
+impl AdjuntoRepository for MockRepository {
+    async fn get_adjunto(&self, id: u32) -> Result<Option<Adjunto>, AppError> {
+        Ok(lock(&self.adjuntos)?.iter().find(|a| a.id == id).cloned())
+    }
+
+    async fn adjuntos(&self, filtro: &AdjuntoFiltro) -> Result<Vec<Adjunto>, AppError> {
+        Ok(lock(&self.adjuntos)?
+            .iter()
+            .filter(|a| filtro.id_paciente.is_none_or(|id| a.id_paciente == id))
+            .filter(|a| filtro.id_atencion.is_none_or(|id| a.id_atencion == Some(id)))
+            .cloned()
+            .collect())
+    }
+
+    async fn adjunto_por_hash(&self, id_paciente: u32, id_atencion: Option<u32>, sha256: &str) -> Result<Option<Adjunto>, AppError> {
+        Ok(lock(&self.adjuntos)?
+            .iter()
+            .find(|a| a.id_paciente == id_paciente && a.id_atencion == id_atencion && a.sha256 == sha256)
+            .cloned())
+    }
+
+    async fn create_adjunto(&self, adjunto: &Adjunto) -> Result<u32, AppError> {
+        let mut adjuntos = lock(&self.adjuntos)?;
+        let mut adjunto = adjunto.clone();
+        adjunto.id = next_id(&adjuntos, |a| a.id);
+        adjuntos.push(adjunto.clone());
+        Ok(adjunto.id)
+    }
+}
+
+/// Aplica `_id`, `_lastUpdated` y la paginación de una consulta FHIR.
+fn paginar_fhir<T>(
+    registros: Vec<Actualizado<T>>,
+    consulta: &ConsultaFhir,
+    id: impl Fn(&T) -> String,
+) -> PaginaFhir<T> {
+    let coincidencias: Vec<Actualizado<T>> = registros
+        .into_iter()
+        .filter(|r| consulta.ids.as_ref().is_none_or(|ids| ids.contains(&id(&r.registro))))
+        .filter(|r| consulta.actualizado_desde.is_none_or(|f| r.actualizado_en >= f))
+        .filter(|r| consulta.actualizado_hasta.is_none_or(|f| r.actualizado_en < f))
+        .collect();
+    PaginaFhir {
+        total: coincidencias.len() as u32,
+        registros: coincidencias
+            .into_iter()
+            .skip(consulta.salto as usize)
+            .take(consulta.cantidad as usize)
+            .collect(),
+    }
+}
+
+#[async_trait::async_trait]
+impl FhirRepository for MockRepository {
+    async fn fhir_pacientes(&self, consulta: &ConsultaFhir) -> Result<PaginaFhir<Paciente>, AppError> {
+        let marcas = lock(&self.pacientes_actualizados)?.clone();
+        let candidatos: Vec<Paciente> = lock(&self.pacientes)?
+            .iter()
+            .filter(|p| consulta.rut.as_ref().is_none_or(|r| &p.rut == r))
+            .cloned()
+            .collect();
+        let mut pacientes = Vec::new();
+        for p in candidatos {
+            if self.comparte(consulta, p.id)? {
+                pacientes.push(p);
+            }
+        }
+        let mut pacientes: Vec<Actualizado<Paciente>> = pacientes
+            .iter()
+            .map(|p| Actualizado {
+                registro: p.clone(),
+                actualizado_en: marcas.iter().find(|(id, _)| *id == p.id).map(|(_, f)| *f).unwrap_or_default(),
+            })
+            .collect();
+        pacientes.sort_by_key(|p| p.registro.id);
+        Ok(paginar_fhir(pacientes, consulta, |p| p.id.to_string()))
+    }
+
+    async fn fhir_profesionales(&self, consulta: &ConsultaFhir) -> Result<PaginaFhir<Profesional>, AppError> {
+        let compacto = |rut: &str| rut.replace(['.', '-'], "").trim_start_matches('0').to_string();
+        let mut profesionales: Vec<Actualizado<Profesional>> = lock(&self.profesionales)?
+            .iter()
+            .filter(|p| consulta.rut.as_ref().is_none_or(|r| compacto(&p.rut) == compacto(r)))
+            .filter(|p| consulta.rnpi.as_ref().is_none_or(|r| p.registro_rnpi.as_ref() == Some(r)))
+            .map(|p| Actualizado { registro: p.clone(), actualizado_en: NaiveDateTime::default() })
+            .collect();
+        profesionales.sort_by_key(|p| p.registro.id_prof);
+        Ok(paginar_fhir(profesionales, consulta, |p| p.id_prof.to_string()))
+    }
+
+    async fn fhir_atenciones(&self, consulta: &ConsultaFhir) -> Result<PaginaFhir<AtencionDetalle>, AppError> {
+        let candidatos = lock(&self.atenciones)?.clone();
+        let mut atenciones = Vec::new();
+        for d in candidatos {
+            if self.comparte(consulta, d.atencion.id_paciente)? {
+                atenciones.push(d);
+            }
+        }
+        let mut atenciones: Vec<Actualizado<AtencionDetalle>> = atenciones
+            .iter()
+            .map(|d| Actualizado {
+                registro: d.clone(),
+                actualizado_en: d.atencion.firmada_en.unwrap_or(d.atencion.creada_en),
+            })
+            .collect();
+        atenciones.sort_by_key(|a| a.registro.atencion.id);
+        Ok(paginar_fhir(atenciones, consulta, |a| a.atencion.id.to_string()))
+    }
+
+    async fn fhir_zonas(&self, consulta: &ConsultaFhir) -> Result<PaginaFhir<Zona>, AppError> {
+        let mut zonas: Vec<Actualizado<Zona>> = lock(&self.zonas)?
+            .iter()
+            .map(|z| Actualizado { registro: z.clone(), actualizado_en: NaiveDateTime::default() })
+            .collect();
+        zonas.sort_by(|a, b| a.registro.cod_zona.cmp(&b.registro.cod_zona));
+        Ok(paginar_fhir(zonas, consulta, |z| z.cod_zona.clone()))
+    }
+}
+
+#[async_trait::async_trait]
+impl DerivacionRepository for MockRepository {
+    async fn get_derivacion(&self, id: u32) -> Result<Option<DerivacionDetalle>, AppError> {
+        Ok(lock(&self.derivaciones)?.iter().find(|d| d.derivacion.id == id).cloned())
+    }
+
+    async fn derivaciones(&self, filtro: &DerivacionFiltro) -> Result<Vec<DerivacionDetalle>, AppError> {
+        let mut derivaciones: Vec<DerivacionDetalle> = lock(&self.derivaciones)?
+            .iter()
+            .filter(|d| filtro.id_paciente.is_none_or(|id| d.derivacion.id_paciente == id))
+            .filter(|d| filtro.estado.as_ref().is_none_or(|e| d.derivacion.estado.eq_ignore_ascii_case(e)))
+            .cloned()
+            .collect();
+        derivaciones.sort_by_key(|d| std::cmp::Reverse(d.derivacion.recibida_en));
+        Ok(derivaciones)
+    }
+
+    async fn importar_derivaciones(&self, importacion: &Importacion) -> Result<ResultadoImportacion, AppError> {
+        let mut resultado = ResultadoImportacion::default();
+        for p in &importacion.pacientes {
+            let existente = lock(&self.pacientes)?.iter().find(|e| e.rut == p.rut).map(|e| e.id);
+            match existente {
+                Some(id) => {
+                    let mut p = p.clone();
+                    p.id = id;
+                    PacienteRepository::update(self, &p).await?;
+                    resultado.pacientes.push((id, false));
+                }
+                None => resultado.pacientes.push((PacienteRepository::create(self, p).await?, true)),
+            }
+        }
+
+        let mut derivaciones = lock(&self.derivaciones)?;
+        let registradas = derivaciones.len();
+        for (k, (indice, detalle)) in importacion.derivaciones.iter().enumerate() {
+            let id_paciente = resultado.pacientes[*indice].0;
+            let existente = derivaciones
+                .iter()
+                .find(|d| d.derivacion.referencia_externa.is_some() && d.derivacion.referencia_externa == detalle.derivacion.referencia_externa)
+                .map(|d| (d.derivacion.id, d.derivacion.id_paciente));
+            if let Some((id, id_paciente_registrado)) = existente {
+                if id_paciente_registrado != id_paciente {
+                    resultado.rechazadas.push(k);
+                }
+                resultado.derivaciones.push((id, false));
+                continue;
+            }
+            let mut detalle = detalle.clone();
+            detalle.derivacion.id = next_id(&derivaciones, |d| d.derivacion.id);
+            detalle.derivacion.id_paciente = id_paciente;
+            resultado.derivaciones.push((detalle.derivacion.id, true));
+            derivaciones.push(detalle);
+        }
+        if !resultado.rechazadas.is_empty() {
+            derivaciones.truncate(registradas);
+        }
+        Ok(resultado)
+    }
+}
+
+#[async_trait::async_trait]
+impl ConsentimientoRepository for MockRepository {
+    async fn plantillas_consentimiento(&self) -> Result<Vec<PlantillaConsentimiento>, AppError> {
+        let plantillas = lock(&self.consentimiento_plantillas)?;
+        let mut vigentes: Vec<PlantillaConsentimiento> = plantillas
+            .iter()
+            .filter(|p| !plantillas.iter().any(|o| o.proposito == p.proposito && o.version > p.version))
+            .cloned()
+            .collect();
+        vigentes.sort_by(|a, b| a.proposito.cmp(&b.proposito));
+        Ok(vigentes)
+    }
+
+    async fn versiones_consentimiento(&self, proposito: &str) -> Result<Vec<PlantillaConsentimiento>, AppError> {
+        let mut versiones: Vec<PlantillaConsentimiento> = lock(&self.consentimiento_plantillas)?
+            .iter()
+            .filter(|p| p.proposito == proposito)
+            .cloned()
+            .collect();
+        versiones.sort_by_key(|p| p.version);
+        Ok(versiones)
+    }
+
+    async fn crear_plantilla_consentimiento(&self, plantilla: &PlantillaConsentimiento) -> Result<u16, AppError> {
+        let mut plantillas = lock(&self.consentimiento_plantillas)?;
+        let version = plantillas
+            .iter()
+            .filter(|p| p.proposito == plantilla.proposito)
+            .map(|p| p.version)
+            .max()
+            .unwrap_or(0) + 1;
+        let mut plantilla = plantilla.clone();
+        plantilla.id = next_id(&plantillas, |p| p.id);
+        plantilla.version = version;
+        plantillas.push(plantilla);
+        Ok(version)
+    }
+
+    async fn registrar_consentimiento(&self, evento: &EventoConsentimiento) -> Result<u32, AppError> {
+        let mut eventos = lock(&self.consentimiento_eventos)?;
+        let mut evento = evento.clone();
+        evento.id = next_id(&eventos, |e| e.id);
+        eventos.push(evento.clone());
+        Ok(evento.id)
+    }
+
+    async fn eventos_consentimiento(&self, id_paciente: u32) -> Result<Vec<EventoConsentimiento>, AppError> {
+        Ok(lock(&self.consentimiento_eventos)?.iter().filter(|e| e.id_paciente == id_paciente).cloned().collect())
+    }
+
+    async fn consentimiento_vigente(&self, id_paciente: u32, proposito: &str) -> Result<Option<EventoConsentimiento>, AppError> {
+        self.consentimiento_vigente_sync(id_paciente, proposito)
+    }
+}
+
+#[async_trait::async_trait]
+impl FacturacionRepository for MockRepository {
+    async fn tarifas(&self, filtro: &TarifaFiltro) -> Result<Vec<Tarifa>, AppError> {
+        let mut tarifas: Vec<Tarifa> = lock(&self.tarifas)?
+            .iter()
+            .filter(|t| filtro.codigo_prestacion.as_ref().is_none_or(|c| t.codigo_prestacion.eq_ignore_ascii_case(c)))
+            .filter(|t| filtro.cod_prevision.is_none_or(|c| t.cod_prevision == Some(c)))
+            .filter(|t| filtro.cod_cliente.is_none_or(|c| t.cod_cliente == Some(c)))
+            .filter(|t| filtro.vigente_en.is_none_or(|f| t.vigente_en(f)))
+            .cloned()
+            .collect();
+        tarifas.sort_by(|a, b| (&a.codigo_prestacion, a.vigencia_desde).cmp(&(&b.codigo_prestacion, b.vigencia_desde)));
+        Ok(tarifas)
+    }
+
+    async fn create_tarifa(&self, tarifa: &Tarifa) -> Result<u32, AppError> {
+        let mut tarifas = lock(&self.tarifas)?;
+        let mut tarifa = tarifa.clone();
+        tarifa.id = next_id(&tarifas, |t| t.id);
+        tarifas.push(tarifa.clone());
+        Ok(tarifa.id)
+    }
+
+    async fn get_cargo_por_atencion(&self, id_atencion: u32) -> Result<Option<Cargo>, AppError> {
+        Ok(lock(&self.cargos)?.iter().find(|c| c.id_atencion == id_atencion).cloned())
+    }
+
+    async fn guardar_cargo(&self, cargo: &Cargo) -> Result<u32, AppError> {
+        let mut cargos = lock(&self.cargos)?;
+        if cargo.id == 0 {
+            let mut cargo = cargo.clone();
+            cargo.id = next_id(&cargos, |c| c.id);
+            cargos.push(cargo.clone());
+            return Ok(cargo.id);
+        }
+        match cargos.iter_mut().find(|c| c.id == cargo.id && c.estado != CARGO_FACTURADO) {
+            Some(c) => {
+                *c = cargo.clone();
+                Ok(cargo.id)
+            }
+            None => Err(AppError::Conflict("El cargo ya fue facturado".into())),
+        }
+    }
+
+    async fn cargos(&self, filtro: &CargoFiltro) -> Result<Vec<Cargo>, AppError> {
+        let mut cargos: Vec<Cargo> = lock(&self.cargos)?
+            .iter()
+            .filter(|c| filtro.id_paciente.is_none_or(|id| c.id_paciente == id))
+            .filter(|c| filtro.estado.as_ref().is_none_or(|e| c.estado.eq_ignore_ascii_case(e)))
+            .filter(|c| filtro.pagador.as_ref().is_none_or(|p| c.pagador.as_ref().is_some_and(|cp| cp.eq_ignore_ascii_case(p))))
+            .filter(|c| filtro.cod_prevision.is_none_or(|p| c.cod_prevision == Some(p)))
+            .filter(|c| filtro.cod_cliente.is_none_or(|p| c.cod_cliente == Some(p)))
+            .filter(|c| filtro.desde.is_none_or(|f| c.fecha >= f))
+            .filter(|c| filtro.hasta.is_none_or(|f| c.fecha <= f))
+            .filter(|c| filtro.id_lote.is_none_or(|l| c.id_lote == Some(l)))
+            .cloned()
+            .collect();
+        cargos.sort_by_key(|c| (c.fecha, c.id));
+        Ok(cargos)
+    }
+
+    async fn crear_lote(&self, lote: &LoteFacturacion, ids: &[u32]) -> Result<u32, AppError> {
+        let mut cargos = lock(&self.cargos)?;
+        let mut lotes = lock(&self.lotes)?;
+        let pendientes = cargos.iter().filter(|c| ids.contains(&c.id) && c.estado == CARGO_PENDIENTE).count();
+        if pendientes != ids.len() {
+            return Err(AppError::Conflict("Algunos cargos cambiaron mientras se armaba el lote; reintente".into()));
+        }
+
+        let mut lote = lote.clone();
+        lote.id = next_id(&lotes, |l| l.id);
+        for cargo in cargos.iter_mut().filter(|c| ids.contains(&c.id)) {
+            cargo.estado = CARGO_FACTURADO.into();
+            cargo.id_lote = Some(lote.id);
+        }
+        lotes.push(lote.clone());
+        Ok(lote.id)
+    }
+
+    async fn get_lote(&self, id: u32) -> Result<Option<LoteFacturacion>, AppError> {
+        Ok(lock(&self.lotes)?.iter().find(|l| l.id == id).cloned())
+    }
+
+    async fn lotes(&self, filtro: &LoteFiltro) -> Result<Vec<LoteFacturacion>, AppError> {
+        let mut lotes: Vec<LoteFacturacion> = lock(&self.lotes)?
+            .iter()
+            .filter(|l| filtro.periodo.as_ref().is_none_or(|p| &l.periodo == p))
+            .filter(|l| filtro.pagador.as_ref().is_none_or(|p| l.pagador.eq_ignore_ascii_case(p)))
+            .cloned()
+            .collect();
+        lotes.sort_by(|a, b| (&b.periodo, b.id).cmp(&(&a.periodo, a.id)));
+        Ok(lotes)
+    }
+}
+
+#[async_trait::async_trait]
+impl HonorarioRepository for MockRepository {
+    async fn tarifas_honorario(&self, filtro: &TarifaHonorarioFiltro) -> Result<Vec<TarifaHonorario>, AppError> {
+        let mut tarifas: Vec<TarifaHonorario> = lock(&self.tarifas_honorario)?
+            .iter()
+            .filter(|t| filtro.tipo.as_ref().is_none_or(|tipo| t.tipo.eq_ignore_ascii_case(tipo)))
+            .filter(|t| filtro.vigente_en.is_none_or(|f| t.vigente_en(f)))
+            .cloned()
+            .collect();
+        tarifas.sort_by(|a, b| (&a.tipo, a.vigencia_desde, a.id).cmp(&(&b.tipo, b.vigencia_desde, b.id)));
+        Ok(tarifas)
+    }
+
+    async fn create_tarifa_honorario(&self, tarifa: &TarifaHonorario) -> Result<u32, AppError> {
+        let mut tarifas = lock(&self.tarifas_honorario)?;
+        let mut tarifa = tarifa.clone();
+        tarifa.id = next_id(&tarifas, |t| t.id);
+        tarifas.push(tarifa.clone());
+        Ok(tarifa.id)
+    }
+
+    async fn retenciones(&self) -> Result<Vec<RetencionHonorarios>, AppError> {
+        let mut retenciones = lock(&self.retenciones)?.clone();
+        retenciones.sort_by_key(|r| r.vigencia_desde);
+        Ok(retenciones)
+    }
+
+    async fn guardar_retencion(&self, retencion: &RetencionHonorarios) -> Result<(), AppError> {
+        let mut retenciones = lock(&self.retenciones)?;
+        retenciones.retain(|r| r.vigencia_desde != retencion.vigencia_desde);
+        retenciones.push(retencion.clone());
+        Ok(())
+    }
+}
+
+#[async_trait::async_trait]
+impl ContratoRepository for MockRepository {
+    async fn contratos(&self, filtro: &ContratoFiltro) -> Result<Vec<Contrato>, AppError> {
+        let mut contratos: Vec<Contrato> = lock(&self.contratos)?
+            .iter()
+            .filter(|c| filtro.id_prof.is_none_or(|id| c.id_prof == id))
+            .filter(|c| filtro.vigente_en.is_none_or(|f| c.vigente_en(f)))
+            .cloned()
+            .collect();
+        contratos.sort_by_key(|c| (c.id_prof, c.fecha_ingreso));
+        Ok(contratos)
+    }
+
+    async fn get_contrato(&self, id: u32) -> Result<Option<Contrato>, AppError> {
+        Ok(lock(&self.contratos)?.iter().find(|c| c.id == id).cloned())
+    }
+
+    async fn create_contrato(&self, contrato: &Contrato) -> Result<u32, AppError> {
+        let mut contratos = lock(&self.contratos)?;
+        let mut contrato = contrato.clone();
+        contrato.id = next_id(&contratos, |c| c.id);
+        contratos.push(contrato.clone());
+        Ok(contrato.id)
+    }
+
+    async fn update_contrato(&self, contrato: &Contrato) -> Result<(), AppError> {
+        let mut contratos = lock(&self.contratos)?;
+        if let Some(c) = contratos.iter_mut().find(|c| c.id == contrato.id) {
+            *c = contrato.clone();
+        }
+        Ok(())
+    }
+
+    async fn actualizar_situacion(&self, id_prof: u32, situacion: &SituacionContractual) -> Result<(), AppError> {
+        let mut profesionales = lock(&self.profesionales)?;
+        if let Some(p) = profesionales.iter_mut().find(|p| p.id_prof == id_prof) {
+            p.estado = Some(situacion.estado.clone());
+            p.fecha_ingreso = situacion.fecha_ingreso;
+            p.fecha_egreso = situacion.fecha_egreso;
+        }
+        Ok(())
+    }
+}
+
+#[async_trait::async_trait]
+impl CredencialRepository for MockRepository {
+    async fn credenciales(&self, filtro: &CredencialFiltro) -> Result<Vec<Credencial>, AppError> {
+        let mut credenciales: Vec<Credencial> = lock(&self.credenciales)?
+            .iter()
+            .filter(|c| filtro.id_prof.is_none_or(|id| c.id_prof == id))
+            .filter(|c| filtro.tipo.as_ref().is_none_or(|t| c.tipo.eq_ignore_ascii_case(t)))
+            .cloned()
+            .collect();
+        credenciales.sort_by(|a, b| (a.id_prof, &a.tipo, a.registrada_en, a.id).cmp(&(b.id_prof, &b.tipo, b.registrada_en, b.id)));
+        Ok(credenciales)
+    }
+
+    async fn get_credencial(&self, id: u32) -> Result<Option<Credencial>, AppError> {
+        Ok(lock(&self.credenciales)?.iter().find(|c| c.id == id).cloned())
+    }
+
+    async fn create_credencial(&self, credencial: &Credencial) -> Result<u32, AppError> {
+        let mut credenciales = lock(&self.credenciales)?;
+        let mut credencial = credencial.clone();
+        credencial.id = next_id(&credenciales, |c| c.id);
+        credenciales.push(credencial.clone());
+        Ok(credencial.id)
+    }
+
+    async fn alerta_enviada(&self, id_credencial: u32, dias: u16) -> Result<bool, AppError> {
+        Ok(lock(&self.credencial_alertas)?.iter().any(|a| a.id_credencial == id_credencial && a.dias == dias))
+    }
+
+    async fn registrar_alerta(&self, alerta: &AlertaCredencial) -> Result<bool, AppError> {
+        let mut alertas = lock(&self.credencial_alertas)?;
+        if alertas.iter().any(|a| a.id_credencial == alerta.id_credencial && a.dias == alerta.dias) {
+            return Ok(false);
+        }
+        alertas.push(alerta.clone());
+        Ok(true)
+    }
+}
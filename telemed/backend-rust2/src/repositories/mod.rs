@@ -2,13 +2,32 @@ pub mod mysql;
 
 #[cfg(test)]
 pub mod mock;
+#[cfg(test)]
+pub mod fixtures;
 
 pub use mysql::MysqlRepository;
 #[cfg(test)]
 pub use mock::MockRepository;
 
 use async_trait::async_trait;
-use crate::{models::Usuario, error::AppError};
+use chrono::{NaiveDate, NaiveDateTime};
+use crate::{
+    models::{
+        Usuario, Paciente, PacienteFiltro, Prevision, PrevisionFiltro, Profesional, ProfesionalFiltro,
+        BloqueDisponibilidad, Cita, CitaFiltro, DisponibilidadFiltro, Feriado, Zona,
+        Adenda, AtencionDetalle, AtencionFiltro, Cie10, EventoVisita, MarcaVisita, Visita, VisitaFiltro,
+        RecetaDetalle, RecetaFiltro, DocumentoClinico, DocumentoDetalle, DocumentoFiltro, Plantilla, VersionDocumento,
+        ClaveFirma, Firma, FirmaFiltro, Teleconsulta, EntradaEspera,
+        Conversacion, ConversacionFiltro, Lectura, MensajeCifrado, Participante,
+        Notificacion, NotificacionFiltro, PreferenciaNotificacion, RecordatorioCita, RespuestaCita,
+        Adjunto, AdjuntoFiltro, ConsultaFhir, PaginaFhir, DerivacionDetalle, DerivacionFiltro, Importacion,
+        ResultadoImportacion, EventoConsentimiento, PlantillaConsentimiento,
+        Cargo, CargoFiltro, LoteFacturacion, LoteFiltro, Tarifa, TarifaFiltro,
+        RetencionHonorarios, TarifaHonorario, TarifaHonorarioFiltro, Contrato, ContratoFiltro, SituacionContractual,
+        AlertaCredencial, Credencial, CredencialFiltro,
+    },
+    error::AppError,
+};
 
 #[async_trait]
 pub trait UsuarioRepository: Send + Sync + Clone {
@@ -17,4 +36,301 @@ pub trait UsuarioRepository: Send + Sync + Clone {
     async fn create(&self, usuario: &Usuario) -> Result<(), AppError>;
     async fn update(&self, usuario: &Usuario) -> Result<(), AppError>;
     async fn delete(&self, id: u32) -> Result<bool, AppError>;
-}
\ No newline at end of file
+}
+
+#[async_trait]
+pub trait PacienteRepository: Send + Sync + Clone {
+    async fn get_by_id(&self, id: u32) -> Result<Option<Paciente>, AppError>;
+    async fn get_by_rut(&self, rut: &str) -> Result<Option<Paciente>, AppError>;
+    async fn search(&self, filtro: &PacienteFiltro) -> Result<Vec<Paciente>, AppError>;
+    async fn create(&self, paciente: &Paciente) -> Result<u32, AppError>;
+    async fn update(&self, paciente: &Paciente) -> Result<(), AppError>;
+    async fn delete(&self, id: u32) -> Result<bool, AppError>;
+}
+
+#[async_trait]
+pub trait PrevisionRepository: Send + Sync + Clone {
+    async fn get_by_id(&self, cod_prevision: u32) -> Result<Option<Prevision>, AppError>;
+    async fn search(&self, filtro: &PrevisionFiltro) -> Result<Vec<Prevision>, AppError>;
+    async fn create(&self, prevision: &Prevision) -> Result<(), AppError>;
+    async fn update(&self, prevision: &Prevision) -> Result<(), AppError>;
+    async fn delete(&self, cod_prevision: u32) -> Result<bool, AppError>;
+}
+
+#[async_trait]
+pub trait ProfesionalRepository: Send + Sync + Clone {
+    async fn get_by_id(&self, id_prof: u32) -> Result<Option<Profesional>, AppError>;
+    async fn search(&self, filtro: &ProfesionalFiltro) -> Result<Vec<Profesional>, AppError>;
+}
+
+#[async_trait]
+pub trait AgendaRepository: Send + Sync + Clone {
+    async fn get_bloque(&self, id: u32) -> Result<Option<BloqueDisponibilidad>, AppError>;
+    async fn bloques_profesional(&self, id_prof: u32) -> Result<Vec<BloqueDisponibilidad>, AppError>;
+    /// Bloques cuyos profesionales cumplen con la especialidad, zona y modalidad pedidas.
+    async fn bloques_disponibles(&self, filtro: &DisponibilidadFiltro) -> Result<Vec<BloqueDisponibilidad>, AppError>;
+    async fn create_bloque(&self, bloque: &BloqueDisponibilidad) -> Result<u32, AppError>;
+    async fn delete_bloque(&self, id: u32) -> Result<bool, AppError>;
+    async fn get_cita(&self, id: u32) -> Result<Option<Cita>, AppError>;
+    /// Citas que se traslapan con el rango `desde`..`hasta` del filtro.
+    async fn search_citas(&self, filtro: &CitaFiltro) -> Result<Vec<Cita>, AppError>;
+    /// Inserta la cita sólo si, al momento de insertarla, el paciente no
+    /// tiene otra activa que se traslape y el profesional tiene a lo más
+    /// `ocupados` (sus sobrecupos permitidos). Con `reprogramada`, esa cita
+    /// no cuenta y queda REPROGRAMADA en la misma operación. Dos reservas
+    /// simultáneas del mismo horario no pueden pasar ambas: la segunda
+    /// recibe `AppError::Conflict`.
+    async fn reservar_cita(&self, cita: &Cita, ocupados: usize, reprogramada: Option<u32>) -> Result<u32, AppError>;
+    async fn update_cita(&self, cita: &Cita) -> Result<(), AppError>;
+}
+
+#[async_trait]
+pub trait ZonaRepository: Send + Sync + Clone {
+    async fn get_by_id(&self, cod_zona: &str) -> Result<Option<Zona>, AppError>;
+    async fn get_all(&self) -> Result<Vec<Zona>, AppError>;
+    async fn update(&self, zona: &Zona) -> Result<(), AppError>;
+}
+
+/// Feriados agregados por administradores; los legales se calculan en `feriados`.
+#[async_trait]
+pub trait FeriadoRepository: Send + Sync + Clone {
+    async fn search(&self, desde: NaiveDate, hasta: NaiveDate) -> Result<Vec<Feriado>, AppError>;
+    async fn create(&self, feriado: &Feriado) -> Result<u32, AppError>;
+    async fn delete(&self, id: u32) -> Result<bool, AppError>;
+}
+
+/// Registro clínico. Las atenciones firmadas no se modifican: `update_borrador`
+/// y `firmar` retornan `false` si la atención ya no está en borrador.
+#[async_trait]
+pub trait AtencionRepository: Send + Sync + Clone {
+    async fn get_by_id(&self, id: u32) -> Result<Option<AtencionDetalle>, AppError>;
+    /// Atenciones creadas entre `desde` y `hasta`, de la más reciente a la más antigua.
+    async fn search(&self, filtro: &AtencionFiltro) -> Result<Vec<AtencionDetalle>, AppError>;
+    async fn create(&self, atencion: &AtencionDetalle) -> Result<u32, AppError>;
+    async fn update_borrador(&self, atencion: &AtencionDetalle) -> Result<bool, AppError>;
+    async fn firmar(&self, id: u32, firmada_en: NaiveDateTime) -> Result<bool, AppError>;
+    async fn create_adenda(&self, adenda: &Adenda) -> Result<u32, AppError>;
+}
+
+/// Catálogo CIE-10 versionado; cada versión entra en vigencia en una fecha.
+#[async_trait]
+pub trait Cie10Repository: Send + Sync + Clone {
+    async fn version_vigente(&self, fecha: NaiveDate) -> Result<Option<String>, AppError>;
+    async fn codigos(&self, version: &str) -> Result<Vec<Cie10>, AppError>;
+    /// Reemplaza los códigos de `version`; retorna cuántos quedaron cargados.
+    async fn importar(&self, version: &str, vigente_desde: NaiveDate, codigos: &[Cie10]) -> Result<usize, AppError>;
+}
+
+/// Visitas domiciliarias. `asignar` sólo procede en visitas pendientes y
+/// `cambiar_estado` sólo si la visita sigue en `desde`; ambos retornan
+/// `false` en caso contrario. `registrar_marca` retorna `None` si la visita
+/// ya tiene una marca del mismo tipo.
+#[async_trait]
+pub trait VisitaRepository: Send + Sync + Clone {
+    async fn get_by_id(&self, id: u32) -> Result<Option<Visita>, AppError>;
+    async fn search(&self, filtro: &VisitaFiltro) -> Result<Vec<Visita>, AppError>;
+    async fn create(&self, visita: &Visita) -> Result<u32, AppError>;
+    async fn asignar(&self, id: u32, id_prof: u32) -> Result<bool, AppError>;
+    async fn cambiar_estado(&self, id: u32, desde: &str, evento: &EventoVisita) -> Result<bool, AppError>;
+    async fn historial(&self, id: u32) -> Result<Vec<EventoVisita>, AppError>;
+    async fn registrar_marca(&self, marca: &MarcaVisita) -> Result<Option<u32>, AppError>;
+    async fn marcas(&self, ids_visita: &[u32]) -> Result<Vec<MarcaVisita>, AppError>;
+}
+
+/// Recetas. `anular` sólo procede sobre recetas emitidas y retorna `false`
+/// si ya estaba anulada.
+#[async_trait]
+pub trait RecetaRepository: Send + Sync + Clone {
+    async fn get_by_id(&self, id: u32) -> Result<Option<RecetaDetalle>, AppError>;
+    async fn get_by_codigo(&self, codigo: &str) -> Result<Option<RecetaDetalle>, AppError>;
+    async fn search(&self, filtro: &RecetaFiltro) -> Result<Vec<RecetaDetalle>, AppError>;
+    async fn create(&self, detalle: &RecetaDetalle) -> Result<u32, AppError>;
+    async fn anular(&self, id: u32, motivo: &str) -> Result<bool, AppError>;
+}
+
+/// Documentos clínicos y sus plantillas. `create` asigna el siguiente folio
+/// del cliente y registra la versión 1; `agregar_version` y
+/// `crear_plantilla` asignan la versión siguiente. Los números asignados se
+/// retornan.
+#[async_trait]
+pub trait DocumentoRepository: Send + Sync + Clone {
+    async fn plantillas(&self) -> Result<Vec<Plantilla>, AppError>;
+    async fn plantilla_vigente(&self, tipo: &str) -> Result<Option<Plantilla>, AppError>;
+    async fn crear_plantilla(&self, plantilla: &Plantilla) -> Result<u16, AppError>;
+    async fn get_by_id(&self, id: u32) -> Result<Option<DocumentoDetalle>, AppError>;
+    async fn search(&self, filtro: &DocumentoFiltro) -> Result<Vec<DocumentoClinico>, AppError>;
+    async fn create(&self, documento: &DocumentoClinico, version: &VersionDocumento) -> Result<(u32, u32), AppError>;
+    async fn agregar_version(&self, version: &VersionDocumento) -> Result<u16, AppError>;
+}
+
+#[async_trait]
+pub trait FirmaRepository: Send + Sync + Clone {
+    async fn clave_vigente(&self, id_prof: u32) -> Result<Option<ClaveFirma>, AppError>;
+    async fn get_clave(&self, id: u32) -> Result<Option<ClaveFirma>, AppError>;
+    async fn claves(&self, id_prof: u32) -> Result<Vec<ClaveFirma>, AppError>;
+    /// Registra la clave y revoca la vigente del profesional, si la hay.
+    async fn crear_clave(&self, clave: &ClaveFirma) -> Result<u32, AppError>;
+    async fn get_by_id(&self, id: u32) -> Result<Option<Firma>, AppError>;
+    async fn search(&self, filtro: &FirmaFiltro) -> Result<Vec<Firma>, AppError>;
+    /// `None` si esa versión del documento ya estaba firmada.
+    async fn create(&self, firma: &Firma) -> Result<Option<u32>, AppError>;
+}
+
+#[async_trait]
+pub trait TeleconsultaRepository: Send + Sync + Clone {
+    async fn get_by_cita(&self, id_cita: u32) -> Result<Option<Teleconsulta>, AppError>;
+    /// Crea la sala de la cita o renueva sus tokens.
+    async fn guardar(&self, teleconsulta: &Teleconsulta) -> Result<(), AppError>;
+    /// Registra el inicio sólo la primera vez.
+    async fn iniciar(&self, id_cita: u32, en: NaiveDateTime) -> Result<bool, AppError>;
+    async fn terminar(&self, id_cita: u32, en: NaiveDateTime) -> Result<bool, AppError>;
+}
+
+#[async_trait]
+pub trait SalaEsperaRepository: Send + Sync + Clone {
+    async fn get_by_cita(&self, id_cita: u32) -> Result<Option<EntradaEspera>, AppError>;
+    /// Crea o reemplaza la entrada de la cita.
+    async fn guardar(&self, entrada: &EntradaEspera) -> Result<(), AppError>;
+    /// Entradas ESPERANDO o LLAMADO del profesional.
+    async fn abiertas(&self, id_prof: u32) -> Result<Vec<EntradaEspera>, AppError>;
+    async fn profesionales_en_espera(&self) -> Result<Vec<u32>, AppError>;
+}
+
+#[async_trait]
+pub trait MensajeRepository: Send + Sync + Clone {
+    async fn get_conversacion(&self, id: u32) -> Result<Option<Conversacion>, AppError>;
+    /// Las de actividad más reciente primero.
+    async fn conversaciones(&self, filtro: &ConversacionFiltro) -> Result<Vec<Conversacion>, AppError>;
+    async fn create_conversacion(&self, conversacion: &Conversacion) -> Result<u32, AppError>;
+    /// Guarda el mensaje y actualiza `ultimo_mensaje_en` de la conversación.
+    async fn create_mensaje(&self, mensaje: &MensajeCifrado) -> Result<u32, AppError>;
+    /// Hasta `limite` mensajes anteriores a `antes_de`, del más reciente al más antiguo.
+    async fn mensajes(&self, id_conversacion: u32, antes_de: Option<u32>, limite: u16) -> Result<Vec<MensajeCifrado>, AppError>;
+    async fn lecturas(&self, id_conversacion: u32) -> Result<Vec<Lectura>, AppError>;
+    /// Avanza la lectura del participante; `false` si ya había leído hasta ahí.
+    async fn marcar_leido(&self, lectura: &Lectura) -> Result<bool, AppError>;
+    /// Mensajes de otros autores posteriores a la lectura del participante.
+    async fn no_leidos(&self, id_conversacion: u32, lector: &Participante) -> Result<u32, AppError>;
+    /// Elimina los mensajes que superaron la retención de su conversación.
+    async fn depurar(&self, ahora: NaiveDateTime) -> Result<u64, AppError>;
+}
+
+#[async_trait]
+pub trait NotificacionRepository: Send + Sync + Clone {
+    async fn preferencias(&self, destinatario_tipo: &str, destinatario_id: u32) -> Result<Option<PreferenciaNotificacion>, AppError>;
+    async fn guardar_preferencias(&self, preferencia: &PreferenciaNotificacion) -> Result<(), AppError>;
+    async fn encolar(&self, notificacion: &Notificacion) -> Result<u32, AppError>;
+    async fn get_notificacion(&self, id: u32) -> Result<Option<Notificacion>, AppError>;
+    /// Las más recientes primero.
+    async fn notificaciones(&self, filtro: &NotificacionFiltro) -> Result<Vec<Notificacion>, AppError>;
+    /// Reclama las PENDIENTE cuyo próximo intento ya llegó, las más
+    /// atrasadas primero, corriendo su próximo intento a `reservar_hasta`
+    /// para que otro despachador no las tome mientras se envían.
+    async fn reclamar(&self, ahora: NaiveDateTime, reservar_hasta: NaiveDateTime, limite: u32) -> Result<Vec<Notificacion>, AppError>;
+    /// Guarda estado, intentos, próximo intento, error y fecha de envío.
+    async fn registrar_intento(&self, notificacion: &Notificacion) -> Result<(), AppError>;
+    /// Sólo notificaciones de la aplicación aún no leídas.
+    async fn marcar_leida(&self, id: u32, ahora: NaiveDateTime) -> Result<bool, AppError>;
+}
+
+#[async_trait]
+pub trait RecordatorioRepository: Send + Sync + Clone {
+    /// `false` si ese recordatorio ya se había enviado.
+    async fn registrar_recordatorio(&self, recordatorio: &RecordatorioCita) -> Result<bool, AppError>;
+    async fn recordatorios(&self, ids_cita: &[u32]) -> Result<Vec<RecordatorioCita>, AppError>;
+    async fn get_respuesta(&self, id_cita: u32) -> Result<Option<RespuestaCita>, AppError>;
+    /// Crea o reemplaza la respuesta de la cita.
+    async fn guardar_respuesta(&self, respuesta: &RespuestaCita) -> Result<(), AppError>;
+    async fn respuestas(&self, ids_cita: &[u32]) -> Result<Vec<RespuestaCita>, AppError>;
+}
+
+#[async_trait]
+pub trait AdjuntoRepository: Send + Sync + Clone {
+    async fn get_adjunto(&self, id: u32) -> Result<Option<Adjunto>, AppError>;
+    async fn adjuntos(&self, filtro: &AdjuntoFiltro) -> Result<Vec<Adjunto>, AppError>;
+    /// El mismo contenido ya subido para el paciente y la atención.
+    async fn adjunto_por_hash(&self, id_paciente: u32, id_atencion: Option<u32>, sha256: &str) -> Result<Option<Adjunto>, AppError>;
+    async fn create_adjunto(&self, adjunto: &Adjunto) -> Result<u32, AppError>;
+}
+
+/// Lecturas para la API FHIR: paginadas y con la fecha de actualización.
+#[async_trait]
+pub trait FhirRepository: Send + Sync + Clone {
+    async fn fhir_pacientes(&self, consulta: &ConsultaFhir) -> Result<PaginaFhir<Paciente>, AppError>;
+    async fn fhir_profesionales(&self, consulta: &ConsultaFhir) -> Result<PaginaFhir<Profesional>, AppError>;
+    async fn fhir_atenciones(&self, consulta: &ConsultaFhir) -> Result<PaginaFhir<AtencionDetalle>, AppError>;
+    async fn fhir_zonas(&self, consulta: &ConsultaFhir) -> Result<PaginaFhir<Zona>, AppError>;
+}
+
+#[async_trait]
+pub trait DerivacionRepository: Send + Sync + Clone {
+    async fn get_derivacion(&self, id: u32) -> Result<Option<DerivacionDetalle>, AppError>;
+    async fn derivaciones(&self, filtro: &DerivacionFiltro) -> Result<Vec<DerivacionDetalle>, AppError>;
+    /// En una sola transacción: crea o actualiza los pacientes por RUT y
+    /// registra las derivaciones que no estuvieran ya (por `referencia_externa`).
+    /// Si una referencia ya es de otro paciente la anota en `rechazadas` y
+    /// deshace todo.
+    async fn importar_derivaciones(&self, importacion: &Importacion) -> Result<ResultadoImportacion, AppError>;
+}
+
+#[async_trait]
+pub trait ConsentimientoRepository: Send + Sync + Clone {
+    /// Versión vigente de cada propósito.
+    async fn plantillas_consentimiento(&self) -> Result<Vec<PlantillaConsentimiento>, AppError>;
+    async fn versiones_consentimiento(&self, proposito: &str) -> Result<Vec<PlantillaConsentimiento>, AppError>;
+    /// Crea la siguiente versión del propósito y la retorna.
+    async fn crear_plantilla_consentimiento(&self, plantilla: &PlantillaConsentimiento) -> Result<u16, AppError>;
+    async fn registrar_consentimiento(&self, evento: &EventoConsentimiento) -> Result<u32, AppError>;
+    async fn eventos_consentimiento(&self, id_paciente: u32) -> Result<Vec<EventoConsentimiento>, AppError>;
+    /// La aceptación que hoy habilita el propósito, si la hay: el último
+    /// evento es una aceptación y no la invalidó una versión posterior que
+    /// requiere renovación.
+    async fn consentimiento_vigente(&self, id_paciente: u32, proposito: &str) -> Result<Option<EventoConsentimiento>, AppError>;
+}
+
+#[async_trait]
+pub trait FacturacionRepository: Send + Sync + Clone {
+    async fn tarifas(&self, filtro: &TarifaFiltro) -> Result<Vec<Tarifa>, AppError>;
+    async fn create_tarifa(&self, tarifa: &Tarifa) -> Result<u32, AppError>;
+    async fn get_cargo_por_atencion(&self, id_atencion: u32) -> Result<Option<Cargo>, AppError>;
+    /// Inserta el cargo si `id` es 0; si no, lo reemplaza mientras no esté
+    /// facturado. Retorna el id.
+    async fn guardar_cargo(&self, cargo: &Cargo) -> Result<u32, AppError>;
+    async fn cargos(&self, filtro: &CargoFiltro) -> Result<Vec<Cargo>, AppError>;
+    /// Crea el lote y le asigna los cargos en una transacción. Falla con
+    /// `Conflict` si alguno dejó de estar pendiente.
+    async fn crear_lote(&self, lote: &LoteFacturacion, cargos: &[u32]) -> Result<u32, AppError>;
+    async fn get_lote(&self, id: u32) -> Result<Option<LoteFacturacion>, AppError>;
+    async fn lotes(&self, filtro: &LoteFiltro) -> Result<Vec<LoteFacturacion>, AppError>;
+}
+
+#[async_trait]
+pub trait HonorarioRepository: Send + Sync + Clone {
+    async fn tarifas_honorario(&self, filtro: &TarifaHonorarioFiltro) -> Result<Vec<TarifaHonorario>, AppError>;
+    async fn create_tarifa_honorario(&self, tarifa: &TarifaHonorario) -> Result<u32, AppError>;
+    async fn retenciones(&self) -> Result<Vec<RetencionHonorarios>, AppError>;
+    /// Fija la tasa desde `vigencia_desde`, reemplazando la de esa fecha.
+    async fn guardar_retencion(&self, retencion: &RetencionHonorarios) -> Result<(), AppError>;
+}
+
+/// Períodos de contrato de los profesionales. `actualizar_situacion` deja
+/// en `paso_profesionales` el estado y las fechas que derivan de ellos.
+#[async_trait]
+pub trait ContratoRepository: Send + Sync + Clone {
+    async fn contratos(&self, filtro: &ContratoFiltro) -> Result<Vec<Contrato>, AppError>;
+    async fn get_contrato(&self, id: u32) -> Result<Option<Contrato>, AppError>;
+    async fn create_contrato(&self, contrato: &Contrato) -> Result<u32, AppError>;
+    async fn update_contrato(&self, contrato: &Contrato) -> Result<(), AppError>;
+    async fn actualizar_situacion(&self, id_prof: u32, situacion: &SituacionContractual) -> Result<(), AppError>;
+}
+
+/// Documentos de los profesionales y avisos de vencimiento enviados.
+#[async_trait]
+pub trait CredencialRepository: Send + Sync + Clone {
+    async fn credenciales(&self, filtro: &CredencialFiltro) -> Result<Vec<Credencial>, AppError>;
+    async fn get_credencial(&self, id: u32) -> Result<Option<Credencial>, AppError>;
+    async fn create_credencial(&self, credencial: &Credencial) -> Result<u32, AppError>;
+    async fn alerta_enviada(&self, id_credencial: u32, dias: u16) -> Result<bool, AppError>;
+    /// Registra el aviso; `false` si ya se había enviado.
+    async fn registrar_alerta(&self, alerta: &AlertaCredencial) -> Result<bool, AppError>;
+}
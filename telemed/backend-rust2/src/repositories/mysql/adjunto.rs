@@ -0,0 +1,74 @@
+use mysql_async::{prelude::*, Params, Value};
+use crate::{
+    models::{Adjunto, AdjuntoFiltro},
+    error::AppError,
+};
+use crate::repositories::AdjuntoRepository;
+use super::MysqlRepository;
+
+const COLUMNAS_ADJUNTO: &str = "id, id_paciente, id_atencion, id_prof, nombre, tipo_mime, tamano, sha256, subido_en";
+
+#[async_trait::async_trait]
+impl AdjuntoRepository for MysqlRepository {
+    async fn get_adjunto(&self, id: u32) -> Result<Option<Adjunto>, AppError> {
+        let mut conn = self.pool.get_conn().await?;
+        let query = format!("SELECT {} FROM adjuntos WHERE id = ?", COLUMNAS_ADJUNTO);
+        Ok(conn.exec_first(query, (id,)).await?)
+    }
+
+    async fn adjuntos(&self, filtro: &AdjuntoFiltro) -> Result<Vec<Adjunto>, AppError> {
+        let mut conn = self.pool.get_conn().await?;
+        let mut condiciones = Vec::new();
+        let mut params: Vec<Value> = Vec::new();
+
+        if let Some(id) = filtro.id_paciente {
+            condiciones.push("id_paciente = ?");
+            params.push(id.into());
+        }
+        if let Some(id) = filtro.id_atencion {
+            condiciones.push("id_atencion = ?");
+            params.push(id.into());
+        }
+
+        let mut query = format!("SELECT {} FROM adjuntos", COLUMNAS_ADJUNTO);
+        if !condiciones.is_empty() {
+            query.push_str(" WHERE ");
+            query.push_str(&condiciones.join(" AND "));
+        }
+        query.push_str(" ORDER BY subido_en DESC, id DESC LIMIT 500");
+
+        let params = if params.is_empty() { Params::Empty } else { Params::Positional(params) };
+        Ok(conn.exec(query, params).await?)
+    }
+
+    async fn adjunto_por_hash(&self, id_paciente: u32, id_atencion: Option<u32>, sha256: &str) -> Result<Option<Adjunto>, AppError> {
+        let mut conn = self.pool.get_conn().await?;
+        let query = format!(
+            "SELECT {} FROM adjuntos WHERE id_paciente = ? AND id_atencion <=> ? AND sha256 = ? LIMIT 1",
+            COLUMNAS_ADJUNTO,
+        );
+        Ok(conn.exec_first(query, (id_paciente, id_atencion, sha256)).await?)
+    }
+
+    async fn create_adjunto(&self, adjunto: &Adjunto) -> Result<u32, AppError> {
+        let mut conn = self.pool.get_conn().await?;
+        let query = r"
+            INSERT INTO adjuntos (id_paciente, id_atencion, id_prof, nombre, tipo_mime, tamano, sha256, subido_en)
+            VALUES (?, ?, ?, ?, ?, ?, ?, ?)";
+
+        conn.exec_drop(query, (
+            &adjunto.id_paciente,
+            &adjunto.id_atencion,
+            &adjunto.id_prof,
+            &adjunto.nombre,
+            &adjunto.tipo_mime,
+            &adjunto.tamano,
+            &adjunto.sha256,
+            &adjunto.subido_en,
+        )).await?;
+
+        conn.last_insert_id()
+            .map(|id| id as u32)
+            .ok_or_else(|| AppError::Internal("INSERT en adjuntos no retornó id".into()))
+    }
+}
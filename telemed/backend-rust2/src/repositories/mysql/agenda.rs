@@ -0,0 +1,217 @@
+use mysql_async::{prelude::*, Params, TxOpts, Value};
+use crate::{
+    models::{BloqueDisponibilidad, Cita, CitaFiltro, DisponibilidadFiltro, CITA_AGENDADA, CITA_REALIZADA, CITA_REPROGRAMADA},
+    error::AppError,
+};
+use crate::repositories::AgendaRepository;
+use super::MysqlRepository;
+
+const COLUMNAS_BLOQUE: &str = "b.id, b.id_prof, b.dia_semana, b.hora_inicio, b.hora_fin, b.duracion_min, b.modalidad, b.cod_zona, b.zona_horaria, b.sobrecupos, b.vigencia_desde, b.vigencia_hasta";
+const COLUMNAS_CITA: &str = "id, id_prof, id_paciente, id_bloque, inicio, fin, modalidad, cod_zona, estado, sobrecupo, motivo_cancelacion, id_cita_original";
+
+#[async_trait::async_trait]
+impl AgendaRepository for MysqlRepository {
+    async fn get_bloque(&self, id: u32) -> Result<Option<BloqueDisponibilidad>, AppError> {
+        let mut conn = self.pool.get_conn().await?;
+        let query = format!("SELECT {} FROM agenda_bloques b WHERE b.id = ?", COLUMNAS_BLOQUE);
+        Ok(conn.exec_first(query, (id,)).await?)
+    }
+
+    async fn bloques_profesional(&self, id_prof: u32) -> Result<Vec<BloqueDisponibilidad>, AppError> {
+        let mut conn = self.pool.get_conn().await?;
+        let query = format!(
+            "SELECT {} FROM agenda_bloques b WHERE b.id_prof = ? ORDER BY b.dia_semana, b.hora_inicio",
+            COLUMNAS_BLOQUE
+        );
+        Ok(conn.exec(query, (id_prof,)).await?)
+    }
+
+    async fn bloques_disponibles(&self, filtro: &DisponibilidadFiltro) -> Result<Vec<BloqueDisponibilidad>, AppError> {
+        let mut conn = self.pool.get_conn().await?;
+        let mut condiciones = Vec::new();
+        let mut params: Vec<Value> = Vec::new();
+
+        if let Some(especialidad) = &filtro.especialidad {
+            condiciones.push("p.especialidad = ?");
+            params.push(especialidad.into());
+        }
+        if let Some(cod_zona) = &filtro.cod_zona {
+            condiciones.push("b.cod_zona = ?");
+            params.push(cod_zona.into());
+        }
+        if let Some(modalidad) = &filtro.modalidad {
+            condiciones.push("b.modalidad = ?");
+            params.push(modalidad.to_uppercase().into());
+        }
+
+        let mut query = format!(
+            "SELECT {} FROM agenda_bloques b JOIN paso_profesionales p ON p.id_prof = b.id_prof",
+            COLUMNAS_BLOQUE
+        );
+        if !condiciones.is_empty() {
+            query.push_str(" WHERE ");
+            query.push_str(&condiciones.join(" AND "));
+        }
+
+        let params = if params.is_empty() { Params::Empty } else { Params::Positional(params) };
+        Ok(conn.exec(query, params).await?)
+    }
+
+    async fn create_bloque(&self, bloque: &BloqueDisponibilidad) -> Result<u32, AppError> {
+        let mut conn = self.pool.get_conn().await?;
+        let query = r"
+            INSERT INTO agenda_bloques
+            (id_prof, dia_semana, hora_inicio, hora_fin, duracion_min, modalidad, cod_zona, zona_horaria, sobrecupos, vigencia_desde, vigencia_hasta)
+            VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)";
+
+        let result = conn.exec_iter(query, (
+            &bloque.id_prof,
+            &bloque.dia_semana,
+            &bloque.hora_inicio,
+            &bloque.hora_fin,
+            &bloque.duracion_min,
+            &bloque.modalidad,
+            &bloque.cod_zona,
+            &bloque.zona_horaria,
+            &bloque.sobrecupos,
+            &bloque.vigencia_desde,
+            &bloque.vigencia_hasta,
+        )).await?;
+
+        result.last_insert_id()
+            .map(|id| id as u32)
+            .ok_or_else(|| AppError::Internal("INSERT en agenda_bloques no retornó id".into()))
+    }
+
+    async fn delete_bloque(&self, id: u32) -> Result<bool, AppError> {
+        let mut conn = self.pool.get_conn().await?;
+        let query = "DELETE FROM agenda_bloques WHERE id = ?";
+
+        let result = conn.exec_iter(query, (id,)).await?;
+        Ok(result.affected_rows() > 0)
+    }
+
+    async fn get_cita(&self, id: u32) -> Result<Option<Cita>, AppError> {
+        let mut conn = self.pool.get_conn().await?;
+        let query = format!("SELECT {} FROM agenda_citas WHERE id = ?", COLUMNAS_CITA);
+        Ok(conn.exec_first(query, (id,)).await?)
+    }
+
+    async fn search_citas(&self, filtro: &CitaFiltro) -> Result<Vec<Cita>, AppError> {
+        let mut conn = self.pool.get_conn().await?;
+        let mut condiciones = Vec::new();
+        let mut params: Vec<Value> = Vec::new();
+
+        if let Some(id_prof) = filtro.id_prof {
+            condiciones.push("id_prof = ?");
+            params.push(id_prof.into());
+        }
+        if let Some(id_paciente) = filtro.id_paciente {
+            condiciones.push("id_paciente = ?");
+            params.push(id_paciente.into());
+        }
+        if let Some(desde) = filtro.desde {
+            condiciones.push("fin > ?");
+            params.push(desde.into());
+        }
+        if let Some(hasta) = filtro.hasta {
+            condiciones.push("inicio < ?");
+            params.push(hasta.into());
+        }
+
+        let mut query = format!("SELECT {} FROM agenda_citas", COLUMNAS_CITA);
+        if !condiciones.is_empty() {
+            query.push_str(" WHERE ");
+            query.push_str(&condiciones.join(" AND "));
+        }
+        query.push_str(" ORDER BY inicio");
+
+        let params = if params.is_empty() { Params::Empty } else { Params::Positional(params) };
+        Ok(conn.exec(query, params).await?)
+    }
+
+    async fn reservar_cita(&self, cita: &Cita, ocupados: usize, reprogramada: Option<u32>) -> Result<u32, AppError> {
+        let mut conn = self.pool.get_conn().await?;
+        let mut tx = conn.start_transaction(TxOpts::default()).await?;
+
+        // Bloquear al profesional y luego al paciente, siempre en ese orden,
+        // deja en fila las reservas que compiten por alguno de los dos
+        tx.exec_drop("SELECT id_prof FROM paso_profesionales WHERE id_prof = ? FOR UPDATE", (cita.id_prof,)).await?;
+        tx.exec_drop("SELECT id FROM pacientes WHERE id = ? FOR UPDATE", (cita.id_paciente,)).await?;
+
+        let query = r"
+            SELECT COUNT(CASE WHEN id_paciente = ? THEN 1 END), COUNT(CASE WHEN id_prof = ? THEN 1 END)
+            FROM agenda_citas
+            WHERE (id_paciente = ? OR id_prof = ?)
+              AND estado IN (?, ?)
+              AND inicio < ? AND fin > ?
+              AND id <> ?";
+        let params: Vec<Value> = vec![
+            cita.id_paciente.into(),
+            cita.id_prof.into(),
+            cita.id_paciente.into(),
+            cita.id_prof.into(),
+            CITA_AGENDADA.into(),
+            CITA_REALIZADA.into(),
+            cita.fin.into(),
+            cita.inicio.into(),
+            reprogramada.unwrap_or(0).into(),
+        ];
+        let (del_paciente, del_profesional): (u64, u64) = tx.exec_first(query, params).await?.unwrap_or_default();
+        if del_paciente > 0 {
+            tx.rollback().await?;
+            return Err(AppError::Conflict("El paciente ya tiene una cita en ese horario".into()));
+        }
+        if del_profesional > ocupados as u64 {
+            tx.rollback().await?;
+            return Err(AppError::Conflict("El horario ya está ocupado".into()));
+        }
+
+        let query = r"
+            INSERT INTO agenda_citas
+            (id_prof, id_paciente, id_bloque, inicio, fin, modalidad, cod_zona, estado, sobrecupo, motivo_cancelacion, id_cita_original)
+            VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)";
+        tx.exec_drop(query, (
+            &cita.id_prof,
+            &cita.id_paciente,
+            &cita.id_bloque,
+            &cita.inicio,
+            &cita.fin,
+            &cita.modalidad,
+            &cita.cod_zona,
+            &cita.estado,
+            &cita.sobrecupo,
+            &cita.motivo_cancelacion,
+            &cita.id_cita_original,
+        )).await?;
+        let id = tx.last_insert_id()
+            .map(|id| id as u32)
+            .ok_or_else(|| AppError::Internal("INSERT en agenda_citas no retornó id".into()))?;
+
+        if let Some(original) = reprogramada {
+            tx.exec_drop("UPDATE agenda_citas SET estado = ? WHERE id = ?", (CITA_REPROGRAMADA, original)).await?;
+        }
+        tx.commit().await?;
+
+        Ok(id)
+    }
+
+    async fn update_cita(&self, cita: &Cita) -> Result<(), AppError> {
+        let mut conn = self.pool.get_conn().await?;
+        let query = r"
+            UPDATE agenda_citas SET
+            inicio = ?, fin = ?, estado = ?, sobrecupo = ?, motivo_cancelacion = ?
+            WHERE id = ?";
+
+        conn.exec_drop(query, (
+            &cita.inicio,
+            &cita.fin,
+            &cita.estado,
+            &cita.sobrecupo,
+            &cita.motivo_cancelacion,
+            &cita.id,
+        )).await?;
+
+        Ok(())
+    }
+}
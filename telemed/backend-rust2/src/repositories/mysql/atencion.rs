@@ -0,0 +1,213 @@
+use chrono::NaiveDateTime;
+use mysql_async::{prelude::*, Conn, Params, TxOpts, Value};
+use crate::{
+    models::{Adenda, AdjuntoAtencion, Atencion, AtencionDetalle, AtencionFiltro, Diagnostico, ATENCION_BORRADOR, ATENCION_FIRMADA},
+    error::AppError,
+};
+use crate::repositories::AtencionRepository;
+use super::MysqlRepository;
+
+pub(super) const COLUMNAS_ATENCION: &str = "id, id_paciente, id_prof, id_cita, motivo, anamnesis, examen_fisico, indicaciones, estado, creada_en, firmada_en";
+
+/// Carga diagnósticos, adjuntos y adendas de las atenciones en tres consultas.
+pub(super) async fn completar(conn: &mut Conn, atenciones: Vec<Atencion>) -> Result<Vec<AtencionDetalle>, AppError> {
+    if atenciones.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let marcadores = vec!["?"; atenciones.len()].join(", ");
+    let ids: Vec<Value> = atenciones.iter().map(|a| a.id.into()).collect();
+
+    let diagnosticos: Vec<(u32, String, String, bool)> = conn.exec(
+        format!("SELECT id_atencion, codigo, descripcion, principal FROM atencion_diagnosticos WHERE id_atencion IN ({}) ORDER BY id", marcadores),
+        ids.clone(),
+    ).await?;
+    let adjuntos: Vec<(u32, String, String)> = conn.exec(
+        format!("SELECT id_atencion, nombre, referencia FROM atencion_adjuntos WHERE id_atencion IN ({}) ORDER BY id", marcadores),
+        ids.clone(),
+    ).await?;
+    let adendas: Vec<Adenda> = conn.exec(
+        format!("SELECT id, id_atencion, id_prof, texto, creada_en FROM atencion_adendas WHERE id_atencion IN ({}) ORDER BY creada_en", marcadores),
+        ids,
+    ).await?;
+
+    Ok(atenciones
+        .into_iter()
+        .map(|atencion| AtencionDetalle {
+            diagnosticos: diagnosticos
+                .iter()
+                .filter(|d| d.0 == atencion.id)
+                .map(|(_, codigo, descripcion, principal)| Diagnostico {
+                    codigo: codigo.clone(),
+                    descripcion: descripcion.clone(),
+                    principal: *principal,
+                })
+                .collect(),
+            adjuntos: adjuntos
+                .iter()
+                .filter(|a| a.0 == atencion.id)
+                .map(|(_, nombre, referencia)| AdjuntoAtencion { nombre: nombre.clone(), referencia: referencia.clone() })
+                .collect(),
+            adendas: adendas.iter().filter(|a| a.id_atencion == atencion.id).cloned().collect(),
+            atencion,
+        })
+        .collect())
+}
+
+/// Inserta diagnósticos y adjuntos de la atención `id` dentro de la transacción.
+async fn insertar_contenido(
+    tx: &mut mysql_async::Transaction<'_>,
+    id: u32,
+    detalle: &AtencionDetalle,
+) -> Result<(), AppError> {
+    tx.exec_batch(
+        "INSERT INTO atencion_diagnosticos (id_atencion, codigo, descripcion, principal) VALUES (?, ?, ?, ?)",
+        detalle.diagnosticos.iter().map(|d| (id, &d.codigo, &d.descripcion, d.principal)),
+    ).await?;
+    tx.exec_batch(
+        "INSERT INTO atencion_adjuntos (id_atencion, nombre, referencia) VALUES (?, ?, ?)",
+        detalle.adjuntos.iter().map(|a| (id, &a.nombre, &a.referencia)),
+    ).await?;
+    Ok(())
+}
+
+#[async_trait::async_trait]
+impl AtencionRepository for MysqlRepository {
+    async fn get_by_id(&self, id: u32) -> Result<Option<AtencionDetalle>, AppError> {
+        let mut conn = self.pool.get_conn().await?;
+        let query = format!("SELECT {} FROM atenciones WHERE id = ?", COLUMNAS_ATENCION);
+        let atencion: Option<Atencion> = conn.exec_first(query, (id,)).await?;
+
+        let detalles = completar(&mut conn, atencion.into_iter().collect()).await?;
+        Ok(detalles.into_iter().next())
+    }
+
+    async fn search(&self, filtro: &AtencionFiltro) -> Result<Vec<AtencionDetalle>, AppError> {
+        let mut conn = self.pool.get_conn().await?;
+        let mut condiciones = Vec::new();
+        let mut params: Vec<Value> = Vec::new();
+
+        if let Some(id_paciente) = filtro.id_paciente {
+            condiciones.push("id_paciente = ?");
+            params.push(id_paciente.into());
+        }
+        if let Some(id_prof) = filtro.id_prof {
+            condiciones.push("id_prof = ?");
+            params.push(id_prof.into());
+        }
+        if let Some(id_cita) = filtro.id_cita {
+            condiciones.push("id_cita = ?");
+            params.push(id_cita.into());
+        }
+        if let Some(estado) = &filtro.estado {
+            condiciones.push("estado = ?");
+            params.push(estado.to_uppercase().into());
+        }
+        if let Some(desde) = filtro.desde {
+            condiciones.push("creada_en >= ?");
+            params.push(desde.into());
+        }
+        if let Some(hasta) = filtro.hasta {
+            condiciones.push("creada_en < ?");
+            params.push(hasta.into());
+        }
+
+        let mut query = format!("SELECT {} FROM atenciones", COLUMNAS_ATENCION);
+        if !condiciones.is_empty() {
+            query.push_str(" WHERE ");
+            query.push_str(&condiciones.join(" AND "));
+        }
+        query.push_str(" ORDER BY creada_en DESC");
+
+        let params = if params.is_empty() { Params::Empty } else { Params::Positional(params) };
+        let atenciones: Vec<Atencion> = conn.exec(query, params).await?;
+        completar(&mut conn, atenciones).await
+    }
+
+    async fn create(&self, detalle: &AtencionDetalle) -> Result<u32, AppError> {
+        let mut conn = self.pool.get_conn().await?;
+        let mut tx = conn.start_transaction(TxOpts::default()).await?;
+        let atencion = &detalle.atencion;
+        let query = r"
+            INSERT INTO atenciones
+            (id_paciente, id_prof, id_cita, motivo, anamnesis, examen_fisico, indicaciones, estado, creada_en)
+            VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?)";
+
+        tx.exec_drop(query, (
+            &atencion.id_paciente,
+            &atencion.id_prof,
+            &atencion.id_cita,
+            &atencion.motivo,
+            &atencion.anamnesis,
+            &atencion.examen_fisico,
+            &atencion.indicaciones,
+            &atencion.estado,
+            &atencion.creada_en,
+        )).await?;
+
+        let id = tx.last_insert_id()
+            .map(|id| id as u32)
+            .ok_or_else(|| AppError::Internal("INSERT en atenciones no retornó id".into()))?;
+        insertar_contenido(&mut tx, id, detalle).await?;
+        tx.commit().await?;
+
+        Ok(id)
+    }
+
+    async fn update_borrador(&self, detalle: &AtencionDetalle) -> Result<bool, AppError> {
+        let mut conn = self.pool.get_conn().await?;
+        let mut tx = conn.start_transaction(TxOpts::default()).await?;
+        let atencion = &detalle.atencion;
+
+        // Bloquea la fila para que no se firme mientras se reemplaza el contenido
+        let estado: Option<String> = tx.exec_first("SELECT estado FROM atenciones WHERE id = ? FOR UPDATE", (atencion.id,)).await?;
+        if estado.as_deref() != Some(ATENCION_BORRADOR) {
+            tx.rollback().await?;
+            return Ok(false);
+        }
+
+        let query = r"
+            UPDATE atenciones SET
+            id_cita = ?, motivo = ?, anamnesis = ?, examen_fisico = ?, indicaciones = ?
+            WHERE id = ?";
+        tx.exec_drop(query, (
+            &atencion.id_cita,
+            &atencion.motivo,
+            &atencion.anamnesis,
+            &atencion.examen_fisico,
+            &atencion.indicaciones,
+            &atencion.id,
+        )).await?;
+
+        tx.exec_drop("DELETE FROM atencion_diagnosticos WHERE id_atencion = ?", (atencion.id,)).await?;
+        tx.exec_drop("DELETE FROM atencion_adjuntos WHERE id_atencion = ?", (atencion.id,)).await?;
+        insertar_contenido(&mut tx, atencion.id, detalle).await?;
+        tx.commit().await?;
+
+        Ok(true)
+    }
+
+    async fn firmar(&self, id: u32, firmada_en: NaiveDateTime) -> Result<bool, AppError> {
+        let mut conn = self.pool.get_conn().await?;
+        let query = "UPDATE atenciones SET estado = ?, firmada_en = ? WHERE id = ? AND estado = ?";
+
+        let result = conn.exec_iter(query, (ATENCION_FIRMADA, firmada_en, id, ATENCION_BORRADOR)).await?;
+        Ok(result.affected_rows() > 0)
+    }
+
+    async fn create_adenda(&self, adenda: &Adenda) -> Result<u32, AppError> {
+        let mut conn = self.pool.get_conn().await?;
+        let query = "INSERT INTO atencion_adendas (id_atencion, id_prof, texto, creada_en) VALUES (?, ?, ?, ?)";
+
+        let result = conn.exec_iter(query, (
+            &adenda.id_atencion,
+            &adenda.id_prof,
+            &adenda.texto,
+            &adenda.creada_en,
+        )).await?;
+
+        result.last_insert_id()
+            .map(|id| id as u32)
+            .ok_or_else(|| AppError::Internal("INSERT en atencion_adendas no retornó id".into()))
+    }
+}
@@ -0,0 +1,38 @@
+use chrono::NaiveDate;
+use mysql_async::{prelude::*, TxOpts};
+use crate::{models::Cie10, error::AppError};
+use crate::repositories::Cie10Repository;
+use super::MysqlRepository;
+
+#[async_trait::async_trait]
+impl Cie10Repository for MysqlRepository {
+    async fn version_vigente(&self, fecha: NaiveDate) -> Result<Option<String>, AppError> {
+        let mut conn = self.pool.get_conn().await?;
+        let query = "SELECT version FROM cie10_versiones WHERE vigente_desde <= ? ORDER BY vigente_desde DESC LIMIT 1";
+        Ok(conn.exec_first(query, (fecha,)).await?)
+    }
+
+    async fn codigos(&self, version: &str) -> Result<Vec<Cie10>, AppError> {
+        let mut conn = self.pool.get_conn().await?;
+        let query = "SELECT codigo, descripcion FROM cie10 WHERE version = ? ORDER BY codigo";
+        Ok(conn.exec(query, (version,)).await?)
+    }
+
+    async fn importar(&self, version: &str, vigente_desde: NaiveDate, codigos: &[Cie10]) -> Result<usize, AppError> {
+        let mut conn = self.pool.get_conn().await?;
+        let mut tx = conn.start_transaction(TxOpts::default()).await?;
+
+        tx.exec_drop(
+            "INSERT INTO cie10_versiones (version, vigente_desde) VALUES (?, ?) ON DUPLICATE KEY UPDATE vigente_desde = VALUES(vigente_desde)",
+            (version, vigente_desde),
+        ).await?;
+        tx.exec_drop("DELETE FROM cie10 WHERE version = ?", (version,)).await?;
+        tx.exec_batch(
+            "INSERT INTO cie10 (version, codigo, descripcion) VALUES (?, ?, ?)",
+            codigos.iter().map(|c| (version, &c.codigo, &c.descripcion)),
+        ).await?;
+        tx.commit().await?;
+
+        Ok(codigos.len())
+    }
+}
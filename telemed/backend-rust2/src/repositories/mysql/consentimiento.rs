@@ -0,0 +1,99 @@
+use mysql_async::{prelude::*, Params, TxOpts};
+use crate::{
+    models::{EventoConsentimiento, PlantillaConsentimiento, CONSENTIMIENTO_OTORGA},
+    error::AppError,
+};
+use crate::repositories::ConsentimientoRepository;
+use super::MysqlRepository;
+
+const COLUMNAS_PLANTILLA: &str = "id, proposito, version, titulo, texto, requiere_renovacion, creada_en";
+const COLUMNAS_EVENTO: &str = "id, id_paciente, proposito, version, accion, canal, otorgante, id_usuario, ip, ocurrido_en";
+
+/// Condición SQL: el paciente de `columna_paciente` tiene vigente el
+/// propósito que se pasa como único parámetro. Misma regla que
+/// `consentimientos::vigente`.
+pub(super) fn condicion_vigente(columna_paciente: &str) -> String {
+    format!(
+        "EXISTS (SELECT 1 FROM consentimiento_eventos e \
+         WHERE e.id = (SELECT MAX(u.id) FROM consentimiento_eventos u WHERE u.id_paciente = {} AND u.proposito = ?) \
+         AND e.accion = '{}' \
+         AND e.version >= (SELECT COALESCE(MAX(p.version), 0) FROM consentimiento_plantillas p \
+         WHERE p.proposito = e.proposito AND p.requiere_renovacion = 1))",
+        columna_paciente, CONSENTIMIENTO_OTORGA
+    )
+}
+
+#[async_trait::async_trait]
+impl ConsentimientoRepository for MysqlRepository {
+    async fn plantillas_consentimiento(&self) -> Result<Vec<PlantillaConsentimiento>, AppError> {
+        let mut conn = self.pool.get_conn().await?;
+        let query = format!(
+            "SELECT {} FROM consentimiento_plantillas p WHERE version = (SELECT MAX(version) FROM consentimiento_plantillas WHERE proposito = p.proposito) ORDER BY proposito",
+            COLUMNAS_PLANTILLA
+        );
+        Ok(conn.exec(query, Params::Empty).await?)
+    }
+
+    async fn versiones_consentimiento(&self, proposito: &str) -> Result<Vec<PlantillaConsentimiento>, AppError> {
+        let mut conn = self.pool.get_conn().await?;
+        let query = format!("SELECT {} FROM consentimiento_plantillas WHERE proposito = ? ORDER BY version", COLUMNAS_PLANTILLA);
+        Ok(conn.exec(query, (proposito,)).await?)
+    }
+
+    async fn crear_plantilla_consentimiento(&self, plantilla: &PlantillaConsentimiento) -> Result<u16, AppError> {
+        let mut conn = self.pool.get_conn().await?;
+        let mut tx = conn.start_transaction(TxOpts::default()).await?;
+
+        let ultima: Option<u16> = tx.exec_first(
+            "SELECT MAX(version) FROM consentimiento_plantillas WHERE proposito = ? FOR UPDATE",
+            (&plantilla.proposito,),
+        ).await?.flatten();
+        let version = ultima.unwrap_or(0) + 1;
+        tx.exec_drop(
+            "INSERT INTO consentimiento_plantillas (proposito, version, titulo, texto, requiere_renovacion, creada_en) VALUES (?, ?, ?, ?, ?, ?)",
+            (&plantilla.proposito, version, &plantilla.titulo, &plantilla.texto, plantilla.requiere_renovacion, &plantilla.creada_en),
+        ).await?;
+        tx.commit().await?;
+
+        Ok(version)
+    }
+
+    async fn registrar_consentimiento(&self, evento: &EventoConsentimiento) -> Result<u32, AppError> {
+        let mut conn = self.pool.get_conn().await?;
+        conn.exec_drop(
+            "INSERT INTO consentimiento_eventos (id_paciente, proposito, version, accion, canal, otorgante, id_usuario, ip, ocurrido_en) \
+             VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?)",
+            (
+                evento.id_paciente,
+                &evento.proposito,
+                evento.version,
+                &evento.accion,
+                &evento.canal,
+                &evento.otorgante,
+                evento.id_usuario,
+                &evento.ip,
+                &evento.ocurrido_en,
+            ),
+        ).await?;
+
+        conn.last_insert_id()
+            .map(|id| id as u32)
+            .ok_or_else(|| AppError::Internal("INSERT en consentimiento_eventos no retornó id".into()))
+    }
+
+    async fn eventos_consentimiento(&self, id_paciente: u32) -> Result<Vec<EventoConsentimiento>, AppError> {
+        let mut conn = self.pool.get_conn().await?;
+        let query = format!("SELECT {} FROM consentimiento_eventos WHERE id_paciente = ? ORDER BY id", COLUMNAS_EVENTO);
+        Ok(conn.exec(query, (id_paciente,)).await?)
+    }
+
+    async fn consentimiento_vigente(&self, id_paciente: u32, proposito: &str) -> Result<Option<EventoConsentimiento>, AppError> {
+        let mut conn = self.pool.get_conn().await?;
+        let query = format!(
+            "SELECT {} FROM consentimiento_eventos c WHERE c.id_paciente = ? AND c.proposito = ? AND {} ORDER BY c.id DESC LIMIT 1",
+            COLUMNAS_EVENTO,
+            condicion_vigente("c.id_paciente")
+        );
+        Ok(conn.exec_first(query, (id_paciente, proposito, proposito)).await?)
+    }
+}
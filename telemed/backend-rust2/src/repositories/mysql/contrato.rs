@@ -0,0 +1,88 @@
+use mysql_async::{prelude::*, Params, Value};
+use crate::{
+    models::{Contrato, ContratoFiltro, SituacionContractual},
+    error::AppError,
+};
+use crate::repositories::ContratoRepository;
+use super::MysqlRepository;
+
+const COLUMNAS: &str = "id, id_prof, fecha_ingreso, fecha_egreso, motivo_egreso, observacion, creado_en";
+
+#[async_trait::async_trait]
+impl ContratoRepository for MysqlRepository {
+    async fn contratos(&self, filtro: &ContratoFiltro) -> Result<Vec<Contrato>, AppError> {
+        let mut conn = self.pool.get_conn().await?;
+        let mut condiciones = Vec::new();
+        let mut params: Vec<Value> = Vec::new();
+
+        if let Some(id_prof) = filtro.id_prof {
+            condiciones.push("id_prof = ?");
+            params.push(id_prof.into());
+        }
+        if let Some(fecha) = filtro.vigente_en {
+            condiciones.push("fecha_ingreso <= ? AND (fecha_egreso IS NULL OR fecha_egreso >= ?)");
+            params.push(fecha.into());
+            params.push(fecha.into());
+        }
+
+        let mut query = format!("SELECT {} FROM contratos_profesionales", COLUMNAS);
+        if !condiciones.is_empty() {
+            query.push_str(" WHERE ");
+            query.push_str(&condiciones.join(" AND "));
+        }
+        query.push_str(" ORDER BY id_prof, fecha_ingreso");
+
+        let params = if params.is_empty() { Params::Empty } else { Params::Positional(params) };
+        Ok(conn.exec(query, params).await?)
+    }
+
+    async fn get_contrato(&self, id: u32) -> Result<Option<Contrato>, AppError> {
+        let mut conn = self.pool.get_conn().await?;
+        let query = format!("SELECT {} FROM contratos_profesionales WHERE id = ?", COLUMNAS);
+        Ok(conn.exec_first(query, (id,)).await?)
+    }
+
+    async fn create_contrato(&self, contrato: &Contrato) -> Result<u32, AppError> {
+        let mut conn = self.pool.get_conn().await?;
+        let query = r"
+            INSERT INTO contratos_profesionales (id_prof, fecha_ingreso, fecha_egreso, motivo_egreso, observacion, creado_en)
+            VALUES (?, ?, ?, ?, ?, ?)";
+        conn.exec_drop(query, (
+            contrato.id_prof,
+            contrato.fecha_ingreso,
+            contrato.fecha_egreso,
+            &contrato.motivo_egreso,
+            &contrato.observacion,
+            contrato.creado_en,
+        )).await?;
+
+        conn.last_insert_id()
+            .map(|id| id as u32)
+            .ok_or_else(|| AppError::Internal("INSERT en contratos_profesionales no retornó id".into()))
+    }
+
+    async fn update_contrato(&self, contrato: &Contrato) -> Result<(), AppError> {
+        let mut conn = self.pool.get_conn().await?;
+        let query = r"
+            UPDATE contratos_profesionales
+            SET fecha_ingreso = ?, fecha_egreso = ?, motivo_egreso = ?, observacion = ?
+            WHERE id = ?";
+        conn.exec_drop(query, (
+            contrato.fecha_ingreso,
+            contrato.fecha_egreso,
+            &contrato.motivo_egreso,
+            &contrato.observacion,
+            contrato.id,
+        )).await?;
+        Ok(())
+    }
+
+    async fn actualizar_situacion(&self, id_prof: u32, situacion: &SituacionContractual) -> Result<(), AppError> {
+        let mut conn = self.pool.get_conn().await?;
+        conn.exec_drop(
+            "UPDATE paso_profesionales SET estado = ?, fecha_ingreso = ?, fecha_egreso = ? WHERE id_prof = ?",
+            (&situacion.estado, situacion.fecha_ingreso, situacion.fecha_egreso, id_prof),
+        ).await?;
+        Ok(())
+    }
+}
@@ -0,0 +1,83 @@
+use mysql_async::{prelude::*, Params, Value};
+use crate::{
+    models::{AlertaCredencial, Credencial, CredencialFiltro},
+    error::AppError,
+};
+use crate::repositories::CredencialRepository;
+use super::MysqlRepository;
+
+const COLUMNAS: &str =
+    "id, id_prof, tipo, descripcion, numero, emitida_en, vence_en, nombre, tipo_mime, tamano, sha256, registrada_en";
+
+#[async_trait::async_trait]
+impl CredencialRepository for MysqlRepository {
+    async fn credenciales(&self, filtro: &CredencialFiltro) -> Result<Vec<Credencial>, AppError> {
+        let mut conn = self.pool.get_conn().await?;
+        let mut condiciones = Vec::new();
+        let mut params: Vec<Value> = Vec::new();
+
+        if let Some(id_prof) = filtro.id_prof {
+            condiciones.push("id_prof = ?");
+            params.push(id_prof.into());
+        }
+        if let Some(tipo) = &filtro.tipo {
+            condiciones.push("tipo = ?");
+            params.push(tipo.to_uppercase().into());
+        }
+
+        let mut query = format!("SELECT {} FROM credenciales_profesionales", COLUMNAS);
+        if !condiciones.is_empty() {
+            query.push_str(" WHERE ");
+            query.push_str(&condiciones.join(" AND "));
+        }
+        query.push_str(" ORDER BY id_prof, tipo, registrada_en, id");
+
+        let params = if params.is_empty() { Params::Empty } else { Params::Positional(params) };
+        Ok(conn.exec(query, params).await?)
+    }
+
+    async fn get_credencial(&self, id: u32) -> Result<Option<Credencial>, AppError> {
+        let mut conn = self.pool.get_conn().await?;
+        let query = format!("SELECT {} FROM credenciales_profesionales WHERE id = ?", COLUMNAS);
+        Ok(conn.exec_first(query, (id,)).await?)
+    }
+
+    async fn create_credencial(&self, credencial: &Credencial) -> Result<u32, AppError> {
+        let mut conn = self.pool.get_conn().await?;
+        let query = r"
+            INSERT INTO credenciales_profesionales
+                (id_prof, tipo, descripcion, numero, emitida_en, vence_en, nombre, tipo_mime, tamano, sha256, registrada_en)
+            VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)";
+        conn.exec_drop(query, (
+            credencial.id_prof,
+            &credencial.tipo,
+            &credencial.descripcion,
+            &credencial.numero,
+            credencial.emitida_en,
+            credencial.vence_en,
+            &credencial.nombre,
+            &credencial.tipo_mime,
+            credencial.tamano,
+            &credencial.sha256,
+            credencial.registrada_en,
+        )).await?;
+
+        conn.last_insert_id()
+            .map(|id| id as u32)
+            .ok_or_else(|| AppError::Internal("INSERT en credenciales_profesionales no retornó id".into()))
+    }
+
+    async fn alerta_enviada(&self, id_credencial: u32, dias: u16) -> Result<bool, AppError> {
+        let mut conn = self.pool.get_conn().await?;
+        let query = "SELECT 1 FROM credenciales_alertas WHERE id_credencial = ? AND dias = ?";
+        let fila: Option<u8> = conn.exec_first(query, (id_credencial, dias)).await?;
+        Ok(fila.is_some())
+    }
+
+    async fn registrar_alerta(&self, alerta: &AlertaCredencial) -> Result<bool, AppError> {
+        let mut conn = self.pool.get_conn().await?;
+        let query = "INSERT IGNORE INTO credenciales_alertas (id_credencial, dias, enviada_en) VALUES (?, ?, ?)";
+        conn.exec_drop(query, (alerta.id_credencial, alerta.dias, alerta.enviada_en)).await?;
+        Ok(conn.affected_rows() > 0)
+    }
+}
@@ -0,0 +1,151 @@
+use mysql_async::{prelude::*, Conn, Params, TxOpts, Value};
+use crate::{
+    models::{Derivacion, DerivacionDetalle, DerivacionFiltro, Diagnostico, Importacion, ResultadoImportacion},
+    error::AppError,
+};
+use crate::repositories::DerivacionRepository;
+use super::{paciente, MysqlRepository};
+
+const COLUMNAS: &str = "id, id_paciente, origen, referencia_externa, especialidad, prioridad, nota, estado, recibida_en";
+
+/// Carga los diagnósticos de las derivaciones en una consulta.
+async fn completar(conn: &mut Conn, derivaciones: Vec<Derivacion>) -> Result<Vec<DerivacionDetalle>, AppError> {
+    if derivaciones.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let marcadores = vec!["?"; derivaciones.len()].join(", ");
+    let ids: Vec<Value> = derivaciones.iter().map(|d| d.id.into()).collect();
+    let diagnosticos: Vec<(u32, String, String, bool)> = conn.exec(
+        format!("SELECT id_derivacion, codigo, descripcion, principal FROM derivacion_diagnosticos WHERE id_derivacion IN ({}) ORDER BY id", marcadores),
+        ids,
+    ).await?;
+
+    Ok(derivaciones
+        .into_iter()
+        .map(|derivacion| DerivacionDetalle {
+            diagnosticos: diagnosticos
+                .iter()
+                .filter(|d| d.0 == derivacion.id)
+                .map(|(_, codigo, descripcion, principal)| Diagnostico {
+                    codigo: codigo.clone(),
+                    descripcion: descripcion.clone(),
+                    principal: *principal,
+                })
+                .collect(),
+            derivacion,
+        })
+        .collect())
+}
+
+#[async_trait::async_trait]
+impl DerivacionRepository for MysqlRepository {
+    async fn get_derivacion(&self, id: u32) -> Result<Option<DerivacionDetalle>, AppError> {
+        let mut conn = self.pool.get_conn().await?;
+        let query = format!("SELECT {} FROM derivaciones WHERE id = ?", COLUMNAS);
+        let derivacion: Option<Derivacion> = conn.exec_first(query, (id,)).await?;
+
+        let detalles = completar(&mut conn, derivacion.into_iter().collect()).await?;
+        Ok(detalles.into_iter().next())
+    }
+
+    async fn derivaciones(&self, filtro: &DerivacionFiltro) -> Result<Vec<DerivacionDetalle>, AppError> {
+        let mut conn = self.pool.get_conn().await?;
+        let mut condiciones = Vec::new();
+        let mut params: Vec<Value> = Vec::new();
+
+        if let Some(id) = filtro.id_paciente {
+            condiciones.push("id_paciente = ?");
+            params.push(id.into());
+        }
+        if let Some(estado) = &filtro.estado {
+            condiciones.push("estado = ?");
+            params.push(estado.to_uppercase().into());
+        }
+
+        let mut query = format!("SELECT {} FROM derivaciones", COLUMNAS);
+        if !condiciones.is_empty() {
+            query.push_str(" WHERE ");
+            query.push_str(&condiciones.join(" AND "));
+        }
+        query.push_str(" ORDER BY recibida_en DESC");
+
+        let params = if params.is_empty() { Params::Empty } else { Params::Positional(params) };
+        let derivaciones: Vec<Derivacion> = conn.exec(query, params).await?;
+        completar(&mut conn, derivaciones).await
+    }
+
+    async fn importar_derivaciones(&self, importacion: &Importacion) -> Result<ResultadoImportacion, AppError> {
+        let mut conn = self.pool.get_conn().await?;
+        let mut tx = conn.start_transaction(TxOpts::default()).await?;
+        let mut resultado = ResultadoImportacion::default();
+
+        for p in &importacion.pacientes {
+            let existente: Option<u32> = tx.exec_first("SELECT id FROM pacientes WHERE rut = ? FOR UPDATE", (&p.rut,)).await?;
+            match existente {
+                Some(id) => {
+                    let mut params = paciente::valores(p);
+                    params.push(id.into());
+                    tx.exec_drop(paciente::ACTUALIZAR, params).await?;
+                    resultado.pacientes.push((id, false));
+                }
+                None => {
+                    tx.exec_drop(paciente::INSERTAR, paciente::valores(p)).await?;
+                    let id = tx.last_insert_id()
+                        .map(|id| id as u32)
+                        .ok_or_else(|| AppError::Internal("INSERT en pacientes no retornó id".into()))?;
+                    resultado.pacientes.push((id, true));
+                }
+            }
+        }
+
+        for (k, (indice, detalle)) in importacion.derivaciones.iter().enumerate() {
+            let d = &detalle.derivacion;
+            let (id_paciente, _) = resultado.pacientes.get(*indice).copied()
+                .ok_or_else(|| AppError::Internal(format!("Derivación con paciente {} inexistente", indice)))?;
+            if let Some(referencia) = &d.referencia_externa {
+                let existente: Option<(u32, u32)> = tx.exec_first(
+                    "SELECT id, id_paciente FROM derivaciones WHERE referencia_externa = ? FOR UPDATE",
+                    (referencia,),
+                ).await?;
+                if let Some((id, id_paciente_registrado)) = existente {
+                    if id_paciente_registrado != id_paciente {
+                        resultado.rechazadas.push(k);
+                    }
+                    resultado.derivaciones.push((id, false));
+                    continue;
+                }
+            }
+
+            let query = r"
+                INSERT INTO derivaciones
+                (id_paciente, origen, referencia_externa, especialidad, prioridad, nota, estado, recibida_en)
+                VALUES (?, ?, ?, ?, ?, ?, ?, ?)";
+            tx.exec_drop(query, (
+                id_paciente,
+                &d.origen,
+                &d.referencia_externa,
+                &d.especialidad,
+                &d.prioridad,
+                &d.nota,
+                &d.estado,
+                d.recibida_en,
+            )).await?;
+            let id = tx.last_insert_id()
+                .map(|id| id as u32)
+                .ok_or_else(|| AppError::Internal("INSERT en derivaciones no retornó id".into()))?;
+            tx.exec_batch(
+                "INSERT INTO derivacion_diagnosticos (id_derivacion, codigo, descripcion, principal) VALUES (?, ?, ?, ?)",
+                detalle.diagnosticos.iter().map(|g| (id, &g.codigo, &g.descripcion, g.principal)),
+            ).await?;
+            resultado.derivaciones.push((id, true));
+        }
+
+        if resultado.rechazadas.is_empty() {
+            tx.commit().await?;
+        } else {
+            tx.rollback().await?;
+        }
+        Ok(resultado)
+    }
+}
@@ -0,0 +1,168 @@
+use mysql_async::{prelude::*, Params, TxOpts, Value};
+use crate::{
+    models::{DocumentoClinico, DocumentoDetalle, DocumentoFiltro, Plantilla, VersionDocumento},
+    error::AppError,
+};
+use crate::repositories::DocumentoRepository;
+use super::MysqlRepository;
+
+const COLUMNAS_PLANTILLA: &str = "id, tipo, version, titulo, cuerpo, creada_en";
+const COLUMNAS_DOCUMENTO: &str = "id, cod_cliente, folio, tipo, id_paciente, id_prof, id_atencion, creado_en";
+const COLUMNAS_VERSION: &str = "id_documento, version, id_plantilla, id_prof, titulo, contenido, huella, creada_en";
+
+/// Inserta la versión con el número indicado dentro de la transacción.
+async fn insertar_version(
+    tx: &mut mysql_async::Transaction<'_>,
+    version: &VersionDocumento,
+    numero: u16,
+) -> Result<(), AppError> {
+    let query = r"
+        INSERT INTO documento_versiones
+        (id_documento, version, id_plantilla, id_prof, titulo, contenido, huella, creada_en)
+        VALUES (?, ?, ?, ?, ?, ?, ?, ?)";
+    tx.exec_drop(query, (
+        &version.id_documento,
+        numero,
+        &version.id_plantilla,
+        &version.id_prof,
+        &version.titulo,
+        &version.contenido,
+        &version.huella,
+        &version.creada_en,
+    )).await?;
+    Ok(())
+}
+
+#[async_trait::async_trait]
+impl DocumentoRepository for MysqlRepository {
+    async fn plantillas(&self) -> Result<Vec<Plantilla>, AppError> {
+        let mut conn = self.pool.get_conn().await?;
+        let query = format!(
+            "SELECT {} FROM documento_plantillas p WHERE version = (SELECT MAX(version) FROM documento_plantillas WHERE tipo = p.tipo) ORDER BY tipo",
+            COLUMNAS_PLANTILLA
+        );
+        Ok(conn.exec(query, Params::Empty).await?)
+    }
+
+    async fn plantilla_vigente(&self, tipo: &str) -> Result<Option<Plantilla>, AppError> {
+        let mut conn = self.pool.get_conn().await?;
+        let query = format!("SELECT {} FROM documento_plantillas WHERE tipo = ? ORDER BY version DESC LIMIT 1", COLUMNAS_PLANTILLA);
+        Ok(conn.exec_first(query, (tipo,)).await?)
+    }
+
+    async fn crear_plantilla(&self, plantilla: &Plantilla) -> Result<u16, AppError> {
+        let mut conn = self.pool.get_conn().await?;
+        let mut tx = conn.start_transaction(TxOpts::default()).await?;
+
+        let ultima: Option<u16> = tx.exec_first(
+            "SELECT MAX(version) FROM documento_plantillas WHERE tipo = ? FOR UPDATE",
+            (&plantilla.tipo,),
+        ).await?.flatten();
+        let version = ultima.unwrap_or(0) + 1;
+        tx.exec_drop(
+            "INSERT INTO documento_plantillas (tipo, version, titulo, cuerpo, creada_en) VALUES (?, ?, ?, ?, ?)",
+            (&plantilla.tipo, version, &plantilla.titulo, &plantilla.cuerpo, &plantilla.creada_en),
+        ).await?;
+        tx.commit().await?;
+
+        Ok(version)
+    }
+
+    async fn get_by_id(&self, id: u32) -> Result<Option<DocumentoDetalle>, AppError> {
+        let mut conn = self.pool.get_conn().await?;
+        let query = format!("SELECT {} FROM documentos WHERE id = ?", COLUMNAS_DOCUMENTO);
+        let Some(documento): Option<DocumentoClinico> = conn.exec_first(query, (id,)).await? else {
+            return Ok(None);
+        };
+
+        let query = format!("SELECT {} FROM documento_versiones WHERE id_documento = ? ORDER BY version", COLUMNAS_VERSION);
+        let versiones: Vec<VersionDocumento> = conn.exec(query, (id,)).await?;
+        Ok(Some(DocumentoDetalle { documento, versiones }))
+    }
+
+    async fn search(&self, filtro: &DocumentoFiltro) -> Result<Vec<DocumentoClinico>, AppError> {
+        let mut conn = self.pool.get_conn().await?;
+        let mut condiciones = Vec::new();
+        let mut params: Vec<Value> = Vec::new();
+
+        if let Some(id_paciente) = filtro.id_paciente {
+            condiciones.push("id_paciente = ?");
+            params.push(id_paciente.into());
+        }
+        if let Some(id_prof) = filtro.id_prof {
+            condiciones.push("id_prof = ?");
+            params.push(id_prof.into());
+        }
+        if let Some(tipo) = &filtro.tipo {
+            condiciones.push("tipo = ?");
+            params.push(tipo.to_uppercase().into());
+        }
+        if let Some(cod_cliente) = filtro.cod_cliente {
+            condiciones.push("cod_cliente = ?");
+            params.push(cod_cliente.into());
+        }
+
+        let mut query = format!("SELECT {} FROM documentos", COLUMNAS_DOCUMENTO);
+        if !condiciones.is_empty() {
+            query.push_str(" WHERE ");
+            query.push_str(&condiciones.join(" AND "));
+        }
+        query.push_str(" ORDER BY creado_en DESC");
+
+        let params = if params.is_empty() { Params::Empty } else { Params::Positional(params) };
+        Ok(conn.exec(query, params).await?)
+    }
+
+    async fn create(&self, documento: &DocumentoClinico, version: &VersionDocumento) -> Result<(u32, u32), AppError> {
+        let mut conn = self.pool.get_conn().await?;
+        let mut tx = conn.start_transaction(TxOpts::default()).await?;
+
+        // El UPDATE bloquea la fila del cliente hasta el commit: dos documentos
+        // simultáneos no pueden recibir el mismo folio ni dejar huecos
+        tx.exec_drop("INSERT IGNORE INTO documento_folios (cod_cliente, ultimo) VALUES (?, 0)", (documento.cod_cliente,)).await?;
+        tx.exec_drop("UPDATE documento_folios SET ultimo = ultimo + 1 WHERE cod_cliente = ?", (documento.cod_cliente,)).await?;
+        let folio: u32 = tx.exec_first("SELECT ultimo FROM documento_folios WHERE cod_cliente = ?", (documento.cod_cliente,))
+            .await?
+            .ok_or_else(|| AppError::Internal("documento_folios sin fila para el cliente".into()))?;
+
+        let query = r"
+            INSERT INTO documentos
+            (cod_cliente, folio, tipo, id_paciente, id_prof, id_atencion, creado_en)
+            VALUES (?, ?, ?, ?, ?, ?, ?)";
+        tx.exec_drop(query, (
+            &documento.cod_cliente,
+            folio,
+            &documento.tipo,
+            &documento.id_paciente,
+            &documento.id_prof,
+            &documento.id_atencion,
+            &documento.creado_en,
+        )).await?;
+        let id = tx.last_insert_id()
+            .map(|id| id as u32)
+            .ok_or_else(|| AppError::Internal("INSERT en documentos no retornó id".into()))?;
+
+        let version = VersionDocumento { id_documento: id, ..version.clone() };
+        insertar_version(&mut tx, &version, 1).await?;
+        tx.commit().await?;
+
+        Ok((id, folio))
+    }
+
+    async fn agregar_version(&self, version: &VersionDocumento) -> Result<u16, AppError> {
+        let mut conn = self.pool.get_conn().await?;
+        let mut tx = conn.start_transaction(TxOpts::default()).await?;
+
+        // Bloquea el documento para numerar las versiones sin carreras
+        tx.exec_drop("SELECT id FROM documentos WHERE id = ? FOR UPDATE", (version.id_documento,)).await?;
+        let ultima: Option<u16> = tx.exec_first(
+            "SELECT MAX(version) FROM documento_versiones WHERE id_documento = ?",
+            (version.id_documento,),
+        ).await?.flatten();
+        let numero = ultima.unwrap_or(0) + 1;
+        insertar_version(&mut tx, version, numero).await?;
+        tx.commit().await?;
+
+        Ok(numero)
+    }
+}
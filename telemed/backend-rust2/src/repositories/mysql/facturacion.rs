@@ -0,0 +1,249 @@
+use mysql_async::{prelude::*, Params, TxOpts, Value};
+use crate::{
+    models::{Cargo, CargoFiltro, LoteFacturacion, LoteFiltro, Tarifa, TarifaFiltro, CARGO_FACTURADO, CARGO_PENDIENTE},
+    error::AppError,
+};
+use crate::repositories::FacturacionRepository;
+use super::MysqlRepository;
+
+const COLUMNAS_TARIFA: &str = "id, codigo_prestacion, descripcion, modalidad, cod_prevision, cod_cliente, valor, cobertura, vigencia_desde, vigencia_hasta";
+const COLUMNAS_CARGO: &str = "id, id_atencion, id_paciente, fecha, codigo_prestacion, pagador, cod_prevision, cod_cliente, valor, monto_pagador, copago, estado, observacion, id_lote, generado_en";
+const COLUMNAS_LOTE: &str = "id, periodo, pagador, cod_prevision, cod_cliente, cantidad, total_valor, total_pagador, total_copago, creado_en";
+
+/// Valores del cargo en el orden de `COLUMNAS_CARGO`, sin el id.
+fn valores(cargo: &Cargo) -> Vec<Value> {
+    vec![
+        cargo.id_atencion.into(),
+        cargo.id_paciente.into(),
+        cargo.fecha.into(),
+        cargo.codigo_prestacion.clone().into(),
+        cargo.pagador.clone().into(),
+        cargo.cod_prevision.into(),
+        cargo.cod_cliente.into(),
+        cargo.valor.into(),
+        cargo.monto_pagador.into(),
+        cargo.copago.into(),
+        cargo.estado.clone().into(),
+        cargo.observacion.clone().into(),
+        cargo.id_lote.into(),
+        cargo.generado_en.into(),
+    ]
+}
+
+#[async_trait::async_trait]
+impl FacturacionRepository for MysqlRepository {
+    async fn tarifas(&self, filtro: &TarifaFiltro) -> Result<Vec<Tarifa>, AppError> {
+        let mut conn = self.pool.get_conn().await?;
+        let mut condiciones = Vec::new();
+        let mut params: Vec<Value> = Vec::new();
+
+        if let Some(codigo) = &filtro.codigo_prestacion {
+            condiciones.push("codigo_prestacion = ?");
+            params.push(codigo.to_uppercase().into());
+        }
+        if let Some(cod_prevision) = filtro.cod_prevision {
+            condiciones.push("cod_prevision = ?");
+            params.push(cod_prevision.into());
+        }
+        if let Some(cod_cliente) = filtro.cod_cliente {
+            condiciones.push("cod_cliente = ?");
+            params.push(cod_cliente.into());
+        }
+        if let Some(fecha) = filtro.vigente_en {
+            condiciones.push("vigencia_desde <= ? AND (vigencia_hasta IS NULL OR vigencia_hasta >= ?)");
+            params.push(fecha.into());
+            params.push(fecha.into());
+        }
+
+        let mut query = format!("SELECT {} FROM tarifas", COLUMNAS_TARIFA);
+        if !condiciones.is_empty() {
+            query.push_str(" WHERE ");
+            query.push_str(&condiciones.join(" AND "));
+        }
+        query.push_str(" ORDER BY codigo_prestacion, vigencia_desde");
+
+        let params = if params.is_empty() { Params::Empty } else { Params::Positional(params) };
+        Ok(conn.exec(query, params).await?)
+    }
+
+    async fn create_tarifa(&self, tarifa: &Tarifa) -> Result<u32, AppError> {
+        let mut conn = self.pool.get_conn().await?;
+        let query = r"
+            INSERT INTO tarifas
+            (codigo_prestacion, descripcion, modalidad, cod_prevision, cod_cliente, valor, cobertura, vigencia_desde, vigencia_hasta)
+            VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?)";
+        conn.exec_drop(query, (
+            &tarifa.codigo_prestacion,
+            &tarifa.descripcion,
+            &tarifa.modalidad,
+            tarifa.cod_prevision,
+            tarifa.cod_cliente,
+            tarifa.valor,
+            tarifa.cobertura,
+            tarifa.vigencia_desde,
+            tarifa.vigencia_hasta,
+        )).await?;
+
+        conn.last_insert_id()
+            .map(|id| id as u32)
+            .ok_or_else(|| AppError::Internal("INSERT en tarifas no retornó id".into()))
+    }
+
+    async fn get_cargo_por_atencion(&self, id_atencion: u32) -> Result<Option<Cargo>, AppError> {
+        let mut conn = self.pool.get_conn().await?;
+        let query = format!("SELECT {} FROM cargos WHERE id_atencion = ?", COLUMNAS_CARGO);
+        Ok(conn.exec_first(query, (id_atencion,)).await?)
+    }
+
+    async fn guardar_cargo(&self, cargo: &Cargo) -> Result<u32, AppError> {
+        let mut conn = self.pool.get_conn().await?;
+        if cargo.id == 0 {
+            let query = r"
+                INSERT INTO cargos
+                (id_atencion, id_paciente, fecha, codigo_prestacion, pagador, cod_prevision, cod_cliente,
+                 valor, monto_pagador, copago, estado, observacion, id_lote, generado_en)
+                VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)";
+            conn.exec_drop(query, valores(cargo)).await?;
+            return conn.last_insert_id()
+                .map(|id| id as u32)
+                .ok_or_else(|| AppError::Internal("INSERT en cargos no retornó id".into()));
+        }
+
+        let query = r"
+            UPDATE cargos SET
+            id_atencion = ?, id_paciente = ?, fecha = ?, codigo_prestacion = ?, pagador = ?, cod_prevision = ?, cod_cliente = ?,
+            valor = ?, monto_pagador = ?, copago = ?, estado = ?, observacion = ?, id_lote = ?, generado_en = ?
+            WHERE id = ? AND estado <> ?";
+        let mut params = valores(cargo);
+        params.push(cargo.id.into());
+        params.push(CARGO_FACTURADO.into());
+        conn.exec_drop(query, params).await?;
+        if conn.affected_rows() == 0 {
+            return Err(AppError::Conflict("El cargo ya fue facturado".into()));
+        }
+        Ok(cargo.id)
+    }
+
+    async fn cargos(&self, filtro: &CargoFiltro) -> Result<Vec<Cargo>, AppError> {
+        let mut conn = self.pool.get_conn().await?;
+        let mut condiciones = Vec::new();
+        let mut params: Vec<Value> = Vec::new();
+
+        if let Some(id_paciente) = filtro.id_paciente {
+            condiciones.push("id_paciente = ?");
+            params.push(id_paciente.into());
+        }
+        if let Some(estado) = &filtro.estado {
+            condiciones.push("estado = ?");
+            params.push(estado.to_uppercase().into());
+        }
+        if let Some(pagador) = &filtro.pagador {
+            condiciones.push("pagador = ?");
+            params.push(pagador.to_uppercase().into());
+        }
+        if let Some(cod_prevision) = filtro.cod_prevision {
+            condiciones.push("cod_prevision = ?");
+            params.push(cod_prevision.into());
+        }
+        if let Some(cod_cliente) = filtro.cod_cliente {
+            condiciones.push("cod_cliente = ?");
+            params.push(cod_cliente.into());
+        }
+        if let Some(desde) = filtro.desde {
+            condiciones.push("fecha >= ?");
+            params.push(desde.into());
+        }
+        if let Some(hasta) = filtro.hasta {
+            condiciones.push("fecha <= ?");
+            params.push(hasta.into());
+        }
+        if let Some(id_lote) = filtro.id_lote {
+            condiciones.push("id_lote = ?");
+            params.push(id_lote.into());
+        }
+
+        let mut query = format!("SELECT {} FROM cargos", COLUMNAS_CARGO);
+        if !condiciones.is_empty() {
+            query.push_str(" WHERE ");
+            query.push_str(&condiciones.join(" AND "));
+        }
+        query.push_str(" ORDER BY fecha, id");
+
+        let params = if params.is_empty() { Params::Empty } else { Params::Positional(params) };
+        Ok(conn.exec(query, params).await?)
+    }
+
+    async fn crear_lote(&self, lote: &LoteFacturacion, cargos: &[u32]) -> Result<u32, AppError> {
+        let mut conn = self.pool.get_conn().await?;
+        let mut tx = conn.start_transaction(TxOpts::default()).await?;
+
+        let query = r"
+            INSERT INTO lotes_facturacion
+            (periodo, pagador, cod_prevision, cod_cliente, cantidad, total_valor, total_pagador, total_copago, creado_en)
+            VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?)";
+        tx.exec_drop(query, (
+            &lote.periodo,
+            &lote.pagador,
+            lote.cod_prevision,
+            lote.cod_cliente,
+            lote.cantidad,
+            lote.total_valor,
+            lote.total_pagador,
+            lote.total_copago,
+            &lote.creado_en,
+        )).await?;
+        let id = tx.last_insert_id()
+            .map(|id| id as u32)
+            .ok_or_else(|| AppError::Internal("INSERT en lotes_facturacion no retornó id".into()))?;
+
+        if !cargos.is_empty() {
+            let mut params: Vec<Value> = vec![CARGO_FACTURADO.into(), id.into(), CARGO_PENDIENTE.into()];
+            params.extend(cargos.iter().map(|&c| c.into()));
+            tx.exec_drop(
+                format!(
+                    "UPDATE cargos SET estado = ?, id_lote = ? WHERE estado = ? AND id IN ({})",
+                    vec!["?"; cargos.len()].join(", ")
+                ),
+                params,
+            ).await?;
+            if tx.affected_rows() != cargos.len() as u64 {
+                tx.rollback().await?;
+                return Err(AppError::Conflict("Algunos cargos cambiaron mientras se armaba el lote; reintente".into()));
+            }
+        }
+        tx.commit().await?;
+
+        Ok(id)
+    }
+
+    async fn get_lote(&self, id: u32) -> Result<Option<LoteFacturacion>, AppError> {
+        let mut conn = self.pool.get_conn().await?;
+        let query = format!("SELECT {} FROM lotes_facturacion WHERE id = ?", COLUMNAS_LOTE);
+        Ok(conn.exec_first(query, (id,)).await?)
+    }
+
+    async fn lotes(&self, filtro: &LoteFiltro) -> Result<Vec<LoteFacturacion>, AppError> {
+        let mut conn = self.pool.get_conn().await?;
+        let mut condiciones = Vec::new();
+        let mut params: Vec<Value> = Vec::new();
+
+        if let Some(periodo) = &filtro.periodo {
+            condiciones.push("periodo = ?");
+            params.push(periodo.into());
+        }
+        if let Some(pagador) = &filtro.pagador {
+            condiciones.push("pagador = ?");
+            params.push(pagador.to_uppercase().into());
+        }
+
+        let mut query = format!("SELECT {} FROM lotes_facturacion", COLUMNAS_LOTE);
+        if !condiciones.is_empty() {
+            query.push_str(" WHERE ");
+            query.push_str(&condiciones.join(" AND "));
+        }
+        query.push_str(" ORDER BY periodo DESC, id DESC");
+
+        let params = if params.is_empty() { Params::Empty } else { Params::Positional(params) };
+        Ok(conn.exec(query, params).await?)
+    }
+}
@@ -0,0 +1,37 @@
+use chrono::NaiveDate;
+use mysql_async::prelude::*;
+use crate::{models::Feriado, error::AppError};
+use crate::repositories::FeriadoRepository;
+use super::MysqlRepository;
+
+#[async_trait::async_trait]
+impl FeriadoRepository for MysqlRepository {
+    async fn search(&self, desde: NaiveDate, hasta: NaiveDate) -> Result<Vec<Feriado>, AppError> {
+        let mut conn = self.pool.get_conn().await?;
+        let query = "SELECT id, fecha, nombre, cod_zona FROM feriados WHERE fecha BETWEEN ? AND ? ORDER BY fecha";
+        Ok(conn.exec(query, (desde, hasta)).await?)
+    }
+
+    async fn create(&self, feriado: &Feriado) -> Result<u32, AppError> {
+        let mut conn = self.pool.get_conn().await?;
+        let query = "INSERT INTO feriados (fecha, nombre, cod_zona) VALUES (?, ?, ?)";
+
+        let result = conn.exec_iter(query, (
+            &feriado.fecha,
+            &feriado.nombre,
+            &feriado.cod_zona,
+        )).await?;
+
+        result.last_insert_id()
+            .map(|id| id as u32)
+            .ok_or_else(|| AppError::Internal("INSERT en feriados no retornó id".into()))
+    }
+
+    async fn delete(&self, id: u32) -> Result<bool, AppError> {
+        let mut conn = self.pool.get_conn().await?;
+        let query = "DELETE FROM feriados WHERE id = ?";
+
+        let result = conn.exec_iter(query, (id,)).await?;
+        Ok(result.affected_rows() > 0)
+    }
+}
@@ -0,0 +1,135 @@
+use chrono::NaiveDateTime;
+use mysql_async::{prelude::*, Conn, Params, Row, Value};
+use crate::{
+    models::{Actualizado, Atencion, AtencionDetalle, ConsultaFhir, Paciente, PaginaFhir, Profesional, Zona},
+    error::AppError,
+};
+use crate::repositories::FhirRepository;
+use super::{atencion, consentimiento, paciente, profesional, MysqlRepository};
+
+const COLUMNAS_ZONA: &str = "cod_zona, nom_zona, orden_zona, zona_horaria";
+
+/// Tabla, columnas y orden de un recurso.
+struct Origen {
+    tabla: &'static str,
+    columnas: &'static str,
+    id: &'static str,
+}
+
+/// Cuenta las coincidencias y lee la página pedida. Todas las tablas
+/// exportadas tienen `fecha_actualizacion`, que MySQL mantiene al día.
+async fn paginar<T: FromRow>(
+    conn: &mut Conn,
+    origen: &Origen,
+    consulta: &ConsultaFhir,
+    mut condiciones: Vec<String>,
+    mut params: Vec<Value>,
+) -> Result<PaginaFhir<T>, AppError> {
+    if let Some(ids) = &consulta.ids {
+        if ids.is_empty() {
+            return Ok(PaginaFhir::default());
+        }
+        condiciones.push(format!("{} IN ({})", origen.id, vec!["?"; ids.len()].join(", ")));
+        params.extend(ids.iter().map(|id| id.as_str().into()));
+    }
+    if let Some(desde) = consulta.actualizado_desde {
+        condiciones.push("fecha_actualizacion >= ?".into());
+        params.push(desde.into());
+    }
+    if let Some(hasta) = consulta.actualizado_hasta {
+        condiciones.push("fecha_actualizacion < ?".into());
+        params.push(hasta.into());
+    }
+
+    let filtro = if condiciones.is_empty() { String::new() } else { format!(" WHERE {}", condiciones.join(" AND ")) };
+    let total: Option<u32> = conn.exec_first(
+        format!("SELECT COUNT(*) FROM {}{}", origen.tabla, filtro),
+        if params.is_empty() { Params::Empty } else { Params::Positional(params.clone()) },
+    ).await?;
+
+    params.push(consulta.cantidad.into());
+    params.push(consulta.salto.into());
+    let filas: Vec<Row> = conn.exec(
+        format!(
+            "SELECT {}, fecha_actualizacion FROM {}{} ORDER BY {} LIMIT ? OFFSET ?",
+            origen.columnas, origen.tabla, filtro, origen.id,
+        ),
+        Params::Positional(params),
+    ).await?;
+
+    let registros = filas
+        .into_iter()
+        .map(|mut fila| {
+            let actualizado_en: NaiveDateTime = fila.take("fecha_actualizacion").unwrap_or_default();
+            T::from_row_opt(fila)
+                .map(|registro| Actualizado { registro, actualizado_en })
+                .map_err(|e| AppError::Internal(format!("Fila de {} inválida: {}", origen.tabla, e)))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(PaginaFhir { total: total.unwrap_or_default(), registros })
+}
+
+#[async_trait::async_trait]
+impl FhirRepository for MysqlRepository {
+    async fn fhir_pacientes(&self, consulta: &ConsultaFhir) -> Result<PaginaFhir<Paciente>, AppError> {
+        let mut conn = self.pool.get_conn().await?;
+        let mut condiciones = Vec::new();
+        let mut params: Vec<Value> = Vec::new();
+        if let Some(rut) = &consulta.rut {
+            condiciones.push("rut = ?".to_string());
+            params.push(rut.into());
+        }
+        if let Some(proposito) = &consulta.consentimiento {
+            condiciones.push(consentimiento::condicion_vigente("pacientes.id"));
+            params.push(proposito.into());
+        }
+        let origen = Origen { tabla: "pacientes", columnas: paciente::COLUMNAS, id: "id" };
+        paginar(&mut conn, &origen, consulta, condiciones, params).await
+    }
+
+    async fn fhir_profesionales(&self, consulta: &ConsultaFhir) -> Result<PaginaFhir<Profesional>, AppError> {
+        let mut conn = self.pool.get_conn().await?;
+        let mut condiciones = Vec::new();
+        let mut params: Vec<Value> = Vec::new();
+        // paso_profesionales trae el RUT con puntos y a veces con cero inicial
+        if let Some(rut) = &consulta.rut {
+            condiciones.push("TRIM(LEADING '0' FROM REPLACE(REPLACE(rut, '.', ''), '-', '')) = ?".to_string());
+            params.push(rut.replace('-', "").trim_start_matches('0').into());
+        }
+        if let Some(rnpi) = &consulta.rnpi {
+            condiciones.push("registro_rnpi = ?".to_string());
+            params.push(rnpi.into());
+        }
+        let origen = Origen { tabla: "paso_profesionales", columnas: profesional::COLUMNAS, id: "id_prof" };
+        paginar(&mut conn, &origen, consulta, condiciones, params).await
+    }
+
+    async fn fhir_atenciones(&self, consulta: &ConsultaFhir) -> Result<PaginaFhir<AtencionDetalle>, AppError> {
+        let mut conn = self.pool.get_conn().await?;
+        let mut condiciones = Vec::new();
+        let mut params: Vec<Value> = Vec::new();
+        if let Some(proposito) = &consulta.consentimiento {
+            condiciones.push(consentimiento::condicion_vigente("atenciones.id_paciente"));
+            params.push(proposito.into());
+        }
+        let origen = Origen { tabla: "atenciones", columnas: atencion::COLUMNAS_ATENCION, id: "id" };
+        let pagina: PaginaFhir<Atencion> = paginar(&mut conn, &origen, consulta, condiciones, params).await?;
+
+        let fechas: Vec<NaiveDateTime> = pagina.registros.iter().map(|r| r.actualizado_en).collect();
+        let detalles = atencion::completar(&mut conn, pagina.registros.into_iter().map(|r| r.registro).collect()).await?;
+        Ok(PaginaFhir {
+            total: pagina.total,
+            registros: detalles
+                .into_iter()
+                .zip(fechas)
+                .map(|(registro, actualizado_en)| Actualizado { registro, actualizado_en })
+                .collect(),
+        })
+    }
+
+    async fn fhir_zonas(&self, consulta: &ConsultaFhir) -> Result<PaginaFhir<Zona>, AppError> {
+        let mut conn = self.pool.get_conn().await?;
+        let origen = Origen { tabla: "zonas_acceso", columnas: COLUMNAS_ZONA, id: "cod_zona" };
+        paginar(&mut conn, &origen, consulta, Vec::new(), Vec::new()).await
+    }
+}
@@ -0,0 +1,113 @@
+use mysql_async::{prelude::*, Params, TxOpts, Value};
+use crate::{
+    models::{ClaveFirma, Firma, FirmaFiltro},
+    error::AppError,
+};
+use crate::repositories::FirmaRepository;
+use super::MysqlRepository;
+
+const COLUMNAS_CLAVE: &str = "id, id_prof, clave_publica, clave_privada, nonce, creada_en, revocada_en";
+const COLUMNAS_FIRMA: &str = "id, tipo, id_documento, version, id_clave, id_prof, rut_firmante, rnpi_firmante, firmada_en, huella, firma";
+
+#[async_trait::async_trait]
+impl FirmaRepository for MysqlRepository {
+    async fn clave_vigente(&self, id_prof: u32) -> Result<Option<ClaveFirma>, AppError> {
+        let mut conn = self.pool.get_conn().await?;
+        let query = format!("SELECT {} FROM firma_claves WHERE id_prof = ? AND revocada_en IS NULL", COLUMNAS_CLAVE);
+        Ok(conn.exec_first(query, (id_prof,)).await?)
+    }
+
+    async fn get_clave(&self, id: u32) -> Result<Option<ClaveFirma>, AppError> {
+        let mut conn = self.pool.get_conn().await?;
+        let query = format!("SELECT {} FROM firma_claves WHERE id = ?", COLUMNAS_CLAVE);
+        Ok(conn.exec_first(query, (id,)).await?)
+    }
+
+    async fn claves(&self, id_prof: u32) -> Result<Vec<ClaveFirma>, AppError> {
+        let mut conn = self.pool.get_conn().await?;
+        let query = format!("SELECT {} FROM firma_claves WHERE id_prof = ? ORDER BY id", COLUMNAS_CLAVE);
+        Ok(conn.exec(query, (id_prof,)).await?)
+    }
+
+    async fn crear_clave(&self, clave: &ClaveFirma) -> Result<u32, AppError> {
+        let mut conn = self.pool.get_conn().await?;
+        let mut tx = conn.start_transaction(TxOpts::default()).await?;
+
+        tx.exec_drop(
+            "UPDATE firma_claves SET revocada_en = ? WHERE id_prof = ? AND revocada_en IS NULL",
+            (&clave.creada_en, &clave.id_prof),
+        ).await?;
+        tx.exec_drop(
+            "INSERT INTO firma_claves (id_prof, clave_publica, clave_privada, nonce, creada_en) VALUES (?, ?, ?, ?, ?)",
+            (&clave.id_prof, &clave.clave_publica, &clave.clave_privada, &clave.nonce, &clave.creada_en),
+        ).await?;
+        let id = tx.last_insert_id()
+            .map(|id| id as u32)
+            .ok_or_else(|| AppError::Internal("INSERT en firma_claves no retornó id".into()))?;
+        tx.commit().await?;
+
+        Ok(id)
+    }
+
+    async fn get_by_id(&self, id: u32) -> Result<Option<Firma>, AppError> {
+        let mut conn = self.pool.get_conn().await?;
+        let query = format!("SELECT {} FROM firmas WHERE id = ?", COLUMNAS_FIRMA);
+        Ok(conn.exec_first(query, (id,)).await?)
+    }
+
+    async fn search(&self, filtro: &FirmaFiltro) -> Result<Vec<Firma>, AppError> {
+        let mut conn = self.pool.get_conn().await?;
+        let mut condiciones = Vec::new();
+        let mut params: Vec<Value> = Vec::new();
+
+        if let Some(tipo) = &filtro.tipo {
+            condiciones.push("tipo = ?");
+            params.push(tipo.to_uppercase().into());
+        }
+        if let Some(id_documento) = filtro.id_documento {
+            condiciones.push("id_documento = ?");
+            params.push(id_documento.into());
+        }
+        if let Some(id_prof) = filtro.id_prof {
+            condiciones.push("id_prof = ?");
+            params.push(id_prof.into());
+        }
+
+        let mut query = format!("SELECT {} FROM firmas", COLUMNAS_FIRMA);
+        if !condiciones.is_empty() {
+            query.push_str(" WHERE ");
+            query.push_str(&condiciones.join(" AND "));
+        }
+        query.push_str(" ORDER BY firmada_en DESC");
+
+        let params = if params.is_empty() { Params::Empty } else { Params::Positional(params) };
+        Ok(conn.exec(query, params).await?)
+    }
+
+    async fn create(&self, firma: &Firma) -> Result<Option<u32>, AppError> {
+        let mut conn = self.pool.get_conn().await?;
+        // La clave única (tipo, id_documento, version) impide firmar dos veces
+        let query = r"
+            INSERT IGNORE INTO firmas
+            (tipo, id_documento, version, id_clave, id_prof, rut_firmante, rnpi_firmante, firmada_en, huella, firma)
+            VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?)";
+
+        let result = conn.exec_iter(query, (
+            &firma.tipo,
+            &firma.id_documento,
+            &firma.version,
+            &firma.id_clave,
+            &firma.id_prof,
+            &firma.rut_firmante,
+            &firma.rnpi_firmante,
+            &firma.firmada_en,
+            &firma.huella,
+            &firma.firma,
+        )).await?;
+
+        if result.affected_rows() == 0 {
+            return Ok(None);
+        }
+        Ok(result.last_insert_id().map(|id| id as u32))
+    }
+}
@@ -0,0 +1,71 @@
+use mysql_async::{prelude::*, Params, Value};
+use crate::{
+    models::{RetencionHonorarios, TarifaHonorario, TarifaHonorarioFiltro},
+    error::AppError,
+};
+use crate::repositories::HonorarioRepository;
+use super::MysqlRepository;
+
+const COLUMNAS_TARIFA: &str = "id, tipo, especialidad, cod_zona, monto, vigencia_desde, vigencia_hasta";
+
+#[async_trait::async_trait]
+impl HonorarioRepository for MysqlRepository {
+    async fn tarifas_honorario(&self, filtro: &TarifaHonorarioFiltro) -> Result<Vec<TarifaHonorario>, AppError> {
+        let mut conn = self.pool.get_conn().await?;
+        let mut condiciones = Vec::new();
+        let mut params: Vec<Value> = Vec::new();
+
+        if let Some(tipo) = &filtro.tipo {
+            condiciones.push("tipo = ?");
+            params.push(tipo.to_uppercase().into());
+        }
+        if let Some(fecha) = filtro.vigente_en {
+            condiciones.push("vigencia_desde <= ? AND (vigencia_hasta IS NULL OR vigencia_hasta >= ?)");
+            params.push(fecha.into());
+            params.push(fecha.into());
+        }
+
+        let mut query = format!("SELECT {} FROM tarifas_honorarios", COLUMNAS_TARIFA);
+        if !condiciones.is_empty() {
+            query.push_str(" WHERE ");
+            query.push_str(&condiciones.join(" AND "));
+        }
+        query.push_str(" ORDER BY tipo, vigencia_desde, id");
+
+        let params = if params.is_empty() { Params::Empty } else { Params::Positional(params) };
+        Ok(conn.exec(query, params).await?)
+    }
+
+    async fn create_tarifa_honorario(&self, tarifa: &TarifaHonorario) -> Result<u32, AppError> {
+        let mut conn = self.pool.get_conn().await?;
+        let query = r"
+            INSERT INTO tarifas_honorarios (tipo, especialidad, cod_zona, monto, vigencia_desde, vigencia_hasta)
+            VALUES (?, ?, ?, ?, ?, ?)";
+        conn.exec_drop(query, (
+            &tarifa.tipo,
+            &tarifa.especialidad,
+            &tarifa.cod_zona,
+            tarifa.monto,
+            tarifa.vigencia_desde,
+            tarifa.vigencia_hasta,
+        )).await?;
+
+        conn.last_insert_id()
+            .map(|id| id as u32)
+            .ok_or_else(|| AppError::Internal("INSERT en tarifas_honorarios no retornó id".into()))
+    }
+
+    async fn retenciones(&self) -> Result<Vec<RetencionHonorarios>, AppError> {
+        let mut conn = self.pool.get_conn().await?;
+        Ok(conn.exec("SELECT vigencia_desde, tasa FROM retenciones_honorarios ORDER BY vigencia_desde", Params::Empty).await?)
+    }
+
+    async fn guardar_retencion(&self, retencion: &RetencionHonorarios) -> Result<(), AppError> {
+        let mut conn = self.pool.get_conn().await?;
+        conn.exec_drop(
+            "INSERT INTO retenciones_honorarios (vigencia_desde, tasa) VALUES (?, ?) ON DUPLICATE KEY UPDATE tasa = VALUES(tasa)",
+            (retencion.vigencia_desde, retencion.tasa),
+        ).await?;
+        Ok(())
+    }
+}
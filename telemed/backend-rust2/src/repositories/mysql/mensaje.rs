@@ -0,0 +1,168 @@
+use chrono::NaiveDateTime;
+use mysql_async::{prelude::*, Params, TxOpts, Value};
+use crate::{
+    models::{Conversacion, ConversacionFiltro, Lectura, MensajeCifrado, Participante},
+    error::AppError,
+};
+use crate::repositories::MensajeRepository;
+use super::MysqlRepository;
+
+const COLUMNAS_CONVERSACION: &str = "id, id_paciente, asunto, cod_cliente, cod_zona, retencion_dias, creada_en, ultimo_mensaje_en";
+const COLUMNAS_MENSAJE: &str = "id, id_conversacion, autor_tipo, autor_id, nonce, contenido, enviado_en";
+const COLUMNAS_LECTURA: &str = "id_conversacion, lector_tipo, lector_id, hasta, leido_en";
+
+#[async_trait::async_trait]
+impl MensajeRepository for MysqlRepository {
+    async fn get_conversacion(&self, id: u32) -> Result<Option<Conversacion>, AppError> {
+        let mut conn = self.pool.get_conn().await?;
+        let query = format!("SELECT {} FROM conversaciones WHERE id = ?", COLUMNAS_CONVERSACION);
+        Ok(conn.exec_first(query, (id,)).await?)
+    }
+
+    async fn conversaciones(&self, filtro: &ConversacionFiltro) -> Result<Vec<Conversacion>, AppError> {
+        let mut conn = self.pool.get_conn().await?;
+        let mut condiciones = Vec::new();
+        let mut params: Vec<Value> = Vec::new();
+
+        if let Some(id_paciente) = filtro.id_paciente {
+            condiciones.push("id_paciente = ?");
+            params.push(id_paciente.into());
+        }
+        let en_zonas;
+        if let Some(zonas) = &filtro.zonas {
+            if zonas.is_empty() {
+                return Ok(Vec::new());
+            }
+            en_zonas = format!("cod_zona IN ({})", vec!["?"; zonas.len()].join(", "));
+            condiciones.push(en_zonas.as_str());
+            params.extend(zonas.iter().map(|z| Value::from(z.as_str())));
+        }
+        if let Some(cod_cliente) = filtro.cod_cliente {
+            condiciones.push("cod_cliente = ?");
+            params.push(cod_cliente.into());
+        }
+
+        let mut query = format!("SELECT {} FROM conversaciones", COLUMNAS_CONVERSACION);
+        if !condiciones.is_empty() {
+            query.push_str(" WHERE ");
+            query.push_str(&condiciones.join(" AND "));
+        }
+        query.push_str(" ORDER BY COALESCE(ultimo_mensaje_en, creada_en) DESC");
+
+        let params = if params.is_empty() { Params::Empty } else { Params::Positional(params) };
+        Ok(conn.exec(query, params).await?)
+    }
+
+    async fn create_conversacion(&self, conversacion: &Conversacion) -> Result<u32, AppError> {
+        let mut conn = self.pool.get_conn().await?;
+        let query = r"
+            INSERT INTO conversaciones (id_paciente, asunto, cod_cliente, cod_zona, retencion_dias, creada_en)
+            VALUES (?, ?, ?, ?, ?, ?)";
+
+        conn.exec_drop(query, (
+            &conversacion.id_paciente,
+            &conversacion.asunto,
+            &conversacion.cod_cliente,
+            &conversacion.cod_zona,
+            &conversacion.retencion_dias,
+            &conversacion.creada_en,
+        )).await?;
+
+        conn.last_insert_id()
+            .map(|id| id as u32)
+            .ok_or_else(|| AppError::Internal("INSERT en conversaciones no retornó id".into()))
+    }
+
+    async fn create_mensaje(&self, mensaje: &MensajeCifrado) -> Result<u32, AppError> {
+        let mut conn = self.pool.get_conn().await?;
+        let mut tx = conn.start_transaction(TxOpts::default()).await?;
+        let query = r"
+            INSERT INTO mensajes (id_conversacion, autor_tipo, autor_id, nonce, contenido, enviado_en)
+            VALUES (?, ?, ?, ?, ?, ?)";
+
+        tx.exec_drop(query, (
+            &mensaje.id_conversacion,
+            &mensaje.autor_tipo,
+            &mensaje.autor_id,
+            &mensaje.nonce,
+            &mensaje.contenido,
+            &mensaje.enviado_en,
+        )).await?;
+
+        let id = tx.last_insert_id()
+            .map(|id| id as u32)
+            .ok_or_else(|| AppError::Internal("INSERT en mensajes no retornó id".into()))?;
+        tx.exec_drop(
+            "UPDATE conversaciones SET ultimo_mensaje_en = ? WHERE id = ?",
+            (&mensaje.enviado_en, mensaje.id_conversacion),
+        ).await?;
+        tx.commit().await?;
+
+        Ok(id)
+    }
+
+    async fn mensajes(&self, id_conversacion: u32, antes_de: Option<u32>, limite: u16) -> Result<Vec<MensajeCifrado>, AppError> {
+        let mut conn = self.pool.get_conn().await?;
+        let query = format!(
+            "SELECT {} FROM mensajes WHERE id_conversacion = ? AND id < ? ORDER BY id DESC LIMIT ?",
+            COLUMNAS_MENSAJE,
+        );
+        Ok(conn.exec(query, (id_conversacion, antes_de.unwrap_or(u32::MAX), limite)).await?)
+    }
+
+    async fn lecturas(&self, id_conversacion: u32) -> Result<Vec<Lectura>, AppError> {
+        let mut conn = self.pool.get_conn().await?;
+        let query = format!("SELECT {} FROM conversacion_lecturas WHERE id_conversacion = ?", COLUMNAS_LECTURA);
+        Ok(conn.exec(query, (id_conversacion,)).await?)
+    }
+
+    async fn marcar_leido(&self, lectura: &Lectura) -> Result<bool, AppError> {
+        let mut conn = self.pool.get_conn().await?;
+        let query = r"
+            INSERT INTO conversacion_lecturas (id_conversacion, lector_tipo, lector_id, hasta, leido_en)
+            VALUES (?, ?, ?, ?, ?)
+            ON DUPLICATE KEY UPDATE
+                leido_en = IF(VALUES(hasta) > hasta, VALUES(leido_en), leido_en),
+                hasta = GREATEST(hasta, VALUES(hasta))";
+
+        conn.exec_drop(query, (
+            &lectura.id_conversacion,
+            &lectura.lector_tipo,
+            &lectura.lector_id,
+            &lectura.hasta,
+            &lectura.leido_en,
+        )).await?;
+        // 1 fila afectada al insertar, 2 al actualizar y 0 si no cambió
+        Ok(conn.affected_rows() > 0)
+    }
+
+    async fn no_leidos(&self, id_conversacion: u32, lector: &Participante) -> Result<u32, AppError> {
+        let mut conn = self.pool.get_conn().await?;
+        let query = r"
+            SELECT COUNT(*) FROM mensajes m
+            WHERE m.id_conversacion = ?
+              AND NOT (m.autor_tipo = ? AND m.autor_id = ?)
+              AND m.id > COALESCE((
+                  SELECT l.hasta FROM conversacion_lecturas l
+                  WHERE l.id_conversacion = m.id_conversacion AND l.lector_tipo = ? AND l.lector_id = ?
+              ), 0)";
+        let total: Option<u32> = conn.exec_first(query, (
+            id_conversacion,
+            &lector.tipo,
+            lector.id,
+            &lector.tipo,
+            lector.id,
+        )).await?;
+        Ok(total.unwrap_or(0))
+    }
+
+    async fn depurar(&self, ahora: NaiveDateTime) -> Result<u64, AppError> {
+        let mut conn = self.pool.get_conn().await?;
+        let query = r"
+            DELETE m FROM mensajes m
+            JOIN conversaciones c ON c.id = m.id_conversacion
+            WHERE m.enviado_en < DATE_SUB(?, INTERVAL c.retencion_dias DAY)";
+        conn.exec_drop(query, (ahora,)).await?;
+        Ok(conn.affected_rows())
+    }
+}
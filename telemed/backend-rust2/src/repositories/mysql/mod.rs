@@ -2,6 +2,32 @@ use mysql_async::{prelude::*, Pool, Params, Row};
 use crate::{models::Usuario, error::AppError};
 use crate::repositories::UsuarioRepository;
 
+mod paciente;
+mod prevision;
+mod profesional;
+mod agenda;
+mod zona;
+mod feriado;
+mod atencion;
+mod cie10;
+mod visita;
+mod receta;
+mod documento;
+mod firma;
+mod teleconsulta;
+mod sala_espera;
+mod mensaje;
+mod notificacion;
+mod recordatorio;
+mod adjunto;
+mod fhir;
+mod derivacion;
+mod consentimiento;
+mod facturacion;
+mod honorario;
+mod contrato;
+mod credencial;
+
 #[derive(Clone)]
 pub struct MysqlRepository {
     pool: Pool,
@@ -16,7 +42,7 @@ impl MysqlRepository {
         let mut conn = self.pool.get_conn().await?;
         let row: Option<Row> = conn.exec_first(query, params).await?;
         
-        Ok(row.map(|r| Usuario::from_row(r)))
+        Ok(row.map(Usuario::from_row))
     }
 
     async fn query_multiple(&self, query: &str, params: Params) -> Result<Vec<Usuario>, AppError> {
@@ -0,0 +1,158 @@
+use chrono::NaiveDateTime;
+use mysql_async::{prelude::*, Params, TxOpts, Value};
+use crate::{
+    models::{Notificacion, NotificacionFiltro, PreferenciaNotificacion, CANAL_APP, NOTIFICACION_ENVIADA, NOTIFICACION_LEIDA, NOTIFICACION_PENDIENTE},
+    error::AppError,
+};
+use crate::repositories::NotificacionRepository;
+use super::MysqlRepository;
+
+const COLUMNAS_NOTIFICACION: &str = "id, plantilla, destinatario_tipo, destinatario_id, canal, direccion, asunto, cuerpo, estado, intentos, proximo_intento_en, ultimo_error, creada_en, enviada_en, leida_en";
+
+#[async_trait::async_trait]
+impl NotificacionRepository for MysqlRepository {
+    async fn preferencias(&self, destinatario_tipo: &str, destinatario_id: u32) -> Result<Option<PreferenciaNotificacion>, AppError> {
+        let mut conn = self.pool.get_conn().await?;
+        let query = r"
+            SELECT destinatario_tipo, destinatario_id, email, sms, app FROM notificacion_preferencias
+            WHERE destinatario_tipo = ? AND destinatario_id = ?";
+        Ok(conn.exec_first(query, (destinatario_tipo, destinatario_id)).await?)
+    }
+
+    async fn guardar_preferencias(&self, preferencia: &PreferenciaNotificacion) -> Result<(), AppError> {
+        let mut conn = self.pool.get_conn().await?;
+        let query = r"
+            INSERT INTO notificacion_preferencias (destinatario_tipo, destinatario_id, email, sms, app)
+            VALUES (?, ?, ?, ?, ?)
+            ON DUPLICATE KEY UPDATE email = VALUES(email), sms = VALUES(sms), app = VALUES(app)";
+
+        conn.exec_drop(query, (
+            &preferencia.destinatario_tipo,
+            &preferencia.destinatario_id,
+            &preferencia.email,
+            &preferencia.sms,
+            &preferencia.app,
+        )).await?;
+        Ok(())
+    }
+
+    async fn encolar(&self, notificacion: &Notificacion) -> Result<u32, AppError> {
+        let mut conn = self.pool.get_conn().await?;
+        let query = r"
+            INSERT INTO notificaciones
+            (plantilla, destinatario_tipo, destinatario_id, canal, direccion, asunto, cuerpo, estado, intentos, proximo_intento_en, creada_en, enviada_en)
+            VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)";
+
+        conn.exec_drop(query, (
+            &notificacion.plantilla,
+            &notificacion.destinatario_tipo,
+            &notificacion.destinatario_id,
+            &notificacion.canal,
+            &notificacion.direccion,
+            &notificacion.asunto,
+            &notificacion.cuerpo,
+            &notificacion.estado,
+            &notificacion.intentos,
+            &notificacion.proximo_intento_en,
+            &notificacion.creada_en,
+            &notificacion.enviada_en,
+        )).await?;
+
+        conn.last_insert_id()
+            .map(|id| id as u32)
+            .ok_or_else(|| AppError::Internal("INSERT en notificaciones no retornó id".into()))
+    }
+
+    async fn get_notificacion(&self, id: u32) -> Result<Option<Notificacion>, AppError> {
+        let mut conn = self.pool.get_conn().await?;
+        let query = format!("SELECT {} FROM notificaciones WHERE id = ?", COLUMNAS_NOTIFICACION);
+        Ok(conn.exec_first(query, (id,)).await?)
+    }
+
+    async fn notificaciones(&self, filtro: &NotificacionFiltro) -> Result<Vec<Notificacion>, AppError> {
+        let mut conn = self.pool.get_conn().await?;
+        let mut condiciones = Vec::new();
+        let mut params: Vec<Value> = Vec::new();
+
+        if let Some(tipo) = &filtro.destinatario_tipo {
+            condiciones.push("destinatario_tipo = ?");
+            params.push(tipo.to_uppercase().into());
+        }
+        if let Some(id) = filtro.destinatario_id {
+            condiciones.push("destinatario_id = ?");
+            params.push(id.into());
+        }
+        if let Some(canal) = &filtro.canal {
+            condiciones.push("canal = ?");
+            params.push(canal.to_uppercase().into());
+        }
+        if let Some(estado) = &filtro.estado {
+            condiciones.push("estado = ?");
+            params.push(estado.to_uppercase().into());
+        }
+        if let Some(plantilla) = &filtro.plantilla {
+            condiciones.push("plantilla = ?");
+            params.push(plantilla.to_uppercase().into());
+        }
+
+        let mut query = format!("SELECT {} FROM notificaciones", COLUMNAS_NOTIFICACION);
+        if !condiciones.is_empty() {
+            query.push_str(" WHERE ");
+            query.push_str(&condiciones.join(" AND "));
+        }
+        query.push_str(" ORDER BY creada_en DESC, id DESC LIMIT 500");
+
+        let params = if params.is_empty() { Params::Empty } else { Params::Positional(params) };
+        Ok(conn.exec(query, params).await?)
+    }
+
+    async fn reclamar(&self, ahora: NaiveDateTime, reservar_hasta: NaiveDateTime, limite: u32) -> Result<Vec<Notificacion>, AppError> {
+        let mut conn = self.pool.get_conn().await?;
+        let mut tx = conn.start_transaction(TxOpts::default()).await?;
+        let query = format!(
+            "SELECT {} FROM notificaciones WHERE estado = ? AND proximo_intento_en <= ? \
+             ORDER BY proximo_intento_en LIMIT ? FOR UPDATE SKIP LOCKED",
+            COLUMNAS_NOTIFICACION,
+        );
+        let mut reclamadas: Vec<Notificacion> = tx.exec(query, (NOTIFICACION_PENDIENTE, ahora, limite)).await?;
+        if !reclamadas.is_empty() {
+            let marcas = vec!["?"; reclamadas.len()].join(", ");
+            let mut params: Vec<Value> = vec![reservar_hasta.into()];
+            params.extend(reclamadas.iter().map(|n| n.id.into()));
+            tx.exec_drop(
+                format!("UPDATE notificaciones SET proximo_intento_en = ? WHERE id IN ({})", marcas),
+                Params::Positional(params),
+            ).await?;
+        }
+        tx.commit().await?;
+        for notificacion in &mut reclamadas {
+            notificacion.proximo_intento_en = reservar_hasta;
+        }
+        Ok(reclamadas)
+    }
+
+    async fn registrar_intento(&self, notificacion: &Notificacion) -> Result<(), AppError> {
+        let mut conn = self.pool.get_conn().await?;
+        let query = r"
+            UPDATE notificaciones
+            SET estado = ?, intentos = ?, proximo_intento_en = ?, ultimo_error = ?, enviada_en = ?
+            WHERE id = ?";
+
+        conn.exec_drop(query, (
+            &notificacion.estado,
+            &notificacion.intentos,
+            &notificacion.proximo_intento_en,
+            &notificacion.ultimo_error,
+            &notificacion.enviada_en,
+            &notificacion.id,
+        )).await?;
+        Ok(())
+    }
+
+    async fn marcar_leida(&self, id: u32, ahora: NaiveDateTime) -> Result<bool, AppError> {
+        let mut conn = self.pool.get_conn().await?;
+        let query = "UPDATE notificaciones SET estado = ?, leida_en = ? WHERE id = ? AND canal = ? AND estado = ?";
+        conn.exec_drop(query, (NOTIFICACION_LEIDA, ahora, id, CANAL_APP, NOTIFICACION_ENVIADA)).await?;
+        Ok(conn.affected_rows() > 0)
+    }
+}
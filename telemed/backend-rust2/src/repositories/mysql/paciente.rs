@@ -0,0 +1,125 @@
+use mysql_async::{prelude::*, Params, Value};
+use crate::{models::{Paciente, PacienteFiltro}, error::AppError};
+use crate::repositories::PacienteRepository;
+use super::MysqlRepository;
+
+pub(super) const COLUMNAS: &str = "id, rut, nombres, ap_paterno, ap_materno, fecha_nacimiento, sexo, cod_prevision, direccion, comuna, ciudad, cod_zona, latitud, longitud, email, telefonos, cod_cliente, estatus";
+
+pub(super) const INSERTAR: &str = r"
+    INSERT INTO pacientes
+    (rut, nombres, ap_paterno, ap_materno, fecha_nacimiento, sexo, cod_prevision, direccion, comuna, ciudad, cod_zona, latitud, longitud, email, telefonos, cod_cliente, estatus)
+    VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)";
+
+pub(super) const ACTUALIZAR: &str = r"
+    UPDATE pacientes SET
+    rut = ?, nombres = ?, ap_paterno = ?, ap_materno = ?, fecha_nacimiento = ?, sexo = ?,
+    cod_prevision = ?, direccion = ?, comuna = ?, ciudad = ?, cod_zona = ?, latitud = ?, longitud = ?, email = ?,
+    telefonos = ?, cod_cliente = ?, estatus = ?
+    WHERE id = ?";
+
+/// Error 1062 de MySQL: otra escritura concurrente ya tomó el RUT.
+const ER_DUP_ENTRY: u16 = 1062;
+
+/// Traduce la violación del índice único de `rut` a un conflicto; la
+/// verificación previa de la API no alcanza a ver inserciones simultáneas.
+fn rut_duplicado(e: mysql_async::Error, rut: &str) -> AppError {
+    match e {
+        mysql_async::Error::Server(ref s) if s.code == ER_DUP_ENTRY => {
+            AppError::Conflict(format!("Ya existe un paciente con RUT {}", rut))
+        }
+        e => e.into(),
+    }
+}
+
+/// Valores de `INSERTAR` y, agregando el id, de `ACTUALIZAR`.
+pub(super) fn valores(paciente: &Paciente) -> Vec<Value> {
+    vec![
+        paciente.rut.as_str().into(),
+        paciente.nombres.as_str().into(),
+        paciente.ap_paterno.as_str().into(),
+        paciente.ap_materno.clone().into(),
+        paciente.fecha_nacimiento.into(),
+        paciente.sexo.clone().into(),
+        paciente.cod_prevision.into(),
+        paciente.direccion.clone().into(),
+        paciente.comuna.clone().into(),
+        paciente.ciudad.clone().into(),
+        paciente.cod_zona.clone().into(),
+        paciente.latitud.into(),
+        paciente.longitud.into(),
+        paciente.email.clone().into(),
+        paciente.telefonos.clone().into(),
+        paciente.cod_cliente.into(),
+        paciente.estatus.into(),
+    ]
+}
+
+#[async_trait::async_trait]
+impl PacienteRepository for MysqlRepository {
+    async fn get_by_id(&self, id: u32) -> Result<Option<Paciente>, AppError> {
+        let mut conn = self.pool.get_conn().await?;
+        let query = format!("SELECT {} FROM pacientes WHERE id = ?", COLUMNAS);
+        Ok(conn.exec_first(query, (id,)).await?)
+    }
+
+    async fn get_by_rut(&self, rut: &str) -> Result<Option<Paciente>, AppError> {
+        let mut conn = self.pool.get_conn().await?;
+        let query = format!("SELECT {} FROM pacientes WHERE rut = ?", COLUMNAS);
+        Ok(conn.exec_first(query, (rut,)).await?)
+    }
+
+    async fn search(&self, filtro: &PacienteFiltro) -> Result<Vec<Paciente>, AppError> {
+        let mut conn = self.pool.get_conn().await?;
+        let mut condiciones = Vec::new();
+        let mut params: Vec<Value> = Vec::new();
+
+        if let Some(rut) = &filtro.rut {
+            condiciones.push("rut = ?");
+            params.push(rut.into());
+        }
+        if let Some(nombre) = &filtro.nombre {
+            condiciones.push("CONCAT_WS(' ', nombres, ap_paterno, ap_materno) LIKE ?");
+            params.push(format!("%{}%", nombre.trim().to_uppercase()).into());
+        }
+
+        let mut query = format!("SELECT {} FROM pacientes", COLUMNAS);
+        if !condiciones.is_empty() {
+            query.push_str(" WHERE ");
+            query.push_str(&condiciones.join(" AND "));
+        }
+        query.push_str(" ORDER BY ap_paterno, ap_materno, nombres");
+
+        let params = if params.is_empty() { Params::Empty } else { Params::Positional(params) };
+        Ok(conn.exec(query, params).await?)
+    }
+
+    async fn create(&self, paciente: &Paciente) -> Result<u32, AppError> {
+        let mut conn = self.pool.get_conn().await?;
+        let result = conn.exec_iter(INSERTAR, valores(paciente))
+            .await
+            .map_err(|e| rut_duplicado(e, &paciente.rut))?;
+
+        result.last_insert_id()
+            .map(|id| id as u32)
+            .ok_or_else(|| AppError::Internal("INSERT en pacientes no retornó id".into()))
+    }
+
+    async fn update(&self, paciente: &Paciente) -> Result<(), AppError> {
+        let mut conn = self.pool.get_conn().await?;
+        let mut params = valores(paciente);
+        params.push(paciente.id.into());
+        conn.exec_drop(ACTUALIZAR, params)
+            .await
+            .map_err(|e| rut_duplicado(e, &paciente.rut))?;
+
+        Ok(())
+    }
+
+    async fn delete(&self, id: u32) -> Result<bool, AppError> {
+        let mut conn = self.pool.get_conn().await?;
+        let query = "DELETE FROM pacientes WHERE id = ?";
+
+        let result = conn.exec_iter(query, (id,)).await?;
+        Ok(result.affected_rows() > 0)
+    }
+}
@@ -0,0 +1,87 @@
+use mysql_async::{prelude::*, Params, Value};
+use crate::{models::{Prevision, PrevisionFiltro}, error::AppError};
+use crate::repositories::PrevisionRepository;
+use super::MysqlRepository;
+
+const COLUMNAS: &str = "cod_prevision, tipo, nombre, tramo, vigencia_desde, vigencia_hasta";
+
+#[async_trait::async_trait]
+impl PrevisionRepository for MysqlRepository {
+    async fn get_by_id(&self, cod_prevision: u32) -> Result<Option<Prevision>, AppError> {
+        let mut conn = self.pool.get_conn().await?;
+        let query = format!("SELECT {} FROM previsiones WHERE cod_prevision = ?", COLUMNAS);
+        Ok(conn.exec_first(query, (cod_prevision,)).await?)
+    }
+
+    async fn search(&self, filtro: &PrevisionFiltro) -> Result<Vec<Prevision>, AppError> {
+        let mut conn = self.pool.get_conn().await?;
+        let mut condiciones = Vec::new();
+        let mut params: Vec<Value> = Vec::new();
+
+        if let Some(tipo) = &filtro.tipo {
+            condiciones.push("tipo = ?");
+            params.push(tipo.to_uppercase().into());
+        }
+        if let Some(fecha) = filtro.vigente_en {
+            condiciones.push("vigencia_desde <= ? AND (vigencia_hasta IS NULL OR vigencia_hasta >= ?)");
+            params.push(fecha.into());
+            params.push(fecha.into());
+        }
+
+        let mut query = format!("SELECT {} FROM previsiones", COLUMNAS);
+        if !condiciones.is_empty() {
+            query.push_str(" WHERE ");
+            query.push_str(&condiciones.join(" AND "));
+        }
+        query.push_str(" ORDER BY tipo, tramo, nombre");
+
+        let params = if params.is_empty() { Params::Empty } else { Params::Positional(params) };
+        Ok(conn.exec(query, params).await?)
+    }
+
+    async fn create(&self, prevision: &Prevision) -> Result<(), AppError> {
+        let mut conn = self.pool.get_conn().await?;
+        let query = r"
+            INSERT INTO previsiones
+            (cod_prevision, tipo, nombre, tramo, vigencia_desde, vigencia_hasta)
+            VALUES (?, ?, ?, ?, ?, ?)";
+
+        conn.exec_drop(query, (
+            &prevision.cod_prevision,
+            &prevision.tipo,
+            &prevision.nombre,
+            &prevision.tramo,
+            &prevision.vigencia_desde,
+            &prevision.vigencia_hasta,
+        )).await?;
+
+        Ok(())
+    }
+
+    async fn update(&self, prevision: &Prevision) -> Result<(), AppError> {
+        let mut conn = self.pool.get_conn().await?;
+        let query = r"
+            UPDATE previsiones SET
+            tipo = ?, nombre = ?, tramo = ?, vigencia_desde = ?, vigencia_hasta = ?
+            WHERE cod_prevision = ?";
+
+        conn.exec_drop(query, (
+            &prevision.tipo,
+            &prevision.nombre,
+            &prevision.tramo,
+            &prevision.vigencia_desde,
+            &prevision.vigencia_hasta,
+            &prevision.cod_prevision,
+        )).await?;
+
+        Ok(())
+    }
+
+    async fn delete(&self, cod_prevision: u32) -> Result<bool, AppError> {
+        let mut conn = self.pool.get_conn().await?;
+        let query = "DELETE FROM previsiones WHERE cod_prevision = ?";
+
+        let result = conn.exec_iter(query, (cod_prevision,)).await?;
+        Ok(result.affected_rows() > 0)
+    }
+}
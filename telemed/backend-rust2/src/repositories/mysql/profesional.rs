@@ -0,0 +1,40 @@
+use mysql_async::{prelude::*, Params, Value};
+use crate::{models::{Profesional, ProfesionalFiltro}, error::AppError};
+use crate::repositories::ProfesionalRepository;
+use super::MysqlRepository;
+
+pub(super) const COLUMNAS: &str = "id_prof, rut, nombres, ap_paterno, ap_materno, direccion, comuna, ciudad, email, telefonos, especialidad, registro_rnpi, fecha_ingreso, fecha_egreso, zona, estado, conara";
+
+#[async_trait::async_trait]
+impl ProfesionalRepository for MysqlRepository {
+    async fn get_by_id(&self, id_prof: u32) -> Result<Option<Profesional>, AppError> {
+        let mut conn = self.pool.get_conn().await?;
+        let query = format!("SELECT {} FROM paso_profesionales WHERE id_prof = ?", COLUMNAS);
+        Ok(conn.exec_first(query, (id_prof,)).await?)
+    }
+
+    async fn search(&self, filtro: &ProfesionalFiltro) -> Result<Vec<Profesional>, AppError> {
+        let mut conn = self.pool.get_conn().await?;
+        let mut condiciones = Vec::new();
+        let mut params: Vec<Value> = Vec::new();
+
+        if let Some(especialidad) = &filtro.especialidad {
+            condiciones.push("especialidad = ?");
+            params.push(especialidad.into());
+        }
+        if let Some(zona) = &filtro.zona {
+            condiciones.push("zona = ?");
+            params.push(zona.into());
+        }
+
+        let mut query = format!("SELECT {} FROM paso_profesionales", COLUMNAS);
+        if !condiciones.is_empty() {
+            query.push_str(" WHERE ");
+            query.push_str(&condiciones.join(" AND "));
+        }
+        query.push_str(" ORDER BY ap_paterno, ap_materno, nombres");
+
+        let params = if params.is_empty() { Params::Empty } else { Params::Positional(params) };
+        Ok(conn.exec(query, params).await?)
+    }
+}
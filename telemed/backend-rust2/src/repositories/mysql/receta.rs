@@ -0,0 +1,135 @@
+use mysql_async::{prelude::*, Conn, Params, TxOpts, Value};
+use crate::{
+    models::{LineaReceta, Receta, RecetaDetalle, RecetaFiltro, RECETA_ANULADA, RECETA_EMITIDA},
+    error::AppError,
+};
+use crate::repositories::RecetaRepository;
+use super::MysqlRepository;
+
+const COLUMNAS_RECETA: &str = "id, id_atencion, id_paciente, id_prof, registro_rnpi, codigo, huella, indicaciones, estado, motivo_anulacion, emitida_en, valida_hasta";
+
+/// Carga las líneas de las recetas en una sola consulta.
+async fn completar(conn: &mut Conn, recetas: Vec<Receta>) -> Result<Vec<RecetaDetalle>, AppError> {
+    if recetas.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let marcadores = vec!["?"; recetas.len()].join(", ");
+    let ids: Vec<Value> = recetas.iter().map(|r| r.id.into()).collect();
+    let lineas: Vec<(u32, String, String, String, String)> = conn.exec(
+        format!("SELECT id_receta, medicamento, dosis, frecuencia, duracion FROM receta_lineas WHERE id_receta IN ({}) ORDER BY id", marcadores),
+        ids,
+    ).await?;
+
+    Ok(recetas
+        .into_iter()
+        .map(|receta| RecetaDetalle {
+            lineas: lineas
+                .iter()
+                .filter(|l| l.0 == receta.id)
+                .map(|(_, medicamento, dosis, frecuencia, duracion)| LineaReceta {
+                    medicamento: medicamento.clone(),
+                    dosis: dosis.clone(),
+                    frecuencia: frecuencia.clone(),
+                    duracion: duracion.clone(),
+                })
+                .collect(),
+            receta,
+        })
+        .collect())
+}
+
+#[async_trait::async_trait]
+impl RecetaRepository for MysqlRepository {
+    async fn get_by_id(&self, id: u32) -> Result<Option<RecetaDetalle>, AppError> {
+        let mut conn = self.pool.get_conn().await?;
+        let query = format!("SELECT {} FROM recetas WHERE id = ?", COLUMNAS_RECETA);
+        let receta: Option<Receta> = conn.exec_first(query, (id,)).await?;
+
+        Ok(completar(&mut conn, receta.into_iter().collect()).await?.into_iter().next())
+    }
+
+    async fn get_by_codigo(&self, codigo: &str) -> Result<Option<RecetaDetalle>, AppError> {
+        let mut conn = self.pool.get_conn().await?;
+        let query = format!("SELECT {} FROM recetas WHERE codigo = ?", COLUMNAS_RECETA);
+        let receta: Option<Receta> = conn.exec_first(query, (codigo,)).await?;
+
+        Ok(completar(&mut conn, receta.into_iter().collect()).await?.into_iter().next())
+    }
+
+    async fn search(&self, filtro: &RecetaFiltro) -> Result<Vec<RecetaDetalle>, AppError> {
+        let mut conn = self.pool.get_conn().await?;
+        let mut condiciones = Vec::new();
+        let mut params: Vec<Value> = Vec::new();
+
+        if let Some(id_paciente) = filtro.id_paciente {
+            condiciones.push("id_paciente = ?");
+            params.push(id_paciente.into());
+        }
+        if let Some(id_prof) = filtro.id_prof {
+            condiciones.push("id_prof = ?");
+            params.push(id_prof.into());
+        }
+        if let Some(id_atencion) = filtro.id_atencion {
+            condiciones.push("id_atencion = ?");
+            params.push(id_atencion.into());
+        }
+        if let Some(estado) = &filtro.estado {
+            condiciones.push("estado = ?");
+            params.push(estado.to_uppercase().into());
+        }
+
+        let mut query = format!("SELECT {} FROM recetas", COLUMNAS_RECETA);
+        if !condiciones.is_empty() {
+            query.push_str(" WHERE ");
+            query.push_str(&condiciones.join(" AND "));
+        }
+        query.push_str(" ORDER BY emitida_en DESC");
+
+        let params = if params.is_empty() { Params::Empty } else { Params::Positional(params) };
+        let recetas: Vec<Receta> = conn.exec(query, params).await?;
+        completar(&mut conn, recetas).await
+    }
+
+    async fn create(&self, detalle: &RecetaDetalle) -> Result<u32, AppError> {
+        let mut conn = self.pool.get_conn().await?;
+        let mut tx = conn.start_transaction(TxOpts::default()).await?;
+        let receta = &detalle.receta;
+        let query = r"
+            INSERT INTO recetas
+            (id_atencion, id_paciente, id_prof, registro_rnpi, codigo, huella, indicaciones, estado, emitida_en, valida_hasta)
+            VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?)";
+
+        tx.exec_drop(query, (
+            &receta.id_atencion,
+            &receta.id_paciente,
+            &receta.id_prof,
+            &receta.registro_rnpi,
+            &receta.codigo,
+            &receta.huella,
+            &receta.indicaciones,
+            &receta.estado,
+            &receta.emitida_en,
+            &receta.valida_hasta,
+        )).await?;
+
+        let id = tx.last_insert_id()
+            .map(|id| id as u32)
+            .ok_or_else(|| AppError::Internal("INSERT en recetas no retornó id".into()))?;
+        tx.exec_batch(
+            "INSERT INTO receta_lineas (id_receta, medicamento, dosis, frecuencia, duracion) VALUES (?, ?, ?, ?, ?)",
+            detalle.lineas.iter().map(|l| (id, &l.medicamento, &l.dosis, &l.frecuencia, &l.duracion)),
+        ).await?;
+        tx.commit().await?;
+
+        Ok(id)
+    }
+
+    async fn anular(&self, id: u32, motivo: &str) -> Result<bool, AppError> {
+        let mut conn = self.pool.get_conn().await?;
+        let query = "UPDATE recetas SET estado = ?, motivo_anulacion = ? WHERE id = ? AND estado = ?";
+
+        let result = conn.exec_iter(query, (RECETA_ANULADA, motivo, id, RECETA_EMITIDA)).await?;
+        Ok(result.affected_rows() > 0)
+    }
+}
@@ -0,0 +1,60 @@
+use mysql_async::{prelude::*, Params, Value};
+use crate::{
+    models::{RecordatorioCita, RespuestaCita},
+    error::AppError,
+};
+use crate::repositories::RecordatorioRepository;
+use super::MysqlRepository;
+
+fn marcadores(ids: &[u32]) -> (String, Params) {
+    let params: Vec<Value> = ids.iter().map(|&id| id.into()).collect();
+    (vec!["?"; ids.len()].join(", "), Params::Positional(params))
+}
+
+#[async_trait::async_trait]
+impl RecordatorioRepository for MysqlRepository {
+    async fn registrar_recordatorio(&self, recordatorio: &RecordatorioCita) -> Result<bool, AppError> {
+        let mut conn = self.pool.get_conn().await?;
+        let query = "INSERT IGNORE INTO cita_recordatorios (id_cita, tipo, enviado_en) VALUES (?, ?, ?)";
+        conn.exec_drop(query, (&recordatorio.id_cita, &recordatorio.tipo, &recordatorio.enviado_en)).await?;
+        Ok(conn.affected_rows() > 0)
+    }
+
+    async fn recordatorios(&self, ids_cita: &[u32]) -> Result<Vec<RecordatorioCita>, AppError> {
+        if ids_cita.is_empty() {
+            return Ok(Vec::new());
+        }
+        let mut conn = self.pool.get_conn().await?;
+        let (marcadores, params) = marcadores(ids_cita);
+        let query = format!(
+            "SELECT id_cita, tipo, enviado_en FROM cita_recordatorios WHERE id_cita IN ({}) ORDER BY enviado_en",
+            marcadores,
+        );
+        Ok(conn.exec(query, params).await?)
+    }
+
+    async fn get_respuesta(&self, id_cita: u32) -> Result<Option<RespuestaCita>, AppError> {
+        let mut conn = self.pool.get_conn().await?;
+        let query = "SELECT id_cita, respuesta, respondida_en FROM cita_respuestas WHERE id_cita = ?";
+        Ok(conn.exec_first(query, (id_cita,)).await?)
+    }
+
+    async fn guardar_respuesta(&self, respuesta: &RespuestaCita) -> Result<(), AppError> {
+        let mut conn = self.pool.get_conn().await?;
+        let query = r"
+            INSERT INTO cita_respuestas (id_cita, respuesta, respondida_en) VALUES (?, ?, ?)
+            ON DUPLICATE KEY UPDATE respuesta = VALUES(respuesta), respondida_en = VALUES(respondida_en)";
+        conn.exec_drop(query, (&respuesta.id_cita, &respuesta.respuesta, &respuesta.respondida_en)).await?;
+        Ok(())
+    }
+
+    async fn respuestas(&self, ids_cita: &[u32]) -> Result<Vec<RespuestaCita>, AppError> {
+        if ids_cita.is_empty() {
+            return Ok(Vec::new());
+        }
+        let mut conn = self.pool.get_conn().await?;
+        let (marcadores, params) = marcadores(ids_cita);
+        let query = format!("SELECT id_cita, respuesta, respondida_en FROM cita_respuestas WHERE id_cita IN ({})", marcadores);
+        Ok(conn.exec(query, params).await?)
+    }
+}
@@ -0,0 +1,51 @@
+use mysql_async::prelude::*;
+use crate::{
+    models::{EntradaEspera, ESPERA_ESPERANDO, ESPERA_LLAMADO},
+    error::AppError,
+};
+use crate::repositories::SalaEsperaRepository;
+use super::MysqlRepository;
+
+const COLUMNAS_ESPERA: &str = "id_cita, id_prof, id_paciente, estado, llegada_en, llamada_en, cerrada_en";
+
+#[async_trait::async_trait]
+impl SalaEsperaRepository for MysqlRepository {
+    async fn get_by_cita(&self, id_cita: u32) -> Result<Option<EntradaEspera>, AppError> {
+        let mut conn = self.pool.get_conn().await?;
+        let query = format!("SELECT {} FROM sala_espera WHERE id_cita = ?", COLUMNAS_ESPERA);
+        Ok(conn.exec_first(query, (id_cita,)).await?)
+    }
+
+    async fn guardar(&self, entrada: &EntradaEspera) -> Result<(), AppError> {
+        let mut conn = self.pool.get_conn().await?;
+        let query = r"
+            INSERT INTO sala_espera (id_cita, id_prof, id_paciente, estado, llegada_en, llamada_en, cerrada_en)
+            VALUES (?, ?, ?, ?, ?, ?, ?)
+            ON DUPLICATE KEY UPDATE
+                estado = VALUES(estado), llegada_en = VALUES(llegada_en),
+                llamada_en = VALUES(llamada_en), cerrada_en = VALUES(cerrada_en)";
+
+        conn.exec_drop(query, (
+            &entrada.id_cita,
+            &entrada.id_prof,
+            &entrada.id_paciente,
+            &entrada.estado,
+            &entrada.llegada_en,
+            &entrada.llamada_en,
+            &entrada.cerrada_en,
+        )).await?;
+        Ok(())
+    }
+
+    async fn abiertas(&self, id_prof: u32) -> Result<Vec<EntradaEspera>, AppError> {
+        let mut conn = self.pool.get_conn().await?;
+        let query = format!("SELECT {} FROM sala_espera WHERE id_prof = ? AND estado IN (?, ?)", COLUMNAS_ESPERA);
+        Ok(conn.exec(query, (id_prof, ESPERA_ESPERANDO, ESPERA_LLAMADO)).await?)
+    }
+
+    async fn profesionales_en_espera(&self) -> Result<Vec<u32>, AppError> {
+        let mut conn = self.pool.get_conn().await?;
+        let query = "SELECT DISTINCT id_prof FROM sala_espera WHERE estado IN (?, ?) ORDER BY id_prof";
+        Ok(conn.exec(query, (ESPERA_ESPERANDO, ESPERA_LLAMADO)).await?)
+    }
+}
@@ -0,0 +1,49 @@
+use chrono::NaiveDateTime;
+use mysql_async::prelude::*;
+use crate::{
+    models::Teleconsulta,
+    error::AppError,
+};
+use crate::repositories::TeleconsultaRepository;
+use super::MysqlRepository;
+
+#[async_trait::async_trait]
+impl TeleconsultaRepository for MysqlRepository {
+    async fn get_by_cita(&self, id_cita: u32) -> Result<Option<Teleconsulta>, AppError> {
+        let mut conn = self.pool.get_conn().await?;
+        let query = r"
+            SELECT id_cita, token_paciente, token_profesional, creada_en, iniciada_en, terminada_en
+            FROM teleconsultas WHERE id_cita = ?";
+        Ok(conn.exec_first(query, (id_cita,)).await?)
+    }
+
+    async fn guardar(&self, teleconsulta: &Teleconsulta) -> Result<(), AppError> {
+        let mut conn = self.pool.get_conn().await?;
+        let query = r"
+            INSERT INTO teleconsultas (id_cita, token_paciente, token_profesional, creada_en)
+            VALUES (?, ?, ?, ?)
+            ON DUPLICATE KEY UPDATE token_paciente = VALUES(token_paciente), token_profesional = VALUES(token_profesional)";
+
+        conn.exec_drop(query, (
+            &teleconsulta.id_cita,
+            &teleconsulta.token_paciente,
+            &teleconsulta.token_profesional,
+            &teleconsulta.creada_en,
+        )).await?;
+        Ok(())
+    }
+
+    async fn iniciar(&self, id_cita: u32, en: NaiveDateTime) -> Result<bool, AppError> {
+        let mut conn = self.pool.get_conn().await?;
+        let query = "UPDATE teleconsultas SET iniciada_en = ? WHERE id_cita = ? AND iniciada_en IS NULL";
+        let result = conn.exec_iter(query, (en, id_cita)).await?;
+        Ok(result.affected_rows() > 0)
+    }
+
+    async fn terminar(&self, id_cita: u32, en: NaiveDateTime) -> Result<bool, AppError> {
+        let mut conn = self.pool.get_conn().await?;
+        let query = "UPDATE teleconsultas SET terminada_en = ? WHERE id_cita = ? AND terminada_en IS NULL";
+        let result = conn.exec_iter(query, (en, id_cita)).await?;
+        Ok(result.affected_rows() > 0)
+    }
+}
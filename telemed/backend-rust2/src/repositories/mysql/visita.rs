@@ -0,0 +1,175 @@
+use mysql_async::{prelude::*, Params, TxOpts, Value};
+use crate::{
+    models::{EventoVisita, MarcaVisita, Visita, VisitaFiltro, VISITA_PENDIENTE},
+    error::AppError,
+};
+use crate::repositories::VisitaRepository;
+use super::MysqlRepository;
+
+const COLUMNAS_VISITA: &str = "id, id_paciente, id_prof, cod_zona, direccion, comuna, latitud, longitud, fecha, ventana_desde, ventana_hasta, duracion_min, motivo, estado, motivo_estado, creada_en";
+const COLUMNAS_MARCA: &str = "id, id_visita, tipo, latitud, longitud, precision_m, distancia_km, registrada_en, recibida_en";
+
+#[async_trait::async_trait]
+impl VisitaRepository for MysqlRepository {
+    async fn get_by_id(&self, id: u32) -> Result<Option<Visita>, AppError> {
+        let mut conn = self.pool.get_conn().await?;
+        let query = format!("SELECT {} FROM visitas WHERE id = ?", COLUMNAS_VISITA);
+        Ok(conn.exec_first(query, (id,)).await?)
+    }
+
+    async fn search(&self, filtro: &VisitaFiltro) -> Result<Vec<Visita>, AppError> {
+        let mut conn = self.pool.get_conn().await?;
+        let mut condiciones = Vec::new();
+        let mut params: Vec<Value> = Vec::new();
+
+        if let Some(id_prof) = filtro.id_prof {
+            condiciones.push("id_prof = ?");
+            params.push(id_prof.into());
+        }
+        if let Some(id_paciente) = filtro.id_paciente {
+            condiciones.push("id_paciente = ?");
+            params.push(id_paciente.into());
+        }
+        if let Some(cod_zona) = &filtro.cod_zona {
+            condiciones.push("cod_zona = ?");
+            params.push(cod_zona.into());
+        }
+        if let Some(fecha) = filtro.fecha {
+            condiciones.push("fecha = ?");
+            params.push(fecha.into());
+        }
+        if let Some(desde) = filtro.desde {
+            condiciones.push("fecha >= ?");
+            params.push(desde.into());
+        }
+        if let Some(hasta) = filtro.hasta {
+            condiciones.push("fecha <= ?");
+            params.push(hasta.into());
+        }
+        if let Some(estado) = &filtro.estado {
+            condiciones.push("estado = ?");
+            params.push(estado.to_uppercase().into());
+        }
+
+        let mut query = format!("SELECT {} FROM visitas", COLUMNAS_VISITA);
+        if !condiciones.is_empty() {
+            query.push_str(" WHERE ");
+            query.push_str(&condiciones.join(" AND "));
+        }
+        query.push_str(" ORDER BY fecha, ventana_desde IS NULL, ventana_desde, id");
+
+        let params = if params.is_empty() { Params::Empty } else { Params::Positional(params) };
+        Ok(conn.exec(query, params).await?)
+    }
+
+    async fn create(&self, visita: &Visita) -> Result<u32, AppError> {
+        let mut conn = self.pool.get_conn().await?;
+        let mut tx = conn.start_transaction(TxOpts::default()).await?;
+        let query = r"
+            INSERT INTO visitas
+            (id_paciente, id_prof, cod_zona, direccion, comuna, latitud, longitud, fecha, ventana_desde, ventana_hasta, duracion_min, motivo, estado, motivo_estado, creada_en)
+            VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)";
+
+        let params: Vec<Value> = vec![
+            visita.id_paciente.into(),
+            visita.id_prof.into(),
+            visita.cod_zona.as_str().into(),
+            visita.direccion.as_str().into(),
+            visita.comuna.as_deref().into(),
+            visita.latitud.into(),
+            visita.longitud.into(),
+            visita.fecha.into(),
+            visita.ventana_desde.into(),
+            visita.ventana_hasta.into(),
+            visita.duracion_min.into(),
+            visita.motivo.as_str().into(),
+            visita.estado.as_str().into(),
+            visita.motivo_estado.as_deref().into(),
+            visita.creada_en.into(),
+        ];
+        tx.exec_drop(query, params).await?;
+
+        let id = tx.last_insert_id()
+            .map(|id| id as u32)
+            .ok_or_else(|| AppError::Internal("INSERT en visitas no retornó id".into()))?;
+        tx.exec_drop(
+            "INSERT INTO visita_eventos (id_visita, estado, motivo, registrado_en) VALUES (?, ?, NULL, ?)",
+            (id, &visita.estado, &visita.creada_en),
+        ).await?;
+        tx.commit().await?;
+
+        Ok(id)
+    }
+
+    async fn asignar(&self, id: u32, id_prof: u32) -> Result<bool, AppError> {
+        let mut conn = self.pool.get_conn().await?;
+        let query = "UPDATE visitas SET id_prof = ? WHERE id = ? AND estado = ?";
+
+        let result = conn.exec_iter(query, (id_prof, id, VISITA_PENDIENTE)).await?;
+        Ok(result.affected_rows() > 0)
+    }
+
+    async fn cambiar_estado(&self, id: u32, desde: &str, evento: &EventoVisita) -> Result<bool, AppError> {
+        let mut conn = self.pool.get_conn().await?;
+        let mut tx = conn.start_transaction(TxOpts::default()).await?;
+
+        tx.exec_drop(
+            "UPDATE visitas SET estado = ?, motivo_estado = ? WHERE id = ? AND estado = ?",
+            (&evento.estado, &evento.motivo, id, desde),
+        ).await?;
+        if tx.affected_rows() == 0 {
+            tx.rollback().await?;
+            return Ok(false);
+        }
+
+        tx.exec_drop(
+            "INSERT INTO visita_eventos (id_visita, estado, motivo, registrado_en) VALUES (?, ?, ?, ?)",
+            (id, &evento.estado, &evento.motivo, &evento.registrado_en),
+        ).await?;
+        tx.commit().await?;
+
+        Ok(true)
+    }
+
+    async fn historial(&self, id: u32) -> Result<Vec<EventoVisita>, AppError> {
+        let mut conn = self.pool.get_conn().await?;
+        let query = "SELECT estado, motivo, registrado_en FROM visita_eventos WHERE id_visita = ? ORDER BY registrado_en, id";
+        Ok(conn.exec(query, (id,)).await?)
+    }
+
+    async fn registrar_marca(&self, marca: &MarcaVisita) -> Result<Option<u32>, AppError> {
+        let mut conn = self.pool.get_conn().await?;
+        // La clave única (id_visita, tipo) descarta la marca repetida
+        let query = r"
+            INSERT IGNORE INTO visita_marcas
+            (id_visita, tipo, latitud, longitud, precision_m, distancia_km, registrada_en, recibida_en)
+            VALUES (?, ?, ?, ?, ?, ?, ?, ?)";
+
+        let result = conn.exec_iter(query, (
+            &marca.id_visita,
+            &marca.tipo,
+            &marca.latitud,
+            &marca.longitud,
+            &marca.precision_m,
+            &marca.distancia_km,
+            &marca.registrada_en,
+            &marca.recibida_en,
+        )).await?;
+
+        if result.affected_rows() == 0 {
+            return Ok(None);
+        }
+        Ok(result.last_insert_id().map(|id| id as u32))
+    }
+
+    async fn marcas(&self, ids_visita: &[u32]) -> Result<Vec<MarcaVisita>, AppError> {
+        if ids_visita.is_empty() {
+            return Ok(Vec::new());
+        }
+        let mut conn = self.pool.get_conn().await?;
+        let marcadores = vec!["?"; ids_visita.len()].join(", ");
+        let query = format!("SELECT {} FROM visita_marcas WHERE id_visita IN ({}) ORDER BY registrada_en", COLUMNAS_MARCA, marcadores);
+        let params: Vec<Value> = ids_visita.iter().map(|&id| id.into()).collect();
+        Ok(conn.exec(query, params).await?)
+    }
+}
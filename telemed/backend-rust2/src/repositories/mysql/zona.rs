@@ -0,0 +1,33 @@
+use mysql_async::{prelude::*, Params};
+use crate::{models::Zona, error::AppError};
+use crate::repositories::ZonaRepository;
+use super::MysqlRepository;
+
+#[async_trait::async_trait]
+impl ZonaRepository for MysqlRepository {
+    async fn get_by_id(&self, cod_zona: &str) -> Result<Option<Zona>, AppError> {
+        let mut conn = self.pool.get_conn().await?;
+        let query = "SELECT cod_zona, nom_zona, orden_zona, zona_horaria FROM zonas_acceso WHERE cod_zona = ?";
+        Ok(conn.exec_first(query, (cod_zona,)).await?)
+    }
+
+    async fn get_all(&self) -> Result<Vec<Zona>, AppError> {
+        let mut conn = self.pool.get_conn().await?;
+        let query = "SELECT cod_zona, nom_zona, orden_zona, zona_horaria FROM zonas_acceso ORDER BY orden_zona";
+        Ok(conn.exec(query, Params::Empty).await?)
+    }
+
+    async fn update(&self, zona: &Zona) -> Result<(), AppError> {
+        let mut conn = self.pool.get_conn().await?;
+        let query = "UPDATE zonas_acceso SET nom_zona = ?, orden_zona = ?, zona_horaria = ? WHERE cod_zona = ?";
+
+        conn.exec_drop(query, (
+            &zona.nom_zona,
+            &zona.orden_zona,
+            &zona.zona_horaria,
+            &zona.cod_zona,
+        )).await?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,63 @@
+use crate::error::AppError;
+
+/// Normaliza un RUT al formato usado en la BD: 10 dígitos con ceros a la
+/// izquierda, guion y dígito verificador en mayúscula (ej. `0010895960-6`).
+/// Acepta puntos, espacios y guion opcional en la entrada.
+pub fn normalizar(rut: &str) -> Result<String, AppError> {
+    let limpio: String = rut
+        .chars()
+        .filter(|c| !matches!(c, '.' | '-' | ' '))
+        .map(|c| c.to_ascii_uppercase())
+        .collect();
+
+    if limpio.len() < 2 {
+        return Err(AppError::Validation(format!("RUT inválido: {}", rut)));
+    }
+
+    let (cuerpo, dv) = limpio.split_at(limpio.len() - 1);
+    if cuerpo.len() > 10 || !cuerpo.chars().all(|c| c.is_ascii_digit()) {
+        return Err(AppError::Validation(format!("RUT inválido: {}", rut)));
+    }
+
+    let dv = dv.chars().next().unwrap_or_default();
+    if digito_verificador(cuerpo) != dv {
+        return Err(AppError::Validation(format!("Dígito verificador incorrecto: {}", rut)));
+    }
+
+    Ok(format!("{:0>10}-{}", cuerpo, dv))
+}
+
+/// Calcula el dígito verificador (módulo 11) de la parte numérica de un RUT.
+pub fn digito_verificador(cuerpo: &str) -> char {
+    let suma: u32 = cuerpo
+        .chars()
+        .rev()
+        .filter_map(|c| c.to_digit(10))
+        .zip([2, 3, 4, 5, 6, 7].iter().cycle())
+        .map(|(d, f)| d * f)
+        .sum();
+
+    match 11 - (suma % 11) {
+        11 => '0',
+        10 => 'K',
+        n => char::from_digit(n, 10).unwrap_or('0'),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn normaliza_formatos_habituales() {
+        assert_eq!(normalizar("10.895.960-6").unwrap(), "0010895960-6");
+        assert_eq!(normalizar("0010895960-6").unwrap(), "0010895960-6");
+        assert_eq!(normalizar("12602780k").unwrap(), "0012602780-K");
+    }
+
+    #[test]
+    fn rechaza_digito_incorrecto() {
+        assert!(normalizar("10.895.960-5").is_err());
+        assert!(normalizar("abc").is_err());
+    }
+}
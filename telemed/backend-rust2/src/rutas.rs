@@ -0,0 +1,182 @@
+use crate::geo;
+
+/// Las calles no van en línea recta: la distancia haversine se multiplica
+/// por este factor para estimar el recorrido real.
+const FACTOR_CALLES: f64 = 1.3;
+/// Costo de cada minuto de atraso respecto de la ventana, en minutos de viaje.
+const PENALIZACION_ATRASO: f64 = 10.0;
+const MAX_PASADAS_2OPT: usize = 50;
+
+/// Visita a ordenar. Los tiempos están en minutos desde la medianoche local;
+/// la ventana acota la hora de inicio de la atención.
+#[derive(Debug, Clone)]
+pub struct Parada {
+    pub id_visita: u32,
+    pub punto: (f64, f64),
+    pub ventana_desde: Option<i64>,
+    pub ventana_hasta: Option<i64>,
+    pub duracion_min: i64,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Parametros {
+    /// Punto de partida del profesional; sin él la ruta parte en la primera visita.
+    pub origen: Option<(f64, f64)>,
+    pub inicio_min: i64,
+    pub velocidad_kmh: f64,
+}
+
+/// Resultado de recorrer una parada en el orden propuesto.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Tramo {
+    pub id_visita: u32,
+    pub distancia_km: f64,
+    pub llegada_min: i64,
+    pub inicio_min: i64,
+    pub fin_min: i64,
+    pub atraso_min: i64,
+}
+
+fn viaje(desde: Option<(f64, f64)>, hasta: (f64, f64), velocidad_kmh: f64) -> (f64, i64) {
+    let km = desde.map_or(0.0, |d| geo::distancia_km(d, hasta) * FACTOR_CALLES);
+    (km, (km / velocidad_kmh * 60.0).ceil() as i64)
+}
+
+/// Recorre las paradas en `orden` esperando la apertura de cada ventana.
+/// Retorna los tramos y el costo: minutos hasta terminar la última visita
+/// más la penalización por atrasos.
+pub fn simular(paradas: &[Parada], orden: &[usize], parametros: &Parametros) -> (Vec<Tramo>, f64) {
+    let mut posicion = parametros.origen;
+    let mut reloj = parametros.inicio_min;
+    let mut atraso_total = 0;
+    let mut tramos = Vec::with_capacity(orden.len());
+
+    for &i in orden {
+        let parada = &paradas[i];
+        let (distancia_km, minutos) = viaje(posicion, parada.punto, parametros.velocidad_kmh);
+        let llegada_min = reloj + minutos;
+        let inicio_min = parada.ventana_desde.map_or(llegada_min, |d| llegada_min.max(d));
+        let atraso_min = parada.ventana_hasta.map_or(0, |h| (inicio_min - h).max(0));
+
+        reloj = inicio_min + parada.duracion_min;
+        posicion = Some(parada.punto);
+        atraso_total += atraso_min;
+        tramos.push(Tramo {
+            id_visita: parada.id_visita,
+            distancia_km,
+            llegada_min,
+            inicio_min,
+            fin_min: reloj,
+            atraso_min,
+        });
+    }
+
+    let costo = (reloj - parametros.inicio_min) as f64 + PENALIZACION_ATRASO * atraso_total as f64;
+    (tramos, costo)
+}
+
+/// Vecino más cercano: desde la posición actual elige la visita que puede
+/// comenzar antes, penalizando las que quedarían fuera de su ventana.
+fn vecino_mas_cercano(paradas: &[Parada], parametros: &Parametros) -> Vec<usize> {
+    let mut pendientes: Vec<usize> = (0..paradas.len()).collect();
+    let mut orden = Vec::with_capacity(paradas.len());
+    let mut posicion = parametros.origen;
+    let mut reloj = parametros.inicio_min;
+
+    while !pendientes.is_empty() {
+        let (k, inicio) = pendientes
+            .iter()
+            .enumerate()
+            .map(|(k, &i)| {
+                let parada = &paradas[i];
+                let llegada = reloj + viaje(posicion, parada.punto, parametros.velocidad_kmh).1;
+                let inicio = parada.ventana_desde.map_or(llegada, |d| llegada.max(d));
+                let atraso = parada.ventana_hasta.map_or(0, |h| (inicio - h).max(0));
+                let costo = (inicio - reloj) as f64 + PENALIZACION_ATRASO * atraso as f64;
+                (k, inicio, costo)
+            })
+            .min_by(|a, b| a.2.total_cmp(&b.2))
+            .map(|(k, inicio, _)| (k, inicio))
+            .expect("quedan paradas pendientes");
+
+        let i = pendientes.remove(k);
+        reloj = inicio + paradas[i].duracion_min;
+        posicion = Some(paradas[i].punto);
+        orden.push(i);
+    }
+    orden
+}
+
+/// Orden de visita: vecino más cercano mejorado con 2-opt (invierte tramos
+/// mientras baje el costo). Retorna índices sobre `paradas`.
+pub fn ordenar(paradas: &[Parada], parametros: &Parametros) -> Vec<usize> {
+    let mut orden = vecino_mas_cercano(paradas, parametros);
+    let mut mejor = simular(paradas, &orden, parametros).1;
+
+    for _ in 0..MAX_PASADAS_2OPT {
+        let mut mejoro = false;
+        for i in 0..orden.len().saturating_sub(1) {
+            for j in i + 1..orden.len() {
+                let mut candidato = orden.clone();
+                candidato[i..=j].reverse();
+                let costo = simular(paradas, &candidato, parametros).1;
+                if costo + 1e-9 < mejor {
+                    orden = candidato;
+                    mejor = costo;
+                    mejoro = true;
+                }
+            }
+        }
+        if !mejoro {
+            break;
+        }
+    }
+    orden
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parada(id_visita: u32, punto: (f64, f64)) -> Parada {
+        Parada { id_visita, punto, ventana_desde: None, ventana_hasta: None, duracion_min: 30 }
+    }
+
+    fn parametros() -> Parametros {
+        Parametros { origen: Some((-36.6066, -72.1034)), inicio_min: 8 * 60, velocidad_kmh: 30.0 }
+    }
+
+    fn ids(paradas: &[Parada], orden: &[usize]) -> Vec<u32> {
+        orden.iter().map(|&i| paradas[i].id_visita).collect()
+    }
+
+    #[test]
+    fn recorre_en_linea_desde_el_origen() {
+        // Cuatro domicilios alineados hacia el norte de Chillán, entregados desordenados
+        let paradas = vec![
+            parada(3, (-36.576, -72.1034)),
+            parada(1, (-36.596, -72.1034)),
+            parada(4, (-36.566, -72.1034)),
+            parada(2, (-36.586, -72.1034)),
+        ];
+        let orden = ordenar(&paradas, &parametros());
+        assert_eq!(ids(&paradas, &orden), [1, 2, 3, 4]);
+    }
+
+    #[test]
+    fn respeta_ventanas_horarias() {
+        // La visita más cercana sólo puede atenderse desde las 11:00
+        let mut cercana = parada(1, (-36.600, -72.1034));
+        cercana.ventana_desde = Some(11 * 60);
+        let mut lejana = parada(2, (-36.560, -72.1034));
+        lejana.ventana_hasta = Some(9 * 60);
+        let paradas = vec![cercana, lejana];
+
+        let orden = ordenar(&paradas, &parametros());
+        assert_eq!(ids(&paradas, &orden), [2, 1]);
+
+        let (tramos, _) = simular(&paradas, &orden, &parametros());
+        assert_eq!(tramos[0].atraso_min, 0);
+        assert_eq!(tramos[1].inicio_min, 11 * 60);
+    }
+}
@@ -0,0 +1,142 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+use chrono::{Duration, NaiveDateTime};
+use tokio::sync::broadcast;
+
+use crate::{
+    error::AppError,
+    models::{Cita, ColaEspera, EntradaEspera, PosicionEspera, ESPERA_LLAMADO},
+};
+
+/// Minutos que tiene un paciente llamado para entrar a la videollamada
+/// antes de quedar como ausente.
+pub const MINUTOS_PARA_ENTRAR: i64 = 5;
+const CAPACIDAD_AVISOS: usize = 16;
+
+/// Llamados que no entraron a tiempo.
+pub fn ausentes(entradas: &[EntradaEspera], ahora: NaiveDateTime) -> Vec<u32> {
+    entradas
+        .iter()
+        .filter(|e| e.estado == ESPERA_LLAMADO)
+        .filter(|e| e.llamada_en.is_some_and(|l| l + Duration::minutes(MINUTOS_PARA_ENTRAR) <= ahora))
+        .map(|e| e.id_cita)
+        .collect()
+}
+
+/// Ordena la cola: primero los llamados y luego los que esperan, por hora
+/// de cita y de llegada. La espera estimada suma la duración agendada de
+/// quienes van antes, pero nunca es menor que lo que falta para la cita.
+pub fn ordenar(entradas: Vec<(EntradaEspera, Cita)>, ahora: NaiveDateTime) -> Vec<PosicionEspera> {
+    let mut entradas: Vec<(EntradaEspera, Cita)> = entradas.into_iter().filter(|(e, _)| e.abierta()).collect();
+    entradas.sort_by_key(|(e, c)| (e.estado != ESPERA_LLAMADO, c.inicio, e.llegada_en));
+
+    let mut acumulado = 0;
+    let mut posicion = 0;
+    entradas
+        .into_iter()
+        .map(|(e, c)| {
+            let llamado = e.estado == ESPERA_LLAMADO;
+            let espera = if llamado { 0 } else { acumulado.max((c.inicio - ahora).num_minutes()).max(0) };
+            acumulado += (c.fin - c.inicio).num_minutes();
+            if !llamado {
+                posicion += 1;
+            }
+            PosicionEspera {
+                id_cita: e.id_cita,
+                id_paciente: e.id_paciente,
+                estado: e.estado,
+                posicion: if llamado { 0 } else { posicion },
+                inicio_cita: c.inicio,
+                llegada_en: e.llegada_en,
+                llamada_en: e.llamada_en,
+                espera_estimada_min: espera,
+            }
+        })
+        .collect()
+}
+
+/// Canales por profesional por los que se difunde su cola cada vez que
+/// cambia, para el profesional y los pacientes que esperan.
+#[derive(Clone, Default)]
+pub struct Avisos {
+    canales: Arc<Mutex<HashMap<u32, broadcast::Sender<Arc<ColaEspera>>>>>,
+}
+
+impl Avisos {
+    pub fn suscribir(&self, id_prof: u32) -> Result<broadcast::Receiver<Arc<ColaEspera>>, AppError> {
+        let mut canales = self.canales.lock().map_err(|e| AppError::Internal(e.to_string()))?;
+        Ok(canales
+            .entry(id_prof)
+            .or_insert_with(|| broadcast::channel(CAPACIDAD_AVISOS).0)
+            .subscribe())
+    }
+
+    /// Difunde la cola; el canal se descarta cuando ya nadie escucha.
+    pub fn publicar(&self, cola: ColaEspera) -> Result<(), AppError> {
+        let mut canales = self.canales.lock().map_err(|e| AppError::Internal(e.to_string()))?;
+        if let Some(canal) = canales.get(&cola.id_prof)
+            && canal.send(Arc::new(cola.clone())).is_err()
+        {
+            canales.remove(&cola.id_prof);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{models::{CITA_AGENDADA, ESPERA_ESPERANDO}, tiempo};
+
+    fn entrada(id_cita: u32, estado: &str, inicio: NaiveDateTime, llegada: NaiveDateTime) -> (EntradaEspera, Cita) {
+        (
+            EntradaEspera {
+                id_cita,
+                id_prof: 1,
+                id_paciente: id_cita,
+                estado: estado.into(),
+                llegada_en: llegada,
+                llamada_en: (estado == ESPERA_LLAMADO).then_some(llegada),
+                cerrada_en: None,
+            },
+            Cita {
+                id: id_cita,
+                id_prof: 1,
+                id_paciente: id_cita,
+                id_bloque: 1,
+                inicio,
+                fin: inicio + Duration::minutes(20),
+                modalidad: "TELECONSULTA".into(),
+                cod_zona: None,
+                estado: CITA_AGENDADA.into(),
+                sobrecupo: false,
+                motivo_cancelacion: None,
+                id_cita_original: None,
+            },
+        )
+    }
+
+    #[test]
+    fn ordena_y_estima_espera() {
+        let ahora = tiempo::ahora();
+        let m = Duration::minutes;
+        let cola = ordenar(vec![
+            entrada(3, ESPERA_ESPERANDO, ahora + m(90), ahora - m(10)),
+            entrada(2, ESPERA_ESPERANDO, ahora, ahora - m(1)),
+            entrada(1, ESPERA_LLAMADO, ahora - m(20), ahora - m(2)),
+            entrada(4, ESPERA_ESPERANDO, ahora, ahora - m(5)),
+        ], ahora);
+
+        let orden: Vec<(u32, u32, i64)> = cola.iter().map(|p| (p.id_cita, p.posicion, p.espera_estimada_min)).collect();
+        // La cita 3 es más tarde: espera hasta su hora aunque la cola avance antes
+        assert_eq!(orden, vec![(1, 0, 0), (4, 1, 20), (2, 2, 40), (3, 3, 90)]);
+
+        let entradas: Vec<EntradaEspera> = [
+            entrada(1, ESPERA_LLAMADO, ahora, ahora - m(MINUTOS_PARA_ENTRAR)),
+            entrada(2, ESPERA_LLAMADO, ahora, ahora - m(1)),
+            entrada(3, ESPERA_ESPERANDO, ahora, ahora - m(30)),
+        ].into_iter().map(|(e, _)| e).collect();
+        assert_eq!(ausentes(&entradas, ahora), vec![1]);
+    }
+}
@@ -0,0 +1,216 @@
+use std::collections::HashMap;
+use std::sync::{atomic::{AtomicU64, Ordering}, Arc, Mutex, MutexGuard};
+
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use tokio::sync::mpsc::{unbounded_channel, UnboundedReceiver, UnboundedSender};
+
+use crate::error::AppError;
+
+/// Quién está en la llamada. Cada sala admite uno de cada rol.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "UPPERCASE")]
+pub enum Rol {
+    Paciente,
+    Profesional,
+}
+
+impl Rol {
+    pub fn par(self) -> Rol {
+        match self {
+            Rol::Paciente => Rol::Profesional,
+            Rol::Profesional => Rol::Paciente,
+        }
+    }
+}
+
+/// Mensajes que envía el navegador. SDP y candidatos ICE se retransmiten
+/// al par sin interpretarlos.
+#[derive(Debug, Deserialize)]
+#[serde(tag = "tipo", rename_all = "snake_case")]
+pub enum Entrante {
+    Oferta { sdp: String },
+    Respuesta { sdp: String },
+    Candidato { candidato: serde_json::Value },
+    Colgar,
+}
+
+/// Mensajes que envía el servidor. `presente` llega cada vez que el par se
+/// (re)conecta, lo que indica al cliente que debe renegociar.
+#[derive(Debug, Clone, Serialize, PartialEq)]
+#[serde(tag = "tipo", rename_all = "snake_case")]
+pub enum Saliente {
+    Bienvenida { rol: Rol, par_presente: bool },
+    Presente { rol: Rol },
+    Ausente { rol: Rol },
+    Oferta { de: Rol, sdp: String },
+    Respuesta { de: Rol, sdp: String },
+    Candidato { de: Rol, candidato: serde_json::Value },
+    /// La misma persona abrió otra conexión (ej. recargó la página).
+    Reemplazada,
+    Terminada,
+    Error { mensaje: String },
+}
+
+/// Token aleatorio para entrar a una sala; se entrega una sola vez.
+/// Subprotocolo del WebSocket de señalización. El cliente lo ofrece junto
+/// con su token y el servidor responde sólo con éste.
+pub const PROTOCOLO: &str = "teleconsulta";
+
+/// Token ofrecido en `Sec-WebSocket-Protocol` junto a `PROTOCOLO`.
+pub fn token_de_protocolos(protocolos: &str) -> Option<String> {
+    let protocolos: Vec<&str> = protocolos.split(',').map(str::trim).collect();
+    if !protocolos.contains(&PROTOCOLO) {
+        return None;
+    }
+    protocolos.into_iter().find(|p| *p != PROTOCOLO && !p.is_empty()).map(str::to_string)
+}
+
+pub fn generar_token() -> String {
+    let mut bytes = [0u8; 32];
+    rand::thread_rng().fill_bytes(&mut bytes);
+    hex::encode(bytes)
+}
+
+/// Lo que se guarda del token.
+pub fn huella_token(token: &str) -> String {
+    hex::encode(Sha256::digest(token.trim().as_bytes()))
+}
+
+struct Participante {
+    conexion: u64,
+    tx: UnboundedSender<Saliente>,
+}
+
+#[derive(Default)]
+struct Sala {
+    participantes: HashMap<Rol, Participante>,
+}
+
+impl Sala {
+    fn enviar(&self, rol: Rol, mensaje: Saliente) -> bool {
+        self.participantes.get(&rol).is_some_and(|p| p.tx.send(mensaje).is_ok())
+    }
+}
+
+/// Salas de señalización en memoria, una por cita. Sólo coordinan la
+/// conexión WebRTC: el audio y video viajan directo entre los navegadores.
+#[derive(Clone, Default)]
+pub struct Salas {
+    salas: Arc<Mutex<HashMap<u32, Sala>>>,
+    conexiones: Arc<AtomicU64>,
+}
+
+impl Salas {
+    fn lock(&self) -> Result<MutexGuard<'_, HashMap<u32, Sala>>, AppError> {
+        self.salas.lock().map_err(|e| AppError::Internal(e.to_string()))
+    }
+
+    /// Registra una conexión. Si el rol ya estaba conectado, la conexión
+    /// anterior recibe `Reemplazada`. Retorna el id de la conexión, por
+    /// donde recibirá sus mensajes y si el par ya está en la sala.
+    pub fn unir(&self, id_cita: u32, rol: Rol) -> Result<(u64, UnboundedReceiver<Saliente>, bool), AppError> {
+        let conexion = self.conexiones.fetch_add(1, Ordering::Relaxed) + 1;
+        let (tx, rx) = unbounded_channel();
+        let mut salas = self.lock()?;
+        let sala = salas.entry(id_cita).or_default();
+
+        if let Some(anterior) = sala.participantes.insert(rol, Participante { conexion, tx }) {
+            let _ = anterior.tx.send(Saliente::Reemplazada);
+        }
+        let par_presente = sala.enviar(rol.par(), Saliente::Presente { rol });
+        sala.enviar(rol, Saliente::Bienvenida { rol, par_presente });
+        Ok((conexion, rx, par_presente))
+    }
+
+    /// Reenvía una oferta, respuesta o candidato al par. `false` si el par
+    /// no está conectado.
+    pub fn retransmitir(&self, id_cita: u32, de: Rol, entrante: Entrante) -> Result<bool, AppError> {
+        let mensaje = match entrante {
+            Entrante::Oferta { sdp } => Saliente::Oferta { de, sdp },
+            Entrante::Respuesta { sdp } => Saliente::Respuesta { de, sdp },
+            Entrante::Candidato { candidato } => Saliente::Candidato { de, candidato },
+            Entrante::Colgar => return Ok(false),
+        };
+        Ok(self.lock()?.get(&id_cita).is_some_and(|s| s.enviar(de.par(), mensaje)))
+    }
+
+    /// Retira la conexión, salvo que ya haya sido reemplazada por una más
+    /// nueva del mismo rol, y avisa al par.
+    pub fn salir(&self, id_cita: u32, rol: Rol, conexion: u64) -> Result<(), AppError> {
+        let mut salas = self.lock()?;
+        let Some(sala) = salas.get_mut(&id_cita) else {
+            return Ok(());
+        };
+        if sala.participantes.get(&rol).is_some_and(|p| p.conexion == conexion) {
+            sala.participantes.remove(&rol);
+            sala.enviar(rol.par(), Saliente::Ausente { rol });
+        }
+        if sala.participantes.is_empty() {
+            salas.remove(&id_cita);
+        }
+        Ok(())
+    }
+
+    /// Cierra la sala avisando a todos los conectados.
+    pub fn terminar(&self, id_cita: u32) -> Result<(), AppError> {
+        if let Some(sala) = self.lock()?.remove(&id_cita) {
+            for p in sala.participantes.values() {
+                let _ = p.tx.send(Saliente::Terminada);
+            }
+        }
+        Ok(())
+    }
+
+    pub fn conectados(&self, id_cita: u32) -> Result<Vec<Rol>, AppError> {
+        let mut roles: Vec<Rol> = self.lock()?
+            .get(&id_cita)
+            .map(|s| s.participantes.keys().copied().collect())
+            .unwrap_or_default();
+        roles.sort_by_key(|r| *r == Rol::Profesional);
+        Ok(roles)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn retransmite_al_par_y_maneja_reconexion() {
+        let salas = Salas::default();
+        let (_, mut paciente, par) = salas.unir(7, Rol::Paciente).unwrap();
+        assert!(!par);
+        assert_eq!(paciente.try_recv().unwrap(), Saliente::Bienvenida { rol: Rol::Paciente, par_presente: false });
+        assert!(!salas.retransmitir(7, Rol::Paciente, Entrante::Oferta { sdp: "v=0".into() }).unwrap());
+
+        let (primera, mut profesional, par) = salas.unir(7, Rol::Profesional).unwrap();
+        assert!(par);
+        assert_eq!(paciente.try_recv().unwrap(), Saliente::Presente { rol: Rol::Profesional });
+        profesional.try_recv().unwrap();
+
+        let oferta: Entrante = serde_json::from_str(r#"{"tipo":"oferta","sdp":"v=0"}"#).unwrap();
+        assert!(salas.retransmitir(7, Rol::Profesional, oferta).unwrap());
+        let recibido = paciente.try_recv().unwrap();
+        assert_eq!(
+            serde_json::to_value(&recibido).unwrap(),
+            serde_json::json!({"tipo": "oferta", "de": "PROFESIONAL", "sdp": "v=0"}),
+        );
+
+        // El profesional recarga: la conexión vieja queda reemplazada y su
+        // salida tardía no saca a la nueva de la sala
+        let (_, mut nueva, _) = salas.unir(7, Rol::Profesional).unwrap();
+        assert_eq!(profesional.try_recv().unwrap(), Saliente::Reemplazada);
+        salas.salir(7, Rol::Profesional, primera).unwrap();
+        assert_eq!(salas.conectados(7).unwrap(), vec![Rol::Paciente, Rol::Profesional]);
+        assert_eq!(paciente.try_recv().unwrap(), Saliente::Presente { rol: Rol::Profesional });
+        assert!(paciente.try_recv().is_err());
+
+        salas.terminar(7).unwrap();
+        assert_eq!(paciente.try_recv().unwrap(), Saliente::Terminada);
+        nueva.try_recv().unwrap();
+        assert_eq!(nueva.try_recv().unwrap(), Saliente::Terminada);
+        assert!(salas.conectados(7).unwrap().is_empty());
+    }
+}
@@ -0,0 +1,30 @@
+use std::borrow::Cow;
+
+/// Pasa a minúsculas y quita tildes, diéresis y eñes para comparar textos
+/// sin importar cómo se escribieron ("neumonia" = "Neumonía").
+pub fn plegar(texto: &str) -> String {
+    texto
+        .chars()
+        .flat_map(char::to_lowercase)
+        .map(|c| match c {
+            'á' | 'à' | 'ä' | 'â' => 'a',
+            'é' | 'è' | 'ë' | 'ê' => 'e',
+            'í' | 'ì' | 'ï' | 'î' => 'i',
+            'ó' | 'ò' | 'ö' | 'ô' => 'o',
+            'ú' | 'ù' | 'ü' | 'û' => 'u',
+            'ñ' => 'n',
+            'ç' => 'c',
+            c => c,
+        })
+        .collect()
+}
+
+/// Campo de texto para un CSV separado por `;`: si trae el separador,
+/// comillas o saltos de línea va entre comillas, con las comillas dobladas.
+pub fn campo_csv(texto: &str) -> Cow<'_, str> {
+    if texto.contains([';', '"', '\r', '\n']) {
+        Cow::Owned(format!("\"{}\"", texto.replace('"', "\"\"")))
+    } else {
+        Cow::Borrowed(texto)
+    }
+}
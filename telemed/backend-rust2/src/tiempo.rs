@@ -0,0 +1,101 @@
+use chrono::{LocalResult, NaiveDateTime, TimeZone, Timelike, Utc};
+use chrono_tz::Tz;
+
+use crate::error::AppError;
+
+/// Zona horaria de Chile continental; se usa cuando la zona de acceso no
+/// define otra (Magallanes y Rapa Nui tienen husos propios).
+pub const ZONA_HORARIA_DEFECTO: &str = "America/Santiago";
+
+pub fn zona_horaria(nombre: &str) -> Result<Tz, AppError> {
+    nombre
+        .parse::<Tz>()
+        .map_err(|_| AppError::Validation(format!("Zona horaria inválida: {}", nombre)))
+}
+
+/// Instante actual en UTC, que es como se guardan todos los DATETIME.
+pub fn ahora() -> NaiveDateTime {
+    Utc::now().naive_utc()
+}
+
+/// Sin fracción de segundo. Lo que se firma, se resume o se liga a un
+/// cifrado debe guardarse así: un DATETIME redondea la fracción y al
+/// releerlo ya no coincidiría.
+pub fn al_segundo(instante: NaiveDateTime) -> NaiveDateTime {
+    instante.with_nanosecond(0).unwrap_or(instante)
+}
+
+/// Convierte una hora local de `tz` a UTC. En el cambio de hora de otoño una
+/// hora local ocurre dos veces y se toma la primera; en el de primavera hay
+/// horas locales que no existen y se retorna `None`.
+pub fn a_utc(local: NaiveDateTime, tz: Tz) -> Option<NaiveDateTime> {
+    match tz.from_local_datetime(&local) {
+        LocalResult::Single(dt) => Some(dt.naive_utc()),
+        LocalResult::Ambiguous(primera, _) => Some(primera.naive_utc()),
+        LocalResult::None => None,
+    }
+}
+
+pub fn a_local(utc: NaiveDateTime, tz: Tz) -> NaiveDateTime {
+    tz.from_utc_datetime(&utc).naive_local()
+}
+
+/// Serializa fechas-hora UTC como RFC 3339 (`2025-03-03T12:00:00Z`) y acepta
+/// cualquier desplazamiento en la entrada, convirtiéndolo a UTC.
+pub mod utc {
+    use chrono::{DateTime, NaiveDateTime};
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(fecha: &NaiveDateTime, s: S) -> Result<S::Ok, S::Error> {
+        s.serialize_str(&fecha.and_utc().to_rfc3339_opts(chrono::SecondsFormat::Secs, true))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(d: D) -> Result<NaiveDateTime, D::Error> {
+        let texto = String::deserialize(d)?;
+        DateTime::parse_from_rfc3339(&texto)
+            .map(|f| f.naive_utc())
+            .map_err(|e| serde::de::Error::custom(format!("fecha-hora sin zona horaria válida ({}): {}", e, texto)))
+    }
+
+    pub mod opcional {
+        use chrono::NaiveDateTime;
+        use serde::{Deserialize, Deserializer, Serializer};
+
+        pub fn serialize<S: Serializer>(fecha: &Option<NaiveDateTime>, s: S) -> Result<S::Ok, S::Error> {
+            match fecha {
+                Some(f) => super::serialize(f, s),
+                None => s.serialize_none(),
+            }
+        }
+
+        pub fn deserialize<'de, D: Deserializer<'de>>(d: D) -> Result<Option<NaiveDateTime>, D::Error> {
+            #[derive(Deserialize)]
+            struct Envoltorio(#[serde(with = "super")] NaiveDateTime);
+
+            Ok(Option::<Envoltorio>::deserialize(d)?.map(|Envoltorio(f)| f))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::NaiveDate;
+    use super::*;
+
+    #[test]
+    fn magallanes_y_rapa_nui_difieren_de_santiago() {
+        // Invierno: Santiago UTC-4, Punta Arenas UTC-3, Isla de Pascua UTC-6
+        let local = NaiveDate::from_ymd_opt(2025, 7, 1).unwrap().and_hms_opt(9, 0, 0).unwrap();
+        let hora_utc = |tz: &str| a_utc(local, zona_horaria(tz).unwrap()).unwrap().time().to_string();
+        assert_eq!(hora_utc("America/Santiago"), "13:00:00");
+        assert_eq!(hora_utc("America/Punta_Arenas"), "12:00:00");
+        assert_eq!(hora_utc("Pacific/Easter"), "15:00:00");
+    }
+
+    #[test]
+    fn hora_inexistente_en_cambio_de_horario() {
+        // El 7 de septiembre de 2025 Santiago salta de 00:00 a 01:00
+        let local = NaiveDate::from_ymd_opt(2025, 9, 7).unwrap().and_hms_opt(0, 30, 0).unwrap();
+        assert_eq!(a_utc(local, zona_horaria("America/Santiago").unwrap()), None);
+    }
+}
@@ -0,0 +1,239 @@
+use chrono::{Datelike, NaiveDate, NaiveDateTime};
+
+use crate::{
+    geo,
+    models::{
+        BloqueDisponibilidad, MarcaVisita, Profesional, VerificacionVisita, Visita, Zona,
+        MARCA_LLEGADA, MARCA_SALIDA, VISITA_EN_RUTA, VISITA_FALLIDA, VISITA_REALIZADA,
+    },
+    texto::plegar,
+};
+
+pub const ANOMALIA_SIN_LLEGADA: &str = "SIN_LLEGADA";
+pub const ANOMALIA_SIN_SALIDA: &str = "SIN_SALIDA";
+pub const ANOMALIA_SIN_UBICACION: &str = "SIN_UBICACION";
+pub const ANOMALIA_LEJOS: &str = "LEJOS_DEL_DOMICILIO";
+pub const ANOMALIA_BREVE: &str = "DURACION_BREVE";
+pub const ANOMALIA_TRASLAPE: &str = "TRASLAPE";
+pub const ANOMALIA_PRECISION_BAJA: &str = "PRECISION_BAJA";
+
+/// Radio aceptado alrededor del domicilio, además de la precisión del GPS.
+pub const DISTANCIA_MAXIMA_KM: f64 = 0.3;
+/// Precisión del GPS que se descuenta como máximo. Una lectura menos
+/// precisa no prueba la presencia en el domicilio y se informa aparte.
+pub const PRECISION_MAXIMA_M: f64 = 100.0;
+/// Una visita realizada debe durar al menos esta fracción de lo programado.
+const FRACCION_DURACION_MINIMA: f64 = 0.5;
+
+/// Un profesional cubre una zona si su zona en `paso_profesionales` coincide
+/// con el nombre de la zona de acceso, o si tiene un bloque a domicilio en
+/// ella vigente el día de la visita.
+pub fn cubre_zona(profesional: &Profesional, zona: &Zona, bloques: &[BloqueDisponibilidad], fecha: NaiveDate) -> bool {
+    let por_nombre = profesional
+        .zona
+        .as_deref()
+        .is_some_and(|z| plegar(z.trim()) == plegar(zona.nom_zona.trim()));
+
+    let por_agenda = bloques.iter().any(|b| {
+        b.id_prof == profesional.id_prof
+            && b.modalidad == "DOMICILIO"
+            && b.cod_zona.as_deref() == Some(zona.cod_zona.as_str())
+            && b.dia_semana as u32 == fecha.weekday().number_from_monday()
+            && b.vigente_en(fecha)
+    });
+
+    por_nombre || por_agenda
+}
+
+/// Distancia de la marca al domicilio de la visita, si éste tiene coordenadas.
+pub fn distancia_al_domicilio(visita: &Visita, latitud: f64, longitud: f64) -> Option<f64> {
+    Some(geo::distancia_km((visita.latitud?, visita.longitud?), (latitud, longitud)))
+}
+
+/// La marca queda fuera del radio aceptado, descontando la precisión
+/// informada hasta `PRECISION_MAXIMA_M`.
+pub fn lejos_del_domicilio(marca: &MarcaVisita) -> bool {
+    let holgura_km = marca.precision_m.unwrap_or(0.0).clamp(0.0, PRECISION_MAXIMA_M) / 1000.0;
+    marca.distancia_km.is_some_and(|d| d > DISTANCIA_MAXIMA_KM + holgura_km)
+}
+
+/// El GPS informó una precisión peor que la que se descuenta.
+pub fn precision_baja(marca: &MarcaVisita) -> bool {
+    marca.precision_m.is_some_and(|p| p > PRECISION_MAXIMA_M)
+}
+
+fn marca<'a>(marcas: &'a [MarcaVisita], id_visita: u32, tipo: &str) -> Option<&'a MarcaVisita> {
+    marcas.iter().find(|m| m.id_visita == id_visita && m.tipo == tipo)
+}
+
+/// Cruza las visitas de un profesional con sus marcas de llegada y salida.
+/// Dos visitas se traslapan si el profesional figura en ambos domicilios a
+/// la vez.
+pub fn verificar(visitas: &[Visita], marcas: &[MarcaVisita]) -> Vec<VerificacionVisita> {
+    let estadias: Vec<(u32, NaiveDateTime, NaiveDateTime)> = visitas
+        .iter()
+        .filter_map(|v| {
+            let llegada = marca(marcas, v.id, MARCA_LLEGADA)?;
+            let salida = marca(marcas, v.id, MARCA_SALIDA)?;
+            Some((v.id, llegada.registrada_en, salida.registrada_en))
+        })
+        .collect();
+
+    visitas
+        .iter()
+        .map(|v| {
+            let llegada = marca(marcas, v.id, MARCA_LLEGADA);
+            let salida = marca(marcas, v.id, MARCA_SALIDA);
+            let minutos = llegada.zip(salida).map(|(l, s)| (s.registrada_en - l.registrada_en).num_minutes());
+            let mut anomalias = Vec::new();
+
+            if llegada.is_none() && v.estado == VISITA_REALIZADA {
+                anomalias.push(ANOMALIA_SIN_LLEGADA);
+            }
+            if llegada.is_some() && salida.is_none() && v.estado != VISITA_EN_RUTA {
+                anomalias.push(ANOMALIA_SIN_SALIDA);
+            }
+            if (llegada.is_some() || salida.is_some()) && (v.latitud.is_none() || v.longitud.is_none()) {
+                anomalias.push(ANOMALIA_SIN_UBICACION);
+            }
+            if llegada.into_iter().chain(salida).any(lejos_del_domicilio) {
+                anomalias.push(ANOMALIA_LEJOS);
+            }
+            if llegada.into_iter().chain(salida).any(precision_baja) {
+                anomalias.push(ANOMALIA_PRECISION_BAJA);
+            }
+            if v.estado != VISITA_FALLIDA
+                && minutos.is_some_and(|m| (m as f64) < f64::from(v.duracion_min) * FRACCION_DURACION_MINIMA)
+            {
+                anomalias.push(ANOMALIA_BREVE);
+            }
+            if let Some(&(_, desde, hasta)) = estadias.iter().find(|e| e.0 == v.id)
+                && estadias.iter().any(|o| o.0 != v.id && o.1 < hasta && desde < o.2)
+            {
+                anomalias.push(ANOMALIA_TRASLAPE);
+            }
+
+            VerificacionVisita {
+                id_visita: v.id,
+                id_paciente: v.id_paciente,
+                fecha: v.fecha,
+                estado: v.estado.clone(),
+                llegada: llegada.cloned(),
+                salida: salida.cloned(),
+                minutos_en_domicilio: minutos,
+                anomalias: anomalias.into_iter().map(String::from).collect(),
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::NaiveTime;
+    use crate::repositories::fixtures;
+    use super::*;
+
+    fn profesional(zona: &str) -> Profesional {
+        Profesional { id_prof: 3, zona: Some(zona.into()), ..fixtures::profesional(3) }
+    }
+
+    #[test]
+    fn cubre_zona_por_nombre_o_por_bloque_domiciliario() {
+        let chillan = Zona {
+            cod_zona: "005".into(),
+            nom_zona: "Chillán".into(),
+            orden_zona: None,
+            zona_horaria: "America/Santiago".into(),
+        };
+        let lunes = NaiveDate::from_ymd_opt(2025, 3, 3).unwrap();
+        assert!(cubre_zona(&profesional("CHILLAN"), &chillan, &[], lunes));
+        assert!(!cubre_zona(&profesional("Temuco"), &chillan, &[], lunes));
+
+        let bloque = BloqueDisponibilidad {
+            id: 1,
+            id_prof: 3,
+            dia_semana: 1,
+            hora_inicio: NaiveTime::from_hms_opt(9, 0, 0).unwrap(),
+            hora_fin: NaiveTime::from_hms_opt(13, 0, 0).unwrap(),
+            duracion_min: 60,
+            modalidad: "DOMICILIO".into(),
+            cod_zona: Some("005".into()),
+            zona_horaria: "America/Santiago".into(),
+            sobrecupos: 0,
+            vigencia_desde: lunes,
+            vigencia_hasta: None,
+        };
+        assert!(cubre_zona(&profesional("Temuco"), &chillan, std::slice::from_ref(&bloque), lunes));
+        assert!(!cubre_zona(&profesional("Temuco"), &chillan, &[bloque], lunes + chrono::Duration::days(1)));
+    }
+
+    fn visita(id: u32, duracion_min: u16) -> Visita {
+        Visita {
+            id,
+            id_paciente: id,
+            id_prof: Some(3),
+            cod_zona: "005".into(),
+            direccion: "Libertad 550".into(),
+            comuna: Some("CHILLAN".into()),
+            latitud: Some(-36.6066),
+            longitud: Some(-72.1034),
+            fecha: NaiveDate::from_ymd_opt(2025, 3, 3).unwrap(),
+            ventana_desde: None,
+            ventana_hasta: None,
+            duracion_min,
+            motivo: "Curación".into(),
+            estado: VISITA_REALIZADA.into(),
+            motivo_estado: None,
+            creada_en: NaiveDateTime::default(),
+        }
+    }
+
+    fn marcar(visita: &Visita, tipo: &str, hora: u32, minuto: u32, punto: (f64, f64)) -> MarcaVisita {
+        let registrada_en = visita.fecha.and_hms_opt(hora, minuto, 0).unwrap();
+        MarcaVisita {
+            id: 0,
+            id_visita: visita.id,
+            tipo: tipo.into(),
+            latitud: punto.0,
+            longitud: punto.1,
+            precision_m: Some(20.0),
+            distancia_km: distancia_al_domicilio(visita, punto.0, punto.1),
+            registrada_en,
+            recibida_en: registrada_en,
+        }
+    }
+
+    #[test]
+    fn detecta_distancia_duracion_y_traslapes() {
+        let domicilio = (-36.6066, -72.1034);
+        let a = visita(1, 30);
+        let b = visita(2, 60);
+        let c = visita(3, 30);
+        let marcas = vec![
+            marcar(&a, MARCA_LLEGADA, 9, 0, domicilio),
+            marcar(&a, MARCA_SALIDA, 9, 40, domicilio),
+            // Salida de b a 2 km del domicilio, 20 minutos después de llegar y cruzándose con a
+            marcar(&b, MARCA_LLEGADA, 9, 30, domicilio),
+            marcar(&b, MARCA_SALIDA, 9, 50, (-36.5886, -72.1034)),
+        ];
+
+        let reporte = verificar(&[a, b, c], &marcas);
+        assert_eq!(reporte[0].minutos_en_domicilio, Some(40));
+        assert_eq!(reporte[0].anomalias, [ANOMALIA_TRASLAPE]);
+        assert_eq!(reporte[1].anomalias, [ANOMALIA_LEJOS, ANOMALIA_BREVE, ANOMALIA_TRASLAPE]);
+        assert_eq!(reporte[2].anomalias, [ANOMALIA_SIN_LLEGADA]);
+    }
+
+    #[test]
+    fn precision_no_amplia_el_radio_sin_limite() {
+        let v = visita(1, 30);
+        // A 2 km del domicilio informando 5 km de precisión
+        let mut salida = marcar(&v, MARCA_SALIDA, 9, 40, (-36.5886, -72.1034));
+        salida.precision_m = Some(5000.0);
+        let marcas = vec![marcar(&v, MARCA_LLEGADA, 9, 0, (-36.6066, -72.1034)), salida];
+
+        assert!(lejos_del_domicilio(&marcas[1]));
+        assert!(!precision_baja(&marcas[0]));
+        assert_eq!(verificar(&[v], &marcas)[0].anomalias, [ANOMALIA_LEJOS, ANOMALIA_PRECISION_BAJA]);
+    }
+}